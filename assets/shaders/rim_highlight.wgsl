@@ -0,0 +1,53 @@
+// Extends the standard material with a fresnel-based rim glow, used to highlight interactable objects.
+// Based on Bevy's `extended_material` example.
+
+#import bevy_pbr::{
+    pbr_fragment::pbr_input_from_standard_material,
+    pbr_functions::alpha_discard,
+    mesh_view_bindings::view,
+}
+
+#ifdef PREPASS_PIPELINE
+#import bevy_pbr::{
+    prepass_io::{VertexOutput, FragmentOutput},
+    pbr_deferred_functions::deferred_output,
+}
+#else
+#import bevy_pbr::{
+    forward_io::{VertexOutput, FragmentOutput},
+    pbr_functions::{apply_pbr_lighting, main_pass_post_lighting_processing},
+}
+#endif
+
+struct RimHighlight {
+    color: vec4<f32>,
+    power: f32,
+}
+
+@group(1) @binding(100)
+var<uniform> rim_highlight: RimHighlight;
+
+@fragment
+fn fragment(
+    in: VertexOutput,
+    @builtin(front_facing) is_front: bool,
+) -> FragmentOutput {
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+
+#ifdef PREPASS_PIPELINE
+    let out = deferred_output(in, pbr_input);
+#else
+    var out: FragmentOutput;
+    out.color = apply_pbr_lighting(pbr_input);
+
+    // 0 when looking straight at the surface, 1 at the silhouette
+    let v = normalize(view.world_position.xyz - in.world_position.xyz);
+    let rim = pow(1.0 - max(dot(pbr_input.N, v), 0.0), rim_highlight.power);
+    out.color = vec4<f32>(out.color.rgb + rim_highlight.color.rgb * rim, out.color.a);
+
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+#endif
+
+    return out;
+}
@@ -0,0 +1,77 @@
+// Extends the standard material with three textures blended by the channels of a splat map,
+// so that large meshes like terrain can show different ground in different places. Used by `terrain_plugin`.
+
+#import bevy_pbr::{
+    pbr_fragment::pbr_input_from_standard_material,
+    pbr_functions::alpha_discard,
+}
+
+#ifdef PREPASS_PIPELINE
+#import bevy_pbr::{
+    prepass_io::{VertexOutput, FragmentOutput},
+    pbr_deferred_functions::deferred_output,
+}
+#else
+#import bevy_pbr::{
+    forward_io::{VertexOutput, FragmentOutput},
+    pbr_functions::{apply_pbr_lighting, main_pass_post_lighting_processing},
+}
+#endif
+
+struct Splat {
+    tiling: f32,
+}
+
+@group(1) @binding(100)
+var<uniform> splat: Splat;
+@group(1) @binding(101)
+var splat_map: texture_2d<f32>;
+@group(1) @binding(102)
+var splat_sampler: sampler;
+@group(1) @binding(103)
+var red_texture: texture_2d<f32>;
+@group(1) @binding(104)
+var red_sampler: sampler;
+@group(1) @binding(105)
+var green_texture: texture_2d<f32>;
+@group(1) @binding(106)
+var green_sampler: sampler;
+@group(1) @binding(107)
+var blue_texture: texture_2d<f32>;
+@group(1) @binding(108)
+var blue_sampler: sampler;
+
+@fragment
+fn fragment(
+    in: VertexOutput,
+    @builtin(front_facing) is_front: bool,
+) -> FragmentOutput {
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+
+#ifdef VERTEX_UVS
+    var weights = textureSample(splat_map, splat_sampler, in.uv).rgb;
+#else
+    var weights = vec3<f32>(1.0, 0.0, 0.0);
+#endif
+    // Falls back to the first layer where the splat map is black
+    weights = weights / max(weights.r + weights.g + weights.b, 0.0001);
+    weights.r = weights.r + 1.0 - (weights.r + weights.g + weights.b);
+
+    // Projected from above, so that the layers tile evenly no matter how the terrain is mapped
+    let position = in.world_position.xz * splat.tiling;
+    let color = textureSample(red_texture, red_sampler, position) * weights.r
+        + textureSample(green_texture, green_sampler, position) * weights.g
+        + textureSample(blue_texture, blue_sampler, position) * weights.b;
+    pbr_input.material.base_color = pbr_input.material.base_color * color;
+    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+
+#ifdef PREPASS_PIPELINE
+    let out = deferred_output(in, pbr_input);
+#else
+    var out: FragmentOutput;
+    out.color = apply_pbr_lighting(pbr_input);
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+#endif
+
+    return out;
+}
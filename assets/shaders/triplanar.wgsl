@@ -0,0 +1,59 @@
+// Extends the standard material with a texture projected along the world axes, so that level geometry
+// without UVs can be textured. Used for greyboxing, see `triplanar_plugin`.
+
+#import bevy_pbr::{
+    pbr_fragment::pbr_input_from_standard_material,
+    pbr_functions::alpha_discard,
+}
+
+#ifdef PREPASS_PIPELINE
+#import bevy_pbr::{
+    prepass_io::{VertexOutput, FragmentOutput},
+    pbr_deferred_functions::deferred_output,
+}
+#else
+#import bevy_pbr::{
+    forward_io::{VertexOutput, FragmentOutput},
+    pbr_functions::{apply_pbr_lighting, main_pass_post_lighting_processing},
+}
+#endif
+
+struct Triplanar {
+    scale: f32,
+    sharpness: f32,
+}
+
+@group(1) @binding(100)
+var<uniform> triplanar: Triplanar;
+@group(1) @binding(101)
+var triplanar_texture: texture_2d<f32>;
+@group(1) @binding(102)
+var triplanar_sampler: sampler;
+
+@fragment
+fn fragment(
+    in: VertexOutput,
+    @builtin(front_facing) is_front: bool,
+) -> FragmentOutput {
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+
+    // Blend the three projections by how much the surface faces each axis
+    let position = in.world_position.xyz * triplanar.scale;
+    var weights = pow(abs(in.world_normal), vec3<f32>(triplanar.sharpness));
+    weights = weights / (weights.x + weights.y + weights.z);
+    let color = textureSample(triplanar_texture, triplanar_sampler, position.zy) * weights.x
+        + textureSample(triplanar_texture, triplanar_sampler, position.xz) * weights.y
+        + textureSample(triplanar_texture, triplanar_sampler, position.xy) * weights.z;
+    pbr_input.material.base_color = pbr_input.material.base_color * color;
+    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+
+#ifdef PREPASS_PIPELINE
+    let out = deferred_output(in, pbr_input);
+#else
+    var out: FragmentOutput;
+    out.color = apply_pbr_lighting(pbr_input);
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+#endif
+
+    return out;
+}
@@ -1,10 +1,19 @@
+use crate::file_system_interaction::mods::mod_asset_source_plugin;
 use anyhow::{Context, Result};
-use bevy::{prelude::*, window::PrimaryWindow, winit::WinitWindows};
+use bevy::{
+    prelude::*,
+    render::{settings::WgpuSettings, RenderPlugin},
+    window::{ExitCondition, PrimaryWindow},
+    winit::{WinitPlugin, WinitWindows},
+};
 use bevy_mod_sysfail::*;
+use seldom_fn_plugin::FnPluginExt;
 use std::io::Cursor;
 use winit::window::Icon;
 
 /// Overrides the default Bevy plugins and configures things like the screen settings.
+/// Assets are read through the [`mod_asset_source_plugin`], so that mods can replace them.
+/// If the [`Headless`] resource was inserted before, no window is opened and nothing is rendered.
 pub(crate) fn bevy_config_plugin(app: &mut App) {
     let default_plugins = DefaultPlugins.set(WindowPlugin {
         primary_window: Some(Window {
@@ -15,10 +24,35 @@ pub(crate) fn bevy_config_plugin(app: &mut App) {
     });
     app.insert_resource(Msaa::Sample4)
         .insert_resource(ClearColor(Color::rgb(0.4, 0.4, 0.4)))
-        .add_plugins(default_plugins)
-        .add_systems(Startup, set_window_icon);
+        .fn_plugin(mod_asset_source_plugin);
+    if app.world.contains_resource::<Headless>() {
+        // The window entity is kept so that UI code finds a window to draw into
+        app.add_plugins(
+            default_plugins
+                .set(WindowPlugin {
+                    primary_window: Some(default()),
+                    exit_condition: ExitCondition::DontExit,
+                    close_when_requested: false,
+                })
+                .set(RenderPlugin {
+                    render_creation: WgpuSettings {
+                        backends: None,
+                        ..default()
+                    }
+                    .into(),
+                })
+                .disable::<WinitPlugin>(),
+        );
+    } else {
+        app.add_plugins(default_plugins)
+            .add_systems(Startup, set_window_icon);
+    }
 }
 
+/// Runs the game without a window or a GPU, e.g. for the [`Simulation`](crate::simulation::Simulation).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Resource, Default)]
+pub(crate) struct Headless;
+
 // Sets the icon on Windows and X11
 #[sysfail(log(level = "error"))]
 fn set_window_icon(
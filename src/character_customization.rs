@@ -0,0 +1,110 @@
+use crate::{character_customization::ui::customization_ui_plugin, GameState};
+use bevy::{prelude::*, utils::HashMap};
+use seldom_fn_plugin::FnPluginExt;
+use serde::{Deserialize, Serialize};
+
+mod ui;
+
+/// Separates slot and variant in the names of swappable model parts, e.g. `hair:ponytail`.
+pub(crate) const PART_SEPARATOR: char = ':';
+
+/// Handles swapping parts of character models and recoloring their materials at runtime
+/// according to their [`CharacterAppearance`].
+/// Split into the following sub-plugins:
+/// - [`customization_ui_plugin`]: A demo screen for editing the player's appearance.
+pub(crate) fn character_customization_plugin(app: &mut App) {
+    app.register_type::<CharacterAppearance>()
+        .add_systems(
+            Update,
+            apply_appearance.run_if(in_state(GameState::Playing)),
+        )
+        .fn_plugin(customization_ui_plugin);
+}
+
+/// Describes how a character looks. Swappable parts are nodes in the model named `<slot>:<variant>`.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct CharacterAppearance {
+    /// The variant shown for each slot. Slots that are not listed show all of their variants.
+    pub(crate) parts: HashMap<String, String>,
+    /// Tint for the materials of a part, keyed by its slot or, for parts without variants, by its name.
+    pub(crate) tints: HashMap<String, Color>,
+}
+
+/// The material a mesh had before it was tinted, so that tints don't stack and can be reset.
+#[derive(Debug, Clone, Component)]
+struct UntintedMaterial(Handle<StandardMaterial>);
+
+pub(crate) fn parse_part_name(name: &str) -> Option<(&str, &str)> {
+    name.split_once(PART_SEPARATOR)
+}
+
+/// The key used in [`CharacterAppearance::tints`] for the given mesh.
+/// GLTF primitives may or may not carry the name of their node, so we check the parent as well.
+pub(crate) fn tint_key(
+    entity: Entity,
+    names: &Query<&Name>,
+    parents: &Query<&Parent>,
+) -> Option<String> {
+    std::iter::once(entity)
+        .chain(parents.iter_ancestors(entity))
+        .take(2)
+        .find_map(|entity| names.get(entity).ok())
+        .map(|name| {
+            parse_part_name(name)
+                .map_or(name.as_str(), |(slot, _variant)| slot)
+                .to_string()
+        })
+}
+
+fn apply_appearance(
+    mut commands: Commands,
+    characters: Query<(Entity, &CharacterAppearance), Changed<CharacterAppearance>>,
+    children: Query<&Children>,
+    parents: Query<&Parent>,
+    names: Query<&Name>,
+    mut visibilities: Query<&mut Visibility>,
+    material_handles: Query<(&Handle<StandardMaterial>, Option<&UntintedMaterial>)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("apply_appearance").entered();
+    for (entity, appearance) in characters.iter() {
+        for descendant in children.iter_descendants(entity) {
+            if let Some((slot, variant)) = names
+                .get(descendant)
+                .ok()
+                .and_then(|name| parse_part_name(name))
+                && let Some(selected) = appearance.parts.get(slot)
+                && let Ok(mut visibility) = visibilities.get_mut(descendant)
+            {
+                *visibility = if selected == variant {
+                    Visibility::Inherited
+                } else {
+                    Visibility::Hidden
+                };
+            }
+
+            let Ok((handle, untinted)) = material_handles.get(descendant) else {
+                continue;
+            };
+            let original = untinted.map_or_else(|| handle.clone(), |untinted| untinted.0.clone());
+            let tint = tint_key(descendant, &names, &parents)
+                .and_then(|key| appearance.tints.get(&key).copied());
+            let material = match tint {
+                Some(tint) => {
+                    let Some(mut material) = materials.get(&original).cloned() else {
+                        continue;
+                    };
+                    // Textures are multiplied with the base color, so this also tints textured materials
+                    material.base_color = tint;
+                    materials.add(material)
+                }
+                None => original.clone(),
+            };
+            commands
+                .entity(descendant)
+                .insert((material, UntintedMaterial(original)));
+        }
+    }
+}
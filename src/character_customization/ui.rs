@@ -0,0 +1,132 @@
+use crate::{
+    character_customization::{parse_part_name, tint_key, CharacterAppearance},
+    ingame_menu::PauseState,
+    player_control::{
+        actions::{ActionsFrozen, UiAction},
+        player_embodiment::Player,
+    },
+    theme::UiTheme,
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use leafwing_input_manager::prelude::ActionState;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Shows a window for editing the player's [`CharacterAppearance`], toggled with [`UiAction::ToggleCustomization`].
+pub(crate) fn customization_ui_plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        show_customization_screen
+            .run_if(in_state(GameState::Playing).and_then(in_state(PauseState::Running))),
+    );
+}
+
+#[derive(Debug, Clone, Default)]
+struct AppearanceOptions {
+    variants: BTreeMap<String, BTreeSet<String>>,
+    tintable: BTreeSet<String>,
+}
+
+impl AppearanceOptions {
+    fn collect(
+        entity: Entity,
+        children: &Query<&Children>,
+        parents: &Query<&Parent>,
+        names: &Query<&Name>,
+        meshes: &Query<(), With<Handle<StandardMaterial>>>,
+    ) -> Self {
+        let mut options = Self::default();
+        for descendant in children.iter_descendants(entity) {
+            if let Some((slot, variant)) = names
+                .get(descendant)
+                .ok()
+                .and_then(|name| parse_part_name(name))
+            {
+                options
+                    .variants
+                    .entry(slot.to_string())
+                    .or_default()
+                    .insert(variant.to_string());
+            }
+            if meshes.contains(descendant)
+                && let Some(key) = tint_key(descendant, names, parents)
+            {
+                options.tintable.insert(key);
+            }
+        }
+        options
+    }
+}
+
+fn show_customization_screen(
+    actions: Query<&ActionState<UiAction>>,
+    mut open: Local<bool>,
+    mut actions_frozen: ResMut<ActionsFrozen>,
+    mut egui_contexts: EguiContexts,
+    mut players: Query<(Entity, &mut CharacterAppearance), With<Player>>,
+    children: Query<&Children>,
+    parents: Query<&Parent>,
+    names: Query<&Name>,
+    meshes: Query<(), With<Handle<StandardMaterial>>>,
+    theme: Res<UiTheme>,
+) {
+    for action in actions.iter() {
+        if action.just_pressed(UiAction::ToggleCustomization) {
+            *open = !*open;
+            if *open {
+                actions_frozen.freeze();
+            } else {
+                actions_frozen.unfreeze();
+            }
+        }
+    }
+    if !*open {
+        return;
+    }
+
+    for (entity, mut appearance) in players.iter_mut() {
+        let options = AppearanceOptions::collect(entity, &children, &parents, &names, &meshes);
+        egui::Window::new("Character Customization")
+            .collapsible(false)
+            .frame(theme.panel_frame())
+            .show(egui_contexts.ctx_mut(), |ui| {
+                if !options.variants.is_empty() {
+                    ui.heading("Parts");
+                    for (slot, variants) in &options.variants {
+                        let mut selected = appearance.parts.get(slot).cloned().unwrap_or_default();
+                        egui::ComboBox::from_label(slot)
+                            .selected_text(selected.as_str())
+                            .show_ui(ui, |ui| {
+                                for variant in variants {
+                                    ui.selectable_value(&mut selected, variant.clone(), variant);
+                                }
+                            });
+                        // Only write on change so that we don't reapply the appearance every frame
+                        if !selected.is_empty() && appearance.parts.get(slot) != Some(&selected) {
+                            appearance.parts.insert(slot.clone(), selected);
+                        }
+                    }
+                }
+
+                ui.heading("Colors");
+                for key in &options.tintable {
+                    let tint = appearance.tints.get(key).copied().unwrap_or(Color::WHITE);
+                    let mut rgb = [tint.r(), tint.g(), tint.b()];
+                    ui.horizontal(|ui| {
+                        ui.label(key);
+                        if ui.color_edit_button_rgb(&mut rgb).changed() {
+                            appearance
+                                .tints
+                                .insert(key.clone(), Color::rgb(rgb[0], rgb[1], rgb[2]));
+                        }
+                        if ui.button("Reset").clicked() {
+                            appearance.tints.remove(key);
+                        }
+                    });
+                }
+                ui.separator();
+                ui.label("Press C to close");
+            });
+    }
+}
@@ -0,0 +1,30 @@
+use crate::combat::{
+    health::health_plugin, hitboxes::hitboxes_plugin, projectiles::projectiles_plugin,
+    respawn::respawn_plugin, status_effects::status_effects_plugin, throwing::throwing_plugin,
+};
+use bevy::prelude::*;
+use seldom_fn_plugin::FnPluginExt;
+
+pub(crate) mod health;
+pub(crate) mod hitboxes;
+pub(crate) mod projectiles;
+pub(crate) mod respawn;
+pub(crate) mod status_effects;
+pub(crate) mod throwing;
+
+/// Handles everything related to characters hurting each other.
+/// Split into the following sub-plugins:
+/// - [`health_plugin`]: Handles the health of characters.
+/// - [`hitboxes_plugin`]: Lets melee attacks hit characters of other teams.
+/// - [`projectiles_plugin`]: Moves pooled projectiles and reports their hits.
+/// - [`throwing_plugin`]: Lets the player throw items and balls and previews where they land.
+/// - [`respawn_plugin`]: Fades out after the player died and respawns them at their last checkpoint.
+/// - [`status_effects_plugin`]: Ticks poison, burning and other lingering effects.
+pub(crate) fn combat_plugin(app: &mut App) {
+    app.fn_plugin(health_plugin)
+        .fn_plugin(hitboxes_plugin)
+        .fn_plugin(projectiles_plugin)
+        .fn_plugin(throwing_plugin)
+        .fn_plugin(respawn_plugin)
+        .fn_plugin(status_effects_plugin);
+}
@@ -0,0 +1,215 @@
+use crate::{
+    combat::hitboxes::detect_hits,
+    game_events::{PlayerDamaged, PlayerDied},
+    level_instantiation::spawning::GltfExtrasAppExt,
+    movement::ragdoll::{Ragdoll, RagdollEvent},
+    player_control::player_embodiment::Player,
+    GameState,
+};
+use anyhow::Context;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Applies [`DamageEvent`]s and [`HealEvent`]s to the [`Health`] of their targets.
+/// Damage is scaled by the target's [`Resistances`] to its [`DamageType`].
+/// When a [`Health`] reaches zero, its entity is marked as [`Dead`], a [`DeathEvent`] is sent
+/// and, if it has a [`Ragdoll`], it collapses.
+/// What happens to the player is additionally published as [`PlayerDamaged`] and [`PlayerDied`].
+/// In Blender, objects get a [`Health`] through the custom property `"health": <max>`.
+pub(crate) fn health_plugin(app: &mut App) {
+    app.register_type::<Health>()
+        .register_gltf_extra("health", |entity, value| {
+            let max = value.as_f64().context("Expected a number")?;
+            entity.insert(Health::new(max as f32));
+            Ok(())
+        })
+        .register_type::<DamageType>()
+        .register_type::<Resistances>()
+        .register_type::<Dead>()
+        .add_event::<DamageEvent>()
+        .add_event::<HealEvent>()
+        .add_event::<DeathEvent>()
+        .add_systems(
+            Update,
+            (apply_damage, apply_healing, handle_deaths)
+                .chain()
+                .after(detect_hits)
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Health {
+    pub(crate) current: f32,
+    pub(crate) max: f32,
+}
+
+impl Health {
+    pub(crate) fn new(max: f32) -> Self {
+        Self { current: max, max }
+    }
+
+    /// Fraction of the maximum health that is left, between 0 and 1
+    pub(crate) fn fraction(&self) -> f32 {
+        if self.max <= 0. {
+            0.
+        } else {
+            (self.current / self.max).clamp(0., 1.)
+        }
+    }
+
+    pub(crate) fn is_dead(&self) -> bool {
+        self.current <= 0.
+    }
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::new(100.)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Serialize, Deserialize)]
+pub(crate) enum DamageType {
+    #[default]
+    Physical,
+    Fire,
+    Poison,
+}
+
+/// Multipliers for incoming damage per [`DamageType`]. 0 means immune, 2 means double damage.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Resistances {
+    pub(crate) physical: f32,
+    pub(crate) fire: f32,
+    pub(crate) poison: f32,
+}
+
+impl Resistances {
+    pub(crate) fn multiplier(&self, damage_type: DamageType) -> f32 {
+        match damage_type {
+            DamageType::Physical => self.physical,
+            DamageType::Fire => self.fire,
+            DamageType::Poison => self.poison,
+        }
+    }
+}
+
+impl Default for Resistances {
+    fn default() -> Self {
+        Self {
+            physical: 1.,
+            fire: 1.,
+            poison: 1.,
+        }
+    }
+}
+
+/// Marks an entity whose [`Health`] ran out. Dead entities take no damage and cannot be healed.
+#[derive(
+    Debug, Clone, Copy, Eq, PartialEq, Component, Reflect, Serialize, Deserialize, Default,
+)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Dead;
+
+#[derive(Debug, Clone, PartialEq, Event)]
+pub(crate) struct DamageEvent {
+    pub(crate) attacker: Option<Entity>,
+    pub(crate) target: Entity,
+    pub(crate) amount: f32,
+    pub(crate) damage_type: DamageType,
+    /// Where the target was hit, in world space
+    pub(crate) point: Vec3,
+    /// Direction in which the hit pushes the target
+    pub(crate) direction: Vec3,
+}
+
+#[derive(Debug, Clone, PartialEq, Event)]
+pub(crate) struct HealEvent {
+    pub(crate) target: Entity,
+    pub(crate) amount: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Event)]
+pub(crate) struct DeathEvent {
+    pub(crate) entity: Entity,
+    /// Whoever dealt the final blow
+    pub(crate) killer: Option<Entity>,
+}
+
+pub(crate) fn apply_damage(
+    mut damage_events: EventReader<DamageEvent>,
+    mut healths: Query<(&mut Health, Option<&Resistances>), Without<Dead>>,
+    players: Query<(), With<Player>>,
+    mut death_events: EventWriter<DeathEvent>,
+    mut player_damaged: EventWriter<PlayerDamaged>,
+) {
+    for event in damage_events.read() {
+        let Ok((mut health, resistances)) = healths.get_mut(event.target) else {
+            continue;
+        };
+        if health.is_dead() {
+            // Already killed by an earlier event this frame
+            continue;
+        }
+        let multiplier = resistances
+            .map(|resistances| resistances.multiplier(event.damage_type))
+            .unwrap_or(1.);
+        let amount = (event.amount * multiplier).min(health.current);
+        health.current -= amount;
+        if amount > 0. && players.contains(event.target) {
+            player_damaged.send(PlayerDamaged {
+                amount,
+                max_health: health.max,
+                damage_type: event.damage_type,
+                attacker: event.attacker,
+                direction: event.direction,
+            });
+        }
+        if health.is_dead() {
+            death_events.send(DeathEvent {
+                entity: event.target,
+                killer: event.attacker,
+            });
+        }
+    }
+}
+
+fn apply_healing(
+    mut heal_events: EventReader<HealEvent>,
+    mut healths: Query<&mut Health, Without<Dead>>,
+) {
+    for event in heal_events.read() {
+        let Ok(mut health) = healths.get_mut(event.target) else {
+            continue;
+        };
+        if health.is_dead() {
+            continue;
+        }
+        health.current = (health.current + event.amount).min(health.max);
+    }
+}
+
+fn handle_deaths(
+    mut commands: Commands,
+    mut death_events: EventReader<DeathEvent>,
+    ragdolls: Query<(), With<Ragdoll>>,
+    players: Query<(), With<Player>>,
+    mut ragdoll_events: EventWriter<RagdollEvent>,
+    mut player_died: EventWriter<PlayerDied>,
+) {
+    for event in death_events.read() {
+        commands.entity(event.entity).insert(Dead);
+        if players.contains(event.entity) {
+            player_died.send(PlayerDied {
+                killer: event.killer,
+            });
+        }
+        if ragdolls.contains(event.entity) {
+            ragdoll_events.send(RagdollEvent::Activate(event.entity));
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use crate::{
+    combat::health::{DamageEvent, DamageType, Health},
+    file_system_interaction::config::GameConfig,
+    movement::{
+        character_controller::{AnimationEvent, AnimationEventKind, AnimationLink, Knockback},
+        physics::CollisionLayer,
+    },
+    time_scale::SlowMotionEvent,
+    util::trait_extension::Vec3Ext,
+    GameState,
+};
+use bevy::{animation::AnimationPlayer, prelude::*};
+use bevy_xpbd_3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const HEAVY_HIT_SLOW_MOTION: SlowMotionEvent = SlowMotionEvent {
+    scale: 0.25,
+    duration: 0.4,
+};
+
+/// Lets characters hurt each other in melee.
+/// A [`Hitbox`] is a sensor collider attached to an attacking character. Its collider is only enabled during the
+/// [`HitWindow`]s of the attack animation that is currently running, as tracked by [`Attacking`].
+/// When an enabled hitbox touches a [`Hurtbox`] of another [`Team`], a [`DamageEvent`] is sent for the hurtbox' owner,
+/// both characters freeze for a moment of [`Hitstop`] and the target receives a [`Knockback`].
+/// Heavy hits additionally trigger a short [`SlowMotionEvent`].
+/// Attacks are started by inserting [`Attacking`] or by an [`AnimationEventKind::Attack`] marker on the attack animation.
+pub(crate) fn hitboxes_plugin(app: &mut App) {
+    app.register_type::<Team>()
+        .register_type::<Hitbox>()
+        .register_type::<Hurtbox>()
+        .register_type::<Attacking>()
+        .register_type::<Hitstop>()
+        .add_systems(
+            Update,
+            (
+                start_animated_attacks,
+                tick_hitstop,
+                tick_attacks,
+                toggle_hitboxes,
+                detect_hits,
+            )
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+/// Characters on the same team cannot hurt each other.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Serialize, Deserialize)]
+pub(crate) enum Team {
+    Player,
+    Enemy,
+    #[default]
+    Neutral,
+}
+
+/// A collider that deals damage while its owner's attack is in one of its [`HitWindow`]s.
+/// Must be a descendant of the attacking character and have a [`Collider`], [`Sensor`] and [`CollisionLayers`].
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Hitbox {
+    pub(crate) team: Team,
+    pub(crate) damage: f32,
+    pub(crate) damage_type: DamageType,
+    /// Speed at which the target is pushed away
+    pub(crate) knockback: f32,
+    /// Seconds that both characters freeze on a hit to make it feel weighty
+    pub(crate) hitstop: f32,
+    pub(crate) windows: Vec<HitWindow>,
+    /// Everything hit during the current window, so that a single swing only hits once
+    #[serde(skip)]
+    pub(crate) already_hit: Vec<Entity>,
+}
+
+impl Default for Hitbox {
+    fn default() -> Self {
+        Self {
+            team: default(),
+            damage: 10.,
+            damage_type: default(),
+            knockback: 6.,
+            hitstop: 0.08,
+            windows: vec![HitWindow {
+                start: 0.2,
+                end: 0.4,
+            }],
+            already_hit: default(),
+        }
+    }
+}
+
+/// The part of an attack animation during which its [`Hitbox`] is enabled, in seconds from the start of the attack.
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Serialize, Deserialize)]
+pub(crate) struct HitWindow {
+    pub(crate) start: f32,
+    pub(crate) end: f32,
+}
+
+impl HitWindow {
+    fn contains(&self, time: f32) -> bool {
+        (self.start..self.end).contains(&time)
+    }
+}
+
+/// A collider that can be hit by [`Hitbox`]es.
+/// Damage goes to the closest entity with a [`Health`] among itself and its ancestors.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Hurtbox {
+    pub(crate) team: Team,
+    /// Multiplies incoming damage, e.g. for weak spots
+    pub(crate) damage_multiplier: f32,
+}
+
+impl Default for Hurtbox {
+    fn default() -> Self {
+        Self {
+            team: default(),
+            damage_multiplier: 1.,
+        }
+    }
+}
+
+/// Inserted on a character to start an attack. Removed again once the attack is over.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Attacking {
+    /// Seconds since the start of the attack. Stands still during [`Hitstop`], just like the animation.
+    pub(crate) elapsed: f32,
+    /// Length of the attack animation
+    pub(crate) duration: f32,
+}
+
+impl Attacking {
+    pub(crate) fn new(duration: f32) -> Self {
+        Self {
+            elapsed: 0.,
+            duration,
+        }
+    }
+}
+
+/// Freezes the attack and animation of a character for a moment after a hit.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Hitstop {
+    pub(crate) remaining: f32,
+}
+
+fn start_animated_attacks(mut commands: Commands, mut events: EventReader<AnimationEvent>) {
+    for event in events.read() {
+        if let AnimationEventKind::Attack(duration) = event.kind {
+            if let Some(mut entity) = commands.get_entity(event.entity) {
+                entity.insert(Attacking::new(duration));
+            }
+        }
+    }
+}
+
+fn tick_hitstop(
+    mut commands: Commands,
+    time: Res<Time<Virtual>>,
+    mut hitstops: Query<(Entity, &mut Hitstop, Option<&AnimationLink>)>,
+    mut animation_players: Query<&mut AnimationPlayer>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, mut hitstop, link) in hitstops.iter_mut() {
+        hitstop.remaining -= dt;
+        let finished = hitstop.remaining <= 0.;
+        if let Some(mut animation_player) =
+            link.and_then(|link| animation_players.get_mut(link.0).ok())
+        {
+            if finished {
+                animation_player.resume();
+            } else {
+                animation_player.pause();
+            }
+        }
+        if finished {
+            commands.entity(entity).remove::<Hitstop>();
+        }
+    }
+}
+
+fn tick_attacks(
+    mut commands: Commands,
+    time: Res<Time<Virtual>>,
+    mut attackers: Query<(Entity, &mut Attacking), Without<Hitstop>>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, mut attacking) in attackers.iter_mut() {
+        attacking.elapsed += dt;
+        if attacking.elapsed >= attacking.duration {
+            commands.entity(entity).remove::<Attacking>();
+        }
+    }
+}
+
+fn toggle_hitboxes(
+    mut hitboxes: Query<(Entity, &mut Hitbox, &mut CollisionLayers)>,
+    attackers: Query<&Attacking>,
+    parents: Query<&Parent>,
+) {
+    for (entity, mut hitbox, mut collision_layers) in hitboxes.iter_mut() {
+        let attack_time = parents
+            .iter_ancestors(entity)
+            .find_map(|ancestor| attackers.get(ancestor).ok())
+            .map(|attacking| attacking.elapsed);
+        let is_active = attack_time
+            .is_some_and(|time| hitbox.windows.iter().any(|window| window.contains(time)));
+        let layers = if is_active {
+            CollisionLayer::character_trigger()
+        } else {
+            if !hitbox.already_hit.is_empty() {
+                hitbox.already_hit.clear();
+            }
+            CollisionLayers::NONE
+        };
+        if *collision_layers != layers {
+            *collision_layers = layers;
+        }
+    }
+}
+
+pub(crate) fn detect_hits(
+    mut commands: Commands,
+    mut collisions: EventReader<Collision>,
+    mut hitboxes: Query<(&mut Hitbox, &CollisionLayers, &GlobalTransform)>,
+    hurtboxes: Query<&Hurtbox>,
+    transforms: Query<&GlobalTransform>,
+    parents: Query<&Parent>,
+    targets: Query<(), With<Health>>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut slow_motion_events: EventWriter<SlowMotionEvent>,
+    config: Res<GameConfig>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("detect_hits").entered();
+    // Either body of a collision can be the hitbox
+    let pairs = collisions
+        .read()
+        .filter(|Collision(contacts)| contacts.during_current_frame)
+        .flat_map(|Collision(contacts)| {
+            [
+                (contacts.entity1, contacts.entity2),
+                (contacts.entity2, contacts.entity1),
+            ]
+        });
+    for (hitbox_entity, hurtbox_entity) in pairs {
+        let Ok(hurtbox) = hurtboxes.get(hurtbox_entity) else {
+            continue;
+        };
+        let Ok((mut hitbox, collision_layers, hitbox_transform)) = hitboxes.get_mut(hitbox_entity)
+        else {
+            continue;
+        };
+        // The collision might have been detected before the window closed
+        if *collision_layers == CollisionLayers::NONE || hitbox.team == hurtbox.team {
+            continue;
+        }
+        let Some(target) = std::iter::once(hurtbox_entity)
+            .chain(parents.iter_ancestors(hurtbox_entity))
+            .find(|entity| targets.contains(*entity))
+        else {
+            continue;
+        };
+        if hitbox.already_hit.contains(&target) {
+            continue;
+        }
+        hitbox.already_hit.push(target);
+
+        let attacker = parents.iter_ancestors(hitbox_entity).last();
+        let point = transforms
+            .get(hurtbox_entity)
+            .map(GlobalTransform::translation)
+            .unwrap_or_else(|_| hitbox_transform.translation());
+        let direction = (point - hitbox_transform.translation())
+            .horizontal()
+            .normalize_or_zero();
+        let amount = hitbox.damage * hurtbox.damage_multiplier;
+        damage_events.send(DamageEvent {
+            attacker,
+            target,
+            amount,
+            damage_type: hitbox.damage_type,
+            point,
+            direction,
+        });
+        if amount >= config.combat.heavy_hit_damage {
+            slow_motion_events.send(HEAVY_HIT_SLOW_MOTION);
+        }
+
+        let hitstop = Hitstop {
+            remaining: hitbox.hitstop,
+        };
+        commands.entity(target).insert((
+            hitstop.clone(),
+            Knockback {
+                velocity: direction * hitbox.knockback,
+                remaining: config.combat.knockback_duration,
+            },
+        ));
+        if let Some(attacker) = attacker {
+            commands.entity(attacker).insert(hitstop);
+        }
+    }
+}
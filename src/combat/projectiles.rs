@@ -0,0 +1,304 @@
+use crate::{
+    level_instantiation::spawning::GltfExtrasAppExt,
+    movement::physics::CollisionLayer,
+    util::pool::{pool_plugin, Pool, Poolable},
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_xpbd_3d::prelude::*;
+use seldom_fn_plugin::FnPluginExt;
+use serde::{Deserialize, Serialize};
+
+const GRAVITY: Vec3 = Vec3::new(0., -9.81, 0.);
+/// Seconds between the points of a predicted trajectory
+const PREDICTION_STEP: f32 = 1. / 30.;
+/// Fraction of its speed a projectile keeps when it bounces
+const BOUNCE_RESTITUTION: f32 = 0.5;
+
+/// Moves [`Projectile`]s and reports what they hit with [`ProjectileHitEvent`]s.
+/// Projectiles are requested with [`SpawnProjectileEvent`]s and taken from a [`Pool`] so that
+/// rapid fire does not spawn and despawn entities every frame. After hitting something or running out
+/// of lifetime, they are hidden and returned to the pool. Projectiles with bounces left bounce off what they hit instead,
+/// which is reported with a [`ProjectileBounceEvent`].
+/// The player throws projectiles through the [`throwing_plugin`](crate::combat::throwing::throwing_plugin).
+/// In Blender, objects are given a [`Surface`] through the custom property `"surface": "<variant>"`.
+pub(crate) fn projectiles_plugin(app: &mut App) {
+    app.register_type::<Projectile>()
+        .register_type::<Surface>()
+        .register_gltf_extra("surface", |entity, value| {
+            entity.insert(serde_json::from_value::<Surface>(value.clone())?);
+            Ok(())
+        })
+        .add_event::<SpawnProjectileEvent>()
+        .add_event::<ProjectileHitEvent>()
+        .add_event::<ProjectileBounceEvent>()
+        .fn_plugin(pool_plugin::<Projectile>)
+        .add_systems(
+            Update,
+            (spawn_projectiles, move_projectiles)
+                .chain()
+                .in_set(ProjectileSystemSet)
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
+pub(crate) struct ProjectileSystemSet;
+
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Projectile {
+    pub(crate) velocity: Vec3,
+    /// Whether the projectile falls in an arc or flies straight
+    pub(crate) gravity: bool,
+    pub(crate) radius: f32,
+    pub(crate) hit_detection: HitDetection,
+    /// Seconds until the projectile is returned to the pool without having hit anything
+    pub(crate) lifetime: f32,
+    /// Who fired the projectile. It never hits its shooter.
+    pub(crate) shooter: Option<Entity>,
+    /// How often the projectile bounces off what it hits before the hit counts
+    pub(crate) bounces: u32,
+    /// The key of the item that was thrown, if any
+    pub(crate) item: Option<String>,
+}
+
+impl Poolable for Projectile {}
+
+impl Default for Projectile {
+    fn default() -> Self {
+        Self {
+            velocity: Vec3::ZERO,
+            gravity: true,
+            radius: 0.1,
+            hit_detection: default(),
+            lifetime: 5.,
+            shooter: None,
+            bounces: 0,
+            item: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Serialize, Deserialize)]
+pub(crate) enum HitDetection {
+    /// Only the center of the projectile collides. Cheap and fine for small, fast projectiles.
+    #[default]
+    Raycast,
+    /// The whole sphere of the projectile acts as a sensor, so it also hits what it only brushes past.
+    Sensor,
+}
+
+/// What a collider is made of, reported when a projectile hits it so that e.g. the right impact sound can be played.
+#[derive(
+    Debug, Clone, Copy, Eq, PartialEq, Component, Reflect, Serialize, Deserialize, Default,
+)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) enum Surface {
+    #[default]
+    Generic,
+    Stone,
+    Wood,
+    Metal,
+    Flesh,
+}
+
+#[derive(Debug, Clone, PartialEq, Event)]
+pub(crate) struct SpawnProjectileEvent {
+    pub(crate) position: Vec3,
+    pub(crate) projectile: Projectile,
+}
+
+#[derive(Debug, Clone, PartialEq, Event)]
+pub(crate) struct ProjectileHitEvent {
+    pub(crate) projectile: Entity,
+    pub(crate) shooter: Option<Entity>,
+    pub(crate) target: Entity,
+    pub(crate) point: Vec3,
+    pub(crate) normal: Vec3,
+    pub(crate) velocity: Vec3,
+    pub(crate) surface: Surface,
+}
+
+/// Sent when a projectile bounces off something instead of hitting it.
+#[derive(Debug, Clone, PartialEq, Event)]
+pub(crate) struct ProjectileBounceEvent {
+    pub(crate) projectile: Entity,
+    pub(crate) target: Entity,
+    pub(crate) point: Vec3,
+    pub(crate) normal: Vec3,
+    /// Velocity before the bounce
+    pub(crate) velocity: Vec3,
+    pub(crate) surface: Surface,
+}
+
+fn spawn_projectiles(
+    mut commands: Commands,
+    mut spawn_events: EventReader<SpawnProjectileEvent>,
+    mut pool: ResMut<Pool<Projectile>>,
+    mut handles: Local<Option<(Handle<Mesh>, Handle<StandardMaterial>)>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for event in spawn_events.read() {
+        let transform = Transform::from_translation(event.position)
+            .with_scale(Vec3::splat(event.projectile.radius));
+        let entity = pool.acquire(
+            &mut commands,
+            event.projectile.clone(),
+            |commands, projectile| {
+                let (mesh, material) = handles
+                    .get_or_insert_with(|| {
+                        (
+                            meshes.add(Mesh::from(shape::UVSphere {
+                                radius: 1.,
+                                ..default()
+                            })),
+                            materials.add(Color::rgb(0.9, 0.9, 0.8).into()),
+                        )
+                    })
+                    .clone();
+                commands
+                    .spawn((
+                        Name::new("Projectile"),
+                        projectile,
+                        PbrBundle {
+                            mesh,
+                            material,
+                            ..default()
+                        },
+                    ))
+                    .id()
+            },
+        );
+        commands.entity(entity).insert(transform);
+    }
+}
+
+fn move_projectiles(
+    mut commands: Commands,
+    time: Res<Time<Virtual>>,
+    mut projectiles: Query<(Entity, &mut Projectile, &mut Transform)>,
+    surfaces: Query<&Surface>,
+    parents: Query<&Parent>,
+    spatial_query: SpatialQuery,
+    mut pool: ResMut<Pool<Projectile>>,
+    mut hit_events: EventWriter<ProjectileHitEvent>,
+    mut bounce_events: EventWriter<ProjectileBounceEvent>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("move_projectiles").entered();
+    let dt = time.delta_seconds();
+    for (entity, mut projectile, mut transform) in projectiles.iter_mut() {
+        projectile.lifetime -= dt;
+        if projectile.lifetime <= 0. {
+            pool.release(&mut commands, entity);
+            continue;
+        }
+        if projectile.gravity {
+            projectile.velocity += GRAVITY * dt;
+        }
+        let step = projectile.velocity * dt;
+        let Some((target, point, normal)) =
+            cast_projectile(&projectile, transform.translation, step, &spatial_query)
+        else {
+            transform.translation += step;
+            continue;
+        };
+        let surface = std::iter::once(target)
+            .chain(parents.iter_ancestors(target))
+            .find_map(|entity| surfaces.get(entity).ok())
+            .copied()
+            .unwrap_or_default();
+        if projectile.bounces > 0 {
+            bounce_events.send(ProjectileBounceEvent {
+                projectile: entity,
+                target,
+                point,
+                normal,
+                velocity: projectile.velocity,
+                surface,
+            });
+            let velocity = projectile.velocity;
+            projectile.velocity = (velocity - 2. * velocity.dot(normal) * normal) * BOUNCE_RESTITUTION;
+            projectile.bounces -= 1;
+            // Keep clear of the surface so that the next step does not hit it again right away
+            transform.translation = point + normal * 0.01;
+            continue;
+        }
+        hit_events.send(ProjectileHitEvent {
+            projectile: entity,
+            shooter: projectile.shooter,
+            target,
+            point,
+            normal,
+            velocity: projectile.velocity,
+            surface,
+        });
+        pool.release(&mut commands, entity);
+    }
+}
+
+/// What a projectile moving by `step` from `origin` hits, with the point and normal of the hit
+fn cast_projectile(
+    projectile: &Projectile,
+    origin: Vec3,
+    step: Vec3,
+    spatial_query: &SpatialQuery,
+) -> Option<(Entity, Vec3, Vec3)> {
+    let distance = step.length();
+    let direction = step.try_normalize()?;
+    let filter = CollisionLayer::solid_filter().without_entities(projectile.shooter);
+    match projectile.hit_detection {
+        HitDetection::Raycast => spatial_query
+            .cast_ray(origin, direction, distance, true, filter)
+            .map(|hit| {
+                (
+                    hit.entity,
+                    origin + direction * hit.time_of_impact,
+                    hit.normal,
+                )
+            }),
+        HitDetection::Sensor => spatial_query
+            .cast_shape(
+                &Collider::ball(projectile.radius),
+                origin,
+                Quat::IDENTITY,
+                direction,
+                distance,
+                true,
+                filter,
+            )
+            .map(|hit| (hit.entity, hit.point1, hit.normal1)),
+    }
+}
+
+/// Simulates a projectile launched from `position` just like [`move_projectiles`] would, at a fixed time step.
+/// Returns the points it passes through until it first hits something or `duration` seconds are up.
+/// The last point is where it hits, if it does.
+pub(crate) fn predict_trajectory(
+    position: Vec3,
+    projectile: &Projectile,
+    spatial_query: &SpatialQuery,
+    duration: f32,
+) -> (Vec<Vec3>, Option<Vec3>) {
+    let mut points = vec![position];
+    let mut position = position;
+    let mut velocity = projectile.velocity;
+    let mut time = 0.;
+    while time < duration.min(projectile.lifetime) {
+        if projectile.gravity {
+            velocity += GRAVITY * PREDICTION_STEP;
+        }
+        let step = velocity * PREDICTION_STEP;
+        if let Some((_, point, _)) = cast_projectile(projectile, position, step, spatial_query) {
+            points.push(point);
+            return (points, Some(point));
+        }
+        position += step;
+        points.push(position);
+        time += PREDICTION_STEP;
+    }
+    (points, None)
+}
@@ -0,0 +1,226 @@
+use crate::{
+    combat::health::Dead,
+    file_system_interaction::game_state_serialization::{
+        enter_save, save_pending, SaveFile, SavedState,
+    },
+    game_events::{LevelLoaded, PlayerDied},
+    level_instantiation::{
+        levels::CurrentLevel,
+        markers::{Marker, MarkersAppExt},
+    },
+    movement::physics::CollisionLayer,
+    player_control::{actions::ActionsFrozen, player_embodiment::Player},
+    screen_transition::{ScreenTransition, TransitionEffect, TransitionFinished},
+    theme::UiTheme,
+    world_interaction::triggers::{Trigger, TriggerEnter, TriggerKind, TriggerSystemSet},
+    GameState,
+};
+use anyhow::Result;
+use bevy::{ecs::world::EntityWorldMut, prelude::*};
+use bevy_egui::{egui, EguiContexts};
+use bevy_mod_sysfail::*;
+use bevy_xpbd_3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Seconds the dead player lies on the ground before the screen starts to fade
+const DEATH_DURATION: f32 = 2.;
+const FADE_OUT_DURATION: f32 = 1.;
+/// Fraction of their maximum health the player respawns with
+const RESPAWN_HEALTH_FRACTION: f32 = 0.5;
+
+/// Handles what happens after the player dies. The player collapses into their ragdoll,
+/// the screen fades to black and the player respawns at the last [`Checkpoint`] they reached,
+/// with the state they had back then but with only half of their health and without status effects.
+/// Entering a level counts as reaching a checkpoint, so without any the player respawns where they entered the level.
+/// In Blender, objects named with a `[checkpoint]` suffix become trigger volumes the size of their scale.
+/// Deaths are counted in the saved [`DeathCount`], which the HUD shows when enabled in the settings.
+pub(crate) fn respawn_plugin(app: &mut App) {
+    app.register_type::<Checkpoint>()
+        .register_type::<DeathCount>()
+        .init_resource::<DeathCount>()
+        .init_resource::<LastCheckpoint>()
+        .register_marker("checkpoint", insert_checkpoint)
+        .add_systems(
+            Update,
+            (
+                mark_level_start,
+                record_level_start.run_if(not(save_pending)),
+                reach_checkpoints.after(TriggerSystemSet),
+                start_respawning,
+                respawn,
+            )
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(OnExit(GameState::Playing), stop_respawning)
+        .add_systems(OnEnter(GameState::Menu), reset_progress);
+}
+
+/// A trigger volume that saves the player's state as the one to respawn with when they walk in.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Checkpoint;
+
+/// How often the player has died in this playthrough.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Resource, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Resource, Serialize, Deserialize)]
+pub(crate) struct DeathCount(pub(crate) u32);
+
+/// Survives respawning, which reloads the level, and is only reset when returning to the menu.
+#[derive(Debug, Clone, PartialEq, Resource, Default)]
+struct LastCheckpoint {
+    save: Option<SaveFile>,
+    /// Set when a level was entered, since the player's state can only be recorded once a loaded save has been applied
+    record_on_spawn: bool,
+}
+
+/// Set while waiting to respawn.
+#[derive(Debug, Clone, PartialEq, Resource, Default)]
+struct Respawning {
+    /// Seconds since the player died
+    elapsed: f32,
+    fading: bool,
+}
+
+fn insert_checkpoint(entity: &mut EntityWorldMut, _marker: &Marker) -> Result<()> {
+    entity.insert((
+        Checkpoint,
+        // Scaled by the object's transform
+        Collider::cuboid(1., 1., 1.),
+        Sensor,
+        CollisionLayer::trigger(),
+        Trigger::new(TriggerKind::Area),
+        Visibility::Hidden,
+    ));
+    Ok(())
+}
+
+fn mark_level_start(
+    mut level_events: EventReader<LevelLoaded>,
+    mut checkpoint: ResMut<LastCheckpoint>,
+) {
+    if level_events.read().last().is_some() {
+        checkpoint.record_on_spawn = true;
+    }
+}
+
+#[sysfail(log(level = "error"))]
+fn record_level_start(
+    mut checkpoint: ResMut<LastCheckpoint>,
+    saved_state: SavedState,
+) -> Result<()> {
+    if !checkpoint.record_on_spawn || saved_state.players.is_empty() {
+        return Ok(());
+    }
+    checkpoint.save = Some(saved_state.snapshot()?);
+    checkpoint.record_on_spawn = false;
+    Ok(())
+}
+
+#[sysfail(log(level = "error"))]
+fn reach_checkpoints(
+    mut trigger_events: EventReader<TriggerEnter>,
+    checkpoints: Query<(), With<Checkpoint>>,
+    living_players: Query<(), (With<Player>, Without<Dead>)>,
+    mut checkpoint: ResMut<LastCheckpoint>,
+    saved_state: SavedState,
+) -> Result<()> {
+    for event in trigger_events.read() {
+        if checkpoints.contains(event.trigger) && living_players.contains(event.other) {
+            checkpoint.save = Some(saved_state.snapshot()?);
+            info!("Reached checkpoint");
+        }
+    }
+    Ok(())
+}
+
+fn start_respawning(
+    mut commands: Commands,
+    mut death_events: EventReader<PlayerDied>,
+    respawning: Option<Res<Respawning>>,
+    mut deaths: ResMut<DeathCount>,
+    mut freeze: ResMut<ActionsFrozen>,
+) {
+    if death_events.read().last().is_none() || respawning.is_some() {
+        return;
+    }
+    deaths.0 += 1;
+    freeze.freeze();
+    commands.init_resource::<Respawning>();
+}
+
+fn respawn(
+    mut commands: Commands,
+    time: Res<Time<Virtual>>,
+    respawning: Option<ResMut<Respawning>>,
+    checkpoint: Res<LastCheckpoint>,
+    deaths: Res<DeathCount>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut transitions: EventWriter<ScreenTransition>,
+    mut finished_transitions: EventReader<TransitionFinished>,
+    theme: Res<UiTheme>,
+    mut egui_contexts: EguiContexts,
+) {
+    let faded_out = finished_transitions
+        .read()
+        .any(|event| event.effect == TransitionEffect::FadeToBlack && event.covered);
+    let Some(mut respawning) = respawning else {
+        return;
+    };
+    respawning.elapsed += time.delta_seconds();
+    let text_opacity = (respawning.elapsed / DEATH_DURATION).min(1.);
+    let ctx = egui_contexts.ctx_mut();
+    // Drawn on its own layer, which stays on top of the fade
+    ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Tooltip,
+        egui::Id::new("death_message"),
+    ))
+    .text(
+        ctx.screen_rect().center(),
+        egui::Align2::CENTER_CENTER,
+        "You died",
+        egui::FontId::proportional(theme.menu_text.heading),
+        theme.colors.text.gamma_multiply(text_opacity),
+    );
+    if !respawning.fading {
+        if respawning.elapsed >= DEATH_DURATION {
+            respawning.fading = true;
+            transitions.send(ScreenTransition::cover(
+                TransitionEffect::FadeToBlack,
+                FADE_OUT_DURATION,
+            ));
+        }
+        return;
+    }
+    if !faded_out {
+        return;
+    }
+    match checkpoint.save.clone() {
+        Some(save) => {
+            let save = save.for_respawn(*deaths, RESPAWN_HEALTH_FRACTION);
+            enter_save(&mut commands, save, &mut current_level, &mut next_state);
+        }
+        None => {
+            warn!("No checkpoint was recorded, restarting the level from scratch");
+            next_state.set(GameState::Loading);
+        }
+    }
+}
+
+fn stop_respawning(
+    mut commands: Commands,
+    respawning: Option<Res<Respawning>>,
+    mut freeze: ResMut<ActionsFrozen>,
+) {
+    if respawning.is_none() {
+        return;
+    }
+    commands.remove_resource::<Respawning>();
+    freeze.unfreeze();
+}
+
+fn reset_progress(mut deaths: ResMut<DeathCount>, mut checkpoint: ResMut<LastCheckpoint>) {
+    *deaths = default();
+    *checkpoint = default();
+}
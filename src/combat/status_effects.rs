@@ -0,0 +1,201 @@
+use crate::{
+    combat::health::{apply_damage, DamageEvent, DamageType, Dead},
+    movement::character_controller::{GeneralMovementSystemSet, SpeedMultiplier},
+    GameState,
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Seconds between two damage ticks of damage over time effects
+const TICK_INTERVAL: f32 = 1.;
+
+/// Handles lingering effects on characters, like poison or burning.
+/// Effects are applied with [`ApplyStatusEffectEvent`]s and tracked in the target's [`StatusEffects`].
+/// What happens when an effect is applied to a target that already has it is decided by its [`Stacking`] rule.
+pub(crate) fn status_effects_plugin(app: &mut App) {
+    app.register_type::<StatusEffects>()
+        .register_type::<StatusEffect>()
+        .register_type::<StatusEffectKind>()
+        .add_event::<ApplyStatusEffectEvent>()
+        .add_systems(
+            Update,
+            (add_status_effects, tick_status_effects, apply_slow)
+                .chain()
+                .before(apply_damage)
+                .before(GeneralMovementSystemSet)
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct StatusEffects(pub(crate) Vec<StatusEffect>);
+
+impl StatusEffects {
+    pub(crate) fn get(&self, kind: StatusEffectKind) -> Option<&StatusEffect> {
+        self.0.iter().find(|effect| effect.kind == kind)
+    }
+
+    fn add(&mut self, kind: StatusEffectKind, duration: f32, source: Option<Entity>) {
+        let Some(effect) = self.0.iter_mut().find(|effect| effect.kind == kind) else {
+            self.0.push(StatusEffect {
+                kind,
+                remaining: duration,
+                stacks: 1,
+                until_tick: TICK_INTERVAL,
+                source,
+            });
+            return;
+        };
+        match kind.stacking() {
+            Stacking::Refresh => {
+                effect.remaining = effect.remaining.max(duration);
+            }
+            Stacking::Stack { max } => {
+                effect.stacks = (effect.stacks + 1).min(max);
+                effect.remaining = effect.remaining.max(duration);
+            }
+        }
+        effect.source = source.or(effect.source);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+#[reflect(Serialize, Deserialize)]
+pub(crate) struct StatusEffect {
+    pub(crate) kind: StatusEffectKind,
+    /// Seconds until the effect wears off
+    pub(crate) remaining: f32,
+    pub(crate) stacks: u32,
+    /// Seconds until the next damage tick
+    pub(crate) until_tick: f32,
+    /// Who applied the effect, credited for its damage
+    #[serde(skip)]
+    pub(crate) source: Option<Entity>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Reflect, Serialize, Deserialize)]
+#[reflect(Serialize, Deserialize)]
+pub(crate) enum StatusEffectKind {
+    /// Deals poison damage every second. Stacks up to five times.
+    Poison,
+    /// Reduces movement speed. Stacks up to three times.
+    Slow,
+    /// Deals fire damage every second. Reapplying only refreshes the duration.
+    Burn,
+}
+
+/// What happens when an effect is applied to a target that already has it.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum Stacking {
+    /// The duration is extended, the strength stays the same
+    Refresh,
+    /// The duration is extended and the effect gets stronger, up to `max` stacks
+    Stack { max: u32 },
+}
+
+impl StatusEffectKind {
+    pub(crate) fn stacking(self) -> Stacking {
+        match self {
+            StatusEffectKind::Poison => Stacking::Stack { max: 5 },
+            StatusEffectKind::Slow => Stacking::Stack { max: 3 },
+            StatusEffectKind::Burn => Stacking::Refresh,
+        }
+    }
+
+    /// Damage dealt per tick and stack, along with its type
+    fn damage_per_tick(self) -> Option<(f32, DamageType)> {
+        match self {
+            StatusEffectKind::Poison => Some((3., DamageType::Poison)),
+            StatusEffectKind::Burn => Some((8., DamageType::Fire)),
+            StatusEffectKind::Slow => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Event)]
+pub(crate) struct ApplyStatusEffectEvent {
+    pub(crate) target: Entity,
+    pub(crate) kind: StatusEffectKind,
+    pub(crate) duration: f32,
+    pub(crate) source: Option<Entity>,
+}
+
+fn add_status_effects(
+    mut commands: Commands,
+    mut events: EventReader<ApplyStatusEffectEvent>,
+    mut targets: Query<Option<&mut StatusEffects>, Without<Dead>>,
+) {
+    for event in events.read() {
+        let Ok(status_effects) = targets.get_mut(event.target) else {
+            continue;
+        };
+        if let Some(mut status_effects) = status_effects {
+            status_effects.add(event.kind, event.duration, event.source);
+        } else {
+            let mut status_effects = StatusEffects::default();
+            status_effects.add(event.kind, event.duration, event.source);
+            commands.entity(event.target).insert(status_effects);
+        }
+    }
+}
+
+fn tick_status_effects(
+    time: Res<Time<Virtual>>,
+    mut targets: Query<(Entity, &mut StatusEffects, Has<Dead>)>,
+    mut damage_events: EventWriter<DamageEvent>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, mut status_effects, is_dead) in targets.iter_mut() {
+        if is_dead {
+            if !status_effects.0.is_empty() {
+                status_effects.0.clear();
+            }
+            continue;
+        }
+        for effect in status_effects.0.iter_mut() {
+            effect.remaining -= dt;
+            effect.until_tick -= dt;
+            if effect.until_tick > 0. {
+                continue;
+            }
+            effect.until_tick += TICK_INTERVAL;
+            let Some((damage, damage_type)) = effect.kind.damage_per_tick() else {
+                continue;
+            };
+            damage_events.send(DamageEvent {
+                attacker: effect.source,
+                target: entity,
+                amount: damage * effect.stacks as f32,
+                damage_type,
+                point: default(),
+                direction: default(),
+            });
+        }
+        status_effects.0.retain(|effect| effect.remaining > 0.);
+    }
+}
+
+fn apply_slow(
+    mut commands: Commands,
+    mut targets: Query<
+        (Entity, &StatusEffects, Option<&mut SpeedMultiplier>),
+        Changed<StatusEffects>,
+    >,
+) {
+    for (entity, status_effects, speed_multiplier) in targets.iter_mut() {
+        let stacks = status_effects
+            .get(StatusEffectKind::Slow)
+            .map(|effect| effect.stacks)
+            .unwrap_or_default();
+        let multiplier = 0.7_f32.powi(stacks as i32);
+        match speed_multiplier {
+            Some(mut speed_multiplier) => {
+                speed_multiplier.set_if_neq(SpeedMultiplier(multiplier));
+            }
+            None => {
+                commands.entity(entity).insert(SpeedMultiplier(multiplier));
+            }
+        }
+    }
+}
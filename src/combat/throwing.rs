@@ -0,0 +1,162 @@
+use crate::{
+    combat::projectiles::{
+        predict_trajectory, Projectile, ProjectileSystemSet, SpawnProjectileEvent,
+    },
+    file_system_interaction::config::GameConfig,
+    hud::Hotbar,
+    player_control::{
+        actions::PlayerAction, aiming::Aiming, camera::IngameCamera, driving::Driving,
+        player_embodiment::Player, riding::Riding,
+    },
+    util::criteria::is_frozen,
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_xpbd_3d::prelude::*;
+use leafwing_input_manager::prelude::ActionState;
+
+/// Seconds of flight shown by the trajectory preview
+const PREVIEW_DURATION: f32 = 3.;
+
+/// Lets the player throw things with [`PlayerAction::Throw`]. While the button is held, the predicted trajectory
+/// is drawn as an arc, and releasing the button throws. If the selected hotbar slot holds an item that is
+/// [`Throwable`](crate::file_system_interaction::config::Throwable), that item is thrown and taken out of the hotbar.
+/// Otherwise, the player throws a ball as a demo. While [`Aiming`], throws fly straight to the aim point.
+/// Thrown things are [`Projectile`]s, so their bounces and impacts are reported like those of any other projectile.
+pub(crate) fn throwing_plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (preview_throw, throw)
+            .chain()
+            .before(ProjectileSystemSet)
+            .run_if(in_state(GameState::Playing).and_then(not(is_frozen))),
+    );
+}
+
+/// A throw that would happen right now
+struct PlannedThrow {
+    position: Vec3,
+    projectile: Projectile,
+    /// The hotbar slot the thrown item is taken from
+    slot: Option<usize>,
+}
+
+fn plan_throw(
+    player: Entity,
+    transform: &Transform,
+    aiming: Option<&Aiming>,
+    hotbar: Option<&Hotbar>,
+    camera_transform: &Transform,
+    config: &GameConfig,
+) -> PlannedThrow {
+    let hand = transform.translation + Vec3::Y * 0.4;
+    let (direction, gravity) = match aiming.and_then(|aiming| (aiming.point - hand).try_normalize())
+    {
+        // Fly straight to whatever is under the reticle
+        Some(direction) => (direction, false),
+        // Aim where the camera looks, but with a bit of an arc
+        None => (
+            (camera_transform.forward() + Vec3::Y * 0.3).normalize(),
+            true,
+        ),
+    };
+    let item = hotbar.and_then(|hotbar| {
+        let key = hotbar.slots.get(hotbar.selected)?.as_ref()?;
+        let throwable = config.items.get(key)?.throwable.as_ref()?;
+        Some((hotbar.selected, key.clone(), throwable))
+    });
+    let mut projectile = Projectile {
+        velocity: direction * config.combat.throw_speed,
+        gravity,
+        shooter: Some(player),
+        ..default()
+    };
+    let slot = item.map(|(slot, key, throwable)| {
+        projectile.bounces = throwable.bounces;
+        projectile.radius = throwable.radius;
+        projectile.item = Some(key);
+        slot
+    });
+    PlannedThrow {
+        position: hand + direction * 0.5,
+        projectile,
+        slot,
+    }
+}
+
+fn preview_throw(
+    players: Query<
+        (
+            Entity,
+            &ActionState<PlayerAction>,
+            &Transform,
+            Option<&Aiming>,
+            Option<&Hotbar>,
+        ),
+        (With<Player>, Without<Driving>, Without<Riding>),
+    >,
+    cameras: Query<&Transform, With<IngameCamera>>,
+    config: Res<GameConfig>,
+    spatial_query: SpatialQuery,
+    mut gizmos: Gizmos,
+) {
+    let Some(camera_transform) = cameras.iter().next() else {
+        return;
+    };
+    for (player, actions, transform, aiming, hotbar) in players.iter() {
+        if !actions.pressed(PlayerAction::Throw) {
+            continue;
+        }
+        let planned = plan_throw(player, transform, aiming, hotbar, camera_transform, &config);
+        let (points, landing) = predict_trajectory(
+            planned.position,
+            &planned.projectile,
+            &spatial_query,
+            PREVIEW_DURATION,
+        );
+        gizmos.linestrip(points, Color::WHITE.with_a(0.7));
+        if let Some(landing) = landing {
+            gizmos.circle(landing + Vec3::Y * 0.05, Vec3::Y, 0.25, Color::WHITE);
+        }
+    }
+}
+
+fn throw(
+    mut players: Query<
+        (
+            Entity,
+            &ActionState<PlayerAction>,
+            &Transform,
+            Option<&Aiming>,
+            Option<&mut Hotbar>,
+        ),
+        (With<Player>, Without<Driving>, Without<Riding>),
+    >,
+    cameras: Query<&Transform, With<IngameCamera>>,
+    mut spawn_events: EventWriter<SpawnProjectileEvent>,
+    config: Res<GameConfig>,
+) {
+    let Some(camera_transform) = cameras.iter().next() else {
+        return;
+    };
+    for (player, actions, transform, aiming, mut hotbar) in players.iter_mut() {
+        if !actions.just_released(PlayerAction::Throw) {
+            continue;
+        }
+        let planned = plan_throw(
+            player,
+            transform,
+            aiming,
+            hotbar.as_deref(),
+            camera_transform,
+            &config,
+        );
+        if let (Some(slot), Some(hotbar)) = (planned.slot, hotbar.as_mut()) {
+            hotbar.slots[slot] = None;
+        }
+        spawn_events.send(SpawnProjectileEvent {
+            position: planned.position,
+            projectile: planned.projectile,
+        });
+    }
+}
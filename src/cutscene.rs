@@ -0,0 +1,466 @@
+use crate::{
+    file_system_interaction::asset_loading::GltfAssets,
+    game_events::LevelLoaded,
+    hud::captions::CaptionEvent,
+    level_instantiation::{levels::LevelRegistry, spawning::objects::camera::CameraAnchor},
+    menu::Settings,
+    player_control::{
+        actions::{ActionsFrozen, UiAction},
+        camera::IngameCamera,
+    },
+    screen_transition::{ScreenTransition, TransitionEffect, LETTERBOX_HEIGHT},
+    theme::UiTheme,
+    GameState,
+};
+use bevy::{
+    asset::LoadState, ecs::system::SystemParam, gltf::Gltf, prelude::*, transform::TransformSystem,
+    utils::HashSet,
+};
+use bevy_common_assets::ron::RonAssetPlugin;
+use bevy_egui::{egui, EguiContexts};
+use bevy_kira_audio::prelude::{Audio, AudioControl, AudioInstance, AudioTween};
+use leafwing_input_manager::prelude::ActionState;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const LETTERBOX_DURATION: f32 = 0.5;
+const ANIMATION_TRANSITION: Duration = Duration::from_millis(200);
+
+/// Plays [`Cutscene`]s, which are timelines loaded from `*.cutscene.ron` assets.
+/// A cutscene is started by a [`PlayCutscene`] event, or when a level with an
+/// [`intro_cutscene`](crate::level_instantiation::levels::LevelDefinition) is loaded for the first time in a session.
+/// While it plays, the player's input is frozen, the camera follows the cutscene's keyframes and
+/// the screen is letterboxed through a [`ScreenTransition`]. Pressing [`UiAction::Confirm`] skips it.
+/// Either way, a [`CutsceneFinished`] event is sent at the end.
+pub(crate) fn cutscene_plugin(app: &mut App) {
+    app.add_plugins(RonAssetPlugin::<Cutscene>::new(&["cutscene.ron"]))
+        .add_event::<PlayCutscene>()
+        .add_event::<CutsceneFinished>()
+        .add_systems(
+            Update,
+            (
+                play_level_intros,
+                start_cutscenes,
+                advance_cutscene.run_if(resource_exists::<ActiveCutscene>()),
+                show_cutscene.run_if(resource_exists::<ActiveCutscene>()),
+            )
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            PostUpdate,
+            move_cutscene_camera
+                .run_if(resource_exists::<ActiveCutscene>())
+                .run_if(in_state(GameState::Playing))
+                .before(TransformSystem::TransformPropagate),
+        )
+        .add_systems(OnExit(GameState::Playing), stop_cutscene);
+}
+
+/// Starts the cutscene at `path`, relative to the assets directory. Ignored while another cutscene plays.
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub(crate) struct PlayCutscene {
+    pub(crate) path: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub(crate) struct CutsceneFinished {
+    pub(crate) path: String,
+    pub(crate) skipped: bool,
+}
+
+/// A timeline of tracks, each made of entries at a time in seconds since the start of the cutscene.
+/// Entries do not need to be sorted.
+#[derive(Debug, Clone, PartialEq, Asset, TypePath, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct Cutscene {
+    /// Seconds until the cutscene ends
+    pub(crate) duration: f32,
+    /// The camera moves smoothly from keyframe to keyframe. Without keyframes, the ingame camera stays in control.
+    pub(crate) camera: Vec<CameraKeyframe>,
+    pub(crate) animations: Vec<AnimationCue>,
+    pub(crate) lines: Vec<LineCue>,
+    pub(crate) sounds: Vec<SoundCue>,
+    /// Opacity of a black overlay, blended between keyframes
+    pub(crate) fades: Vec<FadeKeyframe>,
+}
+
+/// Where the camera is at a point in time, either given by `position` and `look_at`
+/// or by the `anchor` of a camera placed in Blender, see [`CameraAnchor`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct CameraKeyframe {
+    pub(crate) time: f32,
+    #[serde(default)]
+    pub(crate) position: Vec3,
+    #[serde(default)]
+    pub(crate) look_at: Vec3,
+    /// Name of the [`CameraAnchor`] to take the camera's position and rotation from instead
+    #[serde(default)]
+    pub(crate) anchor: Option<String>,
+}
+
+impl CameraKeyframe {
+    fn transform(&self, anchors: &Query<(&CameraAnchor, &GlobalTransform)>) -> Transform {
+        let anchor = self.anchor.as_ref().and_then(|name| {
+            anchors
+                .iter()
+                .find(|(anchor, _)| &anchor.name == name)
+                .map(|(_, transform)| transform.compute_transform())
+        });
+        anchor.unwrap_or_else(|| {
+            Transform::from_translation(self.position).looking_at(self.look_at, Vec3::Y)
+        })
+    }
+}
+
+/// Plays an animation of the level's GLTF on the entity with the given name.
+/// The character controller takes over again once the character starts or stops moving.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct AnimationCue {
+    pub(crate) time: f32,
+    pub(crate) target: String,
+    pub(crate) animation: String,
+    #[serde(default)]
+    pub(crate) repeat: bool,
+}
+
+/// A subtitle shown at the bottom of the screen
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct LineCue {
+    pub(crate) time: f32,
+    pub(crate) duration: f32,
+    #[serde(default)]
+    pub(crate) speaker: Option<String>,
+    pub(crate) text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct SoundCue {
+    pub(crate) time: f32,
+    pub(crate) path: String,
+    #[serde(default = "full_volume")]
+    pub(crate) volume: f64,
+    /// Shown as a sound caption if sound captions are enabled, e.g. "[door creaks]"
+    #[serde(default)]
+    pub(crate) caption: Option<String>,
+}
+
+fn full_volume() -> f64 {
+    1.
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct FadeKeyframe {
+    pub(crate) time: f32,
+    pub(crate) opacity: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Resource)]
+struct ActiveCutscene {
+    path: String,
+    handle: Handle<Cutscene>,
+    /// Seconds since the cutscene started, which only count once it has loaded
+    elapsed: f32,
+    sounds: Vec<Handle<AudioInstance>>,
+}
+
+/// What the cues of a cutscene act on
+#[derive(SystemParam)]
+struct CutsceneTargets<'w, 's> {
+    asset_server: Res<'w, AssetServer>,
+    audio: Res<'w, Audio>,
+    gltf_assets: Res<'w, GltfAssets>,
+    gltfs: Res<'w, Assets<Gltf>>,
+    names: Query<'w, 's, (Entity, &'static Name)>,
+    children: Query<'w, 's, &'static Children>,
+    animation_players: Query<'w, 's, &'static mut AnimationPlayer>,
+    caption_events: EventWriter<'w, CaptionEvent>,
+}
+
+impl CutsceneTargets<'_, '_> {
+    fn play_animation(&mut self, cue: &AnimationCue) {
+        let Some(animation) = self
+            .gltfs
+            .get(&self.gltf_assets.level)
+            .and_then(|level| level.named_animations.get(&cue.animation))
+        else {
+            warn!(
+                "Cutscene animation {} not found in the level",
+                cue.animation
+            );
+            return;
+        };
+        let Some((target, _)) = self
+            .names
+            .iter()
+            .find(|(_, name)| name.as_str() == cue.target)
+        else {
+            warn!("Cutscene target {} not found", cue.target);
+            return;
+        };
+        // The animation player may sit on the model rather than on the named entity itself
+        let Some(player) = std::iter::once(target)
+            .chain(self.children.iter_descendants(target))
+            .find(|entity| self.animation_players.contains(*entity))
+        else {
+            warn!("Cutscene target {} has no animation player", cue.target);
+            return;
+        };
+        if let Ok(mut animation_player) = self.animation_players.get_mut(player) {
+            let playing =
+                animation_player.play_with_transition(animation.clone_weak(), ANIMATION_TRANSITION);
+            if cue.repeat {
+                playing.repeat();
+            }
+        }
+    }
+
+    fn play_sound(&mut self, cue: &SoundCue) -> Handle<AudioInstance> {
+        if let Some(caption) = &cue.caption {
+            self.caption_events
+                .send(CaptionEvent::sound(caption.clone()));
+        }
+        self.audio
+            .play(self.asset_server.load(cue.path.clone()))
+            .with_volume(cue.volume)
+            .handle()
+    }
+}
+
+fn stop_sounds(audio_instances: &mut Assets<AudioInstance>, sounds: &[Handle<AudioInstance>]) {
+    for sound in sounds {
+        if let Some(instance) = audio_instances.get_mut(sound) {
+            instance.stop(AudioTween::default());
+        }
+    }
+}
+
+fn play_level_intros(
+    mut levels_loaded: EventReader<LevelLoaded>,
+    registry: Res<LevelRegistry>,
+    mut played: Local<HashSet<String>>,
+    mut play_events: EventWriter<PlayCutscene>,
+) {
+    for event in levels_loaded.read() {
+        let Some(intro) = registry
+            .get(&event.level)
+            .and_then(|level| level.intro_cutscene.as_ref())
+        else {
+            continue;
+        };
+        if played.insert(event.level.clone()) {
+            play_events.send(PlayCutscene {
+                path: intro.clone(),
+            });
+        }
+    }
+}
+
+fn start_cutscenes(
+    mut commands: Commands,
+    mut play_events: EventReader<PlayCutscene>,
+    active: Option<Res<ActiveCutscene>>,
+    asset_server: Res<AssetServer>,
+    mut freeze: ResMut<ActionsFrozen>,
+    mut transitions: EventWriter<ScreenTransition>,
+) {
+    let Some(event) = play_events.read().last() else {
+        return;
+    };
+    if let Some(active) = active {
+        warn!(
+            "Cannot play cutscene {} while {} is playing",
+            event.path, active.path
+        );
+        return;
+    }
+    freeze.freeze();
+    transitions.send(ScreenTransition::cover(
+        TransitionEffect::Letterbox,
+        LETTERBOX_DURATION,
+    ));
+    commands.insert_resource(ActiveCutscene {
+        path: event.path.clone(),
+        handle: asset_server.load(event.path.clone()),
+        elapsed: 0.,
+        sounds: default(),
+    });
+}
+
+fn advance_cutscene(
+    mut commands: Commands,
+    time: Res<Time<Virtual>>,
+    mut active: ResMut<ActiveCutscene>,
+    cutscenes: Res<Assets<Cutscene>>,
+    actions: Query<&ActionState<UiAction>>,
+    mut targets: CutsceneTargets,
+    mut audio_instances: ResMut<Assets<AudioInstance>>,
+    mut freeze: ResMut<ActionsFrozen>,
+    mut finished_events: EventWriter<CutsceneFinished>,
+    mut transitions: EventWriter<ScreenTransition>,
+) {
+    let Some(cutscene) = cutscenes.get(&active.handle) else {
+        if targets.asset_server.load_state(active.handle.id()) == LoadState::Failed {
+            error!("Failed to load cutscene {}", active.path);
+            commands.remove_resource::<ActiveCutscene>();
+            freeze.unfreeze();
+            transitions.send(ScreenTransition::reveal(
+                TransitionEffect::Letterbox,
+                LETTERBOX_DURATION,
+            ));
+        }
+        return;
+    };
+    let skipped = actions
+        .iter()
+        .any(|actions| actions.just_pressed(UiAction::Confirm));
+    let start = active.elapsed;
+    let end = start + time.delta_seconds();
+    active.elapsed = end;
+    // Cues fire in the frame in which their time is passed
+    let due = |cue_time: f32| (start..end).contains(&cue_time);
+    if !skipped {
+        for cue in cutscene.animations.iter().filter(|cue| due(cue.time)) {
+            targets.play_animation(cue);
+        }
+        for cue in cutscene.sounds.iter().filter(|cue| due(cue.time)) {
+            let sound = targets.play_sound(cue);
+            active.sounds.push(sound);
+        }
+    }
+    if skipped || end >= cutscene.duration {
+        if skipped {
+            stop_sounds(&mut audio_instances, &active.sounds);
+        }
+        commands.remove_resource::<ActiveCutscene>();
+        freeze.unfreeze();
+        transitions.send(ScreenTransition::reveal(
+            TransitionEffect::Letterbox,
+            LETTERBOX_DURATION,
+        ));
+        finished_events.send(CutsceneFinished {
+            path: active.path.clone(),
+            skipped,
+        });
+    }
+}
+
+fn move_cutscene_camera(
+    active: Res<ActiveCutscene>,
+    cutscenes: Res<Assets<Cutscene>>,
+    mut cameras: Query<&mut Transform, With<IngameCamera>>,
+    anchors: Query<(&CameraAnchor, &GlobalTransform)>,
+) {
+    let Some(cutscene) = cutscenes.get(&active.handle) else {
+        return;
+    };
+    let mut keyframes: Vec<_> = cutscene.camera.iter().collect();
+    keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+    let Some((from, to, t)) = surrounding(&keyframes, active.elapsed, |keyframe| keyframe.time)
+    else {
+        return;
+    };
+    let (from, to) = (from.transform(&anchors), to.transform(&anchors));
+    let position = from.translation.lerp(to.translation, t);
+    let rotation = from.rotation.slerp(to.rotation, t);
+    for mut transform in cameras.iter_mut() {
+        *transform = Transform::from_translation(position).with_rotation(rotation);
+    }
+}
+
+fn show_cutscene(
+    active: Res<ActiveCutscene>,
+    cutscenes: Res<Assets<Cutscene>>,
+    settings: Res<Settings>,
+    theme: Res<UiTheme>,
+    mut egui_contexts: EguiContexts,
+) {
+    let Some(cutscene) = cutscenes.get(&active.handle) else {
+        return;
+    };
+    let ctx = egui_contexts.ctx_mut();
+    let screen = ctx.screen_rect();
+    let bar_height = screen.height() * LETTERBOX_HEIGHT;
+
+    let mut fades: Vec<_> = cutscene.fades.iter().collect();
+    fades.sort_by(|a, b| a.time.total_cmp(&b.time));
+    if let Some((from, to, t)) = surrounding(&fades, active.elapsed, |keyframe| keyframe.time) {
+        let opacity = from.opacity + (to.opacity - from.opacity) * t;
+        ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Foreground,
+            egui::Id::new("cutscene_fade"),
+        ))
+        .rect_filled(
+            screen,
+            0.,
+            egui::Color32::from_black_alpha((opacity.clamp(0., 1.) * 255.) as u8),
+        );
+    }
+
+    let line = cutscene
+        .lines
+        .iter()
+        .find(|line| (line.time..line.time + line.duration).contains(&active.elapsed));
+    egui::Area::new("Cutscene")
+        .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0., -bar_height))
+        .order(egui::Order::Foreground)
+        .interactable(false)
+        .show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                if let Some(line) = line {
+                    let text = match &line.speaker {
+                        Some(speaker) => format!("{speaker}: {}", line.text),
+                        None => line.text.clone(),
+                    };
+                    ui.label(
+                        egui::RichText::new(text)
+                            .size(settings.captions.text_size)
+                            .color(theme.colors.text),
+                    );
+                }
+                ui.label(
+                    egui::RichText::new("Press Enter to skip")
+                        .size(theme.text.small)
+                        .color(theme.colors.weak_text),
+                );
+            });
+        });
+}
+
+/// The keyframes around `time` and how far along between them it is, eased in and out.
+/// Before the first and after the last keyframe, that keyframe is held.
+fn surrounding<'a, T>(
+    keyframes: &[&'a T],
+    time: f32,
+    keyframe_time: impl Fn(&T) -> f32,
+) -> Option<(&'a T, &'a T, f32)> {
+    let first = *keyframes.first()?;
+    let Some(next) = keyframes
+        .iter()
+        .position(|keyframe| keyframe_time(keyframe) > time)
+    else {
+        let last = *keyframes.last()?;
+        return Some((last, last, 0.));
+    };
+    if next == 0 {
+        return Some((first, first, 0.));
+    }
+    let (from, to) = (keyframes[next - 1], keyframes[next]);
+    let span = keyframe_time(to) - keyframe_time(from);
+    let t = ((time - keyframe_time(from)) / span).clamp(0., 1.);
+    Some((from, to, t * t * (3. - 2. * t)))
+}
+
+fn stop_cutscene(
+    mut commands: Commands,
+    active: Option<Res<ActiveCutscene>>,
+    mut audio_instances: ResMut<Assets<AudioInstance>>,
+    mut freeze: ResMut<ActionsFrozen>,
+) {
+    let Some(active) = active else {
+        return;
+    };
+    stop_sounds(&mut audio_instances, &active.sounds);
+    commands.remove_resource::<ActiveCutscene>();
+    freeze.unfreeze();
+}
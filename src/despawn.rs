@@ -0,0 +1,71 @@
+use crate::GameState;
+use bevy::prelude::*;
+
+/// Cleans up entities that only make sense for a while, so that leaving a state does not leave anything behind.
+/// - Entities with [`DespawnOnExit`] are despawned together with their children when their state is exited.
+///   Everything spawned outside of the level's scene while playing, like ragdoll bodies or spawned prefabs,
+///   should carry `DespawnOnExit(GameState::Playing)`.
+/// - Entities with a [`Lifetime`] are despawned once it runs out. It only runs while the game is not paused.
+///
+/// Entities that belong to a [`crate::util::pool::Pool`] are cleaned up by their pool instead.
+pub(crate) fn despawn_plugin(app: &mut App) {
+    app.register_type::<Lifetime>().add_systems(
+        Update,
+        expire_lifetimes.run_if(in_state(GameState::Playing)),
+    );
+    for state in [
+        GameState::InitialLoading,
+        GameState::Loading,
+        GameState::Playing,
+        GameState::Menu,
+        GameState::Error,
+    ] {
+        app.add_systems(OnExit(state.clone()), despawn_on_exit(state));
+    }
+}
+
+/// Despawns the entity and its children when the given state is exited.
+#[derive(Debug, Clone, Eq, PartialEq, Component)]
+pub(crate) struct DespawnOnExit(pub(crate) GameState);
+
+/// Despawns the entity and its children after the given amount of seconds.
+#[derive(Debug, Clone, PartialEq, Component, Reflect)]
+#[reflect(Component)]
+pub(crate) struct Lifetime(pub(crate) Timer);
+
+impl Lifetime {
+    pub(crate) fn from_seconds(seconds: f32) -> Self {
+        Self(Timer::from_seconds(seconds, TimerMode::Once))
+    }
+}
+
+fn despawn_on_exit(
+    state: GameState,
+) -> impl FnMut(Commands, Query<(Entity, &DespawnOnExit)>, Query<&Parent>) {
+    move |mut commands, entities, parents| {
+        let marked = |entity: Entity| {
+            entities
+                .get(entity)
+                .is_ok_and(|(_, despawn)| despawn.0 == state)
+        };
+        for (entity, despawn) in entities.iter() {
+            // Descendants are despawned along with their marked ancestor
+            if despawn.0 != state || parents.iter_ancestors(entity).any(marked) {
+                continue;
+            }
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn expire_lifetimes(
+    mut commands: Commands,
+    time: Res<Time<Virtual>>,
+    mut lifetimes: Query<(Entity, &mut Lifetime)>,
+) {
+    for (entity, mut lifetime) in lifetimes.iter_mut() {
+        if lifetime.0.tick(time.delta()).just_finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
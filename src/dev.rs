@@ -1,4 +1,8 @@
-use crate::dev::dev_editor::dev_editor_plugin;
+use crate::dev::{
+    budgets::budgets_plugin, console::console_plugin, debug_overlay::debug_overlay_plugin,
+    dev_editor::dev_editor_plugin, hot_reload::hot_reload_plugin,
+    scene_editor::scene_editor_plugin, tuning::tuning_plugin,
+};
 use bevy::{
     diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
     prelude::*,
@@ -7,7 +11,13 @@ use bevy_editor_pls::prelude::*;
 use bevy_xpbd_3d::prelude::*;
 use seldom_fn_plugin::FnPluginExt;
 
+pub(crate) mod budgets;
+pub(crate) mod console;
+pub(crate) mod debug_overlay;
 pub(crate) mod dev_editor;
+pub(crate) mod hot_reload;
+pub(crate) mod scene_editor;
+pub(crate) mod tuning;
 
 /// Plugin with debugging utility intended for use during development only.
 /// Don't include this in a release build.
@@ -17,6 +27,12 @@ pub(crate) fn dev_plugin(app: &mut App) {
             .insert_resource(default_editor_controls())
             .add_plugins(FrameTimeDiagnosticsPlugin)
             .fn_plugin(dev_editor_plugin)
+            .fn_plugin(hot_reload_plugin)
+            .fn_plugin(console_plugin)
+            .fn_plugin(debug_overlay_plugin)
+            .fn_plugin(scene_editor_plugin)
+            .fn_plugin(tuning_plugin)
+            .fn_plugin(budgets_plugin)
             .add_plugins(LogDiagnosticsPlugin::filtered(vec![]))
             .add_plugins(PhysicsDebugPlugin::default())
             .insert_resource(PhysicsDebugConfig {
@@ -0,0 +1,183 @@
+use crate::{
+    level_instantiation::{markers::MarkerSystemSet, spawning::SpawnSystemSet},
+    movement::{character_controller::GeneralMovementSystemSet, navigation::NavigationSystemSet},
+    player_control::camera::CameraUpdateSystemSet,
+};
+use bevy::{ecs::schedule::ScheduleLabel, prelude::*, utils::HashMap};
+use bevy_egui::{egui, EguiContexts};
+use bevy_xpbd_3d::PhysicsSet;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Seconds a warning stays on screen after a section went over its budget
+const WARNING_DURATION: f32 = 3.;
+/// Seconds between logging warnings about the same section
+const LOG_COOLDOWN: f32 = 10.;
+/// Frames are measured as a whole under this name
+const FRAME: &str = "frame";
+
+/// Measures how long the major parts of a frame take and warns when one exceeds its [`PerformanceBudgets`] entry.
+/// Warnings are logged and shown in the top right corner of the screen; the budgets can be changed in the editor's resource inspector.
+/// Sections are measured from before until after their system set, so systems running in parallel count towards them as well.
+/// Navmesh baking does not show up here, since it runs in background tasks.
+/// Build with the `tracing` feature for per-system spans.
+pub(crate) fn budgets_plugin(app: &mut App) {
+    app.register_type::<PerformanceBudgets>()
+        .init_resource::<PerformanceBudgets>()
+        .init_resource::<SectionTimings>()
+        .measure_section(Update, "spawning", SpawnSystemSet)
+        .measure_section(Update, "markers", MarkerSystemSet)
+        .measure_section(Update, "navigation", NavigationSystemSet)
+        .measure_section(Update, "character controllers", GeneralMovementSystemSet)
+        .measure_section(PostUpdate, "camera", CameraUpdateSystemSet)
+        .measure_section(FixedUpdate, "physics", PhysicsSet::StepSimulation)
+        .add_systems(Last, check_budgets)
+        .add_systems(Update, show_budget_warnings);
+}
+
+/// Milliseconds each section may take per frame
+#[derive(Debug, Clone, PartialEq, Resource, Reflect, Serialize, Deserialize)]
+#[reflect(Resource, Serialize, Deserialize)]
+pub(crate) struct PerformanceBudgets {
+    pub(crate) show_warnings: bool,
+    pub(crate) frame: f32,
+    pub(crate) sections: HashMap<String, f32>,
+}
+
+impl Default for PerformanceBudgets {
+    fn default() -> Self {
+        Self {
+            show_warnings: true,
+            frame: 1000. / 60.,
+            sections: [
+                ("spawning", 4.),
+                ("markers", 2.),
+                ("navigation", 1.),
+                ("character controllers", 2.),
+                ("camera", 1.),
+                ("physics", 6.),
+            ]
+            .into_iter()
+            .map(|(name, budget)| (name.to_string(), budget))
+            .collect(),
+        }
+    }
+}
+
+impl PerformanceBudgets {
+    fn get(&self, section: &str) -> Option<f32> {
+        if section == FRAME {
+            Some(self.frame)
+        } else {
+            self.sections.get(section).copied()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+struct SectionTiming {
+    started: Option<Instant>,
+    /// Sections in [`FixedUpdate`] can run several times per frame
+    this_frame: Duration,
+    /// Milliseconds taken by the last frame that went over the budget, and when that was
+    exceeded: Option<(f32, f32)>,
+    last_logged: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Resource, Default)]
+struct SectionTimings(HashMap<&'static str, SectionTiming>);
+
+trait BudgetAppExt {
+    fn measure_section(
+        &mut self,
+        schedule: impl ScheduleLabel,
+        name: &'static str,
+        set: impl SystemSet + Clone,
+    ) -> &mut Self;
+}
+
+impl BudgetAppExt for App {
+    fn measure_section(
+        &mut self,
+        schedule: impl ScheduleLabel,
+        name: &'static str,
+        set: impl SystemSet + Clone,
+    ) -> &mut Self {
+        self.add_systems(
+            schedule,
+            (
+                (move |mut timings: ResMut<SectionTimings>| {
+                    timings.0.entry(name).or_default().started = Some(Instant::now());
+                })
+                .before(set.clone()),
+                (move |mut timings: ResMut<SectionTimings>| {
+                    let timing = timings.0.entry(name).or_default();
+                    if let Some(started) = timing.started.take() {
+                        timing.this_frame += started.elapsed();
+                    }
+                })
+                .after(set),
+            ),
+        )
+    }
+}
+
+fn check_budgets(
+    time: Res<Time<Real>>,
+    budgets: Res<PerformanceBudgets>,
+    mut timings: ResMut<SectionTimings>,
+) {
+    let now = time.elapsed_seconds();
+    timings.0.entry(FRAME).or_default().this_frame = time.delta();
+    for (name, timing) in timings.0.iter_mut() {
+        let milliseconds = std::mem::take(&mut timing.this_frame).as_secs_f32() * 1000.;
+        let Some(budget) = budgets.get(name) else {
+            continue;
+        };
+        if milliseconds <= budget {
+            continue;
+        }
+        timing.exceeded = Some((milliseconds, now));
+        if timing
+            .last_logged
+            .map_or(true, |logged| now - logged > LOG_COOLDOWN)
+        {
+            timing.last_logged = Some(now);
+            warn!("{name} took {milliseconds:.1} ms, which is over its budget of {budget:.1} ms");
+        }
+    }
+}
+
+fn show_budget_warnings(
+    time: Res<Time<Real>>,
+    budgets: Res<PerformanceBudgets>,
+    timings: Res<SectionTimings>,
+    mut egui_contexts: EguiContexts,
+) {
+    if !budgets.show_warnings {
+        return;
+    }
+    let now = time.elapsed_seconds();
+    let mut warnings: Vec<_> = timings
+        .0
+        .iter()
+        .filter_map(|(name, timing)| {
+            let (milliseconds, at) = timing.exceeded?;
+            let budget = budgets.get(name)?;
+            (now - at < WARNING_DURATION)
+                .then(|| format!("{name}: {milliseconds:.1} ms (budget {budget:.1} ms)"))
+        })
+        .collect();
+    if warnings.is_empty() {
+        return;
+    }
+    warnings.sort();
+    egui::Area::new("budget_warnings")
+        .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-8., 8.))
+        .interactable(false)
+        .show(egui_contexts.ctx_mut(), |ui| {
+            for warning in warnings {
+                ui.colored_label(egui::Color32::YELLOW, warning);
+            }
+        });
+}
@@ -0,0 +1,377 @@
+use crate::{
+    despawn::DespawnOnExit,
+    dev::dev_editor::DevEditorWindow,
+    file_system_interaction::{
+        replay::{load_replay, ReplayPlaybackRequest},
+        storage::GameStorage,
+    },
+    hud::Hotbar,
+    level_instantiation::{levels::LevelTransitionEvent, prefabs::SpawnPrefab},
+    player_control::{actions::ActionsFrozen, player_embodiment::Player},
+    time_scale::TimeScale,
+    GameState,
+};
+use anyhow::{bail, Context, Result};
+use bevy::prelude::*;
+use bevy_editor_pls::editor::Editor;
+use bevy_egui::{egui, EguiContexts};
+use std::collections::BTreeMap;
+
+/// Lines of output kept in the console
+const MAX_LOG_LINES: usize = 200;
+
+/// An in-game console toggled with the backtick key.
+/// Commands are registered with [`ConsoleAppExt::register_console_command`] and run with exclusive access to the world.
+/// The console keeps a history that is browsed with the arrow keys and completes command names with tab.
+/// Type `help` for a list of all commands.
+pub(crate) fn console_plugin(app: &mut App) {
+    app.init_resource::<Console>()
+        .init_resource::<ConsoleCommands>()
+        .register_console_command("help", "help", help)
+        .register_console_command("clear", "clear", clear)
+        .register_console_command("spawn_prefab", "spawn_prefab <name>", spawn_prefab)
+        .register_console_command("teleport", "teleport <x> <y> <z>", teleport)
+        .register_console_command("timescale", "timescale <scale>", set_time_scale)
+        .register_console_command("debug", "debug <colliders|navmesh>", toggle_debug_render)
+        .register_console_command("load_level", "load_level <level> <spawn point>", load_level)
+        .register_console_command("give", "give <item>", give_item)
+        .register_console_command("replay", "replay <name>", play_replay)
+        .add_systems(
+            Update,
+            (toggle_console, show_console, run_console_commands).chain(),
+        );
+}
+
+/// Runs a command with the arguments that followed its name and returns what to print.
+pub(crate) type ConsoleCommand = fn(&mut World, &[&str]) -> Result<String>;
+
+#[derive(Clone, Copy)]
+struct ConsoleCommandEntry {
+    usage: &'static str,
+    run: ConsoleCommand,
+}
+
+/// Sorted by name, so that `help` and completions are listed alphabetically.
+#[derive(Clone, Resource, Default)]
+struct ConsoleCommands(BTreeMap<String, ConsoleCommandEntry>);
+
+pub(crate) trait ConsoleAppExt {
+    fn register_console_command(
+        &mut self,
+        name: &str,
+        usage: &'static str,
+        command: ConsoleCommand,
+    ) -> &mut Self;
+}
+
+impl ConsoleAppExt for App {
+    fn register_console_command(
+        &mut self,
+        name: &str,
+        usage: &'static str,
+        command: ConsoleCommand,
+    ) -> &mut Self {
+        self.init_resource::<ConsoleCommands>();
+        self.world.resource_mut::<ConsoleCommands>().0.insert(
+            name.to_string(),
+            ConsoleCommandEntry {
+                usage,
+                run: command,
+            },
+        );
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Resource, Default)]
+struct Console {
+    open: bool,
+    input: String,
+    log: Vec<String>,
+    /// Previously submitted lines, oldest first
+    history: Vec<String>,
+    /// The entry of the history shown in the input while browsing it
+    history_index: Option<usize>,
+    /// Submitted lines waiting to be run
+    pending: Vec<String>,
+}
+
+impl Console {
+    fn print(&mut self, line: impl Into<String>) {
+        self.log.push(line.into());
+        let overflow = self.log.len().saturating_sub(MAX_LOG_LINES);
+        self.log.drain(..overflow);
+    }
+}
+
+fn toggle_console(
+    keys: Res<Input<KeyCode>>,
+    mut console: ResMut<Console>,
+    mut actions_frozen: ResMut<ActionsFrozen>,
+) {
+    if !keys.just_pressed(KeyCode::Grave) {
+        return;
+    }
+    console.open = !console.open;
+    if console.open {
+        actions_frozen.freeze();
+    } else {
+        actions_frozen.unfreeze();
+    }
+}
+
+fn show_console(
+    mut egui_contexts: EguiContexts,
+    mut console: ResMut<Console>,
+    commands: Res<ConsoleCommands>,
+) {
+    if !console.open {
+        return;
+    }
+    let console = console.as_mut();
+    egui::Window::new("Console")
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0., 8.))
+        .default_width(600.)
+        .collapsible(false)
+        .show(egui_contexts.ctx_mut(), |ui| {
+            egui::ScrollArea::vertical()
+                .max_height(300.)
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    for line in &console.log {
+                        ui.monospace(line);
+                    }
+                });
+            ui.separator();
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut console.input)
+                    .desired_width(f32::INFINITY)
+                    .font(egui::TextStyle::Monospace)
+                    // Keeps tab from moving the focus away
+                    .lock_focus(true),
+            );
+            response.request_focus();
+            // The key that opened the console is typed into it as well
+            console.input.retain(|character| character != '`');
+
+            let (submit, tab, up, down) = ui.input(|input| {
+                (
+                    input.key_pressed(egui::Key::Enter),
+                    input.key_pressed(egui::Key::Tab),
+                    input.key_pressed(egui::Key::ArrowUp),
+                    input.key_pressed(egui::Key::ArrowDown),
+                )
+            });
+            if submit && !console.input.trim().is_empty() {
+                let line = std::mem::take(&mut console.input);
+                console.history.push(line.clone());
+                console.history_index = None;
+                console.pending.push(line);
+            } else if tab {
+                complete(console, &commands);
+            } else if up || down {
+                browse_history(console, up);
+            }
+
+            let suggestions = completions(&console.input, &commands);
+            if !console.input.is_empty() && !suggestions.is_empty() {
+                ui.weak(suggestions.join("  "));
+            }
+        });
+}
+
+/// The usages of all commands whose name starts with the first word of `input`
+fn completions<'a>(input: &str, commands: &'a ConsoleCommands) -> Vec<&'a str> {
+    let typed = input.split_whitespace().next().unwrap_or_default();
+    commands
+        .0
+        .iter()
+        .filter(|(name, _)| name.starts_with(typed))
+        .map(|(_, entry)| entry.usage)
+        .collect()
+}
+
+fn complete(console: &mut Console, commands: &ConsoleCommands) {
+    // Only the command name is completed
+    if console.input.contains(' ') {
+        return;
+    }
+    let candidates: Vec<_> = commands
+        .0
+        .keys()
+        .filter(|name| name.starts_with(console.input.as_str()))
+        .collect();
+    match candidates.as_slice() {
+        [] => {}
+        [name] => console.input = format!("{name} "),
+        [first, rest @ ..] => {
+            let common = rest.iter().fold(first.len(), |length, name| {
+                first
+                    .chars()
+                    .zip(name.chars())
+                    .take(length)
+                    .take_while(|(a, b)| a == b)
+                    .count()
+            });
+            console.input = first[..common].to_string();
+        }
+    }
+}
+
+fn browse_history(console: &mut Console, older: bool) {
+    if console.history.is_empty() {
+        return;
+    }
+    let last = console.history.len() - 1;
+    console.history_index = match (console.history_index, older) {
+        (None, true) => Some(last),
+        (None, false) => None,
+        (Some(index), true) => Some(index.saturating_sub(1)),
+        (Some(index), false) if index < last => Some(index + 1),
+        (Some(_), false) => None,
+    };
+    console.input = console
+        .history_index
+        .map(|index| console.history[index].clone())
+        .unwrap_or_default();
+}
+
+fn run_console_commands(world: &mut World) {
+    let pending = std::mem::take(&mut world.resource_mut::<Console>().pending);
+    for line in pending {
+        world.resource_mut::<Console>().print(format!("> {line}"));
+        let mut words = line.split_whitespace();
+        let Some(name) = words.next() else {
+            continue;
+        };
+        let arguments: Vec<_> = words.collect();
+        let command = world.resource::<ConsoleCommands>().0.get(name).copied();
+        let output = match command {
+            Some(command) => match (command.run)(world, &arguments) {
+                Ok(output) => output,
+                Err(error) => format!("Error: {error:#}\nUsage: {}", command.usage),
+            },
+            None => format!("Unknown command \"{name}\", type \"help\" for a list of commands"),
+        };
+        let mut console = world.resource_mut::<Console>();
+        for line in output.lines() {
+            console.print(line);
+        }
+    }
+}
+
+fn parse_argument<T: std::str::FromStr>(arguments: &[&str], index: usize, name: &str) -> Result<T> {
+    arguments
+        .get(index)
+        .with_context(|| format!("Missing argument <{name}>"))?
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid value for <{name}>"))
+}
+
+fn player_transform(world: &mut World) -> Result<Mut<Transform>> {
+    world
+        .query_filtered::<&mut Transform, With<Player>>()
+        .get_single_mut(world)
+        .context("There is no player")
+}
+
+fn help(world: &mut World, _arguments: &[&str]) -> Result<String> {
+    let commands = world.resource::<ConsoleCommands>();
+    Ok(commands
+        .0
+        .values()
+        .map(|entry| entry.usage)
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+fn clear(world: &mut World, _arguments: &[&str]) -> Result<String> {
+    world.resource_mut::<Console>().log.clear();
+    Ok(String::new())
+}
+
+fn spawn_prefab(world: &mut World, arguments: &[&str]) -> Result<String> {
+    let name = arguments.first().context("Missing argument <name>")?;
+    let transform = *player_transform(world)?;
+    let position = transform.translation + transform.forward() * 2.;
+    let prefab = SpawnPrefab::new(world.resource::<AssetServer>(), name);
+    world.spawn((
+        Name::new(name.to_string()),
+        prefab,
+        SpatialBundle::from_transform(Transform::from_translation(position)),
+        DespawnOnExit(GameState::Playing),
+    ));
+    Ok(format!("Spawned \"{name}\""))
+}
+
+fn teleport(world: &mut World, arguments: &[&str]) -> Result<String> {
+    let position = Vec3::new(
+        parse_argument(arguments, 0, "x")?,
+        parse_argument(arguments, 1, "y")?,
+        parse_argument(arguments, 2, "z")?,
+    );
+    if !position.is_finite() {
+        bail!("The position must be made of numbers");
+    }
+    player_transform(world)?.translation = position;
+    Ok(format!("Teleported to {position}"))
+}
+
+fn set_time_scale(world: &mut World, arguments: &[&str]) -> Result<String> {
+    let scale: f32 = parse_argument(arguments, 0, "scale")?;
+    if !scale.is_finite() || scale <= 0. {
+        bail!("The time scale must be a positive number, use the pause menu to pause");
+    }
+    world.resource_mut::<TimeScale>().base = scale;
+    Ok(format!("Time scale set to {scale}"))
+}
+
+fn toggle_debug_render(world: &mut World, arguments: &[&str]) -> Result<String> {
+    let mut editor = world.resource_mut::<Editor>();
+    let state = editor
+        .window_state_mut::<DevEditorWindow>()
+        .context("Failed to get dev window state")?;
+    let (name, enabled) = match arguments.first().copied() {
+        Some("colliders") => ("Collider", &mut state.collider_render_enabled),
+        Some("navmesh") => ("Navmesh", &mut state.navmesh_render_enabled),
+        _ => bail!("Expected \"colliders\" or \"navmesh\""),
+    };
+    *enabled = !*enabled;
+    let status = if *enabled { "on" } else { "off" };
+    Ok(format!("{name} rendering turned {status}"))
+}
+
+fn load_level(world: &mut World, arguments: &[&str]) -> Result<String> {
+    let level = arguments.first().context("Missing argument <level>")?;
+    let spawn_point = arguments.get(1).context("Missing argument <spawn point>")?;
+    world.send_event(LevelTransitionEvent {
+        level: level.to_string(),
+        spawn_point: spawn_point.to_string(),
+    });
+    Ok(format!("Entering \"{level}\""))
+}
+
+fn give_item(world: &mut World, arguments: &[&str]) -> Result<String> {
+    let item = arguments.first().context("Missing argument <item>")?;
+    let mut hotbar = world
+        .query_filtered::<&mut Hotbar, With<Player>>()
+        .get_single_mut(world)
+        .context("There is no player")?;
+    let index = hotbar
+        .slots
+        .iter()
+        .position(Option::is_none)
+        .unwrap_or(hotbar.selected);
+    hotbar.slots[index] = Some(item.to_string());
+    Ok(format!("Put \"{item}\" into slot {}", index + 1))
+}
+
+fn play_replay(world: &mut World, arguments: &[&str]) -> Result<String> {
+    let name = arguments.first().context("Missing argument <name>")?;
+    let replay = load_replay(world.resource::<GameStorage>(), name)?;
+    world.send_event(ReplayPlaybackRequest {
+        replay,
+        attract: false,
+    });
+    Ok(format!("Playing replay \"{name}\""))
+}
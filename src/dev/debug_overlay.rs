@@ -0,0 +1,244 @@
+use crate::{
+    movement::{
+        character_controller::{AnimationState, Walk},
+        navigation::Navigator,
+        perception::Exposure,
+    },
+    player_control::player_embodiment::Player,
+};
+use bevy::{
+    diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+};
+use bevy_egui::{egui, EguiContexts};
+use bevy_tnua::controller::TnuaController;
+use bevy_xpbd_3d::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Frames shown in the frame time graph
+const FRAME_TIME_SAMPLES: usize = 120;
+/// Frame time at the top of the graph, in milliseconds
+const GRAPH_MAX_MILLISECONDS: f32 = 50.;
+/// Radius around the player in which colliders are listed
+const NEARBY_COLLIDER_RADIUS: f32 = 5.;
+
+/// An overlay toggled with F3 that shows what is going on under the hood while playing.
+/// Each panel of [`DebugOverlay`] can be turned on and off in the overlay itself.
+pub(crate) fn debug_overlay_plugin(app: &mut App) {
+    app.register_type::<DebugOverlay>()
+        .init_resource::<DebugOverlay>()
+        .init_resource::<FrameTimes>()
+        .add_systems(
+            Update,
+            (toggle_debug_overlay, record_frame_time, show_debug_overlay).chain(),
+        );
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Resource, Reflect, Serialize, Deserialize)]
+#[reflect(Resource, Serialize, Deserialize)]
+pub(crate) struct DebugOverlay {
+    pub(crate) open: bool,
+    pub(crate) performance: bool,
+    pub(crate) movement: bool,
+    pub(crate) physics: bool,
+    pub(crate) navigation: bool,
+}
+
+impl Default for DebugOverlay {
+    fn default() -> Self {
+        Self {
+            open: false,
+            performance: true,
+            movement: true,
+            physics: true,
+            navigation: true,
+        }
+    }
+}
+
+/// Durations of the last frames in milliseconds, oldest first
+#[derive(Debug, Clone, PartialEq, Resource, Default)]
+struct FrameTimes(VecDeque<f32>);
+
+fn toggle_debug_overlay(keys: Res<Input<KeyCode>>, mut overlay: ResMut<DebugOverlay>) {
+    if keys.just_pressed(KeyCode::F3) {
+        overlay.open = !overlay.open;
+    }
+}
+
+fn record_frame_time(time: Res<Time<Real>>, mut frame_times: ResMut<FrameTimes>) {
+    frame_times.0.push_back(time.delta_seconds() * 1000.);
+    while frame_times.0.len() > FRAME_TIME_SAMPLES {
+        frame_times.0.pop_front();
+    }
+}
+
+fn show_debug_overlay(
+    mut egui_contexts: EguiContexts,
+    mut overlay: ResMut<DebugOverlay>,
+    frame_times: Res<FrameTimes>,
+    diagnostics: Res<DiagnosticsStore>,
+    players: Query<
+        (
+            Entity,
+            &Transform,
+            Option<&LinearVelocity>,
+            Option<&TnuaController>,
+            Option<&AnimationState>,
+            Option<&Exposure>,
+        ),
+        With<Player>,
+    >,
+    navigators: Query<(Entity, Option<&Name>, &Transform, &Walk), With<Navigator>>,
+    names: Query<&Name>,
+    spatial_query: SpatialQuery,
+) {
+    if !overlay.open {
+        return;
+    }
+    let overlay = overlay.as_mut();
+    let player = players.iter().next();
+    egui::Window::new("Debug Overlay")
+        .anchor(egui::Align2::LEFT_TOP, egui::vec2(8., 8.))
+        .resizable(false)
+        .collapsible(false)
+        .show(egui_contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.toggle_value(&mut overlay.performance, "Performance");
+                ui.toggle_value(&mut overlay.movement, "Movement");
+                ui.toggle_value(&mut overlay.physics, "Physics");
+                ui.toggle_value(&mut overlay.navigation, "Navigation");
+            });
+
+            if overlay.performance {
+                ui.separator();
+                let fps = diagnostics
+                    .get(FrameTimeDiagnosticsPlugin::FPS)
+                    .and_then(|fps| fps.smoothed())
+                    .unwrap_or_default();
+                let frame_time = frame_times.0.back().copied().unwrap_or_default();
+                ui.monospace(format!("FPS: {fps:.0} ({frame_time:.1} ms)"));
+                draw_frame_time_graph(ui, &frame_times);
+            }
+
+            let Some((player, transform, velocity, controller, animation, exposure)) = player
+            else {
+                return;
+            };
+            if overlay.movement {
+                ui.separator();
+                let position = transform.translation;
+                ui.monospace(format!(
+                    "Position: {:.1} {:.1} {:.1}",
+                    position.x, position.y, position.z
+                ));
+                if let Some(velocity) = velocity {
+                    ui.monospace(format!(
+                        "Velocity: {:.1} m/s ({:.1} horizontal)",
+                        velocity.length(),
+                        Vec3::new(velocity.x, 0., velocity.z).length()
+                    ));
+                }
+                if let Some(controller) = controller {
+                    let grounded = match controller.is_airborne() {
+                        Ok(true) => "airborne",
+                        Ok(false) => "grounded",
+                        Err(_) => "unknown",
+                    };
+                    let action = controller.action_name().unwrap_or("none");
+                    ui.monospace(format!("State: {grounded}, action: {action}"));
+                }
+                if let Some(animation) = animation {
+                    ui.monospace(format!(
+                        "Animation: {} ({:.1} s)",
+                        animation.state, animation.elapsed
+                    ));
+                }
+                if let Some(exposure) = exposure {
+                    ui.monospace(format!(
+                        "Exposure: {:.2} (light {:.2})",
+                        exposure.value, exposure.light
+                    ));
+                }
+            }
+
+            if overlay.physics {
+                ui.separator();
+                let nearby = spatial_query.shape_intersections(
+                    &Collider::ball(NEARBY_COLLIDER_RADIUS),
+                    transform.translation,
+                    Quat::IDENTITY,
+                    default(),
+                );
+                let nearby: Vec<_> = nearby
+                    .into_iter()
+                    .filter(|&entity| entity != player)
+                    .collect();
+                ui.monospace(format!(
+                    "Colliders within {NEARBY_COLLIDER_RADIUS} m: {}",
+                    nearby.len()
+                ));
+                for entity in nearby.iter().take(10) {
+                    ui.monospace(format!(
+                        "  {}",
+                        display_name(*entity, names.get(*entity).ok())
+                    ));
+                }
+            }
+
+            if overlay.navigation {
+                ui.separator();
+                ui.monospace(format!("Navmesh agents: {}", navigators.iter().len()));
+                for (entity, name, navigator_transform, walk) in navigators.iter() {
+                    let distance = navigator_transform
+                        .translation
+                        .distance(transform.translation);
+                    let state = if walk.direction.is_some() {
+                        "walking"
+                    } else {
+                        "waiting"
+                    };
+                    ui.monospace(format!(
+                        "  {}: {state}, {distance:.1} m away",
+                        display_name(entity, name)
+                    ));
+                }
+            }
+        });
+}
+
+fn draw_frame_time_graph(ui: &mut egui::Ui, frame_times: &FrameTimes) {
+    let size = egui::vec2(FRAME_TIME_SAMPLES as f32 * 2., 40.);
+    let (response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+    let rect = response.rect;
+    painter.rect_filled(rect, 0., egui::Color32::from_black_alpha(120));
+    // 60 FPS reference line
+    let reference_y = rect.bottom() - rect.height() * (1000. / 60.) / GRAPH_MAX_MILLISECONDS;
+    painter.hline(
+        rect.x_range(),
+        reference_y,
+        egui::Stroke::new(1., egui::Color32::DARK_GREEN),
+    );
+    let points = frame_times
+        .0
+        .iter()
+        .enumerate()
+        .map(|(index, milliseconds)| {
+            let x = rect.left() + index as f32 * 2.;
+            let y = rect.bottom() - rect.height() * (milliseconds / GRAPH_MAX_MILLISECONDS).min(1.);
+            egui::pos2(x, y)
+        })
+        .collect();
+    painter.add(egui::Shape::line(
+        points,
+        egui::Stroke::new(1., egui::Color32::WHITE),
+    ));
+}
+
+fn display_name(entity: Entity, name: Option<&Name>) -> String {
+    match name {
+        Some(name) => format!("{name} ({entity:?})"),
+        None => format!("{entity:?}"),
+    }
+}
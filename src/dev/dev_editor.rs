@@ -1,4 +1,10 @@
-use crate::{player_control::camera::ForceCursorGrabMode, GameState};
+use crate::{
+    dev::scene_editor::{save_scene_patch, EditedTransforms},
+    level_instantiation::validation::SpawnReport,
+    player_control::camera::ForceCursorGrabMode,
+    time_scale::TimeScale,
+    GameState,
+};
 use anyhow::{Context, Result};
 use bevy::{prelude::*, window::CursorGrabMode};
 use bevy_editor_pls::{
@@ -27,7 +33,7 @@ impl EditorWindow for DevEditorWindow {
     const NAME: &'static str = "Foxtrot Dev";
     const DEFAULT_SIZE: (f32, f32) = (200., 150.);
     fn ui(
-        _world: &mut World,
+        world: &mut World,
         mut cx: bevy_editor_pls::editor_window::EditorWindowContext,
         ui: &mut egui::Ui,
     ) {
@@ -39,6 +45,30 @@ impl EditorWindow for DevEditorWindow {
         ui.heading("Debug Rendering");
         ui.checkbox(&mut state.collider_render_enabled, "Colliders");
         ui.checkbox(&mut state.navmesh_render_enabled, "Navmeshes");
+        ui.heading("Time");
+        let mut time_scale = world.resource_mut::<TimeScale>();
+        ui.add(egui::Slider::new(&mut time_scale.base, 0.05..=4.0).text("Time scale"));
+        let problems = world.resource::<SpawnReport>().problems();
+        ui.heading(format!("Spawn Problems ({})", problems.len()));
+        egui::ScrollArea::vertical()
+            .max_height(200.)
+            .show(ui, |ui| {
+                for problem in problems {
+                    ui.label(problem.to_string());
+                }
+            });
+        ui.heading("Scene Patch");
+        let edited = world.resource::<EditedTransforms>().0.len();
+        ui.label(format!("{edited} moved objects"));
+        if ui
+            .add_enabled(edited > 0, egui::Button::new("Save to level"))
+            .clicked()
+        {
+            match save_scene_patch(world) {
+                Ok(path) => info!("Saved scene patch to {}", path.display()),
+                Err(error) => error!("{error:?}"),
+            }
+        }
     }
 }
 
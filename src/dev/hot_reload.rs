@@ -0,0 +1,64 @@
+use crate::{
+    file_system_interaction::game_state_serialization::LevelReloadRequest,
+    level_instantiation::levels::CurrentLevel, player_control::camera::IngameCamera, GameState,
+};
+use bevy::{gltf::Gltf, prelude::*};
+
+/// Respawns the level whenever its GLTF file changes on disk, so that changes made in Blender show up in the running game.
+/// The player keeps their state and position through a [`LevelReloadRequest`] and the camera keeps looking where it did.
+pub(crate) fn hot_reload_plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        reload_modified_level.run_if(in_state(GameState::Playing)),
+    )
+    .add_systems(
+        Update,
+        restore_camera
+            .run_if(in_state(GameState::Playing).and_then(resource_exists::<PreservedCamera>())),
+    );
+}
+
+/// The camera from before the reload, waiting for the new one to spawn.
+#[derive(Debug, Clone, PartialEq, Resource)]
+struct PreservedCamera {
+    transform: Transform,
+    camera: IngameCamera,
+}
+
+fn reload_modified_level(
+    mut commands: Commands,
+    mut gltf_events: EventReader<AssetEvent<Gltf>>,
+    current_level: Res<CurrentLevel>,
+    cameras: Query<(&Transform, &IngameCamera)>,
+    mut reload_requests: EventWriter<LevelReloadRequest>,
+) {
+    let modified = gltf_events.read().any(
+        |event| matches!(event, AssetEvent::Modified { id } if *id == current_level.gltf.id()),
+    );
+    if !modified {
+        return;
+    }
+    info!(
+        "Level \"{}\" changed on disk, reloading",
+        current_level.name
+    );
+    if let Some((transform, camera)) = cameras.iter().next() {
+        commands.insert_resource(PreservedCamera {
+            transform: *transform,
+            camera: camera.clone(),
+        });
+    }
+    reload_requests.send(LevelReloadRequest);
+}
+
+fn restore_camera(
+    mut commands: Commands,
+    preserved: Res<PreservedCamera>,
+    mut cameras: Query<(&mut Transform, &mut IngameCamera), Added<IngameCamera>>,
+) {
+    for (mut transform, mut camera) in cameras.iter_mut() {
+        *transform = preserved.transform;
+        *camera = preserved.camera.clone();
+        commands.remove_resource::<PreservedCamera>();
+    }
+}
@@ -0,0 +1,77 @@
+use crate::{
+    level_instantiation::{
+        levels::{CurrentLevel, LevelRegistry},
+        patches::ScenePatch,
+    },
+    GameState,
+};
+use anyhow::{Context, Result};
+use bevy::{prelude::*, utils::HashMap};
+use bevy_editor_pls::{default_windows::hierarchy::HierarchyWindow, editor::Editor};
+use std::{fs, path::PathBuf};
+
+/// Remembers which objects were moved with the editor's gizmos so that they can be saved into the level's [`ScenePatch`].
+/// Entities are inspected and moved through the editor's hierarchy and inspector windows; saving is done in the dev window.
+pub(crate) fn scene_editor_plugin(app: &mut App) {
+    app.init_resource::<EditedTransforms>()
+        .add_systems(
+            Update,
+            record_edited_transforms.run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(OnExit(GameState::Playing), discard_edits);
+}
+
+/// Transforms changed through the editor since the last save, by object name
+#[derive(Debug, Clone, PartialEq, Resource, Default)]
+pub(crate) struct EditedTransforms(pub(crate) HashMap<String, Transform>);
+
+fn record_edited_transforms(
+    editor: Res<Editor>,
+    changed: Query<(&Name, &Transform), Changed<Transform>>,
+    mut edits: ResMut<EditedTransforms>,
+) {
+    if !editor.active() {
+        return;
+    }
+    let Some(hierarchy) = editor.window_state::<HierarchyWindow>() else {
+        return;
+    };
+    // Only the selected entities are moved by the gizmo, everything else may just be simulated
+    for entity in hierarchy.selected.iter() {
+        if let Ok((name, transform)) = changed.get(entity) {
+            edits.0.insert(name.to_string(), *transform);
+        }
+    }
+}
+
+fn discard_edits(mut edits: ResMut<EditedTransforms>) {
+    edits.0.clear();
+}
+
+/// Merges the edited transforms into the current level's patch file and returns where it was written.
+pub(crate) fn save_scene_patch(world: &mut World) -> Result<PathBuf> {
+    let current_level = world.resource::<CurrentLevel>();
+    let definition = world
+        .resource::<LevelRegistry>()
+        .get(&current_level.name)
+        .context("Failed to get the definition of the current level")?;
+    let path = PathBuf::from("assets").join(&definition.patch);
+    let mut patch = world
+        .resource::<Assets<ScenePatch>>()
+        .get(&current_level.patch)
+        .cloned()
+        .unwrap_or_default();
+
+    let edits = &world.resource::<EditedTransforms>().0;
+    patch.transforms.extend(
+        edits
+            .iter()
+            .map(|(name, transform)| (name.clone(), *transform)),
+    );
+    let serialized =
+        ron::ser::to_string_pretty(&patch, default()).context("Failed to serialize scene patch")?;
+    fs::write(&path, serialized)
+        .with_context(|| format!("Failed to write scene patch to {}", path.display()))?;
+    world.resource_mut::<EditedTransforms>().0.clear();
+    Ok(path)
+}
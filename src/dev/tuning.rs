@@ -0,0 +1,82 @@
+use crate::file_system_interaction::config::GameConfig;
+use anyhow::{Context, Result};
+use bevy::{prelude::*, reflect::ReflectMut};
+use bevy_editor_pls::{editor_window::EditorWindow, AddEditorWindow};
+use bevy_egui::egui;
+use std::fs;
+
+/// Where the [`GameConfig`] is loaded from. Saving overwrites it, which in turn hot-reloads the config.
+const CONFIG_PATH: &str = "assets/config/config.game.toml";
+
+/// Adds an editor window in which every number of the [`GameConfig`] can be tweaked while playing,
+/// e.g. movement speeds, camera smoothing and combat numbers, and saved back to the config file.
+pub(crate) fn tuning_plugin(app: &mut App) {
+    app.add_editor_window::<TuningWindow>();
+}
+
+pub(crate) struct TuningWindow;
+
+impl EditorWindow for TuningWindow {
+    type State = ();
+    const NAME: &'static str = "Tuning";
+    const DEFAULT_SIZE: (f32, f32) = (300., 500.);
+
+    fn ui(
+        world: &mut World,
+        _cx: bevy_editor_pls::editor_window::EditorWindowContext,
+        ui: &mut egui::Ui,
+    ) {
+        let Some(mut config) = world.get_resource_mut::<GameConfig>() else {
+            ui.label("The config has not been loaded yet");
+            return;
+        };
+        if ui.button("Save to config file").clicked() {
+            match save_config(&config) {
+                Ok(()) => info!("Saved config to {CONFIG_PATH}"),
+                Err(error) => error!("{error:?}"),
+            }
+        }
+        ui.separator();
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            // Only notify the systems reading the config when something was actually edited
+            let mut edited = config.bypass_change_detection().clone();
+            if edit_numbers(ui, &mut edited) {
+                *config = edited;
+            }
+        });
+    }
+}
+
+/// Shows a drag value for every `f32` in `value`, with nested structs as collapsible sections.
+/// Returns whether any number was changed.
+fn edit_numbers(ui: &mut egui::Ui, value: &mut dyn Reflect) -> bool {
+    let ReflectMut::Struct(value) = value.reflect_mut() else {
+        return false;
+    };
+    let mut changed = false;
+    for index in 0..value.field_len() {
+        let name = value.name_at(index).unwrap_or_default().to_string();
+        let Some(field) = value.field_at_mut(index) else {
+            continue;
+        };
+        if let Some(number) = field.downcast_mut::<f32>() {
+            let speed = (number.abs() * 0.01).max(1e-4);
+            ui.horizontal(|ui| {
+                ui.label(&name);
+                changed |= ui.add(egui::DragValue::new(number).speed(speed)).changed();
+            });
+        } else {
+            egui::CollapsingHeader::new(&name).show(ui, |ui| {
+                changed |= edit_numbers(ui, field);
+            });
+        }
+    }
+    changed
+}
+
+fn save_config(config: &GameConfig) -> Result<()> {
+    let serialized = toml::to_string_pretty(config).context("Failed to serialize config")?;
+    fs::write(CONFIG_PATH, serialized)
+        .with_context(|| format!("Failed to write config to {CONFIG_PATH}"))?;
+    Ok(())
+}
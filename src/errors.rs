@@ -0,0 +1,164 @@
+use crate::{
+    level_instantiation::levels::CurrentLevel, player_control::player_embodiment::Player,
+    theme::UiTheme, GameState,
+};
+use anyhow::Result;
+use bevy::{app::AppExit, ecs::entity::Entities, prelude::*};
+use bevy_egui::{egui, EguiContexts};
+use std::path::PathBuf;
+
+/// Handles errors the game cannot continue from, like a level whose assets are broken.
+/// Instead of panicking, systems send a [`GameError`], usually by piping their result into [`report_error`].
+/// The game then writes a crash log with the error and a summary of the world into the user's data directory
+/// and shows an error screen in [`GameState::Error`] that leads back to the main menu.
+/// Panics that happen anyway are written to a crash log as well.
+/// On the web, there is no data directory, so errors only go to the browser console.
+pub(crate) fn errors_plugin(app: &mut App) {
+    install_panic_hook();
+    app.add_event::<GameError>()
+        .init_resource::<CrashReport>()
+        .add_systems(Update, handle_game_errors)
+        .add_systems(Update, show_error_screen.run_if(in_state(GameState::Error)));
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub(crate) struct GameError {
+    /// The error with all of its causes
+    pub(crate) message: String,
+}
+
+impl From<anyhow::Error> for GameError {
+    fn from(error: anyhow::Error) -> Self {
+        Self {
+            message: format!("{error:#}"),
+        }
+    }
+}
+
+/// Turns the error of a system piped into it into a [`GameError`].
+pub(crate) fn report_error(In(result): In<Result<()>>, mut game_errors: EventWriter<GameError>) {
+    if let Err(error) = result {
+        game_errors.send(error.into());
+    }
+}
+
+/// The error shown on the error screen
+#[derive(Debug, Clone, PartialEq, Eq, Resource, Default)]
+struct CrashReport {
+    message: String,
+    log: Option<PathBuf>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn crash_log_directory() -> PathBuf {
+    dirs::data_dir()
+        .map(|directory| directory.join("foxtrot"))
+        .unwrap_or_default()
+        .join("crash_logs")
+}
+
+/// Writes a crash log and returns its path.
+#[cfg(not(target_arch = "wasm32"))]
+fn write_crash_log(message: &str, summary: &str) -> Result<PathBuf> {
+    use anyhow::Context;
+    use std::fs;
+
+    let timestamp = crate::file_system_interaction::storage::timestamp();
+    let directory = crash_log_directory();
+    fs::create_dir_all(&directory).context("Failed to create crash log directory")?;
+    let path = directory.join(format!("crash_{timestamp}.log"));
+    let log = format!(
+        "Foxtrot {} crashed at {timestamp}\n\nError: {message}\n\n{summary}\n",
+        env!("CARGO_PKG_VERSION")
+    );
+    fs::write(&path, log)
+        .with_context(|| format!("Failed to write crash log {}", path.display()))?;
+    Ok(path)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write_crash_log(_message: &str, _summary: &str) -> Result<PathBuf> {
+    anyhow::bail!("Crash logs cannot be written on the web")
+}
+
+fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if let Err(error) = write_crash_log(&info.to_string(), "The game panicked") {
+            eprintln!("{error:?}");
+        }
+        default_hook(info);
+    }));
+}
+
+fn handle_game_errors(
+    mut game_errors: EventReader<GameError>,
+    state: Res<State<GameState>>,
+    current_level: Res<CurrentLevel>,
+    players: Query<&GlobalTransform, With<Player>>,
+    entities: &Entities,
+    time: Res<Time<Real>>,
+    mut crash_report: ResMut<CrashReport>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    // Only the first error is reported, the following ones are usually caused by it
+    let Some(error) = game_errors.read().next().cloned() else {
+        return;
+    };
+    game_errors.clear();
+    if state.get() == &GameState::Error {
+        return;
+    }
+    error!("{}", error.message);
+    let player = players
+        .get_single()
+        .map(|transform| format!("{:.2}", transform.translation()))
+        .unwrap_or_else(|_| "none".to_string());
+    let summary = format!(
+        "State: {:?}\nLevel: {}\nPlayer position: {player}\nEntities: {}\nRunning for: {:.1}s",
+        state.get(),
+        current_level.name,
+        entities.len(),
+        time.elapsed_seconds()
+    );
+    let log = write_crash_log(&error.message, &summary)
+        .map_err(|log_error| error!("{log_error:?}"))
+        .ok();
+    *crash_report = CrashReport {
+        message: error.message,
+        log,
+    };
+    next_state.set(GameState::Error);
+}
+
+fn show_error_screen(
+    crash_report: Res<CrashReport>,
+    theme: Res<UiTheme>,
+    mut egui_contexts: EguiContexts,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut app_exit_events: EventWriter<AppExit>,
+) {
+    let spacing = theme.spacing;
+    egui::CentralPanel::default()
+        .frame(theme.backdrop_frame())
+        .show(egui_contexts.ctx_mut(), |ui| {
+            theme.apply_menu_style(ui.style_mut());
+            ui.vertical_centered(|ui| {
+                ui.add_space(2. * spacing.large);
+                ui.heading("Something went wrong");
+                ui.add_space(spacing.medium);
+                ui.label(&crash_report.message);
+                if let Some(log) = &crash_report.log {
+                    ui.add_space(spacing.small);
+                    ui.weak(format!("A crash log was written to {}", log.display()));
+                }
+                ui.add_space(spacing.large);
+                if ui.button("Return to Main Menu").clicked() {
+                    next_state.set(GameState::Menu);
+                }
+                if ui.button("Quit").clicked() {
+                    app_exit_events.send(AppExit);
+                }
+            });
+        });
+}
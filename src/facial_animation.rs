@@ -0,0 +1,277 @@
+use crate::{
+    game_events::DialogEnded,
+    rng::{GameRng, RngStream},
+    world_interaction::dialog::DialogTarget,
+    GameState,
+};
+use bevy::{prelude::*, render::mesh::morph::MorphWeights, utils::HashMap};
+use bevy_yarnspinner::events::PresentLineEvent;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Seconds it takes to close and open the eyes again
+const BLINK_DURATION: f32 = 0.15;
+/// Letters per second that a line is spoken at
+const SPEAKING_RATE: f32 = 14.;
+/// Seconds it takes an expression to fully show or fade out
+const EXPRESSION_BLEND: f32 = 0.25;
+
+/// Drives the morph targets of faces: characters with a [`Face`] blink, flap their mouth while they speak a dialog line
+/// and show expressions. Morph targets are looked up by name in the first mesh of the model that has [`MorphWeights`].
+/// The speaker of a line is the character whose [`DialogTarget::speaker`] is the line's character name.
+/// Lines are not voiced, so the mouth follows an amplitude made up from the letters of the line at reading speed.
+/// A line tagged with `#expression:<name>` shows the morph target `<name>` until the next line.
+/// Gameplay can show expressions as well through a [`SetExpression`].
+pub(crate) fn facial_animation_plugin(app: &mut App) {
+    app.register_type::<Face>()
+        .add_event::<SetExpression>()
+        .add_systems(
+            Update,
+            (
+                resolve_faces,
+                speak_dialog_lines,
+                set_expressions,
+                stop_speaking_after_dialog,
+            )
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            PostUpdate,
+            animate_faces
+                .after(bevy::animation::animation_player)
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+/// Names of the morph targets of a character's face and how often it blinks.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Face {
+    pub(crate) blink: String,
+    pub(crate) mouth_open: String,
+    /// Shortest and longest seconds between blinks
+    pub(crate) blink_interval: (f32, f32),
+}
+
+impl Default for Face {
+    fn default() -> Self {
+        Self {
+            blink: "Blink".to_string(),
+            mouth_open: "MouthOpen".to_string(),
+            blink_interval: (2., 6.),
+        }
+    }
+}
+
+/// Shows the expression with the given morph target name on the face of `entity`, or the neutral face if `None`.
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub(crate) struct SetExpression {
+    pub(crate) entity: Entity,
+    pub(crate) expression: Option<String>,
+}
+
+/// What a [`Face`] is currently doing, inserted once the morph targets of its model are found.
+#[derive(Debug, Clone, PartialEq, Component)]
+struct FaceState {
+    /// The entity with the [`MorphWeights`] and the index of every morph target by name
+    morphs: Entity,
+    targets: HashMap<String, usize>,
+    until_blink: f32,
+    /// Seconds into the current blink, `None` while the eyes are open
+    blinking: Option<f32>,
+    /// The line being spoken and the seconds since it started
+    speaking: Option<(Vec<char>, f32)>,
+    mouth: f32,
+    expression: Option<String>,
+    /// How much every expression shows, so that they blend in and out
+    expression_weights: HashMap<String, f32>,
+}
+
+fn resolve_faces(
+    mut commands: Commands,
+    faces: Query<(Entity, &Face), Without<FaceState>>,
+    children: Query<&Children>,
+    morph_weights: Query<&MorphWeights>,
+    meshes: Res<Assets<Mesh>>,
+    mut rng: ResMut<GameRng>,
+) {
+    let rng = rng.stream(RngStream::Faces);
+    for (entity, face) in faces.iter() {
+        let Some((morphs, names)) = children.iter_descendants(entity).find_map(|child| {
+            let mesh = meshes.get(morph_weights.get(child).ok()?.first_mesh()?)?;
+            Some((child, mesh.morph_target_names()?))
+        }) else {
+            continue;
+        };
+        let targets: HashMap<_, _> = names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| (name.clone(), index))
+            .collect();
+        for name in [&face.blink, &face.mouth_open] {
+            if !targets.contains_key(name) {
+                warn!("Face: no morph target named \"{name}\" found on {entity:?}");
+            }
+        }
+        commands.entity(entity).insert(FaceState {
+            morphs,
+            targets,
+            until_blink: next_blink(face, rng),
+            blinking: None,
+            speaking: None,
+            mouth: 0.,
+            expression: None,
+            expression_weights: default(),
+        });
+    }
+}
+
+fn speak_dialog_lines(
+    mut line_events: EventReader<PresentLineEvent>,
+    mut faces: Query<(Entity, &DialogTarget, &mut FaceState)>,
+    mut expressions: EventWriter<SetExpression>,
+) {
+    for event in line_events.read() {
+        let line = &event.line;
+        let expression = line
+            .metadata
+            .iter()
+            .find_map(|tag| tag.strip_prefix("expression:"))
+            .map(str::to_string);
+        for (entity, target, mut state) in faces.iter_mut() {
+            if line.character_name() != Some(target.speaker.as_str()) {
+                // Only the speaker talks, everyone else listens
+                state.speaking = None;
+                continue;
+            }
+            state.speaking = Some((line.text_without_character_name().chars().collect(), 0.));
+            expressions.send(SetExpression {
+                entity,
+                expression: expression.clone(),
+            });
+        }
+    }
+}
+
+fn set_expressions(mut events: EventReader<SetExpression>, mut faces: Query<&mut FaceState>) {
+    for event in events.read() {
+        if let Ok(mut state) = faces.get_mut(event.entity) {
+            state.expression = event.expression.clone();
+        }
+    }
+}
+
+fn stop_speaking_after_dialog(
+    mut dialogs_ended: EventReader<DialogEnded>,
+    mut faces: Query<&mut FaceState>,
+) {
+    if dialogs_ended.read().count() == 0 {
+        return;
+    }
+    for mut state in faces.iter_mut() {
+        state.speaking = None;
+        state.expression = None;
+    }
+}
+
+fn animate_faces(
+    time: Res<Time<Virtual>>,
+    mut faces: Query<(&Face, &mut FaceState)>,
+    mut morph_weights: Query<&mut MorphWeights>,
+    mut rng: ResMut<GameRng>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("animate_faces").entered();
+    let dt = time.delta_seconds();
+    let rng = rng.stream(RngStream::Faces);
+    for (face, mut state) in faces.iter_mut() {
+        let state = state.as_mut();
+        let Ok(mut weights) = morph_weights.get_mut(state.morphs) else {
+            continue;
+        };
+        let weights = weights.weights_mut();
+        let mut set = |name: &str, weight: f32| {
+            if let Some(slot) = state
+                .targets
+                .get(name)
+                .and_then(|&index| weights.get_mut(index))
+            {
+                *slot = weight;
+            }
+        };
+
+        // Closes the eyes during the first half of a blink and opens them during the second
+        state.until_blink -= dt;
+        if state.until_blink <= 0. && state.blinking.is_none() {
+            state.blinking = Some(0.);
+            state.until_blink = next_blink(face, rng);
+        }
+        let blink = match state.blinking.as_mut() {
+            Some(elapsed) => {
+                *elapsed += dt;
+                let progress = (*elapsed / BLINK_DURATION).min(1.);
+                1. - (progress * 2. - 1.).abs()
+            }
+            None => 0.,
+        };
+        if state
+            .blinking
+            .is_some_and(|elapsed| elapsed >= BLINK_DURATION)
+        {
+            state.blinking = None;
+        }
+        set(&face.blink, blink);
+
+        let amplitude = match state.speaking.as_mut() {
+            Some((letters, elapsed)) => {
+                *elapsed += dt;
+                let letter = letters.get((*elapsed * SPEAKING_RATE) as usize).copied();
+                if letter.is_none() {
+                    state.speaking = None;
+                }
+                letter.map_or(0., letter_amplitude)
+            }
+            None => 0.,
+        };
+        // Smoothed so that the mouth does not snap from letter to letter
+        state.mouth += (amplitude - state.mouth) * (dt * SPEAKING_RATE).min(1.);
+        set(&face.mouth_open, state.mouth);
+
+        let step = dt / EXPRESSION_BLEND;
+        if let Some(expression) = &state.expression {
+            state
+                .expression_weights
+                .entry(expression.clone())
+                .or_default();
+        }
+        for (expression, weight) in state.expression_weights.iter_mut() {
+            let target = if state.expression.as_ref() == Some(expression) {
+                1.
+            } else {
+                0.
+            };
+            *weight = if *weight < target {
+                (*weight + step).min(target)
+            } else {
+                (*weight - step).max(target)
+            };
+            set(expression, *weight);
+        }
+        state.expression_weights.retain(|_, weight| *weight > 0.);
+    }
+}
+
+fn next_blink(face: &Face, rng: &mut impl Rng) -> f32 {
+    let (min, max) = face.blink_interval;
+    rng.gen_range(min..=max.max(min))
+}
+
+/// How far the mouth opens for a letter: wide for vowels, a little for other letters and not at all between words
+fn letter_amplitude(letter: char) -> f32 {
+    match letter.to_ascii_lowercase() {
+        'a' | 'e' | 'i' | 'o' | 'u' => 1.,
+        letter if letter.is_alphanumeric() => 0.4,
+        _ => 0.,
+    }
+}
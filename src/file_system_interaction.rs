@@ -1,16 +1,42 @@
-use crate::file_system_interaction::{asset_loading::loading_plugin, audio::internal_audio_plugin};
+use crate::file_system_interaction::{
+    asset_loading::loading_plugin, audio::internal_audio_plugin, capture::capture_plugin,
+    game_state_serialization::game_state_serialization_plugin, mods::mods_plugin,
+    replay::replay_plugin, sound_effects::sound_effects_plugin, storage::storage_plugin,
+    texture_streaming::texture_streaming_plugin,
+};
 use bevy::prelude::*;
 use seldom_fn_plugin::FnPluginExt;
 
 pub(crate) mod asset_loading;
 pub(crate) mod audio;
+pub(crate) mod capture;
 pub(crate) mod config;
+pub(crate) mod game_state_serialization;
+pub(crate) mod mods;
+pub(crate) mod replay;
+pub(crate) mod sound_effects;
+pub(crate) mod storage;
+pub(crate) mod texture_streaming;
 
 /// Handles loading and saving of levels and save states to disk.
 /// Split into the following sub-plugins:
+/// - [`storage_plugin`] provides the storage that saves, replays and settings are written to, which is the browser's on the web.
 /// - [`loading_plugin`] handles loading of assets.els.
 /// - [`internal_audio_plugin`]: Handles audio initialization
+/// - [`sound_effects_plugin`] plays short sounds within limits on how many play at once.
+/// - [`game_state_serialization_plugin`] handles saving and loading of save files.
+/// - [`replay_plugin`] handles recording and playing back replays.
+/// - [`mods_plugin`] enables the mods installed in the `mods` directory.
+/// - [`capture_plugin`] takes screenshots and records clips.
+/// - [`texture_streaming_plugin`] spreads the upload of large level textures over several frames.
 pub(crate) fn file_system_interaction_plugin(app: &mut App) {
-    app.fn_plugin(loading_plugin)
-        .fn_plugin(internal_audio_plugin);
+    app.fn_plugin(storage_plugin)
+        .fn_plugin(loading_plugin)
+        .fn_plugin(internal_audio_plugin)
+        .fn_plugin(sound_effects_plugin)
+        .fn_plugin(game_state_serialization_plugin)
+        .fn_plugin(replay_plugin)
+        .fn_plugin(mods_plugin)
+        .fn_plugin(capture_plugin)
+        .fn_plugin(texture_streaming_plugin);
 }
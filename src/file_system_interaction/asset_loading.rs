@@ -1,6 +1,12 @@
-use crate::{file_system_interaction::config::GameConfig, GameState};
+use crate::{
+    file_system_interaction::config::GameConfig,
+    level_instantiation::levels::CurrentLevel,
+    movement::character_controller::{AnimationEventMarkers, AnimationGraph},
+    theme::UiTheme,
+    GameState,
+};
 use anyhow::Result;
-use bevy::{gltf::Gltf, prelude::*};
+use bevy::{asset::UntypedAssetId, ecs::system::SystemParam, gltf::Gltf, prelude::*};
 use bevy_asset_loader::prelude::*;
 use bevy_common_assets::toml::TomlAssetPlugin;
 use bevy_egui::{egui, egui::ProgressBar, EguiContexts};
@@ -10,17 +16,21 @@ use iyes_progress::{ProgressCounter, ProgressPlugin};
 
 pub(crate) fn loading_plugin(app: &mut App) {
     app.add_plugins(TomlAssetPlugin::<GameConfig>::new(&["game.toml"]))
-        .add_plugins(ProgressPlugin::new(GameState::Loading).continue_to(GameState::Menu))
+        .add_plugins(ProgressPlugin::new(GameState::InitialLoading).continue_to(GameState::Menu))
         .add_loading_state(
-            LoadingState::new(GameState::Loading)
+            LoadingState::new(GameState::InitialLoading)
                 .continue_to_state(GameState::Menu)
                 .load_collection::<AudioAssets>()
                 .load_collection::<GltfAssets>()
                 .load_collection::<TextureAssets>()
                 .load_collection::<GrassAssets>()
-                .load_collection::<ConfigAssets>(),
+                .load_collection::<ConfigAssets>()
+                .load_collection::<AnimationAssets>(),
+        )
+        .add_systems(
+            Update,
+            show_progress.run_if(in_state(GameState::InitialLoading)),
         )
-        .add_systems(Update, show_progress.run_if(in_state(GameState::Loading)))
         .add_systems(Update, update_config);
 }
 
@@ -33,6 +43,7 @@ pub(crate) struct AudioAssets {
     pub(crate) walking: Handle<AudioSource>,
 }
 
+/// The main level file, which also contains the animations shared by all levels.
 #[derive(AssetCollection, Resource, Clone)]
 pub(crate) struct GltfAssets {
     #[asset(path = "scenes/level.glb")]
@@ -56,6 +67,39 @@ pub(crate) struct ConfigAssets {
     pub(crate) game: Handle<GameConfig>,
 }
 
+#[derive(AssetCollection, Resource, Clone)]
+pub(crate) struct AnimationAssets {
+    /// Used by the player and humanoid NPCs
+    #[asset(path = "animations/character.animgraph.ron")]
+    pub(crate) character: Handle<AnimationGraph>,
+    /// Markers on the animations of the level's GLTF
+    #[asset(path = "scenes/level.animevents.ron")]
+    pub(crate) events: Handle<AnimationEventMarkers>,
+}
+
+/// The assets that need to be fully loaded, including their dependencies, before a level can be spawned.
+#[derive(SystemParam)]
+pub(crate) struct LevelAssets<'w> {
+    audio: Res<'w, AudioAssets>,
+    gltf: Res<'w, GltfAssets>,
+    textures: Res<'w, TextureAssets>,
+    grass: Res<'w, GrassAssets>,
+    current_level: Res<'w, CurrentLevel>,
+}
+
+impl LevelAssets<'_> {
+    pub(crate) fn ids(&self) -> [UntypedAssetId; 6] {
+        [
+            self.audio.walking.id().untyped(),
+            self.gltf.level.id().untyped(),
+            self.current_level.gltf.id().untyped(),
+            self.current_level.patch.id().untyped(),
+            self.textures.glowy_interior.id().untyped(),
+            self.grass.density_map.id().untyped(),
+        ]
+    }
+}
+
 fn show_progress(
     progress: Option<Res<ProgressCounter>>,
     mut egui_contexts: EguiContexts,
@@ -64,29 +108,33 @@ fn show_progress(
     gltf_assets: Option<Res<GltfAssets>>,
     texture_assets: Option<Res<TextureAssets>>,
     config_assets: Option<Res<ConfigAssets>>,
+    theme: Res<UiTheme>,
 ) {
     if let Some(progress) = progress.map(|counter| counter.progress()) {
         if progress.done > *last_done {
             *last_done = progress.done;
         }
 
-        egui::CentralPanel::default().show(egui_contexts.ctx_mut(), |ui| {
-            ui.vertical_centered(|ui| {
-                ui.add_space(100.0);
-                ui.heading("Loading");
-                ui.label("Loading assets...");
-                ui.add(
-                    ProgressBar::new(progress.done as f32 / progress.total as f32).animate(true),
-                );
-                ui.add_space(100.0);
-                ui.add_enabled_ui(false, |ui| {
-                    ui.checkbox(&mut audio_assets.is_some(), "Audio");
-                    ui.checkbox(&mut gltf_assets.is_some(), "Models");
-                    ui.checkbox(&mut texture_assets.is_some(), "Textures");
-                    ui.checkbox(&mut config_assets.is_some(), "Config");
+        egui::CentralPanel::default()
+            .frame(theme.backdrop_frame())
+            .show(egui_contexts.ctx_mut(), |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.add_space(2. * theme.spacing.large);
+                    ui.heading("Loading");
+                    ui.label("Loading assets...");
+                    ui.add(
+                        ProgressBar::new(progress.done as f32 / progress.total as f32)
+                            .animate(true),
+                    );
+                    ui.add_space(2. * theme.spacing.large);
+                    ui.add_enabled_ui(false, |ui| {
+                        ui.checkbox(&mut audio_assets.is_some(), "Audio");
+                        ui.checkbox(&mut gltf_assets.is_some(), "Models");
+                        ui.checkbox(&mut texture_assets.is_some(), "Textures");
+                        ui.checkbox(&mut config_assets.is_some(), "Config");
+                    });
                 });
             });
-        });
     }
 }
 
@@ -5,7 +5,7 @@ use bevy_kira_audio::prelude::{Audio, *};
 /// Handles initialization of all sounds.
 pub(crate) fn internal_audio_plugin(app: &mut App) {
     app.add_plugins(AudioPlugin)
-        .add_systems(OnExit(GameState::Loading), init_audio);
+        .add_systems(OnExit(GameState::InitialLoading), init_audio);
 }
 
 #[derive(Debug, Clone, Resource)]
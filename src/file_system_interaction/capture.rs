@@ -0,0 +1,159 @@
+use crate::file_system_interaction::{config::GameConfig, storage::timestamp};
+use anyhow::{Context, Result};
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::tasks::IoTaskPool;
+use bevy::{prelude::*, render::view::screenshot::ScreenshotManager, window::PrimaryWindow};
+use bevy_mod_sysfail::*;
+#[cfg(not(target_arch = "wasm32"))]
+use image::DynamicImage;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+const SCREENSHOT_KEY: KeyCode = KeyCode::F12;
+const RECORD_KEY: KeyCode = KeyCode::F11;
+
+/// Captures what is on screen for sharing. Pressing F12 writes a screenshot named after the current time,
+/// on the web it is downloaded instead. Holding F11 records a clip, and releasing it writes the last seconds
+/// of the recording as numbered PNG frames into a directory of their own, which can be turned into a GIF
+/// or video with e.g. `ffmpeg -framerate 15 -i frame_%04d.png clip.gif`.
+/// Clips are only recorded on desktop. Where captures go and how clips are recorded is set in the `capture` section
+/// of the [`GameConfig`]. Captures work in every state, including the menus.
+pub(crate) fn capture_plugin(app: &mut App) {
+    app.add_systems(Update, take_screenshot);
+    #[cfg(not(target_arch = "wasm32"))]
+    app.init_resource::<ClipBuffer>()
+        .add_systems(Update, (record_clip, save_clip).chain());
+}
+
+/// The frames recorded while the record key is held, with the real time in seconds they were taken at.
+/// Frames are added from the render world once they are ready, so they are behind a mutex.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Resource, Default)]
+struct ClipBuffer {
+    frames: Arc<Mutex<VecDeque<(f32, DynamicImage)>>>,
+    last_request: Option<f32>,
+}
+
+#[sysfail(log(level = "error"))]
+fn take_screenshot(
+    keys: Res<Input<KeyCode>>,
+    windows: Query<Entity, With<PrimaryWindow>>,
+    mut screenshot_manager: ResMut<ScreenshotManager>,
+    config: Option<Res<GameConfig>>,
+) -> Result<()> {
+    if !keys.just_pressed(SCREENSHOT_KEY) {
+        return Ok(());
+    }
+    let Some(window) = windows.iter().next() else {
+        return Ok(());
+    };
+    let directory = config.map_or_else(
+        || "captures".to_string(),
+        |config| config.capture.directory.clone(),
+    );
+    #[cfg(not(target_arch = "wasm32"))]
+    std::fs::create_dir_all(&directory).with_context(|| format!("Failed to create {directory}"))?;
+    let path = format!("{directory}/screenshot_{}.png", timestamp());
+    screenshot_manager
+        .save_screenshot_to_disk(window, &path)
+        .with_context(|| format!("Failed to take screenshot {path}"))?;
+    info!("Saved screenshot to {path}");
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn record_clip(
+    time: Res<Time<Real>>,
+    keys: Res<Input<KeyCode>>,
+    windows: Query<Entity, With<PrimaryWindow>>,
+    mut screenshot_manager: ResMut<ScreenshotManager>,
+    config: Option<Res<GameConfig>>,
+    mut buffer: ResMut<ClipBuffer>,
+) {
+    let (Some(config), Some(window)) = (config, windows.iter().next()) else {
+        return;
+    };
+    if !keys.pressed(RECORD_KEY) {
+        return;
+    }
+    let now = time.elapsed_seconds();
+    let interval = 1. / config.capture.clip_fps.max(1.);
+    if buffer
+        .last_request
+        .is_some_and(|last_request| now - last_request < interval)
+    {
+        return;
+    }
+    let frames = buffer.frames.clone();
+    let scale = config.capture.clip_scale.clamp(0.1, 1.);
+    let clip_seconds = config.capture.clip_seconds;
+    let requested = screenshot_manager.take_screenshot(window, move |image| {
+        let Ok(image) = image.try_into_dynamic() else {
+            return;
+        };
+        let width = (image.width() as f32 * scale) as u32;
+        let height = (image.height() as f32 * scale) as u32;
+        let frame = image.thumbnail(width, height);
+        let Ok(mut frames) = frames.lock() else {
+            return;
+        };
+        frames.push_back((now, frame));
+        while frames
+            .front()
+            .is_some_and(|(taken, _)| now - taken > clip_seconds)
+        {
+            frames.pop_front();
+        }
+    });
+    // Fails if another capture already claimed the window this frame, in which case the next frame tries again
+    if requested.is_ok() {
+        buffer.last_request = Some(now);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_clip(
+    keys: Res<Input<KeyCode>>,
+    config: Option<Res<GameConfig>>,
+    mut buffer: ResMut<ClipBuffer>,
+) {
+    if !keys.just_released(RECORD_KEY) {
+        return;
+    }
+    buffer.last_request = None;
+    let Some(config) = config else {
+        return;
+    };
+    let frames: Vec<_> = match buffer.frames.lock() {
+        Ok(mut frames) => frames.drain(..).map(|(_, frame)| frame).collect(),
+        Err(_) => return,
+    };
+    if frames.is_empty() {
+        return;
+    }
+    let directory = format!("{}/clip_{}", config.capture.directory, timestamp());
+    info!("Saving {} frames to {directory}", frames.len());
+    // Encoding takes a while, so it should not hold up the game
+    IoTaskPool::get()
+        .spawn(async move {
+            if let Err(error) = write_frames(&directory, &frames) {
+                error!("Failed to save clip: {error:#}");
+            }
+        })
+        .detach();
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_frames(directory: &str, frames: &[DynamicImage]) -> Result<()> {
+    std::fs::create_dir_all(directory).with_context(|| format!("Failed to create {directory}"))?;
+    for (index, frame) in frames.iter().enumerate() {
+        let path = format!("{directory}/frame_{:04}.png", index + 1);
+        frame
+            .save(&path)
+            .with_context(|| format!("Failed to write {path}"))?;
+    }
+    Ok(())
+}
@@ -1,4 +1,5 @@
-use bevy::prelude::*;
+use crate::file_system_interaction::sound_effects::SoundCategory;
+use bevy::{prelude::*, utils::HashMap};
 
 use serde::{Deserialize, Serialize};
 
@@ -6,7 +7,24 @@ use serde::{Deserialize, Serialize};
 #[reflect(Serialize, Deserialize, Resource)]
 pub(crate) struct GameConfig {
     pub(crate) camera: Camera,
+    pub(crate) movement: Movement,
+    pub(crate) combat: Combat,
     pub(crate) player: PlayerEffects,
+    pub(crate) map: Map,
+    pub(crate) progression: Leveling,
+    pub(crate) capture: Capture,
+    pub(crate) lights: Lights,
+    pub(crate) texture_streaming: TextureStreaming,
+    pub(crate) sound_effects: SoundEffects,
+    pub(crate) terrain: Terrain,
+    /// The sets of collectibles by the ID used in their `[collectible:<set>:<id>]` markers
+    #[serde(default)]
+    pub(crate) collectibles: HashMap<String, CollectibleSet>,
+    /// Everything that can be bought and sold, by the key used in the hotbar
+    #[serde(default)]
+    pub(crate) items: HashMap<String, ItemDefinition>,
+    #[serde(default)]
+    pub(crate) shops: HashMap<String, ShopDefinition>,
 }
 
 #[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize, Default)]
@@ -15,6 +33,8 @@ pub(crate) struct Camera {
     pub(crate) fixed_angle: FixedAngle,
     pub(crate) first_person: FirstPerson,
     pub(crate) third_person: ThirdPerson,
+    pub(crate) chase: Chase,
+    pub(crate) aim: Aim,
     pub(crate) mouse_sensitivity_x: f32,
     pub(crate) mouse_sensitivity_y: f32,
 }
@@ -58,8 +78,199 @@ pub(crate) struct ThirdPerson {
     pub(crate) zoom_out_smoothing: f32,
 }
 
+/// The camera used while driving, which swings behind the vehicle on its own.
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Serialize, Deserialize)]
+pub(crate) struct Chase {
+    pub(crate) translation_smoothing: f32,
+    pub(crate) rotation_smoothing: f32,
+    pub(crate) tracking_smoothing: f32,
+    pub(crate) min_distance: f32,
+    pub(crate) max_distance: f32,
+    pub(crate) pitch: f32,
+    /// Fraction of the remaining angle to the vehicle's back covered per second
+    pub(crate) follow_speed: f32,
+}
+
+/// The over-the-shoulder third person camera used while aiming.
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Serialize, Deserialize)]
+pub(crate) struct Aim {
+    /// Distance to the player, if the camera is further away
+    pub(crate) distance: f32,
+    /// How far the camera moves to the right
+    pub(crate) shoulder_offset: f32,
+    /// Fraction of the way into or out of aiming covered per second
+    pub(crate) blend_speed: f32,
+}
+
+/// How the player moves on foot
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Serialize, Deserialize)]
+pub(crate) struct Movement {
+    pub(crate) walk_speed: f32,
+    pub(crate) sprint_multiplier: f32,
+    pub(crate) crouch_multiplier: f32,
+    /// Speed multiplier while aiming
+    pub(crate) aim_multiplier: f32,
+    pub(crate) jump_height: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Serialize, Deserialize)]
+pub(crate) struct Combat {
+    /// How long a knockback pushes its target around
+    pub(crate) knockback_duration: f32,
+    /// Hits dealing at least this much damage briefly slow down the whole game
+    pub(crate) heavy_hit_damage: f32,
+    /// Speed of the balls the player throws
+    pub(crate) throw_speed: f32,
+}
+
 #[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize, Default)]
 #[reflect(Serialize, Deserialize)]
 pub(crate) struct PlayerEffects {
     pub(crate) sprint_effect_speed_threshold: f32,
 }
+
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Serialize, Deserialize)]
+pub(crate) struct Map {
+    /// Width of the area shown on the minimap in world units
+    pub(crate) minimap_size: f32,
+    /// Width of the area shown on the world map in world units, centered on the origin
+    pub(crate) world_size: f32,
+    pub(crate) discovery_radius: f32,
+    pub(crate) fog_cell_size: f32,
+}
+
+/// Where screenshots and clips go and how clips are recorded
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Serialize, Deserialize)]
+pub(crate) struct Capture {
+    /// Relative to the working directory
+    pub(crate) directory: String,
+    /// A clip holds at most this many of the last seconds before the record key was released
+    pub(crate) clip_seconds: f32,
+    pub(crate) clip_fps: f32,
+    /// Clip frames are scaled down by this factor to keep the buffer small
+    pub(crate) clip_scale: f32,
+}
+
+/// Scales the lights imported from the level's GLTF
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Serialize, Deserialize)]
+pub(crate) struct Lights {
+    /// Multiplies the lumens of point and spot lights
+    pub(crate) intensity_scale: f32,
+    /// Multiplies the lux of suns
+    pub(crate) illuminance_scale: f32,
+    /// Whether point and spot lights cast shadows. Suns do when marked with the `Sun` component.
+    pub(crate) shadows: bool,
+}
+
+/// Limits on how many sound effects play at once, see the sound effects plugin
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Serialize, Deserialize)]
+pub(crate) struct SoundEffects {
+    /// Paths of the sounds loaded before they are first played
+    pub(crate) preload: Vec<String>,
+    /// How often the same sound plays at once
+    pub(crate) max_instances: usize,
+    /// How many sounds of characters play at once
+    pub(crate) max_character_voices: usize,
+    /// How many sounds of the level play at once
+    pub(crate) max_world_voices: usize,
+}
+
+impl SoundEffects {
+    pub(crate) fn max_voices(&self, category: SoundCategory) -> usize {
+        match category {
+            SoundCategory::Character => self.max_character_voices,
+            SoundCategory::World => self.max_world_voices,
+        }
+    }
+}
+
+/// The textures of heightmap terrain, see the terrain plugin
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Serialize, Deserialize)]
+pub(crate) struct Terrain {
+    /// Asset paths of the textures blended by the red, green and blue channels of a terrain's splat map
+    pub(crate) layers: [String; 3],
+    /// Repetitions of the textures per meter
+    pub(crate) tiling: f32,
+}
+
+/// How large level textures are streamed in, see the texture streaming plugin
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Serialize, Deserialize)]
+pub(crate) struct TextureStreaming {
+    pub(crate) enabled: bool,
+    /// Textures whose larger side has fewer pixels are left alone
+    pub(crate) min_size: u32,
+    /// Size of the larger side of the low resolution placeholders
+    pub(crate) placeholder_size: u32,
+    /// Full resolution textures are evicted, farthest first, when they take up more than this
+    pub(crate) budget_mb: f32,
+    pub(crate) upload_mb_per_frame: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Serialize, Deserialize)]
+pub(crate) struct CollectibleSet {
+    pub(crate) name: String,
+    /// How many collectibles of this set exist across all levels
+    pub(crate) total: usize,
+    /// Unlocked when the last collectible of the set is found
+    #[serde(default)]
+    pub(crate) achievement: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Serialize, Deserialize)]
+pub(crate) struct ItemDefinition {
+    pub(crate) name: String,
+    /// What the item costs in shops, in coins
+    pub(crate) price: u32,
+    /// Lets the player throw the item from the hotbar
+    #[serde(default)]
+    pub(crate) throwable: Option<Throwable>,
+}
+
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Serialize, Deserialize)]
+pub(crate) struct Throwable {
+    /// How often the item bounces off what it hits before it comes to rest
+    pub(crate) bounces: u32,
+    pub(crate) radius: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Serialize, Deserialize)]
+pub(crate) struct ShopDefinition {
+    pub(crate) name: String,
+    /// Keys of the items the shop sells
+    pub(crate) stock: Vec<String>,
+}
+
+/// How the player gains levels
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Serialize, Deserialize)]
+pub(crate) struct Leveling {
+    /// Experience needed to reach level 2
+    pub(crate) base_experience: u32,
+    /// Factor by which the experience needed grows with every level
+    pub(crate) experience_growth: f32,
+    pub(crate) skill_points_per_level: u32,
+    pub(crate) quest_experience: u32,
+    pub(crate) collectible_experience: u32,
+}
+
+impl Leveling {
+    /// Experience needed to go from the level before `level` to `level`
+    pub(crate) fn experience_for_level(&self, level: u32) -> u32 {
+        let exponent = level.saturating_sub(2) as i32;
+        (self.base_experience as f32 * self.experience_growth.powi(exponent)).round() as u32
+    }
+}
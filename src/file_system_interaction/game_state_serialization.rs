@@ -0,0 +1,349 @@
+use crate::{
+    character_customization::CharacterAppearance,
+    combat::{health::Health, respawn::DeathCount, status_effects::StatusEffects},
+    file_system_interaction::storage::{self, GameStorage},
+    hud::{objectives::ActiveQuests, tutorial::SeenHints, Hotbar},
+    level_instantiation::{
+        levels::{CurrentLevel, LevelTransitionEvent},
+        streaming::{record_loaded_chunks, ChunkStates},
+    },
+    player_control::{player_embodiment::Player, progression::Progression},
+    time_of_day::TimeOfDay,
+    world_interaction::{collectibles::Collection, shop::Coins},
+    GameState,
+};
+use anyhow::{Context, Result};
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_mod_sysfail::*;
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+
+pub(crate) const SAVE_DIRECTORY: &str = "saves";
+const SAVE_EXTENSION: &str = "sav.json";
+
+/// Handles writing save files to disk and restoring them.
+/// Saving is requested with [`GameSaveRequest`] while playing.
+/// A [`GameLoadRequest`] reads a save file, enters the saved level and applies the save once the player has spawned.
+/// The same mechanism carries the player's state over into the next level on a [`LevelTransitionEvent`]
+/// and through respawning the level on a [`LevelReloadRequest`].
+pub(crate) fn game_state_serialization_plugin(app: &mut App) {
+    app.add_event::<GameSaveRequest>()
+        .add_event::<GameLoadRequest>()
+        .add_event::<LevelReloadRequest>()
+        .add_systems(
+            Update,
+            (
+                handle_save_requests.after(record_loaded_chunks),
+                carry_over_player,
+                handle_reload_requests.after(record_loaded_chunks),
+                apply_pending_save.run_if(resource_exists::<PendingSave>()),
+            )
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(Update, handle_load_requests);
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Event, Default)]
+pub(crate) struct GameSaveRequest {
+    /// Name of the save slot. A new slot named after the current time is created if this is `None`.
+    pub(crate) slot: Option<String>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Event)]
+pub(crate) struct GameLoadRequest {
+    pub(crate) slot: String,
+}
+
+/// Despawns and respawns the current level while the player keeps their state and position.
+#[derive(Debug, Clone, Eq, PartialEq, Event, Default)]
+pub(crate) struct LevelReloadRequest;
+
+/// A save file on disk, as listed in the load game menu.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct SaveSlot {
+    pub(crate) name: String,
+    pub(crate) modified: SystemTime,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct SaveFile {
+    /// Saves from before there were multiple levels are in the default level
+    #[serde(default)]
+    level: Option<String>,
+    player: SavedPlayer,
+    /// Modifications to streamed chunks, including the ones that are not loaded right now
+    #[serde(default)]
+    chunks: ChunkStates,
+    /// The tutorial hints that are not shown again
+    #[serde(default)]
+    hints: SeenHints,
+    #[serde(default)]
+    deaths: DeathCount,
+    /// The collectibles the player has found
+    #[serde(default)]
+    collection: Collection,
+    #[serde(default)]
+    coins: Coins,
+    /// The quests the player has started but not completed
+    #[serde(default)]
+    quests: ActiveQuests,
+    #[serde(default)]
+    time_of_day: TimeOfDay,
+}
+
+impl SaveFile {
+    /// Turns a checkpoint into the state the player respawns with after dying.
+    /// The deaths are not rolled back, and the player only gets `health_fraction` of their maximum health.
+    pub(crate) fn for_respawn(mut self, deaths: DeathCount, health_fraction: f32) -> Self {
+        self.deaths = deaths;
+        let health = &mut self.player.health;
+        health.current = health.max * health_fraction;
+        self.player.status_effects = default();
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SavedPlayer {
+    transform: Transform,
+    health: Health,
+    #[serde(default)]
+    status_effects: StatusEffects,
+    appearance: CharacterAppearance,
+    #[serde(default)]
+    hotbar: Hotbar,
+    #[serde(default)]
+    progression: Progression,
+}
+
+/// A loaded save waiting for the level to spawn the player.
+#[derive(Debug, Clone, PartialEq, Resource)]
+struct PendingSave {
+    save: SaveFile,
+    /// Whether the player stays where the level placed them instead of moving to the saved position
+    keep_position: bool,
+}
+
+/// Lists all save slots, most recently modified first.
+pub(crate) fn list_save_slots(storage: &GameStorage) -> Result<Vec<SaveSlot>> {
+    let mut slots: Vec<_> = storage
+        .list(SAVE_DIRECTORY)
+        .context("Failed to read save directory")?
+        .into_iter()
+        .filter_map(|entry| {
+            let name = entry.name.strip_suffix(&format!(".{SAVE_EXTENSION}"))?;
+            Some(SaveSlot {
+                name: name.to_string(),
+                modified: entry.modified,
+            })
+        })
+        .collect();
+    slots.sort_by(|a, b| b.modified.cmp(&a.modified));
+    Ok(slots)
+}
+
+fn save_key(slot: &str) -> String {
+    format!("{SAVE_DIRECTORY}/{slot}.{SAVE_EXTENSION}")
+}
+
+pub(crate) type SavedPlayerQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Transform,
+        &'static Health,
+        &'static StatusEffects,
+        &'static CharacterAppearance,
+        &'static Hotbar,
+        &'static Progression,
+    ),
+    With<Player>,
+>;
+
+/// Everything that is written into a save file.
+#[derive(SystemParam)]
+pub(crate) struct SavedState<'w, 's> {
+    pub(crate) players: SavedPlayerQuery<'w, 's>,
+    current_level: Res<'w, CurrentLevel>,
+    chunk_states: Res<'w, ChunkStates>,
+    seen_hints: Res<'w, SeenHints>,
+    deaths: Res<'w, DeathCount>,
+    collection: Res<'w, Collection>,
+    coins: Res<'w, Coins>,
+    active_quests: Res<'w, ActiveQuests>,
+    time_of_day: Res<'w, TimeOfDay>,
+}
+
+impl SavedState<'_, '_> {
+    /// Captures the current state into a save file.
+    pub(crate) fn snapshot(&self) -> Result<SaveFile> {
+        let (transform, health, status_effects, appearance, hotbar, progression) = self
+            .players
+            .get_single()
+            .context("Failed to get player for saving")?;
+        Ok(SaveFile {
+            level: Some(self.current_level.name.clone()),
+            player: SavedPlayer {
+                transform: *transform,
+                health: health.clone(),
+                status_effects: status_effects.clone(),
+                appearance: appearance.clone(),
+                hotbar: hotbar.clone(),
+                progression: progression.clone(),
+            },
+            chunks: self.chunk_states.clone(),
+            hints: self.seen_hints.clone(),
+            deaths: *self.deaths,
+            collection: self.collection.clone(),
+            coins: *self.coins,
+            quests: self.active_quests.clone(),
+            time_of_day: *self.time_of_day,
+        })
+    }
+}
+
+#[sysfail(log(level = "error"))]
+fn handle_save_requests(
+    mut save_requests: EventReader<GameSaveRequest>,
+    storage: Res<GameStorage>,
+    saved_state: SavedState,
+) -> Result<()> {
+    for request in save_requests.read() {
+        let save = saved_state.snapshot()?;
+        let slot = match &request.slot {
+            Some(slot) => slot.clone(),
+            None => format!("save_{}", storage::timestamp()),
+        };
+        let serialized = serde_json::to_string_pretty(&save)?;
+        let key = save_key(&slot);
+        storage
+            .write(&key, serialized.as_bytes())
+            .context("Failed to write save file")?;
+        info!("Saved game to {key}");
+    }
+    Ok(())
+}
+
+#[sysfail(log(level = "error"))]
+fn handle_load_requests(
+    mut commands: Commands,
+    mut load_requests: EventReader<GameLoadRequest>,
+    storage: Res<GameStorage>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut next_state: ResMut<NextState<GameState>>,
+) -> Result<()> {
+    for request in load_requests.read() {
+        let key = save_key(&request.slot);
+        let serialized = storage
+            .read_string(&key)
+            .context("Failed to read save file")?
+            .with_context(|| format!("Save file {key} does not exist"))?;
+        let save: SaveFile = serde_json::from_str(&serialized)
+            .with_context(|| format!("Failed to parse save file {key}"))?;
+        enter_save(&mut commands, save, &mut current_level, &mut next_state);
+    }
+    Ok(())
+}
+
+/// Enters the level of `save` and applies it to the player once they have spawned.
+pub(crate) fn enter_save(
+    commands: &mut Commands,
+    save: SaveFile,
+    current_level: &mut CurrentLevel,
+    next_state: &mut NextState<GameState>,
+) {
+    if let Some(level) = &save.level {
+        current_level.name = level.clone();
+    }
+    current_level.spawn_point = None;
+    commands.insert_resource(PendingSave {
+        save,
+        keep_position: false,
+    });
+    next_state.set(GameState::Loading);
+}
+
+/// Whether a loaded save is still waiting to be applied to the player.
+pub(crate) fn save_pending(pending_save: Option<Res<PendingSave>>) -> bool {
+    pending_save.is_some()
+}
+
+#[sysfail(log(level = "error"))]
+fn carry_over_player(
+    mut commands: Commands,
+    mut transition_events: EventReader<LevelTransitionEvent>,
+    saved_state: SavedState,
+) -> Result<()> {
+    if transition_events.read().last().is_none() {
+        return Ok(());
+    }
+    commands.insert_resource(PendingSave {
+        save: saved_state.snapshot()?,
+        keep_position: true,
+    });
+    Ok(())
+}
+
+#[sysfail(log(level = "error"))]
+fn handle_reload_requests(
+    mut commands: Commands,
+    mut reload_requests: EventReader<LevelReloadRequest>,
+    saved_state: SavedState,
+    mut next_state: ResMut<NextState<GameState>>,
+) -> Result<()> {
+    if reload_requests.read().last().is_none() {
+        return Ok(());
+    }
+    commands.insert_resource(PendingSave {
+        save: saved_state.snapshot()?,
+        keep_position: false,
+    });
+    next_state.set(GameState::Loading);
+    Ok(())
+}
+
+fn apply_pending_save(
+    mut commands: Commands,
+    pending_save: Res<PendingSave>,
+    mut players: Query<
+        (
+            &mut Transform,
+            &mut Health,
+            &mut StatusEffects,
+            &mut CharacterAppearance,
+            &mut Hotbar,
+            &mut Progression,
+        ),
+        With<Player>,
+    >,
+) {
+    // The components are inserted by the player spawner, so this waits until that has happened
+    let Ok((
+        mut transform,
+        mut health,
+        mut status_effects,
+        mut appearance,
+        mut hotbar,
+        mut progression,
+    )) = players.get_single_mut()
+    else {
+        return;
+    };
+    let player = &pending_save.save.player;
+    if !pending_save.keep_position {
+        *transform = player.transform;
+    }
+    *health = player.health.clone();
+    *status_effects = player.status_effects.clone();
+    *appearance = player.appearance.clone();
+    *hotbar = player.hotbar.clone();
+    *progression = player.progression.clone();
+    commands.insert_resource(pending_save.save.chunks.clone());
+    commands.insert_resource(pending_save.save.hints.clone());
+    commands.insert_resource(pending_save.save.deaths);
+    commands.insert_resource(pending_save.save.collection.clone());
+    commands.insert_resource(pending_save.save.coins);
+    commands.insert_resource(pending_save.save.quests.clone());
+    commands.insert_resource(pending_save.save.time_of_day);
+    commands.remove_resource::<PendingSave>();
+}
@@ -0,0 +1,346 @@
+use crate::menu::{load_settings, Settings};
+use anyhow::{Context, Result};
+use bevy::{
+    asset::io::{
+        AssetReader, AssetReaderError, AssetSource, AssetSourceId, PathStream, Reader, VecReader,
+    },
+    prelude::*,
+    tasks::futures_lite::{stream, StreamExt},
+    utils::BoxedFuture,
+};
+use bevy_egui::egui;
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, PoisonError, RwLock},
+    time::Duration,
+};
+
+const ASSET_DIRECTORY: &str = "assets";
+/// Next to the game's own `assets` directory
+const MODS_DIRECTORY: &str = "mods";
+const MANIFEST_FILE: &str = "mod.toml";
+
+/// Must be added before the `AssetPlugin`, since it replaces the default asset source with one that
+/// looks into the folders of the enabled mods before falling back to the game's own assets.
+/// The mods are only enabled once [`mods_plugin`] has read the [`ModSettings`].
+pub(crate) fn mod_asset_source_plugin(app: &mut App) {
+    let directories = ModDirectories::default();
+    let reader_directories = directories.clone();
+    let mut base_reader = AssetSource::get_default_reader(ASSET_DIRECTORY.to_string());
+    app.insert_resource(directories).register_asset_source(
+        AssetSourceId::Default,
+        AssetSource::build()
+            .with_reader(move || {
+                Box::new(ModAssetReader {
+                    base: base_reader(),
+                    directories: reader_directories.clone(),
+                })
+            })
+            // Only the base assets are watched, mods are expected to be finished when they are installed
+            .with_watcher(AssetSource::get_default_watcher(
+                ASSET_DIRECTORY.to_string(),
+                Duration::from_millis(300),
+            )),
+    );
+}
+
+/// Discovers the mods in the `mods` directory and enables them in the order of the [`ModSettings`].
+/// A mod is a folder laid out like `assets`, and any file in it is used instead of the game's file at the same path.
+/// This way, a mod can replace the `config/config.game.toml`, prefabs, models or dialogs, and add new ones.
+/// Files that are loaded as a whole folder, like the dialogs in `dialogue`, also pick up files that only exist in mods.
+/// A mod can describe itself in an optional `mod.toml` with a `name`, `version`, `author` and `description`.
+/// Newly installed mods are enabled and loaded last. Changing the load order takes effect after restarting the game.
+pub(crate) fn mods_plugin(app: &mut App) {
+    let mods = match discover_mods() {
+        Ok(mods) => mods,
+        Err(error) => {
+            error!("Failed to discover mods: {error:#}");
+            default()
+        }
+    };
+    app.insert_resource(InstalledMods(mods))
+        .add_systems(Startup, enable_mods.after(load_settings));
+}
+
+/// The mods the player has installed, sorted by folder name.
+#[derive(Debug, Clone, PartialEq, Eq, Resource, Default)]
+pub(crate) struct InstalledMods(pub(crate) Vec<InstalledMod>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct InstalledMod {
+    /// The name of the mod's folder
+    pub(crate) id: String,
+    pub(crate) manifest: ModManifest,
+}
+
+/// The contents of a mod's `mod.toml`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct ModManifest {
+    pub(crate) name: String,
+    pub(crate) version: String,
+    pub(crate) author: String,
+    pub(crate) description: String,
+}
+
+impl ModManifest {
+    /// Everything but the name, e.g. "1.2 by Jane\nAdds a new level"
+    fn summary(&self) -> String {
+        let mut summary = self.version.clone();
+        if !self.author.is_empty() {
+            summary = format!("{summary} by {}", self.author).trim().to_string();
+        }
+        if !self.description.is_empty() {
+            summary = format!("{summary}\n{}", self.description)
+                .trim()
+                .to_string();
+        }
+        summary
+    }
+}
+
+/// Which mods are enabled and in which order, as part of the [`Settings`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct ModSettings {
+    /// Mods further down override the files of the ones above them
+    pub(crate) load_order: Vec<ModEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ModEntry {
+    pub(crate) id: String,
+    pub(crate) enabled: bool,
+}
+
+/// The folders of the enabled mods, in load order. Shared with the asset reader, which runs on other threads.
+#[derive(Debug, Clone, Resource, Default)]
+pub(crate) struct ModDirectories(Arc<RwLock<Vec<PathBuf>>>);
+
+impl ModDirectories {
+    fn get(&self) -> Vec<PathBuf> {
+        self.0
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn set(&self, directories: Vec<PathBuf>) {
+        *self.0.write().unwrap_or_else(PoisonError::into_inner) = directories;
+    }
+}
+
+fn discover_mods() -> Result<Vec<InstalledMod>> {
+    let directory = Path::new(MODS_DIRECTORY);
+    if !directory.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut mods = Vec::new();
+    for entry in std::fs::read_dir(directory).context("Failed to read mods directory")? {
+        let path = entry?.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let id = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .with_context(|| format!("Mod folder {path:?} has an invalid name"))?
+            .to_string();
+        let manifest_path = path.join(MANIFEST_FILE);
+        let mut manifest: ModManifest = if manifest_path.is_file() {
+            let serialized = std::fs::read_to_string(&manifest_path)?;
+            toml::from_str(&serialized)
+                .with_context(|| format!("Failed to parse {manifest_path:?}"))?
+        } else {
+            default()
+        };
+        if manifest.name.is_empty() {
+            manifest.name = id.clone();
+        }
+        mods.push(InstalledMod { id, manifest });
+    }
+    mods.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(mods)
+}
+
+fn enable_mods(
+    mut settings: ResMut<Settings>,
+    installed_mods: Res<InstalledMods>,
+    directories: Res<ModDirectories>,
+) {
+    let load_order = &mut settings.mods.load_order;
+    load_order.retain(|entry| installed_mods.0.iter().any(|m| m.id == entry.id));
+    for installed in &installed_mods.0 {
+        if !load_order.iter().any(|entry| entry.id == installed.id) {
+            load_order.push(ModEntry {
+                id: installed.id.clone(),
+                enabled: true,
+            });
+        }
+    }
+    let enabled: Vec<_> = load_order.iter().filter(|entry| entry.enabled).collect();
+    for entry in &enabled {
+        info!("Enabling mod {}", entry.id);
+    }
+    directories.set(
+        enabled
+            .into_iter()
+            .map(|entry| Path::new(MODS_DIRECTORY).join(&entry.id))
+            .collect(),
+    );
+}
+
+/// Draws the load order with buttons to enable and move each mod.
+pub(crate) fn mods_ui(
+    ui: &mut egui::Ui,
+    settings: &mut ModSettings,
+    installed_mods: &InstalledMods,
+) {
+    ui.label("Mods");
+    if cfg!(target_arch = "wasm32") {
+        ui.label("Mods are not supported on the web.");
+        return;
+    }
+    if settings.load_order.is_empty() {
+        ui.label(format!(
+            "No mods installed. Put them into the {MODS_DIRECTORY} folder next to the game."
+        ));
+        return;
+    }
+    let count = settings.load_order.len();
+    let mut swap = None;
+    for (index, entry) in settings.load_order.iter_mut().enumerate() {
+        let manifest = installed_mods
+            .0
+            .iter()
+            .find(|installed| installed.id == entry.id)
+            .map(|installed| &installed.manifest);
+        let name = manifest.map_or(entry.id.as_str(), |manifest| manifest.name.as_str());
+        ui.horizontal(|ui| {
+            let checkbox = ui.checkbox(&mut entry.enabled, name);
+            if let Some(manifest) = manifest {
+                checkbox.on_hover_text(manifest.summary());
+            }
+            if ui.add_enabled(index > 0, egui::Button::new("Up")).clicked() {
+                swap = Some(index - 1);
+            }
+            if ui
+                .add_enabled(index + 1 < count, egui::Button::new("Down"))
+                .clicked()
+            {
+                swap = Some(index);
+            }
+        });
+    }
+    if let Some(index) = swap {
+        settings.load_order.swap(index, index + 1);
+    }
+    ui.label("Mods further down override the ones above. Changes take effect after a restart.");
+}
+
+struct ModAssetReader {
+    base: Box<dyn AssetReader>,
+    directories: ModDirectories,
+}
+
+impl ModAssetReader {
+    /// The file replacing the asset at `path` from the mod loaded last, if any mod has one
+    fn find_override(&self, path: &Path) -> Option<PathBuf> {
+        self.directories
+            .get()
+            .into_iter()
+            .rev()
+            .map(|directory| directory.join(path))
+            .find(|file| file.is_file())
+    }
+}
+
+impl AssetReader for ModAssetReader {
+    fn read<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> BoxedFuture<'a, Result<Box<Reader<'a>>, AssetReaderError>> {
+        Box::pin(async move {
+            match self.find_override(path) {
+                Some(file) => read_file(&file),
+                None => self.base.read(path).await,
+            }
+        })
+    }
+
+    fn read_meta<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> BoxedFuture<'a, Result<Box<Reader<'a>>, AssetReaderError>> {
+        Box::pin(async move {
+            match self.find_override(path) {
+                // The game's meta file may not fit the mod's asset, so only the mod's own one is used
+                Some(file) => {
+                    let mut meta_file = file.into_os_string();
+                    meta_file.push(".meta");
+                    read_file(Path::new(&meta_file))
+                }
+                None => self.base.read_meta(path).await,
+            }
+        })
+    }
+
+    fn read_directory<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> BoxedFuture<'a, Result<Box<PathStream>, AssetReaderError>> {
+        Box::pin(async move {
+            let mut paths: Vec<PathBuf> = match self.base.read_directory(path).await {
+                Ok(base_paths) => base_paths.collect().await,
+                Err(AssetReaderError::NotFound(_)) => Vec::new(),
+                Err(error) => return Err(error),
+            };
+            for directory in self.directories.get() {
+                let Ok(entries) = std::fs::read_dir(directory.join(path)) else {
+                    continue;
+                };
+                for entry in entries.flatten() {
+                    let entry_path = path.join(entry.file_name());
+                    // Like the base reader, meta files are left out since they are read along with their asset
+                    let is_meta = entry_path
+                        .extension()
+                        .is_some_and(|extension| extension == "meta");
+                    if !is_meta && !paths.contains(&entry_path) {
+                        paths.push(entry_path);
+                    }
+                }
+            }
+            let paths: Box<PathStream> = Box::new(stream::iter(paths));
+            Ok(paths)
+        })
+    }
+
+    fn is_directory<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> BoxedFuture<'a, Result<bool, AssetReaderError>> {
+        Box::pin(async move {
+            let in_mod = self
+                .directories
+                .get()
+                .iter()
+                .any(|directory| directory.join(path).is_dir());
+            if in_mod {
+                return Ok(true);
+            }
+            self.base.is_directory(path).await
+        })
+    }
+}
+
+/// Reads the whole file at once, since mod files are few and the base reader's async file type is not exposed.
+fn read_file<'a>(path: &Path) -> Result<Box<Reader<'a>>, AssetReaderError> {
+    match std::fs::read(path) {
+        Ok(bytes) => Ok(Box::new(VecReader::new(bytes))),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            Err(AssetReaderError::NotFound(path.to_path_buf()))
+        }
+        Err(error) => Err(error.into()),
+    }
+}
@@ -0,0 +1,312 @@
+use crate::{
+    file_system_interaction::{
+        game_state_serialization::{enter_save, save_pending, SaveFile, SavedState},
+        storage::GameStorage,
+    },
+    level_instantiation::levels::CurrentLevel,
+    player_control::actions::{
+        ControlledActions, InputBuffer, InputBufferSystemSet, InputOverrideSystemSet, TickInput,
+    },
+    GameState,
+};
+use anyhow::{Context, Result};
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext, LoadState},
+    ecs::system::SystemParam,
+    input::mouse::MouseMotion,
+    prelude::*,
+    time::TimeUpdateStrategy,
+    utils::BoxedFuture,
+};
+use bevy_mod_sysfail::*;
+use serde::{Deserialize, Serialize};
+
+const REPLAY_DIRECTORY: &str = "replays";
+const REPLAY_EXTENSION: &str = "replay";
+/// Overwritten every time the player leaves a level, so that it can be attached to bug reports
+const LATEST_REPLAY: &str = "latest";
+/// Played on the main menu after it has been left alone for [`ATTRACT_DELAY`] seconds
+const ATTRACT_REPLAY: &str = "replays/attract.replay";
+const ATTRACT_DELAY: f32 = 30.;
+
+/// Records every stay in a level as a [`Replay`]: the state of the player when entering it, the [`SessionSeed`]
+/// and the [`TickInput`] of every frame from the [`InputBuffer`], which includes how long that frame took.
+/// A [`ReplayPlaybackRequest`] reloads the recorded state and feeds the recorded actions back in instead of the real input.
+/// Since frames are replayed with their recorded durations, physics and animations step exactly like they did while recording.
+/// The replay of the last level is written to `replays/latest.replay` in the [`GameStorage`], and the main menu shows
+/// `assets/replays/attract.replay` as a demo when nobody touches it for a while.
+/// The demo is loaded as an asset, so that it is fetched asynchronously on the web.
+pub(crate) fn replay_plugin(app: &mut App) {
+    app.insert_resource(SessionSeed(rand::random()))
+        .init_asset::<Replay>()
+        .init_asset_loader::<ReplayLoader>()
+        .add_event::<ReplayPlaybackRequest>()
+        .add_systems(
+            PreUpdate,
+            (
+                play_frame
+                    .run_if(resource_exists::<Playback>())
+                    .in_set(InputOverrideSystemSet),
+                (
+                    start_recording
+                        .run_if(not(resource_exists::<Recording>()))
+                        .run_if(not(resource_exists::<Playback>())),
+                    record_frame.run_if(resource_exists::<Recording>()),
+                )
+                    .chain()
+                    .after(InputBufferSystemSet),
+            )
+                .run_if(in_state(GameState::Playing))
+                .run_if(not(save_pending)),
+        )
+        .add_systems(Update, handle_playback_requests)
+        .add_systems(OnExit(GameState::Playing), finish_recording)
+        .add_systems(OnEnter(GameState::Menu), stop_playback)
+        .add_systems(
+            Update,
+            start_attract_mode
+                .run_if(in_state(GameState::Menu))
+                .run_if(not(resource_exists::<Playback>())),
+        );
+}
+
+/// Seed that randomness influencing gameplay is derived from, so that replays can reproduce it.
+/// See [`GameRng`](crate::rng::GameRng).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Resource, Reflect, Serialize, Deserialize)]
+#[reflect(Resource, Serialize, Deserialize)]
+pub(crate) struct SessionSeed(pub(crate) u64);
+
+/// Plays `replay`. The player gets control back once it ends, unless it is played as an attract mode demo.
+#[derive(Debug, Clone, PartialEq, Event)]
+pub(crate) struct ReplayPlaybackRequest {
+    pub(crate) replay: Replay,
+    /// Whether any input ends the replay and returns to the main menu
+    pub(crate) attract: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Asset, TypePath)]
+pub(crate) struct Replay {
+    pub(crate) seed: u64,
+    pub(crate) start: SaveFile,
+    pub(crate) frames: Vec<TickInput>,
+}
+
+#[derive(Debug, Clone, PartialEq, Resource)]
+struct Recording(Replay);
+
+#[derive(Debug, Clone, PartialEq, Resource)]
+struct Playback {
+    replay: Replay,
+    /// Index of the next frame to play
+    frame: usize,
+    attract: bool,
+}
+
+fn replay_key(name: &str) -> String {
+    format!("{REPLAY_DIRECTORY}/{name}.{REPLAY_EXTENSION}")
+}
+
+/// Reads the replay called `name` from the [`GameStorage`].
+pub(crate) fn load_replay(storage: &GameStorage, name: &str) -> Result<Replay> {
+    let key = replay_key(name);
+    let serialized = storage
+        .read(&key)
+        .context("Failed to read replay")?
+        .with_context(|| format!("There is no replay at {key}"))?;
+    bincode::deserialize(&serialized).with_context(|| format!("Failed to parse replay {key}"))
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct ReplayLoader;
+
+impl AssetLoader for ReplayLoader {
+    type Asset = Replay;
+    type Settings = ();
+    type Error = bincode::Error;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            bincode::deserialize(&bytes)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[REPLAY_EXTENSION]
+    }
+}
+
+/// Any button or mouse movement, used to interrupt the attract mode
+#[derive(SystemParam)]
+struct AnyInput<'w, 's> {
+    keys: Res<'w, Input<KeyCode>>,
+    mouse_buttons: Res<'w, Input<MouseButton>>,
+    gamepad_buttons: Res<'w, Input<GamepadButton>>,
+    mouse_motion: EventReader<'w, 's, MouseMotion>,
+}
+
+impl AnyInput<'_, '_> {
+    fn used(&mut self) -> bool {
+        let moved = self.mouse_motion.read().count() > 0;
+        moved
+            || self.keys.get_just_pressed().next().is_some()
+            || self.mouse_buttons.get_just_pressed().next().is_some()
+            || self.gamepad_buttons.get_just_pressed().next().is_some()
+    }
+}
+
+#[sysfail(log(level = "error"))]
+fn start_recording(
+    mut commands: Commands,
+    saved_state: SavedState,
+    seed: Res<SessionSeed>,
+) -> Result<()> {
+    // Waits for the level to spawn the player
+    if saved_state.players.is_empty() {
+        return Ok(());
+    }
+    commands.insert_resource(Recording(Replay {
+        seed: seed.0,
+        start: saved_state.snapshot()?,
+        frames: Vec::new(),
+    }));
+    Ok(())
+}
+
+fn record_frame(mut recording: ResMut<Recording>, input_buffer: Res<InputBuffer>) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("record_frame").entered();
+    if let Some(input) = input_buffer.latest() {
+        recording.0.frames.push(input.clone());
+    }
+}
+
+#[sysfail(log(level = "error"))]
+fn finish_recording(
+    mut commands: Commands,
+    recording: Option<Res<Recording>>,
+    storage: Res<GameStorage>,
+) -> Result<()> {
+    let Some(recording) = recording else {
+        return Ok(());
+    };
+    commands.remove_resource::<Recording>();
+    if recording.0.frames.is_empty() {
+        return Ok(());
+    }
+    let serialized = bincode::serialize(&recording.0).context("Failed to serialize replay")?;
+    let key = replay_key(LATEST_REPLAY);
+    storage
+        .write(&key, &serialized)
+        .context("Failed to write replay")?;
+    info!("Saved replay to {key}");
+    Ok(())
+}
+
+fn handle_playback_requests(
+    mut commands: Commands,
+    mut playback_requests: EventReader<ReplayPlaybackRequest>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut seed: ResMut<SessionSeed>,
+) {
+    for request in playback_requests.read() {
+        let replay = request.replay.clone();
+        let Some(first_frame) = replay.frames.first() else {
+            warn!("Cannot play a replay without frames");
+            continue;
+        };
+        seed.0 = replay.seed;
+        // The duration of the first frame is applied while loading the level, every following one by the frame before it
+        commands.insert_resource(TimeUpdateStrategy::ManualDuration(first_frame.delta));
+        commands.remove_resource::<Recording>();
+        enter_save(
+            &mut commands,
+            replay.start.clone(),
+            &mut current_level,
+            &mut next_state,
+        );
+        commands.insert_resource(Playback {
+            replay,
+            frame: 0,
+            attract: request.attract,
+        });
+    }
+}
+
+fn play_frame(
+    mut commands: Commands,
+    mut playback: ResMut<Playback>,
+    mut controlled_actions: ControlledActions,
+    mut input: AnyInput,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("play_frame").entered();
+    let interrupted = playback.attract && input.used();
+    let Some(frame) = playback
+        .replay
+        .frames
+        .get(playback.frame)
+        .filter(|_| !interrupted)
+    else {
+        commands.insert_resource(TimeUpdateStrategy::Automatic);
+        commands.remove_resource::<Playback>();
+        if playback.attract {
+            next_state.set(GameState::Menu);
+        } else {
+            info!("Replay finished, handing control back to the player");
+        }
+        return;
+    };
+    controlled_actions.apply(frame);
+    if let Some(next_frame) = playback.replay.frames.get(playback.frame + 1) {
+        commands.insert_resource(TimeUpdateStrategy::ManualDuration(next_frame.delta));
+    }
+    playback.frame += 1;
+}
+
+fn stop_playback(mut commands: Commands) {
+    commands.insert_resource(TimeUpdateStrategy::Automatic);
+    commands.remove_resource::<Playback>();
+}
+
+fn start_attract_mode(
+    time: Res<Time<Real>>,
+    asset_server: Res<AssetServer>,
+    replays: Res<Assets<Replay>>,
+    mut idle: Local<f32>,
+    mut attract_replay: Local<Option<Handle<Replay>>>,
+    mut input: AnyInput,
+    mut playback_requests: EventWriter<ReplayPlaybackRequest>,
+) {
+    if input.used() {
+        *idle = 0.;
+        return;
+    }
+    *idle += time.delta_seconds();
+    if *idle < ATTRACT_DELAY {
+        return;
+    }
+    // Only loaded once it is needed, since most players never see it
+    let handle = attract_replay.get_or_insert_with(|| asset_server.load(ATTRACT_REPLAY));
+    if asset_server.load_state(handle.id()) == LoadState::Failed {
+        // Keeps games without a demo from retrying every frame
+        *idle = f32::NEG_INFINITY;
+        return;
+    }
+    let Some(replay) = replays.get(handle.id()) else {
+        return;
+    };
+    *idle = 0.;
+    playback_requests.send(ReplayPlaybackRequest {
+        replay: replay.clone(),
+        attract: true,
+    });
+}
@@ -0,0 +1,186 @@
+use crate::file_system_interaction::config::GameConfig;
+use bevy::{prelude::*, utils::HashMap};
+use bevy_kira_audio::prelude::{Audio, *};
+use std::time::Duration;
+
+/// Seconds a sound may take to start playing before its voice is given up, e.g. while it is still loading
+const START_TIMEOUT: f32 = 1.;
+/// How quickly a stolen voice fades out
+const STEAL_FADE: Duration = Duration::from_millis(50);
+
+/// Plays the short sounds of the game sent as [`SoundEffect`] events, so that many of them at once,
+/// e.g. the footsteps and swings of a big fight, neither overload the mixer nor load sounds while playing.
+/// Sounds listed in the `sound_effects` section of the [`GameConfig`] are loaded up front, and every sound
+/// stays loaded once it has been played. Each sound and each [`SoundCategory`] can only play a limited number
+/// of times at once. When a limit is reached, the voice with the lowest priority, and of those the oldest,
+/// is stopped to make room, unless the new sound has an even lower priority, in which case it is skipped.
+pub(crate) fn sound_effects_plugin(app: &mut App) {
+    app.add_event::<SoundEffect>()
+        .init_resource::<SoundEffectVoices>()
+        .add_systems(Update, (preload_sound_effects, play_sound_effects).chain());
+}
+
+/// Plays the sound at `path` through the [`sound_effects_plugin`].
+#[derive(Debug, Clone, PartialEq, Event)]
+pub(crate) struct SoundEffect {
+    pub(crate) path: String,
+    pub(crate) category: SoundCategory,
+    /// Sounds with a higher priority take the voices of those with a lower one
+    pub(crate) priority: u8,
+    pub(crate) volume: f64,
+}
+
+impl SoundEffect {
+    pub(crate) fn new(path: impl Into<String>, category: SoundCategory) -> Self {
+        Self {
+            path: path.into(),
+            category,
+            priority: 0,
+            volume: 1.,
+        }
+    }
+
+    pub(crate) fn with_priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+}
+
+/// Groups of sounds that share a limit on how many of them play at once
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum SoundCategory {
+    /// Sounds made by characters, like footsteps and attacks
+    Character,
+    /// Sounds made by the level, like machinery
+    World,
+}
+
+#[derive(Debug, Resource, Default)]
+struct SoundEffectVoices {
+    /// Strong handles that keep every sound loaded that was preloaded or played
+    sources: HashMap<String, Handle<AudioSource>>,
+    /// Oldest first
+    voices: Vec<Voice>,
+}
+
+#[derive(Debug)]
+struct Voice {
+    path: String,
+    category: SoundCategory,
+    priority: u8,
+    instance: Handle<AudioInstance>,
+    started: f32,
+}
+
+fn preload_sound_effects(
+    config: Option<Res<GameConfig>>,
+    asset_server: Res<AssetServer>,
+    mut voices: ResMut<SoundEffectVoices>,
+) {
+    let Some(config) = config.filter(|config| config.is_changed()) else {
+        return;
+    };
+    for path in &config.sound_effects.preload {
+        voices
+            .sources
+            .entry(path.clone())
+            .or_insert_with(|| asset_server.load(path.clone()));
+    }
+}
+
+fn play_sound_effects(
+    time: Res<Time<Real>>,
+    mut events: EventReader<SoundEffect>,
+    config: Option<Res<GameConfig>>,
+    asset_server: Res<AssetServer>,
+    audio: Res<Audio>,
+    mut instances: ResMut<Assets<AudioInstance>>,
+    mut voices: ResMut<SoundEffectVoices>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("play_sound_effects").entered();
+    let now = time.elapsed_seconds();
+    voices
+        .voices
+        .retain(|voice| match instances.get(&voice.instance) {
+            Some(instance) => !matches!(instance.state(), PlaybackState::Stopped),
+            None => now - voice.started < START_TIMEOUT,
+        });
+    let Some(config) = config else {
+        events.clear();
+        return;
+    };
+    let config = &config.sound_effects;
+
+    for event in events.read() {
+        let mut stolen = Vec::new();
+        let same_sound = make_room(
+            &voices.voices,
+            &stolen,
+            config.max_instances,
+            event.priority,
+            |voice| voice.path == event.path,
+        );
+        let Ok(same_sound) = same_sound else {
+            continue;
+        };
+        stolen.extend(same_sound);
+        let same_category = make_room(
+            &voices.voices,
+            &stolen,
+            config.max_voices(event.category),
+            event.priority,
+            |voice| voice.category == event.category,
+        );
+        let Ok(same_category) = same_category else {
+            continue;
+        };
+        stolen.extend(same_category);
+        stolen.sort_unstable_by(|a, b| b.cmp(a));
+        for index in stolen {
+            let voice = voices.voices.remove(index);
+            if let Some(instance) = instances.get_mut(&voice.instance) {
+                instance.stop(AudioTween::linear(STEAL_FADE));
+            }
+        }
+
+        let source = voices
+            .sources
+            .entry(event.path.clone())
+            .or_insert_with(|| asset_server.load(event.path.clone()))
+            .clone();
+        let instance = audio.play(source).with_volume(event.volume).handle();
+        voices.voices.push(Voice {
+            path: event.path.clone(),
+            category: event.category,
+            priority: event.priority,
+            instance,
+            started: now,
+        });
+    }
+}
+
+/// The voice to stop so that one more sound fits into the `limit` of the voices matching `is_limited`,
+/// if it does not fit already. Fails if all of them have a higher priority than the new sound.
+fn make_room(
+    voices: &[Voice],
+    stolen: &[usize],
+    limit: usize,
+    priority: u8,
+    is_limited: impl Fn(&Voice) -> bool,
+) -> Result<Option<usize>, ()> {
+    let playing: Vec<_> = voices
+        .iter()
+        .enumerate()
+        .filter(|(index, voice)| !stolen.contains(index) && is_limited(voice))
+        .collect();
+    if playing.len() < limit {
+        return Ok(None);
+    }
+    playing
+        .into_iter()
+        .min_by_key(|(_, voice)| voice.priority)
+        .filter(|(_, voice)| voice.priority <= priority)
+        .map(|(index, _)| Some(index))
+        .ok_or(())
+}
@@ -0,0 +1,182 @@
+use anyhow::{Context, Result};
+use bevy::prelude::*;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Inserts the [`GameStorage`] that saves, replays and settings are written to.
+/// On desktop, this is a directory next to the game; on the web, where there is no file system, it is the browser's `localStorage`.
+pub(crate) fn storage_plugin(app: &mut App) {
+    #[cfg(not(target_arch = "wasm32"))]
+    app.insert_resource(GameStorage::new(FileStorage));
+    #[cfg(target_arch = "wasm32")]
+    app.insert_resource(GameStorage::new(LocalStorage));
+}
+
+/// Persistent key-value storage for small files. Keys look like relative paths, e.g. `saves/save_1.sav.json`.
+/// Reading and writing is synchronous, so it should only be used for data that fits into a single frame.
+pub(crate) trait Storage: Send + Sync {
+    fn read(&self, key: &str) -> Result<Option<Vec<u8>>>;
+    fn write(&self, key: &str, data: &[u8]) -> Result<()>;
+    /// All entries whose key starts with `directory/`
+    fn list(&self, directory: &str) -> Result<Vec<StoredEntry>>;
+}
+
+#[derive(Resource, Deref)]
+pub(crate) struct GameStorage(Box<dyn Storage>);
+
+impl GameStorage {
+    pub(crate) fn new(storage: impl Storage + 'static) -> Self {
+        Self(Box::new(storage))
+    }
+
+    pub(crate) fn into_inner(self) -> Box<dyn Storage> {
+        self.0
+    }
+
+    pub(crate) fn read_string(&self, key: &str) -> Result<Option<String>> {
+        self.read(key)?
+            .map(String::from_utf8)
+            .transpose()
+            .with_context(|| format!("{key} is not valid UTF-8"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct StoredEntry {
+    /// The key without the directory
+    pub(crate) name: String,
+    pub(crate) modified: SystemTime,
+}
+
+/// The current time. Unlike [`SystemTime::now`], this also works on the web.
+pub(crate) fn now() -> SystemTime {
+    #[cfg(not(target_arch = "wasm32"))]
+    return SystemTime::now();
+    #[cfg(target_arch = "wasm32")]
+    return UNIX_EPOCH + Duration::from_millis(js_sys::Date::now() as u64);
+}
+
+/// Seconds since the Unix epoch, e.g. for naming files
+pub(crate) fn timestamp() -> u64 {
+    now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs()
+}
+
+/// Stores every key as a file relative to the working directory.
+#[cfg(not(target_arch = "wasm32"))]
+struct FileStorage;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Storage for FileStorage {
+    fn read(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match std::fs::read(key) {
+            Ok(data) => Ok(Some(data)),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error).with_context(|| format!("Failed to read {key}")),
+        }
+    }
+
+    fn write(&self, key: &str, data: &[u8]) -> Result<()> {
+        let path = std::path::Path::new(key);
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)
+                .with_context(|| format!("Failed to create {}", directory.display()))?;
+        }
+        std::fs::write(path, data).with_context(|| format!("Failed to write {key}"))
+    }
+
+    fn list(&self, directory: &str) -> Result<Vec<StoredEntry>> {
+        if !std::path::Path::new(directory).exists() {
+            return Ok(Vec::new());
+        }
+        let mut entries = Vec::new();
+        for entry in
+            std::fs::read_dir(directory).with_context(|| format!("Failed to read {directory}"))?
+        {
+            let entry = entry?;
+            entries.push(StoredEntry {
+                name: entry.file_name().to_string_lossy().to_string(),
+                modified: entry.metadata()?.modified()?,
+            });
+        }
+        Ok(entries)
+    }
+}
+
+/// Stores every key in the browser's `localStorage`, along with a second key holding when it was written.
+/// `localStorage` only holds strings, so every byte is stored as one character.
+#[cfg(target_arch = "wasm32")]
+struct LocalStorage;
+
+#[cfg(target_arch = "wasm32")]
+impl LocalStorage {
+    const MODIFIED_SUFFIX: &'static str = "#modified";
+
+    fn storage() -> Result<web_sys::Storage> {
+        web_sys::window()
+            .context("There is no browser window")?
+            .local_storage()
+            .ok()
+            .flatten()
+            .context("localStorage is not available")
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Storage for LocalStorage {
+    fn read(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let value = Self::storage()?
+            .get_item(key)
+            .ok()
+            .with_context(|| format!("Failed to read {key}"))?;
+        Ok(value.map(|value| value.chars().map(|character| character as u8).collect()))
+    }
+
+    fn write(&self, key: &str, data: &[u8]) -> Result<()> {
+        let storage = Self::storage()?;
+        let value: String = data.iter().map(|byte| *byte as char).collect();
+        storage
+            .set_item(key, &value)
+            .ok()
+            .with_context(|| format!("Failed to write {key}, the browser's storage may be full"))?;
+        storage
+            .set_item(
+                &format!("{key}{}", Self::MODIFIED_SUFFIX),
+                &js_sys::Date::now().to_string(),
+            )
+            .ok()
+            .with_context(|| format!("Failed to write modification time of {key}"))?;
+        Ok(())
+    }
+
+    fn list(&self, directory: &str) -> Result<Vec<StoredEntry>> {
+        let storage = Self::storage()?;
+        let prefix = format!("{directory}/");
+        let length = storage.length().ok().context("Failed to list storage")?;
+        let mut entries = Vec::new();
+        for index in 0..length {
+            let Some(key) = storage.key(index).ok().flatten() else {
+                continue;
+            };
+            let Some(name) = key.strip_prefix(&prefix) else {
+                continue;
+            };
+            if name.ends_with(Self::MODIFIED_SUFFIX) {
+                continue;
+            }
+            let modified = storage
+                .get_item(&format!("{key}{}", Self::MODIFIED_SUFFIX))
+                .ok()
+                .flatten()
+                .and_then(|millis| millis.parse::<f64>().ok())
+                .map(|millis| UNIX_EPOCH + Duration::from_millis(millis as u64))
+                .unwrap_or(UNIX_EPOCH);
+            entries.push(StoredEntry {
+                name: name.to_string(),
+                modified,
+            });
+        }
+        Ok(entries)
+    }
+}
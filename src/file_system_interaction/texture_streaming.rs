@@ -0,0 +1,268 @@
+use crate::{file_system_interaction::config::GameConfig, player_control::camera::IngameCamera};
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{Extent3d, TextureDimension},
+        texture::TextureFormatPixelInfo,
+    },
+    tasks::{futures_lite::future, AsyncComputeTaskPool, Task},
+    utils::{HashMap, HashSet},
+};
+
+/// Keeps large level textures from all reaching the GPU in the same frame, which makes the game hitch
+/// when a level or a streamed chunk appears. Textures of scenes that are larger than the configured minimum are
+/// swapped for a placeholder as soon as they are decoded: first a single pixel, then a low resolution copy
+/// that is scaled down in the background. The full resolution textures are swapped back in closest first,
+/// limited to a number of megabytes per frame. Once the full resolution textures exceed the memory budget,
+/// those farthest from the camera are evicted to their placeholder again.
+/// Everything is configured in the `texture_streaming` section of the [`GameConfig`].
+pub(crate) fn texture_streaming_plugin(app: &mut App) {
+    app.init_resource::<StreamedTextures>()
+        .add_systems(PostUpdate, (intercept_textures, stream_textures).chain());
+}
+
+const BYTES_PER_MEGABYTE: f32 = 1024. * 1024.;
+
+#[derive(Resource, Default)]
+struct StreamedTextures {
+    textures: HashMap<AssetId<Image>, StreamedTexture>,
+    /// Every image that has been looked at, so that only new ones are intercepted
+    seen: HashSet<AssetId<Image>>,
+}
+
+struct StreamedTexture {
+    /// The decoded texture, kept on the CPU while a placeholder is shown
+    full: Image,
+    low: Image,
+    low_task: Option<Task<Option<Image>>>,
+    resident: bool,
+}
+
+impl StreamedTexture {
+    fn bytes(&self) -> usize {
+        self.full.data.len()
+    }
+}
+
+fn intercept_textures(
+    config: Option<Res<GameConfig>>,
+    asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    mut streamed: ResMut<StreamedTextures>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("intercept_textures").entered();
+    let Some(config) = config.filter(|config| config.texture_streaming.enabled) else {
+        return;
+    };
+    if !images.is_changed() {
+        return;
+    }
+    let new_images: Vec<_> = images
+        .ids()
+        .filter(|id| !streamed.seen.contains(id))
+        .collect();
+    for id in new_images {
+        streamed.seen.insert(id);
+        // Only textures of scenes are streamed, UI images and the like are small and needed right away
+        let is_scene_texture = asset_server.get_path(id).is_some_and(|path| {
+            path.label()
+                .is_some_and(|label| label.starts_with("Texture"))
+        });
+        if !is_scene_texture {
+            continue;
+        }
+        let Some(image) = images.get(id) else {
+            continue;
+        };
+        let descriptor = &image.texture_descriptor;
+        let size = descriptor.size;
+        if size.width.max(size.height) < config.texture_streaming.min_size
+            || size.depth_or_array_layers != 1
+            || descriptor.mip_level_count != 1
+            || descriptor.format.is_compressed()
+            || descriptor.dimension != TextureDimension::D2
+        {
+            continue;
+        }
+        let full = image.clone();
+        let pixel_size = full.texture_descriptor.format.pixel_size();
+        let pixel = Image::new_fill(
+            Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &full.data[..pixel_size],
+            full.texture_descriptor.format,
+        );
+        show(&mut images, id, &pixel);
+
+        let placeholder_size = config.texture_streaming.placeholder_size;
+        let source = full.clone();
+        let low_task = AsyncComputeTaskPool::get().spawn(async move {
+            let is_srgb = source.texture_descriptor.format.is_srgb();
+            let dynamic = source.try_into_dynamic().ok()?;
+            Some(Image::from_dynamic(
+                dynamic.thumbnail(placeholder_size, placeholder_size),
+                is_srgb,
+            ))
+        });
+        streamed.textures.insert(
+            id,
+            StreamedTexture {
+                full,
+                low: pixel,
+                low_task: Some(low_task),
+                resident: false,
+            },
+        );
+    }
+}
+
+fn stream_textures(
+    config: Option<Res<GameConfig>>,
+    mut images: ResMut<Assets<Image>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut streamed: ResMut<StreamedTextures>,
+    cameras: Query<&GlobalTransform, With<IngameCamera>>,
+    renderables: Query<(&Handle<StandardMaterial>, &GlobalTransform)>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("stream_textures").entered();
+    if streamed.textures.is_empty() {
+        return;
+    }
+    // Textures whose last handle was dropped, e.g. by unloading a chunk, are gone for good
+    streamed.textures.retain(|id, _| images.contains(*id));
+    let Some(config) = config else {
+        return;
+    };
+    let config = &config.texture_streaming;
+    let mut swapped = HashSet::new();
+
+    for (id, texture) in streamed.textures.iter_mut() {
+        let Some(task) = texture.low_task.as_mut() else {
+            continue;
+        };
+        let Some(low) = future::block_on(future::poll_once(task)) else {
+            continue;
+        };
+        texture.low_task = None;
+        if let Some(low) = low {
+            texture.low = low;
+            if !texture.resident {
+                show(&mut images, *id, &texture.low);
+                swapped.insert(*id);
+            }
+        }
+    }
+
+    let camera_position = cameras
+        .iter()
+        .next()
+        .map_or(Vec3::ZERO, GlobalTransform::translation);
+    let mut material_distances = HashMap::<AssetId<StandardMaterial>, f32>::new();
+    for (material, transform) in renderables.iter() {
+        let distance = transform.translation().distance(camera_position);
+        let entry = material_distances
+            .entry(material.id())
+            .or_insert(f32::INFINITY);
+        *entry = entry.min(distance);
+    }
+    let mut distances = HashMap::<AssetId<Image>, f32>::new();
+    for (id, material) in materials.iter() {
+        let Some(&distance) = material_distances.get(&id) else {
+            continue;
+        };
+        for texture in textures(material).into_iter().flatten() {
+            let entry = distances.entry(texture.id()).or_insert(f32::INFINITY);
+            *entry = entry.min(distance);
+        }
+    }
+
+    // The closest textures that fit into the budget should be at full resolution, unused ones never are
+    let mut by_distance: Vec<_> = streamed
+        .textures
+        .iter()
+        .filter_map(|(id, texture)| Some((*id, *distances.get(id)?, texture.bytes())))
+        .collect();
+    by_distance.sort_by(|(_, a, _), (_, b, _)| a.total_cmp(b));
+    let budget = (config.budget_mb * BYTES_PER_MEGABYTE) as usize;
+    let mut planned = 0;
+    let mut wanted = HashSet::new();
+    for (id, _, bytes) in &by_distance {
+        if planned + bytes > budget {
+            break;
+        }
+        planned += bytes;
+        wanted.insert(*id);
+    }
+
+    for (id, texture) in streamed.textures.iter_mut() {
+        if texture.resident && !wanted.contains(id) {
+            texture.resident = false;
+            show(&mut images, *id, &texture.low);
+            swapped.insert(*id);
+        }
+    }
+    let upload_budget = (config.upload_mb_per_frame * BYTES_PER_MEGABYTE) as usize;
+    let mut uploaded = 0;
+    for (id, _, bytes) in by_distance {
+        if !wanted.contains(&id) {
+            continue;
+        }
+        let Some(texture) = streamed.textures.get_mut(&id) else {
+            continue;
+        };
+        if texture.resident {
+            continue;
+        }
+        // At least one texture per frame, even if it is larger than the upload budget on its own
+        if uploaded > 0 && uploaded + bytes > upload_budget {
+            break;
+        }
+        uploaded += bytes;
+        texture.resident = true;
+        show(&mut images, id, &texture.full);
+        swapped.insert(id);
+    }
+
+    if swapped.is_empty() {
+        return;
+    }
+    // Materials only pick up the new contents of their textures when they change themselves
+    let affected: Vec<_> = materials
+        .iter()
+        .filter(|(_, material)| {
+            textures(material)
+                .into_iter()
+                .flatten()
+                .any(|texture| swapped.contains(&texture.id()))
+        })
+        .map(|(id, _)| id)
+        .collect();
+    for id in affected {
+        materials.get_mut(id);
+    }
+}
+
+/// Replaces the contents of the texture, keeping the sampler it has, since it may have been changed after loading
+fn show(images: &mut Assets<Image>, id: AssetId<Image>, image: &Image) {
+    let mut image = image.clone();
+    if let Some(current) = images.get(id) {
+        image.sampler = current.sampler.clone();
+    }
+    images.insert(id, image);
+}
+
+fn textures(material: &StandardMaterial) -> [Option<&Handle<Image>>; 5] {
+    [
+        material.base_color_texture.as_ref(),
+        material.emissive_texture.as_ref(),
+        material.metallic_roughness_texture.as_ref(),
+        material.normal_map_texture.as_ref(),
+        material.occlusion_texture.as_ref(),
+    ]
+}
@@ -0,0 +1,180 @@
+use crate::combat::health::DamageType;
+use bevy::prelude::*;
+
+/// The gameplay events that modules publish and subscribe to instead of reaching into each other's resources and components.
+/// This is the place to hook custom game logic into: read one of these with an [`EventReader`] in a new system
+/// and the module that sends it does not need to know about it.
+/// - [`PlayerDamaged`] and [`PlayerDied`] are sent by the [`health_plugin`](crate::combat::health::health_plugin).
+/// - [`DialogStarted`] and [`DialogEnded`] are sent by the [`dialog_plugin`](crate::world_interaction::dialog::dialog_plugin)
+///   and whatever starts a dialog.
+/// - [`ItemPickedUp`], [`QuestStarted`] and [`QuestCompleted`] are sent by dialogs with `<<give_item key>>`,
+///   `<<start_quest id>>` and `<<complete_quest id>>`.
+/// - [`CollectibleCollected`] and [`CollectibleSetCompleted`] are sent by the
+///   [`collectibles_plugin`](crate::world_interaction::collectibles::collectibles_plugin).
+/// - [`PlayerLeveledUp`] is sent by the [`progression_plugin`](crate::player_control::progression::progression_plugin).
+/// - [`ItemCrafted`] is sent by the [`crafting_plugin`](crate::world_interaction::crafting::crafting_plugin).
+/// - [`SwitchToggled`] is sent by the [`switches_plugin`](crate::world_interaction::switches::switches_plugin).
+/// - [`LevelLoaded`] is sent by the [`levels_plugin`](crate::level_instantiation::levels::levels_plugin)
+///   once the player has spawned in a level.
+///
+/// All of them are logged at the debug level, which makes `RUST_LOG=foxtrot=debug` a quick way to follow what happens.
+pub(crate) fn game_events_plugin(app: &mut App) {
+    app.add_event::<PlayerDamaged>()
+        .add_event::<PlayerDied>()
+        .add_event::<ItemPickedUp>()
+        .add_event::<DialogStarted>()
+        .add_event::<DialogEnded>()
+        .add_event::<LevelLoaded>()
+        .add_event::<QuestStarted>()
+        .add_event::<QuestCompleted>()
+        .add_event::<CollectibleCollected>()
+        .add_event::<CollectibleSetCompleted>()
+        .add_event::<ItemCrafted>()
+        .add_event::<PlayerLeveledUp>()
+        .add_event::<SwitchToggled>()
+        .add_systems(PostUpdate, log_game_events);
+}
+
+/// The player lost health, after their resistances were applied.
+#[derive(Debug, Clone, PartialEq, Event)]
+pub(crate) struct PlayerDamaged {
+    pub(crate) amount: f32,
+    pub(crate) max_health: f32,
+    pub(crate) damage_type: DamageType,
+    pub(crate) attacker: Option<Entity>,
+    /// Direction in which the hit pushes the player
+    pub(crate) direction: Vec3,
+}
+
+#[derive(Debug, Clone, PartialEq, Event)]
+pub(crate) struct PlayerDied {
+    pub(crate) killer: Option<Entity>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub(crate) struct ItemPickedUp {
+    /// What was picked up, e.g. "key_red"
+    pub(crate) item: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub(crate) struct DialogStarted {
+    /// The yarn node the dialog starts at
+    pub(crate) node: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub(crate) struct DialogEnded;
+
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub(crate) struct LevelLoaded {
+    pub(crate) level: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub(crate) struct QuestStarted {
+    pub(crate) id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub(crate) struct QuestCompleted {
+    pub(crate) id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub(crate) struct CollectibleCollected {
+    pub(crate) set: String,
+    pub(crate) id: String,
+    /// How many collectibles of the set have been found, including this one
+    pub(crate) found: usize,
+}
+
+/// The last collectible of a set was found.
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub(crate) struct CollectibleSetCompleted {
+    pub(crate) set: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub(crate) struct ItemCrafted {
+    /// ID of the recipe that was used
+    pub(crate) recipe: String,
+    /// Key of the item that was made
+    pub(crate) item: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub(crate) struct PlayerLeveledUp {
+    /// The level the player reached
+    pub(crate) level: u32,
+}
+
+/// A switch was turned on or off, e.g. by a pressure plate.
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub(crate) struct SwitchToggled {
+    pub(crate) switch: String,
+    pub(crate) on: bool,
+}
+
+fn log_game_events(
+    mut player_damaged: EventReader<PlayerDamaged>,
+    mut player_died: EventReader<PlayerDied>,
+    mut items_picked_up: EventReader<ItemPickedUp>,
+    mut dialogs_started: EventReader<DialogStarted>,
+    mut dialogs_ended: EventReader<DialogEnded>,
+    mut levels_loaded: EventReader<LevelLoaded>,
+    mut quests_started: EventReader<QuestStarted>,
+    mut quests_completed: EventReader<QuestCompleted>,
+    mut collectibles_collected: EventReader<CollectibleCollected>,
+    mut collectible_sets_completed: EventReader<CollectibleSetCompleted>,
+    mut items_crafted: EventReader<ItemCrafted>,
+    mut players_leveled_up: EventReader<PlayerLeveledUp>,
+    mut switches_toggled: EventReader<SwitchToggled>,
+) {
+    for event in player_damaged.read() {
+        debug!(
+            "Player took {} {:?} damage from {:?} in direction {}",
+            event.amount, event.damage_type, event.attacker, event.direction
+        );
+    }
+    for event in player_died.read() {
+        debug!("Player was killed by {:?}", event.killer);
+    }
+    for event in items_picked_up.read() {
+        debug!("Picked up {}", event.item);
+    }
+    for event in dialogs_started.read() {
+        debug!("Started dialog at {}", event.node);
+    }
+    for _ in dialogs_ended.read() {
+        debug!("Dialog ended");
+    }
+    for event in levels_loaded.read() {
+        debug!("Loaded level {}", event.level);
+    }
+    for event in quests_started.read() {
+        debug!("Started quest {}", event.id);
+    }
+    for event in quests_completed.read() {
+        debug!("Completed quest {}", event.id);
+    }
+    for event in collectibles_collected.read() {
+        debug!(
+            "Collected {} of set {}, {} found so far",
+            event.id, event.set, event.found
+        );
+    }
+    for event in collectible_sets_completed.read() {
+        debug!("Completed collectible set {}", event.set);
+    }
+    for event in items_crafted.read() {
+        debug!("Crafted {} with recipe {}", event.item, event.recipe);
+    }
+    for event in players_leveled_up.read() {
+        debug!("Player reached level {}", event.level);
+    }
+    for event in switches_toggled.read() {
+        let state = if event.on { "on" } else { "off" };
+        debug!("Switch {} turned {state}", event.switch);
+    }
+}
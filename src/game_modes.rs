@@ -0,0 +1,32 @@
+use crate::{game_modes::time_trial::time_trial_plugin, GameState};
+use bevy::prelude::*;
+use seldom_fn_plugin::FnPluginExt;
+
+pub(crate) mod time_trial;
+
+/// Handles alternate ways to play a level on top of the regular game.
+/// The active mode is the [`GameMode`] state, which only matters while [`GameState::Playing`] and goes back
+/// to [`GameMode::Free`] whenever the player leaves the game. A mode should follow the same structure
+/// as [`time_trial_plugin`]:
+/// - Its systems run only in its state, i.e. with `run_if(in_state(GameMode::...))`.
+/// - Its state lives in a resource that is inserted `OnEnter` and removed `OnExit` of the mode, so that
+///   nothing is left behind when the mode ends, however it ends.
+/// - It ends by setting the next [`GameMode`] back to [`GameMode::Free`].
+pub(crate) fn game_modes_plugin(app: &mut App) {
+    app.add_state::<GameMode>()
+        .fn_plugin(time_trial_plugin)
+        .add_systems(OnExit(GameState::Playing), reset_game_mode);
+}
+
+#[derive(States, Default, Clone, Eq, PartialEq, Debug, Hash)]
+pub(crate) enum GameMode {
+    /// Exploring the level without any extra rules
+    #[default]
+    Free,
+    /// Racing through the checkpoint gates of the level, see [`time_trial_plugin`]
+    TimeTrial,
+}
+
+fn reset_game_mode(mut next_mode: ResMut<NextState<GameMode>>) {
+    next_mode.set(GameMode::Free);
+}
@@ -0,0 +1,365 @@
+use crate::{
+    file_system_interaction::storage::GameStorage,
+    game_events::PlayerDied,
+    game_modes::GameMode,
+    level_instantiation::{
+        levels::CurrentLevel,
+        markers::{Marker, MarkersAppExt},
+    },
+    movement::physics::CollisionLayer,
+    player_control::{
+        actions::{ActionsFrozen, UiAction},
+        player_embodiment::Player,
+    },
+    theme::UiTheme,
+    util::criteria::is_frozen,
+    world_interaction::triggers::{Trigger, TriggerEnter, TriggerKind, TriggerSystemSet},
+    GameState,
+};
+use anyhow::{Context, Result};
+use bevy::{ecs::world::EntityWorldMut, prelude::*, utils::HashMap};
+use bevy_egui::{egui, EguiContexts};
+use bevy_mod_sysfail::*;
+use bevy_xpbd_3d::prelude::*;
+use leafwing_input_manager::prelude::ActionState;
+use serde::{Deserialize, Serialize};
+
+const BEST_TIMES_KEY: &str = "records/time_trials.json";
+const GATE_WIDTH: f32 = 4.;
+const GATE_HEIGHT: f32 = 4.;
+const GATE_DEPTH: f32 = 0.5;
+
+/// An example of an alternate [`GameMode`]: racing through the checkpoint gates of a level against the clock.
+/// In Blender, objects named with a `[race_gate:<number>]` suffix become gates, which are passed in the order
+/// of their numbers. Walking through the first gate starts the time trial and the last one finishes it.
+/// While racing, the elapsed time and the gates passed are shown at the top of the screen
+/// and the next gate is highlighted. At the finish, a results screen shows the time and the best one so far.
+/// The best time of every level is kept in the [`GameStorage`]. Dying aborts the time trial.
+pub(crate) fn time_trial_plugin(app: &mut App) {
+    app.register_type::<RaceGate>()
+        .init_resource::<BestTimes>()
+        .register_marker("race_gate", insert_race_gate)
+        .add_systems(Startup, load_best_times)
+        .add_systems(
+            Update,
+            start_time_trial
+                .after(TriggerSystemSet)
+                .run_if(in_state(GameState::Playing).and_then(in_state(GameMode::Free))),
+        )
+        .add_systems(OnEnter(GameMode::TimeTrial), begin_time_trial)
+        .add_systems(
+            Update,
+            (
+                pass_race_gates.after(TriggerSystemSet),
+                abort_time_trial,
+                highlight_next_gate,
+                show_time_trial_timer.run_if(not(is_frozen)),
+                show_time_trial_results,
+            )
+                .chain()
+                .run_if(
+                    in_state(GameState::Playing)
+                        .and_then(in_state(GameMode::TimeTrial))
+                        .and_then(resource_exists::<TimeTrial>()),
+                ),
+        )
+        .add_systems(OnExit(GameMode::TimeTrial), end_time_trial);
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Component, Reflect, Serialize, Deserialize, Default,
+)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct RaceGate {
+    /// Gates are passed in the order of their numbers
+    pub(crate) number: u32,
+}
+
+/// The fastest time in seconds for every level, by level name.
+#[derive(Debug, Clone, PartialEq, Resource, Serialize, Deserialize, Default)]
+pub(crate) struct BestTimes(pub(crate) HashMap<String, f32>);
+
+/// The race in progress, only present in [`GameMode::TimeTrial`].
+#[derive(Debug, Clone, PartialEq, Resource)]
+pub(crate) struct TimeTrial {
+    /// Numbers of all gates, in the order they are passed
+    gates: Vec<u32>,
+    /// Index into `gates` of the gate to pass next
+    next: usize,
+    /// Seconds since the start gate was passed
+    elapsed: f32,
+    results: Option<TimeTrialResults>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TimeTrialResults {
+    time: f32,
+    /// The best time before this run, if there was one
+    previous_best: Option<f32>,
+}
+
+fn insert_race_gate(entity: &mut EntityWorldMut, marker: &Marker) -> Result<()> {
+    let number = marker
+        .argument(0)
+        .context("Expected [race_gate:<number>]")?
+        .parse()
+        .context("The number of a race gate must be a whole number")?;
+    entity.insert(RaceGate { number }).with_children(|parent| {
+        parent.spawn((
+            Name::new("Race Gate Trigger"),
+            SpatialBundle::from_transform(Transform::from_translation(Vec3::Y * GATE_HEIGHT / 2.)),
+            Collider::cuboid(GATE_WIDTH, GATE_HEIGHT, GATE_DEPTH),
+            CollisionLayer::trigger(),
+            Sensor,
+            Trigger::new(TriggerKind::Area),
+        ));
+    });
+    Ok(())
+}
+
+#[sysfail(log(level = "error"))]
+fn load_best_times(mut best_times: ResMut<BestTimes>, storage: Res<GameStorage>) -> Result<()> {
+    let Some(serialized) = storage.read_string(BEST_TIMES_KEY)? else {
+        return Ok(());
+    };
+    *best_times = serde_json::from_str(&serialized).context("Failed to parse best times")?;
+    Ok(())
+}
+
+/// The gate whose trigger `event` is about, if the player passed through it
+fn passed_gate(
+    event: &TriggerEnter,
+    parents: &Query<&Parent>,
+    gates: &Query<&RaceGate>,
+    players: &Query<(), With<Player>>,
+) -> Option<RaceGate> {
+    if !players.contains(event.other) {
+        return None;
+    }
+    let parent = parents.get(event.trigger).ok()?;
+    gates.get(parent.get()).ok().copied()
+}
+
+fn start_time_trial(
+    mut trigger_events: EventReader<TriggerEnter>,
+    parents: Query<&Parent>,
+    gates: Query<&RaceGate>,
+    players: Query<(), With<Player>>,
+    mut next_mode: ResMut<NextState<GameMode>>,
+) {
+    let Some(start) = gates.iter().map(|gate| gate.number).min() else {
+        return;
+    };
+    let started = trigger_events
+        .read()
+        .filter_map(|event| passed_gate(event, &parents, &gates, &players))
+        .any(|gate| gate.number == start);
+    if started {
+        next_mode.set(GameMode::TimeTrial);
+    }
+}
+
+fn begin_time_trial(
+    mut commands: Commands,
+    gates: Query<&RaceGate>,
+    mut next_mode: ResMut<NextState<GameMode>>,
+) {
+    let mut numbers: Vec<_> = gates.iter().map(|gate| gate.number).collect();
+    numbers.sort_unstable();
+    numbers.dedup();
+    if numbers.len() < 2 {
+        warn!("A time trial needs at least a start and a finish gate");
+        next_mode.set(GameMode::Free);
+        return;
+    }
+    info!("Started time trial");
+    commands.insert_resource(TimeTrial {
+        gates: numbers,
+        // The start gate was just passed
+        next: 1,
+        elapsed: 0.,
+        results: None,
+    });
+}
+
+#[sysfail(log(level = "error"))]
+fn pass_race_gates(
+    time: Res<Time<Virtual>>,
+    mut trigger_events: EventReader<TriggerEnter>,
+    parents: Query<&Parent>,
+    gates: Query<&RaceGate>,
+    players: Query<(), With<Player>>,
+    mut time_trial: ResMut<TimeTrial>,
+    mut best_times: ResMut<BestTimes>,
+    current_level: Res<CurrentLevel>,
+    storage: Res<GameStorage>,
+    mut freeze: ResMut<ActionsFrozen>,
+) -> Result<()> {
+    if time_trial.results.is_some() {
+        return Ok(());
+    }
+    time_trial.elapsed += time.delta_seconds();
+    for gate in trigger_events
+        .read()
+        .filter_map(|event| passed_gate(event, &parents, &gates, &players))
+    {
+        if time_trial.gates.get(time_trial.next) != Some(&gate.number) {
+            continue;
+        }
+        time_trial.next += 1;
+        if time_trial.next < time_trial.gates.len() {
+            continue;
+        }
+        let time = time_trial.elapsed;
+        let previous_best = best_times.0.get(&current_level.name).copied();
+        time_trial.results = Some(TimeTrialResults {
+            time,
+            previous_best,
+        });
+        freeze.freeze();
+        info!("Finished time trial in {}", format_time(time));
+        if previous_best.map_or(true, |best| time < best) {
+            best_times.0.insert(current_level.name.clone(), time);
+            let serialized = serde_json::to_string_pretty(best_times.as_ref())?;
+            storage
+                .write(BEST_TIMES_KEY, serialized.as_bytes())
+                .context("Failed to save best times")?;
+        }
+        break;
+    }
+    Ok(())
+}
+
+fn abort_time_trial(
+    mut death_events: EventReader<PlayerDied>,
+    mut next_mode: ResMut<NextState<GameMode>>,
+) {
+    if death_events.read().count() > 0 {
+        info!("Aborted time trial");
+        next_mode.set(GameMode::Free);
+    }
+}
+
+fn highlight_next_gate(
+    time_trial: Res<TimeTrial>,
+    gates: Query<(&RaceGate, &GlobalTransform)>,
+    mut gizmos: Gizmos,
+) {
+    let Some(next) = time_trial.gates.get(time_trial.next) else {
+        return;
+    };
+    for (gate, transform) in gates.iter() {
+        if gate.number != *next {
+            continue;
+        }
+        let center = transform.translation() + Vec3::Y * GATE_HEIGHT / 2.;
+        gizmos.circle(center, transform.forward(), GATE_HEIGHT / 2., Color::YELLOW);
+    }
+}
+
+fn show_time_trial_timer(
+    mut egui_contexts: EguiContexts,
+    time_trial: Res<TimeTrial>,
+    best_times: Res<BestTimes>,
+    current_level: Res<CurrentLevel>,
+    theme: Res<UiTheme>,
+) {
+    if time_trial.results.is_some() {
+        return;
+    }
+    let margin = theme.spacing.screen_margin;
+    egui::Area::new("Time Trial")
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0., margin))
+        .show(egui_contexts.ctx_mut(), |ui| {
+            ui.vertical_centered(|ui| {
+                ui.label(
+                    egui::RichText::new(format_time(time_trial.elapsed))
+                        .size(theme.text.heading)
+                        .color(theme.colors.text),
+                );
+                // The start gate does not count as one to pass
+                ui.label(
+                    egui::RichText::new(format!(
+                        "Gate {}/{}",
+                        time_trial.next,
+                        time_trial.gates.len() - 1
+                    ))
+                    .size(theme.text.small)
+                    .color(theme.colors.text),
+                );
+                if let Some(best) = best_times.0.get(&current_level.name) {
+                    ui.label(
+                        egui::RichText::new(format!("Best {}", format_time(*best)))
+                            .size(theme.text.small)
+                            .color(theme.colors.weak_text),
+                    );
+                }
+            });
+        });
+}
+
+fn show_time_trial_results(
+    mut egui_contexts: EguiContexts,
+    time_trial: Res<TimeTrial>,
+    actions: Query<&ActionState<UiAction>>,
+    mut next_mode: ResMut<NextState<GameMode>>,
+    theme: Res<UiTheme>,
+) {
+    let Some(results) = time_trial.results else {
+        return;
+    };
+    let mut close = actions.iter().any(|actions| {
+        actions.just_pressed(UiAction::Confirm) || actions.just_pressed(UiAction::Back)
+    });
+    egui::CentralPanel::default()
+        .frame(theme.overlay_frame())
+        .show(egui_contexts.ctx_mut(), |ui| {
+            ui.vertical_centered(|ui| {
+                ui.add_space(2. * theme.spacing.large);
+                ui.heading("Finished!");
+                ui.separator();
+                ui.label(
+                    egui::RichText::new(format_time(results.time))
+                        .size(theme.text.heading)
+                        .color(theme.colors.accent),
+                );
+                match results.previous_best {
+                    Some(best) if best <= results.time => {
+                        ui.label(format!("Best time: {}", format_time(best)));
+                    }
+                    Some(best) => {
+                        ui.label(format!(
+                            "New best time! {:.2} seconds faster than before",
+                            best - results.time
+                        ));
+                    }
+                    None => {
+                        ui.label("New best time!");
+                    }
+                }
+                ui.separator();
+                close |= ui.button("Continue").clicked();
+            });
+        });
+    if close {
+        next_mode.set(GameMode::Free);
+    }
+}
+
+fn end_time_trial(
+    mut commands: Commands,
+    time_trial: Option<Res<TimeTrial>>,
+    mut freeze: ResMut<ActionsFrozen>,
+) {
+    let showed_results = time_trial.is_some_and(|time_trial| time_trial.results.is_some());
+    if showed_results {
+        freeze.unfreeze();
+    }
+    commands.remove_resource::<TimeTrial>();
+}
+
+/// Formats seconds as minutes, seconds and hundredths, e.g. "1:05.32"
+fn format_time(seconds: f32) -> String {
+    let minutes = (seconds / 60.).floor();
+    format!("{minutes}:{:05.2}", seconds - minutes * 60.)
+}
@@ -0,0 +1,231 @@
+use crate::{
+    combat::{health::Health, respawn::DeathCount},
+    file_system_interaction::config::GameConfig,
+    level_instantiation::spawning::objects::mount::Mount,
+    menu::Settings,
+    movement::{character_controller::Stamina, vehicle::Vehicle},
+    player_control::{
+        actions::PlayerAction,
+        aiming::Aiming,
+        camera::{IngameCamera, IngameCameraKind},
+        player_embodiment::Player,
+        progression::Progression,
+    },
+    theme::UiTheme,
+    util::criteria::is_frozen,
+    world_interaction::{
+        crafting::CraftingStation,
+        dialog::DialogTarget,
+        elevators::ElevatorButton,
+        interactions_ui::{InteractionCandidates, InteractionOpportunity},
+        readables::Readable,
+        shop::{Coins, Vendor},
+    },
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use captions::captions_plugin;
+use damage_feedback::damage_feedback_plugin;
+use leafwing_input_manager::prelude::ActionState;
+use objectives::objectives_plugin;
+use seldom_fn_plugin::FnPluginExt;
+use serde::{Deserialize, Serialize};
+use tutorial::tutorial_plugin;
+use world_labels::world_labels_plugin;
+
+pub(crate) mod captions;
+pub(crate) mod damage_feedback;
+pub(crate) mod objectives;
+pub(crate) mod tutorial;
+pub(crate) mod widgets;
+pub(crate) mod world_labels;
+
+/// Draws the heads-up display while playing. The HUD is built from the widgets in [`widgets`]
+/// and is hidden whenever the player's actions are frozen, e.g. during dialogs and cutscenes.
+/// All sizes are in egui points, so they follow the UI scale set through `EguiSettings`.
+/// Labels anchored in the world are handled by [`world_labels_plugin`],
+/// subtitles and sound captions by [`captions_plugin`], which stay visible while frozen,
+/// hints teaching the controls by [`tutorial_plugin`], markers guiding the player to their objectives by [`objectives_plugin`]
+/// and hit indicators, the low health vignette and rumble by [`damage_feedback_plugin`].
+pub(crate) fn hud_plugin(app: &mut App) {
+    app.register_type::<Hotbar>()
+        .fn_plugin(world_labels_plugin)
+        .fn_plugin(captions_plugin)
+        .fn_plugin(tutorial_plugin)
+        .fn_plugin(objectives_plugin)
+        .fn_plugin(damage_feedback_plugin)
+        .add_systems(
+            Update,
+            (select_hotbar_slot, show_hud)
+                .chain()
+                .run_if(in_state(GameState::Playing).and_then(not(is_frozen))),
+        );
+}
+
+/// Items the player has quick access to. Slots are selected with the number keys.
+#[derive(Debug, Clone, Eq, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Hotbar {
+    pub(crate) slots: Vec<Option<String>>,
+    pub(crate) selected: usize,
+}
+
+impl Default for Hotbar {
+    fn default() -> Self {
+        Self {
+            slots: vec![None; 9],
+            selected: 0,
+        }
+    }
+}
+
+impl Hotbar {
+    /// Puts the item into the first empty slot and returns its index, or `None` if all slots are taken.
+    pub(crate) fn add(&mut self, item: String) -> Option<usize> {
+        let index = self.slots.iter().position(Option::is_none)?;
+        self.slots[index] = Some(item);
+        Some(index)
+    }
+}
+
+fn select_hotbar_slot(mut players: Query<(&ActionState<PlayerAction>, &mut Hotbar)>) {
+    const SLOT_ACTIONS: [PlayerAction; 9] = [
+        PlayerAction::NumberedChoice1,
+        PlayerAction::NumberedChoice2,
+        PlayerAction::NumberedChoice3,
+        PlayerAction::NumberedChoice4,
+        PlayerAction::NumberedChoice5,
+        PlayerAction::NumberedChoice6,
+        PlayerAction::NumberedChoice7,
+        PlayerAction::NumberedChoice8,
+        PlayerAction::NumberedChoice9,
+    ];
+    for (actions, mut hotbar) in players.iter_mut() {
+        let slot_count = hotbar.slots.len();
+        if let Some(index) = SLOT_ACTIONS
+            .iter()
+            .take(slot_count)
+            .position(|action| actions.just_pressed(*action))
+        {
+            hotbar.selected = index;
+        }
+    }
+}
+
+fn show_hud(
+    mut egui_contexts: EguiContexts,
+    players: Query<
+        (
+            Option<&Health>,
+            Option<&Stamina>,
+            Option<&Hotbar>,
+            Option<&Progression>,
+            Has<Aiming>,
+        ),
+        With<Player>,
+    >,
+    config: Res<GameConfig>,
+    cameras: Query<&IngameCamera>,
+    interaction_opportunity: Res<InteractionOpportunity>,
+    interaction_candidates: Res<InteractionCandidates>,
+    dialog_targets: Query<&DialogTarget>,
+    interactables: Query<(
+        Has<Vehicle>,
+        Has<Mount>,
+        Has<Readable>,
+        Has<Vendor>,
+        Has<CraftingStation>,
+        Option<&ElevatorButton>,
+    )>,
+    coins: Res<Coins>,
+    deaths: Res<DeathCount>,
+    settings: Res<Settings>,
+    theme: Res<UiTheme>,
+) {
+    let Some((health, stamina, hotbar, progression, is_aiming)) = players.iter().next() else {
+        return;
+    };
+    let ctx = egui_contexts.ctx_mut();
+    let margin = theme.spacing.screen_margin;
+
+    egui::Area::new("HUD Status")
+        .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(margin, -margin))
+        .show(ctx, |ui| {
+            if let Some(health) = health {
+                widgets::health_bar(ui, &theme, health);
+            }
+            if let Some(stamina) = stamina {
+                widgets::stamina_bar(ui, &theme, stamina);
+            }
+            if let Some(progression) = progression {
+                widgets::experience_bar(ui, &theme, progression, &config.progression);
+            }
+            ui.label(
+                egui::RichText::new(format!("Coins: {}", coins.0))
+                    .size(theme.text.small)
+                    .color(theme.colors.text),
+            );
+        });
+
+    if let Some(hotbar) = hotbar {
+        egui::Area::new("HUD Hotbar")
+            .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0., -margin))
+            .show(ctx, |ui| {
+                widgets::hotbar(ui, &theme, hotbar);
+            });
+    }
+
+    if settings.show_death_counter {
+        egui::Area::new("HUD Deaths")
+            .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-margin, margin))
+            .show(ctx, |ui| {
+                ui.label(
+                    egui::RichText::new(format!("Deaths: {}", deaths.0))
+                        .size(theme.text.body)
+                        .color(theme.colors.text),
+                );
+            });
+    }
+
+    let is_first_person = cameras
+        .iter()
+        .any(|camera| camera.kind == IngameCameraKind::FirstPerson);
+    if is_first_person || is_aiming {
+        widgets::crosshair(ctx, &theme);
+    }
+
+    let label = |target: Entity| {
+        if let Ok(dialog_target) = dialog_targets.get(target) {
+            return Some(format!("Talk to {}", dialog_target.speaker));
+        }
+        let (is_vehicle, is_mount, is_readable, is_vendor, is_crafting_station, elevator_button) =
+            interactables.get(target).ok()?;
+        if is_vehicle {
+            Some("Drive".to_string())
+        } else if is_mount {
+            Some("Ride".to_string())
+        } else if is_readable {
+            Some("Read".to_string())
+        } else if is_vendor {
+            Some("Trade".to_string())
+        } else if is_crafting_station {
+            Some("Craft".to_string())
+        } else {
+            elevator_button.map(|button| format!("Call elevator to floor {}", button.floor))
+        }
+    };
+    let Some(selected) = interaction_opportunity.0 else {
+        return;
+    };
+    if interaction_candidates.0.len() > 1 {
+        let labels: Vec<_> = interaction_candidates
+            .0
+            .iter()
+            .filter_map(|&target| Some((label(target)?, target == selected)))
+            .collect();
+        widgets::interaction_candidates(ctx, &theme, &labels);
+    } else if let Some(label) = label(selected) {
+        widgets::interaction_prompt(ctx, &theme, &format!("E: {label}"));
+    }
+}
@@ -0,0 +1,194 @@
+use crate::{
+    game_events::DialogEnded,
+    menu::{CaptionSettings, Settings},
+    theme::UiTheme,
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use bevy_yarnspinner::events::PresentLineEvent;
+
+/// How long a caption stays up at minimum
+const BASE_DURATION: f32 = 2.;
+/// Additional time per character for reading longer captions
+const DURATION_PER_CHARACTER: f32 = 0.05;
+const MAX_CAPTIONS: usize = 3;
+
+/// Shows subtitles for dialog lines and captions for important sounds at the bottom of the screen.
+/// Captions are requested with [`CaptionEvent`]s. Dialog lines are captioned automatically.
+/// What is shown and how is configured through [`CaptionSettings`].
+pub(crate) fn captions_plugin(app: &mut App) {
+    app.add_event::<CaptionEvent>()
+        .init_resource::<ActiveCaptions>()
+        .add_systems(
+            Update,
+            (caption_dialog_lines, update_captions, show_captions)
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+#[derive(Debug, Clone, PartialEq, Event)]
+pub(crate) struct CaptionEvent {
+    pub(crate) speaker: Option<String>,
+    pub(crate) text: String,
+    pub(crate) kind: CaptionKind,
+    /// How long to show the caption. Derived from the text length if `None`.
+    pub(crate) duration: Option<f32>,
+}
+
+impl CaptionEvent {
+    pub(crate) fn line(speaker: Option<String>, text: impl Into<String>) -> Self {
+        Self {
+            speaker,
+            text: text.into(),
+            kind: CaptionKind::Dialog,
+            duration: None,
+        }
+    }
+
+    /// A caption describing a sound, by convention written in square brackets, e.g. "[door creaks]".
+    pub(crate) fn sound(text: impl Into<String>) -> Self {
+        Self {
+            speaker: None,
+            text: text.into(),
+            kind: CaptionKind::Sound,
+            duration: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum CaptionKind {
+    Dialog,
+    Sound,
+}
+
+impl CaptionKind {
+    fn is_enabled(self, settings: &CaptionSettings) -> bool {
+        match self {
+            CaptionKind::Dialog => settings.dialog,
+            CaptionKind::Sound => settings.sounds,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Resource, Default)]
+struct ActiveCaptions(Vec<(CaptionEvent, f32)>);
+
+fn caption_dialog_lines(
+    mut line_events: EventReader<PresentLineEvent>,
+    mut dialogs_ended: EventReader<DialogEnded>,
+    mut caption_events: EventWriter<CaptionEvent>,
+    mut captions: ResMut<ActiveCaptions>,
+) {
+    for event in line_events.read() {
+        let line = &event.line;
+        caption_events.send(CaptionEvent::line(
+            line.character_name().map(str::to_string),
+            line.text_without_character_name(),
+        ));
+    }
+    if dialogs_ended.read().count() > 0 {
+        captions
+            .0
+            .retain(|(caption, _)| caption.kind != CaptionKind::Dialog);
+    }
+}
+
+fn update_captions(
+    time: Res<Time<Virtual>>,
+    settings: Res<Settings>,
+    mut caption_events: EventReader<CaptionEvent>,
+    mut captions: ResMut<ActiveCaptions>,
+) {
+    for caption in caption_events.read() {
+        if !caption.kind.is_enabled(&settings.captions) {
+            continue;
+        }
+        // A new dialog line replaces the previous one instead of stacking up
+        if caption.kind == CaptionKind::Dialog {
+            captions
+                .0
+                .retain(|(active, _)| active.kind != CaptionKind::Dialog);
+        }
+        let duration = caption.duration.unwrap_or_else(|| {
+            BASE_DURATION + caption.text.chars().count() as f32 * DURATION_PER_CHARACTER
+        });
+        captions.0.push((caption.clone(), duration));
+    }
+    let dt = time.delta_seconds();
+    for (_, remaining) in captions.0.iter_mut() {
+        *remaining -= dt;
+    }
+    captions.0.retain(|(caption, remaining)| {
+        *remaining > 0. && caption.kind.is_enabled(&settings.captions)
+    });
+    let overflow = captions.0.len().saturating_sub(MAX_CAPTIONS);
+    captions.0.drain(..overflow);
+}
+
+fn show_captions(
+    captions: Res<ActiveCaptions>,
+    settings: Res<Settings>,
+    theme: Res<UiTheme>,
+    mut egui_contexts: EguiContexts,
+) {
+    if captions.0.is_empty() {
+        return;
+    }
+    let caption_settings = &settings.captions;
+    let background =
+        egui::Color32::from_black_alpha((caption_settings.background_opacity * 255.) as u8);
+    egui::Area::new("Captions")
+        .anchor(
+            egui::Align2::CENTER_BOTTOM,
+            egui::vec2(0., -5. * theme.spacing.screen_margin),
+        )
+        .order(egui::Order::Foreground)
+        .interactable(false)
+        .show(egui_contexts.ctx_mut(), |ui| {
+            ui.vertical_centered(|ui| {
+                for (caption, _) in captions.0.iter() {
+                    egui::Frame::none()
+                        .fill(background)
+                        .rounding(theme.panel.rounding)
+                        .inner_margin(egui::style::Margin::symmetric(8., 4.))
+                        .show(ui, |ui| {
+                            ui.label(caption_text(caption, caption_settings, &theme));
+                        });
+                }
+            });
+        });
+}
+
+fn caption_text(
+    caption: &CaptionEvent,
+    settings: &CaptionSettings,
+    theme: &UiTheme,
+) -> egui::text::LayoutJob {
+    let font = egui::FontId::proportional(settings.text_size);
+    let mut job = egui::text::LayoutJob::default();
+    if let Some(speaker) = &caption.speaker {
+        job.append(
+            &format!("{speaker}: "),
+            0.,
+            egui::TextFormat::simple(font.clone(), speaker_color(speaker)),
+        );
+    }
+    let color = match caption.kind {
+        CaptionKind::Dialog => theme.colors.text,
+        CaptionKind::Sound => theme.colors.weak_text,
+    };
+    job.append(&caption.text, 0., egui::TextFormat::simple(font, color));
+    job
+}
+
+/// Gives every speaker a stable color that is readable on dark backgrounds.
+fn speaker_color(speaker: &str) -> egui::Color32 {
+    let hash = speaker.bytes().fold(0_u32, |hash, byte| {
+        hash.wrapping_mul(31).wrapping_add(byte as u32)
+    });
+    let hue = (hash % 360) as f32 / 360.;
+    egui::ecolor::Hsva::new(hue, 0.5, 1., 1.).into()
+}
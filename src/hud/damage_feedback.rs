@@ -0,0 +1,235 @@
+use crate::{
+    combat::health::Health,
+    game_events::PlayerDamaged,
+    menu::Settings,
+    player_control::{camera::IngameCamera, player_embodiment::Player},
+    theme::UiTheme,
+    GameState,
+};
+use bevy::{
+    input::gamepad::{GamepadRumbleIntensity, GamepadRumbleRequest},
+    prelude::*,
+};
+use bevy_egui::{egui, EguiContexts};
+use std::time::Duration;
+
+/// Seconds a hit indicator stays on screen
+const INDICATOR_DURATION: f32 = 1.5;
+/// Distance of the hit indicators from the screen center as a fraction of the smaller screen side
+const INDICATOR_RADIUS: f32 = 0.2;
+/// Length of a hit indicator in egui points
+const INDICATOR_SIZE: f32 = 24.;
+/// Half the angle a hit indicator covers, in radians
+const INDICATOR_SPREAD: f32 = 0.15;
+/// Below this fraction of their health, the screen edges start turning red
+const LOW_HEALTH_FRACTION: f32 = 0.3;
+/// Width of the vignette as a fraction of the smaller screen side
+const VIGNETTE_WIDTH: f32 = 0.25;
+/// Heartbeats per second of the low health vignette
+const VIGNETTE_PULSE_RATE: f32 = 1.2;
+/// Seconds the screen edges flash after a hit
+const HIT_FLASH_DURATION: f32 = 0.3;
+const RUMBLE_DURATION: f32 = 0.25;
+
+/// Gives feedback when the player is hurt: a marker around the screen center points to where every hit came from
+/// and follows the attacker while it fades, the screen edges flash red and stay red while health is low,
+/// and connected gamepads rumble, unless turned off in the accessibility settings.
+/// Everything is driven by [`PlayerDamaged`] events.
+pub(crate) fn damage_feedback_plugin(app: &mut App) {
+    app.init_resource::<DamageFeedback>().add_systems(
+        Update,
+        (receive_damage, rumble_on_damage, show_damage_feedback)
+            .chain()
+            .run_if(in_state(GameState::Playing)),
+    );
+}
+
+#[derive(Debug, Clone, PartialEq, Resource, Default)]
+struct DamageFeedback {
+    indicators: Vec<HitIndicator>,
+    /// Seconds since the last hit, `None` if the flash is over
+    since_hit: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct HitIndicator {
+    attacker: Option<Entity>,
+    /// Horizontal direction from the player towards where the hit came from, used if the attacker is gone
+    direction: Vec3,
+    age: f32,
+}
+
+fn receive_damage(
+    time: Res<Time<Virtual>>,
+    mut player_damaged: EventReader<PlayerDamaged>,
+    mut feedback: ResMut<DamageFeedback>,
+) {
+    let dt = time.delta_seconds();
+    for indicator in feedback.indicators.iter_mut() {
+        indicator.age += dt;
+    }
+    feedback
+        .indicators
+        .retain(|indicator| indicator.age < INDICATOR_DURATION);
+    feedback.since_hit = feedback
+        .since_hit
+        .map(|since_hit| since_hit + dt)
+        .filter(|&since_hit| since_hit < HIT_FLASH_DURATION);
+
+    for event in player_damaged.read() {
+        feedback.since_hit = Some(0.);
+        // The event tells where the hit pushes the player, which is away from where it came from
+        let direction = Vec3::new(-event.direction.x, 0., -event.direction.z).normalize_or_zero();
+        if direction == Vec3::ZERO && event.attacker.is_none() {
+            // E.g. fall damage, which comes from nowhere in particular
+            continue;
+        }
+        feedback.indicators.push(HitIndicator {
+            attacker: event.attacker,
+            direction,
+            age: 0.,
+        });
+    }
+}
+
+fn rumble_on_damage(
+    mut player_damaged: EventReader<PlayerDamaged>,
+    settings: Res<Settings>,
+    gamepads: Res<Gamepads>,
+    mut rumble_requests: EventWriter<GamepadRumbleRequest>,
+) {
+    for event in player_damaged.read() {
+        if !settings.accessibility.rumble {
+            continue;
+        }
+        let strength = (2. * event.amount / event.max_health).clamp(0.2, 1.);
+        for gamepad in gamepads.iter() {
+            rumble_requests.send(GamepadRumbleRequest::Add {
+                gamepad,
+                duration: Duration::from_secs_f32(RUMBLE_DURATION),
+                intensity: GamepadRumbleIntensity {
+                    strong_motor: strength,
+                    weak_motor: strength,
+                },
+            });
+        }
+    }
+}
+
+fn show_damage_feedback(
+    time: Res<Time<Virtual>>,
+    mut egui_contexts: EguiContexts,
+    feedback: Res<DamageFeedback>,
+    players: Query<(&GlobalTransform, Option<&Health>), With<Player>>,
+    cameras: Query<&GlobalTransform, With<IngameCamera>>,
+    transforms: Query<&GlobalTransform>,
+    settings: Res<Settings>,
+    theme: Res<UiTheme>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("show_damage_feedback").entered();
+    let Some((player_transform, health)) = players.iter().next() else {
+        return;
+    };
+    let ctx = egui_contexts.ctx_mut();
+    let screen = ctx.screen_rect();
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Background,
+        egui::Id::new("damage_feedback"),
+    ));
+    let short_side = screen.width().min(screen.height());
+
+    let low_health = health.map_or(0., |health| {
+        let fraction = health.current / health.max.max(f32::EPSILON);
+        (1. - fraction / LOW_HEALTH_FRACTION).clamp(0., 1.)
+    });
+    let pulse = if settings.accessibility.reduce_motion {
+        1.
+    } else {
+        0.75 + 0.25 * (time.elapsed_seconds() * VIGNETTE_PULSE_RATE * std::f32::consts::TAU).sin()
+    };
+    let flash = feedback
+        .since_hit
+        .map_or(0., |since_hit| 0.6 * (1. - since_hit / HIT_FLASH_DURATION));
+    let vignette = (0.6 * low_health * pulse).max(flash);
+    if vignette > 0. {
+        draw_vignette(
+            &painter,
+            screen,
+            short_side * VIGNETTE_WIDTH,
+            theme.colors.damage.linear_multiply(vignette),
+        );
+    }
+
+    let Some(camera_transform) = cameras.iter().next() else {
+        return;
+    };
+    let forward = Vec3::new(
+        camera_transform.forward().x,
+        0.,
+        camera_transform.forward().z,
+    )
+    .normalize_or_zero();
+    let right =
+        Vec3::new(camera_transform.right().x, 0., camera_transform.right().z).normalize_or_zero();
+    let center = screen.center();
+    let radius = short_side * INDICATOR_RADIUS;
+    for indicator in feedback.indicators.iter() {
+        let direction = indicator
+            .attacker
+            .and_then(|attacker| transforms.get(attacker).ok())
+            .map(|attacker| {
+                let offset = attacker.translation() - player_transform.translation();
+                Vec3::new(offset.x, 0., offset.z).normalize_or_zero()
+            })
+            .filter(|direction| *direction != Vec3::ZERO)
+            .unwrap_or(indicator.direction);
+        if direction == Vec3::ZERO {
+            continue;
+        }
+        // Zero is straight ahead, i.e. up on the screen, and the angle grows clockwise
+        let angle = direction.dot(right).atan2(direction.dot(forward));
+        let point =
+            |angle: f32, distance: f32| center + egui::vec2(angle.sin(), -angle.cos()) * distance;
+        let alpha = 1. - indicator.age / INDICATOR_DURATION;
+        painter.add(egui::Shape::convex_polygon(
+            vec![
+                point(angle, radius + INDICATOR_SIZE),
+                point(angle + INDICATOR_SPREAD, radius),
+                point(angle - INDICATOR_SPREAD, radius),
+            ],
+            theme.colors.damage.linear_multiply(alpha),
+            egui::Stroke::NONE,
+        ));
+    }
+}
+
+/// Fades from `color` at the screen edges to transparent `width` points further in
+fn draw_vignette(painter: &egui::Painter, screen: egui::Rect, width: f32, color: egui::Color32) {
+    let inner = screen.shrink(width);
+    let outer_corners = [
+        screen.left_top(),
+        screen.right_top(),
+        screen.right_bottom(),
+        screen.left_bottom(),
+    ];
+    let inner_corners = [
+        inner.left_top(),
+        inner.right_top(),
+        inner.right_bottom(),
+        inner.left_bottom(),
+    ];
+    let mut mesh = egui::Mesh::default();
+    for (outer, inner) in outer_corners.into_iter().zip(inner_corners) {
+        mesh.colored_vertex(outer, color);
+        mesh.colored_vertex(inner, egui::Color32::TRANSPARENT);
+    }
+    // One quad per screen edge between consecutive corners
+    for edge in 0..4 {
+        let current = 2 * edge;
+        let next = 2 * ((edge + 1) % 4);
+        mesh.add_triangle(current, next, current + 1);
+        mesh.add_triangle(next, next + 1, current + 1);
+    }
+    painter.add(mesh);
+}
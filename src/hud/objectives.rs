@@ -0,0 +1,243 @@
+use crate::{
+    game_events::{QuestCompleted, QuestStarted},
+    level_instantiation::markers::{Marker, MarkersAppExt},
+    menu::Settings,
+    movement::navigation::Navigation,
+    player_control::{camera::IngameCamera, player_embodiment::Player},
+    theme::UiTheme,
+    util::criteria::is_frozen,
+    GameState,
+};
+use anyhow::{Context, Result};
+use bevy::{ecs::world::EntityWorldMut, prelude::*};
+use bevy_egui::{egui, EguiContexts, EguiSettings};
+use serde::{Deserialize, Serialize};
+
+/// Distance in egui points between the screen edge and markers clamped to it
+const EDGE_MARGIN: f32 = 40.;
+const MARKER_RADIUS: f32 = 8.;
+/// Seconds between recalculations of the breadcrumb trail
+const BREADCRUMB_INTERVAL: f32 = 0.5;
+/// Distance between two breadcrumbs along the path
+const BREADCRUMB_SPACING: f32 = 1.5;
+/// Breadcrumbs are only drawn this far ahead of the player
+const BREADCRUMB_RANGE: f32 = 30.;
+
+/// Points the player towards the objectives of their active quests.
+/// Quests are started by dialogs with `<<start_quest id>>`, which sends a [`QuestStarted`], and end with a [`QuestCompleted`].
+/// The [`ActiveQuests`] are saved with the game.
+/// In Blender, objects named with an `[objective:<quest>]` suffix become [`ObjectiveMarker`]s, which are shown
+/// as icons with their distance while their quest is active. Icons of objectives that are off-screen or behind the camera
+/// stick to the edge of the screen in their direction.
+/// When enabled in the settings, a breadcrumb trail along the navmesh leads to the closest objective.
+pub(crate) fn objectives_plugin(app: &mut App) {
+    app.register_type::<ObjectiveMarker>()
+        .register_type::<ActiveQuests>()
+        .init_resource::<ActiveQuests>()
+        .init_resource::<Breadcrumbs>()
+        .register_marker("objective", insert_objective_marker)
+        .add_systems(
+            Update,
+            (
+                track_quests,
+                update_breadcrumbs,
+                (show_objective_markers, draw_breadcrumbs).run_if(not(is_frozen)),
+            )
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(OnExit(GameState::Playing), reset_quests);
+}
+
+/// A place the player should go to while the quest is active.
+#[derive(Debug, Clone, PartialEq, Eq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct ObjectiveMarker {
+    pub(crate) quest: String,
+}
+
+/// The IDs of the quests the player has started but not completed yet.
+#[derive(Debug, Clone, PartialEq, Eq, Resource, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Resource, Serialize, Deserialize)]
+pub(crate) struct ActiveQuests(pub(crate) Vec<String>);
+
+impl ActiveQuests {
+    pub(crate) fn contains(&self, quest: &str) -> bool {
+        self.0.iter().any(|active| active == quest)
+    }
+}
+
+/// Points along the navmesh path to the closest objective and the seconds until it is recalculated
+#[derive(Debug, Clone, PartialEq, Resource, Default)]
+struct Breadcrumbs {
+    points: Vec<Vec3>,
+    cooldown: f32,
+}
+
+fn insert_objective_marker(entity: &mut EntityWorldMut, marker: &Marker) -> Result<()> {
+    let quest = marker.argument(0).context("Expected [objective:<quest>]")?;
+    entity.insert(ObjectiveMarker {
+        quest: quest.to_string(),
+    });
+    Ok(())
+}
+
+fn track_quests(
+    mut started_events: EventReader<QuestStarted>,
+    mut completed_events: EventReader<QuestCompleted>,
+    mut active_quests: ResMut<ActiveQuests>,
+) {
+    for event in started_events.read() {
+        if !active_quests.contains(&event.id) {
+            active_quests.0.push(event.id.clone());
+        }
+    }
+    for event in completed_events.read() {
+        active_quests.0.retain(|quest| *quest != event.id);
+    }
+}
+
+fn update_breadcrumbs(
+    time: Res<Time<Virtual>>,
+    settings: Res<Settings>,
+    mut breadcrumbs: ResMut<Breadcrumbs>,
+    players: Query<&Transform, With<Player>>,
+    objectives: Query<(&ObjectiveMarker, &GlobalTransform)>,
+    active_quests: Res<ActiveQuests>,
+    navigation: Navigation,
+) {
+    breadcrumbs.cooldown -= time.delta_seconds();
+    if !settings.show_breadcrumbs {
+        breadcrumbs.points.clear();
+        return;
+    }
+    if breadcrumbs.cooldown > 0. {
+        return;
+    }
+    breadcrumbs.cooldown = BREADCRUMB_INTERVAL;
+    breadcrumbs.points.clear();
+    let Some(from) = players.iter().next().map(|transform| transform.translation) else {
+        return;
+    };
+    let Some(to) = objectives
+        .iter()
+        .filter(|(objective, _)| active_quests.contains(&objective.quest))
+        .map(|(_, transform)| transform.translation())
+        .min_by(|a, b| {
+            a.distance_squared(from)
+                .total_cmp(&b.distance_squared(from))
+        })
+    else {
+        return;
+    };
+    let Some(path) = navigation.find_path(from, to) else {
+        return;
+    };
+    // Drop breadcrumbs at regular intervals along the path
+    let mut travelled = 0.;
+    let mut next_crumb = BREADCRUMB_SPACING;
+    for segment in path.windows(2) {
+        let (start, end) = (segment[0], segment[1]);
+        let length = start.distance(end);
+        if length <= 0. {
+            continue;
+        }
+        while next_crumb <= travelled + length && next_crumb <= BREADCRUMB_RANGE {
+            let point = start.lerp(end, (next_crumb - travelled) / length);
+            breadcrumbs.points.push(point);
+            next_crumb += BREADCRUMB_SPACING;
+        }
+        travelled += length;
+    }
+}
+
+fn draw_breadcrumbs(breadcrumbs: Res<Breadcrumbs>, mut gizmos: Gizmos) {
+    for point in &breadcrumbs.points {
+        gizmos.circle(*point + Vec3::Y * 0.05, Vec3::Y, 0.15, Color::GOLD);
+    }
+}
+
+fn show_objective_markers(
+    mut egui_contexts: EguiContexts,
+    egui_settings: Res<EguiSettings>,
+    cameras: Query<(&Camera, &GlobalTransform), With<IngameCamera>>,
+    players: Query<&Transform, With<Player>>,
+    objectives: Query<(&ObjectiveMarker, &GlobalTransform)>,
+    active_quests: Res<ActiveQuests>,
+    theme: Res<UiTheme>,
+) {
+    let Some((camera, camera_transform)) = cameras.iter().next() else {
+        return;
+    };
+    let Some(player_position) = players.iter().next().map(|transform| transform.translation) else {
+        return;
+    };
+    let scale_factor = egui_settings.scale_factor as f32;
+    let ctx = egui_contexts.ctx_mut();
+    let bounds = ctx.screen_rect().shrink(EDGE_MARGIN);
+    let painter = ctx.layer_painter(egui::LayerId::background());
+    for (objective, transform) in objectives.iter() {
+        if !active_quests.contains(&objective.quest) {
+            continue;
+        }
+        let position = transform.translation();
+        let (screen_position, clamped) =
+            project_to_bounds(camera, camera_transform, position, bounds, scale_factor);
+        painter.circle_filled(screen_position, MARKER_RADIUS, theme.colors.marker);
+        if clamped {
+            let direction = (screen_position - bounds.center()).normalized();
+            painter.arrow(
+                screen_position + direction * MARKER_RADIUS,
+                direction * MARKER_RADIUS,
+                egui::Stroke::new(2., theme.colors.marker),
+            );
+        }
+        painter.text(
+            screen_position + egui::vec2(0., MARKER_RADIUS + 2.),
+            egui::Align2::CENTER_TOP,
+            format!("{:.0} m", player_position.distance(position)),
+            egui::FontId::proportional(theme.text.small),
+            theme.colors.text,
+        );
+    }
+}
+
+/// Projects a world position onto the screen, clamped to `bounds`. Also returns whether it had to be clamped.
+fn project_to_bounds(
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    position: Vec3,
+    bounds: egui::Rect,
+    scale_factor: f32,
+) -> (egui::Pos2, bool) {
+    let center = bounds.center();
+    let local = camera_transform
+        .compute_matrix()
+        .inverse()
+        .transform_point3(position);
+    // The camera looks along -Z, so anything with a positive Z is behind it
+    let direction = if local.z < 0. {
+        let Some(screen_position) = camera.world_to_viewport(camera_transform, position) else {
+            return (center, true);
+        };
+        let screen_position = egui::pos2(
+            screen_position.x / scale_factor,
+            screen_position.y / scale_factor,
+        );
+        if bounds.contains(screen_position) {
+            return (screen_position, false);
+        }
+        screen_position - center
+    } else {
+        // Objectives behind the camera are shown at the bottom edge, on their side
+        egui::vec2(local.x, local.y.abs().max(0.01))
+    };
+    let half_size = bounds.size() / 2.;
+    let scale = (half_size.x / direction.x.abs()).min(half_size.y / direction.y.abs());
+    (center + direction * scale, true)
+}
+
+fn reset_quests(mut active_quests: ResMut<ActiveQuests>, mut breadcrumbs: ResMut<Breadcrumbs>) {
+    *active_quests = default();
+    *breadcrumbs = default();
+}
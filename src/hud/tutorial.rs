@@ -0,0 +1,306 @@
+use crate::{
+    level_instantiation::markers::{Marker, MarkersAppExt},
+    movement::physics::CollisionLayer,
+    player_control::{
+        actions::{ActionsFrozen, PlayerAction},
+        player_embodiment::Player,
+    },
+    theme::UiTheme,
+    world_interaction::{
+        interactions_ui::InteractionOpportunity,
+        triggers::{Trigger, TriggerEnter, TriggerKind, TriggerSystemSet},
+    },
+    GameState,
+};
+use anyhow::{Context, Result};
+use bevy::{ecs::world::EntityWorldMut, prelude::*, utils::HashSet};
+use bevy_egui::{egui, EguiContexts};
+use bevy_xpbd_3d::prelude::*;
+use leafwing_input_manager::{
+    prelude::*,
+    user_input::{InputKind, UserInput},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Seconds a hint stays up unless the player does what it says
+const HINT_DURATION: f32 = 6.;
+
+/// The hints that can be shown. `{input}` is replaced with whatever the action is bound to.
+const HINTS: &[TutorialHint] = &[
+    TutorialHint {
+        id: "move",
+        text: "Use {input} to move",
+        action: PlayerAction::Move,
+    },
+    TutorialHint {
+        id: "jump",
+        text: "Press {input} to jump",
+        action: PlayerAction::Jump,
+    },
+    TutorialHint {
+        id: "sprint",
+        text: "Hold {input} to sprint",
+        action: PlayerAction::Sprint,
+    },
+    TutorialHint {
+        id: "crouch",
+        text: "Press {input} to crouch",
+        action: PlayerAction::Crouch,
+    },
+    TutorialHint {
+        id: "interact",
+        text: "Press {input} to interact",
+        action: PlayerAction::Interact,
+    },
+    TutorialHint {
+        id: "throw",
+        text: "Hold {input} to aim a throw and release it to throw",
+        action: PlayerAction::Throw,
+    },
+];
+
+/// Teaches the controls with short hints like "Press Space to jump" the first time they become relevant.
+/// Hints are requested with [`ShowHint`] events. Some are requested automatically, like moving once the player
+/// can move and interacting once something can be interacted with. In Blender, objects named with a
+/// `[hint:<id>]` suffix become trigger volumes the size of their scale that show the hint when the player walks in.
+/// Every hint is shown only once per playthrough, since the seen ones are kept in [`SeenHints`], which is saved.
+/// The input in a hint is looked up in the player's current [`InputMap`], so hints always show the actual binding,
+/// and gamepad buttons are preferred while a gamepad is connected.
+pub(crate) fn tutorial_plugin(app: &mut App) {
+    app.register_type::<HintArea>()
+        .register_type::<SeenHints>()
+        .init_resource::<SeenHints>()
+        .init_resource::<HintQueue>()
+        .add_event::<ShowHint>()
+        .register_marker("hint", insert_hint_area)
+        .add_systems(
+            Update,
+            (
+                request_contextual_hints,
+                enter_hint_areas.after(TriggerSystemSet),
+                queue_hints,
+                show_hints,
+            )
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(OnExit(GameState::Playing), reset_hints);
+}
+
+/// Shows the hint with the given ID from [`HINTS`] unless it has been seen before.
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub(crate) struct ShowHint {
+    pub(crate) id: String,
+}
+
+/// The IDs of the hints the player has already seen in this playthrough.
+#[derive(Debug, Clone, PartialEq, Eq, Resource, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Resource, Serialize, Deserialize)]
+pub(crate) struct SeenHints(HashSet<String>);
+
+/// A trigger volume that shows a hint when the player walks in.
+#[derive(Debug, Clone, PartialEq, Eq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct HintArea {
+    pub(crate) hint: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TutorialHint {
+    id: &'static str,
+    text: &'static str,
+    action: PlayerAction,
+}
+
+#[derive(Debug, Clone, PartialEq, Resource, Default)]
+struct HintQueue {
+    /// The hint on screen and the seconds it has left
+    current: Option<(&'static TutorialHint, f32)>,
+    waiting: VecDeque<&'static TutorialHint>,
+}
+
+fn insert_hint_area(entity: &mut EntityWorldMut, marker: &Marker) -> Result<()> {
+    let hint = marker.argument(0).context("Expected [hint:<id>]")?;
+    entity.insert((
+        HintArea {
+            hint: hint.to_string(),
+        },
+        // Scaled by the object's transform
+        Collider::cuboid(1., 1., 1.),
+        Sensor,
+        CollisionLayer::trigger(),
+        Trigger::new(TriggerKind::Area),
+        Visibility::Hidden,
+    ));
+    Ok(())
+}
+
+fn request_contextual_hints(
+    players: Query<(), With<Player>>,
+    frozen: Res<ActionsFrozen>,
+    interaction_opportunity: Res<InteractionOpportunity>,
+    seen: Res<SeenHints>,
+    mut hint_events: EventWriter<ShowHint>,
+) {
+    if players.is_empty() || frozen.is_frozen() {
+        return;
+    }
+    let mut request = |id: &str| {
+        if !seen.0.contains(id) {
+            hint_events.send(ShowHint { id: id.to_string() });
+        }
+    };
+    request("move");
+    if interaction_opportunity.0.is_some() {
+        request("interact");
+    }
+}
+
+fn enter_hint_areas(
+    mut trigger_events: EventReader<TriggerEnter>,
+    hint_areas: Query<&HintArea>,
+    players: Query<(), With<Player>>,
+    mut hint_events: EventWriter<ShowHint>,
+) {
+    for event in trigger_events.read() {
+        let Ok(area) = hint_areas.get(event.trigger) else {
+            continue;
+        };
+        if players.contains(event.other) {
+            hint_events.send(ShowHint {
+                id: area.hint.clone(),
+            });
+        }
+    }
+}
+
+fn queue_hints(
+    mut hint_events: EventReader<ShowHint>,
+    mut seen: ResMut<SeenHints>,
+    mut queue: ResMut<HintQueue>,
+) {
+    for event in hint_events.read() {
+        if seen.0.contains(&event.id) {
+            continue;
+        }
+        let Some(hint) = HINTS.iter().find(|hint| hint.id == event.id) else {
+            warn!("There is no tutorial hint called {}", event.id);
+            continue;
+        };
+        seen.0.insert(event.id.clone());
+        queue.waiting.push_back(hint);
+    }
+}
+
+fn show_hints(
+    time: Res<Time<Virtual>>,
+    frozen: Res<ActionsFrozen>,
+    mut queue: ResMut<HintQueue>,
+    players: Query<(&InputMap<PlayerAction>, &ActionState<PlayerAction>), With<Player>>,
+    gamepads: Res<Gamepads>,
+    theme: Res<UiTheme>,
+    mut egui_contexts: EguiContexts,
+) {
+    // Hints wait for dialogs and cutscenes to end
+    if frozen.is_frozen() {
+        return;
+    }
+    let Ok((input_map, actions)) = players.get_single() else {
+        return;
+    };
+    if queue.current.is_none() {
+        queue.current = queue.waiting.pop_front().map(|hint| (hint, HINT_DURATION));
+    }
+    let Some((hint, remaining)) = queue.current.as_mut() else {
+        return;
+    };
+    *remaining -= time.delta_seconds();
+    let (hint, remaining) = (*hint, *remaining);
+    // Doing what the hint says is proof enough that it was read
+    if remaining <= 0. || actions.just_pressed(hint.action) {
+        queue.current = None;
+        return;
+    }
+    let prefer_gamepad = gamepads.iter().next().is_some();
+    let input = binding_label(input_map, hint.action, prefer_gamepad);
+    let text = hint.text.replace("{input}", &input);
+    egui::Area::new("Tutorial hint")
+        .anchor(
+            egui::Align2::CENTER_TOP,
+            egui::vec2(0., 4. * theme.spacing.screen_margin),
+        )
+        .order(egui::Order::Foreground)
+        .interactable(false)
+        .show(egui_contexts.ctx_mut(), |ui| {
+            theme.panel(ui, |ui| {
+                ui.label(
+                    egui::RichText::new(text)
+                        .size(theme.text.body)
+                        .color(theme.colors.text),
+                );
+            });
+        });
+}
+
+fn reset_hints(mut seen: ResMut<SeenHints>, mut queue: ResMut<HintQueue>) {
+    *seen = default();
+    *queue = default();
+}
+
+/// A readable name for what `action` is bound to, e.g. "Space" or "WASD".
+fn binding_label(
+    input_map: &InputMap<PlayerAction>,
+    action: PlayerAction,
+    prefer_gamepad: bool,
+) -> String {
+    let Some(inputs) = input_map.get(action).filter(|inputs| !inputs.is_empty()) else {
+        return "an unbound input".to_string();
+    };
+    let preferred = inputs
+        .iter()
+        .find(|input| is_gamepad_input(input) == prefer_gamepad)
+        .unwrap_or(&inputs[0]);
+    known_inputs()
+        .into_iter()
+        .find(|(input, _)| input == preferred)
+        .map_or_else(|| format!("{preferred:?}"), |(_, label)| label.to_string())
+}
+
+fn is_gamepad_input(input: &UserInput) -> bool {
+    match input {
+        UserInput::Single(kind) => matches!(kind, InputKind::GamepadButton(_)),
+        UserInput::VirtualDPad(dpad) => matches!(dpad.up, InputKind::GamepadButton(_)),
+        _ => false,
+    }
+}
+
+/// The inputs that have a nicer name than their debug representation
+fn known_inputs() -> Vec<(UserInput, &'static str)> {
+    vec![
+        (VirtualDPad::wasd().into(), "WASD"),
+        (VirtualDPad::arrow_keys().into(), "the arrow keys"),
+        (VirtualDPad::dpad().into(), "the D-pad"),
+        (DualAxis::left_stick().into(), "the left stick"),
+        (QwertyScanCode::Space.into(), "Space"),
+        (QwertyScanCode::ShiftLeft.into(), "Shift"),
+        (QwertyScanCode::ControlLeft.into(), "Ctrl"),
+        (QwertyScanCode::Escape.into(), "Esc"),
+        (QwertyScanCode::E.into(), "E"),
+        (QwertyScanCode::F.into(), "F"),
+        (QwertyScanCode::Q.into(), "Q"),
+        (QwertyScanCode::R.into(), "R"),
+        (MouseButton::Left.into(), "Left Click"),
+        (MouseButton::Right.into(), "Right Click"),
+        (GamepadButtonType::South.into(), "A"),
+        (GamepadButtonType::East.into(), "B"),
+        (GamepadButtonType::West.into(), "X"),
+        (GamepadButtonType::North.into(), "Y"),
+        (GamepadButtonType::LeftTrigger.into(), "LB"),
+        (GamepadButtonType::RightTrigger.into(), "RB"),
+        (GamepadButtonType::LeftTrigger2.into(), "LT"),
+        (GamepadButtonType::RightTrigger2.into(), "RT"),
+        (GamepadButtonType::LeftThumb.into(), "the left stick"),
+        (GamepadButtonType::RightThumb.into(), "the right stick"),
+    ]
+}
@@ -0,0 +1,172 @@
+//! Reusable building blocks for the HUD. Widgets taking a [`egui::Ui`] are meant to be laid out by the caller,
+//! while widgets taking a [`egui::Context`] position themselves on the screen.
+
+use crate::{
+    combat::health::Health, file_system_interaction::config::Leveling, hud::Hotbar,
+    movement::character_controller::Stamina, player_control::progression::Progression,
+    theme::UiTheme,
+};
+use bevy_egui::egui;
+
+const BAR_SIZE: egui::Vec2 = egui::vec2(200., 16.);
+const SLOT_SIZE: f32 = 40.;
+
+pub(crate) fn health_bar(ui: &mut egui::Ui, theme: &UiTheme, health: &Health) {
+    bar(
+        ui,
+        theme,
+        health.fraction(),
+        theme.colors.health,
+        &format!("{:.0} / {:.0}", health.current.max(0.), health.max),
+    );
+}
+
+pub(crate) fn stamina_bar(ui: &mut egui::Ui, theme: &UiTheme, stamina: &Stamina) {
+    let color = if stamina.exhausted {
+        theme.colors.exhausted
+    } else {
+        theme.colors.stamina
+    };
+    bar(ui, theme, stamina.fraction(), color, "");
+}
+
+pub(crate) fn experience_bar(
+    ui: &mut egui::Ui,
+    theme: &UiTheme,
+    progression: &Progression,
+    leveling: &Leveling,
+) {
+    let needed = leveling.experience_for_level(progression.level + 1);
+    let fraction = if needed == 0 {
+        1.
+    } else {
+        progression.experience as f32 / needed as f32
+    };
+    let mut text = format!("Level {}", progression.level);
+    if progression.skill_points > 0 {
+        text.push_str(" - skill points available");
+    }
+    bar(ui, theme, fraction, theme.colors.accent, &text);
+}
+
+pub(crate) fn bar(
+    ui: &mut egui::Ui,
+    theme: &UiTheme,
+    fraction: f32,
+    color: egui::Color32,
+    text: &str,
+) {
+    let (rect, _response) = ui.allocate_exact_size(BAR_SIZE, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    theme.paint_panel(&painter, rect);
+    let mut filled = rect.shrink(2.);
+    filled.set_width(filled.width() * fraction.clamp(0., 1.));
+    painter.rect_filled(filled, theme.panel.rounding, color);
+    if !text.is_empty() {
+        painter.text(
+            rect.center(),
+            egui::Align2::CENTER_CENTER,
+            text,
+            egui::FontId::proportional(theme.text.small),
+            theme.colors.text,
+        );
+    }
+}
+
+pub(crate) fn hotbar(ui: &mut egui::Ui, theme: &UiTheme, hotbar: &Hotbar) {
+    ui.horizontal(|ui| {
+        for (index, slot) in hotbar.slots.iter().enumerate() {
+            let (rect, _response) =
+                ui.allocate_exact_size(egui::Vec2::splat(SLOT_SIZE), egui::Sense::hover());
+            let painter = ui.painter_at(rect);
+            theme.paint_panel(&painter, rect.shrink(1.));
+            let stroke = if index == hotbar.selected {
+                egui::Stroke::new(2., theme.colors.accent)
+            } else {
+                egui::Stroke::new(1., theme.colors.weak_text.linear_multiply(0.5))
+            };
+            painter.rect_stroke(rect.shrink(1.), theme.panel.rounding, stroke);
+            painter.text(
+                rect.left_top() + egui::vec2(3., 2.),
+                egui::Align2::LEFT_TOP,
+                (index + 1).to_string(),
+                egui::FontId::proportional(theme.text.small),
+                theme.colors.weak_text,
+            );
+            if let Some(item) = slot {
+                painter.text(
+                    rect.center(),
+                    egui::Align2::CENTER_CENTER,
+                    item,
+                    egui::FontId::proportional(theme.text.small),
+                    theme.colors.text,
+                );
+            }
+        }
+    });
+}
+
+pub(crate) fn crosshair(ctx: &egui::Context, theme: &UiTheme) {
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Background,
+        egui::Id::new("crosshair"),
+    ));
+    let center = ctx.screen_rect().center();
+    let stroke = egui::Stroke::new(2., theme.colors.text.linear_multiply(0.8));
+    painter.line_segment(
+        [center - egui::vec2(6., 0.), center + egui::vec2(6., 0.)],
+        stroke,
+    );
+    painter.line_segment(
+        [center - egui::vec2(0., 6.), center + egui::vec2(0., 6.)],
+        stroke,
+    );
+}
+
+pub(crate) fn interaction_prompt(ctx: &egui::Context, theme: &UiTheme, text: &str) {
+    egui::Area::new("Interaction Prompt")
+        .anchor(
+            egui::Align2::CENTER_CENTER,
+            egui::vec2(0., 2. * theme.spacing.medium),
+        )
+        .show(ctx, |ui| {
+            theme.panel(ui, |ui| {
+                ui.label(text);
+            });
+        });
+}
+
+/// Lists everything that can be interacted with, marking the selected one with the interaction key.
+/// `labels` pairs every candidate's label with whether it is selected.
+pub(crate) fn interaction_candidates(
+    ctx: &egui::Context,
+    theme: &UiTheme,
+    labels: &[(String, bool)],
+) {
+    egui::Area::new("Interaction Prompt")
+        .anchor(
+            egui::Align2::CENTER_CENTER,
+            egui::vec2(0., 2. * theme.spacing.medium),
+        )
+        .show(ctx, |ui| {
+            theme.panel(ui, |ui| {
+                for (label, selected) in labels {
+                    if *selected {
+                        ui.label(
+                            egui::RichText::new(format!("E: {label}")).color(theme.colors.accent),
+                        );
+                    } else {
+                        ui.label(
+                            egui::RichText::new(format!("   {label}"))
+                                .color(theme.colors.weak_text),
+                        );
+                    }
+                }
+                ui.label(
+                    egui::RichText::new("Scroll to switch")
+                        .size(theme.text.small)
+                        .color(theme.colors.weak_text),
+                );
+            });
+        });
+}
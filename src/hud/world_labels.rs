@@ -0,0 +1,262 @@
+use crate::{
+    combat::health::Health,
+    movement::physics::CollisionLayer,
+    player_control::camera::IngameCamera,
+    theme::UiTheme,
+    util::{
+        criteria::is_frozen,
+        pool::{pool_plugin, Pool, Poolable},
+    },
+    world_interaction::{dialog::DialogTarget, interactions_ui::InteractionOpportunity},
+    GameState,
+};
+use bevy::{prelude::*, utils::HashMap};
+use bevy_egui::{egui, EguiContexts, EguiSettings};
+use bevy_xpbd_3d::prelude::*;
+use seldom_fn_plugin::FnPluginExt;
+use serde::{Deserialize, Serialize};
+
+/// Distance from the camera at which labels are drawn at their nominal size
+const REFERENCE_DISTANCE: f32 = 5.;
+const FLOATING_NUMBER_LIFETIME: f32 = 1.2;
+const FLOATING_NUMBER_RISE_SPEED: f32 = 0.8;
+const INTERACTION_ICON_DISTANCE: f32 = 8.;
+
+/// Draws text anchored to positions in the world, always facing the camera:
+/// - [`WorldLabel`]s, e.g. nametags above NPCs
+/// - an icon above everything that can be interacted with
+/// - [`FloatingNumber`]s that rise and fade whenever a [`Health`] changes
+///
+/// Labels shrink with distance and are hidden when terrain is between them and the camera.
+pub(crate) fn world_labels_plugin(app: &mut App) {
+    app.register_type::<WorldLabel>()
+        .register_type::<FloatingNumber>()
+        .fn_plugin(pool_plugin::<FloatingNumber>)
+        .add_systems(
+            Update,
+            (
+                spawn_health_change_numbers,
+                update_floating_numbers,
+                show_world_labels,
+            )
+                .chain()
+                .run_if(in_state(GameState::Playing).and_then(not(is_frozen))),
+        );
+}
+
+/// Text drawn above an entity.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct WorldLabel {
+    pub(crate) text: String,
+    /// Offset from the entity's origin in world space
+    pub(crate) offset: Vec3,
+    pub(crate) size: f32,
+    pub(crate) color: Color,
+    /// Distance from the camera beyond which the label is not drawn
+    pub(crate) max_distance: f32,
+}
+
+impl Default for WorldLabel {
+    fn default() -> Self {
+        Self {
+            text: default(),
+            offset: Vec3::Y * 1.2,
+            size: 16.,
+            color: Color::WHITE,
+            max_distance: 15.,
+        }
+    }
+}
+
+impl WorldLabel {
+    pub(crate) fn nametag(name: impl Into<String>) -> Self {
+        Self {
+            text: name.into(),
+            ..default()
+        }
+    }
+}
+
+/// A number that rises from where it was spawned and fades out, e.g. damage dealt or health restored.
+/// Pooled, since big fights can create many of these at once.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct FloatingNumber {
+    pub(crate) value: f32,
+    pub(crate) elapsed: f32,
+}
+
+impl Poolable for FloatingNumber {}
+
+fn spawn_health_change_numbers(
+    mut commands: Commands,
+    healths: Query<(Entity, &Health, &GlobalTransform), Changed<Health>>,
+    mut removed_healths: RemovedComponents<Health>,
+    mut last_health: Local<HashMap<Entity, f32>>,
+    mut pool: ResMut<Pool<FloatingNumber>>,
+) {
+    for entity in removed_healths.read() {
+        last_health.remove(&entity);
+    }
+    for (entity, health, transform) in healths.iter() {
+        let Some(last) = last_health.insert(entity, health.current) else {
+            continue;
+        };
+        let change = health.current - last;
+        if change.abs() < 0.5 {
+            continue;
+        }
+        let number = FloatingNumber {
+            value: change,
+            elapsed: 0.,
+        };
+        let entity = pool.acquire(&mut commands, number, |commands, number| {
+            commands
+                .spawn((
+                    Name::new("Floating Number"),
+                    TransformBundle::default(),
+                    number,
+                ))
+                .id()
+        });
+        commands.entity(entity).insert(Transform::from_translation(
+            transform.translation() + Vec3::Y,
+        ));
+    }
+}
+
+fn update_floating_numbers(
+    mut commands: Commands,
+    time: Res<Time<Virtual>>,
+    mut numbers: Query<(Entity, &mut FloatingNumber, &mut Transform)>,
+    mut pool: ResMut<Pool<FloatingNumber>>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, mut number, mut transform) in numbers.iter_mut() {
+        number.elapsed += dt;
+        transform.translation.y += FLOATING_NUMBER_RISE_SPEED * dt;
+        if number.elapsed >= FLOATING_NUMBER_LIFETIME {
+            pool.release(&mut commands, entity);
+        }
+    }
+}
+
+fn show_world_labels(
+    mut egui_contexts: EguiContexts,
+    egui_settings: Res<EguiSettings>,
+    cameras: Query<(&Camera, &GlobalTransform), With<IngameCamera>>,
+    labels: Query<(&WorldLabel, &GlobalTransform)>,
+    dialog_targets: Query<(Entity, &GlobalTransform), With<DialogTarget>>,
+    numbers: Query<(&FloatingNumber, &GlobalTransform)>,
+    interaction_opportunity: Res<InteractionOpportunity>,
+    spatial_query: SpatialQuery,
+    theme: Res<UiTheme>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("show_world_labels").entered();
+    let Some((camera, camera_transform)) = cameras.iter().next() else {
+        return;
+    };
+    let scale_factor = egui_settings.scale_factor as f32;
+    let filter = CollisionLayer::ground_filter();
+    // Returns the screen position in egui points and the distance scale if the position is visible
+    let project = |position: Vec3, max_distance: f32| {
+        let camera_position = camera_transform.translation();
+        let distance = camera_position.distance(position);
+        if distance > max_distance {
+            return None;
+        }
+        let screen_position = camera.world_to_viewport(camera_transform, position)?;
+        let direction = (position - camera_position).normalize_or_zero();
+        let occluded = spatial_query
+            .cast_ray(camera_position, direction, distance, true, filter.clone())
+            .is_some_and(|hit| hit.time_of_impact < distance - 0.1);
+        if occluded {
+            return None;
+        }
+        let scale = (REFERENCE_DISTANCE / distance.max(0.1)).clamp(0.5, 1.5);
+        Some((
+            egui::pos2(
+                screen_position.x / scale_factor,
+                screen_position.y / scale_factor,
+            ),
+            scale,
+        ))
+    };
+
+    let painter = egui_contexts
+        .ctx_mut()
+        .layer_painter(egui::LayerId::background());
+    let draw_text = |position: egui::Pos2, text: &str, size: f32, color: egui::Color32| {
+        painter.text(
+            position + egui::vec2(1., 1.),
+            egui::Align2::CENTER_BOTTOM,
+            text,
+            egui::FontId::proportional(size),
+            egui::Color32::from_black_alpha(color.a()),
+        );
+        painter.text(
+            position,
+            egui::Align2::CENTER_BOTTOM,
+            text,
+            egui::FontId::proportional(size),
+            color,
+        );
+    };
+
+    for (label, transform) in labels.iter() {
+        let Some((position, scale)) =
+            project(transform.translation() + label.offset, label.max_distance)
+        else {
+            continue;
+        };
+        let [r, g, b, a] = label.color.as_rgba_u8();
+        draw_text(
+            position,
+            &label.text,
+            label.size * scale,
+            egui::Color32::from_rgba_unmultiplied(r, g, b, a),
+        );
+    }
+
+    for (entity, transform) in dialog_targets.iter() {
+        // The interaction prompt is shown for the current target instead
+        if interaction_opportunity.0 == Some(entity) {
+            continue;
+        }
+        let Some((position, scale)) = project(
+            transform.translation() + Vec3::Y * 1.6,
+            INTERACTION_ICON_DISTANCE,
+        ) else {
+            continue;
+        };
+        let radius = 12. * scale;
+        painter.circle_filled(position, radius, theme.colors.panel);
+        painter.text(
+            position,
+            egui::Align2::CENTER_CENTER,
+            "💬",
+            egui::FontId::proportional(radius * 1.2),
+            theme.colors.text,
+        );
+    }
+
+    for (number, transform) in numbers.iter() {
+        let Some((position, scale)) = project(transform.translation(), f32::INFINITY) else {
+            continue;
+        };
+        let alpha = (1. - number.elapsed / FLOATING_NUMBER_LIFETIME).clamp(0., 1.);
+        let (text, color) = if number.value < 0. {
+            (format!("{:.0}", number.value), theme.colors.damage)
+        } else {
+            (format!("+{:.0}", number.value), theme.colors.heal)
+        };
+        draw_text(
+            position,
+            &text,
+            theme.menu_text.heading * scale,
+            color.linear_multiply(alpha),
+        );
+    }
+}
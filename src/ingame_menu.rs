@@ -1,63 +1,245 @@
 use crate::{
-    player_control::actions::{ActionsFrozen, UiAction},
+    combat::respawn::DeathCount,
+    file_system_interaction::{config::GameConfig, game_state_serialization::GameSaveRequest},
+    menu::{accessibility_ui, settings_ui, MenuNavigation, Settings},
+    movement::character_controller::GeneralMovementSystemSet,
+    player_control::{
+        actions::{ActionsFrozen, UiAction},
+        camera::CameraUpdateSystemSet,
+    },
+    theme::UiTheme,
+    world_interaction::collectibles::{completion_ui, Collection},
     GameState,
 };
-use bevy::{app::AppExit, prelude::*};
+use anyhow::{Context, Result};
+use bevy::{
+    app::AppExit,
+    prelude::*,
+    window::{CursorGrabMode, PrimaryWindow},
+};
 use bevy_egui::{egui, EguiContexts};
+use bevy_kira_audio::prelude::Audio;
+use bevy_mod_sysfail::*;
 use bevy_xpbd_3d::prelude::*;
 use leafwing_input_manager::prelude::ActionState;
 
-/// Handles the pause menu accessed while playing the game via ESC.
+/// Handles the pause menu accessed while playing the game via ESC or the start button.
+/// While paused, virtual and physics time are stopped, which also stops animations,
+/// and the movement and camera systems are disabled through [`PauseState`]. Leaving the level always unpauses.
 pub(crate) fn ingame_menu_plugin(app: &mut App) {
-    app.add_systems(Update, handle_pause.run_if(in_state(GameState::Playing)));
+    app.add_state::<PauseState>()
+        .init_resource::<PauseMenu>()
+        .configure_sets(
+            Update,
+            GeneralMovementSystemSet.run_if(in_state(PauseState::Running)),
+        )
+        .configure_sets(
+            PostUpdate,
+            CameraUpdateSystemSet.run_if(in_state(PauseState::Running)),
+        )
+        .add_systems(Update, toggle_pause.run_if(in_state(GameState::Playing)))
+        .add_systems(OnEnter(PauseState::Paused), pause)
+        .add_systems(OnExit(PauseState::Paused), resume)
+        .add_systems(OnExit(GameState::Playing), unpause)
+        .add_systems(
+            Update,
+            show_pause_menu
+                .run_if(in_state(GameState::Playing).and_then(in_state(PauseState::Paused))),
+        );
+}
+
+#[derive(States, Default, Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub(crate) enum PauseState {
+    #[default]
+    Running,
+    Paused,
+}
+
+#[derive(Debug, Clone, PartialEq, Resource, Default)]
+struct PauseMenu {
+    page: PausePage,
+    navigation: MenuNavigation,
+    /// Cursor state from before pausing, restored on resume
+    cursor: Option<(CursorGrabMode, bool)>,
+}
+
+impl PauseMenu {
+    /// Returns from a subpage, keeping the button that opened it selected.
+    fn open_main_page(&mut self) {
+        let index = match self.page {
+            PausePage::Main => 0,
+            PausePage::Settings => 2,
+            PausePage::Accessibility => 3,
+            PausePage::Statistics => 4,
+        };
+        self.page = PausePage::Main;
+        self.navigation.select(index);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+enum PausePage {
+    #[default]
+    Main,
+    Settings,
+    Accessibility,
+    Statistics,
+}
+
+fn toggle_pause(
+    actions: Query<&ActionState<UiAction>>,
+    pause_state: Res<State<PauseState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+) {
+    if actions
+        .iter()
+        .any(|action| action.just_pressed(UiAction::TogglePause))
+    {
+        next_pause_state.set(match pause_state.get() {
+            PauseState::Running => PauseState::Paused,
+            PauseState::Paused => PauseState::Running,
+        });
+    }
 }
 
-fn handle_pause(
+#[sysfail(log(level = "error"))]
+fn pause(
     mut time: ResMut<Time<Virtual>>,
     mut physics_time: ResMut<Time<Physics>>,
+    mut actions_frozen: ResMut<ActionsFrozen>,
+    mut menu: ResMut<PauseMenu>,
+    primary_windows: Query<&Window, With<PrimaryWindow>>,
+) -> Result<()> {
+    time.pause();
+    physics_time.pause();
+    actions_frozen.freeze();
+    let window = primary_windows
+        .get_single()
+        .context("Failed to get primary window")?;
+    menu.cursor = Some((window.cursor.grab_mode, window.cursor.visible));
+    menu.page = PausePage::Main;
+    menu.navigation.select(0);
+    Ok(())
+}
+
+#[sysfail(log(level = "error"))]
+fn resume(
+    mut time: ResMut<Time<Virtual>>,
+    mut physics_time: ResMut<Time<Physics>>,
+    mut actions_frozen: ResMut<ActionsFrozen>,
+    mut menu: ResMut<PauseMenu>,
+    mut primary_windows: Query<&mut Window, With<PrimaryWindow>>,
+) -> Result<()> {
+    time.unpause();
+    physics_time.unpause();
+    actions_frozen.unfreeze();
+    let mut window = primary_windows
+        .get_single_mut()
+        .context("Failed to get primary window")?;
+    if let Some((grab_mode, visible)) = menu.cursor.take() {
+        window.cursor.grab_mode = grab_mode;
+        window.cursor.visible = visible;
+    }
+    Ok(())
+}
+
+/// Leaving the level, e.g. through the error screen after a failed save, must not leave the game paused
+fn unpause(mut next_pause_state: ResMut<NextState<PauseState>>) {
+    next_pause_state.set(PauseState::Running);
+}
+
+fn show_pause_menu(
     actions: Query<&ActionState<UiAction>>,
+    mut menu: ResMut<PauseMenu>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
     mut app_exit_events: EventWriter<AppExit>,
-    mut actions_frozen: ResMut<ActionsFrozen>,
     mut egui_contexts: EguiContexts,
-    mut paused: Local<bool>,
+    mut save_requests: EventWriter<GameSaveRequest>,
+    mut settings: ResMut<Settings>,
+    mut config: Option<ResMut<GameConfig>>,
+    audio: Res<Audio>,
+    deaths: Res<DeathCount>,
+    collection: Res<Collection>,
+    theme: Res<UiTheme>,
 ) {
-    for action in actions.iter() {
-        let toggled = action.just_pressed(UiAction::TogglePause);
-        if toggled {
-            if *paused {
-                *paused = false;
-                time.unpause();
-                physics_time.unpause();
-                actions_frozen.unfreeze();
-            } else {
-                *paused = true;
-                time.pause();
-                physics_time.pause();
-                actions_frozen.freeze();
-            }
-        }
-    }
-    if !*paused {
-        return;
+    let button_count = match menu.page {
+        PausePage::Main => 6,
+        PausePage::Settings | PausePage::Accessibility | PausePage::Statistics => 1,
+    };
+    menu.navigation.update(actions.iter(), button_count);
+    if menu.navigation.back_pressed() && menu.page != PausePage::Main {
+        menu.open_main_page();
     }
 
+    let spacing = theme.spacing;
     egui::CentralPanel::default()
-        .frame(egui::Frame {
-            fill: egui::Color32::from_black_alpha(240),
-            ..default()
-        })
+        .frame(theme.overlay_frame())
         .show(egui_contexts.ctx_mut(), |ui| {
+            theme.apply_menu_style(ui.style_mut());
             ui.vertical_centered_justified(|ui| {
-                ui.visuals_mut().override_text_color = Some(egui::Color32::from_gray(240));
-                ui.add_space(100.0);
-                ui.heading("Game Paused");
-                ui.separator();
-                ui.label("Press ESC to resume");
-
-                ui.add_space(100.0);
-
-                if ui.button("Quit Game").clicked() {
-                    app_exit_events.send(AppExit);
+                ui.add_space(2. * spacing.large);
+                let page = menu.page;
+                match page {
+                    PausePage::Main => {
+                        ui.heading("Game Paused");
+                        ui.separator();
+                        ui.add_space(spacing.large);
+                        let navigation = menu.navigation;
+                        if navigation.button(ui, 0, "Resume") {
+                            next_pause_state.set(PauseState::Running);
+                        }
+                        if navigation.button(ui, 1, "Save Game") {
+                            save_requests.send(default());
+                        }
+                        if navigation.button(ui, 2, "Settings") {
+                            menu.page = PausePage::Settings;
+                            menu.navigation.select(0);
+                        }
+                        if navigation.button(ui, 3, "Accessibility") {
+                            menu.page = PausePage::Accessibility;
+                            menu.navigation.select(0);
+                        }
+                        if navigation.button(ui, 4, "Statistics") {
+                            menu.page = PausePage::Statistics;
+                            menu.navigation.select(0);
+                        }
+                        if navigation.button(ui, 5, "Quit Game") {
+                            app_exit_events.send(AppExit);
+                        }
+                    }
+                    PausePage::Settings => {
+                        ui.heading("Settings");
+                        ui.separator();
+                        ui.add_space(spacing.large);
+                        settings_ui(ui, &mut settings, config.as_deref_mut(), &audio);
+                        ui.add_space(spacing.large);
+                        if menu.navigation.button(ui, 0, "Back") {
+                            menu.open_main_page();
+                        }
+                    }
+                    PausePage::Accessibility => {
+                        ui.heading("Accessibility");
+                        ui.separator();
+                        ui.add_space(spacing.large);
+                        accessibility_ui(ui, &mut settings.accessibility);
+                        ui.add_space(spacing.large);
+                        if menu.navigation.button(ui, 0, "Back") {
+                            menu.open_main_page();
+                        }
+                    }
+                    PausePage::Statistics => {
+                        ui.heading("Statistics");
+                        ui.separator();
+                        ui.add_space(spacing.large);
+                        ui.label(format!("Deaths: {}", deaths.0));
+                        if let Some(config) = config.as_deref() {
+                            completion_ui(ui, &collection, config);
+                        }
+                        ui.add_space(spacing.large);
+                        if menu.navigation.button(ui, 0, "Back") {
+                            menu.open_main_page();
+                        }
+                    }
                 }
             });
         });
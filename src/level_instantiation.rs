@@ -1,17 +1,73 @@
-use crate::level_instantiation::{grass::grass_plugin, map::map_plugin, spawning::spawning_plugin};
+#[cfg(feature = "procgen")]
+use crate::level_instantiation::procgen::procgen_plugin;
+use crate::level_instantiation::{
+    batching::batching_plugin, environment::environment_plugin, grass::grass_plugin,
+    levels::levels_plugin, loading_screen::loading_screen_plugin, map::map_plugin,
+    markers::markers_plugin, patches::patches_plugin, post_spawn::post_spawn_plugin,
+    prefabs::prefabs_plugin, rooms::rooms_plugin, scatter::scatter_plugin,
+    spawning::spawning_plugin, streaming::streaming_plugin, terrain::terrain_plugin,
+    triplanar::triplanar_plugin, validation::validation_plugin,
+};
 use bevy::prelude::*;
 use seldom_fn_plugin::FnPluginExt;
 
+pub(crate) mod batching;
+pub(crate) mod environment;
 pub(crate) mod grass;
+pub(crate) mod levels;
+pub(crate) mod loading_screen;
 pub(crate) mod map;
+pub(crate) mod markers;
+pub(crate) mod patches;
+pub(crate) mod post_spawn;
+pub(crate) mod prefabs;
+#[cfg(feature = "procgen")]
+pub(crate) mod procgen;
+pub(crate) mod rooms;
+pub(crate) mod scatter;
 pub(crate) mod spawning;
+pub(crate) mod streaming;
+pub(crate) mod terrain;
+pub(crate) mod triplanar;
+pub(crate) mod validation;
 
 /// Handles creation of levels and objects. Split into the following sub-plugins:
 /// - [`map_plugin`] handles loading of level files and orchestrates the spawning of the objects therein.
+/// - [`levels_plugin`] handles the registry of levels and moving between them.
+/// - [`post_spawn_plugin`] handles the order in which freshly spawned scenes are turned into game objects.
+/// - [`batching_plugin`] handles merging the static meshes of a level to reduce draw calls.
+/// - [`patches_plugin`] handles applying the changes made in the dev editor on top of a level.
 /// - [`spawning_plugin`] handles the spawning of objects in general.
+/// - [`markers_plugin`] handles parsing the bracketed tags in object names and dispatching them to their handlers.
+/// - [`prefabs_plugin`] handles spawning objects from blueprints defined in RON files.
+/// - [`rooms_plugin`] handles hiding the rooms of indoor levels that cannot be seen from the camera.
+/// - [`procgen_plugin`] handles assembling levels from room pieces instead of a GLTF scene, behind the `procgen` feature.
+/// - [`terrain_plugin`] handles building the ground of outdoor levels from heightmaps.
+/// - [`scatter_plugin`] handles distributing prefabs over the ground of marked regions.
+/// - [`environment_plugin`] handles the skybox and the image-based lighting of levels.
 /// - [`grass_plugin`] handles the spawning of grass on top of marked meshes.
+/// - [`triplanar_plugin`] handles texturing marked greybox geometry without UVs.
+/// - [`loading_screen_plugin`] handles the loading screen shown while entering a level.
+/// - [`streaming_plugin`] handles loading and unloading chunks of large levels around the player.
+/// - [`validation_plugin`] handles reporting problems with the content of a level.
 pub(crate) fn level_instantiation_plugin(app: &mut App) {
     app.fn_plugin(map_plugin)
+        .fn_plugin(levels_plugin)
+        .fn_plugin(post_spawn_plugin)
+        .fn_plugin(batching_plugin)
+        .fn_plugin(patches_plugin)
         .fn_plugin(spawning_plugin)
-        .fn_plugin(grass_plugin);
+        .fn_plugin(markers_plugin)
+        .fn_plugin(prefabs_plugin)
+        .fn_plugin(rooms_plugin)
+        .fn_plugin(terrain_plugin)
+        .fn_plugin(scatter_plugin)
+        .fn_plugin(environment_plugin)
+        .fn_plugin(grass_plugin)
+        .fn_plugin(triplanar_plugin)
+        .fn_plugin(loading_screen_plugin)
+        .fn_plugin(streaming_plugin)
+        .fn_plugin(validation_plugin);
+    #[cfg(feature = "procgen")]
+    app.fn_plugin(procgen_plugin);
 }
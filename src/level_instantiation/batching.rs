@@ -0,0 +1,275 @@
+use crate::{
+    level_instantiation::{
+        levels::{CurrentLevel, LevelRegistry},
+        markers::Markers,
+        post_spawn::PostSpawnStage,
+    },
+    GameState,
+};
+use bevy::{
+    gltf::GltfExtras,
+    math::Affine3A,
+    pbr::{NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+    render::{
+        mesh::{skinning::SkinnedMesh, Indices, MeshVertexAttributeId, VertexAttributeValues},
+        render_resource::PrimitiveTopology,
+    },
+    scene::SceneInstanceReady,
+    utils::HashMap,
+};
+use bevy_xpbd_3d::prelude::*;
+
+/// Merges the static meshes of levels built from many small Blender objects into a few large meshes,
+/// one per material, to cut down on draw calls. Enabled per level by
+/// [`LevelDefinition::batch_static_meshes`](crate::level_instantiation::levels::LevelDefinition).
+/// Runs once when a scene is ready, after its colliders have been built, so that collisions keep using the shapes
+/// of the original objects. Only meshes that nothing can move or change are merged, i.e. those without markers,
+/// custom properties or non-static rigid bodies on themselves or the objects they are parented to in Blender.
+pub(crate) fn batching_plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        batch_static_meshes
+            .in_set(PostSpawnStage::Batching)
+            .run_if(in_state(GameState::Playing)),
+    );
+}
+
+/// Meshes can only be merged if they are drawn with the same material and have the same vertex attributes
+type BatchKey = (AssetId<StandardMaterial>, Vec<MeshVertexAttributeId>);
+
+fn batch_static_meshes(
+    mut commands: Commands,
+    mut ready_scenes: EventReader<SceneInstanceReady>,
+    current_level: Res<CurrentLevel>,
+    registry: Res<LevelRegistry>,
+    children: Query<&Children>,
+    parents: Query<&Parent>,
+    mesh_entities: Query<
+        (&Handle<Mesh>, &Handle<StandardMaterial>, &GlobalTransform),
+        (
+            Without<SkinnedMesh>,
+            Without<NotShadowCaster>,
+            Without<NotShadowReceiver>,
+        ),
+    >,
+    nodes: Query<(
+        &GlobalTransform,
+        Option<&Markers>,
+        Option<&GltfExtras>,
+        Option<&RigidBody>,
+        Option<&Visibility>,
+    )>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("batch_static_meshes").entered();
+    let enabled = registry
+        .get(&current_level.name)
+        .is_some_and(|definition| definition.batch_static_meshes);
+    if !enabled {
+        ready_scenes.clear();
+        return;
+    }
+    for ready in ready_scenes.read() {
+        let Ok((root_transform, ..)) = nodes.get(ready.parent) else {
+            continue;
+        };
+        let to_root = root_transform.affine().inverse();
+        let is_static = |entity: Entity| {
+            std::iter::once(entity)
+                .chain(parents.iter_ancestors(entity))
+                .take_while(|ancestor| *ancestor != ready.parent)
+                .all(|ancestor| {
+                    let Ok((_, markers, extras, rigid_body, visibility)) = nodes.get(ancestor)
+                    else {
+                        return true;
+                    };
+                    let only_colliders = markers.map_or(true, |markers| {
+                        markers.0.iter().all(|marker| marker.name == "collider")
+                    });
+                    only_colliders
+                        && extras.is_none()
+                        && rigid_body.map_or(true, RigidBody::is_static)
+                        && visibility != Some(&Visibility::Hidden)
+                })
+        };
+
+        let mut batches = HashMap::<BatchKey, (Handle<StandardMaterial>, Vec<Entity>)>::new();
+        for entity in children.iter_descendants(ready.parent) {
+            let Ok((mesh, material, _)) = mesh_entities.get(entity) else {
+                continue;
+            };
+            let Some(mesh) = meshes.get(mesh) else {
+                continue;
+            };
+            if mesh.primitive_topology() != PrimitiveTopology::TriangleList || !is_static(entity) {
+                continue;
+            }
+            let mut attributes: Vec<_> = mesh.attributes().map(|(id, _)| id).collect();
+            attributes.sort();
+            batches
+                .entry((material.id(), attributes))
+                .or_insert_with(|| (material.clone(), Vec::new()))
+                .1
+                .push(entity);
+        }
+
+        let mut merged_count = 0;
+        for (material, entities) in batches.into_values() {
+            if entities.len() < 2 {
+                continue;
+            }
+            let mut batch: Option<Mesh> = None;
+            let mut merged = Vec::with_capacity(entities.len());
+            for entity in entities {
+                let Ok((mesh, _, transform)) = mesh_entities.get(entity) else {
+                    continue;
+                };
+                let Some(mesh) = meshes.get(mesh) else {
+                    continue;
+                };
+                let Some(mesh) = transformed(mesh, &(to_root * transform.affine())) else {
+                    continue;
+                };
+                match batch.as_mut() {
+                    None => batch = Some(mesh),
+                    Some(batch) => {
+                        if append(batch, &mesh).is_none() {
+                            continue;
+                        }
+                    }
+                }
+                merged.push(entity);
+            }
+            let Some(batch) = batch else {
+                continue;
+            };
+            merged_count += merged.len();
+            for entity in merged {
+                commands
+                    .entity(entity)
+                    .remove::<(Handle<Mesh>, Handle<StandardMaterial>)>();
+            }
+            commands
+                .spawn((
+                    Name::new("Static Batch"),
+                    PbrBundle {
+                        mesh: meshes.add(batch),
+                        material,
+                        ..default()
+                    },
+                ))
+                .set_parent(ready.parent);
+        }
+        if merged_count > 0 {
+            info!("Merged {merged_count} static meshes of the level into batches");
+        }
+    }
+}
+
+/// A copy of `mesh` with its positions, normals and tangents moved by `transform` and 32 bit indices.
+/// Mirroring transforms, e.g. a negative scale in Blender, also turn the triangles around so they keep facing outwards.
+fn transformed(mesh: &Mesh, transform: &Affine3A) -> Option<Mesh> {
+    let mut mesh = mesh.clone();
+    let normal_matrix = Mat3::from(transform.matrix3).inverse().transpose();
+    let mirrored = transform.matrix3.determinant() < 0.;
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute_mut(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return None;
+    };
+    for position in positions.iter_mut() {
+        *position = transform.transform_point3((*position).into()).into();
+    }
+    if let Some(VertexAttributeValues::Float32x3(normals)) =
+        mesh.attribute_mut(Mesh::ATTRIBUTE_NORMAL)
+    {
+        for normal in normals.iter_mut() {
+            *normal = (normal_matrix * Vec3::from(*normal))
+                .normalize_or_zero()
+                .into();
+        }
+    }
+    if let Some(VertexAttributeValues::Float32x4(tangents)) =
+        mesh.attribute_mut(Mesh::ATTRIBUTE_TANGENT)
+    {
+        for tangent in tangents.iter_mut() {
+            let direction = transform
+                .transform_vector3(Vec3::new(tangent[0], tangent[1], tangent[2]))
+                .normalize_or_zero();
+            // The bitangent is derived from the normal and tangent, so it would point the wrong way otherwise
+            let handedness = if mirrored { -tangent[3] } else { tangent[3] };
+            *tangent = [direction.x, direction.y, direction.z, handedness];
+        }
+    }
+    let mut indices: Vec<u32> = match mesh.indices() {
+        Some(indices) => indices.iter().map(|index| index as u32).collect(),
+        None => (0..mesh.count_vertices() as u32).collect(),
+    };
+    if mirrored {
+        for triangle in indices.chunks_exact_mut(3) {
+            triangle.swap(1, 2);
+        }
+    }
+    mesh.set_indices(Some(Indices::U32(indices)));
+    Some(mesh)
+}
+
+/// Adds the vertices and triangles of `source` to `target`. Both must have the same attributes.
+fn append(target: &mut Mesh, source: &Mesh) -> Option<()> {
+    let offset = target.count_vertices() as u32;
+    let ids: Vec<_> = target.attributes().map(|(id, _)| id).collect();
+    // Checked up front so that a failure does not leave the target half extended
+    for id in &ids {
+        let compatible = matches!(
+            (target.attribute(*id)?, source.attribute(*id)?),
+            (
+                VertexAttributeValues::Float32x2(_),
+                VertexAttributeValues::Float32x2(_)
+            ) | (
+                VertexAttributeValues::Float32x3(_),
+                VertexAttributeValues::Float32x3(_)
+            ) | (
+                VertexAttributeValues::Float32x4(_),
+                VertexAttributeValues::Float32x4(_)
+            )
+        );
+        if !compatible {
+            return None;
+        }
+    }
+    for id in ids {
+        match (target.attribute_mut(id)?, source.attribute(id)?) {
+            (
+                VertexAttributeValues::Float32x2(target),
+                VertexAttributeValues::Float32x2(source),
+            ) => {
+                target.extend_from_slice(source);
+            }
+            (
+                VertexAttributeValues::Float32x3(target),
+                VertexAttributeValues::Float32x3(source),
+            ) => {
+                target.extend_from_slice(source);
+            }
+            (
+                VertexAttributeValues::Float32x4(target),
+                VertexAttributeValues::Float32x4(source),
+            ) => {
+                target.extend_from_slice(source);
+            }
+            _ => return None,
+        }
+    }
+    let source_indices: Vec<u32> = source
+        .indices()?
+        .iter()
+        .map(|index| index as u32 + offset)
+        .collect();
+    match target.indices_mut()? {
+        Indices::U32(indices) => indices.extend(source_indices),
+        Indices::U16(_) => return None,
+    }
+    Some(())
+}
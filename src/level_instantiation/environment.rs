@@ -0,0 +1,123 @@
+use crate::{
+    level_instantiation::{
+        levels::{CurrentLevel, LevelRegistry},
+        markers::MarkersAppExt,
+    },
+    player_control::camera::IngameCamera,
+    GameState,
+};
+use anyhow::Context;
+use bevy::{core_pipeline::Skybox, pbr::EnvironmentMapLight, prelude::*};
+use bevy_atmosphere::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Replaces the procedural atmosphere with a cubemap skybox that also lights the level.
+/// A skybox named `<name>` consists of the KTX2 cubemaps `skyboxes/<name>/skybox.ktx2`, which is drawn behind the level,
+/// and the prefiltered `diffuse.ktx2` and `specular.ktx2` next to it, which are used for image-based lighting.
+/// Levels pick a skybox through their [`LevelDefinition`](crate::level_instantiation::levels::LevelDefinition)
+/// or, in Blender, an object named with a `[skybox:<name>]` suffix. At runtime, the sky is switched with a [`SetSkybox`],
+/// e.g. by a day and night cycle, weather or a cutscene, or from a dialog with `<<set_skybox <name>>>`.
+pub(crate) fn environment_plugin(app: &mut App) {
+    app.register_type::<SkyboxMarker>()
+        .init_resource::<ActiveSkybox>()
+        .add_event::<SetSkybox>()
+        .register_marker("skybox", |entity, marker| {
+            let skybox = marker.argument(0).context("Expected [skybox:<name>]")?;
+            entity.insert(SkyboxMarker {
+                skybox: skybox.to_string(),
+            });
+            Ok(())
+        })
+        .add_systems(OnEnter(GameState::Playing), select_level_skybox)
+        .add_systems(
+            Update,
+            (read_skybox_markers, switch_skybox, apply_skybox)
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+/// Switches the level's skybox to the one with the given name, or back to the procedural atmosphere if `None`.
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub(crate) struct SetSkybox {
+    pub(crate) skybox: Option<String>,
+}
+
+/// Selects the skybox of the level it is in.
+#[derive(Debug, Clone, PartialEq, Eq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct SkyboxMarker {
+    pub(crate) skybox: String,
+}
+
+/// Name of the skybox shown, `None` for the procedural atmosphere
+#[derive(Debug, Clone, PartialEq, Eq, Resource, Default)]
+pub(crate) struct ActiveSkybox(pub(crate) Option<String>);
+
+/// `<<set_skybox night>>` switches the skybox.
+pub(crate) fn set_skybox_command(In(skybox): In<String>, mut events: EventWriter<SetSkybox>) {
+    events.send(SetSkybox {
+        skybox: Some(skybox),
+    });
+}
+
+fn select_level_skybox(
+    current_level: Res<CurrentLevel>,
+    registry: Res<LevelRegistry>,
+    mut active_skybox: ResMut<ActiveSkybox>,
+) {
+    active_skybox.0 = registry
+        .get(&current_level.name)
+        .and_then(|definition| definition.skybox.clone());
+}
+
+fn read_skybox_markers(
+    markers: Query<&SkyboxMarker, Added<SkyboxMarker>>,
+    mut events: EventWriter<SetSkybox>,
+) {
+    for marker in markers.iter() {
+        events.send(SetSkybox {
+            skybox: Some(marker.skybox.clone()),
+        });
+    }
+}
+
+fn switch_skybox(mut events: EventReader<SetSkybox>, mut active_skybox: ResMut<ActiveSkybox>) {
+    if let Some(event) = events.read().last() {
+        if active_skybox.0 != event.skybox {
+            active_skybox.0 = event.skybox.clone();
+        }
+    }
+}
+
+fn apply_skybox(
+    mut commands: Commands,
+    active_skybox: Res<ActiveSkybox>,
+    cameras: Query<Entity, With<IngameCamera>>,
+    added_cameras: Query<(), Added<IngameCamera>>,
+    asset_server: Res<AssetServer>,
+) {
+    if !active_skybox.is_changed() && added_cameras.is_empty() {
+        return;
+    }
+    for camera in cameras.iter() {
+        let mut camera = commands.entity(camera);
+        match &active_skybox.0 {
+            Some(name) => {
+                let path = |file: &str| format!("skyboxes/{name}/{file}.ktx2");
+                camera.remove::<AtmosphereCamera>().insert((
+                    Skybox(asset_server.load(path("skybox"))),
+                    EnvironmentMapLight {
+                        diffuse_map: asset_server.load(path("diffuse")),
+                        specular_map: asset_server.load(path("specular")),
+                    },
+                ));
+            }
+            None => {
+                camera
+                    .remove::<(Skybox, EnvironmentMapLight)>()
+                    .insert(AtmosphereCamera::default());
+            }
+        }
+    }
+}
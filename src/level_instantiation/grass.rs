@@ -1,6 +1,10 @@
 use crate::{
+    despawn::DespawnOnExit,
     file_system_interaction::asset_loading::GrassAssets,
-    level_instantiation::spawning::objects::ground::Grass, GameState,
+    level_instantiation::spawning::objects::ground::Grass,
+    movement::wind::{wind_at, WindZone},
+    player_control::camera::IngameCamera,
+    GameState,
 };
 use bevy::{app::App, prelude::*, render::primitives::Aabb};
 use warbler_grass::{
@@ -9,8 +13,11 @@ use warbler_grass::{
     prelude::*,
 };
 pub(crate) fn grass_plugin(app: &mut App) {
-    app.add_systems(Update, spawn.run_if(in_state(GameState::Playing)))
-        .add_plugins(WarblersPlugin);
+    app.add_systems(
+        Update,
+        (spawn, sway_in_wind).run_if(in_state(GameState::Playing)),
+    )
+    .add_plugins(WarblersPlugin);
 }
 
 // Spawns the grass using the ground as a base
@@ -25,16 +32,42 @@ pub(crate) fn spawn(
         let aabb = Aabb::from_min_max(-offset, offset);
         let grass_transform =
             Transform::from_translation(-offset + transform.translation + Vec3::X);
-        commands.spawn(WarblersBundle {
-            density_map,
-            grass_color: GrassColor {
-                main_color: Color::rgb(0.3, 0.6, 0.0),
-                bottom_color: Color::rgb(0.2, 0.1, 0.),
+        commands.spawn((
+            WarblersBundle {
+                density_map,
+                grass_color: GrassColor {
+                    main_color: Color::rgb(0.3, 0.6, 0.0),
+                    bottom_color: Color::rgb(0.2, 0.1, 0.),
+                },
+                aabb,
+                spatial: SpatialBundle::from_transform(grass_transform),
+                height: WarblerHeight::Uniform(1.2),
+                ..default()
             },
-            aabb,
-            spatial: SpatialBundle::from_transform(grass_transform),
-            height: WarblerHeight::Uniform(1.2),
-            ..default()
-        });
+            DespawnOnExit(GameState::Playing),
+        ));
+    }
+}
+
+/// Grass sways with a single global wind, so we use the wind where the camera is.
+fn sway_in_wind(
+    time: Res<Time<Virtual>>,
+    zones: Query<(&WindZone, &GlobalTransform)>,
+    cameras: Query<&GlobalTransform, With<IngameCamera>>,
+    mut config: ResMut<GrassConfiguration>,
+    mut calm_wind: Local<Option<Vec2>>,
+) {
+    let Some(camera_transform) = cameras.iter().next() else {
+        return;
+    };
+    let calm_wind = *calm_wind.get_or_insert(config.wind);
+    let wind = wind_at(
+        &zones,
+        camera_transform.translation(),
+        time.elapsed_seconds(),
+    );
+    let sway = calm_wind + Vec2::new(wind.x, wind.z) * 0.1;
+    if config.wind != sway {
+        config.wind = sway;
     }
 }
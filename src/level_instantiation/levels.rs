@@ -0,0 +1,317 @@
+use crate::{
+    game_events::LevelLoaded,
+    level_instantiation::{
+        markers::{Marker, MarkersAppExt},
+        patches::ScenePatch,
+    },
+    movement::physics::CollisionLayer,
+    player_control::{actions::ActionsFrozen, player_embodiment::Player},
+    screen_transition::{ScreenTransition, TransitionEffect, TransitionFinished},
+    world_interaction::triggers::{Trigger, TriggerEnter, TriggerKind, TriggerSystemSet},
+    GameState,
+};
+use anyhow::{Context, Result};
+use bevy::{ecs::world::EntityWorldMut, gltf::Gltf, prelude::*, utils::HashMap};
+use bevy_xpbd_3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const FADE_OUT_DURATION: f32 = 0.4;
+
+/// Lets the game consist of several levels that the player walks between.
+/// All levels are listed in the [`LevelRegistry`]; the one to spawn next is the [`CurrentLevel`].
+///
+/// Levels mark where the player can arrive with empties named with a `[spawn:<name>]` suffix.
+/// Objects named with a `[portal:<level>:<spawn>]` suffix become trigger volumes the size of their
+/// scale that send a [`LevelTransitionEvent`] when the player walks in. The screen fades to black, then the transition
+/// goes through [`GameState::Loading`], so the loading screen is shown while the next level is loaded.
+/// Once the player has spawned in a level, a [`LevelLoaded`] is published.
+pub(crate) fn levels_plugin(app: &mut App) {
+    app.register_type::<SpawnPoint>()
+        .register_type::<Portal>()
+        .init_resource::<LevelRegistry>()
+        .init_resource::<CurrentLevel>()
+        .init_resource::<PendingLevelTransition>()
+        .add_event::<LevelTransitionEvent>()
+        .register_marker("spawn", insert_spawn_point)
+        .register_marker("portal", insert_portal)
+        .add_systems(OnEnter(GameState::Loading), load_current_level)
+        .add_systems(OnExit(GameState::Playing), cancel_level_transition)
+        .add_systems(
+            Update,
+            (
+                enter_portals.after(TriggerSystemSet),
+                start_level_transitions,
+                leave_level,
+                place_player_at_spawn_point,
+                publish_level_loaded,
+            )
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+/// Every level that can be entered, by name.
+#[derive(Debug, Clone, PartialEq, Resource)]
+pub(crate) struct LevelRegistry(HashMap<String, LevelDefinition>);
+
+impl Default for LevelRegistry {
+    fn default() -> Self {
+        Self(HashMap::from([(
+            DEFAULT_LEVEL.to_string(),
+            LevelDefinition {
+                path: "scenes/level.glb".to_string(),
+                scene: "World".to_string(),
+                patch: "scenes/level.patch.ron".to_string(),
+                intro_cutscene: Some("cutscenes/intro.cutscene.ron".to_string()),
+                skybox: None,
+                batch_static_meshes: false,
+                #[cfg(feature = "procgen")]
+                generator: None,
+            },
+        )]))
+    }
+}
+
+impl LevelRegistry {
+    pub(crate) fn get(&self, name: &str) -> Option<&LevelDefinition> {
+        self.0.get(name)
+    }
+
+    /// Adds a level, replacing any with the same name.
+    #[cfg_attr(not(feature = "procgen"), allow(dead_code))]
+    pub(crate) fn insert(&mut self, name: impl Into<String>, definition: LevelDefinition) {
+        self.0.insert(name.into(), definition);
+    }
+}
+
+/// The level a new game starts in
+pub(crate) const DEFAULT_LEVEL: &str = "World";
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LevelDefinition {
+    /// Asset path of the GLTF file containing the level
+    pub(crate) path: String,
+    /// Name of the scene inside the GLTF file
+    pub(crate) scene: String,
+    /// Asset path of the [`ScenePatch`] applied on top of the scene
+    pub(crate) patch: String,
+    /// Asset path of a [`Cutscene`](crate::cutscene::Cutscene) played the first time the level is entered in a session
+    pub(crate) intro_cutscene: Option<String>,
+    /// Name of the [skybox](crate::level_instantiation::environment::environment_plugin) shown in the level.
+    /// The procedural atmosphere is used if `None`.
+    pub(crate) skybox: Option<String>,
+    /// Whether static meshes are merged by material when the level spawns, see [`batching_plugin`](crate::level_instantiation::batching::batching_plugin).
+    /// Worth it for levels made of many small objects.
+    pub(crate) batch_static_meshes: bool,
+    /// Asset path of a [`RoomSet`](crate::level_instantiation::procgen::RoomSet) the level is generated from.
+    /// If set, `scene` only provides the objects the room set keeps, like the player.
+    #[cfg(feature = "procgen")]
+    pub(crate) generator: Option<String>,
+}
+
+/// The level that is being played, or loaded next.
+#[derive(Debug, Clone, PartialEq, Resource)]
+pub(crate) struct CurrentLevel {
+    pub(crate) name: String,
+    /// Where to place the player once the level has spawned. The player stays where the level put them if `None`.
+    pub(crate) spawn_point: Option<String>,
+    pub(crate) gltf: Handle<Gltf>,
+    pub(crate) patch: Handle<ScenePatch>,
+}
+
+impl Default for CurrentLevel {
+    fn default() -> Self {
+        Self {
+            name: DEFAULT_LEVEL.to_string(),
+            spawn_point: None,
+            gltf: default(),
+            patch: default(),
+        }
+    }
+}
+
+/// Where the player can arrive in a level.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct SpawnPoint {
+    pub(crate) name: String,
+}
+
+/// A trigger volume that moves the player to another level.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Portal {
+    pub(crate) level: String,
+    pub(crate) spawn_point: String,
+}
+
+/// The level transition waiting for the screen to fade out
+#[derive(Debug, Clone, PartialEq, Resource, Default)]
+struct PendingLevelTransition(Option<LevelTransitionEvent>);
+
+/// Leaves the current level and places the player at `spawn_point` in `level`.
+#[derive(Debug, Clone, PartialEq, Event)]
+pub(crate) struct LevelTransitionEvent {
+    pub(crate) level: String,
+    pub(crate) spawn_point: String,
+}
+
+fn load_current_level(
+    asset_server: Res<AssetServer>,
+    registry: Res<LevelRegistry>,
+    mut current_level: ResMut<CurrentLevel>,
+) {
+    let definition = match registry.get(&current_level.name) {
+        Some(definition) => definition,
+        None => {
+            error!(
+                "Level \"{}\" is not registered, falling back to \"{DEFAULT_LEVEL}\"",
+                current_level.name
+            );
+            current_level.name = DEFAULT_LEVEL.to_string();
+            current_level.spawn_point = None;
+            &registry.0[DEFAULT_LEVEL]
+        }
+    };
+    current_level.gltf = asset_server.load(&definition.path);
+    current_level.patch = asset_server.load(&definition.patch);
+}
+
+fn insert_spawn_point(entity: &mut EntityWorldMut, marker: &Marker) -> Result<()> {
+    let name = marker.argument(0).context("Expected [spawn:<name>]")?;
+    entity.insert(SpawnPoint {
+        name: name.to_string(),
+    });
+    Ok(())
+}
+
+fn insert_portal(entity: &mut EntityWorldMut, marker: &Marker) -> Result<()> {
+    let (Some(level), Some(spawn_point)) = (marker.argument(0), marker.argument(1)) else {
+        anyhow::bail!("Expected [portal:<level>:<spawn>]");
+    };
+    entity.insert((
+        Portal {
+            level: level.to_string(),
+            spawn_point: spawn_point.to_string(),
+        },
+        // Scaled by the object's transform
+        Collider::cuboid(1., 1., 1.),
+        Sensor,
+        CollisionLayer::trigger(),
+        Trigger::new(TriggerKind::Area),
+        Visibility::Hidden,
+    ));
+    Ok(())
+}
+
+fn enter_portals(
+    mut trigger_events: EventReader<TriggerEnter>,
+    portals: Query<&Portal>,
+    players: Query<(), With<Player>>,
+    mut transition_events: EventWriter<LevelTransitionEvent>,
+) {
+    for event in trigger_events.read() {
+        let Ok(portal) = portals.get(event.trigger) else {
+            continue;
+        };
+        if !players.contains(event.other) {
+            continue;
+        }
+        transition_events.send(LevelTransitionEvent {
+            level: portal.level.clone(),
+            spawn_point: portal.spawn_point.clone(),
+        });
+    }
+}
+
+fn start_level_transitions(
+    mut transition_events: EventReader<LevelTransitionEvent>,
+    registry: Res<LevelRegistry>,
+    mut pending: ResMut<PendingLevelTransition>,
+    mut freeze: ResMut<ActionsFrozen>,
+    mut screen_transitions: EventWriter<ScreenTransition>,
+) {
+    // Only the first transition of a frame counts, e.g. when touching two portals at once
+    let Some(event) = transition_events.read().last() else {
+        return;
+    };
+    if pending.0.is_some() {
+        return;
+    }
+    if registry.get(&event.level).is_none() {
+        error!("Cannot enter unregistered level \"{}\"", event.level);
+        return;
+    }
+    pending.0 = Some(event.clone());
+    freeze.freeze();
+    screen_transitions.send(ScreenTransition::cover(
+        TransitionEffect::FadeToBlack,
+        FADE_OUT_DURATION,
+    ));
+}
+
+/// Leaves for the next level once the screen is black
+fn leave_level(
+    mut finished_transitions: EventReader<TransitionFinished>,
+    mut pending: ResMut<PendingLevelTransition>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut freeze: ResMut<ActionsFrozen>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let faded_out = finished_transitions
+        .read()
+        .any(|event| event.effect == TransitionEffect::FadeToBlack && event.covered);
+    if !faded_out {
+        return;
+    }
+    let Some(event) = pending.0.take() else {
+        return;
+    };
+    freeze.unfreeze();
+    current_level.name = event.level;
+    current_level.spawn_point = Some(event.spawn_point);
+    next_state.set(GameState::Loading);
+}
+
+fn place_player_at_spawn_point(
+    mut current_level: ResMut<CurrentLevel>,
+    spawn_points: Query<(&SpawnPoint, &GlobalTransform)>,
+    mut players: Query<&mut Transform, With<Player>>,
+) {
+    let Some(name) = current_level.spawn_point.as_ref() else {
+        return;
+    };
+    // Both are spawned with the level, so wait until they exist
+    let Ok(mut player_transform) = players.get_single_mut() else {
+        return;
+    };
+    let Some((_, spawn_transform)) = spawn_points.iter().find(|(point, _)| &point.name == name)
+    else {
+        return;
+    };
+    let spawn_transform = spawn_transform.compute_transform();
+    player_transform.translation = spawn_transform.translation;
+    player_transform.rotation = spawn_transform.rotation;
+    current_level.spawn_point = None;
+}
+
+fn publish_level_loaded(
+    players: Query<(), Added<Player>>,
+    current_level: Res<CurrentLevel>,
+    mut levels_loaded: EventWriter<LevelLoaded>,
+) {
+    if !players.is_empty() {
+        levels_loaded.send(LevelLoaded {
+            level: current_level.name.clone(),
+        });
+    }
+}
+
+fn cancel_level_transition(
+    mut pending: ResMut<PendingLevelTransition>,
+    mut freeze: ResMut<ActionsFrozen>,
+) {
+    if pending.0.take().is_some() {
+        freeze.unfreeze();
+    }
+}
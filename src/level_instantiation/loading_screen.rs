@@ -0,0 +1,154 @@
+use crate::{
+    errors::GameError,
+    file_system_interaction::asset_loading::LevelAssets,
+    menu::Settings,
+    player_control::player_embodiment::Player,
+    screen_transition::{ScreenTransition, TransitionEffect},
+    theme::UiTheme,
+    GameState,
+};
+use bevy::{asset::RecursiveDependencyLoadState, prelude::*};
+use bevy_egui::{egui, EguiContexts};
+
+const TIPS: &[&str] = &[
+    "Press E to talk to characters near you.",
+    "Hold Shift to sprint, but keep an eye on your stamina.",
+    "Press M to open the world map.",
+    "Press C to change your appearance.",
+    "Press F to throw a ball.",
+    "Scroll to move the camera closer or further away.",
+];
+const TIP_DURATION: f32 = 5.;
+const FADE_IN_DURATION: f32 = 0.8;
+const REDUCED_MOTION_FADE_IN_DURATION: f32 = 0.2;
+
+/// Shows a loading screen while transitioning into a level.
+/// During [`GameState::Loading`], the progress of the assets in [`LevelAssets`] is shown.
+/// Once everything is loaded, the game switches to [`GameState::Playing`] and the screen stays up until the level has spawned the player.
+/// The level then fades in from black.
+pub(crate) fn loading_screen_plugin(app: &mut App) {
+    app.init_resource::<LevelLoadingProgress>()
+        .add_systems(OnEnter(GameState::Loading), reset_progress)
+        .add_systems(
+            Update,
+            (track_level_assets, show_loading_screen)
+                .chain()
+                .run_if(in_state(GameState::Loading)),
+        )
+        .add_systems(
+            Update,
+            show_loading_screen
+                .run_if(in_state(GameState::Playing).and_then(not(any_with_component::<Player>()))),
+        )
+        .add_systems(Update, fade_in.run_if(in_state(GameState::Playing)));
+}
+
+#[derive(Debug, Clone, PartialEq, Resource, Default)]
+struct LevelLoadingProgress {
+    loaded: usize,
+    total: usize,
+    failed: bool,
+}
+
+impl LevelLoadingProgress {
+    fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            return 1.;
+        }
+        self.loaded as f32 / self.total as f32
+    }
+}
+
+fn reset_progress(mut progress: ResMut<LevelLoadingProgress>) {
+    *progress = default();
+}
+
+fn track_level_assets(
+    asset_server: Res<AssetServer>,
+    level_assets: LevelAssets,
+    mut progress: ResMut<LevelLoadingProgress>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut game_errors: EventWriter<GameError>,
+) {
+    let ids = level_assets.ids();
+    let states = ids
+        .iter()
+        .map(|id| asset_server.get_recursive_dependency_load_state(*id));
+    let mut loaded = 0;
+    let mut failed = false;
+    for state in states {
+        match state {
+            Some(RecursiveDependencyLoadState::Loaded) => loaded += 1,
+            Some(RecursiveDependencyLoadState::Failed) => failed = true,
+            _ => {}
+        }
+    }
+    if failed && !progress.failed {
+        game_errors.send(GameError {
+            message: "Failed to load the assets of the level, see the log for details".to_string(),
+        });
+    }
+    *progress = LevelLoadingProgress {
+        loaded,
+        total: ids.len(),
+        failed,
+    };
+    if loaded == ids.len() {
+        next_state.set(GameState::Playing);
+    }
+}
+
+fn show_loading_screen(
+    time: Res<Time<Real>>,
+    state: Res<State<GameState>>,
+    progress: Res<LevelLoadingProgress>,
+    theme: Res<UiTheme>,
+    mut egui_contexts: EguiContexts,
+) {
+    let (status, fraction) = match state.get() {
+        GameState::Loading if progress.failed => ("Failed to load assets", progress.fraction()),
+        GameState::Loading => ("Loading assets...", progress.fraction()),
+        _ => ("Spawning level...", 1.),
+    };
+    let tip = TIPS[(time.elapsed_seconds() / TIP_DURATION) as usize % TIPS.len()];
+    let spacing = theme.spacing;
+    egui::CentralPanel::default()
+        .frame(egui::Frame {
+            fill: egui::Color32::BLACK,
+            ..theme.backdrop_frame()
+        })
+        .show(egui_contexts.ctx_mut(), |ui| {
+            theme.apply_menu_style(ui.style_mut());
+            ui.vertical_centered(|ui| {
+                ui.add_space(2. * spacing.large);
+                ui.heading("Loading");
+                ui.add_space(spacing.small);
+                ui.add(egui::Spinner::new().size(theme.menu_text.heading));
+                ui.label(status);
+                ui.add(
+                    egui::ProgressBar::new(fraction)
+                        .show_percentage()
+                        .animate(true),
+                );
+                ui.add_space(spacing.large);
+                ui.colored_label(theme.colors.weak_text, tip);
+            });
+        });
+}
+
+fn fade_in(
+    players: Query<(), Added<Player>>,
+    settings: Res<Settings>,
+    mut transitions: EventWriter<ScreenTransition>,
+) {
+    if players.is_empty() {
+        return;
+    }
+    let duration = if settings.accessibility.reduce_motion {
+        REDUCED_MOTION_FADE_IN_DURATION
+    } else {
+        FADE_IN_DURATION
+    };
+    transitions
+        .send(ScreenTransition::reveal(TransitionEffect::FadeToBlack, duration).from_covered());
+}
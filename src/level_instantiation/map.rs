@@ -1,41 +1,81 @@
 use crate::{
-    file_system_interaction::asset_loading::GltfAssets, player_control::player_embodiment::Player,
+    despawn::DespawnOnExit,
+    level_instantiation::{
+        levels::{CurrentLevel, LevelRegistry},
+        prefabs::SpawnPrefab,
+    },
+    movement::vehicle::Vehicle,
+    player_control::player_embodiment::Player,
     GameState,
 };
+use anyhow::{Context, Result};
 use bevy::{gltf::Gltf, prelude::*};
-use bevy_egui::{egui, EguiContexts};
+use bevy_mod_sysfail::*;
 
 pub(crate) fn map_plugin(app: &mut App) {
     app.add_systems(OnEnter(GameState::Playing), spawn_level)
         .add_systems(
             Update,
-            show_loading_screen
-                .run_if(not(any_with_component::<Player>()).and_then(in_state(GameState::Playing))),
+            spawn_demo_vehicle.run_if(in_state(GameState::Playing)),
         );
 }
 
-fn spawn_level(mut commands: Commands, models: Res<Assets<Gltf>>, gltf_assets: Res<GltfAssets>) {
-    let gltf = models.get(&gltf_assets.level).unwrap();
+#[sysfail(log(level = "error"))]
+fn spawn_level(
+    mut commands: Commands,
+    models: Res<Assets<Gltf>>,
+    registry: Res<LevelRegistry>,
+    current_level: Res<CurrentLevel>,
+) -> Result<()> {
+    let definition = registry
+        .get(&current_level.name)
+        .context("Failed to get the definition of the current level")?;
     commands.insert_resource(AmbientLight {
         color: Color::rgb(1., 0.65, 0.23),
         ..default()
     });
+    // Spawned by the procgen plugin once its rooms are laid out
+    #[cfg(feature = "procgen")]
+    if definition.generator.is_some() {
+        return Ok(());
+    }
+    let gltf = models
+        .get(&current_level.gltf)
+        .context("Failed to get the GLTF of the current level")?;
+    let scene = gltf
+        .named_scenes
+        .get(&definition.scene)
+        .with_context(|| format!("Level GLTF has no scene named \"{}\"", definition.scene))?;
     commands.spawn((
         SceneBundle {
-            scene: gltf.named_scenes["World"].clone(),
+            scene: scene.clone(),
             ..default()
         },
         Name::new("Level"),
+        DespawnOnExit(GameState::Playing),
     ));
+    Ok(())
 }
 
-fn show_loading_screen(mut egui_contexts: EguiContexts) {
-    egui::CentralPanel::default().show(egui_contexts.ctx_mut(), |ui| {
-        ui.vertical_centered(|ui| {
-            ui.add_space(100.0);
-            ui.heading("Loading");
-            ui.label("Spawning level...");
-            ui.add_space(10.0);
-        });
-    });
+/// The level does not contain any vehicles yet, so a buggy is parked next to where the player starts.
+fn spawn_demo_vehicle(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    players: Query<&Transform, Added<Player>>,
+    vehicles: Query<(), With<Vehicle>>,
+) {
+    if !vehicles.is_empty() {
+        return;
+    }
+    for transform in players.iter() {
+        let position = transform.translation + transform.right() * 3. + Vec3::Y;
+        commands.spawn((
+            Name::new("Buggy"),
+            SpawnPrefab::new(&asset_server, "buggy"),
+            SpatialBundle::from_transform(
+                Transform::from_translation(position).with_rotation(transform.rotation),
+            ),
+            DespawnOnExit(GameState::Playing),
+        ));
+    }
 }
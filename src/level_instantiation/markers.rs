@@ -0,0 +1,128 @@
+use crate::GameState;
+use anyhow::Result;
+use bevy::{ecs::world::EntityWorldMut, prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+/// Parses the bracketed tags in entity names, like "Crate [collider:box][prefab:barrel]", into [`Markers`].
+/// Each tag is parsed once when the name is added. Game code reacts to tags either by registering
+/// a handler with [`MarkersAppExt::register_marker`], which runs once when the markers are added,
+/// or by querying [`Markers`] directly.
+pub(crate) fn markers_plugin(app: &mut App) {
+    app.register_type::<Markers>()
+        .register_type::<Marker>()
+        .init_resource::<MarkerHandlers>()
+        .add_systems(
+            Update,
+            (parse_markers, apply_deferred, dispatch_markers)
+                .chain()
+                .in_set(MarkerSystemSet)
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
+pub(crate) struct MarkerSystemSet;
+
+/// All tags in the name of an entity, in order of appearance.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Markers(pub(crate) Vec<Marker>);
+
+impl Markers {
+    /// Parses every `[name]` or `[name:argument:...]` tag in `text`. Unterminated tags are ignored.
+    pub(crate) fn parse(text: &str) -> Self {
+        let mut markers = Vec::new();
+        let mut rest = text;
+        while let Some((_, tag)) = rest.split_once('[') {
+            let Some((tag, remainder)) = tag.split_once(']') else {
+                warn!("Unterminated marker in \"{text}\"");
+                break;
+            };
+            rest = remainder;
+            let mut parts = tag.split(':').map(str::trim);
+            let Some(name) = parts.next().filter(|name| !name.is_empty()) else {
+                continue;
+            };
+            markers.push(Marker {
+                name: name.to_lowercase(),
+                arguments: parts.map(str::to_string).collect(),
+            });
+        }
+        Self(markers)
+    }
+
+    /// The first tag with the given name.
+    pub(crate) fn get(&self, name: &str) -> Option<&Marker> {
+        self.0.iter().find(|marker| marker.name == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Serialize, Deserialize)]
+pub(crate) struct Marker {
+    /// Lowercase name of the tag, e.g. "collider" for `[collider:box]`
+    pub(crate) name: String,
+    pub(crate) arguments: Vec<String>,
+}
+
+impl Marker {
+    pub(crate) fn argument(&self, index: usize) -> Option<&str> {
+        self.arguments.get(index).map(String::as_str)
+    }
+}
+
+pub(crate) type MarkerHandler = fn(&mut EntityWorldMut, &Marker) -> Result<()>;
+
+#[derive(Clone, Resource, Default)]
+struct MarkerHandlers(HashMap<String, MarkerHandler>);
+
+pub(crate) trait MarkersAppExt {
+    /// Calls `handler` for every entity that gets a marker named `name`.
+    fn register_marker(&mut self, name: &str, handler: MarkerHandler) -> &mut Self;
+}
+
+impl MarkersAppExt for App {
+    fn register_marker(&mut self, name: &str, handler: MarkerHandler) -> &mut Self {
+        self.init_resource::<MarkerHandlers>();
+        self.world
+            .resource_mut::<MarkerHandlers>()
+            .0
+            .insert(name.to_lowercase(), handler);
+        self
+    }
+}
+
+fn parse_markers(mut commands: Commands, names: Query<(Entity, &Name), Added<Name>>) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("parse_markers").entered();
+    for (entity, name) in names.iter() {
+        let markers = Markers::parse(name.as_str());
+        if !markers.0.is_empty() {
+            commands.entity(entity).insert(markers);
+        }
+    }
+}
+
+fn dispatch_markers(world: &mut World, added: &mut QueryState<(Entity, &Markers), Added<Markers>>) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("dispatch_markers").entered();
+    let added: Vec<_> = added
+        .iter(world)
+        .map(|(entity, markers)| (entity, markers.clone()))
+        .collect();
+    world.resource_scope(|world, handlers: Mut<MarkerHandlers>| {
+        for (entity, markers) in added {
+            for marker in &markers.0 {
+                let Some(handler) = handlers.0.get(&marker.name) else {
+                    continue;
+                };
+                if let Err(error) = handler(&mut world.entity_mut(entity), marker) {
+                    warn!(
+                        "Failed to handle marker [{}] of {entity:?}: {error:#}",
+                        marker.name
+                    );
+                }
+            }
+        }
+    });
+}
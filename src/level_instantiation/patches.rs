@@ -0,0 +1,56 @@
+use crate::{
+    level_instantiation::{
+        levels::CurrentLevel,
+        post_spawn::{propagate_scene_transforms, PostSpawnStage},
+    },
+    GameState,
+};
+use bevy::{prelude::*, scene::SceneInstanceReady, utils::HashMap};
+use bevy_common_assets::ron::RonAssetPlugin;
+use serde::{Deserialize, Serialize};
+
+/// Applies changes made in the dev editor on top of the level's GLTF, so that objects can be moved around
+/// in the running game without going back to Blender. Every level has a [`ScenePatch`] next to its GLTF
+/// that overrides the transforms of objects by their name, which is unique within a Blender file.
+/// The patch is applied as soon as a scene is ready, before anything else looks at its objects.
+pub(crate) fn patches_plugin(app: &mut App) {
+    app.add_plugins(RonAssetPlugin::<ScenePatch>::new(&["patch.ron"]))
+        .add_systems(
+            Update,
+            apply_scene_patch
+                .in_set(PostSpawnStage::Ready)
+                .before(propagate_scene_transforms)
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+#[derive(Debug, Clone, PartialEq, Asset, TypePath, Serialize, Deserialize, Default)]
+pub(crate) struct ScenePatch {
+    /// Local transforms of objects by name
+    pub(crate) transforms: HashMap<String, Transform>,
+}
+
+fn apply_scene_patch(
+    current_level: Res<CurrentLevel>,
+    patches: Res<Assets<ScenePatch>>,
+    mut ready_scenes: EventReader<SceneInstanceReady>,
+    children: Query<&Children>,
+    mut entities: Query<(&Name, &mut Transform)>,
+) {
+    let Some(patch) = patches.get(&current_level.patch) else {
+        return;
+    };
+    for ready in ready_scenes.read() {
+        if patch.transforms.is_empty() {
+            continue;
+        }
+        for entity in children.iter_descendants(ready.parent) {
+            let Ok((name, mut transform)) = entities.get_mut(entity) else {
+                continue;
+            };
+            if let Some(patched) = patch.transforms.get(name.as_str()) {
+                *transform = *patched;
+            }
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use crate::level_instantiation::{markers::MarkerSystemSet, spawning::SpawnSystemSet};
+use bevy::{prelude::*, scene::SceneInstanceReady};
+
+/// Orders the work done on the objects of freshly spawned scenes, so that every step can rely on the ones before it
+/// instead of racing the scene spawner. The commands of each stage are applied before the next one runs.
+/// 1. [`PostSpawnStage::Ready`]: Once a scene has sent [`SceneInstanceReady`], the changes made in the dev editor
+///    are applied to it and the global transforms of its objects are brought up to date.
+/// 2. [`PostSpawnStage::Markers`]: Custom properties and the markers in names are turned into components.
+/// 3. [`PostSpawnStage::Colliders`]: Colliders are built from the meshes of marked objects.
+/// 4. [`PostSpawnStage::Objects`]: Game objects like the player and NPCs are spawned from their components.
+/// 5. [`PostSpawnStage::Batching`]: Static meshes are merged, if the level asks for it.
+/// 6. [`PostSpawnStage::NavMesh`]: Finished colliders are handed to the navmesh to be baked.
+pub(crate) fn post_spawn_plugin(app: &mut App) {
+    app.configure_sets(
+        Update,
+        (
+            PostSpawnStage::Ready,
+            PostSpawnStage::Markers,
+            PostSpawnStage::Colliders,
+            PostSpawnStage::Objects,
+            PostSpawnStage::Batching,
+            PostSpawnStage::NavMesh,
+        )
+            .chain(),
+    )
+    .configure_sets(
+        Update,
+        (
+            MarkerSystemSet.in_set(PostSpawnStage::Markers),
+            SpawnSystemSet.in_set(PostSpawnStage::Objects),
+        ),
+    )
+    .add_systems(
+        Update,
+        (
+            propagate_scene_transforms.in_set(PostSpawnStage::Ready),
+            apply_deferred
+                .after(PostSpawnStage::Ready)
+                .before(PostSpawnStage::Markers),
+            apply_deferred
+                .after(PostSpawnStage::Markers)
+                .before(PostSpawnStage::Colliders),
+            apply_deferred
+                .after(PostSpawnStage::Colliders)
+                .before(PostSpawnStage::Objects),
+            apply_deferred
+                .after(PostSpawnStage::Objects)
+                .before(PostSpawnStage::Batching),
+            apply_deferred
+                .after(PostSpawnStage::Batching)
+                .before(PostSpawnStage::NavMesh),
+        ),
+    );
+}
+
+/// The stages of [`post_spawn_plugin`], in the order they run in.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy, SystemSet)]
+pub(crate) enum PostSpawnStage {
+    Ready,
+    Markers,
+    Colliders,
+    Objects,
+    Batching,
+    NavMesh,
+}
+
+/// Brings the global transforms of the objects in ready scenes up to date, which Bevy would only do
+/// at the end of the frame. Later stages read them to place things, e.g. the stops of elevators.
+pub(crate) fn propagate_scene_transforms(
+    mut ready_scenes: EventReader<SceneInstanceReady>,
+    children: Query<&Children>,
+    mut transforms: Query<(&Transform, &mut GlobalTransform)>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("propagate_scene_transforms").entered();
+    for ready in ready_scenes.read() {
+        let Ok((_, root)) = transforms.get(ready.parent) else {
+            continue;
+        };
+        let mut stack = vec![(ready.parent, *root)];
+        while let Some((entity, parent_transform)) = stack.pop() {
+            for child in children.get(entity).into_iter().flatten() {
+                let Ok((transform, mut global_transform)) = transforms.get_mut(*child) else {
+                    continue;
+                };
+                let propagated = parent_transform.mul_transform(*transform);
+                *global_transform = propagated;
+                stack.push((*child, propagated));
+            }
+        }
+    }
+}
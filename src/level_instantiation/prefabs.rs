@@ -0,0 +1,301 @@
+use crate::{level_instantiation::markers::MarkersAppExt, GameState};
+use anyhow::{Context, Result};
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext, LoadState},
+    ecs::world::EntityWorldMut,
+    prelude::*,
+    reflect::{serde::TypedReflectDeserializer, TypeRegistry, TypeRegistryArc},
+    utils::BoxedFuture,
+};
+use serde::{
+    de::{DeserializeSeed, Error, MapAccess, SeqAccess, Visitor},
+    Deserializer,
+};
+use std::fmt;
+
+/// Spawns entities from blueprints, so that new kinds of objects don't need their own spawn function.
+/// A [`Prefab`] is a RON file in `assets/prefabs/<name>.prefab.ron` listing reflected components by their type name,
+/// an optional model scene and child prefabs:
+/// ```ron
+/// (
+///     model: Some("scenes/barrel.glb#Scene0"),
+///     components: {
+///         "Health": (current: 20, max: 20),
+///     },
+///     children: [],
+/// )
+/// ```
+/// A prefab is spawned by inserting a [`SpawnPrefab`] into an entity, which is then filled in once the prefab has loaded.
+/// Objects in a level are turned into prefabs by naming them with a `[prefab:<name>]` suffix.
+pub(crate) fn prefabs_plugin(app: &mut App) {
+    app.init_asset::<Prefab>()
+        .init_asset_loader::<PrefabLoader>()
+        .register_marker("prefab", |entity, marker| {
+            let name = marker.argument(0).context("Expected [prefab:<name>]")?;
+            let prefab = SpawnPrefab::new(entity.world().resource::<AssetServer>(), name);
+            entity.insert(prefab);
+            Ok(())
+        })
+        .add_systems(
+            Update,
+            instantiate_prefabs.run_if(in_state(GameState::Playing)),
+        );
+}
+
+/// Turns its entity into an instance of the prefab as soon as the prefab is loaded.
+#[derive(Debug, Clone, PartialEq, Component)]
+pub(crate) struct SpawnPrefab(pub(crate) Handle<Prefab>);
+
+impl SpawnPrefab {
+    pub(crate) fn new(asset_server: &AssetServer, name: &str) -> Self {
+        Self(asset_server.load(format!("prefabs/{name}.prefab.ron")))
+    }
+}
+
+#[derive(Asset, TypePath)]
+pub(crate) struct Prefab {
+    model: Option<Handle<Scene>>,
+    components: Vec<(ReflectComponent, Box<dyn Reflect>)>,
+    children: Vec<Prefab>,
+}
+
+impl Prefab {
+    fn insert_into(&self, entity: &mut EntityWorldMut) {
+        for (reflect_component, component) in &self.components {
+            reflect_component.insert(entity, component.as_reflect());
+        }
+        if !entity.contains::<Visibility>() {
+            entity.insert(SpatialBundle::default());
+        }
+        // The scene spawner spawns the model as children of the entity
+        if let Some(model) = &self.model {
+            entity.insert(model.clone());
+        }
+        for child in &self.children {
+            let child_entity = entity.world_scope(|world| {
+                let mut child_entity = world.spawn_empty();
+                child.insert_into(&mut child_entity);
+                child_entity.id()
+            });
+            entity.add_child(child_entity);
+        }
+    }
+}
+
+fn instantiate_prefabs(world: &mut World, pending: &mut QueryState<(Entity, &SpawnPrefab)>) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("instantiate_prefabs").entered();
+    let asset_server = world.resource::<AssetServer>();
+    let finished: Vec<_> = pending
+        .iter(world)
+        .filter_map(|(entity, spawn)| {
+            if asset_server.is_loaded_with_dependencies(&spawn.0) {
+                Some((entity, Some(spawn.0.clone())))
+            } else if asset_server.get_load_state(&spawn.0) == Some(LoadState::Failed) {
+                Some((entity, None))
+            } else {
+                None
+            }
+        })
+        .collect();
+    for (entity, handle) in finished {
+        world.entity_mut(entity).remove::<SpawnPrefab>();
+        let Some(handle) = handle else {
+            error!("Failed to load prefab for {entity:?}");
+            continue;
+        };
+        world.resource_scope(|world, prefabs: Mut<Assets<Prefab>>| {
+            if let Some(prefab) = prefabs.get(&handle) {
+                prefab.insert_into(&mut world.entity_mut(entity));
+            }
+        });
+    }
+}
+
+struct PrefabLoader {
+    type_registry: TypeRegistryArc,
+}
+
+impl FromWorld for PrefabLoader {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            type_registry: world.resource::<AppTypeRegistry>().0.clone(),
+        }
+    }
+}
+
+impl AssetLoader for PrefabLoader {
+    type Asset = Prefab;
+    type Settings = ();
+    type Error = anyhow::Error;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Prefab>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let mut deserializer = ron::de::Deserializer::from_bytes(&bytes)?;
+            let raw = {
+                let type_registry = self.type_registry.read();
+                RawPrefabDeserializer {
+                    type_registry: &type_registry,
+                }
+                .deserialize(&mut deserializer)
+                .with_context(|| {
+                    format!("Failed to parse prefab {}", load_context.path().display())
+                })?
+            };
+            Ok(raw.resolve(load_context))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["prefab.ron"]
+    }
+}
+
+/// A prefab as written in its file, before the model has been loaded.
+struct RawPrefab {
+    model: Option<String>,
+    components: Vec<(ReflectComponent, Box<dyn Reflect>)>,
+    children: Vec<RawPrefab>,
+}
+
+impl RawPrefab {
+    fn resolve(self, load_context: &mut LoadContext) -> Prefab {
+        Prefab {
+            model: self.model.map(|path| load_context.load(path)),
+            components: self.components,
+            children: self
+                .children
+                .into_iter()
+                .map(|child| child.resolve(load_context))
+                .collect(),
+        }
+    }
+}
+
+struct RawPrefabDeserializer<'a> {
+    type_registry: &'a TypeRegistry,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for RawPrefabDeserializer<'a> {
+    type Value = RawPrefab;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<RawPrefab, D::Error> {
+        deserializer.deserialize_struct("Prefab", &["model", "components", "children"], self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for RawPrefabDeserializer<'a> {
+    type Value = RawPrefab;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a prefab")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<RawPrefab, A::Error> {
+        let mut prefab = RawPrefab {
+            model: None,
+            components: Vec::new(),
+            children: Vec::new(),
+        };
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "model" => prefab.model = map.next_value()?,
+                "components" => {
+                    prefab.components = map.next_value_seed(ComponentsDeserializer {
+                        type_registry: self.type_registry,
+                    })?
+                }
+                "children" => {
+                    prefab.children = map.next_value_seed(ChildrenDeserializer {
+                        type_registry: self.type_registry,
+                    })?
+                }
+                _ => {
+                    return Err(A::Error::unknown_field(
+                        &key,
+                        &["model", "components", "children"],
+                    ))
+                }
+            }
+        }
+        Ok(prefab)
+    }
+}
+
+/// Reads a map from short type names, e.g. `"Health"`, to the reflected component's data.
+struct ComponentsDeserializer<'a> {
+    type_registry: &'a TypeRegistry,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for ComponentsDeserializer<'a> {
+    type Value = Vec<(ReflectComponent, Box<dyn Reflect>)>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for ComponentsDeserializer<'a> {
+    type Value = Vec<(ReflectComponent, Box<dyn Reflect>)>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map of component names to their data")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut components = Vec::new();
+        while let Some(name) = map.next_key::<String>()? {
+            let registration = self
+                .type_registry
+                .get_with_short_type_path(&name)
+                .ok_or_else(|| A::Error::custom(format!("No type named {name} is registered")))?;
+            let reflect_component = registration
+                .data::<ReflectComponent>()
+                .ok_or_else(|| A::Error::custom(format!("{name} does not reflect Component")))?
+                .clone();
+            let component = map.next_value_seed(TypedReflectDeserializer::new(
+                registration,
+                self.type_registry,
+            ))?;
+            components.push((reflect_component, component));
+        }
+        Ok(components)
+    }
+}
+
+struct ChildrenDeserializer<'a> {
+    type_registry: &'a TypeRegistry,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for ChildrenDeserializer<'a> {
+    type Value = Vec<RawPrefab>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for ChildrenDeserializer<'a> {
+    type Value = Vec<RawPrefab>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of child prefabs")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut children = Vec::new();
+        while let Some(child) = seq.next_element_seed(RawPrefabDeserializer {
+            type_registry: self.type_registry,
+        })? {
+            children.push(child);
+        }
+        Ok(children)
+    }
+}
@@ -0,0 +1,562 @@
+use crate::{
+    despawn::DespawnOnExit,
+    level_instantiation::{
+        levels::{CurrentLevel, LevelDefinition, LevelRegistry},
+        post_spawn::PostSpawnStage,
+        prefabs::SpawnPrefab,
+    },
+    movement::physics::CollisionLayer,
+    rng::{GameRng, RngStream},
+    GameState,
+};
+use anyhow::{Context, Result};
+use bevy::{asset::LoadState, gltf::Gltf, prelude::*, scene::SceneInstanceReady};
+use bevy_common_assets::ron::RonAssetPlugin;
+use bevy_mod_sysfail::*;
+use bevy_xpbd_3d::prelude::*;
+use oxidized_navigation::NavMeshAffector;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::FRAC_PI_2;
+
+const FLOOR_THICKNESS: f32 = 0.5;
+const WALL_THICKNESS: f32 = 0.25;
+/// Rooms may touch, but not overlap by more than this
+const OVERLAP_TOLERANCE: f32 = 0.01;
+/// Where the player arrives in a generated level
+const ENTRANCE: &str = "entrance";
+/// The generated level that comes with the template
+const EXAMPLE_LEVEL: &str = "Dungeon";
+
+/// Builds levels in code instead of loading them from a GLTF scene, as an example of procedural content.
+/// A level whose [`LevelDefinition::generator`] points to a [`RoomSet`] in `procgen/<name>.rooms.ron` is assembled
+/// from the rectangular room pieces listed there. Starting with the start piece, a random open socket of the rooms
+/// placed so far is connected to a matching socket of a random piece, turned so that both face each other,
+/// as long as the new room does not overlap the others. Doorways are cut into the walls where rooms connect,
+/// sockets that stay open are walled up.
+/// The rooms are greybox geometry with static colliders that the navmesh is baked from, and they are named with
+/// markers like hand-made levels, e.g. `[triplanar:<texture>]`, `[spawn:entrance]` in the start room and
+/// `[collectible:<set>:<id>]` in random other rooms, so the usual marker handlers turn them into game objects.
+/// The player and the camera come from the scene of the level's [`LevelDefinition`], of which only the objects
+/// listed in [`RoomSet::library`] are kept.
+/// The example level "Dungeon" can be entered through a `[portal:Dungeon:entrance]` in another level.
+pub(crate) fn procgen_plugin(app: &mut App) {
+    app.add_plugins(RonAssetPlugin::<RoomSet>::new(&["rooms.ron"]))
+        .init_resource::<LevelRegistry>();
+    app.world.resource_mut::<LevelRegistry>().insert(
+        EXAMPLE_LEVEL,
+        LevelDefinition {
+            path: "scenes/level.glb".to_string(),
+            scene: "Library".to_string(),
+            patch: "scenes/dungeon.patch.ron".to_string(),
+            intro_cutscene: None,
+            skybox: None,
+            batch_static_meshes: false,
+            generator: Some("procgen/dungeon.rooms.ron".to_string()),
+        },
+    );
+    app.add_systems(OnEnter(GameState::Loading), load_room_set)
+        .add_systems(
+            Update,
+            generate_level
+                .run_if(resource_exists::<PendingRoomSet>())
+                .before(PostSpawnStage::Ready)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            Update,
+            strip_library
+                .in_set(PostSpawnStage::Ready)
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+/// The pieces a level is assembled from and how
+#[derive(Debug, Clone, PartialEq, Asset, TypePath, Serialize, Deserialize)]
+pub(crate) struct RoomSet {
+    /// How many rooms to place. There are fewer if the pieces run out of room.
+    pub(crate) rooms: usize,
+    /// Name of the piece the level starts with, in which the player arrives
+    pub(crate) start: String,
+    pub(crate) pieces: Vec<RoomPiece>,
+    /// Width and height of the openings where two rooms connect
+    pub(crate) doorway: Vec2,
+    /// Image in `textures/` projected onto floors and walls
+    pub(crate) texture: String,
+    /// Names of the objects in the level's scene to keep, e.g. the player and the camera
+    pub(crate) library: Vec<String>,
+    #[serde(default)]
+    pub(crate) collectibles: Option<CollectiblePlacement>,
+    /// Fixes the layout. Without one, the layout is drawn from the [`GameRng`] and changes every session.
+    #[serde(default)]
+    pub(crate) seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct RoomPiece {
+    pub(crate) name: String,
+    /// Width, height and depth of the room. The origin is in the middle of its floor.
+    pub(crate) size: Vec3,
+    pub(crate) sockets: Vec<Socket>,
+    /// How likely the piece is picked compared to the others
+    #[serde(default = "default_weight")]
+    pub(crate) weight: f32,
+    /// Prefab spawned in the middle of the room to furnish it
+    #[serde(default)]
+    pub(crate) prefab: Option<String>,
+}
+
+fn default_weight() -> f32 {
+    1.
+}
+
+/// A place in a wall where another room can be connected
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Socket {
+    pub(crate) side: Side,
+    /// Distance from the middle of the wall, towards +X on the north and south walls and towards +Z on the others
+    #[serde(default)]
+    pub(crate) offset: f32,
+    /// Only sockets of the same kind are connected, e.g. so that corridors do not lead into each other
+    #[serde(default = "default_kind")]
+    pub(crate) kind: String,
+}
+
+fn default_kind() -> String {
+    "door".to_string()
+}
+
+impl Socket {
+    /// Where the socket is in the space of its room
+    fn position(&self, size: Vec3) -> Vec3 {
+        match self.side {
+            Side::North => Vec3::new(self.offset, 0., -size.z / 2.),
+            Side::East => Vec3::new(size.x / 2., 0., self.offset),
+            Side::South => Vec3::new(self.offset, 0., size.z / 2.),
+            Side::West => Vec3::new(-size.x / 2., 0., self.offset),
+        }
+    }
+}
+
+/// The walls of a room, in clockwise order when seen from above
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum Side {
+    /// Facing -Z
+    North,
+    /// Facing +X
+    East,
+    /// Facing +Z
+    South,
+    /// Facing -X
+    West,
+}
+
+impl Side {
+    const ALL: [Side; 4] = [Side::North, Side::East, Side::South, Side::West];
+
+    /// The side this one ends up as after turning its room clockwise by `turns` quarter turns
+    fn turned(self, turns: usize) -> Side {
+        Self::ALL[(self as usize + turns) % 4]
+    }
+
+    fn normal(self) -> Vec3 {
+        match self {
+            Side::North => Vec3::NEG_Z,
+            Side::East => Vec3::X,
+            Side::South => Vec3::Z,
+            Side::West => Vec3::NEG_X,
+        }
+    }
+}
+
+/// Scatters collectibles of one set over the generated rooms, at most one per room
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct CollectiblePlacement {
+    pub(crate) set: String,
+    pub(crate) count: usize,
+}
+
+/// The room set of the level being entered, until it has been loaded and the level generated
+#[derive(Debug, Clone, PartialEq, Resource)]
+struct PendingRoomSet(Handle<RoomSet>);
+
+/// The scene of a generated level that the player and other objects are taken from
+#[derive(Debug, Clone, PartialEq, Component)]
+struct LibraryScene {
+    keep: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct PlacedRoom {
+    piece: usize,
+    origin: Vec3,
+    /// Clockwise quarter turns around the Y axis
+    turns: usize,
+    /// Which sockets of the piece lead into another room
+    connected: Vec<bool>,
+}
+
+impl PlacedRoom {
+    fn rotation(&self) -> Quat {
+        Quat::from_rotation_y(-FRAC_PI_2 * self.turns as f32)
+    }
+
+    /// The corners of the room's floor with the smallest and largest X and Z
+    fn bounds(&self, piece: &RoomPiece) -> (Vec2, Vec2) {
+        let half_size = if self.turns % 2 == 0 {
+            Vec2::new(piece.size.x, piece.size.z)
+        } else {
+            Vec2::new(piece.size.z, piece.size.x)
+        } / 2.;
+        let center = Vec2::new(self.origin.x, self.origin.z);
+        (center - half_size, center + half_size)
+    }
+}
+
+fn load_room_set(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    registry: Res<LevelRegistry>,
+    current_level: Res<CurrentLevel>,
+) {
+    commands.remove_resource::<PendingRoomSet>();
+    let Some(path) = registry
+        .get(&current_level.name)
+        .and_then(|definition| definition.generator.as_ref())
+    else {
+        return;
+    };
+    commands.insert_resource(PendingRoomSet(asset_server.load(path)));
+}
+
+#[sysfail(log(level = "error"))]
+fn generate_level(
+    mut commands: Commands,
+    pending: Res<PendingRoomSet>,
+    room_sets: Res<Assets<RoomSet>>,
+    asset_server: Res<AssetServer>,
+    models: Res<Assets<Gltf>>,
+    registry: Res<LevelRegistry>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut game_rng: ResMut<GameRng>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) -> Result<()> {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("generate_level").entered();
+    if asset_server.load_state(pending.0.id()) == LoadState::Failed {
+        commands.remove_resource::<PendingRoomSet>();
+        anyhow::bail!(
+            "Failed to load the room set of level \"{}\"",
+            current_level.name
+        );
+    }
+    let Some(room_set) = room_sets.get(&pending.0) else {
+        return Ok(());
+    };
+    commands.remove_resource::<PendingRoomSet>();
+
+    let mut fixed_rng;
+    let rng = match room_set.seed {
+        Some(seed) => {
+            fixed_rng = GameRng::keyed(RngStream::Levels, seed);
+            &mut fixed_rng
+        }
+        None => game_rng.stream(RngStream::Levels),
+    };
+    let rooms = lay_out_rooms(room_set, rng)?;
+    if rooms.len() < room_set.rooms {
+        warn!(
+            "Only {} of {} rooms fit into level \"{}\"",
+            rooms.len(),
+            room_set.rooms,
+            current_level.name
+        );
+    }
+    info!(
+        "Generated {} rooms for level \"{}\"",
+        rooms.len(),
+        current_level.name
+    );
+
+    let definition = registry
+        .get(&current_level.name)
+        .context("Failed to get the definition of the current level")?;
+    let library = models
+        .get(&current_level.gltf)
+        .context("Failed to get the GLTF of the current level")?
+        .named_scenes
+        .get(&definition.scene)
+        .with_context(|| format!("Level GLTF has no scene named \"{}\"", definition.scene))?;
+    commands.spawn((
+        SceneBundle {
+            scene: library.clone(),
+            ..default()
+        },
+        LibraryScene {
+            keep: room_set.library.clone(),
+        },
+        Name::new("Library"),
+        DespawnOnExit(GameState::Playing),
+    ));
+    if current_level.spawn_point.is_none() {
+        current_level.spawn_point = Some(ENTRANCE.to_string());
+    }
+
+    let cube = meshes.add(Mesh::from(shape::Cube { size: 1. }));
+    // Replaced by the triplanar material of the texture
+    let material = materials.add(StandardMaterial::default());
+    let gem = meshes.add(Mesh::from(shape::UVSphere {
+        radius: 0.5,
+        ..default()
+    }));
+    let gem_material = materials.add(StandardMaterial {
+        base_color: Color::GOLD,
+        emissive: Color::GOLD * 2.,
+        ..default()
+    });
+    let mut collectible_rooms: Vec<_> = (1..rooms.len()).collect();
+    collectible_rooms.shuffle(rng);
+
+    commands
+        .spawn((
+            Name::new("Generated Level"),
+            SpatialBundle::default(),
+            DespawnOnExit(GameState::Playing),
+        ))
+        .with_children(|parent| {
+            for (index, room) in rooms.iter().enumerate() {
+                let piece = &room_set.pieces[room.piece];
+                parent
+                    .spawn((
+                        Name::new(format!("Room {index}: {}", piece.name)),
+                        SpatialBundle::from_transform(
+                            Transform::from_translation(room.origin).with_rotation(room.rotation()),
+                        ),
+                    ))
+                    .with_children(|parent| {
+                        build_room(parent, room_set, room, &cube, &material);
+                        if let Some(prefab) = &piece.prefab {
+                            parent.spawn((
+                                Name::new(format!("{} furnishing", piece.name)),
+                                SpawnPrefab::new(&asset_server, prefab),
+                                SpatialBundle::default(),
+                            ));
+                        }
+                    });
+            }
+            parent.spawn((
+                Name::new(format!("Entrance [spawn:{ENTRANCE}]")),
+                SpatialBundle::from_transform(Transform::from_xyz(0., 1., 0.)),
+            ));
+            let Some(collectibles) = &room_set.collectibles else {
+                return;
+            };
+            for (id, &room) in collectible_rooms
+                .iter()
+                .take(collectibles.count)
+                .enumerate()
+            {
+                parent.spawn((
+                    Name::new(format!(
+                        "Collectible [collectible:{}:{id}]",
+                        collectibles.set
+                    )),
+                    PbrBundle {
+                        mesh: gem.clone(),
+                        material: gem_material.clone(),
+                        transform: Transform::from_translation(rooms[room].origin + Vec3::Y)
+                            .with_scale(Vec3::splat(0.5)),
+                        ..default()
+                    },
+                ));
+            }
+        });
+    Ok(())
+}
+
+/// Places rooms one after another at the open sockets of those placed before
+fn lay_out_rooms(room_set: &RoomSet, rng: &mut StdRng) -> Result<Vec<PlacedRoom>> {
+    let pieces = &room_set.pieces;
+    let start = pieces
+        .iter()
+        .position(|piece| piece.name == room_set.start)
+        .with_context(|| format!("Room set has no start piece \"{}\"", room_set.start))?;
+    let mut rooms = vec![PlacedRoom {
+        piece: start,
+        origin: Vec3::ZERO,
+        turns: 0,
+        connected: vec![false; pieces[start].sockets.len()],
+    }];
+    let mut open: Vec<_> = (0..pieces[start].sockets.len())
+        .map(|socket| (0, socket))
+        .collect();
+
+    while rooms.len() < room_set.rooms && !open.is_empty() {
+        let (room_index, socket_index) = open.swap_remove(rng.gen_range(0..open.len()));
+        let room = &rooms[room_index];
+        let socket = &pieces[room.piece].sockets[socket_index];
+        let position = room.origin + room.rotation() * socket.position(pieces[room.piece].size);
+        let facing = socket.side.turned(room.turns);
+        let kind = &socket.kind;
+
+        // Weighted random order, see Efraimidis and Spirakis, "Weighted random sampling with a reservoir"
+        let mut candidates: Vec<_> = pieces
+            .iter()
+            .enumerate()
+            .flat_map(|(piece_index, piece)| {
+                piece
+                    .sockets
+                    .iter()
+                    .enumerate()
+                    .filter(move |(_, candidate)| &candidate.kind == kind)
+                    .map(move |(candidate_index, _)| (piece_index, candidate_index))
+            })
+            .map(|(piece_index, candidate_index)| {
+                let weight = pieces[piece_index].weight.max(f32::EPSILON);
+                (
+                    rng.gen::<f32>().powf(1. / weight),
+                    piece_index,
+                    candidate_index,
+                )
+            })
+            .collect();
+        candidates.sort_by(|(a, ..), (b, ..)| b.total_cmp(a));
+
+        for (_, piece_index, candidate_index) in candidates {
+            let piece = &pieces[piece_index];
+            let candidate = &piece.sockets[candidate_index];
+            // Turned so that the new socket faces the open one
+            let turns = (facing as usize + 2 + 4 - candidate.side as usize) % 4;
+            let mut new_room = PlacedRoom {
+                piece: piece_index,
+                origin: Vec3::ZERO,
+                turns,
+                connected: vec![false; piece.sockets.len()],
+            };
+            new_room.origin = position - new_room.rotation() * candidate.position(piece.size);
+            let (min, max) = new_room.bounds(piece);
+            let overlaps = rooms.iter().any(|other| {
+                let (other_min, other_max) = other.bounds(&pieces[other.piece]);
+                (min + OVERLAP_TOLERANCE).cmplt(other_max).all()
+                    && (other_min + OVERLAP_TOLERANCE).cmplt(max).all()
+            });
+            if overlaps {
+                continue;
+            }
+            new_room.connected[candidate_index] = true;
+            rooms[room_index].connected[socket_index] = true;
+            let new_index = rooms.len();
+            open.extend(
+                (0..piece.sockets.len())
+                    .filter(|socket| *socket != candidate_index)
+                    .map(|socket| (new_index, socket)),
+            );
+            rooms.push(new_room);
+            break;
+        }
+    }
+    Ok(rooms)
+}
+
+/// Spawns the floor and walls of `room` as children of its entity, leaving doorways at its connected sockets
+fn build_room(
+    parent: &mut ChildBuilder,
+    room_set: &RoomSet,
+    room: &PlacedRoom,
+    cube: &Handle<Mesh>,
+    material: &Handle<StandardMaterial>,
+) {
+    let piece = &room_set.pieces[room.piece];
+    let size = piece.size;
+    let mut block = |name: &str, center: Vec3, extents: Vec3| {
+        parent.spawn((
+            Name::new(format!("{name} [triplanar:{}]", room_set.texture)),
+            PbrBundle {
+                mesh: cube.clone(),
+                material: material.clone(),
+                transform: Transform::from_translation(center).with_scale(extents),
+                ..default()
+            },
+            // Scaled by the transform
+            Collider::cuboid(1., 1., 1.),
+            RigidBody::Static,
+            CollisionLayer::terrain(),
+            NavMeshAffector,
+        ));
+    };
+    block(
+        "Floor",
+        Vec3::new(0., -FLOOR_THICKNESS / 2., 0.),
+        Vec3::new(size.x, FLOOR_THICKNESS, size.z),
+    );
+
+    for side in Side::ALL {
+        let normal = side.normal();
+        // The walls along X span the whole room, the others fit between them
+        let (along, half_length, depth) = match side {
+            Side::North | Side::South => (Vec3::X, size.x / 2., size.z / 2.),
+            Side::East | Side::West => (Vec3::Z, size.z / 2. - WALL_THICKNESS, size.x / 2.),
+        };
+        let mut segment = |from: f32, to: f32, bottom: f32, top: f32| {
+            if to - from <= f32::EPSILON || top - bottom <= f32::EPSILON {
+                return;
+            }
+            block(
+                "Wall",
+                normal * (depth - WALL_THICKNESS / 2.)
+                    + along * (from + to) / 2.
+                    + Vec3::Y * (bottom + top) / 2.,
+                along * (to - from) + normal.abs() * WALL_THICKNESS + Vec3::Y * (top - bottom),
+            );
+        };
+        let mut doorways: Vec<_> = piece
+            .sockets
+            .iter()
+            .zip(&room.connected)
+            .filter(|(socket, connected)| **connected && socket.side == side)
+            .map(|(socket, _)| socket.offset)
+            .collect();
+        doorways.sort_by(f32::total_cmp);
+        let half_width = room_set.doorway.x / 2.;
+        let mut start = -half_length;
+        for offset in doorways {
+            segment(start, offset - half_width, 0., size.y);
+            segment(
+                offset - half_width,
+                offset + half_width,
+                room_set.doorway.y,
+                size.y,
+            );
+            start = offset + half_width;
+        }
+        segment(start, half_length, 0., size.y);
+    }
+}
+
+/// Removes everything from the library scene of a generated level that its [`RoomSet`] does not keep,
+/// before the markers of the removed objects are handled
+fn strip_library(
+    mut commands: Commands,
+    mut ready_scenes: EventReader<SceneInstanceReady>,
+    libraries: Query<&LibraryScene>,
+    children: Query<&Children>,
+    names: Query<&Name>,
+) {
+    for ready in ready_scenes.read() {
+        let Ok(library) = libraries.get(ready.parent) else {
+            continue;
+        };
+        // The objects of the scene are the topmost named entities, below the unnamed root of the GLTF scene
+        let mut stack = vec![ready.parent];
+        while let Some(entity) = stack.pop() {
+            for &child in children.get(entity).into_iter().flatten() {
+                match names.get(child) {
+                    Ok(name) if library.keep.iter().any(|kept| kept == name.as_str()) => {}
+                    Ok(_) => commands.entity(child).despawn_recursive(),
+                    Err(_) => stack.push(child),
+                }
+            }
+        }
+    }
+}
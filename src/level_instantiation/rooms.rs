@@ -0,0 +1,179 @@
+use crate::{
+    level_instantiation::markers::{Marker, MarkersAppExt},
+    player_control::camera::IngameCamera,
+    GameState,
+};
+use anyhow::{Context, Result};
+use bevy::{
+    ecs::world::EntityWorldMut,
+    math::Vec3A,
+    prelude::*,
+    render::{
+        primitives::{Aabb, Frustum},
+        view::VisibilitySystems,
+    },
+    utils::{HashMap, HashSet},
+};
+use serde::{Deserialize, Serialize};
+
+/// Culls whole rooms of indoor levels that cannot be seen from the room the camera is in,
+/// which frustum culling alone does not catch when the camera looks at a wall with more rooms behind it.
+/// In Blender, objects named with a `[room:<name>]` suffix group everything parented to them into a room,
+/// which covers the bounding box of all meshes within it.
+/// Objects named `[portal_plane:<room>:<room>]` connect two rooms, e.g. through a doorway or window.
+/// Like other marker volumes, a portal is sized by its scale, spanning its local X and Z axes.
+/// A room is shown when it can be reached from the camera's room through portals in view.
+/// When the camera is in no room, e.g. outdoors, every room is shown.
+pub(crate) fn rooms_plugin(app: &mut App) {
+    app.register_type::<Room>()
+        .register_type::<PortalPlane>()
+        .register_marker("room", |entity, marker| {
+            let name = marker.argument(0).context("Expected [room:<name>]")?;
+            entity.insert(Room {
+                name: name.to_string(),
+            });
+            Ok(())
+        })
+        .register_marker("portal_plane", insert_portal_plane)
+        .add_systems(
+            PostUpdate,
+            (measure_rooms, cull_rooms)
+                .chain()
+                .after(VisibilitySystems::UpdatePerspectiveFrusta)
+                .before(VisibilitySystems::VisibilityPropagate)
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Room {
+    pub(crate) name: String,
+}
+
+/// An opening through which one room can be seen from the other
+#[derive(Debug, Clone, Eq, PartialEq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct PortalPlane {
+    pub(crate) rooms: [String; 2],
+}
+
+/// The world space box covered by a room's meshes, measured once they have their bounds
+#[derive(Debug, Clone, Copy, PartialEq, Component)]
+struct RoomBounds {
+    min: Vec3,
+    max: Vec3,
+}
+
+impl RoomBounds {
+    fn contains(&self, point: Vec3) -> bool {
+        point.cmpge(self.min).all() && point.cmple(self.max).all()
+    }
+}
+
+fn insert_portal_plane(entity: &mut EntityWorldMut, marker: &Marker) -> Result<()> {
+    let (Some(first), Some(second)) = (marker.argument(0), marker.argument(1)) else {
+        anyhow::bail!("Expected [portal_plane:<room>:<room>]");
+    };
+    entity.insert((
+        PortalPlane {
+            rooms: [first.to_string(), second.to_string()],
+        },
+        Visibility::Hidden,
+    ));
+    Ok(())
+}
+
+fn measure_rooms(
+    mut commands: Commands,
+    rooms: Query<(Entity, &Room), Without<RoomBounds>>,
+    children: Query<&Children>,
+    meshes: Query<(&Aabb, &GlobalTransform), With<Handle<Mesh>>>,
+) {
+    for (entity, room) in rooms.iter() {
+        let corners = children
+            .iter_descendants(entity)
+            .filter_map(|child| meshes.get(child).ok())
+            .flat_map(|(aabb, transform)| {
+                let (center, half_extents) =
+                    (Vec3::from(aabb.center), Vec3::from(aabb.half_extents));
+                [-1., 1.].into_iter().flat_map(move |x| {
+                    [-1., 1.].into_iter().flat_map(move |y| {
+                        [-1., 1.].into_iter().map(move |z| {
+                            transform.transform_point(center + half_extents * Vec3::new(x, y, z))
+                        })
+                    })
+                })
+            });
+        let (min, max) = corners.fold(
+            (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+            |(min, max), corner| (min.min(corner), max.max(corner)),
+        );
+        // The meshes get their bounds a frame after spawning
+        if min.cmpgt(max).any() {
+            continue;
+        }
+        debug!("Room \"{}\" spans {min} to {max}", room.name);
+        commands.entity(entity).insert(RoomBounds { min, max });
+    }
+}
+
+fn cull_rooms(
+    cameras: Query<(&GlobalTransform, &Frustum), With<IngameCamera>>,
+    mut rooms: Query<(&Room, &RoomBounds, &mut Visibility)>,
+    portals: Query<(&PortalPlane, &GlobalTransform)>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("cull_rooms").entered();
+    let Some((camera_transform, frustum)) = cameras.iter().next() else {
+        return;
+    };
+    let camera_position = camera_transform.translation();
+    let Some(camera_room) = rooms
+        .iter()
+        .find(|(_, bounds, _)| bounds.contains(camera_position))
+        .map(|(room, _, _)| room.name.clone())
+    else {
+        for (_, _, mut visibility) in rooms.iter_mut() {
+            if *visibility != Visibility::Inherited {
+                *visibility = Visibility::Inherited;
+            }
+        }
+        return;
+    };
+
+    let portal_bounds = Aabb {
+        center: Vec3A::ZERO,
+        half_extents: Vec3A::new(0.5, 0., 0.5),
+    };
+    let mut neighbors = HashMap::<&str, Vec<&str>>::new();
+    for (portal, transform) in portals.iter() {
+        if !frustum.intersects_obb(&portal_bounds, &transform.affine(), true, true) {
+            continue;
+        }
+        let [first, second] = &portal.rooms;
+        neighbors.entry(first).or_default().push(second);
+        neighbors.entry(second).or_default().push(first);
+    }
+    let mut visible = HashSet::new();
+    visible.insert(camera_room.as_str());
+    let mut frontier = vec![camera_room.as_str()];
+    while let Some(room) = frontier.pop() {
+        for &neighbor in neighbors.get(room).into_iter().flatten() {
+            if visible.insert(neighbor) {
+                frontier.push(neighbor);
+            }
+        }
+    }
+
+    for (room, _, mut visibility) in rooms.iter_mut() {
+        let target = if visible.contains(room.name.as_str()) {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        if *visibility != target {
+            *visibility = target;
+        }
+    }
+}
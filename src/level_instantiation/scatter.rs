@@ -0,0 +1,180 @@
+use crate::{
+    despawn::DespawnOnExit,
+    level_instantiation::{markers::MarkersAppExt, prefabs::SpawnPrefab},
+    movement::physics::CollisionLayer,
+    rng::{GameRng, RngStream},
+    GameState,
+};
+use anyhow::Context;
+use bevy::{prelude::*, utils::HashMap};
+use bevy_xpbd_3d::{prelude::*, PhysicsSet};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::hash_map::DefaultHasher,
+    f32::consts::TAU,
+    hash::{Hash, Hasher},
+};
+
+/// Candidate positions tried per requested instance before giving up on finding room for it
+const TRIES_PER_INSTANCE: usize = 8;
+/// Physics steps to wait for the ground below a region to get its collider before giving up
+const MAX_ATTEMPTS: u32 = 120;
+
+/// Dresses levels by distributing prefab instances over the ground.
+/// A region is an object named with a `[scatter:<prefab>:<count>]` or `[scatter:<prefab>:<count>:<seed>]` suffix.
+/// Its scale spans a box in which rays are cast down onto the terrain; every hit that is flat enough and not too close
+/// to another instance gets a randomly rotated and scaled [`SpawnPrefab`].
+/// The same seed always produces the same layout. Without one, the seed is derived from the region's name.
+pub(crate) fn scatter_plugin(app: &mut App) {
+    app.register_type::<Scatter>()
+        .register_marker("scatter", |entity, marker| {
+            let prefab = marker
+                .argument(0)
+                .context("Expected [scatter:<prefab>:<count>]")?;
+            let count = marker
+                .argument(1)
+                .context("Expected [scatter:<prefab>:<count>]")?
+                .parse()
+                .context("Failed to parse scatter count")?;
+            let seed = match marker.argument(2) {
+                Some(seed) => seed.parse().context("Failed to parse scatter seed")?,
+                None => {
+                    let mut hasher = DefaultHasher::new();
+                    entity.get::<Name>().map(Name::as_str).hash(&mut hasher);
+                    hasher.finish()
+                }
+            };
+            entity.insert((
+                Scatter {
+                    prefab: prefab.to_string(),
+                    count,
+                    seed,
+                    ..default()
+                },
+                Visibility::Hidden,
+            ));
+            Ok(())
+        })
+        // Raycasts only see colliders once a physics step has added them to the spatial query pipeline
+        .add_systems(
+            FixedUpdate,
+            scatter_props
+                .after(PhysicsSet::StepSimulation)
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+/// A region of the level covered with instances of a prefab.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Scatter {
+    pub(crate) prefab: String,
+    pub(crate) count: usize,
+    pub(crate) seed: u64,
+    /// Steepest ground an instance is placed on, in radians
+    pub(crate) max_slope: f32,
+    /// Closest two instances may be to each other
+    pub(crate) min_distance: f32,
+    /// How much the scale of an instance may deviate from 1
+    pub(crate) scale_jitter: f32,
+}
+
+impl Default for Scatter {
+    fn default() -> Self {
+        Self {
+            prefab: default(),
+            count: 10,
+            seed: 0,
+            max_slope: 30_f32.to_radians(),
+            min_distance: 1.5,
+            scale_jitter: 0.2,
+        }
+    }
+}
+
+/// The region has been filled and is not looked at again.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Component)]
+struct Scattered;
+
+fn scatter_props(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    regions: Query<(Entity, &Scatter, &GlobalTransform), Without<Scattered>>,
+    spatial_query: SpatialQuery,
+    mut attempts: Local<HashMap<Entity, u32>>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("scatter_props").entered();
+    let ground = CollisionLayer::ground_filter();
+    for (entity, scatter, transform) in regions.iter() {
+        let mut rng = GameRng::keyed(RngStream::Scatter, scatter.seed);
+        let mut placed: Vec<Transform> = Vec::with_capacity(scatter.count);
+        let mut any_hit = false;
+        for _ in 0..scatter.count * TRIES_PER_INSTANCE {
+            if placed.len() >= scatter.count {
+                break;
+            }
+            // Draw everything up front so that the layout only depends on the seed and the ground
+            let x = rng.gen_range(-1.0..=1.0);
+            let z = rng.gen_range(-1.0..=1.0);
+            let yaw = rng.gen_range(0.0..TAU);
+            let scale = 1. + rng.gen_range(-1.0..=1.0) * scatter.scale_jitter;
+
+            let top = transform.transform_point(Vec3::new(x, 1., z));
+            let bottom = transform.transform_point(Vec3::new(x, -1., z));
+            let Some(hit) =
+                spatial_query.cast_ray(top, Vec3::NEG_Y, top.y - bottom.y, true, ground.clone())
+            else {
+                continue;
+            };
+            any_hit = true;
+            if hit.normal.angle_between(Vec3::Y) > scatter.max_slope {
+                continue;
+            }
+            let position = top + Vec3::NEG_Y * hit.time_of_impact;
+            if placed
+                .iter()
+                .any(|other| other.translation.distance(position) < scatter.min_distance)
+            {
+                continue;
+            }
+            placed.push(
+                Transform::from_translation(position)
+                    .with_rotation(Quat::from_rotation_y(yaw))
+                    .with_scale(Vec3::splat(scale)),
+            );
+        }
+
+        if !any_hit {
+            // The ground's collider might not have been built yet
+            let attempt = attempts.entry(entity).or_default();
+            *attempt += 1;
+            if *attempt < MAX_ATTEMPTS {
+                continue;
+            }
+            warn!(
+                "Scatter region {entity:?} for \"{}\" has no ground below it",
+                scatter.prefab
+            );
+        }
+        attempts.remove(&entity);
+        if placed.len() < scatter.count {
+            debug!(
+                "Only found room for {} of {} \"{}\" in scatter region {entity:?}",
+                placed.len(),
+                scatter.count,
+                scatter.prefab
+            );
+        }
+        for (index, transform) in placed.into_iter().enumerate() {
+            commands.spawn((
+                Name::new(format!("{} {index}", scatter.prefab)),
+                SpawnPrefab::new(&asset_server, &scatter.prefab),
+                SpatialBundle::from_transform(transform),
+                DespawnOnExit(GameState::Playing),
+            ));
+        }
+        commands.entity(entity).insert(Scattered);
+    }
+}
@@ -1,80 +1,165 @@
-use crate::{level_instantiation::spawning::objects::*, GameState};
+use crate::{
+    errors::report_error,
+    level_instantiation::{
+        markers::MarkersAppExt, post_spawn::PostSpawnStage, spawning::objects::*,
+    },
+    GameState,
+};
 use anyhow::{Context, Result};
 use bevy::{
-    gltf::GltfExtras, prelude::*, reflect::serde::TypedReflectDeserializer, utils::HashMap,
+    ecs::world::EntityWorldMut,
+    gltf::GltfExtras,
+    prelude::*,
+    reflect::{serde::TypedReflectDeserializer, TypeInfo},
+    utils::HashMap,
 };
 
-use bevy_xpbd_3d::PhysicsSet;
 use serde::{de::DeserializeSeed, Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Value};
 use std::ops::Deref;
 
 pub(crate) mod objects;
 
 pub(crate) fn spawning_plugin(app: &mut App) {
     app.register_type::<camera::IngameCameraMarker>()
+        .register_type::<camera::CameraAnchor>()
         .register_type::<orb::Orb>()
         .register_type::<sunlight::Sun>()
         .register_type::<Hidden>()
         .register_type::<ground::Grass>()
-        .add_systems(Update, add_components_from_gltf_extras.map(Result::unwrap))
+        .register_type::<ground::TextureTiling>()
+        .register_marker("ground", ground::insert_texture_tiling)
+        .register_gltf_extra("tiling", |entity, value| {
+            let scale = value.as_f64().context("Expected a number")?;
+            entity.insert(ground::TextureTiling {
+                scale: scale as f32,
+            });
+            Ok(())
+        })
+        .register_type::<mount::Mount>()
+        .init_resource::<GltfExtrasRegistry>()
+        .add_systems(
+            Update,
+            add_components_from_gltf_extras.in_set(PostSpawnStage::Markers),
+        )
+        .add_systems(
+            Update,
+            (lights::import, camera::import_anchors)
+                .in_set(PostSpawnStage::Ready)
+                .run_if(in_state(GameState::Playing)),
+        )
         .add_systems(
             Update,
             (
-                ground::spawn,
+                ground::spawn.pipe(report_error),
+                ground::apply_texture_tiling.pipe(report_error),
                 camera::spawn,
                 orb::spawn,
-                player::spawn,
-                npc::spawn,
+                player::spawn.pipe(report_error),
+                npc::spawn.pipe(report_error),
+                mount::spawn.pipe(report_error),
                 sunlight::spawn,
-                hide.after(PhysicsSet::Sync),
+                vehicle::spawn,
+                water::spawn,
+                hide,
             )
+                .in_set(SpawnSystemSet)
                 .run_if(in_state(GameState::Playing)),
         );
 }
 
-// Reads the extras filed from the GLTF. In Blender, this is the "Custom Attributes" you can set on an object.
-// We treat each extra as a indication that we want to inject a marker struct for populating the object later.
-// See this as a simplified version of https://github.com/kaosat-dev/Blender_bevy_components_workflow/tree/main/crates/bevy_gltf_components
+/// Turns the objects of a freshly spawned level into game objects
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
+pub(crate) struct SpawnSystemSet;
 
-fn add_components_from_gltf_extras(world: &mut World) -> Result<()> {
-    let mut extras = world.query::<(Entity, &GltfExtras)>();
-    let mut components = HashMap::new();
-    for (entity, extra) in extras.iter(world) {
-        let Ok(json) = serde_json::from_str::<Value>(&extra.value) else {
-            continue;
-        };
-        let Some(object) = json.as_object() else {
-            continue;
-        };
-        let component_names: Vec<_> = object.keys().map(|k| k.to_string()).collect();
-        components.insert(entity, component_names);
+/// Converts custom properties that are not named after a component, like `"health": 50`, into components.
+#[derive(Clone, Resource, Default)]
+pub(crate) struct GltfExtrasRegistry(HashMap<String, GltfExtraHandler>);
+
+pub(crate) type GltfExtraHandler = fn(&mut EntityWorldMut, &Value) -> Result<()>;
+
+pub(crate) trait GltfExtrasAppExt {
+    /// Lets the custom property `key` be converted into components by `handler`.
+    fn register_gltf_extra(&mut self, key: &str, handler: GltfExtraHandler) -> &mut Self;
+}
+
+impl GltfExtrasAppExt for App {
+    fn register_gltf_extra(&mut self, key: &str, handler: GltfExtraHandler) -> &mut Self {
+        self.init_resource::<GltfExtrasRegistry>();
+        self.world
+            .resource_mut::<GltfExtrasRegistry>()
+            .0
+            .insert(key.to_string(), handler);
+        self
     }
-    for (entity, component_names) in components {
-        for component_name in component_names {
-            let (type_registration, component, component_name) = {
-                let type_registry: &AppTypeRegistry = world.resource();
-                let type_registry = type_registry.read();
-                let Some(type_registration) =
-                    type_registry.get_with_short_type_path(&component_name)
-                else {
-                    warn!("No type or ambiguous registration found for component {component_name}");
-                    continue;
-                };
-                let reflection_deserializer =
-                    TypedReflectDeserializer::new(type_registration, type_registry.deref());
-                let mut ron_deserializer = ron::Deserializer::from_str(&component_name)?;
-                let component = reflection_deserializer.deserialize(&mut ron_deserializer)?;
-                (type_registration.clone(), component, component_name)
-            };
+}
 
-            let mut entity_mut = world.entity_mut(entity);
-            type_registration
-                .data::<ReflectComponent>()
-                .with_context(|| format!("{component_name} does not reflect Component"))?
-                .insert(&mut entity_mut, &*component);
+// Reads the extras filed from the GLTF. In Blender, this is the "Custom Properties" you can set on an object.
+// Each property is either named after a reflected component and holds its data, e.g. `"Health": {"current": 50, "max": 50}`,
+// or is handled by the `GltfExtrasRegistry`, e.g. `"health": 50`. The data of marker components is ignored.
+// See this as a simplified version of https://github.com/kaosat-dev/Blender_bevy_components_workflow/tree/main/crates/bevy_gltf_components
+fn add_components_from_gltf_extras(
+    world: &mut World,
+    extras: &mut QueryState<(Entity, &GltfExtras), Added<GltfExtras>>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("add_components_from_gltf_extras").entered();
+    let properties: Vec<_> = extras
+        .iter(world)
+        .filter_map(|(entity, extras)| {
+            match serde_json::from_str::<Map<String, Value>>(&extras.value) {
+                Ok(properties) => Some((entity, properties)),
+                Err(error) => {
+                    warn!("Failed to parse GLTF extras of {entity:?}: {error}");
+                    None
+                }
+            }
+        })
+        .collect();
+    for (entity, properties) in properties {
+        for (key, value) in properties {
+            if let Err(error) = add_component_from_gltf_extra(world, entity, &key, &value) {
+                warn!("Failed to read custom property \"{key}\" of {entity:?}: {error:#}");
+            }
         }
     }
+}
+
+fn add_component_from_gltf_extra(
+    world: &mut World,
+    entity: Entity,
+    key: &str,
+    value: &Value,
+) -> Result<()> {
+    let handler = world
+        .get_resource::<GltfExtrasRegistry>()
+        .and_then(|registry| registry.0.get(key))
+        .copied();
+    if let Some(handler) = handler {
+        return handler(&mut world.entity_mut(entity), value);
+    }
+    let (reflect_component, component) = {
+        let type_registry = world.resource::<AppTypeRegistry>().read();
+        let type_registration = type_registry
+            .get_with_short_type_path(key)
+            .with_context(|| format!("No component or handler named {key} is registered"))?;
+        let reflect_component = type_registration
+            .data::<ReflectComponent>()
+            .with_context(|| format!("{key} does not reflect Component"))?
+            .clone();
+        // Blender does not allow properties without a value, so markers get whatever the artist put in
+        let is_marker = matches!(type_registration.type_info(), TypeInfo::Struct(info) if info.field_len() == 0);
+        let value = if is_marker {
+            Value::Object(default())
+        } else {
+            value.clone()
+        };
+        let component = TypedReflectDeserializer::new(type_registration, type_registry.deref())
+            .deserialize(value)
+            .with_context(|| format!("Failed to deserialize {key}"))?;
+        (reflect_component, component)
+    };
+    reflect_component.insert(&mut world.entity_mut(entity), &*component);
     Ok(())
 }
 
@@ -83,6 +168,8 @@ fn add_components_from_gltf_extras(world: &mut World) -> Result<()> {
 pub(crate) struct Hidden;
 
 fn hide(hidden: Query<Entity, Added<Hidden>>, mut commands: Commands) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("hide").entered();
     for entity in hidden.iter() {
         commands.entity(entity).insert(Visibility::Hidden);
     }
@@ -1,19 +1,13 @@
 pub(crate) mod camera;
+pub(crate) mod lights;
+pub(crate) mod mount;
 pub(crate) mod npc;
 pub(crate) mod orb;
 pub(crate) mod player;
 pub(crate) mod sunlight;
+pub(crate) mod vehicle;
+pub(crate) mod water;
 
 pub(crate) mod ground;
 
 mod util;
-use bevy_xpbd_3d::prelude::*;
-
-#[derive(PhysicsLayer)]
-pub(crate) enum CollisionLayer {
-    Player,
-    Character,
-    Terrain,
-    CameraObstacle,
-    Sensor,
-}
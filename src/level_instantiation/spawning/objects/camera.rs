@@ -1,7 +1,7 @@
 use crate::player_control::{
     actions::create_camera_action_input_manager_bundle, camera::IngameCamera,
 };
-use bevy::prelude::*;
+use bevy::{prelude::*, scene::SceneInstanceReady};
 use bevy_atmosphere::prelude::*;
 use bevy_dolly::prelude::*;
 #[cfg(feature = "dev")]
@@ -12,7 +12,17 @@ use serde::{Deserialize, Serialize};
 #[reflect(Component, Serialize, Deserialize)]
 pub(crate) struct IngameCameraMarker;
 
+/// A camera placed in Blender, kept as a fixed viewpoint instead of rendering on its own.
+/// Cutscene keyframes can refer to it by name to frame a shot exactly as it was set up in Blender.
+#[derive(Debug, Clone, Eq, PartialEq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct CameraAnchor {
+    pub(crate) name: String,
+}
+
 pub(crate) fn spawn(camera: Query<Entity, Added<IngameCameraMarker>>, mut commands: Commands) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("spawn_camera").entered();
     for entity in camera.iter() {
         commands.entity(entity).insert((
             Camera3dBundle::default(),
@@ -31,3 +41,31 @@ pub(crate) fn spawn(camera: Query<Entity, Added<IngameCameraMarker>>, mut comman
         ));
     }
 }
+
+/// Turns the cameras Bevy imports from a ready scene into [`CameraAnchor`]s, so that they do not render
+/// on top of the ingame camera. Anchors are named after their object, or after its parent if it has no name.
+pub(crate) fn import_anchors(
+    mut commands: Commands,
+    mut ready_scenes: EventReader<SceneInstanceReady>,
+    children: Query<&Children>,
+    cameras: Query<(Option<&Name>, Option<&Parent>), (With<Camera>, Without<IngameCamera>)>,
+    names: Query<&Name>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("import_camera_anchors").entered();
+    for ready in ready_scenes.read() {
+        for entity in children.iter_descendants(ready.parent) {
+            let Ok((name, parent)) = cameras.get(entity) else {
+                continue;
+            };
+            let name = name
+                .or_else(|| parent.and_then(|parent| names.get(parent.get()).ok()))
+                .map(|name| name.to_string())
+                .unwrap_or_default();
+            commands
+                .entity(entity)
+                .remove::<Camera3dBundle>()
+                .insert(CameraAnchor { name });
+        }
+    }
+}
@@ -1,22 +1,119 @@
-use bevy::prelude::*;
+use crate::level_instantiation::markers::Marker;
+use anyhow::{Context, Result};
+use bevy::{
+    ecs::world::EntityWorldMut,
+    prelude::*,
+    render::{
+        mesh::VertexAttributeValues,
+        texture::{ImageAddressMode, ImageSampler, ImageSamplerDescriptor},
+    },
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Eq, PartialEq, Component, Reflect, Serialize, Deserialize, Default)]
 #[reflect(Component, Serialize, Deserialize)]
 pub(crate) struct Grass;
 
+/// Repeats the textures of the object's own material `scale` times across its UVs.
+/// Set in Blender with a `[ground:scale=4]` suffix or the custom property `"tiling": 4`.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct TextureTiling {
+    pub(crate) scale: f32,
+}
+
+impl Default for TextureTiling {
+    fn default() -> Self {
+        Self { scale: 1. }
+    }
+}
+
+pub(crate) fn insert_texture_tiling(entity: &mut EntityWorldMut, marker: &Marker) -> Result<()> {
+    let scale = match marker.argument(0) {
+        Some(argument) => argument
+            .strip_prefix("scale=")
+            .context("Expected [ground:scale=<scale>]")?
+            .parse()
+            .context("Failed to parse texture scale")?,
+        None => TextureTiling::default().scale,
+    };
+    entity.insert(TextureTiling { scale });
+    Ok(())
+}
+
 pub(crate) fn spawn(
     sun: Query<&Children, Added<Grass>>,
     material_handles: Query<&Handle<StandardMaterial>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-) {
+) -> Result<()> {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("spawn_ground").entered();
     for children in sun.iter() {
         for child in children.iter() {
             if let Ok(material_handle) = material_handles.get(*child) {
-                let material = materials.get_mut(material_handle).unwrap();
+                let material = materials
+                    .get_mut(material_handle)
+                    .context("Failed to get the ground's material")?;
                 // Blender doesn't export this unfortunately, so we'll have to fix the glossy ground manually
                 material.reflectance = 0.05;
             }
         }
     }
+    Ok(())
+}
+
+/// Materials in Bevy have no UV transform, so the tiling is applied to a copy of each child mesh's UVs.
+/// The textures of the material are switched to repeat, which does not change how they look on other objects,
+/// as long as those keep their UVs within the texture.
+pub(crate) fn apply_texture_tiling(
+    tiled: Query<(&TextureTiling, &Children), Added<TextureTiling>>,
+    mut mesh_handles: Query<(&mut Handle<Mesh>, Option<&Handle<StandardMaterial>>)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    materials: Res<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+) -> Result<()> {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("apply_texture_tiling").entered();
+    for (tiling, children) in tiled.iter() {
+        for child in children.iter() {
+            let Ok((mut mesh_handle, material_handle)) = mesh_handles.get_mut(*child) else {
+                continue;
+            };
+            let mut mesh = meshes
+                .get(&*mesh_handle)
+                .context("Failed to get the tiled mesh")?
+                .clone();
+            let Some(VertexAttributeValues::Float32x2(uvs)) =
+                mesh.attribute_mut(Mesh::ATTRIBUTE_UV_0)
+            else {
+                warn!("Cannot tile the textures of a mesh without UVs");
+                continue;
+            };
+            for uv in uvs.iter_mut() {
+                *uv = uv.map(|coordinate| coordinate * tiling.scale);
+            }
+            *mesh_handle = meshes.add(mesh);
+
+            let Some(material) = material_handle.and_then(|handle| materials.get(handle)) else {
+                continue;
+            };
+            let textures = [
+                &material.base_color_texture,
+                &material.normal_map_texture,
+                &material.metallic_roughness_texture,
+                &material.occlusion_texture,
+                &material.emissive_texture,
+            ];
+            for texture in textures.into_iter().flatten() {
+                if let Some(image) = images.get_mut(texture) {
+                    image.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+                        address_mode_u: ImageAddressMode::Repeat,
+                        address_mode_v: ImageAddressMode::Repeat,
+                        ..ImageSamplerDescriptor::linear()
+                    });
+                }
+            }
+        }
+    }
+    Ok(())
 }
@@ -0,0 +1,34 @@
+use crate::file_system_interaction::config::GameConfig;
+use bevy::{prelude::*, scene::SceneInstanceReady};
+
+/// Adjusts the lights Bevy imports from the `KHR_lights_punctual` extension once their scene is ready.
+/// Bevy already converts GLTF's candela into lumens for point and spot lights and takes the lux of suns as they are.
+/// Blender's exporter derives both from its own watts, so they are scaled by the `lights` section of the
+/// [`GameConfig`] to fit the exposure of the game's camera.
+pub(crate) fn import(
+    mut ready_scenes: EventReader<SceneInstanceReady>,
+    config: Res<GameConfig>,
+    children: Query<&Children>,
+    mut point_lights: Query<&mut PointLight>,
+    mut spot_lights: Query<&mut SpotLight>,
+    mut directional_lights: Query<&mut DirectionalLight>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("import_lights").entered();
+    let config = &config.lights;
+    for ready in ready_scenes.read() {
+        for entity in children.iter_descendants(ready.parent) {
+            if let Ok(mut light) = point_lights.get_mut(entity) {
+                light.intensity *= config.intensity_scale;
+                light.shadows_enabled = config.shadows;
+            }
+            if let Ok(mut light) = spot_lights.get_mut(entity) {
+                light.intensity *= config.intensity_scale;
+                light.shadows_enabled = config.shadows;
+            }
+            if let Ok(mut light) = directional_lights.get_mut(entity) {
+                light.illuminance *= config.illuminance_scale;
+            }
+        }
+    }
+}
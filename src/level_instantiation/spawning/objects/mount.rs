@@ -0,0 +1,79 @@
+use crate::{
+    movement::{
+        character_controller::{CharacterAnimations, CharacterControllerBundle, Walk},
+        physics::CollisionLayer,
+    },
+    world_interaction::triggers::{Trigger, TriggerKind},
+};
+use anyhow::Result;
+use bevy::prelude::*;
+use bevy_xpbd_3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A creature the player can ride. Add it to a character in Blender; the character controller,
+/// animations and the collider used to start riding are added here.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Mount {
+    /// Top speed while ridden
+    pub(crate) speed: f32,
+    /// How fast the mount turns towards where the rider steers it, in radians per second
+    pub(crate) turn_speed: f32,
+    /// Name of the bone the rider sits on. The mount's origin is used if no bone has this name.
+    pub(crate) seat_bone: String,
+    /// Offset of the rider from the seat bone
+    pub(crate) seat_offset: Vec3,
+    pub(crate) height: f32,
+    pub(crate) radius: f32,
+    /// Path of the [`AnimationGraph`](crate::movement::character_controller::AnimationGraph) the mount is animated with
+    pub(crate) animation_graph: String,
+}
+
+impl Default for Mount {
+    fn default() -> Self {
+        Self {
+            speed: 12.,
+            turn_speed: 2.5,
+            seat_bone: "Seat".to_string(),
+            seat_offset: Vec3::new(0., 0.2, 0.),
+            height: 0.6,
+            radius: 0.5,
+            animation_graph: "animations/character.animgraph.ron".to_string(),
+        }
+    }
+}
+
+pub(crate) fn spawn(
+    mounts: Query<(Entity, &Transform, &Mount), Added<Mount>>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) -> Result<()> {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("spawn_mount").entered();
+    for (entity, transform, mount) in mounts.iter() {
+        let mut controller =
+            CharacterControllerBundle::capsule(mount.height, mount.radius, transform.scale.y);
+        controller.walking = Walk {
+            speed: mount.speed,
+            ..default()
+        };
+        commands
+            .entity(entity)
+            .insert((
+                controller,
+                CharacterAnimations {
+                    graph: asset_server.load(mount.animation_graph.clone()),
+                },
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    Name::new("Mount Interaction Collider"),
+                    Collider::cylinder(mount.height, mount.radius * 4.),
+                    CollisionLayer::trigger(),
+                    Sensor,
+                    Trigger::new(TriggerKind::Interaction),
+                ));
+            });
+    }
+    Ok(())
+}
@@ -1,25 +1,34 @@
 use crate::{
-    file_system_interaction::asset_loading::GltfAssets,
-    level_instantiation::spawning::objects::{player, CollisionLayer},
+    facial_animation::Face,
+    file_system_interaction::asset_loading::AnimationAssets,
+    hud::world_labels::WorldLabel,
+    level_instantiation::spawning::objects::player,
     movement::{
-        character_controller::{CharacterAnimations, CharacterControllerBundle},
+        character_controller::{
+            CharacterAnimations, CharacterControllerBundle, FootIk, SpringBones,
+        },
+        companion::Companion,
         navigation::Follower,
+        physics::CollisionLayer,
+        ragdoll::Ragdoll,
+    },
+    world_interaction::{
+        dialog::DialogTarget,
+        triggers::{Trigger, TriggerKind},
     },
-    world_interaction::dialog::DialogTarget,
 };
-use bevy::{gltf::Gltf, prelude::*};
+use anyhow::Result;
+use bevy::prelude::*;
 use bevy_xpbd_3d::prelude::*;
 
 pub(crate) fn spawn(
     follower: Query<(Entity, &Transform), Added<Follower>>,
-    gltf_assets: Res<GltfAssets>,
-    gltfs: Res<Assets<Gltf>>,
+    animation_assets: Res<AnimationAssets>,
     mut commands: Commands,
-) {
+) -> Result<()> {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("spawn_npc").entered();
     for (entity, transform) in follower.iter() {
-        let level = gltfs.get(gltf_assets.level.clone()).unwrap();
-        let animations = &level.named_animations;
-
         commands
             .entity(entity)
             .insert((
@@ -29,23 +38,29 @@ pub(crate) fn spawn(
                     transform.scale.y,
                 ),
                 Follower,
+                Companion::default(),
                 CharacterAnimations {
-                    idle: animations["Idle"].clone(),
-                    walk: animations["Walk"].clone(),
-                    aerial: animations["Run"].clone(),
+                    graph: animation_assets.character.clone(),
                 },
+                FootIk::default(),
+                SpringBones::default(),
+                Face::default(),
+                Ragdoll::default(),
                 DialogTarget {
                     speaker: "The Follower".to_string(),
                     node: "Follower".to_string(),
                 },
+                WorldLabel::nametag("The Follower"),
             ))
             .with_children(|parent| {
                 parent.spawn((
                     Name::new("NPC Dialog Collider"),
                     Collider::cylinder(player::HEIGHT / 2., player::RADIUS * 5.),
-                    CollisionLayers::new([CollisionLayer::Sensor], [CollisionLayer::Player]),
+                    CollisionLayer::trigger(),
                     Sensor,
+                    Trigger::new(TriggerKind::Interaction),
                 ));
             });
     }
+    Ok(())
 }
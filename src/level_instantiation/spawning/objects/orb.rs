@@ -26,6 +26,8 @@ pub(crate) fn spawn(
     materials: Res<ShaderMaterials>,
     children: Query<&Children>,
 ) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("spawn_orb").entered();
     for entity in orb.iter() {
         let mesh_handle = get_or_add_mesh_handle(&mut meshes);
         children.iter_descendants(entity).for_each(|child| {
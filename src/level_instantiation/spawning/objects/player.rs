@@ -1,16 +1,30 @@
 use crate::{
-    file_system_interaction::asset_loading::GltfAssets,
-    level_instantiation::spawning::objects::CollisionLayer,
-    movement::character_controller::{CharacterAnimations, CharacterControllerBundle},
+    character_customization::CharacterAppearance,
+    combat::{
+        health::Health,
+        hitboxes::{Hurtbox, Team},
+        status_effects::StatusEffects,
+    },
+    file_system_interaction::asset_loading::AnimationAssets,
+    hud::Hotbar,
+    movement::{
+        character_controller::{
+            CharacterAnimations, CharacterControllerBundle, FootIk, SpringBones,
+        },
+        physics::CollisionLayer,
+        ragdoll::Ragdoll,
+    },
     particles,
     player_control::{
         actions::{
             create_player_action_input_manager_bundle, create_ui_action_input_manager_bundle,
         },
         player_embodiment::Player,
+        progression::Progression,
     },
 };
-use bevy::{gltf::Gltf, prelude::*};
+use anyhow::Result;
+use bevy::prelude::*;
 use bevy_hanabi::EffectAsset;
 
 pub(crate) const HEIGHT: f32 = 0.4;
@@ -19,28 +33,34 @@ pub(crate) const RADIUS: f32 = 0.3;
 pub(crate) fn spawn(
     player: Query<(Entity, &Transform), Added<Player>>,
     mut commands: Commands,
-    gltf_assets: Res<GltfAssets>,
-    gltfs: Res<Assets<Gltf>>,
+    animation_assets: Res<AnimationAssets>,
     mut effects: ResMut<Assets<EffectAsset>>,
-) {
+) -> Result<()> {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("spawn_player").entered();
     for (entity, transform) in player.iter() {
         let mut controller = CharacterControllerBundle::capsule(HEIGHT, RADIUS, transform.scale.y);
-        controller.collision_layers = controller
-            .collision_layers
-            .add_group(CollisionLayer::Player);
-
-        let level = gltfs.get(gltf_assets.level.clone()).unwrap();
-        let animations = &level.named_animations;
+        controller.collision_layers = CollisionLayer::player();
 
         commands
             .entity(entity)
             .insert((
                 controller,
                 CharacterAnimations {
-                    idle: animations["Idle"].clone(),
-                    walk: animations["Walk"].clone(),
-                    aerial: animations["Run"].clone(),
+                    graph: animation_assets.character.clone(),
+                },
+                FootIk::default(),
+                SpringBones::default(),
+                Ragdoll::default(),
+                CharacterAppearance::default(),
+                Health::default(),
+                StatusEffects::default(),
+                Hurtbox {
+                    team: Team::Player,
+                    ..default()
                 },
+                Hotbar::default(),
+                Progression::default(),
                 create_player_action_input_manager_bundle(),
                 create_ui_action_input_manager_bundle(),
             ))
@@ -49,4 +69,5 @@ pub(crate) fn spawn(
                 parent.spawn(particle_bundle);
             });
     }
+    Ok(())
 }
@@ -9,6 +9,8 @@ pub(crate) fn spawn(
     sun: Query<&Children, Added<Sun>>,
     mut directional_lights: Query<&mut DirectionalLight>,
 ) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("spawn_sunlight").entered();
     for children in sun.iter() {
         for child in children.iter() {
             if let Ok(mut light) = directional_lights.get_mut(*child) {
@@ -0,0 +1,112 @@
+use crate::{
+    level_instantiation::spawning::objects::{player, util::MeshAssetsExt},
+    movement::{
+        physics::CollisionLayer,
+        vehicle::{Vehicle, VehicleControls, Wheel},
+    },
+    player_control::actions::create_vehicle_action_input_manager_bundle,
+    world_interaction::triggers::{Trigger, TriggerKind},
+};
+use bevy::prelude::*;
+use bevy_xpbd_3d::prelude::*;
+use std::f32::consts::FRAC_PI_2;
+
+const CHASSIS_HALF_EXTENTS: Vec3 = Vec3::new(0.8, 0.25, 1.2);
+
+fn get_or_add_chassis_mesh(mesh_assets: &mut Assets<Mesh>) -> Handle<Mesh> {
+    const MESH_HANDLE: Handle<Mesh> = Handle::weak_from_u128(0x8c3f0a2e51d7b46);
+    mesh_assets.get_or_add(MESH_HANDLE, || {
+        Mesh::from(shape::Box::new(
+            CHASSIS_HALF_EXTENTS.x * 2.,
+            CHASSIS_HALF_EXTENTS.y * 2.,
+            CHASSIS_HALF_EXTENTS.z * 2.,
+        ))
+    })
+}
+
+fn get_or_add_wheel_mesh(mesh_assets: &mut Assets<Mesh>, radius: f32) -> Handle<Mesh> {
+    const MESH_HANDLE: Handle<Mesh> = Handle::weak_from_u128(0x2b71e9d04c6a835);
+    mesh_assets.get_or_add(MESH_HANDLE, || {
+        Mesh::from(shape::Cylinder {
+            radius,
+            height: 0.25,
+            ..default()
+        })
+    })
+}
+
+/// Builds a buggy out of primitive shapes around every entity that gets a [`Vehicle`].
+pub(crate) fn spawn(
+    vehicles: Query<Entity, Added<Vehicle>>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("spawn_vehicle").entered();
+    for entity in vehicles.iter() {
+        let wheel = Wheel::default();
+        let chassis_mesh = get_or_add_chassis_mesh(&mut meshes);
+        let wheel_mesh = get_or_add_wheel_mesh(&mut meshes, wheel.radius);
+        let chassis_material = materials.add(Color::rgb(0.8, 0.3, 0.1).into());
+        let wheel_material = materials.add(Color::rgb(0.1, 0.1, 0.1).into());
+
+        commands
+            .entity(entity)
+            .insert((
+                RigidBody::Dynamic,
+                Collider::cuboid(
+                    CHASSIS_HALF_EXTENTS.x * 2.,
+                    CHASSIS_HALF_EXTENTS.y * 2.,
+                    CHASSIS_HALF_EXTENTS.z * 2.,
+                ),
+                ColliderDensity(200.),
+                CollisionLayer::vehicle(),
+                ExternalForce::default(),
+                VehicleControls::default(),
+                create_vehicle_action_input_manager_bundle(),
+                chassis_mesh,
+                chassis_material,
+            ))
+            .with_children(|parent| {
+                for (x, z) in [(-1., -1.), (1., -1.), (-1., 1.), (1., 1.)] {
+                    let front = z < 0.;
+                    let mount = Vec3::new(
+                        x * (CHASSIS_HALF_EXTENTS.x + 0.05),
+                        -0.1,
+                        z * (CHASSIS_HALF_EXTENTS.z - 0.4),
+                    );
+                    parent
+                        .spawn((
+                            Name::new("Wheel"),
+                            Wheel {
+                                mount,
+                                steered: front,
+                                driven: !front,
+                                ..wheel.clone()
+                            },
+                            SpatialBundle::from_transform(Transform::from_translation(mount)),
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(PbrBundle {
+                                mesh: wheel_mesh.clone(),
+                                material: wheel_material.clone(),
+                                // The cylinder stands upright, but the axle points sideways
+                                transform: Transform::from_rotation(Quat::from_rotation_z(
+                                    FRAC_PI_2,
+                                )),
+                                ..default()
+                            });
+                        });
+                }
+                parent.spawn((
+                    Name::new("Vehicle Interaction Collider"),
+                    Collider::cylinder(player::HEIGHT * 2., CHASSIS_HALF_EXTENTS.z + 0.5),
+                    ColliderDensity(0.),
+                    CollisionLayer::trigger(),
+                    Sensor,
+                    Trigger::new(TriggerKind::Interaction),
+                ));
+            });
+    }
+}
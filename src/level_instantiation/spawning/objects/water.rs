@@ -0,0 +1,42 @@
+use crate::movement::fluids::FluidVolume;
+use bevy::{pbr::NotShadowCaster, prelude::*};
+
+/// Draws the surface of every [`FluidVolume`] as a translucent plane covering the volume's top.
+pub(crate) fn spawn(
+    volumes: Query<(Entity, &FluidVolume), Added<FluidVolume>>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("spawn_water").entered();
+    for (entity, volume) in volumes.iter() {
+        let mesh = meshes.add(
+            shape::Box::new(
+                2. * volume.half_extents.x,
+                0.001,
+                2. * volume.half_extents.z,
+            )
+            .into(),
+        );
+        let material = materials.add(StandardMaterial {
+            base_color: volume.color,
+            alpha_mode: AlphaMode::Blend,
+            perceptual_roughness: 0.1,
+            reflectance: 0.6,
+            ..default()
+        });
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                Name::new("Water Surface"),
+                PbrBundle {
+                    mesh,
+                    material,
+                    transform: Transform::from_xyz(0., volume.half_extents.y, 0.),
+                    ..default()
+                },
+                NotShadowCaster,
+            ));
+        });
+    }
+}
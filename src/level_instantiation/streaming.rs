@@ -0,0 +1,255 @@
+use crate::{
+    file_system_interaction::game_state_serialization::GameSaveRequest,
+    level_instantiation::markers::MarkersAppExt, player_control::player_embodiment::Player,
+    GameState,
+};
+use anyhow::Context;
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use serde::{Deserialize, Serialize};
+
+/// Side length of the square cells the world is divided into for streaming
+const CHUNK_SIZE: f32 = 64.;
+/// Chunks within this many cells of the player's cell are loaded
+const LOAD_RADIUS: i32 = 1;
+/// Loaded chunks are only unloaded once they are this many cells away, so that walking along
+/// a cell border does not load and unload the same chunk over and over
+const UNLOAD_RADIUS: i32 = 2;
+/// Seconds a chunk has to stay out of range before it is unloaded
+const UNLOAD_DELAY: f32 = 5.;
+
+/// Splits large worlds into chunk scenes that are loaded and unloaded depending on the player's position.
+/// A chunk is an empty in the level named with a `[chunk:<name>]` suffix, which streams in `scenes/chunks/<name>.glb`
+/// as its child once the player comes close. The scene is loaded asynchronously and spawned when it is ready.
+///
+/// Entities in a chunk that are marked as [`Persistent`] keep their transform and whether they were despawned
+/// across unloading the chunk. This state is kept in [`ChunkStates`] and written to save files.
+pub(crate) fn streaming_plugin(app: &mut App) {
+    app.register_type::<StreamedChunk>()
+        .register_type::<Persistent>()
+        .register_marker("chunk", |entity, marker| {
+            let chunk = marker.argument(0).context("Expected [chunk:<name>]")?;
+            entity.insert(StreamedChunk {
+                path: format!("scenes/chunks/{chunk}.glb#Scene0"),
+            });
+            Ok(())
+        })
+        .init_resource::<ChunkStates>()
+        .init_resource::<TrackedPersistents>()
+        .add_systems(
+            Update,
+            (
+                stream_chunks,
+                track_persistents,
+                record_removed_persistents,
+                record_loaded_chunks.run_if(on_event::<GameSaveRequest>()),
+            )
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(OnExit(GameState::Playing), reset_chunk_states);
+}
+
+/// A part of the world that is streamed in from its own scene file.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct StreamedChunk {
+    /// Asset path of the chunk's scene. Also identifies the chunk in [`ChunkStates`].
+    pub(crate) path: String,
+}
+
+/// Marks an entity in a [`StreamedChunk`] whose transform and existence survive unloading the chunk.
+/// Persistent entities are identified by their [`Name`], which must be unique within their chunk.
+#[derive(Debug, Clone, Eq, PartialEq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Persistent;
+
+/// The modifications made to [`Persistent`] entities, by chunk.
+#[derive(Debug, Clone, PartialEq, Resource, Serialize, Deserialize, Default)]
+pub(crate) struct ChunkStates(HashMap<String, ChunkState>);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub(crate) struct ChunkState {
+    /// Last known local transforms of persistent entities by name
+    transforms: HashMap<String, Transform>,
+    /// Persistent entities that were despawned, e.g. because they were picked up
+    removed: HashSet<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Component, Default)]
+enum ChunkStatus {
+    #[default]
+    Unloaded,
+    Loading(Handle<Scene>),
+    Loaded {
+        scene: Entity,
+        /// Seconds the chunk has been out of range
+        out_of_range: f32,
+    },
+}
+
+/// The chunk and name of every spawned [`Persistent`] entity, so that they can still be looked up after despawning.
+#[derive(Debug, Clone, PartialEq, Resource, Default)]
+struct TrackedPersistents(HashMap<Entity, (String, String)>);
+
+fn cell(position: Vec3) -> IVec2 {
+    IVec2::new(
+        (position.x / CHUNK_SIZE).floor() as i32,
+        (position.z / CHUNK_SIZE).floor() as i32,
+    )
+}
+
+fn stream_chunks(
+    mut commands: Commands,
+    time: Res<Time<Virtual>>,
+    asset_server: Res<AssetServer>,
+    players: Query<&GlobalTransform, With<Player>>,
+    mut chunks: Query<(
+        Entity,
+        &StreamedChunk,
+        &GlobalTransform,
+        Option<&mut ChunkStatus>,
+    )>,
+    transforms: Query<&Transform>,
+    mut tracked: ResMut<TrackedPersistents>,
+    mut states: ResMut<ChunkStates>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("stream_chunks").entered();
+    let Some(player_cell) = players.iter().next().map(|t| cell(t.translation())) else {
+        return;
+    };
+    let dt = time.delta_seconds();
+    for (entity, chunk, transform, status) in chunks.iter_mut() {
+        let Some(mut status) = status else {
+            commands.entity(entity).insert(ChunkStatus::default());
+            continue;
+        };
+        let distance = (cell(transform.translation()) - player_cell)
+            .abs()
+            .max_element();
+        match status.as_mut() {
+            ChunkStatus::Unloaded if distance <= LOAD_RADIUS => {
+                *status = ChunkStatus::Loading(asset_server.load(&chunk.path));
+            }
+            ChunkStatus::Unloaded => {}
+            // Dropping the handle cancels the load
+            ChunkStatus::Loading(_) if distance > UNLOAD_RADIUS => {
+                *status = ChunkStatus::Unloaded;
+            }
+            ChunkStatus::Loading(handle) => {
+                if !asset_server.is_loaded_with_dependencies(handle.id()) {
+                    continue;
+                }
+                let scene = commands
+                    .spawn((
+                        Name::new("Chunk Scene"),
+                        SceneBundle {
+                            scene: handle.clone(),
+                            ..default()
+                        },
+                    ))
+                    .set_parent(entity)
+                    .id();
+                *status = ChunkStatus::Loaded {
+                    scene,
+                    out_of_range: 0.,
+                };
+            }
+            ChunkStatus::Loaded {
+                scene,
+                out_of_range,
+            } => {
+                if distance <= UNLOAD_RADIUS {
+                    *out_of_range = 0.;
+                    continue;
+                }
+                *out_of_range += dt;
+                if *out_of_range < UNLOAD_DELAY {
+                    continue;
+                }
+                // Untrack before despawning so that unloading does not count as removing the entities
+                let state = states.0.entry(chunk.path.clone()).or_default();
+                tracked.0.retain(|entity, (path, name)| {
+                    if *path != chunk.path {
+                        return true;
+                    }
+                    if let Ok(transform) = transforms.get(*entity) {
+                        state.transforms.insert(name.clone(), *transform);
+                    }
+                    false
+                });
+                commands.entity(*scene).despawn_recursive();
+                *status = ChunkStatus::Unloaded;
+            }
+        }
+    }
+}
+
+fn track_persistents(
+    mut commands: Commands,
+    mut persistents: Query<(Entity, &Name, &mut Transform), Added<Persistent>>,
+    chunks: Query<&StreamedChunk>,
+    parents: Query<&Parent>,
+    states: Res<ChunkStates>,
+    mut tracked: ResMut<TrackedPersistents>,
+) {
+    for (entity, name, mut transform) in persistents.iter_mut() {
+        let Some(chunk) = parents
+            .iter_ancestors(entity)
+            .find_map(|ancestor| chunks.get(ancestor).ok())
+        else {
+            continue;
+        };
+        let name = name.to_string();
+        if let Some(state) = states.0.get(&chunk.path) {
+            if state.removed.contains(&name) {
+                commands.entity(entity).despawn_recursive();
+                continue;
+            }
+            if let Some(saved) = state.transforms.get(&name) {
+                *transform = *saved;
+            }
+        }
+        tracked.0.insert(entity, (chunk.path.clone(), name));
+    }
+}
+
+fn record_removed_persistents(
+    mut removed: RemovedComponents<Persistent>,
+    mut tracked: ResMut<TrackedPersistents>,
+    mut states: ResMut<ChunkStates>,
+) {
+    for entity in removed.read() {
+        if let Some((path, name)) = tracked.0.remove(&entity) {
+            let state = states.0.entry(path).or_default();
+            state.transforms.remove(&name);
+            state.removed.insert(name);
+        }
+    }
+}
+
+/// Writes the state of the chunks that are currently loaded into [`ChunkStates`] so that it can be saved.
+pub(crate) fn record_loaded_chunks(
+    tracked: Res<TrackedPersistents>,
+    transforms: Query<&Transform>,
+    mut states: ResMut<ChunkStates>,
+) {
+    for (entity, (path, name)) in tracked.0.iter() {
+        if let Ok(transform) = transforms.get(*entity) {
+            states
+                .0
+                .entry(path.clone())
+                .or_default()
+                .transforms
+                .insert(name.clone(), *transform);
+        }
+    }
+}
+
+fn reset_chunk_states(mut states: ResMut<ChunkStates>, mut tracked: ResMut<TrackedPersistents>) {
+    states.0.clear();
+    tracked.0.clear();
+}
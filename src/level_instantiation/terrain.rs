@@ -0,0 +1,364 @@
+use crate::{
+    file_system_interaction::config::GameConfig,
+    level_instantiation::{
+        markers::{Marker, MarkersAppExt},
+        post_spawn::PostSpawnStage,
+    },
+    movement::physics::CollisionLayer,
+    shader::{Splat, SplatMaterial},
+    GameState,
+};
+use anyhow::{Context, Result};
+use bevy::{
+    asset::LoadState,
+    ecs::world::EntityWorldMut,
+    prelude::*,
+    render::{
+        mesh::Indices,
+        render_resource::{Extent3d, PrimitiveTopology, TextureDimension, TextureFormat},
+        texture::{ImageAddressMode, ImageLoaderSettings, ImageSampler, ImageSamplerDescriptor},
+    },
+};
+use bevy_xpbd_3d::prelude::*;
+use oxidized_navigation::NavMeshAffector;
+use serde::{Deserialize, Serialize};
+
+/// Cells along each side of a terrain chunk
+const CHUNK_CELLS: u32 = 32;
+
+/// Builds the ground of large outdoor levels from heightmaps instead of hand-modeled meshes.
+/// In Blender, an empty named with a `[terrain:<heightmap>]` or `[terrain:<heightmap>:<splatmap>]` suffix
+/// stretches the image `textures/<heightmap>` over its local X and Z axes from -1 to 1, like a scatter region,
+/// from its origin for black up to a local Y of 1 for white. Grayscale images with 8 or 16 bits are supported.
+/// The terrain is split into square chunks, each with its own mesh, so that those out of view are culled,
+/// and its own heightfield collider on the terrain layer, so that the navmesh and scatter regions treat it as ground.
+/// The red, green and blue channels of the optional splat map `textures/<splatmap>` blend the textures listed
+/// in the `terrain` section of the [`GameConfig`]. Without one, the first texture covers everything.
+pub(crate) fn terrain_plugin(app: &mut App) {
+    app.register_type::<Terrain>()
+        .register_marker("terrain", insert_terrain)
+        .add_systems(
+            Update,
+            (load_terrain_images, build_terrain)
+                .chain()
+                .in_set(PostSpawnStage::Colliders)
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Terrain {
+    /// Path of the heightmap relative to `textures/`
+    pub(crate) heightmap: String,
+    /// Path of the splat map relative to `textures/`
+    pub(crate) splatmap: Option<String>,
+}
+
+/// The images of a terrain that has not been built yet
+#[derive(Debug, Clone, PartialEq, Component)]
+struct TerrainImages {
+    heightmap: Handle<Image>,
+    splatmap: Option<Handle<Image>>,
+}
+
+fn insert_terrain(entity: &mut EntityWorldMut, marker: &Marker) -> Result<()> {
+    let heightmap = marker
+        .argument(0)
+        .context("Expected [terrain:<heightmap>]")?;
+    entity.insert((
+        Terrain {
+            heightmap: heightmap.to_string(),
+            splatmap: marker.argument(1).map(str::to_string),
+        },
+        Visibility::Hidden,
+    ));
+    Ok(())
+}
+
+fn load_terrain_images(
+    mut commands: Commands,
+    terrains: Query<(Entity, &Terrain), Added<Terrain>>,
+    asset_server: Res<AssetServer>,
+) {
+    // Heights and weights are data, not colors
+    let load = |path: &str| {
+        asset_server.load_with_settings(
+            format!("textures/{path}"),
+            |settings: &mut ImageLoaderSettings| settings.is_srgb = false,
+        )
+    };
+    for (entity, terrain) in terrains.iter() {
+        commands.entity(entity).insert(TerrainImages {
+            heightmap: load(&terrain.heightmap),
+            splatmap: terrain.splatmap.as_deref().map(load),
+        });
+    }
+}
+
+fn build_terrain(
+    mut commands: Commands,
+    mut terrains: Query<(Entity, &TerrainImages, &mut Transform, &mut Visibility)>,
+    asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<SplatMaterial>>,
+    config: Res<GameConfig>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("build_terrain").entered();
+    for (entity, terrain_images, mut transform, mut visibility) in terrains.iter_mut() {
+        let handles = std::iter::once(&terrain_images.heightmap).chain(&terrain_images.splatmap);
+        if handles
+            .clone()
+            .any(|handle| asset_server.load_state(handle.id()) == LoadState::Failed)
+        {
+            error!("Failed to load the images of terrain {entity:?}");
+            commands.entity(entity).remove::<TerrainImages>();
+            continue;
+        }
+        if handles.clone().any(|handle| !images.contains(handle)) {
+            continue;
+        }
+        commands.entity(entity).remove::<TerrainImages>();
+        let Some(image) = images.get(&terrain_images.heightmap) else {
+            continue;
+        };
+        let Some(heightmap) = Heightmap::read(image) else {
+            error!(
+                "Heightmap of terrain {entity:?} has the unsupported format {:?}",
+                image.texture_descriptor.format
+            );
+            continue;
+        };
+
+        // The chunks are built in meters instead of being scaled, so that their colliders need no scaling
+        let size = Vec3::new(
+            2. * transform.scale.x,
+            transform.scale.y,
+            2. * transform.scale.z,
+        );
+        transform.scale = Vec3::ONE;
+        *visibility = Visibility::Inherited;
+        let chunks = (heightmap.width.max(heightmap.height) - 1)
+            .max(1)
+            .div_ceil(CHUNK_CELLS);
+        let grid = Grid {
+            heightmap,
+            cells: chunks * CHUNK_CELLS,
+            size,
+        };
+        let splat_map = terrain_images.splatmap.clone().unwrap_or_else(|| {
+            images.add(Image::new_fill(
+                Extent3d {
+                    width: 1,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+                TextureDimension::D2,
+                &[255, 0, 0, 255],
+                TextureFormat::Rgba8Unorm,
+            ))
+        });
+        let [red, green, blue] = config.terrain.layers.clone().map(|path| {
+            asset_server.load_with_settings(path, |settings: &mut ImageLoaderSettings| {
+                settings.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+                    address_mode_u: ImageAddressMode::Repeat,
+                    address_mode_v: ImageAddressMode::Repeat,
+                    ..ImageSamplerDescriptor::linear()
+                });
+            })
+        });
+        let material = materials.add(SplatMaterial {
+            base: StandardMaterial {
+                perceptual_roughness: 0.9,
+                reflectance: 0.05,
+                ..default()
+            },
+            extension: Splat {
+                tiling: config.terrain.tiling,
+                splat_map,
+                red,
+                green,
+                blue,
+            },
+        });
+
+        commands.entity(entity).with_children(|parent| {
+            for chunk_x in 0..chunks {
+                for chunk_z in 0..chunks {
+                    let first = UVec2::new(chunk_x, chunk_z) * CHUNK_CELLS;
+                    let center = grid.position(first + UVec2::splat(CHUNK_CELLS / 2))
+                        * Vec3::new(1., 0., 1.);
+                    // Indexed by X, then Z
+                    let heights: Vec<Vec<f32>> = (0..=CHUNK_CELLS)
+                        .map(|x| {
+                            (0..=CHUNK_CELLS)
+                                .map(|z| grid.height(first + UVec2::new(x, z)))
+                                .collect()
+                        })
+                        .collect();
+                    let chunk_size = grid.cell_size() * CHUNK_CELLS as f32;
+                    parent.spawn((
+                        Name::new(format!("Terrain chunk {chunk_x}, {chunk_z}")),
+                        MaterialMeshBundle {
+                            mesh: meshes.add(grid.chunk_mesh(first, center)),
+                            material: material.clone(),
+                            transform: Transform::from_translation(center),
+                            ..default()
+                        },
+                        Collider::heightfield(
+                            heights,
+                            Vec3::new(chunk_size.x, grid.size.y, chunk_size.y),
+                        ),
+                        RigidBody::Static,
+                        CollisionLayer::terrain(),
+                        NavMeshAffector,
+                    ));
+                }
+            }
+        });
+        info!(
+            "Built terrain {entity:?} of {} by {} meters from {} chunks",
+            size.x,
+            size.z,
+            chunks * chunks
+        );
+    }
+}
+
+/// The brightness of a grayscale image from 0 to 1, row by row
+struct Heightmap {
+    width: u32,
+    height: u32,
+    values: Vec<f32>,
+}
+
+impl Heightmap {
+    fn read(image: &Image) -> Option<Self> {
+        let format = image.texture_descriptor.format;
+        let pixel_size = match format {
+            TextureFormat::R8Unorm => 1,
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => 4,
+            TextureFormat::R16Uint | TextureFormat::R16Unorm => 2,
+            TextureFormat::Rgba16Unorm => 8,
+            _ => return None,
+        };
+        // Only the first channel is read, which is the gray value for images converted from grayscale
+        let values = image
+            .data
+            .chunks_exact(pixel_size)
+            .map(|pixel| match pixel_size {
+                1 | 4 => pixel[0] as f32 / u8::MAX as f32,
+                _ => u16::from_le_bytes([pixel[0], pixel[1]]) as f32 / u16::MAX as f32,
+            })
+            .collect();
+        let size = image.texture_descriptor.size;
+        Some(Self {
+            width: size.width,
+            height: size.height,
+            values,
+        })
+    }
+
+    /// Interpolates between the pixels around `uv`, which spans the image from 0 to 1
+    fn sample(&self, uv: Vec2) -> f32 {
+        let max = UVec2::new(self.width - 1, self.height - 1);
+        let position = uv.clamp(Vec2::ZERO, Vec2::ONE) * max.as_vec2();
+        let low = position.floor().as_uvec2().min(max);
+        let high = (low + UVec2::ONE).min(max);
+        let t = position - low.as_vec2();
+        let value = |x: u32, y: u32| self.values[(y * self.width + x) as usize];
+        let top = value(low.x, low.y).lerp(value(high.x, low.y), t.x);
+        let bottom = value(low.x, high.y).lerp(value(high.x, high.y), t.x);
+        top.lerp(bottom, t.y)
+    }
+}
+
+/// The square grid of vertices the heightmap is resampled to, so that every chunk has the same number of cells
+struct Grid {
+    heightmap: Heightmap,
+    /// Along each side
+    cells: u32,
+    size: Vec3,
+}
+
+impl Grid {
+    fn cell_size(&self) -> Vec2 {
+        Vec2::new(self.size.x, self.size.z) / self.cells as f32
+    }
+
+    fn uv(&self, vertex: UVec2) -> Vec2 {
+        vertex.as_vec2() / self.cells as f32
+    }
+
+    /// From 0 to 1
+    fn height(&self, vertex: UVec2) -> f32 {
+        self.heightmap.sample(self.uv(vertex))
+    }
+
+    /// In the space of the terrain
+    fn position(&self, vertex: UVec2) -> Vec3 {
+        let uv = self.uv(vertex);
+        Vec3::new(
+            (uv.x - 0.5) * self.size.x,
+            self.height(vertex) * self.size.y,
+            (uv.y - 0.5) * self.size.z,
+        )
+    }
+
+    fn normal(&self, vertex: UVec2) -> Vec3 {
+        let previous = vertex.saturating_sub(UVec2::ONE);
+        let next = (vertex + UVec2::ONE).min(UVec2::splat(self.cells));
+        let slope = |a: UVec2, b: UVec2, distance: f32| {
+            (self.height(b) - self.height(a)) * self.size.y / distance.max(f32::EPSILON)
+        };
+        let cell_size = self.cell_size();
+        let dx = slope(
+            UVec2::new(previous.x, vertex.y),
+            UVec2::new(next.x, vertex.y),
+            (next.x - previous.x) as f32 * cell_size.x,
+        );
+        let dz = slope(
+            UVec2::new(vertex.x, previous.y),
+            UVec2::new(vertex.x, next.y),
+            (next.y - previous.y) as f32 * cell_size.y,
+        );
+        Vec3::new(-dx, 1., -dz).normalize()
+    }
+
+    /// The mesh of the chunk whose first vertex is `first`, relative to its `center`
+    fn chunk_mesh(&self, first: UVec2, center: Vec3) -> Mesh {
+        let side = CHUNK_CELLS + 1;
+        let vertices =
+            || (0..side).flat_map(move |x| (0..side).map(move |z| first + UVec2::new(x, z)));
+        let positions: Vec<[f32; 3]> = vertices()
+            .map(|vertex| (self.position(vertex) - center).into())
+            .collect();
+        let normals: Vec<[f32; 3]> = vertices()
+            .map(|vertex| self.normal(vertex).into())
+            .collect();
+        let uvs: Vec<[f32; 2]> = vertices().map(|vertex| self.uv(vertex).into()).collect();
+        let index = |x: u32, z: u32| x * side + z;
+        let indices = (0..CHUNK_CELLS)
+            .flat_map(|x| (0..CHUNK_CELLS).map(move |z| (x, z)))
+            .flat_map(|(x, z)| {
+                let (a, b, c, d) = (
+                    index(x, z),
+                    index(x + 1, z),
+                    index(x, z + 1),
+                    index(x + 1, z + 1),
+                );
+                // Counterclockwise when seen from above
+                [a, c, b, b, c, d]
+            })
+            .collect();
+
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+        mesh.set_indices(Some(Indices::U32(indices)));
+        mesh
+    }
+}
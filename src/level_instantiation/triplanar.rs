@@ -0,0 +1,110 @@
+use crate::{
+    level_instantiation::markers::{Marker, MarkersAppExt},
+    shader::{Triplanar, TriplanarMaterial},
+    GameState,
+};
+use anyhow::{Context, Result};
+use bevy::{
+    ecs::world::EntityWorldMut,
+    prelude::*,
+    render::texture::{
+        ImageAddressMode, ImageLoaderSettings, ImageSampler, ImageSamplerDescriptor,
+    },
+    utils::HashMap,
+};
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_SCALE: f32 = 0.5;
+const SHARPNESS: f32 = 4.;
+
+/// Textures greybox geometry without needing UVs. In Blender, objects named with a `[triplanar:<texture>]`
+/// or `[triplanar:<texture>:<scale>]` suffix get the image `textures/<texture>` projected onto them along
+/// the world axes, repeated `scale` times per meter. Objects using the same texture and scale share a material.
+pub(crate) fn triplanar_plugin(app: &mut App) {
+    app.register_type::<TriplanarMapping>()
+        .init_resource::<TriplanarMaterials>()
+        .register_marker("triplanar", insert_triplanar_mapping)
+        .add_systems(
+            Update,
+            apply_triplanar_mapping.run_if(in_state(GameState::Playing)),
+        );
+}
+
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct TriplanarMapping {
+    /// Path of the image relative to `textures/`
+    pub(crate) texture: String,
+    /// Repetitions of the texture per meter
+    pub(crate) scale: f32,
+}
+
+/// The library of triplanar materials, by texture and scale
+#[derive(Debug, Clone, PartialEq, Resource, Default)]
+struct TriplanarMaterials(HashMap<(String, u32), Handle<TriplanarMaterial>>);
+
+fn insert_triplanar_mapping(entity: &mut EntityWorldMut, marker: &Marker) -> Result<()> {
+    let texture = marker
+        .argument(0)
+        .context("Expected [triplanar:<texture>]")?;
+    let scale = match marker.argument(1) {
+        Some(scale) => scale.parse().context("Failed to parse triplanar scale")?,
+        None => DEFAULT_SCALE,
+    };
+    entity.insert(TriplanarMapping {
+        texture: texture.to_string(),
+        scale,
+    });
+    Ok(())
+}
+
+fn apply_triplanar_mapping(
+    mut commands: Commands,
+    mapped: Query<(Entity, &TriplanarMapping), Added<TriplanarMapping>>,
+    children: Query<&Children>,
+    material_handles: Query<(), With<Handle<StandardMaterial>>>,
+    mut library: ResMut<TriplanarMaterials>,
+    mut materials: ResMut<Assets<TriplanarMaterial>>,
+    asset_server: Res<AssetServer>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("apply_triplanar_mapping").entered();
+    for (entity, mapping) in mapped.iter() {
+        let material = library
+            .0
+            .entry((mapping.texture.clone(), mapping.scale.to_bits()))
+            .or_insert_with(|| {
+                let texture = asset_server.load_with_settings(
+                    format!("textures/{}", mapping.texture),
+                    |settings: &mut ImageLoaderSettings| {
+                        settings.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+                            address_mode_u: ImageAddressMode::Repeat,
+                            address_mode_v: ImageAddressMode::Repeat,
+                            ..ImageSamplerDescriptor::linear()
+                        });
+                    },
+                );
+                materials.add(TriplanarMaterial {
+                    base: StandardMaterial {
+                        perceptual_roughness: 0.9,
+                        reflectance: 0.1,
+                        ..default()
+                    },
+                    extension: Triplanar {
+                        scale: mapping.scale,
+                        sharpness: SHARPNESS,
+                        texture,
+                    },
+                })
+            })
+            .clone();
+        for mesh in std::iter::once(entity).chain(children.iter_descendants(entity)) {
+            if material_handles.contains(mesh) {
+                commands
+                    .entity(mesh)
+                    .remove::<Handle<StandardMaterial>>()
+                    .insert(material.clone());
+            }
+        }
+    }
+}
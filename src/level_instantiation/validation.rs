@@ -0,0 +1,94 @@
+use crate::GameState;
+use bevy::{prelude::*, render::render_resource::PrimitiveTopology};
+use std::fmt;
+
+/// Collects problems with the content of a level, like objects marked as colliders that have no mesh,
+/// so that a broken export is reported instead of crashing the game.
+/// Every problem is logged as a warning when it is found and listed in the dev editor.
+/// The report is cleared whenever a level starts loading.
+pub(crate) fn validation_plugin(app: &mut App) {
+    app.init_resource::<SpawnReport>()
+        .add_systems(OnEnter(GameState::Loading), clear_spawn_report);
+}
+
+#[derive(Debug, Clone, PartialEq, Resource, Default)]
+pub(crate) struct SpawnReport(Vec<SpawnProblem>);
+
+impl SpawnReport {
+    pub(crate) fn add(&mut self, entity: Entity, name: Option<&Name>, kind: SpawnProblemKind) {
+        let problem = SpawnProblem {
+            entity,
+            name: name.map(|name| name.to_string()),
+            kind,
+        };
+        warn!("{problem}");
+        self.0.push(problem);
+    }
+
+    pub(crate) fn problems(&self) -> &[SpawnProblem] {
+        &self.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SpawnProblem {
+    pub(crate) entity: Entity,
+    pub(crate) name: Option<String>,
+    pub(crate) kind: SpawnProblemKind,
+}
+
+impl fmt::Display for SpawnProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "\"{name}\" ({:?}): {}", self.entity, self.kind),
+            None => write!(f, "{:?}: {}", self.entity, self.kind),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SpawnProblemKind {
+    /// A collider was requested, but none of the children have a mesh
+    MissingMesh,
+    WrongTopology(PrimitiveTopology),
+    MissingPositions,
+    /// Indices that point past the end of the vertex positions
+    InvalidIndices,
+    /// Edges shared by more than two triangles, which the navmesh cannot be built on reliably
+    NonManifold {
+        edges: usize,
+    },
+    ColliderFailed(String),
+    MissingChildren,
+    /// Animations were requested, but the model has no animation player
+    MissingAnimationPlayer,
+}
+
+impl fmt::Display for SpawnProblemKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingMesh => write!(f, "marked as collider, but has no mesh"),
+            Self::WrongTopology(topology) => {
+                write!(f, "mesh has topology {topology:?} instead of triangles")
+            }
+            Self::MissingPositions => write!(f, "mesh has no vertex positions"),
+            Self::InvalidIndices => write!(f, "mesh has indices pointing past its vertices"),
+            Self::NonManifold { edges } => write!(
+                f,
+                "mesh has {edges} non-manifold edges, the navmesh may have holes around it"
+            ),
+            Self::ColliderFailed(error) => write!(f, "failed to build collider: {error}"),
+            Self::MissingChildren => write!(f, "expected a model as child, but has no children"),
+            Self::MissingAnimationPlayer => {
+                write!(
+                    f,
+                    "has animations, but no animation player was found on its model"
+                )
+            }
+        }
+    }
+}
+
+fn clear_spawn_report(mut report: ResMut<SpawnReport>) {
+    report.0.clear();
+}
@@ -18,45 +18,82 @@
 
 #[cfg(feature = "dev")]
 use crate::dev::dev_plugin;
+#[cfg(feature = "multiplayer")]
+use crate::network::network_plugin;
+#[cfg(feature = "scripting")]
+use crate::scripting::scripting_plugin;
 use crate::{
-    bevy_config::bevy_config_plugin, file_system_interaction::file_system_interaction_plugin,
-    ingame_menu::ingame_menu_plugin, level_instantiation::level_instantiation_plugin,
-    menu::menu_plugin, movement::movement_plugin, particles::particle_plugin,
-    player_control::player_control_plugin, shader::shader_plugin,
-    world_interaction::world_interaction_plugin,
+    bevy_config::bevy_config_plugin, character_customization::character_customization_plugin,
+    combat::combat_plugin, cutscene::cutscene_plugin, despawn::despawn_plugin,
+    errors::errors_plugin, facial_animation::facial_animation_plugin,
+    file_system_interaction::file_system_interaction_plugin, game_events::game_events_plugin,
+    game_modes::game_modes_plugin, hud::hud_plugin, ingame_menu::ingame_menu_plugin,
+    level_instantiation::level_instantiation_plugin, menu::menu_plugin, movement::movement_plugin,
+    particles::particle_plugin, platform::platform_plugin, player_control::player_control_plugin,
+    quality::quality_plugin, rng::rng_plugin, screen_transition::screen_transition_plugin,
+    shader::shader_plugin, theme::theme_plugin, time_of_day::time_of_day_plugin,
+    time_scale::time_scale_plugin, world_interaction::world_interaction_plugin,
+    world_map::world_map_plugin,
 };
 use bevy::prelude::*;
 use seldom_fn_plugin::FnPluginExt;
 
 pub(crate) mod bevy_config;
+pub(crate) mod character_customization;
+pub(crate) mod combat;
+pub(crate) mod cutscene;
+pub(crate) mod despawn;
 #[cfg(feature = "dev")]
 pub(crate) mod dev;
+pub(crate) mod errors;
+pub(crate) mod facial_animation;
 pub(crate) mod file_system_interaction;
+pub(crate) mod game_events;
+pub(crate) mod game_modes;
+pub(crate) mod hud;
 pub(crate) mod ingame_menu;
 pub(crate) mod level_instantiation;
 pub(crate) mod menu;
 pub(crate) mod movement;
+#[cfg(feature = "multiplayer")]
+pub(crate) mod network;
 pub(crate) mod particles;
+pub(crate) mod platform;
 pub(crate) mod player_control;
+pub(crate) mod quality;
+pub(crate) mod rng;
+pub(crate) mod screen_transition;
+#[cfg(feature = "scripting")]
+pub(crate) mod scripting;
 pub(crate) mod shader;
+pub mod simulation;
+pub(crate) mod theme;
+pub(crate) mod time_of_day;
+pub(crate) mod time_scale;
 pub(crate) mod util;
 pub(crate) mod world_interaction;
+pub(crate) mod world_map;
 
 #[derive(States, Default, Clone, Eq, PartialEq, Debug, Hash)]
 enum GameState {
-    /// During the loading State the loading_plugin will load our assets
+    /// During the initial loading State the loading_plugin will load our assets
     #[default]
+    InitialLoading,
+    /// Shown between levels until all assets needed by the next level are ready
     Loading,
     /// During this State the actual game logic is executed
     Playing,
     /// Here the menu is drawn and waiting for player interaction
     Menu,
+    /// Shown after an error the game could not recover from, see [`errors_plugin`]
+    Error,
 }
 
 /// Main entrypoint for Foxtrot.
 ///
 /// The top-level plugins are:
 /// - [`bevy_config_plugin`]: Sets up the bevy configuration.
+/// - [`game_events_plugin`]: Handles the gameplay events that the other plugins communicate through.
 /// - [`menu_plugin`]: Handles the menu.
 /// - [`movement_plugin`]: Handles the movement of entities.
 /// - [`player_control_plugin`]: Handles the player's control.
@@ -67,6 +104,24 @@ enum GameState {
 /// - [`dev_plugin`]: Handles the dev tools.
 /// - [`ingame_menu_plugin`]: Handles the ingame menu accessed via ESC.
 /// - [`particle_plugin`]: Handles the particle system.
+/// - [`character_customization_plugin`]: Handles swappable character parts and material tints.
+/// - [`facial_animation_plugin`]: Handles blinking, talking and expressions of faces.
+/// - [`world_map_plugin`]: Handles the minimap and the world map.
+/// - [`combat_plugin`]: Handles health and everything else related to fighting.
+/// - [`hud_plugin`]: Handles the heads-up display.
+/// - [`cutscene_plugin`]: Handles cutscenes.
+/// - [`screen_transition_plugin`]: Handles fades and other effects covering the screen.
+/// - [`theme_plugin`]: Handles the look and scale of the UI.
+/// - [`time_scale_plugin`]: Handles slow motion and the speed of the game.
+/// - [`time_of_day_plugin`]: Handles the in-game clock.
+/// - [`despawn_plugin`]: Handles cleaning up entities when leaving a state or after a while.
+/// - [`errors_plugin`]: Handles errors the game cannot recover from.
+/// - [`quality_plugin`]: Handles graphics quality and scaling it to the framerate.
+/// - [`rng_plugin`]: Handles the seeded randomness that replays and procedural content rely on.
+/// - [`platform_plugin`]: Handles achievements and other integrations with the platform the game runs on.
+/// - [`network_plugin`]: Handles playing together over the network.
+/// - [`scripting_plugin`]: Handles gameplay logic written in scripts.
+/// - [`game_modes_plugin`]: Handles alternate modes like time trials.
 ///
 /// Because Foxtrot uses `seldom_fn_plugin`, these are all functions.
 pub struct GamePlugin;
@@ -75,6 +130,7 @@ impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.add_state::<GameState>()
             .fn_plugin(bevy_config_plugin)
+            .fn_plugin(game_events_plugin)
             .fn_plugin(menu_plugin)
             .fn_plugin(movement_plugin)
             .fn_plugin(player_control_plugin)
@@ -83,8 +139,30 @@ impl Plugin for GamePlugin {
             .fn_plugin(file_system_interaction_plugin)
             .fn_plugin(shader_plugin)
             .fn_plugin(ingame_menu_plugin)
-            .fn_plugin(particle_plugin);
+            .fn_plugin(particle_plugin)
+            .fn_plugin(character_customization_plugin)
+            .fn_plugin(facial_animation_plugin)
+            .fn_plugin(world_map_plugin)
+            .fn_plugin(combat_plugin)
+            .fn_plugin(hud_plugin)
+            .fn_plugin(cutscene_plugin)
+            .fn_plugin(screen_transition_plugin)
+            .fn_plugin(theme_plugin)
+            .fn_plugin(time_scale_plugin)
+            .fn_plugin(time_of_day_plugin)
+            .fn_plugin(despawn_plugin)
+            .fn_plugin(errors_plugin)
+            .fn_plugin(quality_plugin)
+            .fn_plugin(rng_plugin)
+            .fn_plugin(platform_plugin)
+            .fn_plugin(game_modes_plugin);
+        #[cfg(feature = "multiplayer")]
+        app.fn_plugin(network_plugin);
+        #[cfg(feature = "scripting")]
+        app.fn_plugin(scripting_plugin);
         #[cfg(feature = "dev")]
-        app.fn_plugin(dev_plugin);
+        if !app.world.contains_resource::<bevy_config::Headless>() {
+            app.fn_plugin(dev_plugin);
+        }
     }
 }
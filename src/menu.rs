@@ -1,49 +1,390 @@
-use crate::GameState;
-use bevy::prelude::*;
-use bevy_egui::{
-    egui,
-    egui::{
-        FontFamily::Proportional,
-        FontId,
-        TextStyle::{Body, Button, Heading},
+use crate::{
+    despawn::DespawnOnExit,
+    file_system_interaction::{
+        config::GameConfig,
+        game_state_serialization::{list_save_slots, GameLoadRequest, SaveSlot},
+        mods::{mods_ui, InstalledMods},
+        storage::{self, GameStorage},
     },
-    EguiContexts,
+    level_instantiation::levels::CurrentLevel,
+    player_control::actions::{create_ui_action_input_manager_bundle, UiAction},
+    theme::UiTheme,
+    GameState,
 };
+use bevy::{app::AppExit, prelude::*};
+use bevy_egui::{egui, EguiContexts};
+use bevy_kira_audio::prelude::Audio;
+use leafwing_input_manager::prelude::ActionState;
+#[cfg(feature = "multiplayer")]
+use lobby::{lobby_ui, Lobby, SessionRequests};
+use settings::save_settings;
+pub(crate) use settings::{
+    accessibility_ui, load_settings, settings_ui, AccessibilitySettings, CaptionSettings, Settings,
+};
+use std::time::SystemTime;
+
+#[cfg(feature = "multiplayer")]
+mod lobby;
+mod settings;
+
+const PAGE_TRANSITION_DURATION: f32 = 0.25;
+const EXIT_TRANSITION_DURATION: f32 = 0.4;
+const CREDITS: &str = include_str!("../credits/credits.md");
 
 /// This plugin is responsible for the game menu
 /// The menu is only drawn during the State `GameState::Menu` and is removed when that state is exited.
+/// It offers continuing from the latest save, starting a new game, loading a specific save, settings and credits.
+/// With the `multiplayer` feature, it also has a page for hosting or joining a session.
+/// All pages can be navigated with the mouse, the keyboard or a gamepad.
+/// The [`Settings`] are restored on startup and saved whenever they change.
 pub(crate) fn menu_plugin(app: &mut App) {
-    app.add_systems(Update, setup_menu.run_if(in_state(GameState::Menu)));
-}
-
-fn setup_menu(mut egui_contexts: EguiContexts, mut next_state: ResMut<NextState<GameState>>) {
-    get_menu_panel().show(egui_contexts.ctx_mut(), |ui| {
-        set_menu_style(ui.style_mut());
-        ui.vertical_centered_justified(|ui| {
-            ui.add_space(50.);
-            ui.heading("Foxtrot");
-            ui.separator();
-            ui.add_space(50.);
-            if ui.button("Play").clicked() {
-                next_state.set(GameState::Playing);
+    app.init_resource::<MainMenu>()
+        .init_resource::<Settings>()
+        .add_systems(Startup, load_settings)
+        .add_systems(OnEnter(GameState::Menu), open_menu)
+        .add_systems(
+            Update,
+            (setup_menu.run_if(in_state(GameState::Menu)), save_settings),
+        );
+}
+
+/// Keyboard and gamepad navigation through the buttons of a menu page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct MenuNavigation {
+    pub(crate) selected: usize,
+    confirmed: bool,
+    back: bool,
+}
+
+impl MenuNavigation {
+    /// Reads this frame's input for a page with `button_count` buttons.
+    pub(crate) fn update<'a>(
+        &mut self,
+        actions: impl IntoIterator<Item = &'a ActionState<UiAction>>,
+        button_count: usize,
+    ) {
+        let button_count = button_count.max(1);
+        self.confirmed = false;
+        self.back = false;
+        for action in actions {
+            if action.just_pressed(UiAction::NavigateDown) {
+                self.selected = (self.selected + 1) % button_count;
             }
-        })
+            if action.just_pressed(UiAction::NavigateUp) {
+                self.selected = (self.selected + button_count - 1) % button_count;
+            }
+            self.confirmed |= action.just_pressed(UiAction::Confirm);
+            self.back |= action.just_pressed(UiAction::Back);
+        }
+        self.selected = self.selected.min(button_count - 1);
+    }
+
+    /// A button that can be clicked with the mouse or activated with [`UiAction::Confirm`] while selected.
+    pub(crate) fn button(&self, ui: &mut egui::Ui, index: usize, text: &str) -> bool {
+        let selected = self.selected == index;
+        let response = ui.add(egui::Button::new(text).selected(selected));
+        response.clicked() || (selected && self.confirmed)
+    }
+
+    pub(crate) fn back_pressed(&self) -> bool {
+        self.back
+    }
+
+    pub(crate) fn select(&mut self, index: usize) {
+        self.selected = index;
+        self.confirmed = false;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Resource, Default)]
+struct MainMenu {
+    page: MenuPage,
+    navigation: MenuNavigation,
+    /// Time at which the current page was opened, used to animate it in
+    page_opened: f32,
+    saves: Vec<SaveSlot>,
+    /// Command to execute once the screen has faded out
+    exit: Option<(MenuCommand, f32)>,
+    #[cfg(feature = "multiplayer")]
+    lobby: Lobby,
+}
+
+impl MainMenu {
+    fn open(&mut self, page: MenuPage, now: f32) {
+        self.page = page;
+        self.navigation.select(0);
+        self.page_opened = now;
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+enum MenuPage {
+    #[default]
+    Main,
+    LoadGame,
+    Settings,
+    Accessibility,
+    Credits,
+    #[cfg(feature = "multiplayer")]
+    Multiplayer,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum MenuCommand {
+    Open(MenuPage),
+    NewGame,
+    Load(String),
+    /// Hosts a session and starts a new game in it
+    #[cfg(feature = "multiplayer")]
+    Host,
+    /// Connects to the address entered in the lobby, which happens without leaving the menu
+    #[cfg(feature = "multiplayer")]
+    Join,
+    Quit,
+}
+
+fn open_menu(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    storage: Res<GameStorage>,
+    mut menu: ResMut<MainMenu>,
+) {
+    commands.spawn((
+        Name::new("Menu Input"),
+        create_ui_action_input_manager_bundle(),
+        DespawnOnExit(GameState::Menu),
+    ));
+    let saves = list_save_slots(&storage).unwrap_or_else(|error| {
+        error!("Failed to list save slots: {error:?}");
+        Vec::new()
     });
+    *menu = MainMenu { saves, ..default() };
+    menu.open(MenuPage::Main, time.elapsed_seconds());
+}
+
+fn setup_menu(
+    time: Res<Time<Real>>,
+    actions: Query<&ActionState<UiAction>>,
+    mut menu: ResMut<MainMenu>,
+    mut settings: ResMut<Settings>,
+    mut config: Option<ResMut<GameConfig>>,
+    audio: Res<Audio>,
+    installed_mods: Res<InstalledMods>,
+    theme: Res<UiTheme>,
+    mut egui_contexts: EguiContexts,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut load_requests: EventWriter<GameLoadRequest>,
+    mut app_exit_events: EventWriter<AppExit>,
+    #[cfg(feature = "multiplayer")] mut session_requests: SessionRequests,
+) {
+    let now = time.elapsed_seconds();
+    let reduce_motion = settings.accessibility.reduce_motion;
+    let transition_scale = if reduce_motion { 0.2 } else { 1. };
+    if let Some((command, started)) = menu.exit.clone() {
+        let progress = (now - started) / (EXIT_TRANSITION_DURATION * transition_scale);
+        if progress >= 1. {
+            menu.exit = None;
+            match command {
+                MenuCommand::NewGame => {
+                    *current_level = default();
+                    next_state.set(GameState::Loading);
+                }
+                MenuCommand::Load(slot) => load_requests.send(GameLoadRequest { slot }),
+                #[cfg(feature = "multiplayer")]
+                MenuCommand::Host => {
+                    session_requests.host();
+                    *current_level = default();
+                    next_state.set(GameState::Loading);
+                }
+                #[cfg(feature = "multiplayer")]
+                MenuCommand::Join => session_requests.join(&mut menu.lobby),
+                MenuCommand::Quit => app_exit_events.send(AppExit),
+                MenuCommand::Open(page) => menu.open(page, now),
+            }
+        }
+    }
+
+    let entries = page_entries(&menu);
+    let exiting = menu.exit.is_some();
+    if !exiting {
+        menu.navigation.update(actions.iter(), entries.len());
+    }
+    if menu.navigation.back_pressed() && menu.page != MenuPage::Main {
+        menu.open(MenuPage::Main, now);
+    }
+
+    // Slide the page in from below while fading it in
+    let page_progress = if reduce_motion {
+        1.
+    } else {
+        ease_out((now - menu.page_opened) / PAGE_TRANSITION_DURATION)
+    };
+    let text_color = theme.colors.text.linear_multiply(page_progress);
+
+    let ctx = egui_contexts.ctx_mut();
+    let mut command = None;
+    let spacing = theme.spacing;
+    egui::CentralPanel::default()
+        .frame(theme.backdrop_frame())
+        .show(ctx, |ui| {
+            theme.apply_menu_style(ui.style_mut());
+            ui.visuals_mut().override_text_color = Some(text_color);
+            ui.visuals_mut().widgets.noninteractive.fg_stroke.color = text_color;
+            ui.vertical_centered_justified(|ui| {
+                ui.add_space(spacing.large);
+                ui.heading(page_title(menu.page));
+                ui.separator();
+                ui.add_space(spacing.large + (1. - page_progress) * spacing.medium);
+                ui.set_enabled(!exiting);
+                match menu.page {
+                    MenuPage::Settings => {
+                        let config = config.as_deref_mut();
+                        settings_ui(ui, &mut settings, config, &audio);
+                        ui.add_space(spacing.medium);
+                        mods_ui(ui, &mut settings.mods, &installed_mods);
+                        ui.add_space(spacing.medium);
+                    }
+                    MenuPage::Accessibility => {
+                        accessibility_ui(ui, &mut settings.accessibility);
+                        ui.add_space(spacing.medium);
+                    }
+                    MenuPage::Credits => {
+                        egui::ScrollArea::vertical()
+                            .max_height(ui.available_height() - 2. * spacing.large)
+                            .show(ui, |ui| {
+                                for line in CREDITS.lines().filter(|line| !line.is_empty()) {
+                                    match line.trim_start_matches('#').strip_prefix("- ") {
+                                        Some(entry) => ui.label(entry),
+                                        None => ui.strong(line.trim_start_matches('#').trim()),
+                                    };
+                                }
+                            });
+                        ui.add_space(spacing.medium);
+                    }
+                    #[cfg(feature = "multiplayer")]
+                    MenuPage::Multiplayer => {
+                        lobby_ui(ui, &mut settings, &mut menu.lobby);
+                        ui.add_space(spacing.medium);
+                    }
+                    MenuPage::Main | MenuPage::LoadGame => {}
+                }
+                for (index, (label, entry_command)) in entries.iter().enumerate() {
+                    if menu.navigation.button(ui, index, label) {
+                        command = Some(entry_command.clone());
+                    }
+                }
+            })
+        });
+
+    match command {
+        Some(MenuCommand::Open(page)) => menu.open(page, now),
+        #[cfg(feature = "multiplayer")]
+        Some(MenuCommand::Join) => session_requests.join(&mut menu.lobby),
+        Some(command) => menu.exit = Some((command, now)),
+        None => {}
+    }
+
+    if let Some((_, started)) = menu.exit {
+        let alpha =
+            ((now - started) / (EXIT_TRANSITION_DURATION * transition_scale)).clamp(0., 1.) * 255.;
+        ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Foreground,
+            egui::Id::new("menu_fade_out"),
+        ))
+        .rect_filled(
+            ctx.screen_rect(),
+            0.,
+            egui::Color32::from_black_alpha(alpha as u8),
+        );
+    }
+}
+
+fn page_entries(menu: &MainMenu) -> Vec<(String, MenuCommand)> {
+    let back = ("Back".to_string(), MenuCommand::Open(MenuPage::Main));
+    match menu.page {
+        MenuPage::Main => {
+            let mut entries = Vec::new();
+            if let Some(latest) = menu.saves.first() {
+                entries.push((
+                    "Continue".to_string(),
+                    MenuCommand::Load(latest.name.clone()),
+                ));
+            }
+            entries.push(("New Game".to_string(), MenuCommand::NewGame));
+            if !menu.saves.is_empty() {
+                entries.push((
+                    "Load Game".to_string(),
+                    MenuCommand::Open(MenuPage::LoadGame),
+                ));
+            }
+            #[cfg(feature = "multiplayer")]
+            entries.push((
+                "Multiplayer".to_string(),
+                MenuCommand::Open(MenuPage::Multiplayer),
+            ));
+            entries.extend([
+                (
+                    "Settings".to_string(),
+                    MenuCommand::Open(MenuPage::Settings),
+                ),
+                (
+                    "Accessibility".to_string(),
+                    MenuCommand::Open(MenuPage::Accessibility),
+                ),
+                ("Credits".to_string(), MenuCommand::Open(MenuPage::Credits)),
+                ("Quit".to_string(), MenuCommand::Quit),
+            ]);
+            entries
+        }
+        MenuPage::LoadGame => menu
+            .saves
+            .iter()
+            .map(|slot| {
+                (
+                    format!("{} ({})", slot.name, format_age(slot.modified)),
+                    MenuCommand::Load(slot.name.clone()),
+                )
+            })
+            .chain([back])
+            .collect(),
+        // Joining comes first, so that confirming the typed address joins
+        #[cfg(feature = "multiplayer")]
+        MenuPage::Multiplayer => vec![
+            ("Join Session".to_string(), MenuCommand::Join),
+            ("Host Session".to_string(), MenuCommand::Host),
+            back,
+        ],
+        MenuPage::Settings | MenuPage::Accessibility | MenuPage::Credits => vec![back],
+    }
+}
+
+fn page_title(page: MenuPage) -> &'static str {
+    match page {
+        MenuPage::Main => "Foxtrot",
+        MenuPage::LoadGame => "Load Game",
+        MenuPage::Settings => "Settings",
+        MenuPage::Accessibility => "Accessibility",
+        MenuPage::Credits => "Credits",
+        #[cfg(feature = "multiplayer")]
+        MenuPage::Multiplayer => "Multiplayer",
+    }
 }
 
-fn get_menu_panel() -> egui::CentralPanel {
-    egui::CentralPanel::default().frame(egui::Frame {
-        inner_margin: egui::style::Margin::same(60.),
-        ..default()
-    })
+fn format_age(time: SystemTime) -> String {
+    let seconds = storage::now()
+        .duration_since(time)
+        .unwrap_or_default()
+        .as_secs();
+    match seconds {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{} minutes ago", seconds / 60),
+        3600..=86399 => format!("{} hours ago", seconds / 3600),
+        _ => format!("{} days ago", seconds / 86400),
+    }
 }
 
-fn set_menu_style(style: &mut egui::Style) {
-    style.text_styles = [
-        (Heading, FontId::new(30.0, Proportional)),
-        (Body, FontId::new(20.0, Proportional)),
-        (Button, FontId::new(20.0, Proportional)),
-    ]
-    .into();
-    style.visuals.widgets.noninteractive.fg_stroke.color = egui::Color32::from_gray(250);
+fn ease_out(t: f32) -> f32 {
+    let t = t.clamp(0., 1.);
+    1. - (1. - t).powi(3)
 }
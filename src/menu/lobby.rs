@@ -0,0 +1,56 @@
+use crate::{
+    menu::Settings,
+    network::{
+        parse_address, HostSessionRequest, JoinSessionRequest, DEFAULT_PORT, MAX_NAME_LENGTH,
+    },
+};
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_egui::egui;
+
+/// What the player entered on the multiplayer page
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(super) struct Lobby {
+    pub(super) address: String,
+    /// Shown below the fields, e.g. while connecting or when the address is invalid
+    status: Option<String>,
+}
+
+#[derive(SystemParam)]
+pub(super) struct SessionRequests<'w> {
+    host: EventWriter<'w, HostSessionRequest>,
+    join: EventWriter<'w, JoinSessionRequest>,
+}
+
+impl SessionRequests<'_> {
+    pub(super) fn host(&mut self) {
+        self.host.send(HostSessionRequest { port: DEFAULT_PORT });
+    }
+
+    /// Connects to the address entered in the lobby. The menu stays open until the host sends their level.
+    pub(super) fn join(&mut self, lobby: &mut Lobby) {
+        match parse_address(&lobby.address) {
+            Ok(address) => {
+                self.join.send(JoinSessionRequest { address });
+                lobby.status = Some(format!("Connecting to {address}…"));
+            }
+            Err(error) => lobby.status = Some(format!("{error}")),
+        }
+    }
+}
+
+pub(super) fn lobby_ui(ui: &mut egui::Ui, settings: &mut Settings, lobby: &mut Lobby) {
+    ui.horizontal(|ui| {
+        ui.label("Name");
+        ui.add(egui::TextEdit::singleline(&mut settings.player_name).char_limit(MAX_NAME_LENGTH));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Host address");
+        ui.add(egui::TextEdit::singleline(&mut lobby.address).hint_text("192.168.0.2"));
+    });
+    if let Some(status) = &lobby.status {
+        ui.label(status);
+    }
+    ui.label(format!(
+        "Hosting makes your game reachable on port {DEFAULT_PORT}."
+    ));
+}
@@ -0,0 +1,321 @@
+use crate::{
+    file_system_interaction::{config::GameConfig, mods::ModSettings, storage::GameStorage},
+    quality::QualityLevel,
+    theme::ColorblindMode,
+};
+use anyhow::{Context, Result};
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_kira_audio::prelude::{Audio, AudioControl};
+use bevy_mod_sysfail::*;
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const SETTINGS_KEY: &str = "settings.json";
+/// Seconds the settings have to stay unchanged before they are written, so dragging a slider does not write every frame
+const SAVE_DELAY: f32 = 1.;
+/// The languages texts are translated into, by code and name
+const LANGUAGES: &[(&str, &str)] = &[("en", "English"), ("de", "Deutsch")];
+
+/// Player-facing options that are shared by the main menu and the pause menu.
+/// Missing fields, e.g. from settings written by an older version, fall back to their defaults.
+#[derive(Debug, Clone, PartialEq, Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Settings {
+    pub(crate) volume: f64,
+    /// Scale of the whole UI on top of the window's scale factor
+    pub(crate) ui_scale: f64,
+    /// Shows how often the player has died in the HUD
+    pub(crate) show_death_counter: bool,
+    /// Draws a trail along the ground leading to the closest objective
+    pub(crate) show_breadcrumbs: bool,
+    pub(crate) graphics: GraphicsSettings,
+    pub(crate) captions: CaptionSettings,
+    pub(crate) accessibility: AccessibilitySettings,
+    /// Shown to other players in multiplayer sessions
+    pub(crate) player_name: String,
+    /// Code of the language texts are shown in, e.g. "en"
+    pub(crate) language: String,
+    pub(crate) mods: ModSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            ui_scale: 1.0,
+            show_death_counter: false,
+            show_breadcrumbs: false,
+            graphics: default(),
+            captions: default(),
+            accessibility: default(),
+            player_name: "Player".to_string(),
+            language: "en".to_string(),
+            mods: default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct GraphicsSettings {
+    /// The highest quality used. Without dynamic quality, this is always the quality used.
+    pub(crate) quality: QualityLevel,
+    /// Lowers the quality while the game runs below the target framerate
+    pub(crate) dynamic_quality: bool,
+    pub(crate) target_fps: f32,
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        Self {
+            quality: default(),
+            dynamic_quality: true,
+            target_fps: 60.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct CaptionSettings {
+    /// Whether to caption dialog lines. Off by default since the dialog view already shows them.
+    pub(crate) dialog: bool,
+    pub(crate) sounds: bool,
+    pub(crate) text_size: f32,
+    pub(crate) background_opacity: f32,
+}
+
+impl Default for CaptionSettings {
+    fn default() -> Self {
+        Self {
+            dialog: false,
+            sounds: false,
+            text_size: 18.,
+            background_opacity: 0.6,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct AccessibilitySettings {
+    pub(crate) sprint_mode: InputMode,
+    pub(crate) crouch_mode: InputMode,
+    /// Slowly turns the third person camera behind the player while they move without touching the camera
+    pub(crate) camera_assist: bool,
+    /// Lets gamepads vibrate, e.g. when the player is hit
+    pub(crate) rumble: bool,
+    /// Disables camera shake and shortens screen transitions
+    pub(crate) reduce_motion: bool,
+    /// Replaces translucent panels with opaque black and all text with pure white
+    pub(crate) high_contrast: bool,
+    /// Multiplier for all UI text sizes
+    pub(crate) text_scale: f32,
+    /// Swaps the colors used for UI and gameplay highlights for ones that stay distinguishable
+    pub(crate) colorblind_mode: ColorblindMode,
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        Self {
+            sprint_mode: default(),
+            crouch_mode: default(),
+            camera_assist: false,
+            rumble: true,
+            reduce_motion: false,
+            high_contrast: false,
+            text_scale: 1.,
+            colorblind_mode: default(),
+        }
+    }
+}
+
+/// How an action that can be kept up, like sprinting, is triggered.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default, Serialize, Deserialize)]
+pub(crate) enum InputMode {
+    /// The action is active while the button is held
+    #[default]
+    Hold,
+    /// Each press turns the action on or off
+    Toggle,
+}
+
+impl InputMode {
+    /// Whether `action` is active this frame, given whether it was active before.
+    pub(crate) fn is_active<A: Actionlike>(
+        self,
+        actions: &ActionState<A>,
+        action: A,
+        was_active: bool,
+    ) -> bool {
+        match self {
+            InputMode::Hold => actions.pressed(action),
+            InputMode::Toggle => was_active != actions.just_pressed(action),
+        }
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, label: &str) {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            ui.selectable_value(self, InputMode::Hold, "Hold");
+            ui.selectable_value(self, InputMode::Toggle, "Toggle");
+        });
+    }
+}
+
+/// Restores the [`Settings`] saved by [`save_settings`].
+#[sysfail(log(level = "error"))]
+pub(crate) fn load_settings(
+    mut settings: ResMut<Settings>,
+    storage: Res<GameStorage>,
+    audio: Res<Audio>,
+) -> Result<()> {
+    let Some(serialized) = storage.read_string(SETTINGS_KEY)? else {
+        return Ok(());
+    };
+    let loaded: Settings = serde_json::from_str(&serialized).context("Failed to parse settings")?;
+    audio.set_volume(loaded.volume);
+    *settings = loaded;
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(super) struct SavedSettings {
+    settings: Option<Settings>,
+    /// When the settings started to differ from the saved ones
+    changed_at: Option<f32>,
+}
+
+/// Writes the [`Settings`] once they have stopped changing for a moment.
+#[sysfail(log(level = "error"))]
+pub(super) fn save_settings(
+    time: Res<Time<Real>>,
+    settings: Res<Settings>,
+    storage: Res<GameStorage>,
+    mut saved: Local<SavedSettings>,
+) -> Result<()> {
+    // Settings are borrowed mutably every frame while a settings page is open, so change detection is not enough
+    let Some(saved_settings) = &saved.settings else {
+        saved.settings = Some(settings.clone());
+        return Ok(());
+    };
+    if saved_settings == settings.as_ref() {
+        saved.changed_at = None;
+        return Ok(());
+    }
+    let now = time.elapsed_seconds();
+    let changed_at = *saved.changed_at.get_or_insert(now);
+    if now - changed_at < SAVE_DELAY {
+        return Ok(());
+    }
+    saved.settings = Some(settings.clone());
+    saved.changed_at = None;
+    let serialized = serde_json::to_string_pretty(settings.as_ref())?;
+    storage
+        .write(SETTINGS_KEY, serialized.as_bytes())
+        .context("Failed to save settings")?;
+    Ok(())
+}
+
+/// Draws the controls for [`Settings`] and the tweakable parts of the [`GameConfig`].
+pub(crate) fn settings_ui(
+    ui: &mut egui::Ui,
+    settings: &mut Settings,
+    config: Option<&mut GameConfig>,
+    audio: &Audio,
+) {
+    if let Some(config) = config {
+        ui.label("Mouse sensitivity");
+        ui.add(
+            egui::Slider::new(&mut config.camera.mouse_sensitivity_x, 1e-4..=3e-3)
+                .text("Horizontal"),
+        );
+        ui.add(
+            egui::Slider::new(&mut config.camera.mouse_sensitivity_y, 1e-4..=3e-3).text("Vertical"),
+        );
+    }
+    ui.label("Audio");
+    if ui
+        .add(egui::Slider::new(&mut settings.volume, 0.0..=1.0).text("Volume"))
+        .changed()
+    {
+        audio.set_volume(settings.volume);
+    }
+    ui.label("Graphics");
+    let graphics = &mut settings.graphics;
+    egui::ComboBox::from_label("Quality")
+        .selected_text(graphics.quality.name())
+        .show_ui(ui, |ui| {
+            for level in QualityLevel::ALL {
+                ui.selectable_value(&mut graphics.quality, level, level.name());
+            }
+        });
+    ui.checkbox(
+        &mut graphics.dynamic_quality,
+        "Lower quality to hold framerate",
+    );
+    ui.add_enabled(
+        graphics.dynamic_quality,
+        egui::Slider::new(&mut graphics.target_fps, 30.0..=144.0).text("Target FPS"),
+    );
+    ui.label("Interface");
+    let mut ui_scale = settings.ui_scale;
+    let response = ui.add(egui::Slider::new(&mut ui_scale, 0.5..=2.0).text("UI scale"));
+    // Rescaling while dragging would move the slider out from under the cursor
+    if response.drag_released() || (response.changed() && !response.dragged()) {
+        settings.ui_scale = ui_scale;
+    }
+    ui.checkbox(&mut settings.show_death_counter, "Death counter");
+    ui.checkbox(&mut settings.show_breadcrumbs, "Trail to objective");
+    let language_name = |code: &str| {
+        LANGUAGES
+            .iter()
+            .find(|(language, _)| *language == code)
+            .map_or(code.to_string(), |(_, name)| name.to_string())
+    };
+    egui::ComboBox::from_label("Language")
+        .selected_text(language_name(&settings.language))
+        .show_ui(ui, |ui| {
+            for (code, name) in LANGUAGES {
+                ui.selectable_value(&mut settings.language, code.to_string(), *name);
+            }
+        });
+    ui.label("Captions");
+    let captions = &mut settings.captions;
+    ui.checkbox(&mut captions.dialog, "Subtitles");
+    ui.checkbox(&mut captions.sounds, "Sound captions");
+    ui.add(egui::Slider::new(&mut captions.text_size, 12.0..=36.0).text("Caption size"));
+    ui.add(
+        egui::Slider::new(&mut captions.background_opacity, 0.0..=1.0).text("Caption background"),
+    );
+}
+
+/// Draws the controls for [`AccessibilitySettings`].
+pub(crate) fn accessibility_ui(ui: &mut egui::Ui, settings: &mut AccessibilitySettings) {
+    ui.label("Controls");
+    settings.sprint_mode.ui(ui, "Sprint");
+    settings.crouch_mode.ui(ui, "Crouch");
+    ui.checkbox(&mut settings.camera_assist, "Camera assist");
+    ui.checkbox(&mut settings.rumble, "Controller vibration");
+    ui.label("Motion");
+    ui.checkbox(
+        &mut settings.reduce_motion,
+        "Reduce camera shake and motion",
+    );
+    ui.label("Readability");
+    ui.checkbox(&mut settings.high_contrast, "High contrast");
+    let mut text_scale = settings.text_scale;
+    let response = ui.add(egui::Slider::new(&mut text_scale, 0.75..=2.0).text("Text size"));
+    // Like the UI scale, resizing text while dragging would move the slider out from under the cursor
+    if response.drag_released() || (response.changed() && !response.dragged()) {
+        settings.text_scale = text_scale;
+    }
+    egui::ComboBox::from_label("Color vision")
+        .selected_text(settings.colorblind_mode.name())
+        .show_ui(ui, |ui| {
+            for mode in ColorblindMode::ALL {
+                ui.selectable_value(&mut settings.colorblind_mode, mode, mode.name());
+            }
+        });
+}
@@ -1,11 +1,21 @@
 pub(crate) mod character_controller;
 
+pub(crate) mod companion;
+pub(crate) mod fluids;
 pub(crate) mod navigation;
+pub(crate) mod perception;
 pub(crate) mod physics;
+pub(crate) mod ragdoll;
+pub(crate) mod ropes;
+pub(crate) mod schedules;
+pub(crate) mod vehicle;
+pub(crate) mod wind;
 
 use crate::movement::{
-    character_controller::character_controller_plugin, navigation::navigation_plugin,
-    physics::physics_plugin,
+    character_controller::character_controller_plugin, companion::companion_plugin,
+    fluids::fluids_plugin, navigation::navigation_plugin, perception::perception_plugin,
+    physics::physics_plugin, ragdoll::ragdoll_plugin, ropes::ropes_plugin,
+    schedules::schedules_plugin, vehicle::vehicle_plugin, wind::wind_plugin,
 };
 use bevy::prelude::*;
 use seldom_fn_plugin::FnPluginExt;
@@ -17,8 +27,24 @@ use seldom_fn_plugin::FnPluginExt;
 /// this sense is anything that behaves in a not-quite completely physical way, like a player, an npc, an elevator, a moving platform, etc.
 /// Contrast this with pure rigidbodies like a ball, a crate, etc.
 /// - [`navigation_plugin`]: Handles npc pathfinding via bevy_pathmesh integration.
+/// - [`companion_plugin`]: Lets companions follow the player by giving their navigators destinations.
+/// - [`schedules_plugin`]: Sends NPCs to places depending on the time of day.
+/// - [`perception_plugin`]: Lets NPCs spot the player depending on light, crouching and movement.
+/// - [`ragdoll_plugin`]: Turns characters into ragdolls on request or on heavy impacts.
+/// - [`vehicle_plugin`]: Simulates wheeled vehicles with raycast suspension.
+/// - [`fluids_plugin`]: Makes bodies float or sink in water and lets characters swim.
+/// - [`wind_plugin`]: Blows bodies and characters around inside wind zones.
+/// - [`ropes_plugin`]: Spawns ropes and breaks joints under too much force.
 pub(crate) fn movement_plugin(app: &mut App) {
     app.fn_plugin(physics_plugin)
         .fn_plugin(character_controller_plugin)
-        .fn_plugin(navigation_plugin);
+        .fn_plugin(navigation_plugin)
+        .fn_plugin(companion_plugin)
+        .fn_plugin(schedules_plugin)
+        .fn_plugin(perception_plugin)
+        .fn_plugin(ragdoll_plugin)
+        .fn_plugin(vehicle_plugin)
+        .fn_plugin(fluids_plugin)
+        .fn_plugin(wind_plugin)
+        .fn_plugin(ropes_plugin);
 }
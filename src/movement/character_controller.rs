@@ -1,48 +1,127 @@
-use crate::GameState;
+use crate::{util::trait_extension::Vec3Ext, GameState};
+pub(crate) use animation_events::*;
+pub(crate) use animation_graph::*;
+pub(crate) use animation_links::*;
 pub(crate) use animations::*;
-use bevy::prelude::*;
-use bevy_tnua::prelude::*;
+use bevy::{prelude::*, render::view::VisibilitySystems, transform::TransformSystem};
+use bevy_common_assets::ron::RonAssetPlugin;
+use bevy_tnua::{builtins::TnuaBuiltinCrouch, prelude::*};
 use bevy_tnua_xpbd3d::*;
-use bevy_xpbd_3d::PhysicsSet;
+use bevy_xpbd_3d::{prelude::*, PhysicsSet};
 pub(crate) use components::*;
+pub(crate) use foot_ik::*;
 pub(crate) use models::*;
+use serde::{Deserialize, Serialize};
+pub(crate) use spring_bones::*;
+pub(crate) use sweep::*;
 
+mod animation_events;
+mod animation_graph;
+mod animation_links;
 mod animations;
 mod components;
+mod foot_ik;
 
 mod models;
+mod spring_bones;
+mod sweep;
 
 /// This plugin communicates with the Tnua character controller by propagating settings found in
-/// the control components [`Walk`] and [`Jump`]. It also controls a state machine to determine which animations to play.
+/// the control components [`Walk`], [`Crouching`], [`Jump`] and [`Knockback`].
+/// Which animations characters play is decided by the [`AnimationGraph`] referenced in their [`CharacterAnimations`].
+/// They are played on the animation player of the character's model, which it is linked to by an [`AnimationLink`].
+/// Whenever an animation passes one of its [`AnimationEventMarkers`], an [`AnimationEvent`] is sent, e.g. for footsteps.
+/// Characters with a [`FootIk`] component additionally get their feet placed on the ground after animating,
+/// and those with [`SpringBones`] get their capes, hair and tails swung around.
+/// The bounds of their skinned meshes follow the animation, so that they are only culled once actually out of view.
+/// Before every physics step, fast characters are swept through the level to keep them from tunneling through walls.
 pub(crate) fn character_controller_plugin(app: &mut App) {
     app.add_plugins((TnuaXpbd3dPlugin, TnuaControllerPlugin))
+        .add_plugins(RonAssetPlugin::<AnimationGraph>::new(&["animgraph.ron"]))
+        .add_plugins(RonAssetPlugin::<AnimationEventMarkers>::new(&[
+            "animevents.ron",
+        ]))
+        .add_event::<AnimationEvent>()
         .register_type::<Jump>()
         .register_type::<Walk>()
+        .register_type::<Sprinting>()
+        .register_type::<Crouching>()
+        .register_type::<Knockback>()
+        .register_type::<SpeedMultiplier>()
+        .register_type::<ExternalDrift>()
+        .register_type::<Stamina>()
         .register_type::<CharacterAnimations>()
+        .register_type::<AnimationState>()
+        .register_type::<FootIk>()
+        .register_type::<SpringBones>()
         .add_systems(
             Update,
-            (apply_jumping, apply_walking, play_animations)
+            (
+                apply_crouching,
+                apply_jumping,
+                use_stamina,
+                apply_walking,
+                apply_knockback,
+                link_animation_players,
+                play_animations,
+            )
                 .chain()
                 .in_set(GeneralMovementSystemSet)
-                .before(PhysicsSet::Prepare)
                 .run_if(in_state(GameState::Playing)),
         )
         .add_systems(
             Update,
-            prepare_models_of_controllers.after(PhysicsSet::Sync),
+            (
+                prepare_models_of_controllers,
+                resolve_foot_ik_bones,
+                resolve_spring_bones,
+            ),
+        )
+        .add_systems(
+            Update,
+            play_animation_sounds.run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            FixedUpdate,
+            sweep_fast_characters
+                .after(PhysicsSet::Prepare)
+                .before(PhysicsSet::StepSimulation)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            PostUpdate,
+            emit_animation_events
+                .after(bevy::animation::animation_player)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            PostUpdate,
+            (apply_foot_ik, simulate_spring_bones)
+                .after(bevy::animation::animation_player)
+                .before(TransformSystem::TransformPropagate)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            PostUpdate,
+            update_skinned_bounds
+                .after(TransformSystem::TransformPropagate)
+                .after(VisibilitySystems::CalculateBounds)
+                .before(VisibilitySystems::CheckVisibility),
         );
 }
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
 pub(crate) struct GeneralMovementSystemSet;
 
-/// Managed by [`play_animations`]
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub(crate) enum AnimationState {
-    Standing,
-    Airborne,
-    Walking(f32),
-    Running(f32),
+/// The state of a character's [`AnimationGraph`], managed by [`play_animations`].
+/// Empty until the graph has been loaded.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct AnimationState {
+    pub(crate) state: String,
+    /// Seconds since `state` was entered
+    #[serde(skip)]
+    pub(crate) elapsed: f32,
 }
 
 pub(crate) fn apply_walking(
@@ -50,40 +129,135 @@ pub(crate) fn apply_walking(
         &mut TnuaController,
         &mut Walk,
         Option<&Sprinting>,
+        Option<&Crouching>,
+        Option<&SpeedMultiplier>,
+        Option<&ExternalDrift>,
         &FloatHeight,
     )>,
 ) {
     #[cfg(feature = "tracing")]
     let _span = info_span!("apply_walking").entered();
-    for (mut controller, mut walking, sprinting, float_height) in &mut character_query {
+    for (
+        mut controller,
+        mut walking,
+        sprinting,
+        crouching,
+        speed_multiplier,
+        drift,
+        float_height,
+    ) in &mut character_query
+    {
         let direction = walking.direction.unwrap_or_default();
-        let sprinting_multiplier = sprinting
-            .filter(|s| s.requested)
-            .map(|s| s.multiplier)
-            .unwrap_or(1.);
-        let speed = walking.speed * sprinting_multiplier;
+        let crouching_multiplier = crouching.filter(|c| c.requested).map(|c| c.multiplier);
+        let sprinting_multiplier = sprinting.filter(|s| s.requested).map(|s| s.multiplier);
+        // Crouching takes precedence, you cannot sprint while sneaking
+        let speed = walking.speed
+            * crouching_multiplier.or(sprinting_multiplier).unwrap_or(1.)
+            * speed_multiplier.map_or(1., |multiplier| multiplier.0);
         controller.basis(TnuaBuiltinWalk {
-            desired_velocity: direction * speed,
-            desired_forward: direction.normalize_or_zero(),
+            desired_velocity: direction * speed + drift.map_or(Vec3::ZERO, |drift| drift.0),
+            desired_forward: walking.facing.unwrap_or(direction).normalize_or_zero(),
             float_height: float_height.0,
             cling_distance: 0.1,
             ..Default::default()
         });
         walking.direction = None;
+        walking.facing = None;
     }
 }
 
-pub(crate) fn apply_jumping(mut character_query: Query<(&mut TnuaController, &mut Jump)>) {
+pub(crate) fn use_stamina(
+    time: Res<Time<Virtual>>,
+    mut character_query: Query<(&mut Stamina, &mut Sprinting, &Walk)>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("use_stamina").entered();
+    let dt = time.delta_seconds();
+    for (mut stamina, mut sprinting, walk) in &mut character_query {
+        let is_moving = walk
+            .direction
+            .is_some_and(|direction| !direction.is_approx_zero());
+        if stamina.exhausted {
+            sprinting.requested = false;
+        }
+        if sprinting.requested && is_moving {
+            stamina.current = (stamina.current - stamina.drain * dt).max(0.);
+        } else if stamina.current < stamina.max {
+            stamina.current = (stamina.current + stamina.regeneration * dt).min(stamina.max);
+        }
+        if stamina.current <= 0. {
+            stamina.exhausted = true;
+        } else if stamina.exhausted && stamina.fraction() >= 0.25 {
+            stamina.exhausted = false;
+        }
+    }
+}
+
+/// Runs after [`apply_walking`] so that a knockback replaces whatever movement the character wanted to do.
+pub(crate) fn apply_knockback(
+    mut commands: Commands,
+    time: Res<Time<Virtual>>,
+    mut character_query: Query<(Entity, &mut TnuaController, &mut Knockback, &FloatHeight)>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("apply_knockback").entered();
+    let dt = time.delta_seconds();
+    for (entity, mut controller, mut knockback, float_height) in &mut character_query {
+        knockback.remaining -= dt;
+        if knockback.remaining <= 0. {
+            commands.entity(entity).remove::<Knockback>();
+            continue;
+        }
+        controller.basis(TnuaBuiltinWalk {
+            desired_velocity: knockback.velocity,
+            float_height: float_height.0,
+            cling_distance: 0.1,
+            ..Default::default()
+        });
+    }
+}
+
+/// Runs before [`apply_jumping`] so that jumping out of a crouch replaces the crouch action.
+pub(crate) fn apply_crouching(mut character_query: Query<(&mut TnuaController, &Crouching)>) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("apply_crouching").entered();
+    for (mut controller, crouching) in &mut character_query {
+        if crouching.requested {
+            controller.action(TnuaBuiltinCrouch {
+                float_offset: crouching.float_offset,
+                ..Default::default()
+            });
+        }
+    }
+}
+
+/// Tnua only jumps off the ground, so air jumps launch the character upwards directly.
+pub(crate) fn apply_jumping(
+    gravity: Res<Gravity>,
+    mut character_query: Query<(&mut TnuaController, &mut Jump, &mut LinearVelocity)>,
+) {
     #[cfg(feature = "tracing")]
     let _span = info_span!("apply_jumping").entered();
-    for (mut controller, mut jump) in &mut character_query {
-        if jump.requested {
+    for (mut controller, mut jump, mut velocity) in &mut character_query {
+        let is_airborne = controller.is_airborne().unwrap_or(false);
+        if !is_airborne {
+            jump.air_jumps_left = jump.air_jumps;
+        }
+        let just_requested = jump.requested && !jump.was_requested;
+        jump.was_requested = jump.requested;
+        if !jump.requested {
+            continue;
+        }
+        if is_airborne && just_requested && jump.air_jumps_left > 0 {
+            jump.air_jumps_left -= 1;
+            velocity.y = (2. * gravity.0.length() * jump.height).sqrt();
+        } else {
             controller.action(TnuaBuiltinJump {
                 height: jump.height,
                 takeoff_extra_gravity: 10.0,
                 ..Default::default()
             });
-            jump.requested = false;
         }
+        jump.requested = false;
     }
 }
@@ -0,0 +1,141 @@
+use crate::{
+    file_system_interaction::{
+        asset_loading::{AnimationAssets, GltfAssets},
+        sound_effects::{SoundCategory, SoundEffect},
+    },
+    movement::character_controller::AnimationLink,
+};
+use bevy::{gltf::Gltf, prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+/// Markers on the animation clips of the level's GLTF, which cannot carry them itself.
+/// Loaded from the `*.animevents.ron` file next to it, mapping clip names to the markers on them.
+#[derive(Debug, Clone, PartialEq, Asset, TypePath, Serialize, Deserialize, Default)]
+#[serde(transparent)]
+pub(crate) struct AnimationEventMarkers(pub(crate) HashMap<String, Vec<AnimationEventMarker>>);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct AnimationEventMarker {
+    /// Seconds from the start of the clip
+    pub(crate) time: f32,
+    pub(crate) event: AnimationEventKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) enum AnimationEventKind {
+    /// A foot touches the ground
+    Footstep,
+    /// Starts an attack lasting the given seconds. The [`HitWindow`](crate::combat::hitboxes::HitWindow)s
+    /// of the character's hitboxes count from this marker.
+    Attack(f32),
+    /// Plays the sound at the given path
+    Sound(String),
+    /// Plays a visual effect by name, e.g. `"impact"`
+    Effect(String),
+}
+
+/// Sent when the animation playing on `entity` passes a marker.
+#[derive(Debug, Clone, PartialEq, Event)]
+pub(crate) struct AnimationEvent {
+    /// The character playing the animation, or the entity with the [`AnimationPlayer`] if it belongs to no character
+    pub(crate) entity: Entity,
+    /// Name of the clip the marker is on
+    pub(crate) clip: String,
+    pub(crate) kind: AnimationEventKind,
+}
+
+/// Where in which clip an [`AnimationPlayer`] was when its markers were last checked
+#[derive(Debug, Clone, PartialEq, Component)]
+pub(crate) struct AnimationEventCursor {
+    clip: AssetId<AnimationClip>,
+    time: f32,
+}
+
+/// Runs after the animations have advanced and sends an [`AnimationEvent`] for every marker passed since the last frame.
+pub(crate) fn emit_animation_events(
+    mut commands: Commands,
+    mut players: Query<(Entity, &AnimationPlayer, Option<&mut AnimationEventCursor>)>,
+    links: Query<(Entity, &AnimationLink)>,
+    animation_assets: Res<AnimationAssets>,
+    markers: Res<Assets<AnimationEventMarkers>>,
+    gltf_assets: Res<GltfAssets>,
+    gltfs: Res<Assets<Gltf>>,
+    mut clip_names: Local<HashMap<AssetId<AnimationClip>, String>>,
+    mut events: EventWriter<AnimationEvent>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("emit_animation_events").entered();
+    let Some(markers) = markers.get(&animation_assets.events) else {
+        return;
+    };
+    if clip_names.is_empty() {
+        let Some(level) = gltfs.get(gltf_assets.level.clone()) else {
+            return;
+        };
+        *clip_names = level
+            .named_animations
+            .iter()
+            .map(|(name, clip)| (clip.id(), name.clone()))
+            .collect();
+    }
+    let characters: HashMap<_, _> = links
+        .iter()
+        .map(|(character, link)| (link.0, character))
+        .collect();
+    for (entity, player, cursor) in players.iter_mut() {
+        let clip = player.animation_clip().id();
+        let time = player.seek_time();
+        let previous = cursor
+            .as_ref()
+            .filter(|cursor| cursor.clip == clip)
+            .map(|cursor| cursor.time);
+        match cursor {
+            Some(mut cursor) => {
+                if cursor.clip != clip || cursor.time != time {
+                    *cursor = AnimationEventCursor { clip, time };
+                }
+            }
+            None => {
+                commands
+                    .entity(entity)
+                    .insert(AnimationEventCursor { clip, time });
+            }
+        }
+        if previous == Some(time) {
+            continue;
+        }
+        let Some(name) = clip_names.get(&clip) else {
+            continue;
+        };
+        for marker in markers.0.get(name).into_iter().flatten() {
+            if passed(marker.time, previous, time) {
+                events.send(AnimationEvent {
+                    entity: characters.get(&entity).copied().unwrap_or(entity),
+                    clip: name.clone(),
+                    kind: marker.event.clone(),
+                });
+            }
+        }
+    }
+}
+
+/// Whether a clip that went from `previous` to `current` passed `marker`. `previous` is `None` if the clip just started.
+fn passed(marker: f32, previous: Option<f32>, current: f32) -> bool {
+    match previous {
+        None => marker <= current,
+        Some(previous) if previous <= current => previous < marker && marker <= current,
+        // A repeating clip wrapped around its end
+        Some(previous) => previous < marker || marker <= current,
+    }
+}
+
+pub(crate) fn play_animation_sounds(
+    mut events: EventReader<AnimationEvent>,
+    mut sound_effects: EventWriter<SoundEffect>,
+) {
+    for event in events.read() {
+        if let AnimationEventKind::Sound(path) = &event.kind {
+            sound_effects.send(SoundEffect::new(path, SoundCategory::Character));
+        }
+    }
+}
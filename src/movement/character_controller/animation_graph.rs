@@ -0,0 +1,124 @@
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+/// A state machine deciding which animation a character plays, loaded from `*.animgraph.ron` assets.
+/// Every frame, the [`transitions`](Self::transitions) are checked in order against the character's
+/// [`AnimationVariables`]. The first one that applies decides the next state, so more specific transitions go first.
+#[derive(Debug, Clone, PartialEq, Asset, TypePath, Serialize, Deserialize)]
+pub(crate) struct AnimationGraph {
+    /// State a character starts in
+    pub(crate) initial: String,
+    pub(crate) states: HashMap<String, AnimationGraphState>,
+    pub(crate) transitions: Vec<AnimationTransition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct AnimationGraphState {
+    /// Name of the animation in the level's GLTF
+    pub(crate) animation: String,
+    #[serde(default = "default_repeat")]
+    pub(crate) repeat: bool,
+    /// Seconds to blend from the previous animation into this one
+    #[serde(default = "default_blend")]
+    pub(crate) blend: f32,
+    /// Plays the animation faster or slower depending on a variable. Without it, the animation plays at normal speed.
+    #[serde(default)]
+    pub(crate) speed: Option<PlaybackSpeed>,
+}
+
+/// The animation plays at `variable * scale` times its normal speed, but at least at `min` times.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct PlaybackSpeed {
+    pub(crate) variable: String,
+    pub(crate) scale: f32,
+    #[serde(default)]
+    pub(crate) min: f32,
+}
+
+/// Goes to `to` if all conditions in `when` hold.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct AnimationTransition {
+    /// Only applies while in this state. Applies in every state if `None`.
+    #[serde(default)]
+    pub(crate) from: Option<String>,
+    pub(crate) to: String,
+    #[serde(default)]
+    pub(crate) when: Vec<AnimationCondition>,
+}
+
+/// Compares one of the [`AnimationVariables`] by its name.
+/// Unknown variables count as 0, so that graphs can use variables only some characters have.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) enum AnimationCondition {
+    Above(String, f32),
+    Below(String, f32),
+    True(String),
+    False(String),
+}
+
+impl AnimationCondition {
+    pub(crate) fn holds(&self, variables: &AnimationVariables) -> bool {
+        match self {
+            Self::Above(name, threshold) => variables.get(name) > *threshold,
+            Self::Below(name, threshold) => variables.get(name) < *threshold,
+            Self::True(name) => variables.get(name) != 0.,
+            Self::False(name) => variables.get(name) == 0.,
+        }
+    }
+}
+
+impl AnimationGraph {
+    /// The state to go to from `current`, which may be `current` itself
+    pub(crate) fn next_state<'a>(
+        &'a self,
+        current: &'a str,
+        variables: &AnimationVariables,
+    ) -> &'a str {
+        self.transitions
+            .iter()
+            .filter(|transition| {
+                transition
+                    .from
+                    .as_ref()
+                    .map_or(true, |from| from.as_str() == current)
+            })
+            .find(|transition| {
+                transition
+                    .when
+                    .iter()
+                    .all(|condition| condition.holds(variables))
+            })
+            .map_or(current, |transition| transition.to.as_str())
+    }
+}
+
+/// Values describing what a character is doing, read by [`AnimationCondition`]s and [`PlaybackSpeed`]s.
+/// Flags are 1 if set and 0 if not.
+/// - `speed`: horizontal speed the character is walking at
+/// - `vertical_speed`: positive while moving upwards
+/// - `airborne`, `crouching`, `sprinting`
+/// - `time_in_state`: seconds since the current state was entered
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) struct AnimationVariables(HashMap<&'static str, f32>);
+
+impl AnimationVariables {
+    pub(crate) fn set(&mut self, name: &'static str, value: f32) {
+        self.0.insert(name, value);
+    }
+
+    pub(crate) fn set_flag(&mut self, name: &'static str, value: bool) {
+        self.set(name, if value { 1. } else { 0. });
+    }
+
+    pub(crate) fn get(&self, name: &str) -> f32 {
+        self.0.get(name).copied().unwrap_or_default()
+    }
+}
+
+fn default_repeat() -> bool {
+    true
+}
+
+fn default_blend() -> f32 {
+    0.2
+}
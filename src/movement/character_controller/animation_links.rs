@@ -0,0 +1,81 @@
+use crate::{
+    level_instantiation::validation::{SpawnProblemKind, SpawnReport},
+    movement::character_controller::{AnimationState, CharacterAnimations},
+};
+use bevy::{animation::AnimationPlayer, prelude::*};
+
+/// Seconds to wait for a character's model to spawn its animation player before reporting it as missing
+const LINK_TIMEOUT: f32 = 5.;
+
+/// Points from a character to the entity holding the [`AnimationPlayer`] of its model,
+/// which may be the character itself or any of its descendants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+pub(crate) struct AnimationLink(pub(crate) Entity);
+
+/// How long a character has been waiting for an [`AnimationLink`]
+#[derive(Debug, Clone, Copy, PartialEq, Component, Default)]
+pub(crate) struct PendingAnimationLink {
+    waited: f32,
+    reported: bool,
+}
+
+/// Links characters with [`CharacterAnimations`] to the animation player of their model. Models are scenes
+/// that spawn some frames after the character, and may be swapped out later, so characters are searched
+/// until a player shows up and searched again whenever their player disappears.
+/// Until a character is linked, its [`AnimationState`] stays where it is, so the animation it should play
+/// starts as soon as the link is made. Characters that are still unlinked after [`LINK_TIMEOUT`] seconds
+/// are added to the [`SpawnReport`] once, but keep being searched.
+pub(crate) fn link_animation_players(
+    mut commands: Commands,
+    time: Res<Time<Virtual>>,
+    mut characters: Query<
+        (
+            Entity,
+            Option<&Name>,
+            Option<&AnimationLink>,
+            Option<&mut PendingAnimationLink>,
+            Option<&mut AnimationState>,
+        ),
+        With<CharacterAnimations>,
+    >,
+    children: Query<&Children>,
+    animation_players: Query<(), With<AnimationPlayer>>,
+    mut report: ResMut<SpawnReport>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("link_animation_players").entered();
+    for (entity, name, link, pending, animation_state) in characters.iter_mut() {
+        if link.is_some_and(|link| animation_players.contains(link.0)) {
+            continue;
+        }
+        let found = std::iter::once(entity)
+            .chain(children.iter_descendants(entity))
+            .find(|candidate| animation_players.contains(*candidate));
+        match (found, pending) {
+            (Some(player), _) => {
+                commands
+                    .entity(entity)
+                    .insert(AnimationLink(player))
+                    .remove::<PendingAnimationLink>();
+                // A new player has nothing playing yet, so the current state has to be started again
+                if let Some(mut animation_state) = animation_state {
+                    animation_state.state.clear();
+                }
+            }
+            (None, Some(mut pending)) => {
+                pending.waited += time.delta_seconds();
+                if pending.waited >= LINK_TIMEOUT && !pending.reported {
+                    pending.reported = true;
+                    report.add(entity, name, SpawnProblemKind::MissingAnimationPlayer);
+                }
+            }
+            (None, None) => {
+                let mut entity_commands = commands.entity(entity);
+                entity_commands.insert(PendingAnimationLink::default());
+                if link.is_some() {
+                    entity_commands.remove::<AnimationLink>();
+                }
+            }
+        }
+    }
+}
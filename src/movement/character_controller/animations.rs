@@ -1,77 +1,98 @@
-use crate::movement::character_controller::{AnimationState, CharacterAnimations};
-use bevy::{animation::AnimationPlayer, prelude::*};
-use bevy_mod_sysfail::sysfail;
-use bevy_tnua::{
-    builtins::TnuaBuiltinWalk, controller::TnuaController, TnuaAnimatingState,
-    TnuaAnimatingStateDirective,
+use crate::{
+    file_system_interaction::asset_loading::GltfAssets,
+    movement::character_controller::{
+        AnimationGraph, AnimationLink, AnimationState, AnimationVariables, CharacterAnimations,
+        Crouching, Sprinting,
+    },
 };
+use anyhow::Context;
+use bevy::{animation::AnimationPlayer, gltf::Gltf, prelude::*, utils::HashSet};
+use bevy_mod_sysfail::sysfail;
+use bevy_tnua::{builtins::TnuaBuiltinWalk, controller::TnuaController};
+use bevy_xpbd_3d::prelude::*;
 use std::time::Duration;
 
+/// Moves every character through its [`AnimationGraph`] and plays the animation of the state it ends up in
+/// on the animation player it is linked to by its [`AnimationLink`]. Unlinked characters wait for their link.
+/// Problems with the graph or its animations are logged once and leave the character in its current state.
 #[sysfail(log(level = "error"))]
 pub(crate) fn play_animations(
+    time: Res<Time<Virtual>>,
     mut query: Query<(
-        &mut TnuaAnimatingState<AnimationState>,
+        &mut AnimationState,
         &TnuaController,
+        &LinearVelocity,
+        Option<&Crouching>,
+        Option<&Sprinting>,
         &CharacterAnimations,
-        &mut AnimationPlayer,
+        Option<&AnimationLink>,
     )>,
+    mut animation_players: Query<&mut AnimationPlayer>,
+    graphs: Res<Assets<AnimationGraph>>,
+    gltf_assets: Res<GltfAssets>,
+    gltfs: Res<Assets<Gltf>>,
+    mut reported: Local<HashSet<String>>,
 ) -> anyhow::Result<()> {
     #[cfg(feature = "tracing")]
     let _span = info_span!("play_animations").entered();
-    for (mut animating_state, controller, animations, mut animation_player) in query.iter_mut() {
-        match animating_state.update_by_discriminant({
-            let Some((_, basis_state)) = controller.concrete_basis::<TnuaBuiltinWalk>() else {
-                continue;
-            };
-            let speed = basis_state.running_velocity.length();
-            if controller.is_airborne()? {
-                AnimationState::Airborne
-            } else if speed > 10.0 {
-                AnimationState::Running(speed)
-            } else if speed > 0.01 {
-                AnimationState::Walking(speed)
-            } else {
-                AnimationState::Standing
+    let level = gltfs
+        .get(gltf_assets.level.clone())
+        .context("Failed to get the level's GLTF for character animations")?;
+    for (mut animation_state, controller, velocity, crouching, sprinting, animations, link) in
+        query.iter_mut()
+    {
+        let Some(mut animation_player) =
+            link.and_then(|link| animation_players.get_mut(link.0).ok())
+        else {
+            continue;
+        };
+        let Some(graph) = graphs.get(&animations.graph) else {
+            continue;
+        };
+        let Some((_, basis_state)) = controller.concrete_basis::<TnuaBuiltinWalk>() else {
+            continue;
+        };
+        animation_state.elapsed += time.delta_seconds();
+        let mut variables = AnimationVariables::default();
+        variables.set("speed", basis_state.running_velocity.length());
+        variables.set("vertical_speed", velocity.y);
+        variables.set("time_in_state", animation_state.elapsed);
+        variables.set_flag("airborne", controller.is_airborne()?);
+        variables.set_flag("crouching", crouching.is_some_and(|c| c.requested));
+        variables.set_flag("sprinting", sprinting.is_some_and(|s| s.requested));
+
+        // Borrowed from the graph so that the state can be updated below
+        let current = graph
+            .states
+            .get_key_value(&animation_state.state)
+            .map_or(graph.initial.as_str(), |(name, _)| name.as_str());
+        let next = graph.next_state(current, &variables);
+        let Some(state) = graph.states.get(next) else {
+            if reported.insert(next.to_string()) {
+                error!("Animation graph has no state \"{next}\"");
             }
-        }) {
-            TnuaAnimatingStateDirective::Maintain { state } => {
-                if let AnimationState::Running(speed) = state {
-                    let anim_speed = (speed / 7.0).max(1.0);
-                    animation_player.set_speed(anim_speed);
+            continue;
+        };
+        if next != animation_state.state {
+            let Some(clip) = level.named_animations.get(&state.animation) else {
+                if reported.insert(state.animation.clone()) {
+                    error!("Animation \"{}\" not found in the level", state.animation);
                 }
+                continue;
+            };
+            let playing = animation_player
+                .play_with_transition(clip.clone_weak(), Duration::from_secs_f32(state.blend));
+            if state.repeat {
+                playing.repeat();
             }
-            TnuaAnimatingStateDirective::Alter {
-                // We don't need the old state here, but it's available for transition
-                // animations.
-                old_state: _,
-                state,
-            } => match state {
-                AnimationState::Airborne | AnimationState::Running(..) => {
-                    animation_player
-                        .play_with_transition(
-                            animations.aerial.clone_weak(),
-                            Duration::from_secs_f32(0.2),
-                        )
-                        .repeat();
-                }
-                AnimationState::Standing => {
-                    animation_player
-                        .play_with_transition(
-                            animations.idle.clone_weak(),
-                            Duration::from_secs_f32(0.2),
-                        )
-                        .repeat();
-                }
-                AnimationState::Walking(_speed) => {
-                    animation_player
-                        .play_with_transition(
-                            animations.walk.clone_weak(),
-                            Duration::from_secs_f32(0.1),
-                        )
-                        .repeat();
-                }
-            },
+            animation_state.state = next.to_string();
+            animation_state.elapsed = 0.;
         }
+
+        let speed = state.speed.as_ref().map_or(1., |speed| {
+            (variables.get(&speed.variable) * speed.scale).max(speed.min)
+        });
+        animation_player.set_speed(speed);
     }
     Ok(())
 }
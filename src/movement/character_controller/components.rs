@@ -1,9 +1,9 @@
-use crate::{
-    level_instantiation::spawning::objects::CollisionLayer,
-    movement::character_controller::AnimationState,
+use crate::movement::{
+    character_controller::{AnimationGraph, AnimationState},
+    physics::CollisionLayer,
 };
 use bevy::prelude::*;
-use bevy_tnua::{prelude::*, TnuaAnimatingState};
+use bevy_tnua::prelude::*;
 use bevy_tnua_xpbd3d::*;
 use bevy_xpbd_3d::prelude::*;
 use serde::{Deserialize, Serialize};
@@ -12,6 +12,9 @@ use serde::{Deserialize, Serialize};
 pub(crate) struct CharacterControllerBundle {
     pub(crate) walking: Walk,
     pub(crate) sprinting: Sprinting,
+    pub(crate) crouching: Crouching,
+    pub(crate) drift: ExternalDrift,
+    pub(crate) stamina: Stamina,
     pub(crate) jumping: Jump,
     pub(crate) collider: Collider,
     pub(crate) rigid_body: RigidBody,
@@ -20,7 +23,7 @@ pub(crate) struct CharacterControllerBundle {
     pub(crate) tnua_sensor_shape: TnuaXpbd3dSensorShape,
     pub(crate) tnua_controller: TnuaControllerBundle,
     pub(crate) float_height: FloatHeight,
-    pub(crate) animation_state: TnuaAnimatingState<AnimationState>,
+    pub(crate) animation_state: AnimationState,
 }
 
 impl CharacterControllerBundle {
@@ -28,19 +31,14 @@ impl CharacterControllerBundle {
         Self {
             walking: default(),
             sprinting: default(),
+            crouching: default(),
+            drift: default(),
+            stamina: default(),
             jumping: default(),
             collider: Collider::capsule(height, radius),
             rigid_body: RigidBody::Dynamic,
             locked_axes: LockedAxes::new().lock_rotation_x().lock_rotation_z(),
-            collision_layers: CollisionLayers::new(
-                [CollisionLayer::Character],
-                [
-                    CollisionLayer::Player,
-                    CollisionLayer::Character,
-                    CollisionLayer::Terrain,
-                    CollisionLayer::Sensor,
-                ],
-            ),
+            collision_layers: CollisionLayer::character(),
             tnua_sensor_shape: TnuaXpbd3dSensorShape(Collider::capsule(
                 height * 0.95,
                 radius * 0.95,
@@ -59,6 +57,9 @@ pub(crate) struct Walk {
     pub(crate) speed: f32,
     /// Direction in which we want to walk and turn this tick.
     pub(crate) direction: Option<Vec3>,
+    /// Direction to turn to this tick instead of the walking direction, e.g. to strafe around a locked-on target.
+    #[serde(default)]
+    pub(crate) facing: Option<Vec3>,
 }
 
 impl Default for Walk {
@@ -66,6 +67,7 @@ impl Default for Walk {
         Self {
             speed: 8.,
             direction: None,
+            facing: None,
         }
     }
 }
@@ -77,6 +79,15 @@ pub(crate) struct Jump {
     pub(crate) height: f32,
     /// Was jump requested this frame?
     pub(crate) requested: bool,
+    /// How often the character can jump again while in the air
+    #[serde(default)]
+    pub(crate) air_jumps: u32,
+    /// Air jumps left until the character lands
+    #[serde(default)]
+    pub(crate) air_jumps_left: u32,
+    /// Was jump requested last frame? Only a new press starts an air jump.
+    #[serde(default)]
+    pub(crate) was_requested: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
@@ -97,6 +108,88 @@ impl Default for Sprinting {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Crouching {
+    /// The speed multiplier when crouching
+    pub(crate) multiplier: f32,
+    /// How far the body is lowered relative to the [`FloatHeight`]
+    pub(crate) float_offset: f32,
+    /// Was crouching requested?
+    pub(crate) requested: bool,
+}
+
+impl Default for Crouching {
+    fn default() -> Self {
+        Self {
+            multiplier: 0.5,
+            float_offset: -0.2,
+            requested: false,
+        }
+    }
+}
+
+/// Velocity with which the environment carries a character along, e.g. wind. Added to its walking velocity.
+#[derive(Debug, Clone, Copy, PartialEq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct ExternalDrift(pub(crate) Vec3);
+
+/// Scales the walking speed of a character, e.g. while it is slowed.
+#[derive(Debug, Clone, Copy, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct SpeedMultiplier(pub(crate) f32);
+
+impl Default for SpeedMultiplier {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
+
+/// Pushes a character in a direction, overriding its own movement until the knockback runs out.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Knockback {
+    pub(crate) velocity: Vec3,
+    /// Seconds until the character regains control
+    pub(crate) remaining: f32,
+}
+
+/// Used up while sprinting. After running out, sprinting is not possible until a quarter of the stamina has regenerated.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Stamina {
+    pub(crate) current: f32,
+    pub(crate) max: f32,
+    /// Stamina used per second of sprinting
+    pub(crate) drain: f32,
+    /// Stamina regained per second when not sprinting
+    pub(crate) regeneration: f32,
+    pub(crate) exhausted: bool,
+}
+
+impl Stamina {
+    /// Fraction of the maximum stamina that is left, between 0 and 1
+    pub(crate) fn fraction(&self) -> f32 {
+        if self.max <= 0. {
+            0.
+        } else {
+            (self.current / self.max).clamp(0., 1.)
+        }
+    }
+}
+
+impl Default for Stamina {
+    fn default() -> Self {
+        Self {
+            current: 100.,
+            max: 100.,
+            drain: 20.,
+            regeneration: 15.,
+            exhausted: false,
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
 #[reflect(Component, Serialize, Deserialize)]
 /// Must be larger than the height of the entity's center from the bottom of its
@@ -108,14 +201,17 @@ impl Default for Jump {
         Self {
             height: 1.0,
             requested: false,
+            air_jumps: 0,
+            air_jumps_left: 0,
+            was_requested: false,
         }
     }
 }
 
+/// The animation graph a character plays its animations with. The animations it refers to are looked up by
+/// their names in [`Gltf::named_animations`](bevy::gltf::Gltf) of the level.
 #[derive(Debug, Clone, PartialEq, Component, Reflect, Default)]
 #[reflect(Component)]
 pub(crate) struct CharacterAnimations {
-    pub(crate) idle: Handle<AnimationClip>,
-    pub(crate) walk: Handle<AnimationClip>,
-    pub(crate) aerial: Handle<AnimationClip>,
+    pub(crate) graph: Handle<AnimationGraph>,
 }
@@ -0,0 +1,312 @@
+use crate::{
+    movement::physics::CollisionLayer,
+    util::{smoothness_to_lerp_factor, trait_extension::F32Ext},
+};
+use anyhow::Result;
+use bevy::prelude::*;
+use bevy_mod_sysfail::*;
+use bevy_tnua::controller::TnuaController;
+use bevy_xpbd_3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Places the feet of a character on the ground beneath them and lowers the pelvis so that
+/// the lower foot can still reach the ground on slopes and stairs.
+/// Bones are looked up by name in the descendants of the entity. Missing bones are reported once
+/// and the affected leg is simply left to the animation.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct FootIk {
+    pub(crate) enabled: bool,
+    pub(crate) pelvis: String,
+    pub(crate) left_leg: FootIkLeg,
+    pub(crate) right_leg: FootIkLeg,
+    /// How far above and below the animated foot position we look for ground
+    pub(crate) max_step: f32,
+    pub(crate) smoothing: f32,
+}
+
+impl Default for FootIk {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            pelvis: "b_Hip_01".to_string(),
+            left_leg: FootIkLeg {
+                upper: "b_LeftLeg01_015".to_string(),
+                lower: "b_LeftLeg02_016".to_string(),
+                foot: "b_LeftFoot01_017".to_string(),
+            },
+            right_leg: FootIkLeg {
+                upper: "b_RightLeg01_019".to_string(),
+                lower: "b_RightLeg02_020".to_string(),
+                foot: "b_RightFoot01_021".to_string(),
+            },
+            max_step: 0.3,
+            smoothing: 0.1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Serialize, Deserialize)]
+pub(crate) struct FootIkLeg {
+    pub(crate) upper: String,
+    pub(crate) lower: String,
+    pub(crate) foot: String,
+}
+
+/// Resolved bone entities of a [`FootIk`], inserted the first time the rig is seen.
+#[derive(Debug, Clone, PartialEq, Component, Default)]
+pub(crate) struct FootIkBones {
+    pelvis: Option<Entity>,
+    legs: [Option<LegBones>; 2],
+    pelvis_offset: f32,
+    foot_offsets: [f32; 2],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct LegBones {
+    upper: Entity,
+    lower: Entity,
+    foot: Entity,
+}
+
+pub(crate) fn resolve_foot_ik_bones(
+    mut commands: Commands,
+    characters: Query<(Entity, &FootIk), Without<FootIkBones>>,
+    children: Query<&Children>,
+    names: Query<&Name>,
+) {
+    for (entity, foot_ik) in characters.iter() {
+        // The model is spawned asynchronously, so wait until there is something to search through
+        if children.iter_descendants(entity).next().is_none() {
+            continue;
+        }
+        let find = |name: &str| {
+            let bone = children
+                .iter_descendants(entity)
+                .find(|child| names.get(*child).is_ok_and(|n| n.as_str() == name));
+            if bone.is_none() {
+                warn!("Foot IK: no bone named \"{name}\" found on {entity:?}");
+            }
+            bone
+        };
+        let find_leg = |leg: &FootIkLeg| {
+            Some(LegBones {
+                upper: find(&leg.upper)?,
+                lower: find(&leg.lower)?,
+                foot: find(&leg.foot)?,
+            })
+        };
+        let legs = [find_leg(&foot_ik.left_leg), find_leg(&foot_ik.right_leg)];
+        let pelvis = find(&foot_ik.pelvis);
+        commands.entity(entity).insert(FootIkBones {
+            pelvis,
+            legs,
+            ..default()
+        });
+    }
+}
+
+/// Runs after the animation player has written the pose for this frame.
+/// Note that the global transforms used here are from the last frame, which is not noticeable in practice.
+#[sysfail(log(level = "error"))]
+pub(crate) fn apply_foot_ik(
+    time: Res<Time<Virtual>>,
+    mut characters: Query<(&FootIk, &mut FootIkBones, &TnuaController)>,
+    mut transforms: Query<&mut Transform>,
+    global_transforms: Query<&GlobalTransform>,
+    parents: Query<&Parent>,
+    spatial_query: SpatialQuery,
+) -> Result<()> {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("apply_foot_ik").entered();
+    let dt = time.delta_seconds();
+    let filter = CollisionLayer::ground_filter();
+    for (foot_ik, mut bones, controller) in characters.iter_mut() {
+        let active = foot_ik.enabled && !controller.is_airborne()?;
+        let factor = smoothness_to_lerp_factor(foot_ik.smoothing, dt);
+
+        let mut hits = [None; 2];
+        for (leg, hit) in bones.legs.iter().zip(hits.iter_mut()) {
+            let Some(leg) = leg else {
+                continue;
+            };
+            if !active {
+                continue;
+            }
+            let Ok(foot) = global_transforms.get(leg.foot) else {
+                continue;
+            };
+            let origin = foot.translation() + Vec3::Y * foot_ik.max_step;
+            *hit = spatial_query
+                .cast_ray(
+                    origin,
+                    Vec3::NEG_Y,
+                    foot_ik.max_step * 2.,
+                    true,
+                    filter.clone(),
+                )
+                .map(|hit| (foot_ik.max_step - hit.time_of_impact, hit.normal));
+        }
+
+        // Lower the pelvis by the largest downwards step so that both feet can reach the ground
+        let target_pelvis_offset = hits
+            .iter()
+            .flatten()
+            .map(|(offset, _)| *offset)
+            .fold(0.0_f32, f32::min);
+        bones.pelvis_offset = bones.pelvis_offset.lerp(target_pelvis_offset, factor);
+        let pelvis_offset = bones.pelvis_offset;
+        if let Some(pelvis) = bones.pelvis {
+            offset_pelvis(
+                pelvis,
+                pelvis_offset,
+                &mut transforms,
+                &global_transforms,
+                &parents,
+            );
+        }
+
+        for (index, hit) in hits.iter().enumerate() {
+            let target_offset = hit.map(|(offset, _)| offset).unwrap_or_default();
+            bones.foot_offsets[index] = bones.foot_offsets[index].lerp(target_offset, factor);
+            let Some(leg) = bones.legs[index] else {
+                continue;
+            };
+            let normal = hit.map(|(_, normal)| normal).unwrap_or(Vec3::Y);
+            let leg_offset = bones.foot_offsets[index] - pelvis_offset;
+            place_foot(
+                leg,
+                pelvis_offset,
+                leg_offset,
+                normal,
+                &mut transforms,
+                &global_transforms,
+                &parents,
+            );
+        }
+    }
+    Ok(())
+}
+
+fn offset_pelvis(
+    pelvis: Entity,
+    offset: f32,
+    transforms: &mut Query<&mut Transform>,
+    global_transforms: &Query<&GlobalTransform>,
+    parents: &Query<&Parent>,
+) {
+    let parent_global = parents
+        .get(pelvis)
+        .ok()
+        .and_then(|parent| global_transforms.get(parent.get()).ok())
+        .copied()
+        .unwrap_or_default();
+    let local_offset = parent_global
+        .affine()
+        .inverse()
+        .transform_vector3(Vec3::Y * offset);
+    if let Ok(mut transform) = transforms.get_mut(pelvis) {
+        transform.translation += local_offset;
+    }
+}
+
+/// Moves the foot of a leg by `leg_offset` relative to the hip using a two-bone IK solve
+/// and aligns it to the ground normal.
+fn place_foot(
+    leg: LegBones,
+    pelvis_offset: f32,
+    leg_offset: f32,
+    normal: Vec3,
+    transforms: &mut Query<&mut Transform>,
+    global_transforms: &Query<&GlobalTransform>,
+    parents: &Query<&Parent>,
+) {
+    let (Ok(upper), Ok(lower), Ok(foot)) = (
+        global_transforms.get(leg.upper),
+        global_transforms.get(leg.lower),
+        global_transforms.get(leg.foot),
+    ) else {
+        return;
+    };
+    let Some(upper_parent) = parents
+        .get(leg.upper)
+        .ok()
+        .and_then(|parent| global_transforms.get(parent.get()).ok())
+    else {
+        return;
+    };
+    let pelvis_shift = Vec3::Y * pelvis_offset;
+    let hip = upper.translation() + pelvis_shift;
+    let knee = lower.translation() + pelvis_shift;
+    let ankle = foot.translation() + pelvis_shift;
+    let target = ankle + Vec3::Y * leg_offset;
+
+    let (upper_rotation, lower_rotation) = solve_two_bone_ik(hip, knee, ankle, target);
+
+    let (_, upper_global, _) = upper.to_scale_rotation_translation();
+    let (_, lower_global, _) = lower.to_scale_rotation_translation();
+    let (_, foot_global, _) = foot.to_scale_rotation_translation();
+    let (_, upper_parent_global, _) = upper_parent.to_scale_rotation_translation();
+
+    let new_upper_global = upper_rotation * upper_global;
+    let new_lower_global = upper_rotation * lower_rotation * lower_global;
+    let new_foot_global =
+        Quat::from_rotation_arc(Vec3::Y, normal.normalize_or_zero()) * foot_global;
+
+    if let Ok(mut transform) = transforms.get_mut(leg.upper) {
+        transform.rotation = (upper_parent_global.inverse() * new_upper_global).normalize();
+    }
+    if let Ok(mut transform) = transforms.get_mut(leg.lower) {
+        transform.rotation = (new_upper_global.inverse() * new_lower_global).normalize();
+    }
+    if let Ok(mut transform) = transforms.get_mut(leg.foot) {
+        transform.rotation = (new_lower_global.inverse() * new_foot_global).normalize();
+    }
+}
+
+/// Returns the world space rotations to apply to the upper and lower bone so that the end of the chain reaches `target`.
+/// See <https://theorangeduck.com/page/simple-two-joint>
+fn solve_two_bone_ik(hip: Vec3, knee: Vec3, ankle: Vec3, target: Vec3) -> (Quat, Quat) {
+    const EPSILON: f32 = 1e-4;
+    let upper_length = (knee - hip).length();
+    let lower_length = (ankle - knee).length();
+    let target_length = (target - hip)
+        .length()
+        .clamp(EPSILON, upper_length + lower_length - EPSILON);
+
+    let angle = |a: Vec3, b: Vec3| {
+        a.normalize_or_zero()
+            .dot(b.normalize_or_zero())
+            .clamp(-1., 1.)
+            .acos()
+    };
+    let hip_angle_current = angle(ankle - hip, knee - hip);
+    let knee_angle_current = angle(hip - knee, ankle - knee);
+    let hip_to_target_angle = angle(ankle - hip, target - hip);
+
+    let hip_angle_desired =
+        ((lower_length.squared() - upper_length.squared() - target_length.squared())
+            / (-2. * upper_length * target_length))
+            .clamp(-1., 1.)
+            .acos();
+    let knee_angle_desired =
+        ((target_length.squared() - upper_length.squared() - lower_length.squared())
+            / (-2. * upper_length * lower_length))
+            .clamp(-1., 1.)
+            .acos();
+
+    let Some(bend_axis) = (ankle - hip).cross(knee - hip).try_normalize() else {
+        // The leg is fully stretched, so there is no well-defined bending direction
+        return (Quat::IDENTITY, Quat::IDENTITY);
+    };
+    let swing_axis = (ankle - hip)
+        .cross(target - hip)
+        .try_normalize()
+        .unwrap_or(bend_axis);
+
+    let hip_bend = Quat::from_axis_angle(bend_axis, hip_angle_desired - hip_angle_current);
+    let knee_bend = Quat::from_axis_angle(bend_axis, knee_angle_desired - knee_angle_current);
+    let hip_swing = Quat::from_axis_angle(swing_axis, hip_to_target_angle);
+    (hip_swing * hip_bend, knee_bend)
+}
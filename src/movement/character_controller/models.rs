@@ -1,31 +1,78 @@
-use crate::movement::character_controller::FloatHeight;
-use bevy::{prelude::*, render::view::NoFrustumCulling};
+use crate::{
+    level_instantiation::validation::{SpawnProblemKind, SpawnReport},
+    movement::character_controller::FloatHeight,
+};
+use bevy::{
+    prelude::*,
+    render::{mesh::skinning::SkinnedMesh, primitives::Aabb},
+};
 use bevy_tnua::controller::TnuaController;
 use bevy_xpbd_3d::prelude::*;
 
+/// How far the skin of an animated model may reach past its joints, in meters
+const SKINNED_BOUNDS_PADDING: f32 = 0.3;
+
+/// The [`Aabb`] of a skinned mesh, which is recalculated from its joints every frame, see [`update_skinned_bounds`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component, Default)]
+pub(crate) struct SkinnedBounds;
+
 pub(crate) fn prepare_models_of_controllers(
     mut commands: Commands,
-    controllers: Query<(Entity, &Transform, &FloatHeight), (Added<TnuaController>, With<Collider>)>,
+    controllers: Query<
+        (Entity, Option<&Name>, &Transform, &FloatHeight),
+        (Added<TnuaController>, With<Collider>),
+    >,
     mut transforms: Query<&mut Transform, Without<Collider>>,
     children_q: Query<&Children>,
-    meshes: Query<&Handle<Mesh>>,
+    skinned_meshes: Query<(), (With<Handle<Mesh>>, With<SkinnedMesh>)>,
+    mut report: ResMut<SpawnReport>,
 ) {
-    for (entity, transform, float_height) in controllers.iter() {
+    for (entity, name, transform, float_height) in controllers.iter() {
         // Shift models down because XPBD will make controllers float,
         // but our models definitely should not be floating!
         let offset = (float_height.0 / transform.scale.y) * 2.;
-        let children = children_q.get(entity).unwrap();
+        let Ok(children) = children_q.get(entity) else {
+            report.add(entity, name, SpawnProblemKind::MissingChildren);
+            continue;
+        };
         for child in children.iter() {
             if let Ok(mut model_transform) = transforms.get_mut(*child) {
                 model_transform.translation.y -= offset;
             }
         }
 
-        // Frustum culling is erroneous for animated models because the AABB can be too small
+        // The AABB Bevy calculates from the mesh only fits the bind pose, so animated models would be culled too early
         for entity in children_q.iter_descendants(entity) {
-            if meshes.contains(entity) {
-                commands.entity(entity).insert(NoFrustumCulling);
+            if skinned_meshes.contains(entity) {
+                commands.entity(entity).insert(SkinnedBounds);
             }
         }
     }
 }
+
+/// Fits the [`Aabb`]s of skinned meshes around their animated joints, padded by [`SKINNED_BOUNDS_PADDING`]
+/// for the skin around them, so that frustum culling stays enabled for animated characters.
+pub(crate) fn update_skinned_bounds(
+    mut skinned_meshes: Query<(&SkinnedMesh, &GlobalTransform, &mut Aabb), With<SkinnedBounds>>,
+    joints: Query<&GlobalTransform>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("update_skinned_bounds").entered();
+    for (skinned_mesh, mesh_transform, mut aabb) in skinned_meshes.iter_mut() {
+        let world_to_mesh = mesh_transform.affine().inverse();
+        let Some((min, max)) = joints
+            .iter_many(&skinned_mesh.joints)
+            .map(|joint| world_to_mesh.transform_point3(joint.translation()))
+            .fold(None, |bounds: Option<(Vec3, Vec3)>, point| match bounds {
+                Some((min, max)) => Some((min.min(point), max.max(point))),
+                None => Some((point, point)),
+            })
+        else {
+            continue;
+        };
+        // The padding is in world space, but the AABB is in the mesh's space
+        let padding =
+            Vec3::splat(SKINNED_BOUNDS_PADDING) / mesh_transform.compute_transform().scale;
+        *aabb = Aabb::from_min_max(min - padding, max + padding);
+    }
+}
@@ -0,0 +1,181 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Bones whose heads move further than this in a frame were teleported, so their springs are reset instead of flung away
+const TELEPORT_DISTANCE: f32 = 1.;
+
+/// Lets capes, hair, tails and other accessories of a character swing along with its movement.
+/// Every bone whose name starts with [`prefix`](Self::prefix) is simulated as a spring pulling its tail, i.e. its first child,
+/// back to where the animation puts it, so that the bone lags behind, overshoots and sags under gravity.
+/// The last bone of a chain has no tail to simulate and simply follows its parent.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct SpringBones {
+    pub(crate) enabled: bool,
+    pub(crate) prefix: String,
+    /// How strongly bones return to their animated pose
+    pub(crate) stiffness: f32,
+    /// Fraction of its velocity a bone loses every frame, from 0 to 1
+    pub(crate) drag: f32,
+    pub(crate) gravity: Vec3,
+}
+
+impl Default for SpringBones {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            prefix: "spring_".to_string(),
+            stiffness: 1.,
+            drag: 0.4,
+            gravity: Vec3::new(0., -0.5, 0.),
+        }
+    }
+}
+
+/// Resolved bones of [`SpringBones`] and their simulated tails, inserted the first time the rig is seen.
+#[derive(Debug, Clone, PartialEq, Component, Default)]
+pub(crate) struct SpringBoneChains(Vec<Vec<SpringJoint>>);
+
+#[derive(Debug, Clone, PartialEq)]
+struct SpringJoint {
+    bone: Entity,
+    /// Position of the bone's tail in the bone's space
+    tail_offset: Vec3,
+    tail: Option<SpringTail>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SpringTail {
+    head: Vec3,
+    current: Vec3,
+    previous: Vec3,
+}
+
+pub(crate) fn resolve_spring_bones(
+    mut commands: Commands,
+    characters: Query<(Entity, &SpringBones), Without<SpringBoneChains>>,
+    children: Query<&Children>,
+    parents: Query<&Parent>,
+    names: Query<&Name>,
+    transforms: Query<&Transform>,
+) {
+    for (entity, spring_bones) in characters.iter() {
+        // The model is spawned asynchronously, so wait until there is something to search through
+        if children.iter_descendants(entity).next().is_none() {
+            continue;
+        }
+        let is_spring = |bone: Entity| {
+            names
+                .get(bone)
+                .is_ok_and(|name| name.as_str().starts_with(&spring_bones.prefix))
+        };
+        let roots = children.iter_descendants(entity).filter(|&bone| {
+            is_spring(bone)
+                && !parents
+                    .get(bone)
+                    .is_ok_and(|parent| is_spring(parent.get()))
+        });
+        let chains = roots
+            .map(|root| {
+                let mut chain = Vec::new();
+                let mut bone = Some(root);
+                while let Some(current) = bone {
+                    // Follow the first child that is part of the chain, or any bone to serve as the tail
+                    let tail = children.get(current).ok().and_then(|bone_children| {
+                        bone_children
+                            .iter()
+                            .copied()
+                            .find(|&child| is_spring(child))
+                            .or_else(|| bone_children.first().copied())
+                    });
+                    let Some(offset) = tail
+                        .and_then(|tail| transforms.get(tail).ok())
+                        .map(|transform| transform.translation)
+                        .filter(|offset| *offset != Vec3::ZERO)
+                    else {
+                        break;
+                    };
+                    chain.push(SpringJoint {
+                        bone: current,
+                        tail_offset: offset,
+                        tail: None,
+                    });
+                    bone = tail.filter(|&tail| is_spring(tail));
+                }
+                chain
+            })
+            .filter(|chain| !chain.is_empty())
+            .collect();
+        commands.entity(entity).insert(SpringBoneChains(chains));
+    }
+}
+
+/// Runs after the animation player has written the pose for this frame.
+/// Like foot IK, the chains start from the global transforms of last frame.
+pub(crate) fn simulate_spring_bones(
+    time: Res<Time<Virtual>>,
+    mut characters: Query<(&SpringBones, &mut SpringBoneChains)>,
+    mut transforms: Query<&mut Transform>,
+    global_transforms: Query<&GlobalTransform>,
+    parents: Query<&Parent>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("simulate_spring_bones").entered();
+    let dt = time.delta_seconds();
+    if dt <= 0. {
+        return;
+    }
+    for (spring_bones, mut chains) in characters.iter_mut() {
+        if !spring_bones.enabled {
+            continue;
+        }
+        for chain in chains.0.iter_mut() {
+            let Some(root) = chain.first() else {
+                continue;
+            };
+            let Some(mut parent_global) = parents
+                .get(root.bone)
+                .ok()
+                .and_then(|parent| global_transforms.get(parent.get()).ok())
+                .map(|global| global.compute_transform())
+            else {
+                continue;
+            };
+            for joint in chain.iter_mut() {
+                let Ok(mut transform) = transforms.get_mut(joint.bone) else {
+                    break;
+                };
+                let global = parent_global.mul_transform(*transform);
+                let head = global.translation;
+                let animated_tail = global.transform_point(joint.tail_offset);
+                let length = head.distance(animated_tail);
+                let Some(animated_direction) = (animated_tail - head).try_normalize() else {
+                    break;
+                };
+                let tail = match joint.tail {
+                    Some(tail) if tail.head.distance(head) < TELEPORT_DISTANCE => tail,
+                    _ => SpringTail {
+                        head,
+                        current: animated_tail,
+                        previous: animated_tail,
+                    },
+                };
+
+                let inertia = (tail.current - tail.previous) * (1. - spring_bones.drag);
+                let pull = animated_direction * spring_bones.stiffness * dt;
+                let next = tail.current + inertia + pull + spring_bones.gravity * dt;
+                let direction = (next - head).try_normalize().unwrap_or(animated_direction);
+                joint.tail = Some(SpringTail {
+                    head,
+                    current: head + direction * length,
+                    previous: tail.current,
+                });
+
+                let rotation =
+                    Quat::from_rotation_arc(animated_direction, direction) * global.rotation;
+                transform.rotation = (parent_global.rotation.inverse() * rotation).normalize();
+                parent_global = Transform { rotation, ..global };
+            }
+        }
+    }
+}
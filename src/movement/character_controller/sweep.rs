@@ -0,0 +1,93 @@
+use crate::movement::{character_controller::Walk, physics::CollisionLayer};
+use bevy::prelude::*;
+use bevy_xpbd_3d::prelude::*;
+
+/// Characters moving less than this per physics step are left to the regular collision handling
+const MIN_SWEEP_DISTANCE: f32 = 0.1;
+/// Longest distance covered by a single shape cast. Smaller than the thinnest walls characters should not pass.
+const MAX_SUBSTEP_DISTANCE: f32 = 0.25;
+/// Upper bound for the shape casts per character and physics step
+const MAX_SUBSTEPS: usize = 8;
+/// Gap left between a character and whatever it was swept into, so that the next cast does not start inside it
+const SKIN_WIDTH: f32 = 0.01;
+
+/// Prevents fast characters from tunneling through thin walls, e.g. while dashing or at a low framerate.
+/// The displacement of the coming physics step is split into substeps of at most [`MAX_SUBSTEP_DISTANCE`],
+/// each of which casts the character's collider. On a hit, the character slides along the surface
+/// and its velocity is reduced to what actually fits into the step.
+pub(crate) fn sweep_fast_characters(
+    time: Res<Time>,
+    spatial_query: SpatialQuery,
+    mut characters: Query<
+        (&Collider, &Position, &Rotation, &mut LinearVelocity),
+        (With<Walk>, Without<Sensor>),
+    >,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("sweep_fast_characters").entered();
+    let dt = time.delta_seconds();
+    if dt <= 0. {
+        return;
+    }
+    for (collider, position, rotation, mut velocity) in &mut characters {
+        let displacement = velocity.0 * dt;
+        if displacement.length() < MIN_SWEEP_DISTANCE {
+            continue;
+        }
+        if let Some(resolved) = sweep(
+            &spatial_query,
+            collider,
+            position.0,
+            rotation.0,
+            displacement,
+        ) {
+            velocity.0 = resolved / dt;
+        }
+    }
+}
+
+/// Returns how far the collider can actually move, or `None` if nothing is in its way.
+fn sweep(
+    spatial_query: &SpatialQuery,
+    collider: &Collider,
+    start: Vec3,
+    rotation: Quat,
+    displacement: Vec3,
+) -> Option<Vec3> {
+    let filter = CollisionLayer::ground_filter();
+    let substeps =
+        ((displacement.length() / MAX_SUBSTEP_DISTANCE).ceil() as usize).clamp(1, MAX_SUBSTEPS);
+    let mut position = start;
+    let mut remaining = displacement;
+    let mut was_hit = false;
+    for substep in 0..substeps {
+        let step = remaining / (substeps - substep) as f32;
+        let length = step.length();
+        let Some(direction) = step.try_normalize() else {
+            break;
+        };
+        let Some(hit) = spatial_query.cast_shape(
+            collider,
+            position,
+            rotation,
+            direction,
+            length,
+            true,
+            filter.clone(),
+        ) else {
+            position += step;
+            remaining -= step;
+            continue;
+        };
+        was_hit = true;
+        let travelled = direction * (hit.time_of_impact - SKIN_WIDTH).max(0.);
+        position += travelled;
+        remaining -= travelled;
+        // Slide along the surface instead of moving into it
+        let into_surface = remaining.dot(hit.normal1);
+        if into_surface < 0. {
+            remaining -= hit.normal1 * into_surface;
+        }
+    }
+    was_hit.then_some(position - start)
+}
@@ -0,0 +1,195 @@
+use crate::{
+    movement::{
+        navigation::{NavigationSystemSet, Navigator},
+        physics::CollisionLayer,
+    },
+    player_control::player_embodiment::Player,
+    util::{criteria::is_frozen, trait_extension::Vec3Ext},
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_xpbd_3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Companions moving slower than this are considered stuck
+const STUCK_SPEED: f32 = 0.3;
+/// How far in front of the moving player a companion counts as in the way
+const BLOCKING_DISTANCE: f32 = 2.5;
+/// How far to the side of the player's path a companion counts as in the way
+const BLOCKING_WIDTH: f32 = 1.;
+const SIDESTEP_DISTANCE: f32 = 1.5;
+/// How far a companion walks ahead when it cannot step aside, e.g. in a doorway
+const WALK_AHEAD_DISTANCE: f32 = 3.;
+
+/// Makes NPCs with a [`Companion`] follow the player around using their [`Navigator`].
+/// A companion walks towards the player once they are further away than its follow distance and stops again once it is close.
+/// When the player is more than the teleport distance away or the companion has not made progress for a while,
+/// it teleports behind the player. When the player walks towards an idle companion, it steps aside,
+/// or walks ahead if there is no room to the sides, so that it does not block doorways and corridors.
+/// Companions stand still while the player's actions are frozen, e.g. during dialogs and cutscenes.
+pub(crate) fn companion_plugin(app: &mut App) {
+    app.register_type::<Companion>().add_systems(
+        Update,
+        (
+            init_companions,
+            follow_player.run_if(not(is_frozen)),
+            stop_companions.run_if(is_frozen),
+        )
+            .chain()
+            .before(NavigationSystemSet)
+            .run_if(in_state(GameState::Playing)),
+    );
+}
+
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Companion {
+    /// Distance to the player the companion keeps
+    pub(crate) follow_distance: f32,
+    /// Distance to the player beyond which the companion teleports behind them
+    pub(crate) teleport_distance: f32,
+    /// Seconds the companion may be stuck before it teleports behind the player
+    pub(crate) stuck_duration: f32,
+}
+
+impl Default for Companion {
+    fn default() -> Self {
+        Self {
+            follow_distance: 3.,
+            teleport_distance: 30.,
+            stuck_duration: 3.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Component, Default)]
+struct CompanionState {
+    /// Seconds without progress while walking somewhere
+    stuck_time: f32,
+    last_position: Option<Vec3>,
+}
+
+fn init_companions(mut commands: Commands, companions: Query<Entity, Added<Companion>>) {
+    for entity in companions.iter() {
+        commands
+            .entity(entity)
+            .insert((CompanionState::default(), Navigator::default()));
+    }
+}
+
+fn follow_player(
+    time: Res<Time>,
+    mut companions: Query<
+        (
+            &Companion,
+            &mut CompanionState,
+            &mut Navigator,
+            &mut Transform,
+            Option<&mut LinearVelocity>,
+        ),
+        Without<Player>,
+    >,
+    players: Query<(&Transform, &LinearVelocity), With<Player>>,
+    spatial_query: SpatialQuery,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("follow_player").entered();
+    let Ok((player_transform, player_velocity)) = players.get_single() else {
+        return;
+    };
+    let dt = time.delta_seconds();
+    let player_position = player_transform.translation;
+    for (companion, mut state, mut navigator, mut transform, velocity) in companions.iter_mut() {
+        let position = transform.translation;
+        let moved = state
+            .last_position
+            .map_or(f32::INFINITY, |last| last.distance(position));
+        state.last_position = Some(position);
+        if navigator.destination.is_some() && moved < STUCK_SPEED * dt {
+            state.stuck_time += dt;
+        } else {
+            state.stuck_time = 0.;
+        }
+
+        let distance = position.distance(player_position);
+        if distance > companion.teleport_distance || state.stuck_time > companion.stuck_duration {
+            transform.translation =
+                teleport_target(player_transform, companion.follow_distance, &spatial_query);
+            if let Some(mut velocity) = velocity {
+                velocity.0 = Vec3::ZERO;
+            }
+            *state = default();
+            navigator.destination = None;
+            continue;
+        }
+        if distance > companion.follow_distance {
+            navigator.destination = Some(player_position);
+            continue;
+        }
+        navigator.destination =
+            make_way(position, player_position, player_velocity.0, &spatial_query);
+    }
+}
+
+/// Where to go to get out of the way of the player, if the companion is in their way
+fn make_way(
+    position: Vec3,
+    player_position: Vec3,
+    player_velocity: Vec3,
+    spatial_query: &SpatialQuery,
+) -> Option<Vec3> {
+    let heading = player_velocity.horizontal().try_normalize()?;
+    let offset = (position - player_position).horizontal();
+    let ahead = offset.dot(heading);
+    let side = Vec3::Y.cross(heading);
+    let lateral = offset.dot(side);
+    if !(0.0..BLOCKING_DISTANCE).contains(&ahead) || lateral.abs() > BLOCKING_WIDTH {
+        return None;
+    }
+    // Prefer the side the companion is already on
+    let preferred_side = if lateral >= 0. { side } else { -side };
+    let filter = CollisionLayer::ground_filter();
+    let is_free = |direction: Vec3, distance: f32| {
+        spatial_query
+            .cast_ray(position, direction, distance, true, filter.clone())
+            .is_none()
+    };
+    [preferred_side, -preferred_side]
+        .into_iter()
+        .find(|side| is_free(*side, SIDESTEP_DISTANCE))
+        .map(|side| position + side * SIDESTEP_DISTANCE)
+        .or_else(|| {
+            is_free(heading, WALK_AHEAD_DISTANCE).then(|| position + heading * WALK_AHEAD_DISTANCE)
+        })
+}
+
+/// A spot behind the player that is not inside a wall
+fn teleport_target(
+    player_transform: &Transform,
+    follow_distance: f32,
+    spatial_query: &SpatialQuery,
+) -> Vec3 {
+    let player_position = player_transform.translation;
+    let behind = player_transform
+        .back()
+        .horizontal()
+        .try_normalize()
+        .unwrap_or(Vec3::Z);
+    let distance = spatial_query
+        .cast_ray(
+            player_position,
+            behind,
+            follow_distance,
+            true,
+            CollisionLayer::ground_filter(),
+        )
+        .map_or(follow_distance, |hit| (hit.time_of_impact - 0.5).max(0.));
+    player_position + behind * distance
+}
+
+fn stop_companions(mut companions: Query<(&mut Navigator, &mut CompanionState)>) {
+    for (mut navigator, mut state) in companions.iter_mut() {
+        navigator.destination = None;
+        *state = default();
+    }
+}
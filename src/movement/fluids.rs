@@ -0,0 +1,175 @@
+use crate::{
+    movement::character_controller::{apply_jumping, GeneralMovementSystemSet, Jump},
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_xpbd_3d::{prelude::*, PhysicsSet};
+use serde::{Deserialize, Serialize};
+
+/// Upwards speed of a character pressing jump while swimming
+const SWIM_UP_SPEED: f32 = 2.5;
+/// How much of a character needs to be under water before it swims instead of jumps
+const SWIM_SUBMERSION: f32 = 0.4;
+
+/// Simulates bodies of water and other fluids. A [`FluidVolume`] is the single definition used for
+/// - marking everything dynamic inside it as [`Submerged`],
+/// - pushing submerged rigid bodies up with a buoyant force and slowing them down with drag,
+/// - letting submerged characters swim upwards instead of jumping,
+/// - and drawing the water surface, see [`crate::level_instantiation::spawning::objects::water`].
+///
+/// Whether something floats depends on its [`ColliderDensity`] compared to the [`FluidVolume::density`].
+pub(crate) fn fluids_plugin(app: &mut App) {
+    app.register_type::<FluidVolume>()
+        .register_type::<Submerged>()
+        .add_systems(
+            FixedUpdate,
+            (detect_submerged_bodies, apply_buoyancy)
+                .chain()
+                .before(PhysicsSet::Prepare)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            Update,
+            swim.in_set(GeneralMovementSystemSet)
+                .before(apply_jumping)
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+/// An axis-aligned box of fluid centered on its entity's translation. The top of the box is the fluid's surface.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct FluidVolume {
+    pub(crate) half_extents: Vec3,
+    /// Bodies with a lower [`ColliderDensity`] float, bodies with a higher one sink.
+    /// Colliders have a density of 1 by default.
+    pub(crate) density: f32,
+    /// How quickly submerged bodies lose their velocity
+    pub(crate) linear_drag: f32,
+    pub(crate) angular_drag: f32,
+    pub(crate) color: Color,
+}
+
+impl Default for FluidVolume {
+    fn default() -> Self {
+        Self {
+            half_extents: Vec3::new(5., 1., 5.),
+            density: 1.5,
+            linear_drag: 1.5,
+            angular_drag: 1.,
+            color: Color::rgba(0.1, 0.35, 0.5, 0.7),
+        }
+    }
+}
+
+impl FluidVolume {
+    pub(crate) fn surface_height(&self, transform: &GlobalTransform) -> f32 {
+        transform.translation().y + self.half_extents.y
+    }
+
+    fn contains_horizontally(&self, transform: &GlobalTransform, point: Vec3) -> bool {
+        let offset = (point - transform.translation()).abs();
+        offset.x <= self.half_extents.x && offset.z <= self.half_extents.z
+    }
+}
+
+/// Present on rigid bodies that are at least partially inside a [`FluidVolume`].
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Submerged {
+    pub(crate) volume: Entity,
+    /// Fraction of the body's height that is below the surface, between 0 and 1
+    pub(crate) fraction: f32,
+    /// Distance from the body's lowest point to the surface
+    pub(crate) depth: f32,
+}
+
+fn detect_submerged_bodies(
+    mut commands: Commands,
+    volumes: Query<(Entity, &FluidVolume, &GlobalTransform)>,
+    bodies: Query<(Entity, &RigidBody, &ColliderAabb, Option<&Submerged>)>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("detect_submerged_bodies").entered();
+    for (entity, rigid_body, aabb, submerged) in bodies.iter() {
+        if !rigid_body.is_dynamic() {
+            continue;
+        }
+        let min = Vec3::new(aabb.mins.x, aabb.mins.y, aabb.mins.z);
+        let max = Vec3::new(aabb.maxs.x, aabb.maxs.y, aabb.maxs.z);
+        let center = (min + max) / 2.;
+        let height = (max.y - min.y).max(1e-5);
+        let submersion = volumes.iter().find_map(|(volume, fluid, transform)| {
+            if !fluid.contains_horizontally(transform, center) {
+                return None;
+            }
+            let surface = fluid.surface_height(transform);
+            let bottom = transform.translation().y - fluid.half_extents.y;
+            if min.y > surface || max.y < bottom {
+                return None;
+            }
+            Some(Submerged {
+                volume,
+                fraction: ((surface - min.y) / height).clamp(0., 1.),
+                depth: surface - min.y,
+            })
+        });
+        match (submersion, submerged) {
+            (Some(submersion), Some(submerged)) if submersion == *submerged => {}
+            (Some(submersion), _) => {
+                commands.entity(entity).insert(submersion);
+            }
+            (None, Some(_)) => {
+                commands.entity(entity).remove::<Submerged>();
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+fn apply_buoyancy(
+    time: Res<Time>,
+    gravity: Res<Gravity>,
+    volumes: Query<&FluidVolume>,
+    mut bodies: Query<(
+        &Submerged,
+        &ColliderMassProperties,
+        &Mass,
+        &mut LinearVelocity,
+        &mut AngularVelocity,
+    )>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("apply_buoyancy").entered();
+    let dt = time.delta_seconds();
+    for (submerged, collider_mass, mass, mut linear_velocity, mut angular_velocity) in
+        bodies.iter_mut()
+    {
+        let Ok(fluid) = volumes.get(submerged.volume) else {
+            continue;
+        };
+        if mass.0 <= 0. || collider_mass.density <= 0. {
+            continue;
+        }
+        // Archimedes: the fluid pushes up with the weight of the fluid the body displaces
+        let volume = collider_mass.mass.0 / collider_mass.density;
+        let displaced_mass = fluid.density * volume * submerged.fraction;
+        let buoyancy = -gravity.0 * displaced_mass / mass.0;
+        linear_velocity.0 += buoyancy * dt;
+
+        let drag = submerged.fraction * dt;
+        linear_velocity.0 /= 1. + fluid.linear_drag * drag;
+        angular_velocity.0 /= 1. + fluid.angular_drag * drag;
+    }
+}
+
+/// Turns jumps of characters that are too deep in a fluid to stand into swimming strokes towards the surface.
+fn swim(mut characters: Query<(&mut Jump, &Submerged, &mut LinearVelocity)>) {
+    for (mut jump, submerged, mut linear_velocity) in characters.iter_mut() {
+        if !jump.requested || submerged.fraction < SWIM_SUBMERSION {
+            continue;
+        }
+        jump.requested = false;
+        linear_velocity.y = linear_velocity.y.max(SWIM_UP_SPEED);
+    }
+}
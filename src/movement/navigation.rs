@@ -1,7 +1,9 @@
 use crate::{
-    level_instantiation::spawning::objects::player,
-    movement::character_controller::{GeneralMovementSystemSet, Walk},
-    player_control::player_embodiment::Player,
+    level_instantiation::{post_spawn::PostSpawnStage, spawning::objects::player},
+    movement::{
+        character_controller::{GeneralMovementSystemSet, Walk},
+        physics::ColliderMarker,
+    },
     util::trait_extension::{F32Ext, Vec3Ext},
     GameState,
 };
@@ -9,23 +11,31 @@ use crate::{
 #[cfg(feature = "dev")]
 use anyhow::Context;
 use anyhow::Result;
-use bevy::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*};
 use bevy_mod_sysfail::*;
 use bevy_xpbd_3d::prelude::Collider;
 #[cfg(feature = "dev")]
 use oxidized_navigation::debug_draw::{DrawNavMesh, DrawPath, OxidizedNavigationDebugDrawPlugin};
 use oxidized_navigation::{
     query::{find_polygon_path, perform_string_pulling_on_path},
-    NavMesh, NavMeshSettings, OxidizedNavigationPlugin,
+    NavMesh, NavMeshAffector, NavMeshSettings, OxidizedNavigationPlugin,
 };
+use rand::Rng;
+use std::f32::consts::TAU;
 
 use crate::dev::dev_editor::DevEditorWindow;
 use serde::{Deserialize, Serialize};
 
 /// Manually tweaked
 const CELL_WIDTH: f32 = 0.4 * player::RADIUS;
+/// How far from a point the navmesh is searched for the closest position on it
+const SNAP_DISTANCE: f32 = 2.;
+/// Attempts at finding a random point before giving up, e.g. when the area is mostly off the navmesh
+const RANDOM_POINT_ATTEMPTS: usize = 10;
 
-/// Handles NPC pathfinding. Currently, all entities with the [`Follower`] component will follow the [`Player`].
+/// Handles NPC pathfinding. Entities with a [`Navigator`] walk along the navmesh towards its destination.
+/// What the destination is, is up to the AI controlling the entity, e.g. the [`companion_plugin`](crate::movement::companion::companion_plugin).
+/// Gameplay code queries the navmesh through the [`Navigation`] system param.
 pub(crate) fn navigation_plugin(app: &mut App) {
     // consts manually tweaked
     app.add_plugins(OxidizedNavigationPlugin::<Collider>::new(NavMeshSettings {
@@ -47,71 +57,154 @@ pub(crate) fn navigation_plugin(app: &mut App) {
     .add_systems(
         Update,
         query_mesh
+            .in_set(NavigationSystemSet)
             .before(GeneralMovementSystemSet)
             .run_if(in_state(GameState::Playing)),
     )
-    .register_type::<Follower>();
+    .add_systems(
+        Update,
+        add_navmesh_affectors
+            .in_set(PostSpawnStage::NavMesh)
+            .run_if(in_state(GameState::Playing)),
+    )
+    .register_type::<Follower>()
+    .register_type::<Navigator>();
     #[cfg(feature = "dev")]
     app.add_plugins(OxidizedNavigationDebugDrawPlugin)
         .add_systems(Update, draw_navmesh);
 }
 
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
+pub(crate) struct NavigationSystemSet;
+
+/// Marks the NPC that is spawned as the player's companion.
 #[derive(Debug, Component, Clone, PartialEq, Default, Reflect, Serialize, Deserialize)]
 #[reflect(Component, Serialize, Deserialize)]
 pub(crate) struct Follower;
 
+#[derive(Debug, Component, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Navigator {
+    /// Where to walk to. Stands still while this is `None`.
+    pub(crate) destination: Option<Vec3>,
+    /// Distance to the destination at which it counts as reached
+    pub(crate) arrival_distance: f32,
+}
+
+impl Default for Navigator {
+    fn default() -> Self {
+        Self {
+            destination: None,
+            arrival_distance: 0.5,
+        }
+    }
+}
+
+/// Lets the colliders built from the level's meshes shape the navmesh. Only done once they are finished,
+/// since every change to an affector bakes its part of the navmesh again.
+fn add_navmesh_affectors(
+    mut commands: Commands,
+    colliders: Query<Entity, (With<ColliderMarker>, Added<Collider>)>,
+) {
+    for entity in colliders.iter() {
+        commands.entity(entity).insert(NavMeshAffector);
+    }
+}
+
+/// Queries the baked navmesh. Use this instead of calling into `oxidized_navigation` directly.
+/// All methods return nothing while the navmesh is still being generated.
+#[derive(SystemParam)]
+pub(crate) struct Navigation<'w> {
+    nav_mesh: Res<'w, NavMesh>,
+    settings: Res<'w, NavMeshSettings>,
+}
+
+impl Navigation<'_> {
+    /// The corners of the shortest path from `from` to `to` along the navmesh, ending at `to`.
+    pub(crate) fn find_path(&self, from: Vec3, to: Vec3) -> Option<Vec<Vec3>> {
+        let nav_mesh = self.nav_mesh.get();
+        let nav_mesh = nav_mesh.read().ok()?;
+        let polygons = find_polygon_path(&nav_mesh, &self.settings, from, to, None, None).ok()?;
+        perform_string_pulling_on_path(&nav_mesh, from, to, &polygons).ok()
+    }
+
+    /// The point on the navmesh closest to `point`, if there is one within a couple of meters.
+    pub(crate) fn closest_point_on_navmesh(&self, point: Vec3) -> Option<Vec3> {
+        let nav_mesh = self.nav_mesh.get();
+        let nav_mesh = nav_mesh.read().ok()?;
+        nav_mesh
+            .find_closest_polygon_in_box(&self.settings, point, SNAP_DISTANCE)
+            .map(|(_tile, _polygon, position)| position)
+    }
+
+    /// A random point on the navmesh at most `radius` away from `center` horizontally.
+    /// Gameplay should draw from a stream of the [`GameRng`](crate::rng::GameRng), so that replays pick the same point.
+    pub(crate) fn random_point_in_radius(
+        &self,
+        center: Vec3,
+        radius: f32,
+        rng: &mut impl Rng,
+    ) -> Option<Vec3> {
+        (0..RANDOM_POINT_ATTEMPTS).find_map(|_| {
+            let angle = rng.gen_range(0.0..TAU);
+            // The square root spreads the points evenly over the area of the circle
+            let distance = radius * rng.gen::<f32>().sqrt();
+            let candidate = center + Vec3::new(angle.cos(), 0., angle.sin()) * distance;
+            self.closest_point_on_navmesh(candidate)
+                .filter(|point| (*point - center).horizontal().length() <= radius)
+        })
+    }
+
+    /// Whether something walking along the navmesh can get from `a` to `b`.
+    pub(crate) fn is_reachable(&self, a: Vec3, b: Vec3) -> bool {
+        self.find_path(a, b).is_some()
+    }
+}
+
 #[sysfail(log(level = "error"))]
 fn query_mesh(
     #[cfg(feature = "dev")] mut commands: Commands,
-    mut with_follower: Query<(&Transform, &mut Walk), (With<Follower>, Without<Player>)>,
-    with_player: Query<&Transform, (With<Player>, Without<Follower>)>,
-    nav_mesh_settings: Res<NavMeshSettings>,
-    nav_mesh: Res<NavMesh>,
+    mut navigators: Query<(&Transform, &Navigator, &mut Walk)>,
+    navigation: Navigation,
     #[cfg(feature = "dev")] editor_state: Res<bevy_editor_pls::editor::Editor>,
 ) -> Result<()> {
     #[cfg(feature = "tracing")]
     let _span = info_span!("query_mesh").entered();
-    if let Ok(nav_mesh) = nav_mesh.get().read() {
-        for (follower_transform, mut walking) in &mut with_follower {
-            for player_transform in &with_player {
-                let from = follower_transform.translation;
-                let to = player_transform.translation;
-                if (to - from).length_squared() < 3.0f32.squared() {
-                    continue;
-                }
+    for (transform, navigator, mut walking) in &mut navigators {
+        let Some(to) = navigator.destination else {
+            continue;
+        };
+        let from = transform.translation;
+        if (to - from).length_squared() < navigator.arrival_distance.squared() {
+            continue;
+        }
 
-                if let Ok(path) =
-                    find_polygon_path(&nav_mesh, &nav_mesh_settings, from, to, None, None)
-                {
-                    let path = perform_string_pulling_on_path(&nav_mesh, from, to, &path)
-                        .map_err(|e| anyhow::Error::msg(format!("{e:?}")))?;
-                    #[cfg(feature = "dev")]
-                    {
-                        let nav_render_enabled = editor_state
-                            .window_state::<DevEditorWindow>()
-                            .context("Failed to read dev window state")?
-                            .navmesh_render_enabled;
-                        if nav_render_enabled {
-                            let shifted_path = path
-                                .iter()
-                                .map(|point| *point + Vec3::new(0., 0.2, 0.))
-                                .collect::<Vec<_>>();
-                            commands.spawn(DrawPath {
-                                timer: Some(Timer::from_seconds(4.0, TimerMode::Once)),
-                                pulled_path: shifted_path,
-                                color: Color::BLUE,
-                            });
-                        }
-                    }
-                    let dir = path
-                        .into_iter()
-                        .map(|next_point| (next_point - from).horizontal())
-                        .filter(|dir| dir.length_squared() > 1e-3f32.squared())
-                        .filter_map(|dir| dir.try_normalize())
-                        .next();
-                    walking.direction = dir;
+        if let Some(path) = navigation.find_path(from, to) {
+            #[cfg(feature = "dev")]
+            {
+                let nav_render_enabled = editor_state
+                    .window_state::<DevEditorWindow>()
+                    .context("Failed to read dev window state")?
+                    .navmesh_render_enabled;
+                if nav_render_enabled {
+                    let shifted_path = path
+                        .iter()
+                        .map(|point| *point + Vec3::new(0., 0.2, 0.))
+                        .collect::<Vec<_>>();
+                    commands.spawn(DrawPath {
+                        timer: Some(Timer::from_seconds(4.0, TimerMode::Once)),
+                        pulled_path: shifted_path,
+                        color: Color::BLUE,
+                    });
                 }
             }
+            let dir = path
+                .into_iter()
+                .map(|next_point| (next_point - from).horizontal())
+                .filter(|dir| dir.length_squared() > 1e-3f32.squared())
+                .filter_map(|dir| dir.try_normalize())
+                .next();
+            walking.direction = dir;
         }
     }
 
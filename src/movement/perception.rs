@@ -0,0 +1,267 @@
+use crate::{
+    movement::{
+        character_controller::{Crouching, Walk},
+        physics::CollisionLayer,
+    },
+    player_control::player_embodiment::Player,
+    util::criteria::is_frozen,
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_xpbd_3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Illuminance in lux at which the player counts as fully lit, e.g. standing in direct sunlight
+const FULL_LIGHT_LUX: f32 = 10_000.;
+/// Even in complete darkness, a player right in front of an NPC can be made out
+const MIN_LIGHT: f32 = 0.1;
+/// How far to look for something between the player and the sun
+const SUN_OCCLUSION_DISTANCE: f32 = 100.;
+/// Lamps are usually inside some geometry of their own, which should not count as blocking their light
+const LIGHT_OCCLUSION_MARGIN: f32 = 0.5;
+/// Exposure is multiplied by this while crouching
+const CROUCH_FACTOR: f32 = 0.5;
+/// Exposure is multiplied by this while standing still, rising to 1 at walking speed
+const STILL_FACTOR: f32 = 0.6;
+
+/// Lets NPCs notice the player depending on how visible they are, so that the game can be played stealthily.
+/// The player's [`Exposure`] combines how brightly they are lit by nearby point and spot lights and the sun,
+/// whether they are crouching and how fast they move. Lights only count if nothing solid is in the way.
+/// NPCs with a [`Perception`] see the player when they are within their sight range and field of view
+/// and nothing blocks the line of sight. Their [`Awareness`] then rises the faster the more exposed and closer
+/// the player is, and sinks again once the player is out of sight.
+/// A [`PlayerSpotted`] is sent when it is full, a [`PlayerLost`] when it is empty again.
+/// NPCs do not perceive anything while the player's actions are frozen, e.g. during dialogs and cutscenes.
+pub(crate) fn perception_plugin(app: &mut App) {
+    app.register_type::<Perception>()
+        .add_event::<PlayerSpotted>()
+        .add_event::<PlayerLost>()
+        .add_systems(
+            Update,
+            (
+                init_perception,
+                update_exposure,
+                perceive_player.run_if(not(is_frozen)),
+            )
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+/// How visible the player is, inserted on the player automatically.
+#[derive(Debug, Clone, Copy, PartialEq, Component, Default)]
+pub(crate) struct Exposure {
+    /// How brightly the player is lit, from 0 to 1
+    pub(crate) light: f32,
+    /// The overall visibility from 0 to 1, combining light, crouching and movement
+    pub(crate) value: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Perception {
+    /// How far the NPC can see
+    pub(crate) sight_range: f32,
+    /// Full opening angle of the NPC's view in degrees
+    pub(crate) field_of_view: f32,
+    /// Seconds it takes to spot a fully exposed player standing right in front of the NPC
+    pub(crate) reaction_time: f32,
+    /// Seconds it takes to forget a player that was spotted once they are out of sight
+    pub(crate) memory: f32,
+}
+
+impl Default for Perception {
+    fn default() -> Self {
+        Self {
+            sight_range: 20.,
+            field_of_view: 120.,
+            reaction_time: 1.,
+            memory: 5.,
+        }
+    }
+}
+
+/// What an NPC with a [`Perception`] knows about the player, inserted automatically.
+#[derive(Debug, Clone, PartialEq, Component, Default)]
+pub(crate) struct Awareness {
+    /// From 0 for unaware to 1 for having spotted the player
+    pub(crate) level: f32,
+    pub(crate) spotted: bool,
+    /// Where the player was when the NPC last saw them
+    pub(crate) last_seen: Option<Vec3>,
+}
+
+/// Sent when an NPC becomes fully aware of the player.
+#[derive(Debug, Clone, Copy, PartialEq, Event)]
+pub(crate) struct PlayerSpotted {
+    pub(crate) npc: Entity,
+    pub(crate) position: Vec3,
+}
+
+/// Sent when an NPC that spotted the player has forgotten about them.
+#[derive(Debug, Clone, Copy, PartialEq, Event)]
+pub(crate) struct PlayerLost {
+    pub(crate) npc: Entity,
+    pub(crate) last_seen: Option<Vec3>,
+}
+
+fn init_perception(
+    mut commands: Commands,
+    players: Query<Entity, (With<Player>, Without<Exposure>)>,
+    npcs: Query<Entity, Added<Perception>>,
+) {
+    for entity in players.iter() {
+        commands.entity(entity).insert(Exposure::default());
+    }
+    for entity in npcs.iter() {
+        commands.entity(entity).insert(Awareness::default());
+    }
+}
+
+fn update_exposure(
+    mut players: Query<
+        (
+            &GlobalTransform,
+            &mut Exposure,
+            Option<&LinearVelocity>,
+            Option<&Walk>,
+            Option<&Crouching>,
+        ),
+        With<Player>,
+    >,
+    point_lights: Query<(&PointLight, &GlobalTransform)>,
+    spot_lights: Query<(&SpotLight, &GlobalTransform)>,
+    directional_lights: Query<(&DirectionalLight, &GlobalTransform)>,
+    spatial_query: SpatialQuery,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("update_exposure").entered();
+    let filter = CollisionLayer::sight_filter();
+    for (transform, mut exposure, velocity, walk, crouching) in players.iter_mut() {
+        let position = transform.translation();
+        let lit_from = |light_position: Vec3, max_distance: f32| {
+            let Some(direction) = (light_position - position).try_normalize() else {
+                return true;
+            };
+            spatial_query
+                .cast_ray(position, direction, max_distance, true, filter.clone())
+                .is_none()
+        };
+
+        let mut lux = 0.;
+        for (light, light_transform) in point_lights.iter() {
+            let distance = light_transform.translation().distance(position);
+            if distance <= light.range
+                && lit_from(
+                    light_transform.translation(),
+                    distance - LIGHT_OCCLUSION_MARGIN,
+                )
+            {
+                lux += point_illuminance(light.intensity, distance);
+            }
+        }
+        for (light, light_transform) in spot_lights.iter() {
+            let to_player = position - light_transform.translation();
+            let distance = to_player.length();
+            let in_cone = distance > 0.
+                && light_transform.forward().angle_between(to_player) <= light.outer_angle;
+            if distance <= light.range
+                && in_cone
+                && lit_from(
+                    light_transform.translation(),
+                    distance - LIGHT_OCCLUSION_MARGIN,
+                )
+            {
+                lux += point_illuminance(light.intensity, distance);
+            }
+        }
+        for (light, light_transform) in directional_lights.iter() {
+            // Directional lights shine along their forward direction, so the sun is behind them
+            let towards_sun = position + light_transform.back() * SUN_OCCLUSION_DISTANCE;
+            if lit_from(towards_sun, SUN_OCCLUSION_DISTANCE) {
+                lux += light.illuminance;
+            }
+        }
+        // Perceived brightness is roughly logarithmic
+        let light = ((1. + lux).log10() / (1. + FULL_LIGHT_LUX).log10()).clamp(MIN_LIGHT, 1.);
+
+        let crouch = if crouching.is_some_and(|crouching| crouching.requested) {
+            CROUCH_FACTOR
+        } else {
+            1.
+        };
+        let speed = velocity.map_or(0., |velocity| {
+            Vec3::new(velocity.x, 0., velocity.z).length()
+        });
+        let walking_speed = walk.map_or(1., |walk| walk.speed.max(f32::EPSILON));
+        let movement = STILL_FACTOR + (1. - STILL_FACTOR) * (speed / walking_speed).min(1.);
+
+        let new_exposure = Exposure {
+            light,
+            value: (light * crouch * movement).clamp(0., 1.),
+        };
+        if *exposure != new_exposure {
+            *exposure = new_exposure;
+        }
+    }
+}
+
+/// Illuminance in lux at `distance` meters from a light shining `intensity` lumens in all directions
+fn point_illuminance(intensity: f32, distance: f32) -> f32 {
+    intensity / (4. * std::f32::consts::PI * distance.max(1.).powi(2))
+}
+
+fn perceive_player(
+    time: Res<Time<Virtual>>,
+    players: Query<(&GlobalTransform, &Exposure), With<Player>>,
+    mut npcs: Query<(Entity, &Perception, &GlobalTransform, &mut Awareness)>,
+    spatial_query: SpatialQuery,
+    mut spotted_events: EventWriter<PlayerSpotted>,
+    mut lost_events: EventWriter<PlayerLost>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("perceive_player").entered();
+    let Some((player_transform, exposure)) = players.iter().next() else {
+        return;
+    };
+    let player_position = player_transform.translation();
+    let dt = time.delta_seconds();
+    let filter = CollisionLayer::sight_filter();
+    for (npc, perception, transform, mut awareness) in npcs.iter_mut() {
+        let eye = transform.translation();
+        let to_player = player_position - eye;
+        let distance = to_player.length();
+        let sees_player = distance <= perception.sight_range
+            && to_player.try_normalize().is_some_and(|direction| {
+                transform.forward().angle_between(direction)
+                    <= perception.field_of_view.to_radians() / 2.
+                    && spatial_query
+                        .cast_ray(eye, direction, distance, true, filter.clone())
+                        .is_none()
+            });
+
+        let level = if sees_player {
+            awareness.last_seen = Some(player_position);
+            let closeness = 1. - distance / perception.sight_range;
+            awareness.level
+                + exposure.value * closeness * dt / perception.reaction_time.max(f32::EPSILON)
+        } else {
+            awareness.level - dt / perception.memory.max(f32::EPSILON)
+        };
+        awareness.level = level.clamp(0., 1.);
+
+        if !awareness.spotted && awareness.level >= 1. {
+            awareness.spotted = true;
+            spotted_events.send(PlayerSpotted {
+                npc,
+                position: player_position,
+            });
+        } else if awareness.spotted && awareness.level <= 0. {
+            awareness.spotted = false;
+            lost_events.send(PlayerLost {
+                npc,
+                last_seen: awareness.last_seen,
+            });
+        }
+    }
+}
@@ -1,67 +1,266 @@
-use crate::{level_instantiation::spawning::objects::CollisionLayer, GameState};
-use anyhow::{Context, Result};
-use bevy::prelude::*;
-use bevy_mod_sysfail::*;
+use crate::{
+    level_instantiation::{
+        markers::{Marker, Markers, MarkersAppExt},
+        post_spawn::PostSpawnStage,
+        validation::{SpawnProblemKind, SpawnReport},
+    },
+    GameState,
+};
+use anyhow::Result;
+use bevy::{
+    prelude::*,
+    render::{mesh::VertexAttributeValues, render_resource::PrimitiveTopology},
+    utils::HashMap,
+};
 use bevy_xpbd_3d::prelude::*;
-use oxidized_navigation::NavMeshAffector;
 use serde::{Deserialize, Serialize};
 
-/// Sets up the [`RapierPhysicsPlugin`] and [`RapierConfiguration`].
+use seldom_fn_plugin::FnPluginExt;
+
+pub(crate) use interpolation::TransformInterpolationSet;
+pub(crate) use layers::*;
+
+mod collider_cache;
+pub(crate) mod interpolation;
+mod layers;
+mod mesh_cleanup;
+
+/// Simulation steps per second
+const PHYSICS_HZ: f64 = 60.;
+
+/// Sets up XPBD to step exactly once per [`FixedUpdate`], so that the simulation behaves the same at any framerate.
+/// Bodies are rendered smoothly between steps by the [`interpolation::interpolation_plugin`].
+/// Forces and velocities that act on bodies, like wind, buoyancy and wheels, are applied in [`FixedUpdate`]
+/// before [`PhysicsSet::Prepare`], and reactions to the results of a step, like breaking joints, run after [`PhysicsSet::Sync`].
+/// [`PhysicsSet`] only exists in [`FixedUpdate`], so ordering [`Update`] systems against it has no effect.
+///
+/// Character controllers are the exception: the pinned Tnua version only adds its systems to [`Update`] and expects
+/// its basis to be fed every frame, so the controllers and the systems feeding them stay there. They only set
+/// the desired motion of their bodies, which the following fixed steps then simulate.
 pub(crate) fn physics_plugin(app: &mut App) {
     app.register_type::<ColliderMarker>()
-        .add_plugins(PhysicsPlugins::default())
-        // Using the default fixed timestep causes issues on faster (165 Hz) machines.
-        .insert_resource(Time::new_with(Physics::variable(1.0 / 60.)))
-        .add_systems(Update, read_colliders.run_if(in_state(GameState::Playing)));
+        .register_marker("collider", |entity, _marker| {
+            entity.insert(ColliderMarker);
+            Ok(())
+        })
+        .add_plugins(PhysicsPlugins::new(FixedUpdate))
+        .insert_resource(Time::<Fixed>::from_hz(PHYSICS_HZ))
+        .insert_resource(Time::new_with(Physics::fixed_once_hz(PHYSICS_HZ)))
+        .fn_plugin(interpolation::interpolation_plugin)
+        .add_systems(
+            Update,
+            read_colliders
+                .in_set(PostSpawnStage::Colliders)
+                .run_if(in_state(GameState::Playing)),
+        );
+    #[cfg(not(target_arch = "wasm32"))]
+    app.add_systems(Startup, collider_cache::prune_collider_cache);
 }
 
+/// Builds a collider from the entity's mesh. Added through the GLTF extras or a `[collider]` marker.
 #[derive(Debug, Clone, Eq, PartialEq, Component, Reflect, Serialize, Deserialize, Default)]
 #[reflect(Component, Serialize, Deserialize)]
 pub(crate) struct ColliderMarker;
 
-#[sysfail(log(level = "error"))]
+/// How a collider is built from the mesh of an entity with a [`ColliderMarker`].
+/// Picked by the argument of the `[collider]` marker in the entity's name, e.g. "Crate [collider:box]".
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+enum ColliderShape {
+    /// Exactly matches the mesh. Expensive and only usable for static bodies.
+    #[default]
+    TriMesh,
+    /// `[collider:convex]`: Splits the mesh into several convex parts. Works for concave dynamic bodies.
+    ConvexDecomposition,
+    /// `[collider:hull]`: The smallest convex shape containing the whole mesh
+    ConvexHull,
+    /// `[collider:box]`: The axis-aligned bounding box of the mesh
+    Box,
+}
+
+impl ColliderShape {
+    fn from_marker(marker: &Marker) -> Result<Self> {
+        match marker.argument(0) {
+            None | Some("trimesh") => Ok(Self::TriMesh),
+            Some("convex") => Ok(Self::ConvexDecomposition),
+            Some("hull") => Ok(Self::ConvexHull),
+            Some("box") => Ok(Self::Box),
+            Some(other) => Err(anyhow::anyhow!("Unknown collider shape \"{other}\"")),
+        }
+    }
+
+    /// Builds one collider for all of the meshes, e.g. the primitives of a GLTF mesh with several materials.
+    /// All shapes but boxes are built from the [`mesh_cleanup::clean`]ed meshes, since the navmesh is generated
+    /// from colliders and breaks on the duplicated vertices and zero-area triangles many exporters produce.
+    fn build(self, meshes: &[&Mesh]) -> Option<Collider> {
+        match self {
+            Self::TriMesh => {
+                let mesh = mesh_cleanup::clean(meshes)?;
+                Some(Collider::trimesh(mesh.vertices, mesh.triangles))
+            }
+            Self::ConvexDecomposition => {
+                let mesh = mesh_cleanup::clean(meshes)?;
+                Some(Collider::convex_decomposition(
+                    mesh.vertices,
+                    mesh.triangles,
+                ))
+            }
+            Self::ConvexHull => Collider::convex_hull(mesh_cleanup::clean(meshes)?.vertices),
+            Self::Box => {
+                let (min, max) = meshes
+                    .iter()
+                    .filter_map(|mesh| mesh.compute_aabb())
+                    .map(|aabb| (Vec3::from(aabb.min()), Vec3::from(aabb.max())))
+                    .reduce(|(min_a, max_a), (min_b, max_b)| {
+                        (min_a.min(min_b), max_a.max(max_b))
+                    })?;
+                let size = max - min;
+                let cuboid = Collider::cuboid(size.x, size.y, size.z);
+                Some(Collider::compound(vec![(
+                    (min + max) / 2.,
+                    Quat::IDENTITY,
+                    cuboid,
+                )]))
+            }
+        }
+    }
+}
+
+/// Builds colliders for entities marked with [`ColliderMarker`] from the meshes of all of their direct children.
+/// Problems with the meshes are added to the [`SpawnReport`] and the entity is left without a collider
+/// instead of stopping the game.
 pub(crate) fn read_colliders(
-    collider_marker: Query<Entity, Added<ColliderMarker>>,
+    collider_marker: Query<
+        (Entity, Option<&Name>, Option<&Markers>, Option<&RigidBody>),
+        Added<ColliderMarker>,
+    >,
     mut commands: Commands,
     children: Query<&Children>,
     meshes: Res<Assets<Mesh>>,
     mesh_handles: Query<&Handle<Mesh>>,
-) -> Result<()> {
+    mut report: ResMut<SpawnReport>,
+) {
     #[cfg(feature = "tracing")]
     let _span = info_span!("read_colliders").entered();
-    for entity in collider_marker.iter() {
-        let mesh = find_mesh(entity, &children, &meshes, &mesh_handles)
-            .context("Failed to find mesh for collider")?;
-        let collider =
-            Collider::trimesh_from_mesh(mesh).context("Failed to create collider from mesh")?;
+    for (entity, name, markers, rigid_body) in collider_marker.iter() {
+        let found_meshes = find_meshes(entity, &children, &meshes, &mesh_handles);
+        if found_meshes.is_empty() {
+            report.add(entity, name, SpawnProblemKind::MissingMesh);
+            continue;
+        }
+        let shape = match markers.and_then(|markers| markers.get("collider")) {
+            Some(marker) => match ColliderShape::from_marker(marker) {
+                Ok(shape) => shape,
+                Err(error) => {
+                    report.add(
+                        entity,
+                        name,
+                        SpawnProblemKind::ColliderFailed(format!("{error:#}")),
+                    );
+                    continue;
+                }
+            },
+            None => default(),
+        };
+        if let Err(problem) = found_meshes.iter().try_for_each(|mesh| validate_mesh(mesh)) {
+            report.add(entity, name, problem);
+            continue;
+        }
+        if shape == ColliderShape::TriMesh {
+            let edges: usize = found_meshes
+                .iter()
+                .map(|mesh| count_non_manifold_edges(mesh))
+                .sum();
+            if edges > 0 {
+                report.add(entity, name, SpawnProblemKind::NonManifold { edges });
+            }
+        }
+        let collider = match collider_cache::load_or_build(shape, &found_meshes) {
+            Ok(collider) => collider,
+            Err(error) => {
+                report.add(
+                    entity,
+                    name,
+                    SpawnProblemKind::ColliderFailed(format!("{error:#}")),
+                );
+                continue;
+            }
+        };
 
-        commands.entity(entity).insert((
-            collider,
-            RigidBody::Static,
-            CollisionLayers::new(
-                [CollisionLayer::Terrain, CollisionLayer::CameraObstacle],
-                [CollisionLayer::Character],
-            ),
-            NavMeshAffector,
-        ));
+        // Bodies that were made dynamic through the GLTF extras keep their rigid body and are treated as props
+        let (rigid_body, collision_layers) = match rigid_body {
+            Some(rigid_body) if rigid_body.is_dynamic() => {
+                if shape == ColliderShape::TriMesh {
+                    warn!("Dynamic body {name:?} uses a trimesh collider, which will not collide properly. Add a [collider:convex], [collider:hull] or [collider:box] suffix to its name.");
+                }
+                (*rigid_body, CollisionLayer::prop())
+            }
+            Some(rigid_body) => (*rigid_body, CollisionLayer::terrain()),
+            None => (RigidBody::Static, CollisionLayer::terrain()),
+        };
+        commands
+            .entity(entity)
+            .insert((collider, rigid_body, collision_layers));
     }
-    Ok(())
 }
 
-fn find_mesh<'a>(
+/// All loaded meshes among the direct children of `parent`
+fn find_meshes<'a>(
     parent: Entity,
     children_query: &'a Query<&Children>,
     meshes: &'a Assets<Mesh>,
     mesh_handles: &'a Query<&Handle<Mesh>>,
-) -> Option<&'a Mesh> {
-    if let Ok(children) = children_query.get(parent) {
-        for child in children.iter() {
-            if let Ok(mesh_handle) = mesh_handles.get(*child) {
-                if let Some(mesh) = meshes.get(mesh_handle) {
-                    return Some(mesh);
-                }
-            }
+) -> Vec<&'a Mesh> {
+    let Ok(children) = children_query.get(parent) else {
+        return Vec::new();
+    };
+    children
+        .iter()
+        .filter_map(|child| mesh_handles.get(*child).ok())
+        .filter_map(|mesh_handle| meshes.get(mesh_handle))
+        .collect()
+}
+
+/// Checks that a collider can be built from the mesh without panicking.
+fn validate_mesh(mesh: &Mesh) -> Result<(), SpawnProblemKind> {
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+        return Err(SpawnProblemKind::WrongTopology(mesh.primitive_topology()));
+    }
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return Err(SpawnProblemKind::MissingPositions);
+    };
+    if let Some(indices) = mesh.indices() {
+        if indices.iter().any(|index| index >= positions.len()) {
+            return Err(SpawnProblemKind::InvalidIndices);
+        }
+    }
+    Ok(())
+}
+
+/// Counts edges that belong to more than two triangles. Vertices are compared by position,
+/// since exporters split vertices at seams of normals and UVs.
+fn count_non_manifold_edges(mesh: &Mesh) -> usize {
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return 0;
+    };
+    let indices: Vec<usize> = match mesh.indices() {
+        Some(indices) => indices.iter().collect(),
+        None => (0..positions.len()).collect(),
+    };
+    let vertex = |index: usize| positions[index].map(f32::to_bits);
+    let mut triangles_per_edge = HashMap::<_, usize>::new();
+    for triangle in indices.chunks_exact(3) {
+        for (a, b) in [(0, 1), (1, 2), (2, 0)] {
+            let (a, b) = (vertex(triangle[a]), vertex(triangle[b]));
+            let edge = if a < b { (a, b) } else { (b, a) };
+            *triangles_per_edge.entry(edge).or_default() += 1;
         }
     }
-    None
+    triangles_per_edge
+        .values()
+        .filter(|&&count| count > 2)
+        .count()
 }
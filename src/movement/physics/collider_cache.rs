@@ -0,0 +1,151 @@
+use crate::movement::physics::ColliderShape;
+use anyhow::{Context, Result};
+use bevy::{
+    prelude::*,
+    render::mesh::{Indices, VertexAttributeValues},
+};
+#[cfg(not(target_arch = "wasm32"))]
+use bevy_mod_sysfail::*;
+use bevy_xpbd_3d::prelude::*;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+const CACHE_DIRECTORY: &str = "cache/colliders";
+/// Bump this whenever the way colliders are built changes to invalidate all cached colliders.
+#[cfg(not(target_arch = "wasm32"))]
+const CACHE_VERSION: u32 = 2;
+/// Entries that were not used for this long are deleted on startup, e.g. those of meshes that were edited since
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_UNUSED_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Builds the collider of the given shape for `meshes`, or loads it from disk if it was built before.
+/// The cache is keyed by a BLAKE3 hash of the meshes' vertices and indices, which stays the same across
+/// Rust versions and platforms, so editing a mesh in the level automatically invalidates its collider.
+/// Failing to read or write the cache is not fatal.
+///
+/// The navmesh is not cached: oxidized_navigation bakes its tiles in background tasks whenever a
+/// [`NavMeshAffector`](oxidized_navigation::NavMeshAffector) is added, which happens on every level load,
+/// and offers no way to serialize them, so a cached navmesh would be baked again anyway.
+#[cfg(not(target_arch = "wasm32"))]
+pub(super) fn load_or_build(shape: ColliderShape, meshes: &[&Mesh]) -> Result<Collider> {
+    let path = cache_path(shape, meshes);
+    match read_cached(&path) {
+        Ok(Some(collider)) => return Ok(collider),
+        Ok(None) => {}
+        Err(error) => warn!("Ignoring broken collider cache entry: {error:?}"),
+    }
+    let collider = build(shape, meshes)?;
+    if let Err(error) = write_cached(&path, &collider) {
+        warn!("Failed to cache collider: {error:?}");
+    }
+    Ok(collider)
+}
+
+/// There is no file system to cache colliders in on the web, so they are always built.
+#[cfg(target_arch = "wasm32")]
+pub(super) fn load_or_build(shape: ColliderShape, meshes: &[&Mesh]) -> Result<Collider> {
+    build(shape, meshes)
+}
+
+fn build(shape: ColliderShape, meshes: &[&Mesh]) -> Result<Collider> {
+    shape
+        .build(meshes)
+        .with_context(|| format!("Failed to create {shape:?} collider from mesh"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn cache_path(shape: ColliderShape, meshes: &[&Mesh]) -> PathBuf {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&CACHE_VERSION.to_le_bytes());
+    hasher.update(format!("{shape:?}").as_bytes());
+    for mesh in meshes {
+        if let Some(VertexAttributeValues::Float32x3(positions)) =
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        {
+            hasher.update(&(positions.len() as u64).to_le_bytes());
+            for coordinate in positions.iter().flatten() {
+                hasher.update(&coordinate.to_le_bytes());
+            }
+        }
+        // The index format is hashed as well, so that the same numbers in either format do not collide
+        match mesh.indices() {
+            Some(Indices::U16(indices)) => {
+                hasher.update(&[16]);
+                for index in indices {
+                    hasher.update(&index.to_le_bytes());
+                }
+            }
+            Some(Indices::U32(indices)) => {
+                hasher.update(&[32]);
+                for index in indices {
+                    hasher.update(&index.to_le_bytes());
+                }
+            }
+            None => {
+                hasher.update(&[0]);
+            }
+        }
+    }
+    Path::new(CACHE_DIRECTORY).join(format!("{}.bin", hasher.finalize().to_hex()))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_cached(path: &Path) -> Result<Option<Collider>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let collider = bincode::deserialize(&bytes)
+        .with_context(|| format!("Failed to deserialize {}", path.display()))?;
+    // Marks the entry as used, so that it is not pruned
+    if let Err(error) = fs::File::options()
+        .write(true)
+        .open(path)
+        .and_then(|file| file.set_modified(SystemTime::now()))
+    {
+        warn!("Failed to mark {} as used: {error}", path.display());
+    }
+    Ok(Some(collider))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_cached(path: &Path, collider: &Collider) -> Result<()> {
+    fs::create_dir_all(CACHE_DIRECTORY).context("Failed to create collider cache directory")?;
+    let bytes = bincode::serialize(collider)?;
+    fs::write(path, bytes).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+/// Deletes the cached colliders that were not used for [`MAX_UNUSED_AGE`],
+/// so that entries of edited meshes and older cache versions do not pile up.
+#[cfg(not(target_arch = "wasm32"))]
+#[sysfail(log(level = "error"))]
+pub(super) fn prune_collider_cache() -> Result<()> {
+    let directory = Path::new(CACHE_DIRECTORY);
+    if !directory.exists() {
+        return Ok(());
+    }
+    let now = SystemTime::now();
+    let entries = fs::read_dir(directory)
+        .with_context(|| format!("Failed to read {}", directory.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().and_then(|extension| extension.to_str()) != Some("bin") {
+            continue;
+        }
+        let modified = fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .with_context(|| format!("Failed to read the age of {}", path.display()))?;
+        let unused_for = now.duration_since(modified).unwrap_or_default();
+        if unused_for > MAX_UNUSED_AGE {
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to delete {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,149 @@
+use bevy::{prelude::*, transform::TransformSystem};
+use bevy_xpbd_3d::{prelude::*, PhysicsSet};
+
+/// Smooths the rendering of physics bodies. The simulation runs in [`FixedUpdate`], which can step zero
+/// or several times per frame, so bodies would visibly stutter if they were rendered at their last simulated pose.
+/// Instead, every frame renders them between their last two simulated poses, see [`InterpolationAlpha`].
+///
+/// Systems that overwrite the [`Transform`] of a body for rendering, e.g. to attach it to a bone,
+/// must run after the [`TransformInterpolationSet`]. Changing a [`Transform`] during [`Update`] teleports the body,
+/// whether or not a physics step runs in the same frame.
+pub(super) fn interpolation_plugin(app: &mut App) {
+    app.init_resource::<InterpolationAlpha>()
+        .configure_sets(
+            PostUpdate,
+            TransformInterpolationSet.before(TransformSystem::TransformPropagate),
+        )
+        .add_systems(
+            FixedUpdate,
+            (
+                restore_simulated_transforms.before(PhysicsSet::Prepare),
+                record_simulated_transforms.after(PhysicsSet::Sync),
+            ),
+        )
+        .add_systems(Update, add_interpolation)
+        .add_systems(
+            PostUpdate,
+            (update_interpolation_alpha, interpolate_transforms)
+                .chain()
+                .in_set(TransformInterpolationSet),
+        );
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
+pub(crate) struct TransformInterpolationSet;
+
+/// How far the current frame is between the last and the next physics step, between 0 and 1.
+/// Useful for anything else that wants to be rendered in sync with interpolated bodies.
+#[derive(Debug, Clone, Copy, PartialEq, Resource, Default)]
+pub(crate) struct InterpolationAlpha(pub(crate) f32);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Pose {
+    translation: Vec3,
+    rotation: Quat,
+}
+
+impl From<&Transform> for Pose {
+    fn from(transform: &Transform) -> Self {
+        Self {
+            translation: transform.translation,
+            rotation: transform.rotation,
+        }
+    }
+}
+
+impl Pose {
+    fn lerp(self, other: Self, alpha: f32) -> Self {
+        Self {
+            translation: self.translation.lerp(other.translation, alpha),
+            rotation: self.rotation.slerp(other.rotation, alpha),
+        }
+    }
+
+    fn apply_to(self, transform: &mut Transform) {
+        transform.translation = self.translation;
+        transform.rotation = self.rotation;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Component)]
+struct TransformInterpolation {
+    previous: Pose,
+    current: Pose,
+    /// What was last written to the transform for rendering, used to detect teleports
+    rendered: Option<Pose>,
+}
+
+impl TransformInterpolation {
+    fn at(pose: Pose) -> Self {
+        Self {
+            previous: pose,
+            current: pose,
+            rendered: None,
+        }
+    }
+}
+
+/// Bodies in a hierarchy have their transforms synced relative to their parent, so only root bodies are interpolated.
+fn add_interpolation(
+    mut commands: Commands,
+    bodies: Query<(Entity, &RigidBody, &Transform), (Added<RigidBody>, Without<Parent>)>,
+) {
+    for (entity, rigid_body, transform) in bodies.iter() {
+        if rigid_body.is_static() {
+            continue;
+        }
+        commands
+            .entity(entity)
+            .insert(TransformInterpolation::at(transform.into()));
+    }
+}
+
+/// Puts bodies back at their simulated pose so that the physics does not pick up the interpolated one.
+fn restore_simulated_transforms(mut bodies: Query<(&mut Transform, &mut TransformInterpolation)>) {
+    for (mut transform, mut interpolation) in bodies.iter_mut() {
+        let Some(rendered) = interpolation.rendered.take() else {
+            continue;
+        };
+        let pose = Pose::from(&*transform);
+        if pose == rendered {
+            interpolation.current.apply_to(&mut transform);
+        } else {
+            // Something moved the body on purpose since the last frame
+            *interpolation = TransformInterpolation::at(pose);
+        }
+    }
+}
+
+fn record_simulated_transforms(mut bodies: Query<(&Transform, &mut TransformInterpolation)>) {
+    for (transform, mut interpolation) in bodies.iter_mut() {
+        interpolation.previous = interpolation.current;
+        interpolation.current = transform.into();
+    }
+}
+
+fn update_interpolation_alpha(time: Res<Time<Fixed>>, mut alpha: ResMut<InterpolationAlpha>) {
+    alpha.0 = time.overstep_percentage().clamp(0., 1.);
+}
+
+fn interpolate_transforms(
+    alpha: Res<InterpolationAlpha>,
+    mut bodies: Query<(&mut Transform, &mut TransformInterpolation)>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("interpolate_transforms").entered();
+    for (mut transform, mut interpolation) in bodies.iter_mut() {
+        let current = Pose::from(&*transform);
+        if interpolation
+            .rendered
+            .is_some_and(|rendered| rendered != current)
+        {
+            // Moved on purpose during a frame without a physics step
+            *interpolation = TransformInterpolation::at(current);
+        }
+        let pose = interpolation.previous.lerp(interpolation.current, alpha.0);
+        pose.apply_to(&mut transform);
+        interpolation.rendered = Some(pose);
+    }
+}
@@ -0,0 +1,165 @@
+use bevy_xpbd_3d::prelude::*;
+
+/// Every physics layer in the game. Colliders should not combine these by hand,
+/// but get their [`CollisionLayers`] from the constructor for their role below,
+/// and spatial queries should use one of the predefined filters.
+/// This way, e.g. adding a new kind of collider only requires touching this file.
+#[derive(PhysicsLayer)]
+pub(crate) enum CollisionLayer {
+    Player,
+    Character,
+    Terrain,
+    CameraObstacle,
+    Sensor,
+    Ragdoll,
+    Vehicle,
+    Rope,
+    Prop,
+}
+
+impl CollisionLayer {
+    /// Static level geometry
+    pub(crate) fn terrain() -> CollisionLayers {
+        CollisionLayers::new(
+            [CollisionLayer::Terrain, CollisionLayer::CameraObstacle],
+            [
+                CollisionLayer::Character,
+                CollisionLayer::Ragdoll,
+                CollisionLayer::Vehicle,
+                CollisionLayer::Rope,
+                CollisionLayer::Prop,
+            ],
+        )
+    }
+
+    /// NPCs and anything else driven by a character controller
+    pub(crate) fn character() -> CollisionLayers {
+        CollisionLayers::new(
+            [CollisionLayer::Character],
+            [
+                CollisionLayer::Player,
+                CollisionLayer::Character,
+                CollisionLayer::Terrain,
+                CollisionLayer::Sensor,
+                CollisionLayer::Vehicle,
+                CollisionLayer::Prop,
+            ],
+        )
+    }
+
+    /// A character that triggers sensors meant for the player only
+    pub(crate) fn player() -> CollisionLayers {
+        Self::character().add_group(CollisionLayer::Player)
+    }
+
+    /// Dynamic objects that can be pushed around, like crates
+    pub(crate) fn prop() -> CollisionLayers {
+        CollisionLayers::new(
+            [CollisionLayer::Prop],
+            [
+                CollisionLayer::Terrain,
+                CollisionLayer::Character,
+                CollisionLayer::Player,
+                CollisionLayer::Vehicle,
+                CollisionLayer::Prop,
+                CollisionLayer::Sensor,
+            ],
+        )
+    }
+
+    /// Sensors that detect when the player is close, e.g. to offer an interaction
+    pub(crate) fn trigger() -> CollisionLayers {
+        CollisionLayers::new([CollisionLayer::Sensor], [CollisionLayer::Player])
+    }
+
+    /// Sensors that detect all characters, e.g. the hitboxes of attacks
+    pub(crate) fn character_trigger() -> CollisionLayers {
+        CollisionLayers::new(
+            [CollisionLayer::Sensor],
+            [CollisionLayer::Player, CollisionLayer::Character],
+        )
+    }
+
+    /// Sensors that detect everything with weight, e.g. pressure plates
+    pub(crate) fn weight_trigger() -> CollisionLayers {
+        CollisionLayers::new(
+            [CollisionLayer::Sensor],
+            [
+                CollisionLayer::Player,
+                CollisionLayer::Character,
+                CollisionLayer::Prop,
+            ],
+        )
+    }
+
+    pub(crate) fn ragdoll() -> CollisionLayers {
+        CollisionLayers::new([CollisionLayer::Ragdoll], [CollisionLayer::Terrain])
+    }
+
+    pub(crate) fn vehicle() -> CollisionLayers {
+        CollisionLayers::new(
+            [CollisionLayer::Vehicle],
+            [
+                CollisionLayer::Terrain,
+                CollisionLayer::Character,
+                CollisionLayer::Player,
+                CollisionLayer::Vehicle,
+                CollisionLayer::Ragdoll,
+                CollisionLayer::Rope,
+                CollisionLayer::Prop,
+            ],
+        )
+    }
+
+    pub(crate) fn rope() -> CollisionLayers {
+        CollisionLayers::new(
+            [CollisionLayer::Rope],
+            [CollisionLayer::Terrain, CollisionLayer::Vehicle],
+        )
+    }
+
+    /// Static ground, for placing feet, wheels and the like
+    pub(crate) fn ground_filter() -> SpatialQueryFilter {
+        Self::filter([CollisionLayer::Terrain])
+    }
+
+    /// Everything that can be stood on, including vehicles
+    pub(crate) fn standable_filter() -> SpatialQueryFilter {
+        Self::filter([CollisionLayer::Terrain, CollisionLayer::Vehicle])
+    }
+
+    /// What the camera must not clip through. Excludes characters so that they never push the camera around.
+    pub(crate) fn camera_probe_filter() -> SpatialQueryFilter {
+        Self::filter([CollisionLayer::CameraObstacle])
+    }
+
+    /// Everything solid, for projectiles and for checking whether there is room to place a character
+    pub(crate) fn solid_filter() -> SpatialQueryFilter {
+        Self::filter([
+            CollisionLayer::Terrain,
+            CollisionLayer::Character,
+            CollisionLayer::Vehicle,
+            CollisionLayer::Prop,
+        ])
+    }
+
+    /// What blocks the line of sight of NPCs and the light of lamps. Excludes characters so that they never hide each other.
+    pub(crate) fn sight_filter() -> SpatialQueryFilter {
+        Self::filter([
+            CollisionLayer::Terrain,
+            CollisionLayer::Vehicle,
+            CollisionLayer::Prop,
+        ])
+    }
+
+    pub(crate) fn rope_filter() -> SpatialQueryFilter {
+        Self::filter([CollisionLayer::Rope])
+    }
+
+    fn filter(layers: impl IntoIterator<Item = CollisionLayer>) -> SpatialQueryFilter {
+        let bits = layers
+            .into_iter()
+            .fold(0, |bits, layer| bits | layer.to_bits());
+        SpatialQueryFilter::new().with_masks_from_bits(bits)
+    }
+}
@@ -0,0 +1,401 @@
+use bevy::{
+    prelude::*,
+    render::mesh::VertexAttributeValues,
+    utils::{HashMap, HashSet},
+};
+use std::collections::VecDeque;
+
+/// Vertices closer than this are merged into one
+const WELD_DISTANCE: f32 = 1e-4;
+/// Triangles with a smaller area are dropped
+const MIN_TRIANGLE_AREA: f32 = 1e-8;
+
+/// The triangles of a mesh, cleaned up so that colliders and the navmesh built on them get proper adjacency.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(super) struct CleanMesh {
+    pub(super) vertices: Vec<Vec3>,
+    pub(super) triangles: Vec<[u32; 3]>,
+}
+
+/// Merges the meshes, welds vertices that exporters duplicated, e.g. at UV seams, drops zero-area triangles
+/// and turns the triangles of every connected part the same way.
+/// Meshes may use 16 or 32 bit indices or be non-indexed triangle lists.
+/// Returns `None` if none of the meshes has positions or no triangles are left.
+pub(super) fn clean(meshes: &[&Mesh]) -> Option<CleanMesh> {
+    let mut positions = Vec::new();
+    let mut indices = Vec::new();
+    for mesh in meshes {
+        let Some(VertexAttributeValues::Float32x3(mesh_positions)) =
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            continue;
+        };
+        let offset = positions.len();
+        match mesh.indices() {
+            Some(mesh_indices) => {
+                // Whole triangles are dropped, so that the ones after an invalid index keep their corners
+                let mesh_indices: Vec<usize> = mesh_indices.iter().collect();
+                indices.extend(
+                    mesh_indices
+                        .chunks_exact(3)
+                        .filter(|triangle| {
+                            triangle.iter().all(|&index| index < mesh_positions.len())
+                        })
+                        .flatten()
+                        .map(|index| index + offset),
+                );
+            }
+            None => indices.extend(offset..offset + mesh_positions.len()),
+        }
+        positions.extend_from_slice(mesh_positions);
+    }
+    let (vertices, remap) = weld(&positions);
+    let mut triangles: Vec<[u32; 3]> = indices
+        .chunks_exact(3)
+        .filter(|triangle| triangle.iter().all(|&index| index < remap.len()))
+        .map(|triangle| [remap[triangle[0]], remap[triangle[1]], remap[triangle[2]]])
+        .filter(|triangle| !is_degenerate(&vertices, *triangle))
+        .collect();
+    let dropped = indices.len() / 3 - triangles.len();
+    if dropped > 0 {
+        debug!("Dropped {dropped} degenerate triangles");
+    }
+    fix_winding(&vertices, &mut triangles);
+    (!triangles.is_empty()).then_some(CleanMesh {
+        vertices,
+        triangles,
+    })
+}
+
+/// Merges positions within [`WELD_DISTANCE`] of each other.
+/// Returns the merged positions and, for every original position, the index of its merged one.
+fn weld(positions: &[[f32; 3]]) -> (Vec<Vec3>, Vec<u32>) {
+    let cell = |position: Vec3| (position / WELD_DISTANCE).floor().as_ivec3();
+    let mut grid = HashMap::<IVec3, Vec<u32>>::new();
+    let mut vertices = Vec::new();
+    let mut remap = Vec::with_capacity(positions.len());
+    for position in positions {
+        let position = Vec3::from(*position);
+        let center = cell(position);
+        // Close vertices can end up in neighboring cells
+        let existing = (-1..=1)
+            .flat_map(|x| (-1..=1).flat_map(move |y| (-1..=1).map(move |z| IVec3::new(x, y, z))))
+            .filter_map(|offset| grid.get(&(center + offset)))
+            .flatten()
+            .copied()
+            .find(|&index| vertices[index as usize].distance(position) <= WELD_DISTANCE);
+        let index = existing.unwrap_or_else(|| {
+            let index = vertices.len() as u32;
+            vertices.push(position);
+            grid.entry(center).or_default().push(index);
+            index
+        });
+        remap.push(index);
+    }
+    (vertices, remap)
+}
+
+fn is_degenerate(vertices: &[Vec3], [a, b, c]: [u32; 3]) -> bool {
+    if a == b || b == c || c == a {
+        return true;
+    }
+    normal(vertices, [a, b, c]).length() * 0.5 < MIN_TRIANGLE_AREA
+}
+
+/// Not normalized, its length is twice the triangle's area
+fn normal(vertices: &[Vec3], [a, b, c]: [u32; 3]) -> Vec3 {
+    let (a, b, c) = (
+        vertices[a as usize],
+        vertices[b as usize],
+        vertices[c as usize],
+    );
+    (b - a).cross(c - a)
+}
+
+/// Makes neighboring triangles wind the same way by walking across their shared edges.
+/// Every connected part is then turned so that it faces outwards if it is closed, or mostly upwards if it is not,
+/// which is what the navmesh expects of walkable ground.
+fn fix_winding(vertices: &[Vec3], triangles: &mut [[u32; 3]]) {
+    let edge = |a: u32, b: u32| if a < b { (a, b) } else { (b, a) };
+    let mut triangles_per_edge = HashMap::<(u32, u32), Vec<usize>>::new();
+    for (index, triangle) in triangles.iter().enumerate() {
+        for (a, b) in edges(*triangle) {
+            triangles_per_edge
+                .entry(edge(a, b))
+                .or_default()
+                .push(index);
+        }
+    }
+
+    let mut visited = HashSet::new();
+    for start in 0..triangles.len() {
+        if !visited.insert(start) {
+            continue;
+        }
+        let mut part = vec![start];
+        let mut queue = VecDeque::from([start]);
+        while let Some(current) = queue.pop_front() {
+            for (a, b) in edges(triangles[current]) {
+                let neighbors = &triangles_per_edge[&edge(a, b)];
+                // Non-manifold edges give no clear answer on how their triangles should wind
+                if neighbors.len() != 2 {
+                    continue;
+                }
+                for &neighbor in neighbors {
+                    if neighbor == current || !visited.insert(neighbor) {
+                        continue;
+                    }
+                    // Consistently wound neighbors run through their shared edge in opposite directions
+                    if edges(triangles[neighbor]).contains(&(a, b)) {
+                        flip(&mut triangles[neighbor]);
+                    }
+                    part.push(neighbor);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+
+        let is_closed = part.iter().all(|&index| {
+            edges(triangles[index])
+                .into_iter()
+                .all(|(a, b)| triangles_per_edge[&edge(a, b)].len() == 2)
+        });
+        let facing_inwards = if is_closed {
+            signed_volume(vertices, part.iter().map(|&index| triangles[index])) < 0.
+        } else {
+            let up: f32 = part
+                .iter()
+                .map(|&index| normal(vertices, triangles[index]).y)
+                .sum();
+            up < 0.
+        };
+        if facing_inwards {
+            for &index in &part {
+                flip(&mut triangles[index]);
+            }
+        }
+    }
+}
+
+fn edges([a, b, c]: [u32; 3]) -> [(u32, u32); 3] {
+    [(a, b), (b, c), (c, a)]
+}
+
+fn flip(triangle: &mut [u32; 3]) {
+    triangle.swap(1, 2);
+}
+
+/// Positive for closed meshes whose triangles wind counter-clockwise when seen from outside
+fn signed_volume(vertices: &[Vec3], triangles: impl Iterator<Item = [u32; 3]>) -> f32 {
+    triangles
+        .map(|[a, b, c]| {
+            vertices[a as usize].dot(vertices[b as usize].cross(vertices[c as usize])) / 6.
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::{mesh::Indices, render_resource::PrimitiveTopology};
+
+    /// A unit square on the ground, wound so that it faces up
+    const QUAD: [[f32; 3]; 4] = [[0., 0., 0.], [1., 0., 0.], [1., 0., 1.], [0., 0., 1.]];
+    const QUAD_UP: [u32; 6] = [0, 3, 1, 1, 3, 2];
+
+    /// A tetrahedron above the quad, wound so that it faces outwards
+    const TETRAHEDRON: [[f32; 3]; 4] = [[0., 1., 0.], [1., 1., 0.], [0., 2., 0.], [0., 1., 1.]];
+    const TETRAHEDRON_OUT: [u32; 12] = [0, 2, 1, 0, 1, 3, 0, 3, 2, 1, 2, 3];
+
+    fn mesh(positions: &[[f32; 3]], indices: Option<Indices>) -> Mesh {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions.to_vec());
+        mesh.set_indices(indices);
+        mesh
+    }
+
+    fn triangles(indices: &[u32]) -> Vec<[u32; 3]> {
+        indices
+            .chunks_exact(3)
+            .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+            .collect()
+    }
+
+    fn vertices(positions: &[[f32; 3]]) -> Vec<Vec3> {
+        positions.iter().copied().map(Vec3::from).collect()
+    }
+
+    fn faces_up(vertices: &[Vec3], triangle: [u32; 3]) -> bool {
+        normal(vertices, triangle).y > 0.
+    }
+
+    /// Whether the triangle faces away from the center of the part it belongs to
+    fn faces_outwards(vertices: &[Vec3], triangle: [u32; 3], center: Vec3) -> bool {
+        let centroid = triangle
+            .iter()
+            .map(|&index| vertices[index as usize])
+            .sum::<Vec3>()
+            / 3.;
+        normal(vertices, triangle).dot(centroid - center) > 0.
+    }
+
+    #[test]
+    fn weld_merges_duplicated_vertices() {
+        let (vertices, remap) = weld(&[[0., 0., 0.], [1., 0., 0.], [0., 0., 0.], [1., 0., 0.]]);
+        assert_eq!(vertices, vec![Vec3::ZERO, Vec3::X]);
+        assert_eq!(remap, vec![0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn weld_merges_near_duplicate_vertices() {
+        let (vertices, remap) = weld(&[[1., 0., 0.], [1. + 0.5 * WELD_DISTANCE, 0., 0.]]);
+        assert_eq!(vertices, vec![Vec3::X]);
+        assert_eq!(remap, vec![0, 0]);
+    }
+
+    #[test]
+    fn weld_merges_near_duplicate_vertices_across_grid_cells() {
+        let offset = 0.25 * WELD_DISTANCE;
+        let (vertices, remap) = weld(&[[-offset, 0., 0.], [offset, 0., 0.]]);
+        assert_eq!(vertices.len(), 1);
+        assert_eq!(remap, vec![0, 0]);
+    }
+
+    #[test]
+    fn weld_keeps_vertices_further_apart() {
+        let (vertices, remap) = weld(&[[0., 0., 0.], [3. * WELD_DISTANCE, 0., 0.]]);
+        assert_eq!(vertices.len(), 2);
+        assert_eq!(remap, vec![0, 1]);
+    }
+
+    #[test]
+    fn clean_welds_vertices_split_at_seams() {
+        // The quad's two triangles do not share any vertices, like at a UV seam
+        let positions = [QUAD[0], QUAD[3], QUAD[1], QUAD[1], QUAD[3], QUAD[2]];
+        let clean = clean(&[&mesh(&positions, None)]).unwrap();
+        assert_eq!(
+            clean.vertices,
+            vertices(&[QUAD[0], QUAD[3], QUAD[1], QUAD[2]])
+        );
+        assert_eq!(clean.triangles, vec![[0, 1, 2], [2, 1, 3]]);
+    }
+
+    #[test]
+    fn clean_drops_zero_area_triangles() {
+        let mut positions = QUAD.to_vec();
+        positions.push([2., 0., 0.]);
+        let mut indices = QUAD_UP.to_vec();
+        // All corners on one line
+        indices.extend([0, 1, 4]);
+        let clean = clean(&[&mesh(&positions, Some(Indices::U32(indices)))]).unwrap();
+        assert_eq!(clean.triangles, triangles(&QUAD_UP));
+    }
+
+    #[test]
+    fn clean_drops_collapsed_triangles() {
+        let mut positions = QUAD.to_vec();
+        // Welded onto the first corner of the quad
+        positions.push([0.5 * WELD_DISTANCE, 0., 0.]);
+        // Also covers meshes with 16 bit indices
+        let mut indices: Vec<u16> = QUAD_UP.iter().map(|&index| index as u16).collect();
+        indices.extend([0, 4, 1, 2, 2, 3]);
+        let clean = clean(&[&mesh(&positions, Some(Indices::U16(indices)))]).unwrap();
+        assert_eq!(clean.vertices, vertices(&QUAD));
+        assert_eq!(clean.triangles, triangles(&QUAD_UP));
+    }
+
+    #[test]
+    fn clean_returns_none_without_triangles_left() {
+        let positions = [[0., 0., 0.], [1., 0., 0.], [2., 0., 0.]];
+        assert_eq!(clean(&[&mesh(&positions, None)]), None);
+        assert_eq!(clean(&[]), None);
+    }
+
+    #[test]
+    fn clean_drops_triangles_with_out_of_range_indices() {
+        let mut indices = QUAD_UP.to_vec();
+        indices.splice(3..3, [0, 2, 7]);
+        let clean = clean(&[&mesh(&QUAD, Some(Indices::U32(indices)))]).unwrap();
+        assert_eq!(clean.vertices, vertices(&QUAD));
+        assert_eq!(clean.triangles, triangles(&QUAD_UP));
+    }
+
+    #[test]
+    fn clean_offsets_the_indices_of_merged_meshes() {
+        let quad = mesh(&QUAD, Some(Indices::U32(QUAD_UP.to_vec())));
+        let tetrahedron = mesh(&TETRAHEDRON, Some(Indices::U32(TETRAHEDRON_OUT.to_vec())));
+        let clean = clean(&[&quad, &tetrahedron]).unwrap();
+        let mut expected = triangles(&QUAD_UP);
+        expected.extend(triangles(&TETRAHEDRON_OUT.map(|index| index + 4)));
+        assert_eq!(clean.vertices.len(), 8);
+        assert_eq!(clean.triangles, expected);
+    }
+
+    #[test]
+    fn fix_winding_turns_open_parts_upwards() {
+        let vertices = vertices(&QUAD);
+        for indices in [[0, 3, 1, 1, 2, 3], [0, 1, 3, 1, 3, 2], [0, 1, 3, 1, 2, 3]] {
+            let mut triangles = triangles(&indices);
+            fix_winding(&vertices, &mut triangles);
+            assert!(
+                triangles
+                    .iter()
+                    .all(|triangle| faces_up(&vertices, *triangle)),
+                "{indices:?} was turned into {triangles:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn fix_winding_turns_closed_parts_outwards() {
+        let vertices = vertices(&TETRAHEDRON);
+        let center = vertices.iter().sum::<Vec3>() / 4.;
+        let mut all_inwards = triangles(&TETRAHEDRON_OUT);
+        all_inwards.iter_mut().for_each(flip);
+        let mut mixed = triangles(&TETRAHEDRON_OUT);
+        flip(&mut mixed[1]);
+        flip(&mut mixed[3]);
+        for mut triangles in [all_inwards, mixed] {
+            fix_winding(&vertices, &mut triangles);
+            assert!(
+                triangles
+                    .iter()
+                    .all(|triangle| faces_outwards(&vertices, *triangle, center)),
+                "Not all of {triangles:?} face outwards"
+            );
+            assert!(signed_volume(&vertices, triangles.iter().copied()) > 0.);
+        }
+    }
+
+    #[test]
+    fn clean_fixes_mixed_winding_of_closed_and_open_parts() {
+        let mut quad_indices = QUAD_UP;
+        quad_indices.swap(4, 5);
+        let mut tetrahedron_indices = TETRAHEDRON_OUT;
+        tetrahedron_indices.swap(1, 2);
+        tetrahedron_indices.swap(10, 11);
+        let quad = mesh(&QUAD, Some(Indices::U32(quad_indices.to_vec())));
+        let tetrahedron = mesh(
+            &TETRAHEDRON,
+            Some(Indices::U32(tetrahedron_indices.to_vec())),
+        );
+
+        let clean = clean(&[&quad, &tetrahedron]).unwrap();
+
+        let center = clean.vertices[4..].iter().sum::<Vec3>() / 4.;
+        let (ground, tetrahedron): (Vec<_>, Vec<_>) = clean
+            .triangles
+            .iter()
+            .partition(|triangle| triangle.iter().all(|&index| index < 4));
+        assert_eq!(ground.len(), 2);
+        assert_eq!(tetrahedron.len(), 4);
+        assert!(ground
+            .iter()
+            .all(|triangle| faces_up(&clean.vertices, **triangle)));
+        assert!(tetrahedron.iter().all(|triangle| faces_outwards(
+            &clean.vertices,
+            **triangle,
+            center
+        )));
+    }
+}
@@ -0,0 +1,357 @@
+use crate::{
+    combat::health::Dead,
+    despawn::DespawnOnExit,
+    movement::{
+        character_controller::{
+            apply_foot_ik, apply_jumping, GeneralMovementSystemSet, Jump, Walk,
+        },
+        physics::{CollisionLayer, TransformInterpolationSet},
+    },
+    GameState,
+};
+use bevy::{prelude::*, render::mesh::skinning::SkinnedMesh, transform::TransformSystem};
+use bevy_xpbd_3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Handles ragdolls. Characters with a [`Ragdoll`] component get a physics body per bone of their skinned mesh
+/// when a [`RagdollEvent::Activate`] is sent or when they are hit hard enough.
+/// Once the bodies have settled, the character optionally blends back into its animation.
+pub(crate) fn ragdoll_plugin(app: &mut App) {
+    app.register_type::<Ragdoll>()
+        .add_event::<RagdollEvent>()
+        .add_systems(
+            Update,
+            (
+                detect_heavy_impacts,
+                handle_ragdoll_events,
+                hold_ragdolled_characters
+                    .in_set(GeneralMovementSystemSet)
+                    .before(apply_jumping),
+            )
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            PostUpdate,
+            pose_ragdolls
+                .after(TransformInterpolationSet)
+                .after(apply_foot_ik)
+                .before(TransformSystem::TransformPropagate)
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Ragdoll {
+    /// Relative speed of a collision above which the character goes limp. `None` disables impact activation.
+    pub(crate) impact_speed_threshold: Option<f32>,
+    pub(crate) bone_radius: f32,
+    /// Whether to blend back into the animation after the body has settled
+    pub(crate) get_up: bool,
+    /// Speed below which a bone counts as resting
+    pub(crate) settle_speed: f32,
+    /// How long all bones need to rest before getting up
+    pub(crate) settle_duration: f32,
+    pub(crate) blend_duration: f32,
+}
+
+impl Default for Ragdoll {
+    fn default() -> Self {
+        Self {
+            impact_speed_threshold: Some(15.),
+            bone_radius: 0.04,
+            get_up: true,
+            settle_speed: 0.2,
+            settle_duration: 1.5,
+            blend_duration: 0.6,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
+pub(crate) enum RagdollEvent {
+    Activate(Entity),
+    /// Immediately starts blending back into the animation, even if the ragdoll has not settled yet
+    Recover(Entity),
+}
+
+/// Present while a character is not fully driven by its animation.
+#[derive(Debug, Clone, PartialEq, Component)]
+pub(crate) enum RagdollState {
+    Simulated {
+        bodies: Vec<RagdollBody>,
+        joints: Vec<Entity>,
+        root_body: Entity,
+        settled_for: f32,
+    },
+    Recovering {
+        pose: Vec<(Entity, Transform)>,
+        elapsed: f32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct RagdollBody {
+    bone: Entity,
+    body: Entity,
+    scale: Vec3,
+}
+
+fn detect_heavy_impacts(
+    mut collisions: EventReader<CollisionStarted>,
+    ragdolls: Query<&Ragdoll, Without<RagdollState>>,
+    velocities: Query<&LinearVelocity>,
+    mut ragdoll_events: EventWriter<RagdollEvent>,
+) {
+    for CollisionStarted(entity1, entity2) in collisions.read() {
+        for (entity, other) in [(*entity1, *entity2), (*entity2, *entity1)] {
+            let Some(threshold) = ragdolls
+                .get(entity)
+                .ok()
+                .and_then(|ragdoll| ragdoll.impact_speed_threshold)
+            else {
+                continue;
+            };
+            let velocity = velocities.get(entity).map(|v| v.0).unwrap_or_default();
+            let other_velocity = velocities.get(other).map(|v| v.0).unwrap_or_default();
+            if (velocity - other_velocity).length() > threshold {
+                ragdoll_events.send(RagdollEvent::Activate(entity));
+            }
+        }
+    }
+}
+
+fn handle_ragdoll_events(
+    mut commands: Commands,
+    mut ragdoll_events: EventReader<RagdollEvent>,
+    mut ragdolls: Query<(&Ragdoll, Option<&mut RagdollState>, &LinearVelocity)>,
+    children: Query<&Children>,
+    skinned_meshes: Query<&SkinnedMesh>,
+    parents: Query<&Parent>,
+    transforms: Query<&Transform>,
+    global_transforms: Query<&GlobalTransform>,
+) {
+    for event in ragdoll_events.read() {
+        match *event {
+            RagdollEvent::Activate(entity) => {
+                let Ok((ragdoll, None, velocity)) = ragdolls.get(entity) else {
+                    continue;
+                };
+                let Some(skinned_mesh) = children
+                    .iter_descendants(entity)
+                    .find_map(|child| skinned_meshes.get(child).ok())
+                else {
+                    warn!("Cannot ragdoll {entity:?} because it has no skinned mesh");
+                    continue;
+                };
+                let state = spawn_ragdoll_bodies(
+                    &mut commands,
+                    ragdoll,
+                    &skinned_mesh.joints,
+                    velocity.0,
+                    &parents,
+                    &children,
+                    &global_transforms,
+                );
+                commands.entity(entity).insert(state);
+            }
+            RagdollEvent::Recover(entity) => {
+                let Ok((_, Some(mut state), _)) = ragdolls.get_mut(entity) else {
+                    continue;
+                };
+                start_recovering(&mut commands, &mut state, &transforms);
+            }
+        }
+    }
+}
+
+fn spawn_ragdoll_bodies(
+    commands: &mut Commands,
+    ragdoll: &Ragdoll,
+    bones: &[Entity],
+    velocity: Vec3,
+    parents: &Query<&Parent>,
+    children: &Query<&Children>,
+    global_transforms: &Query<&GlobalTransform>,
+) -> RagdollState {
+    let mut bodies = Vec::with_capacity(bones.len());
+    let mut body_transforms = Vec::with_capacity(bones.len());
+    for &bone in bones {
+        let Ok(global) = global_transforms.get(bone) else {
+            continue;
+        };
+        let (scale, rotation, translation) = global.to_scale_rotation_translation();
+        let transform = Transform::from_translation(translation).with_rotation(rotation);
+
+        // Span the collider from this bone to its first child bone, or use a small ball for end bones
+        let child_bone = children
+            .get(bone)
+            .ok()
+            .and_then(|c| c.iter().find(|child| bones.contains(child)).copied());
+        let collider = match child_bone.and_then(|child| global_transforms.get(child).ok()) {
+            Some(child_global) => {
+                let end = rotation.inverse() * (child_global.translation() - translation);
+                Collider::capsule_endpoints(Vec3::ZERO, end, ragdoll.bone_radius)
+            }
+            None => Collider::ball(ragdoll.bone_radius),
+        };
+
+        let body = commands
+            .spawn((
+                Name::new("Ragdoll Body"),
+                TransformBundle::from_transform(transform),
+                RigidBody::Dynamic,
+                collider,
+                LinearVelocity(velocity),
+                CollisionLayer::ragdoll(),
+                DespawnOnExit(GameState::Playing),
+            ))
+            .id();
+        bodies.push(RagdollBody { bone, body, scale });
+        body_transforms.push(transform);
+    }
+
+    let mut joints = Vec::new();
+    let mut root_body = None;
+    for (index, ragdoll_body) in bodies.iter().enumerate() {
+        let parent_index = parents
+            .get(ragdoll_body.bone)
+            .ok()
+            .and_then(|parent| bodies.iter().position(|b| b.bone == parent.get()));
+        let Some(parent_index) = parent_index else {
+            root_body.get_or_insert(ragdoll_body.body);
+            continue;
+        };
+        let parent_transform = body_transforms[parent_index];
+        let anchor = parent_transform.rotation.inverse()
+            * (body_transforms[index].translation - parent_transform.translation);
+        let joint = commands
+            .spawn((
+                SphericalJoint::new(bodies[parent_index].body, ragdoll_body.body)
+                    .with_local_anchor_1(anchor)
+                    .with_swing_limits(-0.8, 0.8)
+                    .with_twist_limits(-0.4, 0.4),
+                DespawnOnExit(GameState::Playing),
+            ))
+            .id();
+        joints.push(joint);
+    }
+
+    RagdollState::Simulated {
+        root_body: root_body.unwrap_or(Entity::PLACEHOLDER),
+        bodies,
+        joints,
+        settled_for: 0.,
+    }
+}
+
+fn start_recovering(
+    commands: &mut Commands,
+    state: &mut RagdollState,
+    transforms: &Query<&Transform>,
+) {
+    let RagdollState::Simulated { bodies, joints, .. } = state else {
+        return;
+    };
+    let pose = bodies
+        .iter()
+        .filter_map(|body| Some((body.bone, *transforms.get(body.bone).ok()?)))
+        .collect();
+    for entity in joints.iter().chain(bodies.iter().map(|body| &body.body)) {
+        commands.entity(*entity).despawn_recursive();
+    }
+    *state = RagdollState::Recovering { pose, elapsed: 0. };
+}
+
+/// Keeps the character's own body on top of the ragdoll so that it gets up where it fell
+/// and ignores any movement requests in the meantime.
+fn hold_ragdolled_characters(
+    mut characters: Query<(
+        &RagdollState,
+        &mut Transform,
+        &mut LinearVelocity,
+        &mut Walk,
+        &mut Jump,
+    )>,
+    bodies: Query<&Transform, Without<RagdollState>>,
+) {
+    for (state, mut transform, mut velocity, mut walk, mut jump) in characters.iter_mut() {
+        walk.direction = None;
+        jump.requested = false;
+        velocity.0 = Vec3::ZERO;
+        if let RagdollState::Simulated { root_body, .. } = state {
+            if let Ok(root_transform) = bodies.get(*root_body) {
+                transform.translation.x = root_transform.translation.x;
+                transform.translation.z = root_transform.translation.z;
+            }
+        }
+    }
+}
+
+fn pose_ragdolls(
+    mut commands: Commands,
+    time: Res<Time<Virtual>>,
+    mut ragdolls: Query<(Entity, &Ragdoll, &mut RagdollState, Has<Dead>)>,
+    mut transforms: Query<&mut Transform>,
+    global_transforms: Query<&GlobalTransform>,
+    velocities: Query<&LinearVelocity>,
+    parents: Query<&Parent>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("pose_ragdolls").entered();
+    let dt = time.delta_seconds();
+    for (entity, ragdoll, mut state, is_dead) in ragdolls.iter_mut() {
+        match &mut *state {
+            RagdollState::Simulated {
+                bodies,
+                settled_for,
+                ..
+            } => {
+                let mut resting = true;
+                for body in bodies.iter() {
+                    let Ok(body_transform) = transforms.get(body.body).copied() else {
+                        continue;
+                    };
+                    let parent_global = parents
+                        .get(body.bone)
+                        .ok()
+                        .and_then(|parent| global_transforms.get(parent.get()).ok())
+                        .copied()
+                        .unwrap_or_default();
+                    let target = GlobalTransform::from(body_transform.with_scale(body.scale));
+                    if let Ok(mut bone_transform) = transforms.get_mut(body.bone) {
+                        *bone_transform = target.reparented_to(&parent_global);
+                    }
+                    let speed = velocities
+                        .get(body.body)
+                        .map(|v| v.length())
+                        .unwrap_or_default();
+                    resting &= speed < ragdoll.settle_speed;
+                }
+                *settled_for = if resting { *settled_for + dt } else { 0. };
+                if ragdoll.get_up && !is_dead && *settled_for > ragdoll.settle_duration {
+                    start_recovering(&mut commands, &mut state, &transforms.to_readonly());
+                }
+            }
+            RagdollState::Recovering { pose, elapsed } => {
+                *elapsed += dt;
+                let weight = (*elapsed / ragdoll.blend_duration.max(1e-5)).min(1.);
+                for (bone, ragdoll_transform) in pose.iter() {
+                    if let Ok(mut bone_transform) = transforms.get_mut(*bone) {
+                        // The animation player already wrote this frame's pose, so we blend from the ragdoll to it
+                        bone_transform.translation = ragdoll_transform
+                            .translation
+                            .lerp(bone_transform.translation, weight);
+                        bone_transform.rotation = ragdoll_transform
+                            .rotation
+                            .slerp(bone_transform.rotation, weight);
+                    }
+                }
+                if weight >= 1. {
+                    commands.entity(entity).remove::<RagdollState>();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use crate::{despawn::DespawnOnExit, movement::physics::CollisionLayer, GameState};
+use bevy::prelude::*;
+use bevy_xpbd_3d::{prelude::*, PhysicsSet};
+use serde::{Deserialize, Serialize};
+
+/// Handles ropes and joints that snap under too much strain.
+/// A [`Rope`] hangs down from its entity as a chain of capsule segments connected by [`SphericalJoint`]s.
+/// Any joint with a [`BreakableJoint`] is despawned once the force it exerts exceeds its threshold,
+/// sending a [`JointBrokenEvent`] that effects can react to.
+/// Climbing and swinging on ropes is handled by [`crate::player_control::climbing`].
+pub(crate) fn ropes_plugin(app: &mut App) {
+    app.register_type::<Rope>()
+        .register_type::<RopeSegment>()
+        .register_type::<BreakableJoint>()
+        .add_event::<JointBrokenEvent>()
+        .add_systems(Update, spawn_ropes.run_if(in_state(GameState::Playing)))
+        .add_systems(
+            FixedUpdate,
+            (
+                break_joints::<SphericalJoint>,
+                break_joints::<FixedJoint>,
+                break_joints::<RevoluteJoint>,
+            )
+                .after(PhysicsSet::Sync)
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+/// A rope hanging down from this entity. If the entity has no [`RigidBody`], it is made static.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Rope {
+    pub(crate) length: f32,
+    pub(crate) segment_count: usize,
+    pub(crate) radius: f32,
+    /// Force at which a joint of the rope snaps. `None` makes the rope unbreakable.
+    pub(crate) break_force: Option<f32>,
+    /// The spawned segments, from top to bottom
+    #[serde(skip)]
+    pub(crate) segments: Vec<Entity>,
+}
+
+impl Default for Rope {
+    fn default() -> Self {
+        Self {
+            length: 6.,
+            segment_count: 12,
+            radius: 0.05,
+            break_force: None,
+            segments: default(),
+        }
+    }
+}
+
+impl Rope {
+    pub(crate) fn segment_length(&self) -> f32 {
+        self.length / self.segment_count.max(1) as f32
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct RopeSegment {
+    pub(crate) rope: Entity,
+    /// Position in the rope, 0 being the topmost segment
+    pub(crate) index: usize,
+}
+
+/// Makes the joint on this entity break when it exerts more than `max_force`.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct BreakableJoint {
+    pub(crate) max_force: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Event)]
+pub(crate) struct JointBrokenEvent {
+    pub(crate) joint: Entity,
+    /// The bodies the joint used to connect
+    pub(crate) bodies: [Entity; 2],
+    pub(crate) force: f32,
+    /// Where the joint broke, in world space
+    pub(crate) position: Vec3,
+}
+
+fn spawn_ropes(
+    mut commands: Commands,
+    mut ropes: Query<(Entity, &mut Rope, &GlobalTransform, Has<RigidBody>), Added<Rope>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (rope_entity, mut rope, transform, has_rigid_body) in ropes.iter_mut() {
+        if !has_rigid_body {
+            commands.entity(rope_entity).insert(RigidBody::Static);
+        }
+        let segment_length = rope.segment_length();
+        let capsule_height = (segment_length - 2. * rope.radius).max(0.);
+        let mesh = meshes.add(Mesh::from(shape::Capsule {
+            radius: rope.radius,
+            depth: capsule_height,
+            ..default()
+        }));
+        let material = materials.add(Color::rgb(0.55, 0.4, 0.25).into());
+        let top = transform.translation();
+        let mut previous = rope_entity;
+        let mut segments = Vec::with_capacity(rope.segment_count);
+        for index in 0..rope.segment_count {
+            let center = top - Vec3::Y * segment_length * (index as f32 + 0.5);
+            let segment = commands
+                .spawn((
+                    Name::new(format!("Rope Segment {index}")),
+                    RopeSegment {
+                        rope: rope_entity,
+                        index,
+                    },
+                    PbrBundle {
+                        mesh: mesh.clone(),
+                        material: material.clone(),
+                        transform: Transform::from_translation(center),
+                        ..default()
+                    },
+                    RigidBody::Dynamic,
+                    Collider::capsule(capsule_height, rope.radius),
+                    CollisionLayer::rope(),
+                    AngularDamping(0.5),
+                    DespawnOnExit(GameState::Playing),
+                ))
+                .id();
+            // Segments are connected at their ends, the first one to the rope's origin
+            let anchor = if previous == rope_entity {
+                Vec3::ZERO
+            } else {
+                Vec3::NEG_Y * segment_length / 2.
+            };
+            let mut joint = commands.spawn((
+                SphericalJoint::new(previous, segment)
+                    .with_local_anchor_1(anchor)
+                    .with_local_anchor_2(Vec3::Y * segment_length / 2.),
+                DespawnOnExit(GameState::Playing),
+            ));
+            if let Some(max_force) = rope.break_force {
+                joint.insert(BreakableJoint { max_force });
+            }
+            segments.push(segment);
+            previous = segment;
+        }
+        rope.segments = segments;
+    }
+}
+
+/// Access to the force of the joint types that can be made breakable.
+trait JointForce: Joint + Component {
+    fn force(&self) -> Vec3;
+}
+
+impl JointForce for SphericalJoint {
+    fn force(&self) -> Vec3 {
+        self.force
+    }
+}
+
+impl JointForce for FixedJoint {
+    fn force(&self) -> Vec3 {
+        self.force
+    }
+}
+
+impl JointForce for RevoluteJoint {
+    fn force(&self) -> Vec3 {
+        self.force
+    }
+}
+
+fn break_joints<T: JointForce>(
+    mut commands: Commands,
+    joints: Query<(Entity, &T, &BreakableJoint)>,
+    transforms: Query<&GlobalTransform>,
+    mut broken_events: EventWriter<JointBrokenEvent>,
+) {
+    for (entity, joint, breakable) in joints.iter() {
+        let force = joint.force().length();
+        if force <= breakable.max_force {
+            continue;
+        }
+        let bodies = joint.entities();
+        let position = transforms
+            .get(bodies[1])
+            .map(|transform| transform.transform_point(joint.local_anchor_2()))
+            .unwrap_or_default();
+        commands.entity(entity).despawn_recursive();
+        broken_events.send(JointBrokenEvent {
+            joint: entity,
+            bodies,
+            force,
+            position,
+        });
+    }
+}
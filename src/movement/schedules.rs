@@ -0,0 +1,211 @@
+use crate::{
+    level_instantiation::markers::{Marker, MarkersAppExt},
+    movement::navigation::{Navigation, NavigationSystemSet, Navigator},
+    rng::{GameRng, RngStream},
+    time_of_day::TimeOfDay,
+    util::{criteria::is_frozen, trait_extension::Vec3Ext},
+    world_interaction::dialog::DialogTarget,
+    GameState,
+};
+use anyhow::{Context, Result};
+use bevy::{ecs::world::EntityWorldMut, prelude::*, utils::HashMap};
+use bevy_common_assets::ron::RonAssetPlugin;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Average seconds a wandering NPC lingers at a spot before strolling to the next one
+const WANDER_PAUSE: f32 = 4.;
+
+/// Lets NPCs go about their day. An NPC named with a `[schedule:<name>]` suffix in Blender follows the
+/// [`Schedule`] loaded from `schedules/<name>.schedule.ron`, walking with its [`Navigator`] to the place of
+/// whatever entry is due at the current [`TimeOfDay`]. Places are objects named with a `[place:<name>]` suffix.
+/// An entry can also switch the dialog node of the NPC, so that it says different things at home than at the market,
+/// and let it wander around the place, strolling between random reachable spots on the navmesh.
+/// Dialogs can additionally check the time through the `$hour` yarn variable.
+/// NPCs stand still while the player's actions are frozen, e.g. during dialogs and cutscenes.
+pub(crate) fn schedules_plugin(app: &mut App) {
+    app.add_plugins(RonAssetPlugin::<Schedule>::new(&["schedule.ron"]))
+        .register_type::<SchedulePlace>()
+        .register_marker("schedule", insert_schedule)
+        .register_marker("place", |entity, marker| {
+            let name = marker.argument(0).context("Expected [place:<name>]")?;
+            entity.insert(SchedulePlace {
+                name: name.to_string(),
+            });
+            Ok(())
+        })
+        .add_systems(
+            Update,
+            (
+                init_scheduled_npcs,
+                follow_schedules.run_if(not(is_frozen)),
+                stop_scheduled_npcs.run_if(is_frozen),
+            )
+                .chain()
+                .before(NavigationSystemSet)
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+/// What an NPC does over the course of a day. Each entry lasts until the next one starts, and the last one
+/// lasts until the first one starts again the next day. Entries do not need to be sorted.
+#[derive(Debug, Clone, PartialEq, Asset, TypePath, Serialize, Deserialize, Default)]
+pub(crate) struct Schedule {
+    pub(crate) entries: Vec<ScheduleEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ScheduleEntry {
+    /// Hour of the day the entry starts at, e.g. 20.5 for half past eight in the evening
+    pub(crate) hour: f32,
+    /// Name of the [`SchedulePlace`] to go to
+    pub(crate) place: String,
+    /// Yarn node the NPC's dialog starts at while this entry is active. Keeps the previous one if `None`.
+    #[serde(default)]
+    pub(crate) dialog: Option<String>,
+    /// How far from the place the NPC wanders around once it got there. Stands at the place if 0.
+    #[serde(default)]
+    pub(crate) wander_radius: f32,
+}
+
+impl Schedule {
+    /// The entry that is due at the given time of day
+    pub(crate) fn active_entry(&self, time_of_day: TimeOfDay) -> Option<&ScheduleEntry> {
+        let by_hour = |a: &&ScheduleEntry, b: &&ScheduleEntry| a.hour.total_cmp(&b.hour);
+        self.entries
+            .iter()
+            .filter(|entry| entry.hour <= time_of_day.0)
+            .max_by(by_hour)
+            // Before the first entry of the day, the last one of yesterday is still going
+            .or_else(|| self.entries.iter().max_by(by_hour))
+    }
+}
+
+/// A location NPCs can be sent to by their [`Schedule`].
+#[derive(Debug, Clone, PartialEq, Eq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct SchedulePlace {
+    pub(crate) name: String,
+}
+
+/// The [`Schedule`] an NPC follows.
+#[derive(Debug, Clone, PartialEq, Component)]
+pub(crate) struct ScheduledNpc {
+    pub(crate) schedule: Handle<Schedule>,
+}
+
+fn insert_schedule(entity: &mut EntityWorldMut, marker: &Marker) -> Result<()> {
+    let name = marker.argument(0).context("Expected [schedule:<name>]")?;
+    let schedule = entity
+        .world()
+        .resource::<AssetServer>()
+        .load(format!("schedules/{name}.schedule.ron"));
+    entity.insert(ScheduledNpc { schedule });
+    Ok(())
+}
+
+fn init_scheduled_npcs(
+    mut commands: Commands,
+    npcs: Query<Entity, (Added<ScheduledNpc>, Without<Navigator>)>,
+) {
+    for entity in npcs.iter() {
+        commands.entity(entity).insert(Navigator::default());
+    }
+}
+
+fn follow_schedules(
+    time: Res<Time>,
+    time_of_day: Res<TimeOfDay>,
+    schedules: Res<Assets<Schedule>>,
+    mut npcs: Query<(
+        Entity,
+        &ScheduledNpc,
+        &Transform,
+        &mut Navigator,
+        Option<&mut DialogTarget>,
+    )>,
+    places: Query<(&SchedulePlace, &GlobalTransform)>,
+    navigation: Navigation,
+    mut rng: ResMut<GameRng>,
+    mut missing_places: Local<Vec<String>>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("follow_schedules").entered();
+    let places: HashMap<_, _> = places
+        .iter()
+        .map(|(place, transform)| (place.name.as_str(), transform.translation()))
+        .collect();
+    let rng = rng.stream(RngStream::Wandering);
+    for (entity, npc, transform, mut navigator, dialog_target) in npcs.iter_mut() {
+        let Some(entry) = schedules
+            .get(&npc.schedule)
+            .and_then(|schedule| schedule.active_entry(*time_of_day))
+        else {
+            continue;
+        };
+        let destination = places.get(entry.place.as_str()).copied();
+        if destination.is_none() && !missing_places.contains(&entry.place) {
+            warn!(
+                "Schedule of {entity:?}: no place named \"{}\" in the level",
+                entry.place
+            );
+            missing_places.push(entry.place.clone());
+        }
+        let destination = match destination {
+            Some(place) if entry.wander_radius > 0. => wander(
+                transform.translation,
+                &navigator,
+                place,
+                entry.wander_radius,
+                time.delta_seconds(),
+                &navigation,
+                rng,
+            ),
+            destination => destination,
+        };
+        if navigator.destination != destination {
+            navigator.destination = destination;
+        }
+        if let (Some(node), Some(mut dialog_target)) = (&entry.dialog, dialog_target) {
+            if &dialog_target.node != node {
+                dialog_target.node = node.clone();
+            }
+        }
+    }
+}
+
+/// Where an NPC wandering around `place` within `radius` walks to next
+fn wander(
+    position: Vec3,
+    navigator: &Navigator,
+    place: Vec3,
+    radius: f32,
+    dt: f32,
+    navigation: &Navigation,
+    rng: &mut impl Rng,
+) -> Option<Vec3> {
+    if (position - place).horizontal().length() > radius {
+        return Some(place);
+    }
+    let current = navigator
+        .destination
+        .filter(|current| (*current - place).horizontal().length() <= radius);
+    if let Some(current) = current {
+        let has_arrived = position.distance(current) < navigator.arrival_distance;
+        if !has_arrived || !rng.gen_bool((dt / WANDER_PAUSE).clamp(0., 1.) as f64) {
+            return Some(current);
+        }
+    }
+    navigation
+        .random_point_in_radius(place, radius, rng)
+        .filter(|point| navigation.is_reachable(position, *point))
+        .or(current)
+}
+
+fn stop_scheduled_npcs(mut npcs: Query<&mut Navigator, With<ScheduledNpc>>) {
+    for mut navigator in npcs.iter_mut() {
+        if navigator.destination.is_some() {
+            navigator.destination = None;
+        }
+    }
+}
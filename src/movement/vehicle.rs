@@ -0,0 +1,200 @@
+use crate::{movement::physics::CollisionLayer, GameState};
+use bevy::prelude::*;
+use bevy_xpbd_3d::{prelude::*, PhysicsSet};
+use serde::{Deserialize, Serialize};
+
+/// Drives rigid bodies with a [`Vehicle`] component on [`Wheel`]s that are simulated with raycasts
+/// instead of colliders. Each wheel pushes the body up with a damped spring and, when touching the ground,
+/// applies engine, brake and grip forces. What the vehicle should do is set in its [`VehicleControls`].
+/// The forces are applied before every physics step, the wheel models are placed once per frame.
+pub(crate) fn vehicle_plugin(app: &mut App) {
+    app.register_type::<Vehicle>()
+        .register_type::<Wheel>()
+        .register_type::<VehicleControls>()
+        .add_systems(
+            FixedUpdate,
+            apply_wheel_forces
+                .before(PhysicsSet::Prepare)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(Update, position_wheels.run_if(in_state(GameState::Playing)));
+}
+
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Vehicle {
+    /// Force split between all driven wheels at full throttle
+    pub(crate) engine_force: f32,
+    /// Force applied by each wheel while braking
+    pub(crate) brake_force: f32,
+    /// Speed above which the engine stops accelerating
+    pub(crate) max_speed: f32,
+    /// Steering angle of the steered wheels at full lock in radians
+    pub(crate) max_steer_angle: f32,
+    /// Where the driver sits, relative to the vehicle
+    pub(crate) seat: Vec3,
+}
+
+impl Default for Vehicle {
+    fn default() -> Self {
+        Self {
+            engine_force: 4000.,
+            brake_force: 1500.,
+            max_speed: 15.,
+            max_steer_angle: 0.5,
+            seat: Vec3::new(0., 0.4, 0.),
+        }
+    }
+}
+
+/// Input for a [`Vehicle`]. A vehicle without a driver keeps its brakes on.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct VehicleControls {
+    /// Between -1 (full reverse) and 1 (full throttle)
+    pub(crate) throttle: f32,
+    /// Between -1 (full left) and 1 (full right)
+    pub(crate) steering: f32,
+    pub(crate) brake: bool,
+}
+
+impl Default for VehicleControls {
+    fn default() -> Self {
+        Self {
+            throttle: 0.,
+            steering: 0.,
+            brake: true,
+        }
+    }
+}
+
+/// A wheel of the [`Vehicle`] it is a child of. Its transform is overwritten every frame to show
+/// the suspension travel, steering and rotation, so any meshes should be added as its children.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Wheel {
+    /// Top of the suspension, relative to the vehicle
+    pub(crate) mount: Vec3,
+    pub(crate) radius: f32,
+    /// Length of the fully extended suspension
+    pub(crate) suspension_length: f32,
+    pub(crate) stiffness: f32,
+    pub(crate) damping: f32,
+    /// Force per unit of sideways speed that keeps the wheel from sliding
+    pub(crate) grip: f32,
+    pub(crate) steered: bool,
+    pub(crate) driven: bool,
+    /// Current length of the suspension
+    pub(crate) length: f32,
+    /// Current rotation around the axle in radians
+    pub(crate) spin: f32,
+}
+
+impl Default for Wheel {
+    fn default() -> Self {
+        Self {
+            mount: Vec3::ZERO,
+            radius: 0.35,
+            suspension_length: 0.35,
+            stiffness: 6000.,
+            damping: 800.,
+            grip: 500.,
+            steered: false,
+            driven: false,
+            length: 0.35,
+            spin: 0.,
+        }
+    }
+}
+
+pub(crate) fn apply_wheel_forces(
+    time: Res<Time>,
+    mut vehicles: Query<(
+        &Vehicle,
+        &VehicleControls,
+        &Transform,
+        &LinearVelocity,
+        &AngularVelocity,
+        &mut ExternalForce,
+        &Children,
+    )>,
+    mut wheels: Query<&mut Wheel>,
+    spatial_query: SpatialQuery,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("apply_wheel_forces").entered();
+    let dt = time.delta_seconds();
+    let filter = CollisionLayer::ground_filter();
+    for (vehicle, controls, transform, linear_velocity, angular_velocity, mut force, children) in
+        vehicles.iter_mut()
+    {
+        force.clear();
+        let up = transform.up();
+        let speed = linear_velocity.dot(transform.forward());
+        let driven_count = children
+            .iter()
+            .filter(|child| wheels.get(**child).is_ok_and(|wheel| wheel.driven))
+            .count()
+            .max(1);
+        let engine_force = if speed.abs() < vehicle.max_speed {
+            controls.throttle.clamp(-1., 1.) * vehicle.engine_force / driven_count as f32
+        } else {
+            0.
+        };
+        let steer_angle = -controls.steering.clamp(-1., 1.) * vehicle.max_steer_angle;
+
+        let mut wheel_iter = wheels.iter_many_mut(children);
+        while let Some(mut wheel) = wheel_iter.fetch_next() {
+            let mount = transform.transform_point(wheel.mount);
+            let max_distance = wheel.suspension_length + wheel.radius;
+            let Some(hit) = spatial_query.cast_ray(mount, -up, max_distance, true, filter.clone())
+            else {
+                wheel.length = wheel.suspension_length;
+                continue;
+            };
+            wheel.length = (hit.time_of_impact - wheel.radius).max(0.);
+
+            let offset = mount - transform.translation;
+            let point_velocity = linear_velocity.0 + angular_velocity.0.cross(offset);
+            let compression = wheel.suspension_length - wheel.length;
+            let suspension =
+                (wheel.stiffness * compression - wheel.damping * point_velocity.dot(up)).max(0.);
+
+            let steering = if wheel.steered {
+                Quat::from_axis_angle(up, steer_angle)
+            } else {
+                Quat::IDENTITY
+            };
+            let forward = steering * transform.forward();
+            let right = steering * transform.right();
+            let forward_speed = point_velocity.dot(forward);
+            let grip = -point_velocity.dot(right) * wheel.grip;
+            let drive = if wheel.driven { engine_force } else { 0. };
+            let brake = if controls.brake {
+                -forward_speed.clamp(-1., 1.) * vehicle.brake_force
+            } else {
+                0.
+            };
+
+            let wheel_force = up * suspension + right * grip + forward * (drive + brake);
+            force.apply_force_at_point(wheel_force, offset, Vec3::ZERO);
+            wheel.spin += forward_speed / wheel.radius * dt;
+        }
+    }
+}
+
+fn position_wheels(
+    vehicles: Query<(&Vehicle, &VehicleControls, &Children)>,
+    mut wheels: Query<(&Wheel, &mut Transform)>,
+) {
+    for (vehicle, controls, children) in vehicles.iter() {
+        let steer_angle = -controls.steering.clamp(-1., 1.) * vehicle.max_steer_angle;
+        let mut wheel_iter = wheels.iter_many_mut(children);
+        while let Some((wheel, mut transform)) = wheel_iter.fetch_next() {
+            let steering = if wheel.steered { steer_angle } else { 0. };
+            transform.translation = wheel.mount - Vec3::Y * wheel.length;
+            transform.rotation =
+                Quat::from_rotation_y(steering) * Quat::from_rotation_x(-wheel.spin);
+        }
+    }
+}
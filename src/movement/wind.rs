@@ -0,0 +1,159 @@
+use crate::{
+    movement::character_controller::{
+        apply_walking, ExternalDrift, GeneralMovementSystemSet, Walk,
+    },
+    util::trait_extension::Vec3Ext,
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_xpbd_3d::{prelude::*, PhysicsSet};
+use serde::{Deserialize, Serialize};
+
+/// How strongly the difference between wind and body velocity accelerates a body of mass 1
+const BODY_DRAG: f32 = 0.5;
+/// Fraction of the wind speed with which characters drift along
+const CHARACTER_DRIFT: f32 = 0.3;
+
+/// Handles [`WindZone`]s, which can be placed in the level through the GLTF extras like any other marker.
+/// Wind pushes dynamic rigid bodies and makes characters drift. The grass and particle effects sample
+/// the same zones via [`wind_at`] to sway and be blown away.
+pub(crate) fn wind_plugin(app: &mut App) {
+    app.register_type::<WindZone>()
+        .register_type::<WindShape>()
+        .add_systems(
+            FixedUpdate,
+            push_bodies
+                .before(PhysicsSet::Prepare)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            Update,
+            drift_characters
+                .in_set(GeneralMovementSystemSet)
+                .before(apply_walking)
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct WindZone {
+    pub(crate) shape: WindShape,
+    /// Wind speed in m/s at the center of the zone
+    pub(crate) strength: f32,
+    /// Strength oscillates by this fraction to simulate gusts
+    pub(crate) gustiness: f32,
+    /// Gusts per second
+    pub(crate) gust_frequency: f32,
+}
+
+impl Default for WindZone {
+    fn default() -> Self {
+        Self {
+            shape: default(),
+            strength: 4.,
+            gustiness: 0.3,
+            gust_frequency: 0.2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+#[reflect(Serialize, Deserialize)]
+pub(crate) enum WindShape {
+    /// Blows along the zone's forward direction inside a box centered on it
+    Directional { half_extents: Vec3 },
+    /// Blows outward from the zone's center, fading out towards the radius.
+    /// A negative strength pulls inward instead.
+    Radial { radius: f32 },
+}
+
+impl Default for WindShape {
+    fn default() -> Self {
+        Self::Directional {
+            half_extents: Vec3::splat(10.),
+        }
+    }
+}
+
+impl WindZone {
+    /// Wind velocity caused by this zone at `point`, `elapsed` seconds into the game.
+    pub(crate) fn velocity_at(
+        &self,
+        transform: &GlobalTransform,
+        point: Vec3,
+        elapsed: f32,
+    ) -> Vec3 {
+        let gust =
+            1. + self.gustiness * (elapsed * self.gust_frequency * std::f32::consts::TAU).sin();
+        let strength = self.strength * gust;
+        match &self.shape {
+            WindShape::Directional { half_extents } => {
+                let local = transform.affine().inverse().transform_point3(point);
+                if local.abs().cmpgt(*half_extents).any() {
+                    return Vec3::ZERO;
+                }
+                transform.forward() * strength
+            }
+            WindShape::Radial { radius } => {
+                let offset = point - transform.translation();
+                let distance = offset.length();
+                if distance >= *radius {
+                    return Vec3::ZERO;
+                }
+                let falloff = 1. - distance / radius;
+                offset.normalize_or_zero() * strength * falloff
+            }
+        }
+    }
+}
+
+/// Sum of the wind of all zones at `point`.
+pub(crate) fn wind_at<'a>(
+    zones: impl IntoIterator<Item = (&'a WindZone, &'a GlobalTransform)>,
+    point: Vec3,
+    elapsed: f32,
+) -> Vec3 {
+    zones
+        .into_iter()
+        .map(|(zone, transform)| zone.velocity_at(transform, point, elapsed))
+        .sum()
+}
+
+fn push_bodies(
+    time: Res<Time>,
+    zones: Query<(&WindZone, &GlobalTransform)>,
+    mut bodies: Query<(&RigidBody, &Mass, &GlobalTransform, &mut LinearVelocity), Without<Walk>>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("push_bodies").entered();
+    if zones.is_empty() {
+        return;
+    }
+    let dt = time.delta_seconds();
+    let elapsed = time.elapsed_seconds();
+    for (rigid_body, mass, transform, mut velocity) in bodies.iter_mut() {
+        if !rigid_body.is_dynamic() || mass.0 <= 0. {
+            continue;
+        }
+        let wind = wind_at(&zones, transform.translation(), elapsed);
+        if wind == Vec3::ZERO {
+            continue;
+        }
+        // Drag pulls the body's velocity towards the wind's, lighter bodies get there faster
+        let acceleration = (wind - velocity.0) * BODY_DRAG / mass.0;
+        velocity.0 += acceleration * dt;
+    }
+}
+
+fn drift_characters(
+    time: Res<Time<Virtual>>,
+    zones: Query<(&WindZone, &GlobalTransform)>,
+    mut characters: Query<(&GlobalTransform, &mut ExternalDrift)>,
+) {
+    let elapsed = time.elapsed_seconds();
+    for (transform, mut drift) in characters.iter_mut() {
+        let wind = wind_at(&zones, transform.translation(), elapsed);
+        drift.set_if_neq(ExternalDrift(wind.horizontal() * CHARACTER_DRIFT));
+    }
+}
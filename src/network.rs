@@ -0,0 +1,568 @@
+#[cfg(feature = "dev")]
+use crate::dev::console::ConsoleAppExt;
+use crate::{
+    combat::projectiles::{Projectile, SpawnProjectileEvent},
+    despawn::DespawnOnExit,
+    file_system_interaction::asset_loading::{AnimationAssets, GltfAssets},
+    hud::world_labels::WorldLabel,
+    movement::character_controller::{
+        AnimationGraph, AnimationLink, AnimationState, CharacterAnimations,
+    },
+    network::{
+        chat::{chat_plugin, ChatLog},
+        client::{client_plugin, LocalClientId},
+        server::server_plugin,
+    },
+    player_control::{actions::PlayerAction, player_embodiment::Player},
+    world_interaction::interactions_ui::InteractionOpportunity,
+    GameState,
+};
+use anyhow::{Context, Result};
+use bevy::{gltf::Gltf, prelude::*, utils::HashMap};
+use bevy_renet::{
+    renet::{
+        transport::{NetcodeClientTransport, NetcodeServerTransport},
+        DefaultChannel, RenetClient, RenetServer,
+    },
+    transport::{NetcodeClientPlugin, NetcodeServerPlugin},
+    RenetClientPlugin, RenetServerPlugin,
+};
+use leafwing_input_manager::prelude::ActionState;
+use seldom_fn_plugin::FnPluginExt;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{collections::VecDeque, net::SocketAddr, time::Duration};
+
+pub(crate) mod chat;
+pub(crate) mod client;
+pub(crate) mod server;
+
+/// Changing this makes older builds unable to connect
+const PROTOCOL_ID: u64 = 0x466f_7874_726f_7401;
+pub(crate) const DEFAULT_PORT: u16 = 5000;
+/// Seconds between two state updates sent by each peer
+const SEND_INTERVAL: f32 = 1. / 30.;
+/// Remote players are shown this far in the past, so that there are always two states to interpolate between
+const INTERPOLATION_DELAY: f64 = 0.1;
+/// The ID the host's own player is known by
+pub(crate) const HOST_ID: u64 = 0;
+/// For states, which are outdated by the time they would be resent
+const UNRELIABLE: DefaultChannel = DefaultChannel::Unreliable;
+/// For messages that must arrive, like interactions and corrections
+const RELIABLE: DefaultChannel = DefaultChannel::ReliableOrdered;
+/// Longer names are cut off, so that nameplates stay readable
+pub(crate) const MAX_NAME_LENGTH: usize = 24;
+
+/// Lets players join each other's game over the network, with the `multiplayer` feature.
+/// One player hosts a listen server with a [`HostSessionRequest`] and plays as usual; others connect with a [`JoinSessionRequest`]
+/// and load the host's level.
+/// - Every peer simulates their own character and sends its transform and [`AnimationState`] to the host, which relays them to everyone.
+///   This makes movement feel instant for everyone, i.e. the local character is predicted.
+///   The host rejects movement faster than a character can go and sends a correction, which the client reconciles with the
+///   movement it predicted since then, see [`client_plugin`].
+/// - Other players are shown as [`RemotePlayer`]s, interpolated between the last two states received.
+/// - Thrown projectiles and interactions are sent as [`RemoteInteraction`]s, see [`RemoteInteractionEvent`].
+/// - Every player introduces themselves with the name from the [`Settings`](crate::menu::Settings).
+///   The host collects all names into [`PlayerNames`], which are shown as nametags above other players and in the chat, see [`chat_plugin`].
+///
+/// Other players are drawn as capsules, since the player model only exists as part of the level.
+/// Sessions are started from the multiplayer page of the main menu or, with the dev tools, with the console commands
+/// `host [port]` and `join <address>`.
+pub(crate) fn network_plugin(app: &mut App) {
+    app.add_plugins((
+        RenetServerPlugin,
+        NetcodeServerPlugin,
+        RenetClientPlugin,
+        NetcodeClientPlugin,
+    ))
+    .register_type::<RemotePlayer>()
+    .add_event::<HostSessionRequest>()
+    .add_event::<JoinSessionRequest>()
+    .add_event::<LeaveSessionRequest>()
+    .add_event::<RemoteInteractionEvent>()
+    .init_resource::<RemotePlayers>()
+    .init_resource::<PlayerNames>()
+    .init_resource::<SendTimer>()
+    .fn_plugin(server_plugin)
+    .fn_plugin(client_plugin)
+    .fn_plugin(chat_plugin)
+    .add_systems(
+        Update,
+        (
+            tick_send_timer,
+            (
+                sync_remote_player_entities,
+                interpolate_remote_players,
+                play_remote_animations,
+                update_nameplates,
+                apply_remote_interactions,
+            )
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        ),
+    )
+    .add_systems(
+        Update,
+        leave_session.run_if(on_event::<LeaveSessionRequest>()),
+    )
+    .add_systems(OnEnter(GameState::Menu), leave_session);
+    #[cfg(feature = "dev")]
+    app.register_console_command("host", "host [port]", host_command)
+        .register_console_command("join", "join <address>", join_command);
+}
+
+#[cfg(feature = "dev")]
+fn host_command(world: &mut World, arguments: &[&str]) -> Result<String> {
+    let port = match arguments.first() {
+        Some(port) => port.parse().context("Expected a port number")?,
+        None => DEFAULT_PORT,
+    };
+    world.send_event(HostSessionRequest { port });
+    Ok(format!("Hosting on port {port}"))
+}
+
+#[cfg(feature = "dev")]
+fn join_command(world: &mut World, arguments: &[&str]) -> Result<String> {
+    let address = parse_address(arguments.first().context("Missing argument <address>")?)?;
+    world.send_event(JoinSessionRequest { address });
+    Ok(format!("Joining {address}"))
+}
+
+/// Accepts addresses with or without a port, e.g. "192.168.0.2" or "192.168.0.2:5000".
+pub(crate) fn parse_address(address: &str) -> Result<SocketAddr> {
+    let address = address.trim();
+    address
+        .parse()
+        .or_else(|_| format!("{address}:{DEFAULT_PORT}").parse())
+        .context("Expected an address like 192.168.0.2 or 192.168.0.2:5000")
+}
+
+/// Starts a listen server on the given port.
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub(crate) struct HostSessionRequest {
+    pub(crate) port: u16,
+}
+
+/// Connects to a host and loads their level.
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub(crate) struct JoinSessionRequest {
+    pub(crate) address: SocketAddr,
+}
+
+/// Disconnects from the session or closes it when hosting. Happens automatically when returning to the main menu.
+#[derive(Debug, Clone, PartialEq, Eq, Event, Default)]
+pub(crate) struct LeaveSessionRequest;
+
+/// Something another player did that the local game should show.
+#[derive(Debug, Clone, PartialEq, Event)]
+pub(crate) struct RemoteInteractionEvent {
+    pub(crate) client_id: u64,
+    pub(crate) interaction: RemoteInteraction,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) enum RemoteInteraction {
+    Throw {
+        position: Vec3,
+        velocity: Vec3,
+    },
+    /// Interacted with the object of the given name, e.g. started talking to an NPC
+    Interact {
+        target: String,
+    },
+}
+
+/// The state of a player as sent over the network
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct PlayerState {
+    /// Counts up with every state sent by a peer, used to match corrections to predicted states
+    pub(crate) tick: u32,
+    pub(crate) translation: Vec3,
+    pub(crate) rotation: Quat,
+    /// State of the player's [`AnimationGraph`]
+    pub(crate) animation: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum ClientMessage {
+    State(PlayerState),
+    Interaction(RemoteInteraction),
+    /// Sent after joining and whenever the player changes their name
+    Introduce {
+        name: String,
+    },
+    Chat(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum ServerMessage {
+    /// Sent to a client once it has connected
+    Welcome {
+        level: String,
+    },
+    /// The latest state of every player, including the receiving one
+    Snapshot {
+        players: Vec<(u64, PlayerState)>,
+    },
+    /// Tells a client that its state with the given tick was rejected and where its player actually was
+    Correction {
+        tick: u32,
+        translation: Vec3,
+    },
+    Interaction {
+        client_id: u64,
+        interaction: RemoteInteraction,
+    },
+    PlayerLeft {
+        client_id: u64,
+    },
+    /// The names of all players in the session, sent whenever someone joins, leaves or renames themselves
+    Names(Vec<(u64, String)>),
+    Chat {
+        client_id: u64,
+        text: String,
+    },
+}
+
+fn encode(message: &impl Serialize) -> Vec<u8> {
+    // Serializing into memory only fails for types bincode does not support, which is a bug
+    bincode::serialize(message).expect("Failed to serialize network message")
+}
+
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    bincode::deserialize(bytes).context("Failed to deserialize network message")
+}
+
+/// Another player, shown at their interpolated position.
+#[derive(Debug, Clone, PartialEq, Eq, Component, Reflect, Default)]
+#[reflect(Component)]
+pub(crate) struct RemotePlayer {
+    pub(crate) client_id: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Component)]
+struct RemoteAnimation(String);
+
+/// Recently received states of all other players, keyed by their client ID
+#[derive(Debug, Clone, PartialEq, Resource, Default)]
+pub(crate) struct RemotePlayers(HashMap<u64, VecDeque<ReceivedState>>);
+
+#[derive(Debug, Clone, PartialEq)]
+struct ReceivedState {
+    /// Real time at which the state was received
+    received: f64,
+    state: PlayerState,
+}
+
+impl RemotePlayers {
+    fn receive(&mut self, client_id: u64, state: PlayerState, now: f64) {
+        let states = self.0.entry(client_id).or_default();
+        // Unreliable messages can arrive out of order
+        if states
+            .back()
+            .is_some_and(|last| last.state.tick >= state.tick)
+        {
+            return;
+        }
+        states.push_back(ReceivedState {
+            received: now,
+            state,
+        });
+        // Only the states around the interpolation delay are needed
+        while states
+            .get(1)
+            .is_some_and(|next| now - next.received > INTERPOLATION_DELAY * 2.)
+        {
+            states.pop_front();
+        }
+    }
+
+    fn latest(&self, client_id: u64) -> Option<PlayerState> {
+        Some(self.0.get(&client_id)?.back()?.state.clone())
+    }
+
+    fn remove(&mut self, client_id: u64) {
+        self.0.remove(&client_id);
+    }
+}
+
+/// The names of everyone in the session, including the local player, keyed by their client ID
+#[derive(Debug, Clone, PartialEq, Eq, Resource, Default)]
+pub(crate) struct PlayerNames(HashMap<u64, String>);
+
+impl PlayerNames {
+    pub(crate) fn get(&self, client_id: u64) -> &str {
+        self.0.get(&client_id).map_or("Player", String::as_str)
+    }
+}
+
+/// Trims whitespace and cuts off overly long names, since names are chosen by the remote players.
+fn sanitize_name(name: &str) -> String {
+    let name: String = name
+        .trim()
+        .chars()
+        .filter(|character| !character.is_control())
+        .take(MAX_NAME_LENGTH)
+        .collect();
+    if name.is_empty() {
+        "Player".to_string()
+    } else {
+        name
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Resource)]
+struct SendTimer(Timer);
+
+impl Default for SendTimer {
+    fn default() -> Self {
+        Self(Timer::from_seconds(SEND_INTERVAL, TimerMode::Repeating))
+    }
+}
+
+fn tick_send_timer(time: Res<Time<Real>>, mut timer: ResMut<SendTimer>) {
+    timer.0.tick(time.delta());
+}
+
+fn send_timer_finished(timer: Res<SendTimer>) -> bool {
+    timer.0.just_finished()
+}
+
+/// Whether the game is hosting or connected to a session
+pub(crate) fn in_session(
+    server: Option<Res<RenetServer>>,
+    client: Option<Res<RenetClient>>,
+) -> bool {
+    server.is_some() || client.is_some_and(|client| client.is_connected())
+}
+
+/// The state of the local player, to be sent with the given tick
+fn local_player_state(
+    players: &Query<(&Transform, Option<&AnimationState>), With<Player>>,
+    tick: u32,
+) -> Option<PlayerState> {
+    let (transform, animation_state) = players.get_single().ok()?;
+    Some(PlayerState {
+        tick,
+        translation: transform.translation,
+        rotation: transform.rotation,
+        animation: animation_state
+            .map(|animation_state| animation_state.state.clone())
+            .unwrap_or_default(),
+    })
+}
+
+/// Interactions of the local player this frame
+fn local_interactions(
+    spawn_events: &mut EventReader<SpawnProjectileEvent>,
+    players: &Query<(Entity, &ActionState<PlayerAction>), With<Player>>,
+    interaction_opportunity: &InteractionOpportunity,
+    names: &Query<&Name>,
+) -> Vec<RemoteInteraction> {
+    let Ok((player, actions)) = players.get_single() else {
+        spawn_events.clear();
+        return Vec::new();
+    };
+    let mut interactions: Vec<_> = spawn_events
+        .read()
+        .filter(|event| event.projectile.shooter == Some(player))
+        .map(|event| RemoteInteraction::Throw {
+            position: event.position,
+            velocity: event.projectile.velocity,
+        })
+        .collect();
+    if actions.just_pressed(PlayerAction::Interact) {
+        if let Some(target) = interaction_opportunity
+            .0
+            .and_then(|target| names.get(target).ok())
+        {
+            interactions.push(RemoteInteraction::Interact {
+                target: target.to_string(),
+            });
+        }
+    }
+    interactions
+}
+
+fn sync_remote_player_entities(
+    mut commands: Commands,
+    remote_players: Res<RemotePlayers>,
+    local_client_id: Option<Res<LocalClientId>>,
+    entities: Query<(Entity, &RemotePlayer)>,
+    animation_assets: Res<AnimationAssets>,
+    mut handles: Local<Option<(Handle<Mesh>, Handle<StandardMaterial>)>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let local_id = local_client_id.map_or(HOST_ID, |id| id.0);
+    for (entity, remote_player) in entities.iter() {
+        if !remote_players.0.contains_key(&remote_player.client_id) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    for (&client_id, states) in remote_players.0.iter() {
+        let exists = entities
+            .iter()
+            .any(|(_, remote_player)| remote_player.client_id == client_id);
+        let Some(latest) = states.back() else {
+            continue;
+        };
+        if exists || client_id == local_id {
+            continue;
+        }
+        let (mesh, material) = handles
+            .get_or_insert_with(|| {
+                (
+                    meshes.add(Mesh::from(shape::Capsule {
+                        radius: 0.3,
+                        depth: 0.8,
+                        ..default()
+                    })),
+                    materials.add(Color::rgb(0.9, 0.5, 0.2).into()),
+                )
+            })
+            .clone();
+        commands.spawn((
+            Name::new(format!("Remote Player {client_id}")),
+            RemotePlayer { client_id },
+            RemoteAnimation(latest.state.animation.clone()),
+            PbrBundle {
+                mesh,
+                material,
+                transform: Transform::from_translation(latest.state.translation)
+                    .with_rotation(latest.state.rotation),
+                ..default()
+            },
+            DespawnOnExit(GameState::Playing),
+            CharacterAnimations {
+                graph: animation_assets.character.clone(),
+            },
+        ));
+    }
+}
+
+fn interpolate_remote_players(
+    time: Res<Time<Real>>,
+    remote_players: Res<RemotePlayers>,
+    mut entities: Query<(&RemotePlayer, &mut Transform, &mut RemoteAnimation)>,
+) {
+    let render_time = time.elapsed_seconds_f64() - INTERPOLATION_DELAY;
+    for (remote_player, mut transform, mut animation) in entities.iter_mut() {
+        let Some(states) = remote_players.0.get(&remote_player.client_id) else {
+            continue;
+        };
+        let Some(latest) = states.back() else {
+            continue;
+        };
+        let (from, to) = match states.iter().position(|state| state.received > render_time) {
+            Some(index) if index > 0 => (&states[index - 1], &states[index]),
+            // Nothing to interpolate between, so the player stays at the closest known state
+            Some(_) => (&states[0], &states[0]),
+            None => (latest, latest),
+        };
+        let span = (to.received - from.received).max(f64::EPSILON);
+        let t = ((render_time - from.received) / span).clamp(0., 1.) as f32;
+        transform.translation = from.state.translation.lerp(to.state.translation, t);
+        transform.rotation = from.state.rotation.slerp(to.state.rotation, t);
+        let state = if t < 0.5 { from } else { to };
+        if animation.0 != state.state.animation {
+            animation.0 = state.state.animation.clone();
+        }
+    }
+}
+
+/// Plays the animation of the replicated [`AnimationGraph`] state on remote players once their model's [`AnimationPlayer`]
+/// is linked to them by an [`AnimationLink`].
+fn play_remote_animations(
+    remote_players: Query<
+        (&RemoteAnimation, &CharacterAnimations, &AnimationLink),
+        Or<(Changed<RemoteAnimation>, Changed<AnimationLink>)>,
+    >,
+    mut animation_players: Query<&mut AnimationPlayer>,
+    graphs: Res<Assets<AnimationGraph>>,
+    gltf_assets: Res<GltfAssets>,
+    gltfs: Res<Assets<Gltf>>,
+) {
+    let Some(level) = gltfs.get(gltf_assets.level.clone()) else {
+        return;
+    };
+    for (animation, animations, link) in remote_players.iter() {
+        let Ok(mut animation_player) = animation_players.get_mut(link.0) else {
+            continue;
+        };
+        let Some(state) = graphs
+            .get(&animations.graph)
+            .and_then(|graph| graph.states.get(&animation.0))
+        else {
+            continue;
+        };
+        let Some(clip) = level.named_animations.get(&state.animation) else {
+            continue;
+        };
+        let playing = animation_player
+            .play_with_transition(clip.clone_weak(), Duration::from_secs_f32(state.blend));
+        if state.repeat {
+            playing.repeat();
+        }
+    }
+}
+
+fn update_nameplates(
+    mut commands: Commands,
+    names: Res<PlayerNames>,
+    remote_players: Query<(Entity, &RemotePlayer, Option<&WorldLabel>)>,
+) {
+    for (entity, remote_player, label) in remote_players.iter() {
+        let name = names.get(remote_player.client_id);
+        if label.map(|label| label.text.as_str()) != Some(name) {
+            commands.entity(entity).insert(WorldLabel::nametag(name));
+        }
+    }
+}
+
+fn apply_remote_interactions(
+    mut interactions: EventReader<RemoteInteractionEvent>,
+    mut spawn_events: EventWriter<SpawnProjectileEvent>,
+) {
+    for event in interactions.read() {
+        match &event.interaction {
+            RemoteInteraction::Throw { position, velocity } => {
+                spawn_events.send(SpawnProjectileEvent {
+                    position: *position,
+                    projectile: Projectile {
+                        velocity: *velocity,
+                        ..default()
+                    },
+                });
+            }
+            RemoteInteraction::Interact { target } => {
+                info!("Player {} interacted with {target}", event.client_id);
+            }
+        }
+    }
+}
+
+fn leave_session(
+    mut commands: Commands,
+    server: Option<ResMut<RenetServer>>,
+    client_transport: Option<ResMut<NetcodeClientTransport>>,
+    mut remote_players: ResMut<RemotePlayers>,
+    mut names: ResMut<PlayerNames>,
+    mut chat_log: ResMut<ChatLog>,
+) {
+    if let Some(mut server) = server {
+        server.disconnect_all();
+        commands.remove_resource::<RenetServer>();
+        commands.remove_resource::<NetcodeServerTransport>();
+        info!("Closed the session");
+    }
+    if let Some(mut transport) = client_transport {
+        transport.disconnect();
+        commands.remove_resource::<RenetClient>();
+        commands.remove_resource::<NetcodeClientTransport>();
+        commands.remove_resource::<LocalClientId>();
+        info!("Left the session");
+    }
+    remote_players.0.clear();
+    names.0.clear();
+    chat_log.clear();
+}
@@ -0,0 +1,188 @@
+use crate::{
+    file_system_interaction::storage,
+    ingame_menu::PauseState,
+    network::{in_session, PlayerNames},
+    player_control::actions::ActionsFrozen,
+    theme::UiTheme,
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use std::time::SystemTime;
+
+const OPEN_CHAT_KEY: KeyCode = KeyCode::T;
+/// Longer messages are cut off
+const MAX_MESSAGE_LENGTH: usize = 200;
+const MAX_LOG_LINES: usize = 50;
+/// Seconds a message stays on screen while the chat box is closed
+const MESSAGE_LIFETIME: f32 = 8.;
+const CHAT_WIDTH: f32 = 360.;
+
+/// Text chat between all players in a session.
+/// Pressing T opens the chat box, which freezes the player's actions while typing. Enter sends the message and closes it again.
+/// While closed, recent messages are still shown for a few seconds.
+pub(super) fn chat_plugin(app: &mut App) {
+    app.add_event::<SendChatMessage>()
+        .init_resource::<ChatLog>()
+        .init_resource::<ChatBox>()
+        .add_systems(
+            Update,
+            (open_chat, show_chat).chain().run_if(
+                in_state(GameState::Playing)
+                    .and_then(in_state(PauseState::Running))
+                    .and_then(in_session),
+            ),
+        )
+        .add_systems(OnEnter(PauseState::Paused), close_chat)
+        .add_systems(OnExit(GameState::Playing), close_chat);
+}
+
+/// Sends a chat message from the local player to everyone in the session.
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub(crate) struct SendChatMessage {
+    pub(crate) text: String,
+}
+
+/// Messages sent in the current session, oldest first
+#[derive(Debug, Clone, PartialEq, Resource, Default)]
+pub(crate) struct ChatLog(Vec<ChatLine>);
+
+#[derive(Debug, Clone, PartialEq)]
+struct ChatLine {
+    client_id: u64,
+    text: String,
+    received: SystemTime,
+}
+
+impl ChatLog {
+    pub(crate) fn push(&mut self, client_id: u64, text: String) {
+        self.0.push(ChatLine {
+            client_id,
+            text,
+            received: storage::now(),
+        });
+        let overflow = self.0.len().saturating_sub(MAX_LOG_LINES);
+        self.0.drain(..overflow);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Resource, Default)]
+struct ChatBox {
+    open: bool,
+    input: String,
+}
+
+/// Trims a message and cuts it off at [`MAX_MESSAGE_LENGTH`]. Returns `None` for messages that are empty.
+pub(super) fn sanitize_message(text: &str) -> Option<String> {
+    let text: String = text
+        .trim()
+        .chars()
+        .filter(|character| !character.is_control())
+        .take(MAX_MESSAGE_LENGTH)
+        .collect();
+    (!text.is_empty()).then_some(text)
+}
+
+fn open_chat(
+    keys: Res<Input<KeyCode>>,
+    mut chat_box: ResMut<ChatBox>,
+    mut actions_frozen: ResMut<ActionsFrozen>,
+) {
+    // Other screens like dialogs or the map already use the keyboard
+    if chat_box.open || actions_frozen.is_frozen() || !keys.just_pressed(OPEN_CHAT_KEY) {
+        return;
+    }
+    chat_box.open = true;
+    actions_frozen.freeze();
+}
+
+fn close_chat(mut chat_box: ResMut<ChatBox>, mut actions_frozen: ResMut<ActionsFrozen>) {
+    if chat_box.open {
+        chat_box.open = false;
+        actions_frozen.unfreeze();
+    }
+}
+
+fn show_chat(
+    keys: Res<Input<KeyCode>>,
+    mut chat_box: ResMut<ChatBox>,
+    mut actions_frozen: ResMut<ActionsFrozen>,
+    chat_log: Res<ChatLog>,
+    names: Res<PlayerNames>,
+    theme: Res<UiTheme>,
+    mut chat_events: EventWriter<SendChatMessage>,
+    mut egui_contexts: EguiContexts,
+) {
+    let now = storage::now();
+    let is_recent = |line: &&ChatLine| {
+        now.duration_since(line.received)
+            .map_or(true, |age| age.as_secs_f32() < MESSAGE_LIFETIME)
+    };
+    if !chat_box.open && !chat_log.0.iter().any(|line| is_recent(&line)) {
+        return;
+    }
+    let chat_box = chat_box.as_mut();
+    let background = if chat_box.open {
+        theme.colors.overlay
+    } else {
+        egui::Color32::TRANSPARENT
+    };
+    egui::Area::new("Chat")
+        .anchor(
+            egui::Align2::LEFT_BOTTOM,
+            egui::vec2(
+                theme.spacing.screen_margin,
+                -4. * theme.spacing.screen_margin,
+            ),
+        )
+        .show(egui_contexts.ctx_mut(), |ui| {
+            egui::Frame::none()
+                .fill(background)
+                .rounding(theme.panel.rounding)
+                .inner_margin(egui::style::Margin::same(6.))
+                .show(ui, |ui| {
+                    ui.set_width(CHAT_WIDTH);
+                    egui::ScrollArea::vertical()
+                        .max_height(200.)
+                        .stick_to_bottom(true)
+                        .show(ui, |ui| {
+                            let lines = chat_log
+                                .0
+                                .iter()
+                                .filter(|line| chat_box.open || is_recent(line));
+                            for line in lines {
+                                ui.horizontal_wrapped(|ui| {
+                                    ui.colored_label(
+                                        theme.colors.accent,
+                                        format!("{}:", names.get(line.client_id)),
+                                    );
+                                    ui.label(&line.text);
+                                });
+                            }
+                        });
+                    // The key that opened the chat would be typed into it
+                    if !chat_box.open || keys.just_pressed(OPEN_CHAT_KEY) {
+                        return;
+                    }
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut chat_box.input)
+                            .hint_text("Say something…")
+                            .char_limit(MAX_MESSAGE_LENGTH)
+                            .desired_width(f32::INFINITY),
+                    );
+                    response.request_focus();
+                    if ui.input(|input| input.key_pressed(egui::Key::Enter)) {
+                        let text = std::mem::take(&mut chat_box.input);
+                        if sanitize_message(&text).is_some() {
+                            chat_events.send(SendChatMessage { text });
+                        }
+                        chat_box.open = false;
+                        actions_frozen.unfreeze();
+                    }
+                });
+        });
+}
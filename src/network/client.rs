@@ -0,0 +1,268 @@
+use crate::{
+    combat::projectiles::SpawnProjectileEvent,
+    level_instantiation::levels::CurrentLevel,
+    menu::Settings,
+    movement::character_controller::AnimationState,
+    network::{
+        chat::{sanitize_message, ChatLog, SendChatMessage},
+        decode, encode, local_interactions, local_player_state, send_timer_finished, ClientMessage,
+        JoinSessionRequest, PlayerNames, RemoteInteractionEvent, RemotePlayers, ServerMessage,
+        PROTOCOL_ID, RELIABLE, UNRELIABLE,
+    },
+    player_control::{actions::PlayerAction, player_embodiment::Player},
+    world_interaction::interactions_ui::InteractionOpportunity,
+    GameState,
+};
+use anyhow::{Context, Result};
+use bevy::prelude::*;
+use bevy_mod_sysfail::*;
+use bevy_renet::renet::{
+    transport::{ClientAuthentication, NetcodeClientTransport},
+    ConnectionConfig, RenetClient,
+};
+use leafwing_input_manager::prelude::ActionState;
+use std::{
+    collections::VecDeque,
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// States older than this many ticks cannot be corrected anymore
+const HISTORY_LENGTH: usize = 64;
+
+/// Connects to a host and exchanges states with it.
+/// The local player moves as soon as the input arrives, so their position is a prediction the host can reject.
+/// Every state sent is kept in a [`PredictionHistory`]. When the host corrects a state, the difference between
+/// the corrected and the predicted position is applied to the player, keeping the movement predicted since then.
+/// Chat messages and the player's name are sent to the host, which relays them to everyone else.
+pub(super) fn client_plugin(app: &mut App) {
+    app.init_resource::<PredictionHistory>()
+        .init_resource::<IntroducedName>()
+        .add_systems(
+            Update,
+            (
+                join_session,
+                (
+                    receive_server_messages,
+                    introduce,
+                    send_client_chat,
+                    (
+                        send_client_interactions,
+                        send_client_state.run_if(send_timer_finished),
+                    )
+                        .run_if(in_state(GameState::Playing)),
+                )
+                    .chain()
+                    .run_if(is_connected),
+            )
+                .chain(),
+        );
+}
+
+/// The ID this client is known by to the host and other clients
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Resource)]
+pub(crate) struct LocalClientId(pub(crate) u64);
+
+/// Positions the local player was predicted to be at, by the tick of the state they were sent with
+#[derive(Debug, Clone, PartialEq, Resource, Default)]
+struct PredictionHistory {
+    tick: u32,
+    translations: VecDeque<(u32, Vec3)>,
+}
+
+/// The name last sent to the host, so that it is only sent again when it changes
+#[derive(Debug, Clone, PartialEq, Eq, Resource, Default)]
+struct IntroducedName(Option<String>);
+
+fn is_connected(client: Option<Res<RenetClient>>) -> bool {
+    client.is_some_and(|client| client.is_connected())
+}
+
+#[sysfail(log(level = "error"))]
+fn join_session(
+    mut commands: Commands,
+    mut join_requests: EventReader<JoinSessionRequest>,
+) -> Result<()> {
+    let Some(request) = join_requests.read().last() else {
+        return Ok(());
+    };
+    let socket = UdpSocket::bind(SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0))
+        .context("Failed to open a socket")?;
+    let client_id = rand::random();
+    let authentication = ClientAuthentication::Unsecure {
+        protocol_id: PROTOCOL_ID,
+        client_id,
+        server_addr: request.address,
+        user_data: None,
+    };
+    let current_time = SystemTime::now().duration_since(UNIX_EPOCH)?;
+    let transport = NetcodeClientTransport::new(current_time, authentication, socket)
+        .with_context(|| format!("Failed to connect to {}", request.address))?;
+    commands.insert_resource(transport);
+    commands.insert_resource(RenetClient::new(ConnectionConfig::default()));
+    commands.insert_resource(LocalClientId(client_id));
+    commands.insert_resource(PredictionHistory::default());
+    commands.insert_resource(IntroducedName::default());
+    info!("Connecting to {}", request.address);
+    Ok(())
+}
+
+fn receive_server_messages(
+    time: Res<Time<Real>>,
+    mut client: ResMut<RenetClient>,
+    local_client_id: Res<LocalClientId>,
+    mut remote_players: ResMut<RemotePlayers>,
+    mut names: ResMut<PlayerNames>,
+    mut chat_log: ResMut<ChatLog>,
+    mut history: ResMut<PredictionHistory>,
+    mut players: Query<&mut Transform, With<Player>>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut interaction_events: EventWriter<RemoteInteractionEvent>,
+) {
+    let now = time.elapsed_seconds_f64();
+    for channel in [UNRELIABLE, RELIABLE] {
+        while let Some(bytes) = client.receive_message(channel) {
+            let message = match decode::<ServerMessage>(&bytes) {
+                Ok(message) => message,
+                Err(error) => {
+                    warn!("Ignoring message from host: {error:?}");
+                    continue;
+                }
+            };
+            match message {
+                ServerMessage::Welcome { level } => {
+                    info!("Joined session in level {level}");
+                    current_level.name = level;
+                    current_level.spawn_point = None;
+                    remote_players.0.clear();
+                    next_state.set(GameState::Loading);
+                }
+                ServerMessage::Snapshot { players: states } => {
+                    for (client_id, state) in states {
+                        if client_id != local_client_id.0 {
+                            remote_players.receive(client_id, state, now);
+                        }
+                    }
+                }
+                ServerMessage::Correction { tick, translation } => {
+                    let Ok(mut transform) = players.get_single_mut() else {
+                        continue;
+                    };
+                    history.reconcile(tick, translation, &mut transform);
+                }
+                ServerMessage::Interaction {
+                    client_id,
+                    interaction,
+                } => {
+                    interaction_events.send(RemoteInteractionEvent {
+                        client_id,
+                        interaction,
+                    });
+                }
+                ServerMessage::PlayerLeft { client_id } => {
+                    remote_players.remove(client_id);
+                }
+                ServerMessage::Names(received) => {
+                    names.0 = received.into_iter().collect();
+                }
+                ServerMessage::Chat { client_id, text } => {
+                    chat_log.push(client_id, text);
+                }
+            }
+        }
+    }
+}
+
+impl PredictionHistory {
+    fn record(&mut self, translation: Vec3) -> u32 {
+        self.tick += 1;
+        self.translations.push_back((self.tick, translation));
+        while self.translations.len() > HISTORY_LENGTH {
+            self.translations.pop_front();
+        }
+        self.tick
+    }
+
+    /// Moves the player by how far the prediction for `tick` was off, and shifts all later predictions with it.
+    fn reconcile(&mut self, tick: u32, corrected: Vec3, transform: &mut Transform) {
+        let Some(predicted) = self
+            .translations
+            .iter()
+            .find(|(predicted_tick, _)| *predicted_tick == tick)
+            .map(|(_, translation)| *translation)
+        else {
+            // Too old to reconcile, so the player is put where the host last saw them
+            transform.translation = corrected;
+            self.translations.clear();
+            return;
+        };
+        let offset = corrected - predicted;
+        transform.translation += offset;
+        for (predicted_tick, translation) in self.translations.iter_mut() {
+            if *predicted_tick >= tick {
+                *translation += offset;
+            }
+        }
+    }
+}
+
+fn send_client_state(
+    mut client: ResMut<RenetClient>,
+    mut history: ResMut<PredictionHistory>,
+    players: Query<(&Transform, Option<&AnimationState>), With<Player>>,
+) {
+    let Some(mut state) = local_player_state(&players, 0) else {
+        return;
+    };
+    state.tick = history.record(state.translation);
+    client.send_message(UNRELIABLE, encode(&ClientMessage::State(state)));
+}
+
+fn introduce(
+    mut client: ResMut<RenetClient>,
+    settings: Res<Settings>,
+    mut introduced: ResMut<IntroducedName>,
+) {
+    if introduced.0.as_ref() == Some(&settings.player_name) {
+        return;
+    }
+    let name = settings.player_name.clone();
+    client.send_message(
+        RELIABLE,
+        encode(&ClientMessage::Introduce { name: name.clone() }),
+    );
+    introduced.0 = Some(name);
+}
+
+fn send_client_chat(
+    mut client: ResMut<RenetClient>,
+    local_client_id: Res<LocalClientId>,
+    mut chat_events: EventReader<SendChatMessage>,
+    mut chat_log: ResMut<ChatLog>,
+) {
+    for event in chat_events.read() {
+        let Some(text) = sanitize_message(&event.text) else {
+            continue;
+        };
+        client.send_message(RELIABLE, encode(&ClientMessage::Chat(text.clone())));
+        chat_log.push(local_client_id.0, text);
+    }
+}
+
+fn send_client_interactions(
+    mut client: ResMut<RenetClient>,
+    mut spawn_events: EventReader<SpawnProjectileEvent>,
+    players: Query<(Entity, &ActionState<PlayerAction>), With<Player>>,
+    interaction_opportunity: Res<InteractionOpportunity>,
+    names: Query<&Name>,
+) {
+    for interaction in local_interactions(
+        &mut spawn_events,
+        &players,
+        &interaction_opportunity,
+        &names,
+    ) {
+        client.send_message(RELIABLE, encode(&ClientMessage::Interaction(interaction)));
+    }
+}
@@ -0,0 +1,281 @@
+use crate::{
+    combat::projectiles::SpawnProjectileEvent,
+    level_instantiation::levels::CurrentLevel,
+    menu::Settings,
+    movement::character_controller::AnimationState,
+    network::{
+        chat::{sanitize_message, ChatLog, SendChatMessage},
+        decode, encode, local_interactions, local_player_state, sanitize_name, send_timer_finished,
+        ClientMessage, HostSessionRequest, PlayerNames, PlayerState, RemoteInteractionEvent,
+        RemotePlayers, ServerMessage, HOST_ID, PROTOCOL_ID, RELIABLE, SEND_INTERVAL, UNRELIABLE,
+    },
+    player_control::{actions::PlayerAction, player_embodiment::Player},
+    world_interaction::interactions_ui::InteractionOpportunity,
+};
+use anyhow::{Context, Result};
+use bevy::prelude::*;
+use bevy_mod_sysfail::*;
+use bevy_renet::renet::{
+    transport::{NetcodeServerTransport, ServerAuthentication, ServerConfig},
+    ConnectionConfig, RenetServer, ServerEvent,
+};
+use leafwing_input_manager::prelude::ActionState;
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const MAX_CLIENTS: usize = 8;
+/// Meters per second no character can exceed, including vehicles and knockback
+const MAX_SPEED: f32 = 40.;
+/// Extra distance allowed per state to account for states arriving in bursts
+const MOVEMENT_TOLERANCE: f32 = 1.;
+
+/// Runs the listen server while hosting: relays every client's state, interactions and chat messages to all others,
+/// sends the host's own state along with them, and rejects movement that is faster than [`MAX_SPEED`].
+/// Keeps everyone's [`PlayerNames`] up to date.
+pub(super) fn server_plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (
+            host_session,
+            (
+                handle_server_events,
+                receive_client_messages,
+                update_host_name,
+                broadcast_names.run_if(resource_changed::<PlayerNames>()),
+                send_host_interactions,
+                send_host_chat,
+                send_snapshots.run_if(send_timer_finished),
+            )
+                .chain()
+                .run_if(resource_exists::<RenetServer>()),
+        )
+            .chain(),
+    );
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Resource, Default)]
+struct HostTick(u32);
+
+#[sysfail(log(level = "error"))]
+fn host_session(
+    mut commands: Commands,
+    mut host_requests: EventReader<HostSessionRequest>,
+) -> Result<()> {
+    let Some(request) = host_requests.read().last() else {
+        return Ok(());
+    };
+    let socket = UdpSocket::bind(SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), request.port))
+        .with_context(|| format!("Failed to open port {}", request.port))?;
+    // Clients have to connect to one of these addresses
+    let mut public_addresses = vec![SocketAddr::new(Ipv4Addr::LOCALHOST.into(), request.port)];
+    if let Some(address) = local_network_address() {
+        public_addresses.push(SocketAddr::new(address, request.port));
+    }
+    let config = ServerConfig {
+        current_time: SystemTime::now().duration_since(UNIX_EPOCH)?,
+        max_clients: MAX_CLIENTS,
+        protocol_id: PROTOCOL_ID,
+        public_addresses: public_addresses.clone(),
+        authentication: ServerAuthentication::Unsecure,
+    };
+    let transport =
+        NetcodeServerTransport::new(config, socket).context("Failed to start the server")?;
+    commands.insert_resource(transport);
+    commands.insert_resource(RenetServer::new(ConnectionConfig::default()));
+    commands.init_resource::<HostTick>();
+    info!("Hosting a session at {public_addresses:?}");
+    Ok(())
+}
+
+/// The address of this machine in the local network
+fn local_network_address() -> Option<IpAddr> {
+    // Connecting a UDP socket sends nothing, but makes the OS pick the interface that routes outside
+    let socket = UdpSocket::bind(SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0)).ok()?;
+    socket.connect("8.8.8.8:80").ok()?;
+    Some(socket.local_addr().ok()?.ip())
+}
+
+fn handle_server_events(
+    mut server_events: EventReader<ServerEvent>,
+    mut server: ResMut<RenetServer>,
+    current_level: Res<CurrentLevel>,
+    mut remote_players: ResMut<RemotePlayers>,
+    mut names: ResMut<PlayerNames>,
+) {
+    // Clients follow the host into other levels
+    if current_level.is_changed() {
+        let welcome = ServerMessage::Welcome {
+            level: current_level.name.clone(),
+        };
+        server.broadcast_message(RELIABLE, encode(&welcome));
+    }
+    for event in server_events.read() {
+        match event {
+            ServerEvent::ClientConnected { client_id } => {
+                info!("Player {client_id} joined");
+                let welcome = ServerMessage::Welcome {
+                    level: current_level.name.clone(),
+                };
+                server.send_message(*client_id, RELIABLE, encode(&welcome));
+            }
+            ServerEvent::ClientDisconnected { client_id, reason } => {
+                info!("Player {client_id} left: {reason:?}");
+                remote_players.remove(client_id.raw());
+                names.0.remove(&client_id.raw());
+                let left = ServerMessage::PlayerLeft {
+                    client_id: client_id.raw(),
+                };
+                server.broadcast_message(RELIABLE, encode(&left));
+            }
+        }
+    }
+}
+
+fn receive_client_messages(
+    time: Res<Time<Real>>,
+    mut server: ResMut<RenetServer>,
+    mut remote_players: ResMut<RemotePlayers>,
+    mut names: ResMut<PlayerNames>,
+    mut chat_log: ResMut<ChatLog>,
+    mut interaction_events: EventWriter<RemoteInteractionEvent>,
+) {
+    let now = time.elapsed_seconds_f64();
+    for client_id in server.clients_id() {
+        for channel in [UNRELIABLE, RELIABLE] {
+            while let Some(bytes) = server.receive_message(client_id, channel) {
+                let message = match decode::<ClientMessage>(&bytes) {
+                    Ok(message) => message,
+                    Err(error) => {
+                        warn!("Ignoring message from player {client_id}: {error:?}");
+                        continue;
+                    }
+                };
+                match message {
+                    ClientMessage::State(state) => {
+                        let previous = remote_players.latest(client_id.raw());
+                        if let Some(previous) =
+                            previous.filter(|previous| !is_plausible(previous, &state))
+                        {
+                            let correction = ServerMessage::Correction {
+                                tick: state.tick,
+                                translation: previous.translation,
+                            };
+                            server.send_message(client_id, RELIABLE, encode(&correction));
+                            continue;
+                        }
+                        remote_players.receive(client_id.raw(), state, now);
+                    }
+                    ClientMessage::Interaction(interaction) => {
+                        let relayed = ServerMessage::Interaction {
+                            client_id: client_id.raw(),
+                            interaction: interaction.clone(),
+                        };
+                        server.broadcast_message_except(client_id, RELIABLE, encode(&relayed));
+                        interaction_events.send(RemoteInteractionEvent {
+                            client_id: client_id.raw(),
+                            interaction,
+                        });
+                    }
+                    ClientMessage::Introduce { name } => {
+                        names.0.insert(client_id.raw(), sanitize_name(&name));
+                    }
+                    ClientMessage::Chat(text) => {
+                        let Some(text) = sanitize_message(&text) else {
+                            continue;
+                        };
+                        // The sender already shows their own message
+                        let relayed = ServerMessage::Chat {
+                            client_id: client_id.raw(),
+                            text: text.clone(),
+                        };
+                        server.broadcast_message_except(client_id, RELIABLE, encode(&relayed));
+                        chat_log.push(client_id.raw(), text);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Whether a character can have moved from `previous` to `next` in the time between them.
+fn is_plausible(previous: &PlayerState, next: &PlayerState) -> bool {
+    let elapsed = next.tick.saturating_sub(previous.tick) as f32 * SEND_INTERVAL;
+    let distance = previous.translation.distance(next.translation);
+    distance <= MAX_SPEED * elapsed + MOVEMENT_TOLERANCE
+}
+
+fn update_host_name(settings: Res<Settings>, mut names: ResMut<PlayerNames>) {
+    let name = sanitize_name(&settings.player_name);
+    if names.0.get(&HOST_ID) != Some(&name) {
+        names.0.insert(HOST_ID, name);
+    }
+}
+
+fn broadcast_names(mut server: ResMut<RenetServer>, names: Res<PlayerNames>) {
+    let names = names
+        .0
+        .iter()
+        .map(|(client_id, name)| (*client_id, name.clone()))
+        .collect();
+    server.broadcast_message(RELIABLE, encode(&ServerMessage::Names(names)));
+}
+
+fn send_host_interactions(
+    mut server: ResMut<RenetServer>,
+    mut spawn_events: EventReader<SpawnProjectileEvent>,
+    players: Query<(Entity, &ActionState<PlayerAction>), With<Player>>,
+    interaction_opportunity: Res<InteractionOpportunity>,
+    names: Query<&Name>,
+) {
+    for interaction in local_interactions(
+        &mut spawn_events,
+        &players,
+        &interaction_opportunity,
+        &names,
+    ) {
+        let message = ServerMessage::Interaction {
+            client_id: HOST_ID,
+            interaction,
+        };
+        server.broadcast_message(RELIABLE, encode(&message));
+    }
+}
+
+fn send_host_chat(
+    mut server: ResMut<RenetServer>,
+    mut chat_events: EventReader<SendChatMessage>,
+    mut chat_log: ResMut<ChatLog>,
+) {
+    for event in chat_events.read() {
+        let Some(text) = sanitize_message(&event.text) else {
+            continue;
+        };
+        let message = ServerMessage::Chat {
+            client_id: HOST_ID,
+            text: text.clone(),
+        };
+        server.broadcast_message(RELIABLE, encode(&message));
+        chat_log.push(HOST_ID, text);
+    }
+}
+
+fn send_snapshots(
+    mut server: ResMut<RenetServer>,
+    mut tick: ResMut<HostTick>,
+    remote_players: Res<RemotePlayers>,
+    players: Query<(&Transform, Option<&AnimationState>), With<Player>>,
+) {
+    tick.0 += 1;
+    let clients = server.clients_id();
+    let mut states: Vec<_> = clients
+        .iter()
+        .filter_map(|client_id| Some((client_id.raw(), remote_players.latest(client_id.raw())?)))
+        .collect();
+    if let Some(host_state) = local_player_state(&players, tick.0) {
+        states.push((HOST_ID, host_state));
+    }
+    let snapshot = ServerMessage::Snapshot { players: states };
+    server.broadcast_message(UNRELIABLE, encode(&snapshot));
+}
@@ -1,28 +1,55 @@
 use crate::{
+    combat::projectiles::{ProjectileBounceEvent, ProjectileHitEvent},
+    despawn::{DespawnOnExit, Lifetime},
     file_system_interaction::config::GameConfig,
+    movement::{
+        character_controller::{AnimationEvent, AnimationEventKind},
+        ropes::JointBrokenEvent,
+        wind::{wind_at, WindZone},
+    },
     player_control::player_embodiment::Player,
-    util::trait_extension::{F32Ext, Vec3Ext},
+    quality::CurrentQuality,
+    rng::{GameRng, RngStream},
+    util::{
+        pool::{pool_plugin, Pool, Poolable},
+        trait_extension::{F32Ext, Vec3Ext},
+    },
     GameState,
 };
 use anyhow::Result;
-use bevy::prelude::*;
+use bevy::{pbr::NotShadowReceiver, prelude::*};
 use bevy_hanabi::prelude::*;
 use bevy_mod_sysfail::sysfail;
 use bevy_tnua::prelude::*;
-use bevy_xpbd_3d::PhysicsSet;
 pub(crate) use creation::*;
+use rand::Rng;
+use seldom_fn_plugin::FnPluginExt;
+use std::f32::consts::TAU;
 
 mod creation;
 
 /// Handles particle effects instantiation and playing.
+/// Frequent one-shot effects like [`ImpactParticle`]s are pooled, rare ones like the burst of a snapping joint
+/// are spawned with a [`Lifetime`]. Lower graphics qualities spawn fewer of them and turn off the sprint effect.
 pub(crate) fn particle_plugin(app: &mut App) {
     app.register_type::<SprintingParticle>()
+        .register_type::<ImpactParticle>()
         .add_plugins(HanabiPlugin)
+        .fn_plugin(pool_plugin::<ImpactParticle>)
         .add_systems(
             Update,
-            play_sprinting_effect
-                .run_if(in_state(GameState::Playing))
-                .after(PhysicsSet::Sync),
+            (
+                play_sprinting_effect,
+                blow_particles,
+                (
+                    spawn_impact_particles,
+                    replay_impact_particles,
+                    expire_impact_particles,
+                )
+                    .chain(),
+                spawn_snap_particles,
+            )
+                .run_if(in_state(GameState::Playing)),
         );
 }
 
@@ -35,6 +62,7 @@ fn play_sprinting_effect(
     with_player: Query<&TnuaController, With<Player>>,
     mut with_particle: Query<&mut EffectSpawner, With<SprintingParticle>>,
     config: Res<GameConfig>,
+    quality: Res<CurrentQuality>,
 ) -> Result<()> {
     for controller in with_player.iter() {
         let Some((_, basis_state)) = controller.concrete_basis::<TnuaBuiltinWalk>() else {
@@ -44,9 +72,155 @@ fn play_sprinting_effect(
         for mut effect_spawner in with_particle.iter_mut() {
             let threshold = config.player.sprint_effect_speed_threshold;
             let active = !controller.is_airborne().unwrap_or_default()
-                && horizontal_speed_squared > threshold.squared();
+                && horizontal_speed_squared > threshold.squared()
+                && quality.particle_density() >= 0.5;
             effect_spawner.set_active(active);
         }
     }
     Ok(())
 }
+
+/// A burst of dust where a projectile hit something, or where an animation has an `Effect("impact")` marker.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Default)]
+#[reflect(Component)]
+pub(crate) struct ImpactParticle {
+    /// Seconds until the burst has faded out and the effect can be reused
+    remaining: f32,
+}
+
+impl Poolable for ImpactParticle {}
+
+fn spawn_impact_particles(
+    mut commands: Commands,
+    mut hit_events: EventReader<ProjectileHitEvent>,
+    mut bounce_events: EventReader<ProjectileBounceEvent>,
+    mut animation_events: EventReader<AnimationEvent>,
+    transforms: Query<&GlobalTransform>,
+    mut pool: ResMut<Pool<ImpactParticle>>,
+    mut effect: Local<Option<Handle<EffectAsset>>>,
+    mut effects: ResMut<Assets<EffectAsset>>,
+    quality: Res<CurrentQuality>,
+    mut density: Local<f32>,
+    mut rng: ResMut<GameRng>,
+) {
+    let rng = rng.stream(RngStream::Particles);
+    let hits = hit_events.read().map(|event| (event.point, event.normal));
+    let bounces = bounce_events
+        .read()
+        .map(|event| (event.point, event.normal));
+    let animated = animation_events
+        .read()
+        .filter(|event| matches!(&event.kind, AnimationEventKind::Effect(name) if name == "impact"))
+        .filter_map(|event| transforms.get(event.entity).ok())
+        .map(|transform| (transform.translation(), Vec3::Y));
+    for (point, normal) in hits.chain(bounces).chain(animated) {
+        if !should_spawn(&mut density, &quality) {
+            continue;
+        }
+        let impact = ImpactParticle {
+            remaining: IMPACT_LIFETIME,
+        };
+        let entity = pool.acquire(&mut commands, impact, |commands, impact| {
+            let effect = effect
+                .get_or_insert_with(|| create_impact_effect(&mut effects))
+                .clone();
+            commands
+                .spawn((
+                    Name::new("Impact particle"),
+                    impact,
+                    ParticleEffectBundle {
+                        effect: ParticleEffect::new(effect),
+                        ..default()
+                    },
+                    NotShadowReceiver,
+                ))
+                .id()
+        });
+        // Turned randomly around the normal so that bursts at the same spot do not look identical
+        let up = Quat::from_axis_angle(normal, rng.gen_range(0.0..TAU))
+            * normal.any_orthonormal_vector();
+        commands
+            .entity(entity)
+            .insert(Transform::from_translation(point).looking_to(normal, up));
+    }
+}
+
+/// Spawns every n-th effect according to the particle density of the current quality.
+/// `accumulated` carries the fraction of an effect over to the next call.
+fn should_spawn(accumulated: &mut f32, quality: &CurrentQuality) -> bool {
+    *accumulated += quality.particle_density();
+    if *accumulated < 1. {
+        return false;
+    }
+    *accumulated -= 1.;
+    true
+}
+
+/// A reused effect has already fired its burst, so it needs to be restarted
+fn replay_impact_particles(mut spawners: Query<&mut EffectSpawner, Added<ImpactParticle>>) {
+    for mut spawner in spawners.iter_mut() {
+        spawner.reset();
+    }
+}
+
+fn expire_impact_particles(
+    mut commands: Commands,
+    time: Res<Time<Virtual>>,
+    mut impacts: Query<(Entity, &mut ImpactParticle)>,
+    mut pool: ResMut<Pool<ImpactParticle>>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, mut impact) in impacts.iter_mut() {
+        impact.remaining -= dt;
+        if impact.remaining <= 0. {
+            pool.release(&mut commands, entity);
+        }
+    }
+}
+
+fn spawn_snap_particles(
+    mut commands: Commands,
+    mut broken_events: EventReader<JointBrokenEvent>,
+    mut effect: Local<Option<Handle<EffectAsset>>>,
+    mut effects: ResMut<Assets<EffectAsset>>,
+    quality: Res<CurrentQuality>,
+    mut density: Local<f32>,
+) {
+    for event in broken_events.read() {
+        if !should_spawn(&mut density, &quality) {
+            continue;
+        }
+        let effect = effect
+            .get_or_insert_with(|| create_impact_effect(&mut effects))
+            .clone();
+        commands.spawn((
+            Name::new("Snap particle"),
+            ParticleEffectBundle {
+                effect: ParticleEffect::new(effect),
+                transform: Transform::from_translation(event.position),
+                ..default()
+            },
+            NotShadowReceiver,
+            Lifetime::from_seconds(IMPACT_LIFETIME),
+            DespawnOnExit(GameState::Playing),
+        ));
+    }
+}
+
+/// Name of the property through which effects can receive the wind at their position as an acceleration
+pub(crate) const WIND_PROPERTY: &str = "wind";
+
+/// How strongly particles are accelerated per m/s of wind
+const PARTICLE_WIND_FACTOR: f32 = 0.5;
+
+fn blow_particles(
+    time: Res<Time<Virtual>>,
+    zones: Query<(&WindZone, &GlobalTransform)>,
+    mut effects: Query<(&mut EffectProperties, &GlobalTransform)>,
+) {
+    let elapsed = time.elapsed_seconds();
+    for (mut properties, transform) in effects.iter_mut() {
+        let wind = wind_at(&zones, transform.translation(), elapsed) * PARTICLE_WIND_FACTOR;
+        properties.set(WIND_PROPERTY, wind.into());
+    }
+}
@@ -1,7 +1,13 @@
-use crate::{level_instantiation::spawning::objects::player, particles::SprintingParticle};
+use crate::{
+    level_instantiation::spawning::objects::player,
+    particles::{SprintingParticle, WIND_PROPERTY},
+};
 use bevy::{pbr::NotShadowReceiver, prelude::*};
 use bevy_hanabi::prelude::*;
 
+/// How long particles of an impact burst live
+pub(crate) const IMPACT_LIFETIME: f32 = 0.6;
+
 pub(crate) fn create_sprint_particle_bundle(effects: &mut Assets<EffectAsset>) -> impl Bundle {
     let sprinting = create_sprinting_effect(effects);
     (
@@ -47,7 +53,9 @@ fn create_sprinting_effect(effects: &mut Assets<EffectAsset>) -> ParticleEffect
         mode: OrientMode::FaceCameraPosition,
         rotation: None,
     };
-    let accel_modifier = AccelModifier::new(module.lit(Vec3::new(0., 1., 0.)));
+    let rise = module.lit(Vec3::new(0., 1., 0.));
+    let wind = module.prop(WIND_PROPERTY);
+    let accel_modifier = AccelModifier::new(module.add(rise, wind));
 
     ParticleEffect::new(
         effects.add(
@@ -57,6 +65,7 @@ fn create_sprinting_effect(effects: &mut Assets<EffectAsset>) -> ParticleEffect
                 module,
             )
             .with_name("Sprint")
+            .with_property(WIND_PROPERTY, Vec3::ZERO.into())
             .init(position_circle_modifier)
             .init(velocity_sphere_modifier)
             .init(lifetime)
@@ -73,3 +82,54 @@ fn create_sprinting_effect(effects: &mut Assets<EffectAsset>) -> ParticleEffect
         ),
     )
 }
+
+pub(crate) fn create_impact_effect(effects: &mut Assets<EffectAsset>) -> Handle<EffectAsset> {
+    let mut color_gradient = Gradient::new();
+    color_gradient.add_key(0.0, Vec4::new(0.9, 0.85, 0.8, 0.8));
+    color_gradient.add_key(1.0, Vec4::new(0.9, 0.85, 0.8, 0.0));
+
+    let mut size_gradient = Gradient::new();
+    size_gradient.add_key(0.0, Vec2::splat(0.05));
+    size_gradient.add_key(1.0, Vec2::splat(0.12));
+
+    let mut module = Module::default();
+    let position_sphere_modifier = SetPositionSphereModifier {
+        center: module.lit(Vec3::ZERO),
+        radius: module.lit(0.05),
+        dimension: ShapeDimension::Volume,
+    };
+    // The effect's entity looks along the hit normal, so a center behind it sprays the burst away from the surface
+    let velocity_sphere_modifier = SetVelocitySphereModifier {
+        center: module.lit(Vec3::Z * 0.1),
+        speed: module.lit(2.0),
+    };
+    let lifetime = SetAttributeModifier::new(Attribute::LIFETIME, module.lit(IMPACT_LIFETIME));
+    let linear_drag_modifier = LinearDragModifier {
+        drag: module.lit(4.0),
+    };
+    let gravity = module.lit(Vec3::new(0., -4., 0.));
+    let wind = module.prop(WIND_PROPERTY);
+    let accel_modifier = AccelModifier::new(module.add(gravity, wind));
+
+    effects.add(
+        EffectAsset::new(32, Spawner::once(16.0.into(), true), module)
+            .with_name("Impact")
+            .with_property(WIND_PROPERTY, Vec3::ZERO.into())
+            .init(position_sphere_modifier)
+            .init(velocity_sphere_modifier)
+            .init(lifetime)
+            .update(linear_drag_modifier)
+            .update(accel_modifier)
+            .render(OrientModifier {
+                mode: OrientMode::FaceCameraPosition,
+                rotation: None,
+            })
+            .render(ColorOverLifetimeModifier {
+                gradient: color_gradient,
+            })
+            .render(SizeOverLifetimeModifier {
+                gradient: size_gradient,
+                screen_space_size: false,
+            }),
+    )
+}
@@ -0,0 +1,55 @@
+#[cfg(feature = "discord")]
+use crate::platform::discord::discord_plugin;
+#[cfg(feature = "steam")]
+use crate::platform::steam::steam_plugin;
+use crate::{level_instantiation::levels::CurrentLevel, GameState};
+use bevy::prelude::*;
+#[cfg(any(feature = "steam", feature = "discord"))]
+use seldom_fn_plugin::FnPluginExt;
+
+#[cfg(feature = "discord")]
+pub(crate) mod discord;
+#[cfg(feature = "steam")]
+pub(crate) mod steam;
+
+/// Handles integration with the store or launcher the game was started from.
+/// Gameplay code only sends platform-agnostic events like [`AchievementUnlocked`], which the integrations pick up.
+/// Without any integration enabled, they are only logged.
+/// The integrations are behind features:
+/// - [`steam_plugin`] with the `steam` feature: achievements, cloud saves and rich presence through Steamworks.
+/// - [`discord_plugin`] with the `discord` feature: rich presence on Discord.
+///
+/// Must be added after the [`file_system_interaction_plugin`](crate::file_system_interaction::file_system_interaction_plugin),
+/// since integrations may wrap its [`GameStorage`](crate::file_system_interaction::storage::GameStorage).
+pub(crate) fn platform_plugin(app: &mut App) {
+    app.add_event::<AchievementUnlocked>()
+        .add_systems(Update, log_achievements);
+    #[cfg(feature = "steam")]
+    app.fn_plugin(steam_plugin);
+    #[cfg(feature = "discord")]
+    app.fn_plugin(discord_plugin);
+}
+
+/// Unlocks the achievement with the given ID as configured on the platform, e.g. "FIRST_DIALOG".
+/// Unlocking an achievement twice is harmless.
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub(crate) struct AchievementUnlocked {
+    pub(crate) id: String,
+}
+
+fn log_achievements(mut achievements: EventReader<AchievementUnlocked>) {
+    for achievement in achievements.read() {
+        info!("Unlocked achievement {}", achievement.id);
+    }
+}
+
+/// What the player is doing right now, as shown to their friends.
+#[cfg_attr(not(any(feature = "steam", feature = "discord")), allow(dead_code))]
+pub(crate) fn presence_status(state: &GameState, current_level: &CurrentLevel) -> String {
+    match state {
+        GameState::Loading | GameState::Playing => format!("Exploring {}", current_level.name),
+        GameState::InitialLoading | GameState::Menu | GameState::Error => {
+            "In the main menu".to_string()
+        }
+    }
+}
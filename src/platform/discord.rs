@@ -0,0 +1,76 @@
+use crate::{
+    file_system_interaction::storage, level_instantiation::levels::CurrentLevel,
+    platform::presence_status, GameState,
+};
+use anyhow::{anyhow, Context, Result};
+use bevy::prelude::*;
+use bevy_mod_sysfail::*;
+use discord_rich_presence::{activity, DiscordIpc, DiscordIpcClient};
+
+/// The ID of the application registered at <https://discord.com/developers/applications>, set when building.
+/// Discord shows the application's name as the game being played.
+const APPLICATION_ID: Option<&str> = option_env!("DISCORD_APPLICATION_ID");
+
+/// Shows what the player is doing and for how long they have been playing in their Discord status.
+/// The status is updated whenever the game state or the level changes.
+/// Needs the `DISCORD_APPLICATION_ID` environment variable to be set at build time.
+/// Does nothing but log a warning if Discord is not running.
+pub(crate) fn discord_plugin(app: &mut App) {
+    let client = match connect() {
+        Ok(client) => client,
+        Err(error) => {
+            warn!("Failed to connect to Discord, continuing without rich presence: {error:#}");
+            return;
+        }
+    };
+    // The IPC socket is only ever touched from the main thread
+    app.insert_non_send_resource(DiscordClient {
+        client,
+        started: storage::timestamp() as i64,
+    })
+    .add_systems(
+        Update,
+        update_rich_presence
+            .run_if(state_changed::<GameState>().or_else(resource_changed::<CurrentLevel>())),
+    )
+    .add_systems(Last, disconnect.run_if(on_event::<bevy::app::AppExit>()));
+}
+
+struct DiscordClient {
+    client: DiscordIpcClient,
+    /// Unix timestamp of when the game was started, from which Discord counts the playtime
+    started: i64,
+}
+
+fn connect() -> Result<DiscordIpcClient> {
+    let application_id =
+        APPLICATION_ID.context("The game was built without DISCORD_APPLICATION_ID")?;
+    // The crate's errors are not `Send`, so they cannot be turned into `anyhow` errors directly
+    let mut client = DiscordIpcClient::new(application_id).map_err(|error| anyhow!("{error}"))?;
+    client.connect().map_err(|error| anyhow!("{error}"))?;
+    Ok(client)
+}
+
+#[sysfail(log(level = "error"))]
+fn update_rich_presence(
+    state: Res<State<GameState>>,
+    current_level: Res<CurrentLevel>,
+    mut discord: NonSendMut<DiscordClient>,
+) -> Result<()> {
+    let status = presence_status(state.get(), &current_level);
+    let started = discord.started;
+    let activity = activity::Activity::new()
+        .state(&status)
+        .timestamps(activity::Timestamps::new().start(started));
+    discord
+        .client
+        .set_activity(activity)
+        .map_err(|error| anyhow!("Failed to set Discord rich presence: {error}"))?;
+    Ok(())
+}
+
+fn disconnect(mut discord: NonSendMut<DiscordClient>) {
+    if let Err(error) = discord.client.close() {
+        warn!("Failed to disconnect from Discord: {error}");
+    }
+}
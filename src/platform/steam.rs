@@ -0,0 +1,162 @@
+use crate::{
+    file_system_interaction::{
+        game_state_serialization::SAVE_DIRECTORY,
+        storage::{GameStorage, Storage, StoredEntry},
+    },
+    level_instantiation::levels::CurrentLevel,
+    platform::{presence_status, AchievementUnlocked},
+    GameState,
+};
+use anyhow::{Context, Result};
+use bevy::prelude::*;
+use std::{
+    io::{Read, Write},
+    time::{Duration, UNIX_EPOCH},
+};
+use steamworks::{Client, SingleClient};
+
+/// Connects to a running Steam client. Does nothing but log a warning if Steam is not running,
+/// so that builds with the `steam` feature can still be started from elsewhere. During development,
+/// a `steam_appid.txt` containing the app ID has to be next to the executable.
+/// - [`AchievementUnlocked`] events unlock the achievement with the same API name.
+/// - Save files are mirrored into Steam Cloud when written. On startup, saves from the cloud that are newer than the local ones are downloaded.
+/// - The rich presence `status` shows whether the player is in the menu or which level they are in.
+pub(crate) fn steam_plugin(app: &mut App) {
+    let (client, single_client) = match Client::init() {
+        Ok(clients) => clients,
+        Err(error) => {
+            warn!("Failed to connect to Steam, continuing without it: {error}");
+            return;
+        }
+    };
+    client.user_stats().request_current_stats();
+    if let Some(storage) = app.world.remove_resource::<GameStorage>() {
+        let storage = SteamCloudStorage {
+            local: storage.into_inner(),
+            client: client.clone(),
+        };
+        if let Err(error) = storage.download_newer_saves() {
+            error!("Failed to sync saves from Steam Cloud: {error:?}");
+        }
+        app.insert_resource(GameStorage::new(storage));
+    }
+    app.insert_resource(SteamClient(client))
+        .insert_non_send_resource(single_client)
+        .add_systems(
+            Update,
+            (
+                run_steam_callbacks,
+                unlock_achievements,
+                update_rich_presence.run_if(
+                    state_changed::<GameState>().or_else(resource_changed::<CurrentLevel>()),
+                ),
+            ),
+        );
+}
+
+#[derive(Resource, Deref)]
+struct SteamClient(Client);
+
+fn run_steam_callbacks(single_client: NonSend<SingleClient>) {
+    single_client.run_callbacks();
+}
+
+fn unlock_achievements(
+    mut achievements: EventReader<AchievementUnlocked>,
+    client: Res<SteamClient>,
+) {
+    let mut unlocked_any = false;
+    for achievement in achievements.read() {
+        if client
+            .user_stats()
+            .achievement(&achievement.id)
+            .set()
+            .is_err()
+        {
+            warn!("Failed to unlock Steam achievement {}", achievement.id);
+            continue;
+        }
+        unlocked_any = true;
+    }
+    // Achievements are only shown to the player once stored
+    if unlocked_any && client.user_stats().store_stats().is_err() {
+        warn!("Failed to store Steam stats");
+    }
+}
+
+fn update_rich_presence(
+    state: Res<State<GameState>>,
+    current_level: Res<CurrentLevel>,
+    client: Res<SteamClient>,
+) {
+    let status = presence_status(state.get(), &current_level);
+    if !client.friends().set_rich_presence("status", Some(&status)) {
+        warn!("Failed to set Steam rich presence");
+    }
+}
+
+/// Writes to the wrapped storage and mirrors save files into Steam Cloud.
+/// Cloud files are named like the save file, without the directory.
+struct SteamCloudStorage {
+    local: Box<dyn Storage>,
+    client: Client,
+}
+
+impl SteamCloudStorage {
+    fn cloud_enabled(&self) -> bool {
+        let remote_storage = self.client.remote_storage();
+        remote_storage.is_cloud_enabled_for_account() && remote_storage.is_cloud_enabled_for_app()
+    }
+
+    fn download_newer_saves(&self) -> Result<()> {
+        if !self.cloud_enabled() {
+            return Ok(());
+        }
+        let local_saves = self.local.list(SAVE_DIRECTORY)?;
+        let remote_storage = self.client.remote_storage();
+        for info in remote_storage.files() {
+            let file = remote_storage.file(&info.name);
+            let modified = UNIX_EPOCH + Duration::from_secs(file.timestamp().max(0) as u64);
+            let local_is_newer = local_saves
+                .iter()
+                .any(|local| local.name == info.name && local.modified >= modified);
+            if local_is_newer {
+                continue;
+            }
+            let mut data = Vec::new();
+            file.read()
+                .read_to_end(&mut data)
+                .with_context(|| format!("Failed to download {} from Steam Cloud", info.name))?;
+            self.local
+                .write(&format!("{SAVE_DIRECTORY}/{}", info.name), &data)?;
+            info!("Downloaded save {} from Steam Cloud", info.name);
+        }
+        Ok(())
+    }
+}
+
+impl Storage for SteamCloudStorage {
+    fn read(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        self.local.read(key)
+    }
+
+    fn write(&self, key: &str, data: &[u8]) -> Result<()> {
+        self.local.write(key, data)?;
+        let Some(name) = key.strip_prefix(&format!("{SAVE_DIRECTORY}/")) else {
+            return Ok(());
+        };
+        if !self.cloud_enabled() {
+            return Ok(());
+        }
+        // The local save already succeeded, so a failed upload is not worth failing the save for
+        let mut writer = self.client.remote_storage().file(name).write();
+        if let Err(error) = writer.write_all(data).and_then(|_| writer.flush()) {
+            warn!("Failed to upload {name} to Steam Cloud: {error}");
+        }
+        Ok(())
+    }
+
+    fn list(&self, directory: &str) -> Result<Vec<StoredEntry>> {
+        self.local.list(directory)
+    }
+}
@@ -1,20 +1,41 @@
 pub(crate) use crate::player_control::{
-    actions::actions_plugin, camera::camera_plugin, player_embodiment::player_embodiment_plugin,
+    actions::actions_plugin, aiming::aiming_plugin, camera::camera_plugin,
+    climbing::climbing_plugin, driving::driving_plugin, lock_on::lock_on_plugin,
+    player_embodiment::player_embodiment_plugin, progression::progression_plugin,
+    riding::riding_plugin,
 };
 use bevy::prelude::*;
 use seldom_fn_plugin::FnPluginExt;
 
 pub(crate) mod actions;
+pub(crate) mod aiming;
 pub(crate) mod camera;
+pub(crate) mod climbing;
+pub(crate) mod driving;
+pub(crate) mod lock_on;
 pub(crate) mod player_embodiment;
+pub(crate) mod progression;
+pub(crate) mod riding;
 
 /// Handles systems exclusive to the player's control. Is split into the following sub-plugins:
 /// - [`actions_plugin`]: Handles player input such as mouse and keyboard and neatly packs it into an [`actions::Actions`] resource.
 /// - [`camera_plugin`]: Handles camera movement.
 /// - [`player_embodiment_plugin`]: Tells the components from [`super::movement_plugin`] about the desired player [`actions::Actions`].
 /// Also handles other systems that change how the player is physically represented in the world.
+/// - [`lock_on_plugin`]: Lets the player lock onto enemies and strafe around them.
+/// - [`aiming_plugin`]: Moves the camera over the shoulder and tracks what the player aims at.
+/// - [`driving_plugin`]: Lets the player get into vehicles and drive them.
+/// - [`riding_plugin`]: Lets the player ride mounts.
+/// - [`climbing_plugin`]: Lets the player climb and swing on ropes.
+/// - [`progression_plugin`]: Handles experience, levels and the skills unlocked with them.
 pub(crate) fn player_control_plugin(app: &mut App) {
     app.fn_plugin(actions_plugin)
         .fn_plugin(camera_plugin)
-        .fn_plugin(player_embodiment_plugin);
+        .fn_plugin(player_embodiment_plugin)
+        .fn_plugin(lock_on_plugin)
+        .fn_plugin(aiming_plugin)
+        .fn_plugin(driving_plugin)
+        .fn_plugin(riding_plugin)
+        .fn_plugin(climbing_plugin)
+        .fn_plugin(progression_plugin);
 }
@@ -1,8 +1,18 @@
-use crate::util::criteria::is_frozen;
+use crate::{util::criteria::is_frozen, GameState};
 use bevy::prelude::*;
+use input_buffer::input_buffer_plugin;
+pub(crate) use input_buffer::{
+    ActionSnapshot, ControlledActions, InputBuffer, InputBufferSystemSet, InputOverrideSystemSet,
+    TickInput,
+};
 use leafwing_input_manager::{axislike::DualAxisData, plugin::InputManagerSystem, prelude::*};
+use seldom_fn_plugin::FnPluginExt;
 use serde::{Deserialize, Serialize};
 
+mod input_buffer;
+
+/// Counts the UIs, dialogs and cutscenes that currently take the player's input away.
+/// Reset when leaving the level, so that freezes of UIs that were still open do not carry over into the next one.
 #[derive(Resource, Default, Reflect, Serialize, Deserialize)]
 #[reflect(Resource, Serialize, Deserialize)]
 pub(crate) struct ActionsFrozen {
@@ -13,7 +23,7 @@ impl ActionsFrozen {
         self.freeze_count += 1;
     }
     pub(crate) fn unfreeze(&mut self) {
-        self.freeze_count -= 1;
+        self.freeze_count = self.freeze_count.saturating_sub(1);
     }
     pub(crate) fn is_frozen(&self) -> bool {
         self.freeze_count > 0
@@ -22,31 +32,45 @@ impl ActionsFrozen {
 
 /// Configures [`Actions`], the resource that holds all player input.
 /// Add new input in [`set_actions`] and in [`game_control::generate_bindings!`](game_control).
+/// The input of every tick is also captured into the [`InputBuffer`], see [`input_buffer_plugin`].
 
 pub(crate) fn actions_plugin(app: &mut App) {
     app.register_type::<PlayerAction>()
         .register_type::<CameraAction>()
         .register_type::<UiAction>()
+        .register_type::<VehicleAction>()
         .register_type::<ActionsFrozen>()
         .init_resource::<ActionsFrozen>()
         .add_plugins(InputManagerPlugin::<PlayerAction>::default())
         .add_plugins(InputManagerPlugin::<CameraAction>::default())
         .add_plugins(InputManagerPlugin::<UiAction>::default())
+        .add_plugins(InputManagerPlugin::<VehicleAction>::default())
+        .fn_plugin(input_buffer_plugin)
         .add_systems(
             PreUpdate,
             remove_actions_when_frozen
                 .run_if(is_frozen)
                 .after(InputManagerSystem::ManualControl),
-        );
+        )
+        .add_systems(OnExit(GameState::Playing), reset_actions_frozen);
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Actionlike, Reflect, Default)]
+#[derive(
+    Debug, Clone, Copy, Eq, PartialEq, Hash, Actionlike, Reflect, Serialize, Deserialize, Default,
+)]
 pub(crate) enum PlayerAction {
     #[default]
     Move,
     Sprint,
+    Crouch,
     Jump,
     Interact,
+    Throw,
+    Dash,
+    LockOn,
+    Aim,
+    NextInteraction,
+    PreviousInteraction,
     SpeedUpDialog,
     NumberedChoice1,
     NumberedChoice2,
@@ -60,17 +84,39 @@ pub(crate) enum PlayerAction {
     NumberedChoice0,
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash, Actionlike, Reflect, Default)]
+#[derive(
+    Debug, Clone, Eq, PartialEq, Hash, Actionlike, Reflect, Serialize, Deserialize, Default,
+)]
 pub(crate) enum CameraAction {
     #[default]
     Orbit,
     Zoom,
 }
 
+/// Controls used instead of [`PlayerAction`]s while driving. Lives on the vehicle, not on the player.
+#[derive(
+    Debug, Clone, Copy, Eq, PartialEq, Hash, Actionlike, Reflect, Serialize, Deserialize, Default,
+)]
+pub(crate) enum VehicleAction {
+    /// Forward and backward for throttle and reverse, sideways for steering
+    #[default]
+    Drive,
+    Brake,
+    Exit,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Actionlike, Reflect, Default)]
 pub(crate) enum UiAction {
     #[default]
     TogglePause,
+    ToggleCustomization,
+    ToggleMap,
+    ToggleCrafting,
+    ToggleSkills,
+    NavigateUp,
+    NavigateDown,
+    Confirm,
+    Back,
 }
 
 pub(crate) fn create_player_action_input_manager_bundle() -> InputManagerBundle<PlayerAction> {
@@ -78,7 +124,10 @@ pub(crate) fn create_player_action_input_manager_bundle() -> InputManagerBundle<
         input_map: InputMap::new([
             (QwertyScanCode::Space, PlayerAction::Jump),
             (QwertyScanCode::ShiftLeft, PlayerAction::Sprint),
+            (QwertyScanCode::ControlLeft, PlayerAction::Crouch),
             (QwertyScanCode::E, PlayerAction::Interact),
+            (QwertyScanCode::F, PlayerAction::Throw),
+            (QwertyScanCode::Q, PlayerAction::Dash),
             (QwertyScanCode::Space, PlayerAction::SpeedUpDialog),
             (QwertyScanCode::Key1, PlayerAction::NumberedChoice1),
             (QwertyScanCode::Key2, PlayerAction::NumberedChoice2),
@@ -92,6 +141,29 @@ pub(crate) fn create_player_action_input_manager_bundle() -> InputManagerBundle<
             (QwertyScanCode::Key0, PlayerAction::NumberedChoice0),
         ])
         .insert(VirtualDPad::wasd(), PlayerAction::Move)
+        .insert(MouseButton::Middle, PlayerAction::LockOn)
+        .insert(GamepadButtonType::RightThumb, PlayerAction::LockOn)
+        .insert(MouseButton::Right, PlayerAction::Aim)
+        .insert(GamepadButtonType::LeftTrigger2, PlayerAction::Aim)
+        .insert(MouseWheelDirection::Down, PlayerAction::NextInteraction)
+        .insert(GamepadButtonType::DPadRight, PlayerAction::NextInteraction)
+        .insert(MouseWheelDirection::Up, PlayerAction::PreviousInteraction)
+        .insert(
+            GamepadButtonType::DPadLeft,
+            PlayerAction::PreviousInteraction,
+        )
+        .build(),
+        ..default()
+    }
+}
+
+pub(crate) fn create_vehicle_action_input_manager_bundle() -> InputManagerBundle<VehicleAction> {
+    InputManagerBundle {
+        input_map: InputMap::new([
+            (QwertyScanCode::Space, VehicleAction::Brake),
+            (QwertyScanCode::E, VehicleAction::Exit),
+        ])
+        .insert(VirtualDPad::wasd(), VehicleAction::Drive)
         .build(),
         ..default()
     }
@@ -109,7 +181,27 @@ pub(crate) fn create_camera_action_input_manager_bundle() -> InputManagerBundle<
 
 pub(crate) fn create_ui_action_input_manager_bundle() -> InputManagerBundle<UiAction> {
     InputManagerBundle {
-        input_map: InputMap::new([(QwertyScanCode::Escape, UiAction::TogglePause)]),
+        input_map: InputMap::new([
+            (QwertyScanCode::Escape, UiAction::TogglePause),
+            (QwertyScanCode::C, UiAction::ToggleCustomization),
+            (QwertyScanCode::M, UiAction::ToggleMap),
+            (QwertyScanCode::Tab, UiAction::ToggleCrafting),
+            (QwertyScanCode::K, UiAction::ToggleSkills),
+        ])
+        .insert_multiple([
+            (KeyCode::Up, UiAction::NavigateUp),
+            (KeyCode::Down, UiAction::NavigateDown),
+            (KeyCode::Return, UiAction::Confirm),
+            (KeyCode::Back, UiAction::Back),
+        ])
+        .insert_multiple([
+            (GamepadButtonType::Start, UiAction::TogglePause),
+            (GamepadButtonType::DPadUp, UiAction::NavigateUp),
+            (GamepadButtonType::DPadDown, UiAction::NavigateDown),
+            (GamepadButtonType::South, UiAction::Confirm),
+            (GamepadButtonType::East, UiAction::Back),
+        ])
+        .build(),
         ..default()
     }
 }
@@ -117,12 +209,25 @@ pub(crate) fn create_ui_action_input_manager_bundle() -> InputManagerBundle<UiAc
 pub(crate) fn remove_actions_when_frozen(
     mut player_actions_query: Query<&mut ActionState<PlayerAction>>,
     mut camera_actions_query: Query<&mut ActionState<CameraAction>>,
+    mut vehicle_actions_query: Query<&mut ActionState<VehicleAction>>,
 ) {
     for mut player_actions in player_actions_query.iter_mut() {
         player_actions.action_data_mut(PlayerAction::Move).axis_pair = Some(default());
         player_actions.release(PlayerAction::Jump);
         player_actions.release(PlayerAction::Interact);
         player_actions.release(PlayerAction::Sprint);
+        player_actions.release(PlayerAction::Crouch);
+        player_actions.release(PlayerAction::Throw);
+        player_actions.release(PlayerAction::Dash);
+        player_actions.release(PlayerAction::LockOn);
+        player_actions.release(PlayerAction::Aim);
+    }
+    for mut vehicle_actions in vehicle_actions_query.iter_mut() {
+        vehicle_actions
+            .action_data_mut(VehicleAction::Drive)
+            .axis_pair = Some(default());
+        vehicle_actions.release(VehicleAction::Brake);
+        vehicle_actions.release(VehicleAction::Exit);
     }
     for mut camera_actions in camera_actions_query.iter_mut() {
         camera_actions
@@ -132,6 +237,10 @@ pub(crate) fn remove_actions_when_frozen(
     }
 }
 
+fn reset_actions_frozen(mut actions_frozen: ResMut<ActionsFrozen>) {
+    *actions_frozen = default();
+}
+
 pub(crate) trait DualAxisDataExt {
     fn max_normalized(self) -> Option<Vec2>;
 }
@@ -0,0 +1,209 @@
+use crate::{
+    player_control::{
+        actions::{remove_actions_when_frozen, CameraAction, PlayerAction, VehicleAction},
+        camera::IngameCamera,
+        driving::Driving,
+        player_embodiment::Player,
+    },
+    GameState,
+};
+use bevy::{ecs::system::SystemParam, prelude::*};
+use leafwing_input_manager::{axislike::DualAxisData, prelude::*};
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, time::Duration};
+
+/// Ticks kept in the [`InputBuffer`], a bit more than two seconds at 60 frames per second
+const INPUT_BUFFER_LENGTH: usize = 128;
+
+/// Captures the final input of every tick as a serializable [`TickInput`] and keeps the most recent ones in the [`InputBuffer`].
+/// Systems that replace the player's input, like replay playback, run in [`InputOverrideSystemSet`], so that the buffer
+/// always holds the input the game actually acted on. Anything that needs past input, like recording replays or
+/// rolling back a prediction, reads it from the buffer after [`InputBufferSystemSet`] instead of from the [`ActionState`]s.
+pub(super) fn input_buffer_plugin(app: &mut App) {
+    app.init_resource::<InputBuffer>()
+        .configure_sets(
+            PreUpdate,
+            (InputOverrideSystemSet, InputBufferSystemSet)
+                .chain()
+                .after(remove_actions_when_frozen),
+        )
+        .add_systems(
+            PreUpdate,
+            buffer_input
+                .in_set(InputBufferSystemSet)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(OnEnter(GameState::Playing), clear_input_buffer);
+}
+
+/// Systems that overwrite the [`ActionState`]s read from the devices with input from elsewhere
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
+pub(crate) struct InputOverrideSystemSet;
+
+/// Pushes this tick's [`TickInput`] into the [`InputBuffer`]
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
+pub(crate) struct InputBufferSystemSet;
+
+/// Everything the player controlled during one tick.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub(crate) struct TickInput {
+    /// Counts up with every tick since the level was entered
+    pub(crate) tick: u32,
+    /// Real time that passed since the last tick
+    pub(crate) delta: Duration,
+    pub(crate) player: ActionSnapshot<PlayerAction>,
+    pub(crate) camera: ActionSnapshot<CameraAction>,
+    /// Only set while the player is driving
+    pub(crate) vehicle: ActionSnapshot<VehicleAction>,
+}
+
+/// The most recent [`TickInput`]s, oldest first.
+#[derive(Debug, Clone, PartialEq, Resource, Default)]
+pub(crate) struct InputBuffer {
+    inputs: VecDeque<TickInput>,
+    next_tick: u32,
+}
+
+impl InputBuffer {
+    fn push(&mut self, mut input: TickInput) {
+        input.tick = self.next_tick;
+        self.next_tick += 1;
+        self.inputs.push_back(input);
+        while self.inputs.len() > INPUT_BUFFER_LENGTH {
+            self.inputs.pop_front();
+        }
+    }
+
+    /// The input of the current tick once [`InputBufferSystemSet`] has run
+    pub(crate) fn latest(&self) -> Option<&TickInput> {
+        self.inputs.back()
+    }
+}
+
+/// The state of an [`ActionState`] in a single tick, without the actions that were untouched.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ActionSnapshot<A> {
+    pub(crate) pressed: Vec<A>,
+    pub(crate) axis_pairs: Vec<(A, Vec2)>,
+    pub(crate) values: Vec<(A, f32)>,
+}
+
+impl<A> Default for ActionSnapshot<A> {
+    fn default() -> Self {
+        Self {
+            pressed: default(),
+            axis_pairs: default(),
+            values: default(),
+        }
+    }
+}
+
+impl<A: Actionlike> ActionSnapshot<A> {
+    pub(crate) fn capture(actions: &ActionState<A>) -> Self {
+        let mut snapshot = Self {
+            pressed: actions.get_pressed(),
+            ..default()
+        };
+        for action in A::variants() {
+            if let Some(axis_pair) = actions.axis_pair(action.clone()) {
+                snapshot.axis_pairs.push((action.clone(), axis_pair.xy()));
+            }
+            let value = actions.value(action.clone());
+            if value != 0. {
+                snapshot.values.push((action, value));
+            }
+        }
+        snapshot
+    }
+
+    /// Overwrites `actions` with the snapshot. Pressing and releasing goes through the [`ActionState`],
+    /// so that `just_pressed` and `just_released` behave like they did when the snapshot was captured.
+    pub(crate) fn apply(&self, actions: &mut ActionState<A>) {
+        for action in A::variants() {
+            if self.pressed.contains(&action) {
+                actions.press(action.clone());
+            } else {
+                actions.release(action.clone());
+            }
+            let data = actions.action_data_mut(action.clone());
+            data.axis_pair = self
+                .axis_pairs
+                .iter()
+                .find(|(captured, _)| *captured == action)
+                .map(|(_, axis_pair)| DualAxisData::from_xy(*axis_pair));
+            data.value = self
+                .values
+                .iter()
+                .find(|(captured, _)| *captured == action)
+                .map(|(_, value)| *value)
+                .unwrap_or_default();
+        }
+    }
+}
+
+/// The [`ActionState`]s the player is in control of: their own, the camera's and the one of the vehicle they are driving.
+#[derive(SystemParam)]
+pub(crate) struct ControlledActions<'w, 's> {
+    players: Query<
+        'w,
+        's,
+        (
+            &'static mut ActionState<PlayerAction>,
+            Option<&'static Driving>,
+        ),
+        With<Player>,
+    >,
+    cameras: Query<'w, 's, &'static mut ActionState<CameraAction>, With<IngameCamera>>,
+    vehicles: Query<'w, 's, &'static mut ActionState<VehicleAction>, Without<Player>>,
+}
+
+impl ControlledActions<'_, '_> {
+    /// This tick's input, with the tick left to be assigned by the [`InputBuffer`]
+    pub(crate) fn capture(&self, delta: Duration) -> TickInput {
+        let player = self.players.get_single().ok();
+        let vehicle = player
+            .and_then(|(_, driving)| driving)
+            .and_then(|driving| self.vehicles.get(driving.vehicle).ok());
+        TickInput {
+            tick: 0,
+            delta,
+            player: player
+                .map(|(actions, _)| ActionSnapshot::capture(actions))
+                .unwrap_or_default(),
+            camera: self
+                .cameras
+                .get_single()
+                .map(ActionSnapshot::capture)
+                .unwrap_or_default(),
+            vehicle: vehicle.map(ActionSnapshot::capture).unwrap_or_default(),
+        }
+    }
+
+    pub(crate) fn apply(&mut self, input: &TickInput) {
+        for (mut actions, driving) in &mut self.players {
+            input.player.apply(&mut actions);
+            if let Some(mut vehicle_actions) =
+                driving.and_then(|driving| self.vehicles.get_mut(driving.vehicle).ok())
+            {
+                input.vehicle.apply(&mut vehicle_actions);
+            }
+        }
+        for mut actions in &mut self.cameras {
+            input.camera.apply(&mut actions);
+        }
+    }
+}
+
+fn buffer_input(
+    time: Res<Time<Real>>,
+    controlled_actions: ControlledActions,
+    mut input_buffer: ResMut<InputBuffer>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("buffer_input").entered();
+    input_buffer.push(controlled_actions.capture(time.delta()));
+}
+
+fn clear_input_buffer(mut input_buffer: ResMut<InputBuffer>) {
+    *input_buffer = default();
+}
@@ -0,0 +1,131 @@
+use crate::{
+    movement::{
+        character_controller::{GeneralMovementSystemSet, Walk},
+        physics::CollisionLayer,
+    },
+    player_control::{
+        actions::PlayerAction,
+        camera::{IngameCamera, IngameCameraKind},
+        climbing::Climbing,
+        driving::Driving,
+        player_embodiment::Player,
+        riding::Riding,
+    },
+    util::trait_extension::Vec3Ext,
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_dolly::prelude::*;
+use bevy_xpbd_3d::prelude::*;
+use leafwing_input_manager::prelude::ActionState;
+
+/// How far the aim ray reaches
+const AIM_DISTANCE: f32 = 100.;
+
+/// Lets the player aim while holding [`PlayerAction::Aim`]. The third person camera moves in over the player's shoulder,
+/// a reticle is shown, the player walks slower and turns with the camera.
+/// While aiming, the player has an [`Aiming`] holding what lies under the reticle, so that e.g. thrown balls fly right there.
+pub(crate) fn aiming_plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        follow_aim
+            .before(GeneralMovementSystemSet)
+            .run_if(in_state(GameState::Playing)),
+    )
+    .add_systems(
+        PostUpdate,
+        update_aim
+            .after(Dolly::<IngameCamera>::update_active)
+            .run_if(in_state(GameState::Playing)),
+    );
+}
+
+/// Inserted on the player while they aim.
+#[derive(Debug, Clone, Copy, PartialEq, Component)]
+pub(crate) struct Aiming {
+    /// Where the ray through the center of the screen hits, or where it ends if it hits nothing
+    pub(crate) point: Vec3,
+    /// What the ray hit
+    pub(crate) entity: Option<Entity>,
+}
+
+fn update_aim(
+    mut commands: Commands,
+    mut players: Query<
+        (
+            Entity,
+            &ActionState<PlayerAction>,
+            Option<&mut Aiming>,
+            Has<Driving>,
+            Has<Riding>,
+            Has<Climbing>,
+        ),
+        With<Player>,
+    >,
+    cameras: Query<(&IngameCamera, &Transform)>,
+    spatial_query: SpatialQuery,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("update_aim").entered();
+    let Some((camera, camera_transform)) = cameras.iter().next() else {
+        return;
+    };
+    for (player, actions, aiming, is_driving, is_riding, is_climbing) in players.iter_mut() {
+        let can_aim = matches!(
+            camera.kind,
+            IngameCameraKind::ThirdPerson | IngameCameraKind::FirstPerson
+        ) && !is_driving
+            && !is_riding
+            && !is_climbing;
+        if !can_aim || !actions.pressed(PlayerAction::Aim) {
+            if aiming.is_some() {
+                commands.entity(player).remove::<Aiming>();
+            }
+            continue;
+        }
+        let origin = camera_transform.translation;
+        let direction = camera_transform.forward();
+        let new_aiming = match spatial_query.cast_ray(
+            origin,
+            direction,
+            AIM_DISTANCE,
+            true,
+            CollisionLayer::solid_filter().without_entities([player]),
+        ) {
+            Some(hit) => Aiming {
+                point: origin + direction * hit.time_of_impact,
+                entity: Some(hit.entity),
+            },
+            None => Aiming {
+                point: origin + direction * AIM_DISTANCE,
+                entity: None,
+            },
+        };
+        match aiming {
+            Some(mut aiming) => {
+                if *aiming != new_aiming {
+                    *aiming = new_aiming;
+                }
+            }
+            None => {
+                commands.entity(player).insert(new_aiming);
+            }
+        }
+    }
+}
+
+fn follow_aim(
+    mut players: Query<(&mut Walk, Has<Aiming>), With<Player>>,
+    mut cameras: Query<(&mut IngameCamera, &Transform)>,
+) {
+    for (mut walk, is_aiming) in players.iter_mut() {
+        for (mut camera, camera_transform) in cameras.iter_mut() {
+            if camera.aiming != is_aiming {
+                camera.aiming = is_aiming;
+            }
+            if is_aiming {
+                walk.facing = Some(camera_transform.forward().horizontal());
+            }
+        }
+    }
+}
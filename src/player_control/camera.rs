@@ -1,25 +1,27 @@
 use crate::{
+    movement::physics::TransformInterpolationSet,
     player_control::camera::{
         cursor::grab_cursor,
         focus::set_camera_focus,
         kind::{update_drivers, update_kind},
         rig::update_rig,
+        shake::{shake_camera, shake_on_player_damage, CameraTrauma},
     },
     GameState,
 };
-use bevy::prelude::*;
+use bevy::{prelude::*, transform::TransformSystem};
 use bevy_atmosphere::prelude::*;
 use bevy_dolly::prelude::*;
-use bevy_xpbd_3d::PhysicsSet;
-use bevy_yarnspinner_example_dialogue_view::ExampleYarnSpinnerDialogueViewSystemSet;
 pub(crate) use cursor::ForceCursorGrabMode;
 use serde::{Deserialize, Serialize};
+pub(crate) use shake::CameraShakeEvent;
 use ui::*;
 
 mod cursor;
 pub(crate) mod focus;
 mod kind;
 mod rig;
+mod shake;
 mod ui;
 
 #[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
@@ -27,6 +29,10 @@ mod ui;
 pub(crate) struct IngameCamera {
     pub(crate) target: Transform,
     pub(crate) secondary_target: Option<Transform>,
+    /// Point the camera keeps in view along with the player while they are locked onto a target
+    pub(crate) lock_on: Option<Vec3>,
+    /// Moves the third person camera over the player's shoulder
+    pub(crate) aiming: bool,
     pub(crate) desired_distance: f32,
     pub(crate) kind: IngameCameraKind,
 }
@@ -37,6 +43,8 @@ impl Default for IngameCamera {
             desired_distance: 5.,
             target: default(),
             secondary_target: default(),
+            lock_on: default(),
+            aiming: default(),
             kind: default(),
         }
     }
@@ -49,33 +57,53 @@ pub(crate) enum IngameCameraKind {
     ThirdPerson,
     FirstPerson,
     FixedAngle,
+    /// Follows behind a vehicle, see [`Chase`](crate::file_system_interaction::config::Chase)
+    Chase,
 }
 
 /// Handles the main ingame camera, i.e. not the UI camera in the menu.
 /// Cameras are controlled with [`CameraActions`]. Depending on the distance, a first person,
-/// third person or fixed angle camera is used.
+/// third person or fixed angle camera is used. [`CameraShakeEvent`]s shake the camera unless reduced motion is enabled.
+/// The camera follows the interpolated transforms of the bodies it looks at, so it is moved in [`PostUpdate`],
+/// after the [`TransformInterpolationSet`] and before the transforms are propagated.
 pub(crate) fn camera_plugin(app: &mut App) {
     app.add_plugins(AtmospherePlugin)
         .register_type::<UiCamera>()
         .register_type::<IngameCamera>()
         .register_type::<IngameCameraKind>()
         .init_resource::<ForceCursorGrabMode>()
-        .add_systems(Update, Dolly::<IngameCamera>::update_active)
+        .init_resource::<CameraTrauma>()
+        .add_event::<CameraShakeEvent>()
         .add_systems(Startup, spawn_ui_camera)
         .add_systems(OnEnter(GameState::Playing), despawn_ui_camera)
+        // Moving between levels despawns the ingame camera along with the level
+        .add_systems(
+            OnEnter(GameState::Loading),
+            spawn_ui_camera.run_if(not(any_with_component::<UiCamera>())),
+        )
         .add_systems(Update, grab_cursor.run_if(in_state(GameState::Playing)))
+        .configure_sets(
+            PostUpdate,
+            CameraUpdateSystemSet
+                .after(TransformInterpolationSet)
+                .before(TransformSystem::TransformPropagate),
+        )
         .add_systems(
-            Update,
+            PostUpdate,
             (
-                update_kind,
-                update_drivers,
-                set_camera_focus.after(ExampleYarnSpinnerDialogueViewSystemSet),
-                update_rig,
-            )
-                .chain()
-                .in_set(CameraUpdateSystemSet)
-                .after(PhysicsSet::Sync)
-                .run_if(in_state(GameState::Playing)),
+                (update_kind, update_drivers, set_camera_focus, update_rig)
+                    .chain()
+                    .in_set(CameraUpdateSystemSet)
+                    .run_if(in_state(GameState::Playing)),
+                Dolly::<IngameCamera>::update_active
+                    .after(CameraUpdateSystemSet)
+                    .before(TransformSystem::TransformPropagate),
+                (shake_on_player_damage, shake_camera)
+                    .chain()
+                    .after(Dolly::<IngameCamera>::update_active)
+                    .before(TransformSystem::TransformPropagate)
+                    .run_if(in_state(GameState::Playing)),
+            ),
         );
 }
 
@@ -1,11 +1,11 @@
 use crate::{
+    game_events::DialogEnded,
     player_control::{camera::IngameCamera, player_embodiment::Player},
     world_interaction::dialog::DialogTarget,
 };
 use anyhow::Result;
 use bevy::prelude::*;
 use bevy_mod_sysfail::*;
-use bevy_yarnspinner::events::DialogueCompleteEvent;
 use bevy_yarnspinner_example_dialogue_view::SpeakerChangeEvent;
 
 #[sysfail(log(level = "error"))]
@@ -14,7 +14,7 @@ pub(crate) fn set_camera_focus(
     mut speaker_change_events: EventReader<SpeakerChangeEvent>,
     player_query: Query<&Transform, With<Player>>,
     dialog_targets: Query<(&Transform, &DialogTarget), Without<Player>>,
-    mut dialogue_complete_event: EventReader<DialogueCompleteEvent>,
+    mut dialogs_ended: EventReader<DialogEnded>,
 ) -> Result<()> {
     for mut camera in camera_query.iter_mut() {
         for player_transform in player_query.iter() {
@@ -30,7 +30,7 @@ pub(crate) fn set_camera_focus(
             camera.target = *player_transform;
         }
     }
-    for _event in dialogue_complete_event.read() {
+    for _event in dialogs_ended.read() {
         for mut camera in camera_query.iter_mut() {
             camera.secondary_target = None;
         }
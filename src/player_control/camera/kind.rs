@@ -48,7 +48,9 @@ pub(crate) fn update_kind(
 pub(crate) fn update_drivers(mut camera_query: Query<(&IngameCamera, &mut Rig)>) {
     for (camera, mut rig) in camera_query.iter_mut() {
         match camera.kind {
-            IngameCameraKind::ThirdPerson => set_third_person_drivers(&mut rig),
+            IngameCameraKind::ThirdPerson | IngameCameraKind::Chase => {
+                set_third_person_drivers(&mut rig)
+            }
             IngameCameraKind::FirstPerson => match camera.secondary_target {
                 Some(_) => set_first_person_drivers_with_target(&mut rig),
                 None => set_first_person_drivers_without_target(&mut rig),
@@ -1,5 +1,6 @@
 use crate::{
     file_system_interaction::config::GameConfig,
+    menu::Settings,
     player_control::{
         actions::CameraAction,
         camera::{
@@ -7,7 +8,7 @@ use crate::{
             IngameCamera, IngameCameraKind,
         },
     },
-    util::trait_extension::Vec2Ext,
+    util::trait_extension::{F32Ext, Vec2Ext, Vec3Ext},
 };
 use anyhow::Result;
 use bevy::prelude::*;
@@ -18,6 +19,13 @@ use leafwing_input_manager::prelude::ActionState;
 
 mod arm;
 
+/// Time without camera input after which camera assist takes over
+const ASSIST_DELAY: f32 = 1.;
+/// Fraction of the remaining yaw covered per second by camera assist
+const ASSIST_SPEED: f32 = 1.5;
+/// Fraction of the remaining yaw covered per second when turning behind the player towards a locked-on target
+const LOCK_ON_SPEED: f32 = 6.;
+
 #[sysfail(log(level = "error"))]
 pub(crate) fn update_rig(
     time: Res<Time<Virtual>>,
@@ -28,31 +36,68 @@ pub(crate) fn update_rig(
         &Transform,
     )>,
     config: Res<GameConfig>,
+    settings: Res<Settings>,
     spatial_query: SpatialQuery,
+    mut assist_delay: Local<f32>,
+    mut last_target: Local<Vec3>,
+    mut aim_blend: Local<f32>,
 ) -> Result<()> {
     let dt = time.delta_seconds();
     for (mut camera, mut rig, actions, transform) in camera_query.iter_mut() {
+        let is_aiming = camera.aiming && camera.kind == IngameCameraKind::ThirdPerson;
+        let step = config.camera.aim.blend_speed * dt;
+        *aim_blend = if is_aiming {
+            (*aim_blend + step).min(1.)
+        } else {
+            (*aim_blend - step).max(0.)
+        };
         set_look_at(&mut rig, &camera);
         set_position(&mut rig, &camera);
         if camera.kind == IngameCameraKind::FixedAngle {
             let yaw_pitch = rig.driver_mut::<YawPitch>();
             yaw_pitch.yaw_degrees = 0.;
             yaw_pitch.pitch_degrees = config.camera.fixed_angle.pitch;
+        } else if let Some(lock_on) = camera.lock_on
+            && camera.secondary_target.is_none()
+        {
+            // Sideways input switches targets instead, see `lock_on_plugin`
+            let camera_movement = get_camera_movement(actions);
+            set_yaw_pitch(&mut rig, &camera, Vec2::new(0., camera_movement.y), &config);
+            let direction = (lock_on - camera.target.translation).horizontal();
+            turn_yaw_towards(&mut rig, direction, LOCK_ON_SPEED, dt);
+            *assist_delay = ASSIST_DELAY;
         } else {
             let camera_movement = get_camera_movement(actions);
             if !camera_movement.is_approx_zero() {
                 set_yaw_pitch(&mut rig, &camera, camera_movement, &config);
+                *assist_delay = ASSIST_DELAY;
+            } else if camera.kind == IngameCameraKind::Chase {
+                follow_vehicle(&mut rig, &camera, &config, dt);
+            } else {
+                *assist_delay -= dt;
+                let is_target_moving = !(camera.target.translation - *last_target).is_approx_zero();
+                if settings.accessibility.camera_assist
+                    && camera.kind == IngameCameraKind::ThirdPerson
+                    && camera.secondary_target.is_none()
+                    && is_target_moving
+                    && *assist_delay <= 0.
+                {
+                    assist_yaw(&mut rig, &camera, ASSIST_SPEED, dt);
+                }
             }
         }
+        *last_target = camera.target.translation;
 
         set_desired_distance(&mut camera, actions, &config);
         let distance = get_arm_distance(&camera, transform, &spatial_query, &config);
         if let Some(distance) = distance {
+            let distance = distance.lerp(distance.min(config.camera.aim.distance), *aim_blend);
             let zoom_smoothness = get_zoom_smoothness(&config, &camera, &rig, distance);
             set_arm(&mut rig, distance, zoom_smoothness, dt);
         }
 
         set_smoothness(&mut rig, &config, &camera);
+        set_shoulder_offset(&mut rig, config.camera.aim.shoulder_offset * *aim_blend);
     }
     Ok(())
 }
@@ -73,10 +118,53 @@ fn set_yaw_pitch(rig: &mut Rig, camera: &IngameCamera, camera_movement: Vec2, co
     yaw_pitch.pitch_degrees = yaw_pitch.pitch_degrees.clamp(min_pitch, max_pitch);
 }
 
+/// Swings the chase camera behind the vehicle and back to its default pitch.
+fn follow_vehicle(rig: &mut Rig, camera: &IngameCamera, config: &GameConfig, dt: f32) {
+    let chase = &config.camera.chase;
+    assist_yaw(rig, camera, chase.follow_speed, dt);
+    let yaw_pitch = rig.driver_mut::<YawPitch>();
+    let factor = (chase.follow_speed * dt).min(1.);
+    yaw_pitch.pitch_degrees += (chase.pitch - yaw_pitch.pitch_degrees) * factor;
+}
+
+/// Turns the camera towards the target's back, which faces where the player is walking.
+fn assist_yaw(rig: &mut Rig, camera: &IngameCamera, speed: f32, dt: f32) {
+    turn_yaw_towards(rig, camera.target.forward().horizontal(), speed, dt);
+}
+
+/// Turns the camera so that it looks along `forward`.
+fn turn_yaw_towards(rig: &mut Rig, forward: Vec3, speed: f32, dt: f32) {
+    if forward.is_approx_zero() {
+        return;
+    }
+    let target_yaw = (-forward.x).atan2(-forward.z).to_degrees();
+    let yaw_pitch = rig.driver_mut::<YawPitch>();
+    let difference = (target_yaw - yaw_pitch.yaw_degrees + 540.).rem_euclid(360.) - 180.;
+    yaw_pitch.rotate_yaw_pitch(difference * (speed * dt).min(1.), 0.);
+}
+
+/// Moves the camera to the right and looks just as far to the right of the player,
+/// so that the camera still looks in the direction given by its yaw and pitch.
+fn set_shoulder_offset(rig: &mut Rig, offset: f32) {
+    let yaw = rig.driver::<YawPitch>().yaw_degrees;
+    let right = Quat::from_rotation_y(yaw.to_radians()) * Vec3::X;
+    if let Some(arm) = rig.try_driver_mut::<Arm>() {
+        arm.offset.x = offset;
+    }
+    if let Some(look_at) = rig.try_driver_mut::<LookAt>() {
+        look_at.target += right * offset;
+    }
+}
+
 fn set_look_at(rig: &mut Rig, camera: &IngameCamera) {
     if let Some(look_at) = rig.try_driver_mut::<LookAt>() {
         if let Some(secondary_target) = camera.secondary_target {
             look_at.target = secondary_target.translation
+        } else if let Some(lock_on) = camera.lock_on
+            && camera.kind != IngameCameraKind::FirstPerson
+        {
+            // Halfway between the player and the target frames both
+            look_at.target = camera.target.translation.lerp(lock_on, 0.5)
         } else if camera.kind != IngameCameraKind::FirstPerson {
             look_at.target = camera.target.translation
         }
@@ -96,7 +184,7 @@ fn set_position(rig: &mut Rig, camera: &IngameCamera) {
 
 fn get_pitch_extrema(config: &GameConfig, camera: &IngameCamera) -> (f32, f32) {
     match camera.kind {
-        IngameCameraKind::ThirdPerson => (
+        IngameCameraKind::ThirdPerson | IngameCameraKind::Chase => (
             config.camera.third_person.min_pitch,
             config.camera.third_person.max_pitch,
         ),
@@ -123,6 +211,10 @@ fn set_desired_distance(
             config.camera.fixed_angle.min_distance,
             config.camera.fixed_angle.max_distance,
         ),
+        IngameCameraKind::Chase => (
+            config.camera.chase.min_distance,
+            config.camera.chase.max_distance,
+        ),
         IngameCameraKind::FirstPerson => (0.0, 0.0),
     };
     camera.desired_distance = (camera.desired_distance - zoom).clamp(min_distance, max_distance);
@@ -146,6 +238,12 @@ fn set_smoothness(rig: &mut Rig, config: &GameConfig, camera: &IngameCamera) {
                 look_at.smoothness = config.camera.first_person.tracking_smoothing;
             }
         }
+        IngameCameraKind::Chase => {
+            rig.driver_mut::<Smooth>().position_smoothness =
+                config.camera.chase.translation_smoothing;
+            rig.driver_mut::<Smooth>().rotation_smoothness = config.camera.chase.rotation_smoothing;
+            rig.driver_mut::<LookAt>().smoothness = config.camera.chase.tracking_smoothing;
+        }
         IngameCameraKind::FixedAngle => {
             rig.driver_mut::<Smooth>().position_smoothness =
                 config.camera.fixed_angle.translation_smoothing;
@@ -1,6 +1,6 @@
 use crate::{
     file_system_interaction::config::GameConfig,
-    level_instantiation::spawning::objects::CollisionLayer,
+    movement::physics::CollisionLayer,
     player_control::camera::{IngameCamera, IngameCameraKind},
     util::{smoothness_to_lerp_factor, trait_extension::F32Ext},
 };
@@ -15,7 +15,7 @@ pub(crate) fn get_arm_distance(
     config: &GameConfig,
 ) -> Option<f32> {
     match camera.kind {
-        IngameCameraKind::ThirdPerson => Some(get_distance_to_collision(
+        IngameCameraKind::ThirdPerson | IngameCameraKind::Chase => Some(get_distance_to_collision(
             spatial_query,
             config,
             camera,
@@ -35,13 +35,17 @@ pub(crate) fn get_zoom_smoothness(
     let current_distance = rig.driver::<Arm>().offset.z;
     if new_distance < current_distance - 1e-4 {
         match camera.kind {
-            IngameCameraKind::ThirdPerson => config.camera.third_person.zoom_in_smoothing,
+            IngameCameraKind::ThirdPerson | IngameCameraKind::Chase => {
+                config.camera.third_person.zoom_in_smoothing
+            }
             IngameCameraKind::FixedAngle => config.camera.fixed_angle.zoom_in_smoothing,
             _ => unreachable!(),
         }
     } else {
         match camera.kind {
-            IngameCameraKind::ThirdPerson => config.camera.third_person.zoom_out_smoothing,
+            IngameCameraKind::ThirdPerson | IngameCameraKind::Chase => {
+                config.camera.third_person.zoom_out_smoothing
+            }
             IngameCameraKind::FixedAngle => config.camera.fixed_angle.zoom_out_smoothing,
             _ => unreachable!(),
         }
@@ -65,11 +69,12 @@ fn get_distance_to_collision(
 
     let max_toi = camera.desired_distance;
     let solid = true;
-    let filter =
-        SpatialQueryFilter::new().with_masks_from_bits(CollisionLayer::CameraObstacle.to_bits());
+    let filter = CollisionLayer::camera_probe_filter();
 
     let min_distance = match camera.kind {
-        IngameCameraKind::ThirdPerson => config.camera.third_person.min_distance_to_objects,
+        IngameCameraKind::ThirdPerson | IngameCameraKind::Chase => {
+            config.camera.third_person.min_distance_to_objects
+        }
         _ => unreachable!(),
     };
 
@@ -0,0 +1,62 @@
+use crate::{game_events::PlayerDamaged, menu::Settings, player_control::camera::IngameCamera};
+use bevy::prelude::*;
+
+/// Rotation of the camera at full trauma in radians
+const MAX_SHAKE_ANGLE: f32 = 0.06;
+/// Trauma lost per second
+const TRAUMA_DECAY: f32 = 1.5;
+
+/// Makes the ingame camera shake. Trauma adds up and decays over time, the shake grows with its square.
+#[derive(Debug, Clone, Copy, PartialEq, Event)]
+pub(crate) struct CameraShakeEvent {
+    /// Between 0 and 1
+    pub(crate) trauma: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Resource, Default)]
+pub(crate) struct CameraTrauma(f32);
+
+pub(crate) fn shake_on_player_damage(
+    mut player_damaged: EventReader<PlayerDamaged>,
+    mut shake_events: EventWriter<CameraShakeEvent>,
+) {
+    for event in player_damaged.read() {
+        shake_events.send(CameraShakeEvent {
+            trauma: (2. * event.amount / event.max_health).min(1.),
+        });
+    }
+}
+
+/// Runs after the camera rig has placed the camera, so the shake is applied on top of it and never accumulates.
+pub(crate) fn shake_camera(
+    time: Res<Time<Virtual>>,
+    settings: Res<Settings>,
+    mut shake_events: EventReader<CameraShakeEvent>,
+    mut trauma: ResMut<CameraTrauma>,
+    mut cameras: Query<&mut Transform, With<IngameCamera>>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("shake_camera").entered();
+    for event in shake_events.read() {
+        trauma.0 = (trauma.0 + event.trauma).min(1.);
+    }
+    trauma.0 = (trauma.0 - TRAUMA_DECAY * time.delta_seconds()).max(0.);
+    if trauma.0 <= 0. || settings.accessibility.reduce_motion {
+        return;
+    }
+    // Overlapping sines are cheap and smooth enough to pass for noise here
+    let t = time.elapsed_seconds();
+    let noise = |frequency: f32, phase: f32| {
+        (t * frequency + phase).sin() * 0.6 + (t * frequency * 2.3 + phase * 1.7).sin() * 0.4
+    };
+    let strength = trauma.0 * trauma.0 * MAX_SHAKE_ANGLE;
+    let shake = Quat::from_euler(
+        EulerRot::YXZ,
+        noise(23., 0.) * strength,
+        noise(29., 1.3) * strength,
+        noise(17., 2.9) * strength * 0.5,
+    );
+    for mut transform in cameras.iter_mut() {
+        transform.rotation *= shake;
+    }
+}
@@ -0,0 +1,227 @@
+use crate::{
+    movement::{
+        character_controller::GeneralMovementSystemSet,
+        physics::{CollisionLayer, TransformInterpolationSet},
+        ropes::{Rope, RopeSegment},
+    },
+    player_control::{
+        actions::{DualAxisDataExt, PlayerAction},
+        camera::{CameraUpdateSystemSet, IngameCamera},
+        driving::Driving,
+        player_embodiment::{camera_relative_direction, Player},
+        riding::Riding,
+    },
+    util::criteria::is_frozen,
+    world_interaction::interactions_ui::InteractionOpportunity,
+    GameState,
+};
+use bevy::{prelude::*, transform::TransformSystem};
+use bevy_tnua::{prelude::*, TnuaToggle};
+use bevy_xpbd_3d::prelude::*;
+use leafwing_input_manager::prelude::ActionState;
+
+/// How far from the player's center a rope can be grabbed
+const GRAB_RADIUS: f32 = 0.6;
+const CLIMB_SPEED: f32 = 1.5;
+/// Acceleration the player adds to the held segment when swinging
+const SWING_ACCELERATION: f32 = 12.;
+const JUMP_OFF_SPEED: f32 = 4.;
+/// Time after letting go during which the player cannot grab a rope again
+const REGRAB_COOLDOWN: f32 = 0.5;
+/// Offset from the grabbed point to the player's center
+const HAND_OFFSET: Vec3 = Vec3::new(0., -0.5, 0.);
+
+/// Lets the player climb and swing on [`Rope`]s. A rope is grabbed by interacting with it or by jumping into it.
+/// While holding on, moving forward and backward climbs along the rope, moving sideways swings it and jumping lets go.
+pub(crate) fn climbing_plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        ((let_go, grab_rope).run_if(not(is_frozen)), climb)
+            .chain()
+            .before(GeneralMovementSystemSet)
+            .run_if(in_state(GameState::Playing)),
+    )
+    .add_systems(
+        PostUpdate,
+        hold_on
+            .after(TransformInterpolationSet)
+            .before(CameraUpdateSystemSet)
+            .before(TransformSystem::TransformPropagate)
+            .run_if(in_state(GameState::Playing)),
+    );
+}
+
+/// Present on the player while they hold on to a rope.
+#[derive(Debug, Clone, PartialEq, Component)]
+pub(crate) struct Climbing {
+    pub(crate) rope: Entity,
+    segment: Entity,
+    /// Position along the segment, from -half to +half of its length
+    offset: f32,
+    collision_layers: CollisionLayers,
+}
+
+fn grab_rope(
+    mut commands: Commands,
+    time: Res<Time<Virtual>>,
+    interaction_opportunity: Res<InteractionOpportunity>,
+    players: Query<
+        (
+            Entity,
+            &ActionState<PlayerAction>,
+            &Transform,
+            &CollisionLayers,
+            &TnuaController,
+        ),
+        (
+            With<Player>,
+            Without<Climbing>,
+            Without<Driving>,
+            Without<Riding>,
+        ),
+    >,
+    segments: Query<(&RopeSegment, &GlobalTransform)>,
+    spatial_query: SpatialQuery,
+    mut cooldown: Local<f32>,
+) {
+    *cooldown -= time.delta_seconds();
+    if *cooldown > 0. {
+        return;
+    }
+    for (player, actions, transform, collision_layers, controller) in players.iter() {
+        let wants_to_grab = (actions.just_pressed(PlayerAction::Interact)
+            && interaction_opportunity.0.is_none())
+            || controller.is_airborne().unwrap_or_default();
+        if !wants_to_grab {
+            continue;
+        }
+        let hands = transform.translation - HAND_OFFSET;
+        let filter = CollisionLayer::rope_filter();
+        let Some((segment, rope_segment, segment_transform)) = spatial_query
+            .shape_intersections(&Collider::ball(GRAB_RADIUS), hands, Quat::IDENTITY, filter)
+            .into_iter()
+            .filter_map(|entity| {
+                let (rope_segment, segment_transform) = segments.get(entity).ok()?;
+                Some((entity, rope_segment, segment_transform))
+            })
+            .min_by(|(_, _, a), (_, _, b)| {
+                let a = a.translation().distance_squared(hands);
+                let b = b.translation().distance_squared(hands);
+                a.total_cmp(&b)
+            })
+        else {
+            continue;
+        };
+        let local_hands = segment_transform.affine().inverse().transform_point3(hands);
+        commands.entity(player).insert((
+            Climbing {
+                rope: rope_segment.rope,
+                segment,
+                offset: local_hands.y,
+                collision_layers: *collision_layers,
+            },
+            RigidBody::Kinematic,
+            CollisionLayers::NONE,
+            LinearVelocity::ZERO,
+            TnuaToggle::Disabled,
+        ));
+    }
+}
+
+fn let_go(
+    mut commands: Commands,
+    players: Query<(Entity, &ActionState<PlayerAction>, &Climbing, &Transform), With<Player>>,
+    velocities: Query<&LinearVelocity, Without<Player>>,
+    cameras: Query<(&IngameCamera, &Transform), Without<Player>>,
+    mut linear_velocities: Query<&mut LinearVelocity, With<Player>>,
+) {
+    for (player, actions, climbing, transform) in players.iter() {
+        if !actions.just_pressed(PlayerAction::Jump) {
+            continue;
+        }
+        let swing = velocities
+            .get(climbing.segment)
+            .map(|velocity| velocity.0)
+            .unwrap_or_default();
+        let jump_direction = cameras
+            .iter()
+            .next()
+            .and_then(|(camera, camera_transform)| {
+                let movement = actions
+                    .axis_pair(PlayerAction::Move)
+                    .and_then(|axis| axis.max_normalized())?;
+                Some(camera_relative_direction(
+                    movement,
+                    camera,
+                    camera_transform,
+                ))
+            })
+            .unwrap_or_else(|| transform.back());
+        if let Ok(mut velocity) = linear_velocities.get_mut(player) {
+            velocity.0 = swing + (jump_direction + Vec3::Y).normalize_or_zero() * JUMP_OFF_SPEED;
+        }
+        commands.entity(player).remove::<Climbing>().insert((
+            RigidBody::Dynamic,
+            climbing.collision_layers,
+            TnuaToggle::Enabled,
+        ));
+    }
+}
+
+fn climb(
+    time: Res<Time<Virtual>>,
+    mut players: Query<(&ActionState<PlayerAction>, &mut Climbing), With<Player>>,
+    ropes: Query<&Rope>,
+    mut segments: Query<(&RopeSegment, &mut LinearVelocity)>,
+    cameras: Query<(&IngameCamera, &Transform)>,
+) {
+    let Some((camera, camera_transform)) = cameras.iter().next() else {
+        return;
+    };
+    let dt = time.delta_seconds();
+    for (actions, mut climbing) in players.iter_mut() {
+        let Some(movement) = actions
+            .axis_pair(PlayerAction::Move)
+            .and_then(|axis| axis.max_normalized())
+        else {
+            continue;
+        };
+        let Ok(rope) = ropes.get(climbing.rope) else {
+            continue;
+        };
+        let Ok((rope_segment, mut velocity)) = segments.get_mut(climbing.segment) else {
+            continue;
+        };
+        let swing = camera_relative_direction(Vec2::new(movement.x, 0.), camera, camera_transform);
+        velocity.0 += swing * SWING_ACCELERATION * dt;
+
+        let half_length = rope.segment_length() / 2.;
+        let mut index = rope_segment.index;
+        let mut offset = climbing.offset + movement.y * CLIMB_SPEED * dt;
+        if offset > half_length && index > 0 {
+            index -= 1;
+            offset -= 2. * half_length;
+        } else if offset < -half_length && index + 1 < rope.segments.len() {
+            index += 1;
+            offset += 2. * half_length;
+        }
+        climbing.offset = offset.clamp(-half_length, half_length);
+        if let Some(segment) = rope.segments.get(index) {
+            climbing.segment = *segment;
+        }
+    }
+}
+
+/// Keeps the player's hands on the held rope segment. Like riders, climbers are not parented to what they hold on to.
+fn hold_on(
+    mut players: Query<(&Climbing, &mut Transform), With<Player>>,
+    segments: Query<&Transform, (With<RopeSegment>, Without<Player>)>,
+) {
+    for (climbing, mut transform) in players.iter_mut() {
+        let Ok(segment_transform) = segments.get(climbing.segment) else {
+            continue;
+        };
+        let hands = segment_transform.transform_point(Vec3::Y * climbing.offset);
+        transform.translation = hands + HAND_OFFSET;
+    }
+}
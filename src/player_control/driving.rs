@@ -0,0 +1,228 @@
+use crate::{
+    movement::{
+        physics::{CollisionLayer, TransformInterpolationSet},
+        vehicle::{Vehicle, VehicleControls},
+    },
+    player_control::{
+        actions::{PlayerAction, VehicleAction},
+        camera::{CameraUpdateSystemSet, IngameCamera, IngameCameraKind},
+        player_embodiment::Player,
+    },
+    util::{criteria::is_frozen, trait_extension::Vec3Ext},
+    world_interaction::interactions_ui::InteractionOpportunity,
+    GameState,
+};
+use bevy::{prelude::*, transform::TransformSystem};
+use bevy_tnua::TnuaToggle;
+use bevy_xpbd_3d::prelude::*;
+use leafwing_input_manager::prelude::ActionState;
+
+/// Where to try placing the player when leaving a vehicle or mount, relative to it and in order of preference:
+/// left, right, behind, in front and on top.
+const EXIT_OFFSETS: [Vec3; 5] = [
+    Vec3::new(-1.5, 0.3, 0.),
+    Vec3::new(1.5, 0.3, 0.),
+    Vec3::new(0., 0.3, 2.2),
+    Vec3::new(0., 0.3, -2.2),
+    Vec3::new(0., 1.2, 0.),
+];
+/// How far the ground may be below an exit position
+const MAX_EXIT_DROP: f32 = 2.;
+
+/// Lets the player drive [`Vehicle`]s. Interacting with a vehicle seats the player in it and swaps the
+/// active control context: the player's [`PlayerAction`]s are ignored while the vehicle's [`VehicleAction`]s
+/// drive it, and the camera switches to [`IngameCameraKind::Chase`].
+/// When exiting, the player is placed next to the vehicle wherever there is room, or stays seated if there is none.
+/// If the vehicle disappears, e.g. because its chunk was unloaded, the player is let out right where they are.
+pub(crate) fn driving_plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (
+            (exit_vehicle, enter_vehicle).run_if(not(is_frozen)),
+            control_vehicles,
+        )
+            .chain()
+            .run_if(in_state(GameState::Playing)),
+    )
+    .add_systems(
+        PostUpdate,
+        seat_drivers
+            .after(TransformInterpolationSet)
+            .before(CameraUpdateSystemSet)
+            .before(TransformSystem::TransformPropagate)
+            .run_if(in_state(GameState::Playing)),
+    );
+}
+
+/// Present on the player while they are in a vehicle. Remembers what to restore when they get out.
+#[derive(Debug, Clone, PartialEq, Component)]
+pub(crate) struct Driving {
+    pub(crate) vehicle: Entity,
+    rigid_body: RigidBody,
+    collision_layers: CollisionLayers,
+    camera_kind: IngameCameraKind,
+}
+
+fn enter_vehicle(
+    mut commands: Commands,
+    interaction_opportunity: Res<InteractionOpportunity>,
+    players: Query<
+        (
+            Entity,
+            &ActionState<PlayerAction>,
+            &RigidBody,
+            &CollisionLayers,
+        ),
+        With<Player>,
+    >,
+    vehicles: Query<(), With<Vehicle>>,
+    drivers: Query<&Driving>,
+    mut cameras: Query<&mut IngameCamera>,
+) {
+    let Some(vehicle) = interaction_opportunity
+        .0
+        .filter(|target| vehicles.contains(*target))
+    else {
+        return;
+    };
+    if drivers.iter().any(|driving| driving.vehicle == vehicle) {
+        return;
+    }
+    for (player, actions, rigid_body, collision_layers) in players.iter() {
+        if !actions.just_pressed(PlayerAction::Interact) || drivers.contains(player) {
+            continue;
+        }
+        let camera_kind = cameras
+            .iter_mut()
+            .next()
+            .map(|mut camera| std::mem::replace(&mut camera.kind, IngameCameraKind::Chase))
+            .unwrap_or_default();
+        commands.entity(player).insert((
+            Driving {
+                vehicle,
+                rigid_body: *rigid_body,
+                collision_layers: *collision_layers,
+                camera_kind,
+            },
+            RigidBody::Kinematic,
+            CollisionLayers::NONE,
+            LinearVelocity::ZERO,
+            TnuaToggle::Disabled,
+        ));
+    }
+}
+
+fn exit_vehicle(
+    mut commands: Commands,
+    mut players: Query<(Entity, &Driving, &Collider, &mut Transform), With<Player>>,
+    vehicles: Query<(&ActionState<VehicleAction>, &Transform), Without<Player>>,
+    mut cameras: Query<&mut IngameCamera>,
+    spatial_query: SpatialQuery,
+) {
+    for (player, driving, collider, mut transform) in players.iter_mut() {
+        let Ok((actions, vehicle_transform)) = vehicles.get(driving.vehicle) else {
+            leave_vehicle(&mut commands, player, driving, &mut cameras);
+            continue;
+        };
+        if !actions.just_pressed(VehicleAction::Exit) {
+            continue;
+        }
+        let Some(position) = find_exit_position(vehicle_transform, collider, &spatial_query) else {
+            info!("No room to exit the vehicle");
+            continue;
+        };
+        transform.translation = position;
+        transform.rotation = yaw_of(vehicle_transform);
+        leave_vehicle(&mut commands, player, driving, &mut cameras);
+    }
+}
+
+/// Gives the player back everything that [`Driving`] took from them.
+fn leave_vehicle(
+    commands: &mut Commands,
+    player: Entity,
+    driving: &Driving,
+    cameras: &mut Query<&mut IngameCamera>,
+) {
+    for mut camera in cameras.iter_mut() {
+        camera.kind = driving.camera_kind.clone();
+    }
+    commands.entity(player).remove::<Driving>().insert((
+        driving.rigid_body,
+        driving.collision_layers,
+        TnuaToggle::Enabled,
+    ));
+}
+
+/// Finds a spot next to `vehicle` where `collider` fits and has ground below it.
+pub(crate) fn find_exit_position(
+    vehicle: &Transform,
+    collider: &Collider,
+    spatial_query: &SpatialQuery,
+) -> Option<Vec3> {
+    let obstacles = CollisionLayer::solid_filter();
+    let ground = CollisionLayer::standable_filter();
+    let yaw = yaw_of(vehicle);
+    EXIT_OFFSETS
+        .iter()
+        .map(|offset| vehicle.translation + yaw * *offset)
+        .find(|position| {
+            let is_free = spatial_query
+                .shape_intersections(collider, *position, Quat::IDENTITY, obstacles.clone())
+                .is_empty();
+            is_free
+                && spatial_query
+                    .cast_ray(*position, Vec3::NEG_Y, MAX_EXIT_DROP, true, ground.clone())
+                    .is_some()
+        })
+}
+
+/// The vehicle's rotation around the vertical axis only, so the player stands upright even if it tipped over.
+fn yaw_of(transform: &Transform) -> Quat {
+    let forward = transform.forward().horizontal();
+    if forward.is_approx_zero() {
+        return Quat::IDENTITY;
+    }
+    Quat::from_rotation_arc(Vec3::NEG_Z, forward.normalize())
+}
+
+fn control_vehicles(
+    drivers: Query<&Driving>,
+    mut vehicles: Query<(Entity, &ActionState<VehicleAction>, &mut VehicleControls)>,
+) {
+    for (entity, actions, mut controls) in vehicles.iter_mut() {
+        let has_driver = drivers.iter().any(|driving| driving.vehicle == entity);
+        let new_controls = if has_driver {
+            let drive = actions
+                .axis_pair(VehicleAction::Drive)
+                .map(|axis| axis.xy())
+                .unwrap_or_default();
+            VehicleControls {
+                throttle: drive.y,
+                steering: drive.x,
+                brake: actions.pressed(VehicleAction::Brake),
+            }
+        } else {
+            default()
+        };
+        controls.set_if_neq(new_controls);
+    }
+}
+
+/// Runs after physics so the driver, and with them the camera, follows the vehicle without lagging a frame behind.
+fn seat_drivers(
+    mut commands: Commands,
+    mut drivers: Query<(Entity, &Driving, &mut Transform, &mut LinearVelocity)>,
+    vehicles: Query<(&Vehicle, &Transform), Without<Driving>>,
+    mut cameras: Query<&mut IngameCamera>,
+) {
+    for (player, driving, mut transform, mut velocity) in drivers.iter_mut() {
+        let Ok((vehicle, vehicle_transform)) = vehicles.get(driving.vehicle) else {
+            leave_vehicle(&mut commands, player, driving, &mut cameras);
+            continue;
+        };
+        transform.translation = vehicle_transform.transform_point(vehicle.seat);
+        transform.rotation = vehicle_transform.rotation;
+        velocity.0 = Vec3::ZERO;
+    }
+}
@@ -0,0 +1,290 @@
+use crate::{
+    combat::health::Dead,
+    file_system_interaction::config::GameConfig,
+    movement::{
+        character_controller::{GeneralMovementSystemSet, Walk},
+        physics::CollisionLayer,
+    },
+    player_control::{
+        actions::{CameraAction, PlayerAction},
+        camera::IngameCamera,
+        climbing::Climbing,
+        driving::Driving,
+        player_embodiment::Player,
+        riding::Riding,
+    },
+    theme::UiTheme,
+    util::trait_extension::Vec3Ext,
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiSettings};
+use bevy_xpbd_3d::prelude::*;
+use leafwing_input_manager::prelude::ActionState;
+use serde::{Deserialize, Serialize};
+
+/// How far away targets can be locked onto
+const LOCK_ON_RANGE: f32 = 20.;
+/// Targets further away than this are released
+const RELEASE_RANGE: f32 = 25.;
+/// Sideways camera input in radians per frame that counts as a flick to switch targets
+const FLICK_THRESHOLD: f32 = 0.04;
+/// Sideways camera input must fall below this before the next flick counts
+const FLICK_RESET_THRESHOLD: f32 = 0.01;
+
+/// Lets the player lock onto a [`Targetable`] with [`PlayerAction::LockOn`]. Pressing it picks the closest target
+/// in view and pressing it again releases it. While locked on, the camera turns behind the player to frame both them
+/// and the target, and the player keeps facing the target, so that they strafe around it.
+/// Flicking the camera sideways switches to the next target in that direction.
+/// The lock is released when the target dies, gets out of range or out of sight, or the player stops walking on their own feet.
+pub(crate) fn lock_on_plugin(app: &mut App) {
+    app.register_type::<Targetable>()
+        .add_systems(
+            Update,
+            (
+                toggle_lock_on,
+                switch_lock_on_target,
+                release_lock_on,
+                follow_lock_on_target,
+            )
+                .chain()
+                .before(GeneralMovementSystemSet)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            Update,
+            show_lock_on_marker.run_if(in_state(GameState::Playing)),
+        );
+}
+
+/// Something the player can lock onto, like an enemy. Dead targets cannot be locked onto.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Targetable {
+    /// Height above the origin that the camera and the player aim at
+    pub(crate) height: f32,
+}
+
+impl Default for Targetable {
+    fn default() -> Self {
+        Self { height: 0.5 }
+    }
+}
+
+/// The target the player is locked onto.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+pub(crate) struct LockOn {
+    pub(crate) target: Entity,
+}
+
+fn aim_point(targetable: &Targetable, transform: &GlobalTransform) -> Vec3 {
+    transform.translation() + Vec3::Y * targetable.height
+}
+
+fn is_in_sight(spatial_query: &SpatialQuery, from: Vec3, to: Vec3) -> bool {
+    let Some(direction) = (to - from).try_normalize() else {
+        return true;
+    };
+    spatial_query
+        .cast_ray(
+            from,
+            direction,
+            from.distance(to),
+            true,
+            CollisionLayer::sight_filter(),
+        )
+        .is_none()
+}
+
+fn toggle_lock_on(
+    mut commands: Commands,
+    players: Query<
+        (Entity, &ActionState<PlayerAction>, &Transform, Has<LockOn>),
+        (
+            With<Player>,
+            Without<Driving>,
+            Without<Riding>,
+            Without<Climbing>,
+        ),
+    >,
+    targets: Query<(Entity, &Targetable, &GlobalTransform), Without<Dead>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<IngameCamera>>,
+    spatial_query: SpatialQuery,
+) {
+    for (player, actions, player_transform, is_locked_on) in players.iter() {
+        if !actions.just_pressed(PlayerAction::LockOn) {
+            continue;
+        }
+        if is_locked_on {
+            commands.entity(player).remove::<LockOn>();
+            continue;
+        }
+        let Some((camera, camera_transform)) = cameras.iter().next() else {
+            continue;
+        };
+        let position = player_transform.translation;
+        let is_in_view = |point: Vec3| {
+            camera
+                .world_to_ndc(camera_transform, point)
+                .is_some_and(|ndc| {
+                    ndc.x.abs() <= 1. && ndc.y.abs() <= 1. && (0.0..=1.).contains(&ndc.z)
+                })
+        };
+        let closest = targets
+            .iter()
+            .map(|(entity, targetable, transform)| (entity, aim_point(targetable, transform)))
+            .filter(|(_, point)| {
+                point.distance(position) <= LOCK_ON_RANGE
+                    && is_in_view(*point)
+                    && is_in_sight(&spatial_query, position, *point)
+            })
+            .min_by(|(_, a), (_, b)| {
+                a.distance_squared(position)
+                    .total_cmp(&b.distance_squared(position))
+            });
+        if let Some((target, _)) = closest {
+            commands.entity(player).insert(LockOn { target });
+        }
+    }
+}
+
+fn switch_lock_on_target(
+    mut players: Query<(&Transform, &mut LockOn), With<Player>>,
+    targets: Query<(Entity, &Targetable, &GlobalTransform), Without<Dead>>,
+    cameras: Query<&ActionState<CameraAction>>,
+    config: Res<GameConfig>,
+    spatial_query: SpatialQuery,
+    mut flick_ready: Local<bool>,
+) {
+    let Some(flick) = cameras
+        .iter()
+        .next()
+        .and_then(|actions| actions.axis_pair(CameraAction::Orbit))
+        .map(|orbit| orbit.x() * config.camera.mouse_sensitivity_x)
+    else {
+        return;
+    };
+    if flick.abs() < FLICK_RESET_THRESHOLD {
+        *flick_ready = true;
+    }
+    if !*flick_ready || flick.abs() < FLICK_THRESHOLD {
+        return;
+    }
+    *flick_ready = false;
+    for (player_transform, mut lock_on) in players.iter_mut() {
+        let position = player_transform.translation;
+        let Ok((_, current, current_transform)) = targets.get(lock_on.target) else {
+            continue;
+        };
+        let forward = (aim_point(current, current_transform) - position)
+            .horizontal()
+            .normalize_or_zero();
+        let right = forward.cross(Vec3::Y);
+        // Angle of every other target around the player from the current one, growing in the flick's direction
+        let next = targets
+            .iter()
+            .filter(|(entity, ..)| *entity != lock_on.target)
+            .map(|(entity, targetable, transform)| (entity, aim_point(targetable, transform)))
+            .filter(|(_, point)| {
+                point.distance(position) <= LOCK_ON_RANGE
+                    && is_in_sight(&spatial_query, position, *point)
+            })
+            .filter_map(|(entity, point)| {
+                let direction = (point - position).horizontal();
+                let angle = direction.dot(right).atan2(direction.dot(forward)) * flick.signum();
+                (angle > 0.).then_some((entity, angle))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b));
+        if let Some((target, _)) = next {
+            lock_on.target = target;
+        }
+    }
+}
+
+fn release_lock_on(
+    mut commands: Commands,
+    players: Query<
+        (
+            Entity,
+            &Transform,
+            &LockOn,
+            Has<Driving>,
+            Has<Riding>,
+            Has<Climbing>,
+        ),
+        With<Player>,
+    >,
+    targets: Query<(&Targetable, &GlobalTransform), Without<Dead>>,
+    spatial_query: SpatialQuery,
+) {
+    for (player, transform, lock_on, is_driving, is_riding, is_climbing) in players.iter() {
+        let position = transform.translation;
+        let is_valid = targets
+            .get(lock_on.target)
+            .is_ok_and(|(targetable, target_transform)| {
+                let point = aim_point(targetable, target_transform);
+                point.distance(position) <= RELEASE_RANGE
+                    && is_in_sight(&spatial_query, position, point)
+            });
+        if !is_valid || is_driving || is_riding || is_climbing {
+            commands.entity(player).remove::<LockOn>();
+        }
+    }
+}
+
+fn follow_lock_on_target(
+    mut players: Query<(&Transform, &mut Walk, Option<&LockOn>), With<Player>>,
+    targets: Query<(&Targetable, &GlobalTransform)>,
+    mut cameras: Query<&mut IngameCamera>,
+) {
+    for (transform, mut walk, lock_on) in players.iter_mut() {
+        let point = lock_on
+            .and_then(|lock_on| targets.get(lock_on.target).ok())
+            .map(|(targetable, target_transform)| aim_point(targetable, target_transform));
+        if let Some(point) = point {
+            walk.facing = Some((point - transform.translation).horizontal());
+        }
+        for mut camera in cameras.iter_mut() {
+            if camera.lock_on != point {
+                camera.lock_on = point;
+            }
+        }
+    }
+}
+
+fn show_lock_on_marker(
+    mut egui_contexts: EguiContexts,
+    egui_settings: Res<EguiSettings>,
+    players: Query<&LockOn, With<Player>>,
+    targets: Query<(&Targetable, &GlobalTransform)>,
+    cameras: Query<(&Camera, &GlobalTransform), With<IngameCamera>>,
+    theme: Res<UiTheme>,
+) {
+    let Some(lock_on) = players.iter().next() else {
+        return;
+    };
+    let Some((camera, camera_transform)) = cameras.iter().next() else {
+        return;
+    };
+    let Some(screen_position) =
+        targets
+            .get(lock_on.target)
+            .ok()
+            .and_then(|(targetable, transform)| {
+                camera.world_to_viewport(camera_transform, aim_point(targetable, transform))
+            })
+    else {
+        return;
+    };
+    let scale_factor = egui_settings.scale_factor as f32;
+    let painter = egui_contexts.ctx_mut().layer_painter(egui::LayerId::new(
+        egui::Order::Background,
+        egui::Id::new("lock_on_marker"),
+    ));
+    let center = egui::pos2(
+        screen_position.x / scale_factor,
+        screen_position.y / scale_factor,
+    );
+    painter.circle_stroke(center, 10., egui::Stroke::new(2., theme.colors.accent));
+    painter.circle_filled(center, 2., theme.colors.accent);
+}
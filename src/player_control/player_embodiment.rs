@@ -1,9 +1,15 @@
 use crate::{
-    file_system_interaction::audio::AudioHandles,
+    file_system_interaction::{audio::AudioHandles, config::GameConfig},
+    hud::captions::CaptionEvent,
+    menu::Settings,
     movement::character_controller::*,
     player_control::{
         actions::{DualAxisDataExt, PlayerAction},
-        camera::{CameraUpdateSystemSet, IngameCamera, IngameCameraKind},
+        aiming::Aiming,
+        camera::{IngameCamera, IngameCameraKind},
+        climbing::Climbing,
+        driving::Driving,
+        riding::Riding,
     },
 };
 
@@ -25,6 +31,7 @@ pub(crate) fn player_embodiment_plugin(app: &mut App) {
         .add_systems(
             Update,
             (
+                apply_movement_config,
                 handle_jump,
                 handle_horizontal_movement,
                 rotate_to_speaker,
@@ -32,7 +39,6 @@ pub(crate) fn player_embodiment_plugin(app: &mut App) {
                 handle_camera_kind,
             )
                 .chain()
-                .before(CameraUpdateSystemSet)
                 .before(GeneralMovementSystemSet)
                 .run_if(in_state(GameState::Playing)),
         );
@@ -42,7 +48,40 @@ pub(crate) fn player_embodiment_plugin(app: &mut App) {
 #[reflect(Component, Serialize, Deserialize)]
 pub(crate) struct Player;
 
-fn handle_jump(mut player_query: Query<(&ActionState<PlayerAction>, &mut Jump), With<Player>>) {
+/// Keeps the player's movement in sync with the [`GameConfig`], which can change while playing.
+pub(crate) fn apply_movement_config(
+    config: Res<GameConfig>,
+    mut players: Query<(
+        Ref<Player>,
+        &mut Walk,
+        &mut Sprinting,
+        &mut Crouching,
+        &mut Jump,
+    )>,
+) {
+    let movement = &config.movement;
+    for (player, mut walk, mut sprint, mut crouch, mut jump) in &mut players {
+        if !config.is_changed() && !player.is_added() {
+            continue;
+        }
+        walk.speed = movement.walk_speed;
+        sprint.multiplier = movement.sprint_multiplier;
+        crouch.multiplier = movement.crouch_multiplier;
+        jump.height = movement.jump_height;
+    }
+}
+
+fn handle_jump(
+    mut player_query: Query<
+        (&ActionState<PlayerAction>, &mut Jump),
+        (
+            With<Player>,
+            Without<Driving>,
+            Without<Riding>,
+            Without<Climbing>,
+        ),
+    >,
+) {
     #[cfg(feature = "tracing")]
     let _span = info_span!("handle_jump").entered();
     for (actions, mut jump) in &mut player_query {
@@ -52,8 +91,24 @@ fn handle_jump(mut player_query: Query<(&ActionState<PlayerAction>, &mut Jump),
 
 #[sysfail(log(level = "error"))]
 fn handle_horizontal_movement(
-    mut player_query: Query<(&ActionState<PlayerAction>, &mut Walk, &mut Sprinting), With<Player>>,
+    mut player_query: Query<
+        (
+            &ActionState<PlayerAction>,
+            &mut Walk,
+            &mut Sprinting,
+            &mut Crouching,
+            Has<Aiming>,
+        ),
+        (
+            With<Player>,
+            Without<Driving>,
+            Without<Riding>,
+            Without<Climbing>,
+        ),
+    >,
     camera_query: Query<(&IngameCamera, &Transform), Without<Player>>,
+    settings: Res<Settings>,
+    config: Res<GameConfig>,
 ) -> Result<()> {
     #[cfg(feature = "tracing")]
     let _span = info_span!("handle_horizontal_movement").entered();
@@ -61,39 +116,63 @@ fn handle_horizontal_movement(
         return Ok(());
     };
 
-    for (actions, mut walk, mut sprint) in &mut player_query {
+    let accessibility = &settings.accessibility;
+    for (actions, mut walk, mut sprint, mut crouch, is_aiming) in &mut player_query {
+        crouch.requested =
+            accessibility
+                .crouch_mode
+                .is_active(actions, PlayerAction::Crouch, crouch.requested);
         let Some(axis) = actions.axis_pair(PlayerAction::Move) else {
             continue;
         };
-        if let Some(movement) = axis.max_normalized() {
-            let forward = if camera.kind == IngameCameraKind::FixedAngle {
-                camera_transform.up()
-            } else {
-                camera_transform.forward()
-            }
-            .horizontal()
-            .normalize();
-
-            let sideways = forward.cross(Vec3::Y);
-            let forward_action = forward * movement.y;
-            let sideways_action = sideways * movement.x;
-
-            let is_looking_backward = forward.dot(forward_action) < 0.0;
-            let is_first_person = camera.kind == IngameCameraKind::FirstPerson;
-            let modifier = if is_looking_backward && is_first_person {
-                0.7
-            } else {
-                1.
-            };
-            let direction = forward_action * modifier + sideways_action;
-
-            walk.direction = Some(direction);
-            sprint.requested = actions.pressed(PlayerAction::Sprint);
+        let Some(movement) = axis.max_normalized() else {
+            // Standing still ends a toggled sprint
+            sprint.requested = false;
+            continue;
+        };
+        let direction = camera_relative_direction(movement, camera, camera_transform);
+        if is_aiming {
+            walk.direction = Some(direction * config.movement.aim_multiplier);
+            sprint.requested = false;
+            continue;
         }
+        walk.direction = Some(direction);
+        sprint.requested =
+            accessibility
+                .sprint_mode
+                .is_active(actions, PlayerAction::Sprint, sprint.requested);
     }
     Ok(())
 }
 
+/// Turns a movement input into a horizontal direction in the world, as seen from the camera.
+pub(crate) fn camera_relative_direction(
+    movement: Vec2,
+    camera: &IngameCamera,
+    camera_transform: &Transform,
+) -> Vec3 {
+    let forward = if camera.kind == IngameCameraKind::FixedAngle {
+        camera_transform.up()
+    } else {
+        camera_transform.forward()
+    }
+    .horizontal()
+    .normalize();
+
+    let sideways = forward.cross(Vec3::Y);
+    let forward_action = forward * movement.y;
+    let sideways_action = sideways * movement.x;
+
+    let is_looking_backward = forward.dot(forward_action) < 0.0;
+    let is_first_person = camera.kind == IngameCameraKind::FirstPerson;
+    let modifier = if is_looking_backward && is_first_person {
+        0.7
+    } else {
+        1.
+    };
+    forward_action * modifier + sideways_action
+}
+
 fn handle_camera_kind(
     mut with_player: Query<(&mut Transform, &mut Visibility), With<Player>>,
     camera_query: Query<(&Transform, &IngameCamera), Without<Player>>,
@@ -103,6 +182,10 @@ fn handle_camera_kind(
     for (camera_transform, camera) in camera_query.iter() {
         for (mut player_transform, mut visibility) in with_player.iter_mut() {
             match camera.kind {
+                // The driver is hidden inside the vehicle
+                IngameCameraKind::Chase => {
+                    *visibility = Visibility::Hidden;
+                }
                 IngameCameraKind::FirstPerson => {
                     let horizontal_direction = camera_transform.forward().horizontal();
                     let looking_target = player_transform.translation + horizontal_direction;
@@ -150,6 +233,8 @@ fn control_walking_sound(
     character_query: Query<&TnuaController, With<Player>>,
     audio: Res<AudioHandles>,
     mut audio_instances: ResMut<Assets<AudioInstance>>,
+    mut caption_events: EventWriter<CaptionEvent>,
+    mut was_walking: Local<bool>,
 ) -> Result<()> {
     #[cfg(feature = "tracing")]
     let _span = info_span!("control_walking_sound").entered();
@@ -162,11 +247,16 @@ fn control_walking_sound(
         };
         let has_horizontal_movement = !basis_state.running_velocity.horizontal().is_approx_zero();
         let is_moving_on_ground = has_horizontal_movement && !controller.is_airborne()?;
-        if is_moving_on_ground && !time.is_paused() {
+        let is_walking = is_moving_on_ground && !time.is_paused();
+        if is_walking {
             audio_instance.resume(default());
         } else {
             audio_instance.pause(default());
         }
+        if is_walking && !*was_walking {
+            caption_events.send(CaptionEvent::sound("[footsteps]"));
+        }
+        *was_walking = is_walking;
     }
     Ok(())
 }
@@ -0,0 +1,384 @@
+use crate::{
+    combat::health::Health,
+    file_system_interaction::config::GameConfig,
+    game_events::{CollectibleCollected, PlayerLeveledUp, QuestCompleted},
+    movement::character_controller::{GeneralMovementSystemSet, Jump, Knockback, Stamina, Walk},
+    player_control::{
+        actions::{ActionsFrozen, PlayerAction, UiAction},
+        player_embodiment::{apply_movement_config, Player},
+    },
+    theme::UiTheme,
+    util::{criteria::is_frozen, trait_extension::Vec3Ext},
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use leafwing_input_manager::prelude::ActionState;
+use serde::{Deserialize, Serialize};
+
+const SWIFTNESS_SPEED_MULTIPLIER: f32 = 1.15;
+const ENDURANCE_STAMINA_MULTIPLIER: f32 = 1.5;
+const VITALITY_HEALTH_BONUS: f32 = 25.;
+const DASH_SPEED: f32 = 20.;
+const DASH_DURATION: f32 = 0.2;
+const DASH_COOLDOWN: f32 = 1.;
+const DASH_STAMINA_COST: f32 = 25.;
+
+/// Lets the player earn experience, level up and spend the skill points they get on [`Skill`]s.
+/// Experience comes from [`ExperienceGained`] events, completed quests and found collectibles,
+/// with the amounts and the experience needed per level configured in the `progression` section of the [`GameConfig`].
+/// Dialogs grant experience with `<<give_experience 50>>`. Every level gained sends a [`PlayerLeveledUp`].
+/// The skill tree is toggled with [`UiAction::ToggleSkills`]. The player's [`Progression`] is saved with them.
+pub(crate) fn progression_plugin(app: &mut App) {
+    app.register_type::<Progression>()
+        .register_type::<Skill>()
+        .add_event::<ExperienceGained>()
+        .init_resource::<SkillTreeOpen>()
+        .add_systems(
+            Update,
+            (
+                grant_experience,
+                level_up,
+                apply_skills.after(apply_movement_config),
+                dash.run_if(not(is_frozen)),
+            )
+                .chain()
+                .before(GeneralMovementSystemSet)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            Update,
+            (toggle_skill_tree, show_skill_tree)
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(OnExit(GameState::Playing), close_skill_tree);
+}
+
+/// The player's level and what they unlocked with it.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Progression {
+    pub(crate) level: u32,
+    /// Experience gained since reaching the current level
+    pub(crate) experience: u32,
+    pub(crate) skill_points: u32,
+    pub(crate) skills: Vec<Skill>,
+}
+
+impl Default for Progression {
+    fn default() -> Self {
+        Self {
+            level: 1,
+            experience: 0,
+            skill_points: 0,
+            skills: Vec::new(),
+        }
+    }
+}
+
+impl Progression {
+    pub(crate) fn has(&self, skill: Skill) -> bool {
+        self.skills.contains(&skill)
+    }
+
+    fn can_unlock(&self, skill: Skill) -> bool {
+        self.skill_points > 0
+            && !self.has(skill)
+            && skill
+                .requirement()
+                .map_or(true, |required| self.has(required))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+#[reflect(Serialize, Deserialize)]
+pub(crate) enum Skill {
+    Vitality,
+    Endurance,
+    Swiftness,
+    DoubleJump,
+    Dash,
+}
+
+impl Skill {
+    /// In the order shown in the skill tree
+    pub(crate) const ALL: [Self; 5] = [
+        Self::Vitality,
+        Self::Endurance,
+        Self::DoubleJump,
+        Self::Swiftness,
+        Self::Dash,
+    ];
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Vitality => "Vitality",
+            Self::Endurance => "Endurance",
+            Self::Swiftness => "Swiftness",
+            Self::DoubleJump => "Double Jump",
+            Self::Dash => "Dash",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::Vitality => "+25 maximum health",
+            Self::Endurance => "+50% stamina",
+            Self::Swiftness => "+15% walking speed",
+            Self::DoubleJump => "Jump once more in the air",
+            Self::Dash => "Dash forward, using stamina",
+        }
+    }
+
+    /// The skill that has to be unlocked first
+    fn requirement(self) -> Option<Self> {
+        match self {
+            Self::Vitality | Self::Endurance | Self::Swiftness => None,
+            Self::DoubleJump => Some(Self::Endurance),
+            Self::Dash => Some(Self::Swiftness),
+        }
+    }
+}
+
+/// Sent to give the player experience.
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub(crate) struct ExperienceGained {
+    pub(crate) amount: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Resource, Default)]
+struct SkillTreeOpen(bool);
+
+/// `<<give_experience 50>>` grants the player experience.
+pub(crate) fn give_experience_command(
+    In(amount): In<f32>,
+    mut experience_events: EventWriter<ExperienceGained>,
+) {
+    experience_events.send(ExperienceGained {
+        amount: amount.max(0.) as u32,
+    });
+}
+
+fn grant_experience(
+    mut quests_completed: EventReader<QuestCompleted>,
+    mut collectibles_collected: EventReader<CollectibleCollected>,
+    mut experience_events: EventWriter<ExperienceGained>,
+    config: Res<GameConfig>,
+) {
+    let progression = &config.progression;
+    for _ in quests_completed.read() {
+        experience_events.send(ExperienceGained {
+            amount: progression.quest_experience,
+        });
+    }
+    for _ in collectibles_collected.read() {
+        experience_events.send(ExperienceGained {
+            amount: progression.collectible_experience,
+        });
+    }
+}
+
+fn level_up(
+    mut experience_events: EventReader<ExperienceGained>,
+    mut players: Query<&mut Progression, With<Player>>,
+    config: Res<GameConfig>,
+    mut level_up_events: EventWriter<PlayerLeveledUp>,
+) {
+    let Ok(mut progression) = players.get_single_mut() else {
+        return;
+    };
+    let gained: u32 = experience_events.read().map(|event| event.amount).sum();
+    if gained == 0 {
+        return;
+    }
+    progression.experience = progression.experience.saturating_add(gained);
+    let curve = &config.progression;
+    loop {
+        let needed = curve.experience_for_level(progression.level + 1);
+        if needed == 0 || progression.experience < needed {
+            break;
+        }
+        progression.experience -= needed;
+        progression.level += 1;
+        progression.skill_points += curve.skill_points_per_level;
+        info!("Reached level {}", progression.level);
+        level_up_events.send(PlayerLeveledUp {
+            level: progression.level,
+        });
+    }
+}
+
+/// Applies the stat boosts and abilities of the unlocked skills. Runs after [`apply_movement_config`],
+/// which resets the walking speed to the configured one.
+fn apply_skills(
+    config: Res<GameConfig>,
+    mut players: Query<
+        (
+            Ref<Progression>,
+            &mut Walk,
+            &mut Jump,
+            &mut Stamina,
+            &mut Health,
+        ),
+        With<Player>,
+    >,
+) {
+    for (progression, mut walk, mut jump, mut stamina, mut health) in &mut players {
+        if !config.is_changed() && !progression.is_changed() {
+            continue;
+        }
+        let speed_multiplier = if progression.has(Skill::Swiftness) {
+            SWIFTNESS_SPEED_MULTIPLIER
+        } else {
+            1.
+        };
+        walk.speed = config.movement.walk_speed * speed_multiplier;
+        jump.air_jumps = u32::from(progression.has(Skill::DoubleJump));
+        let stamina_multiplier = if progression.has(Skill::Endurance) {
+            ENDURANCE_STAMINA_MULTIPLIER
+        } else {
+            1.
+        };
+        stamina.max = Stamina::default().max * stamina_multiplier;
+        stamina.current = stamina.current.min(stamina.max);
+        let max_health = if progression.has(Skill::Vitality) {
+            Health::default().max + VITALITY_HEALTH_BONUS
+        } else {
+            Health::default().max
+        };
+        if health.max != max_health {
+            // Gaining maximum health also heals by that much
+            let bonus = (max_health - health.max).max(0.);
+            health.max = max_health;
+            health.current = (health.current + bonus).min(health.max);
+        }
+    }
+}
+
+fn dash(
+    mut commands: Commands,
+    time: Res<Time<Virtual>>,
+    mut cooldown: Local<f32>,
+    mut players: Query<
+        (
+            Entity,
+            &ActionState<PlayerAction>,
+            &Progression,
+            &Transform,
+            &mut Stamina,
+        ),
+        (With<Player>, Without<Knockback>),
+    >,
+) {
+    *cooldown = (*cooldown - time.delta_seconds()).max(0.);
+    for (entity, actions, progression, transform, mut stamina) in &mut players {
+        if !actions.just_pressed(PlayerAction::Dash)
+            || !progression.has(Skill::Dash)
+            || *cooldown > 0.
+            || stamina.current < DASH_STAMINA_COST
+        {
+            continue;
+        }
+        let Some(direction) = transform.forward().horizontal().try_normalize() else {
+            continue;
+        };
+        stamina.current -= DASH_STAMINA_COST;
+        *cooldown = DASH_COOLDOWN;
+        commands.entity(entity).insert(Knockback {
+            velocity: direction * DASH_SPEED,
+            remaining: DASH_DURATION,
+        });
+    }
+}
+
+fn toggle_skill_tree(
+    actions: Query<&ActionState<UiAction>>,
+    mut open: ResMut<SkillTreeOpen>,
+    mut freeze: ResMut<ActionsFrozen>,
+) {
+    if !actions
+        .iter()
+        .any(|actions| actions.just_pressed(UiAction::ToggleSkills))
+    {
+        return;
+    }
+    if open.0 {
+        open.0 = false;
+        freeze.unfreeze();
+    } else if !freeze.is_frozen() {
+        open.0 = true;
+        freeze.freeze();
+    }
+}
+
+fn show_skill_tree(
+    mut open: ResMut<SkillTreeOpen>,
+    actions: Query<&ActionState<UiAction>>,
+    mut players: Query<&mut Progression, With<Player>>,
+    config: Res<GameConfig>,
+    mut freeze: ResMut<ActionsFrozen>,
+    theme: Res<UiTheme>,
+    mut egui_contexts: EguiContexts,
+) {
+    if !open.0 {
+        return;
+    }
+    let back_pressed = actions
+        .iter()
+        .any(|actions| actions.just_pressed(UiAction::Back));
+    let Some(mut progression) = players.get_single_mut().ok().filter(|_| !back_pressed) else {
+        open.0 = false;
+        freeze.unfreeze();
+        return;
+    };
+    let needed = config
+        .progression
+        .experience_for_level(progression.level + 1);
+    let mut close = false;
+    egui::CentralPanel::default()
+        .frame(theme.overlay_frame())
+        .show(egui_contexts.ctx_mut(), |ui| {
+            ui.vertical_centered(|ui| {
+                ui.set_max_width(30. * theme.text.body);
+                ui.add_space(2. * theme.spacing.large);
+                ui.heading("Skills");
+                ui.label(format!(
+                    "Level {}: {} / {needed} experience",
+                    progression.level, progression.experience
+                ));
+                ui.label(format!("Skill points: {}", progression.skill_points));
+                ui.separator();
+                for skill in Skill::ALL {
+                    let mut text = format!("{}: {}", skill.name(), skill.description());
+                    if progression.has(skill) {
+                        text.push_str(" (unlocked)");
+                    } else if let Some(required) = skill.requirement() {
+                        text.push_str(&format!(" (needs {})", required.name()));
+                    }
+                    let button = egui::Button::new(text);
+                    if ui
+                        .add_enabled(progression.can_unlock(skill), button)
+                        .clicked()
+                    {
+                        progression.skill_points -= 1;
+                        progression.skills.push(skill);
+                        info!("Unlocked skill {}", skill.name());
+                    }
+                }
+                ui.separator();
+                close = ui.button("Close").clicked();
+            });
+        });
+    if close {
+        open.0 = false;
+        freeze.unfreeze();
+    }
+}
+
+fn close_skill_tree(mut open: ResMut<SkillTreeOpen>, mut freeze: ResMut<ActionsFrozen>) {
+    if open.0 {
+        open.0 = false;
+        freeze.unfreeze();
+    }
+}
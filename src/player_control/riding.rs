@@ -0,0 +1,203 @@
+use crate::{
+    level_instantiation::spawning::objects::mount::Mount,
+    menu::Settings,
+    movement::{
+        character_controller::{GeneralMovementSystemSet, Jump, Sprinting, Walk},
+        physics::TransformInterpolationSet,
+    },
+    player_control::{
+        actions::{DualAxisDataExt, PlayerAction},
+        camera::{CameraUpdateSystemSet, IngameCamera},
+        driving::find_exit_position,
+        player_embodiment::{camera_relative_direction, Player},
+    },
+    util::{criteria::is_frozen, trait_extension::Vec3Ext},
+    world_interaction::interactions_ui::InteractionOpportunity,
+    GameState,
+};
+use bevy::{prelude::*, transform::TransformSystem};
+use bevy_tnua::TnuaToggle;
+use bevy_xpbd_3d::prelude::*;
+use leafwing_input_manager::prelude::ActionState;
+
+/// Lets the player ride [`Mount`]s. Interacting with a mount seats the player on its seat bone and
+/// hands the player's movement input to the mount, which moves with its own speed and turns no faster than
+/// its [`Mount::turn_speed`]. Interacting again dismounts next to the mount, if there is room.
+pub(crate) fn riding_plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        ((dismount, mount).run_if(not(is_frozen)), steer_mounts)
+            .chain()
+            .before(GeneralMovementSystemSet)
+            .run_if(in_state(GameState::Playing)),
+    )
+    .add_systems(
+        PostUpdate,
+        seat_riders
+            .after(TransformInterpolationSet)
+            .before(CameraUpdateSystemSet)
+            .before(TransformSystem::TransformPropagate)
+            .run_if(in_state(GameState::Playing)),
+    );
+}
+
+/// Present on the player while they ride a mount.
+#[derive(Debug, Clone, PartialEq, Component)]
+pub(crate) struct Riding {
+    pub(crate) mount: Entity,
+    /// The bone the player is attached to
+    seat: Entity,
+    collision_layers: CollisionLayers,
+}
+
+fn mount(
+    mut commands: Commands,
+    interaction_opportunity: Res<InteractionOpportunity>,
+    players: Query<(Entity, &ActionState<PlayerAction>, &CollisionLayers), With<Player>>,
+    mounts: Query<&Mount>,
+    riders: Query<&Riding>,
+    children: Query<&Children>,
+    names: Query<&Name>,
+) {
+    let Some((mount_entity, mount)) = interaction_opportunity
+        .0
+        .and_then(|target| Some((target, mounts.get(target).ok()?)))
+    else {
+        return;
+    };
+    if riders.iter().any(|riding| riding.mount == mount_entity) {
+        return;
+    }
+    for (player, actions, collision_layers) in players.iter() {
+        if !actions.just_pressed(PlayerAction::Interact) || riders.contains(player) {
+            continue;
+        }
+        let seat = children
+            .iter_descendants(mount_entity)
+            .find(|child| {
+                names
+                    .get(*child)
+                    .is_ok_and(|name| name.as_str() == mount.seat_bone)
+            })
+            .unwrap_or(mount_entity);
+        commands.entity(player).insert((
+            Riding {
+                mount: mount_entity,
+                seat,
+                collision_layers: *collision_layers,
+            },
+            RigidBody::Kinematic,
+            CollisionLayers::NONE,
+            LinearVelocity::ZERO,
+            TnuaToggle::Disabled,
+        ));
+    }
+}
+
+fn dismount(
+    mut commands: Commands,
+    mut players: Query<
+        (
+            Entity,
+            &ActionState<PlayerAction>,
+            &Riding,
+            &Collider,
+            &mut Transform,
+        ),
+        With<Player>,
+    >,
+    mut mounts: Query<(&Transform, &mut Walk), (With<Mount>, Without<Player>)>,
+    spatial_query: SpatialQuery,
+) {
+    for (player, actions, riding, collider, mut transform) in players.iter_mut() {
+        if !actions.just_pressed(PlayerAction::Interact) {
+            continue;
+        }
+        let Ok((mount_transform, mut walk)) = mounts.get_mut(riding.mount) else {
+            continue;
+        };
+        let Some(position) = find_exit_position(mount_transform, collider, &spatial_query) else {
+            info!("No room to dismount");
+            continue;
+        };
+        walk.direction = None;
+        transform.translation = position;
+        transform.rotation =
+            Quat::from_rotation_y(mount_transform.rotation.to_euler(EulerRot::YXZ).0);
+        commands.entity(player).remove::<Riding>().insert((
+            RigidBody::Dynamic,
+            riding.collision_layers,
+            TnuaToggle::Enabled,
+        ));
+    }
+}
+
+/// Like [`handle_horizontal_movement`](crate::player_control::player_embodiment), but the mount
+/// only turns gradually towards the requested direction and always moves the way it is facing.
+fn steer_mounts(
+    time: Res<Time<Virtual>>,
+    settings: Res<Settings>,
+    riders: Query<(&ActionState<PlayerAction>, &Riding), With<Player>>,
+    mut mounts: Query<(&Mount, &Transform, &mut Walk, &mut Sprinting, &mut Jump), Without<Player>>,
+    cameras: Query<(&IngameCamera, &Transform), Without<Mount>>,
+) {
+    let Some((camera, camera_transform)) = cameras.iter().next() else {
+        return;
+    };
+    let dt = time.delta_seconds();
+    for (actions, riding) in riders.iter() {
+        let Ok((mount, transform, mut walk, mut sprint, mut jump)) = mounts.get_mut(riding.mount)
+        else {
+            continue;
+        };
+        jump.requested |= actions.pressed(PlayerAction::Jump);
+        let Some(movement) = actions
+            .axis_pair(PlayerAction::Move)
+            .and_then(|axis| axis.max_normalized())
+        else {
+            walk.direction = None;
+            sprint.requested = false;
+            continue;
+        };
+        let desired = camera_relative_direction(movement, camera, camera_transform);
+        let forward = transform.forward().horizontal().normalize_or_zero();
+        let angle = forward.angle_between(desired.normalize_or_zero());
+        let max_turn = mount.turn_speed * dt;
+        let direction = if angle <= max_turn || forward.is_approx_zero() {
+            desired.normalize_or_zero()
+        } else {
+            let side = forward.cross(desired).y.signum();
+            Quat::from_rotation_y(side * max_turn) * forward
+        };
+        walk.direction = Some(direction * desired.length());
+        sprint.requested = settings.accessibility.sprint_mode.is_active(
+            actions,
+            PlayerAction::Sprint,
+            sprint.requested,
+        );
+    }
+}
+
+/// Keeps riders on their seat bone. They are not made children of the bone, since a physics body
+/// inside another body's hierarchy would be merged into it.
+fn seat_riders(
+    mut riders: Query<(&Riding, &mut Transform, &mut LinearVelocity)>,
+    mounts: Query<(&Mount, &Transform, &GlobalTransform), Without<Riding>>,
+    global_transforms: Query<&GlobalTransform>,
+) {
+    for (riding, mut transform, mut velocity) in riders.iter_mut() {
+        let Ok((mount, mount_transform, mount_global)) = mounts.get(riding.mount) else {
+            continue;
+        };
+        // The seat's pose relative to the mount is from the last frame, but the mount itself has already moved
+        let seat_global = global_transforms
+            .get(riding.seat)
+            .copied()
+            .unwrap_or(*mount_global);
+        let seat = seat_global.reparented_to(mount_global);
+        let seat_in_world = mount_transform.mul_transform(seat);
+        transform.translation = seat_in_world.transform_point(mount.seat_offset);
+        transform.rotation = mount_transform.rotation;
+        velocity.0 = Vec3::ZERO;
+    }
+}
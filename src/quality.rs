@@ -0,0 +1,233 @@
+use crate::menu::Settings;
+use bevy::{
+    pbr::{CascadeShadowConfig, CascadeShadowConfigBuilder, DirectionalLightShadowMap},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Frames averaged to decide whether the game runs too slow or fast enough for more detail
+const FRAME_SAMPLES: usize = 60;
+/// Seconds the framerate has to stay below the target before quality is lowered
+const DOWNGRADE_AFTER: f32 = 2.;
+/// Seconds the framerate has to stay well above the target before quality is raised again
+const UPGRADE_AFTER: f32 = 8.;
+/// Fraction of the target frame time below which there is room for more detail
+const UPGRADE_HEADROOM: f32 = 0.7;
+/// Frame time over the target that is tolerated before lowering quality
+const DOWNGRADE_TOLERANCE: f32 = 1.1;
+
+/// Applies the graphics [`QualityLevel`] chosen in the [`Settings::graphics`]:
+/// shadow map resolution, shadow draw distance, anti-aliasing and particle density.
+/// With dynamic quality turned on, the chosen level is only where the game starts from; the [`CurrentQuality`] is then
+/// stepped down when the frame time stays above the target framerate and back up when there is plenty of headroom.
+/// The delays before stepping differ, so that quality does not flip back and forth around the target.
+/// Bevy has no render scale yet, so the resolution itself is not changed.
+pub(crate) fn quality_plugin(app: &mut App) {
+    app.init_resource::<CurrentQuality>()
+        .init_resource::<FrameTimeMonitor>()
+        .add_systems(
+            Update,
+            (
+                follow_quality_settings.run_if(resource_changed::<Settings>()),
+                scale_quality_dynamically,
+                apply_quality.run_if(resource_changed::<CurrentQuality>()),
+                apply_quality_to_new_lights,
+            )
+                .chain(),
+        );
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
+pub(crate) enum QualityLevel {
+    Low,
+    Medium,
+    #[default]
+    High,
+    Ultra,
+}
+
+impl QualityLevel {
+    pub(crate) const ALL: [Self; 4] = [Self::Low, Self::Medium, Self::High, Self::Ultra];
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Low => "Low",
+            Self::Medium => "Medium",
+            Self::High => "High",
+            Self::Ultra => "Ultra",
+        }
+    }
+
+    fn lower(self) -> Option<Self> {
+        match self {
+            Self::Low => None,
+            Self::Medium => Some(Self::Low),
+            Self::High => Some(Self::Medium),
+            Self::Ultra => Some(Self::High),
+        }
+    }
+
+    fn higher(self) -> Option<Self> {
+        match self {
+            Self::Low => Some(Self::Medium),
+            Self::Medium => Some(Self::High),
+            Self::High => Some(Self::Ultra),
+            Self::Ultra => None,
+        }
+    }
+
+    fn shadow_map_size(self) -> usize {
+        match self {
+            Self::Low => 512,
+            Self::Medium => 1024,
+            Self::High => 2048,
+            Self::Ultra => 4096,
+        }
+    }
+
+    /// Distance up to which the sun casts shadows
+    fn shadow_distance(self) -> f32 {
+        match self {
+            Self::Low => 30.,
+            Self::Medium => 60.,
+            Self::High => 100.,
+            Self::Ultra => 150.,
+        }
+    }
+
+    fn msaa(self) -> Msaa {
+        match self {
+            Self::Low => Msaa::Off,
+            Self::Medium => Msaa::Sample2,
+            Self::High | Self::Ultra => Msaa::Sample4,
+        }
+    }
+
+    /// Fraction of optional particle effects that are spawned
+    pub(crate) fn particle_density(self) -> f32 {
+        match self {
+            Self::Low => 0.25,
+            Self::Medium => 0.5,
+            Self::High | Self::Ultra => 1.,
+        }
+    }
+}
+
+/// The quality level in effect right now, which differs from the chosen one while it is scaled dynamically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Resource, Default, Deref)]
+pub(crate) struct CurrentQuality(pub(crate) QualityLevel);
+
+#[derive(Debug, Clone, PartialEq, Resource, Default)]
+struct FrameTimeMonitor {
+    /// Frame times in seconds, oldest first
+    samples: VecDeque<f32>,
+    /// Seconds the frame time has been above the target
+    too_slow: f32,
+    /// Seconds the frame time has been well below the target
+    headroom: f32,
+}
+
+impl FrameTimeMonitor {
+    fn reset(&mut self) {
+        self.too_slow = 0.;
+        self.headroom = 0.;
+    }
+}
+
+fn follow_quality_settings(
+    settings: Res<Settings>,
+    mut current_quality: ResMut<CurrentQuality>,
+    mut last_chosen: Local<Option<QualityLevel>>,
+) {
+    let chosen = settings.graphics.quality;
+    // A level picked by the player always overrides the dynamically chosen one
+    if *last_chosen != Some(chosen) || !settings.graphics.dynamic_quality {
+        *last_chosen = Some(chosen);
+        current_quality.set_if_neq(CurrentQuality(chosen));
+    }
+}
+
+fn scale_quality_dynamically(
+    time: Res<Time<Real>>,
+    settings: Res<Settings>,
+    mut monitor: ResMut<FrameTimeMonitor>,
+    mut current_quality: ResMut<CurrentQuality>,
+) {
+    let graphics = &settings.graphics;
+    if !graphics.dynamic_quality {
+        monitor.reset();
+        return;
+    }
+    let delta = time.delta_seconds();
+    monitor.samples.push_back(delta);
+    while monitor.samples.len() > FRAME_SAMPLES {
+        monitor.samples.pop_front();
+    }
+    let average = monitor.samples.iter().sum::<f32>() / monitor.samples.len() as f32;
+    let target = 1. / graphics.target_fps.max(1.);
+
+    if average > target * DOWNGRADE_TOLERANCE {
+        monitor.too_slow += delta;
+        monitor.headroom = 0.;
+    } else if average < target * UPGRADE_HEADROOM {
+        monitor.headroom += delta;
+        monitor.too_slow = 0.;
+    } else {
+        monitor.reset();
+    }
+
+    let next = if monitor.too_slow > DOWNGRADE_AFTER {
+        current_quality.0.lower()
+    } else if monitor.headroom > UPGRADE_AFTER {
+        // Never go above what the player chose
+        current_quality
+            .0
+            .higher()
+            .filter(|higher| *higher <= graphics.quality)
+    } else {
+        None
+    };
+    if let Some(next) = next {
+        info!(
+            "Changing graphics quality to {} to hold {} FPS",
+            next.name(),
+            graphics.target_fps
+        );
+        current_quality.0 = next;
+        // The new level needs a moment to show its effect on the frame time
+        monitor.samples.clear();
+        monitor.reset();
+    }
+}
+
+fn apply_quality(
+    mut commands: Commands,
+    current_quality: Res<CurrentQuality>,
+    mut shadow_map: ResMut<DirectionalLightShadowMap>,
+    mut lights: Query<&mut CascadeShadowConfig, With<DirectionalLight>>,
+) {
+    let quality = current_quality.0;
+    shadow_map.size = quality.shadow_map_size();
+    commands.insert_resource(quality.msaa());
+    for mut cascades in lights.iter_mut() {
+        *cascades = shadow_config(quality);
+    }
+}
+
+fn apply_quality_to_new_lights(
+    current_quality: Res<CurrentQuality>,
+    mut lights: Query<&mut CascadeShadowConfig, Added<DirectionalLight>>,
+) {
+    for mut cascades in lights.iter_mut() {
+        *cascades = shadow_config(current_quality.0);
+    }
+}
+
+fn shadow_config(quality: QualityLevel) -> CascadeShadowConfig {
+    CascadeShadowConfigBuilder {
+        maximum_distance: quality.shadow_distance(),
+        ..default()
+    }
+    .into()
+}
@@ -0,0 +1,75 @@
+use crate::{file_system_interaction::replay::SessionSeed, GameState};
+use bevy::{prelude::*, utils::HashMap};
+use rand::{rngs::StdRng, SeedableRng};
+
+/// Hands out the randomness that influences gameplay, all derived from the [`SessionSeed`],
+/// so that a run can be reproduced for replays, tests and debugging procedural content.
+/// Every subsystem draws from its own [`RngStream`] of the [`GameRng`], so that one of them drawing more or fewer numbers,
+/// e.g. fewer particles on a lower graphics quality, does not change the numbers the others get.
+/// The streams restart whenever the seed changes and whenever a level is entered, which is where replays start.
+/// Content that must look the same in every run, like scattered props, uses [`GameRng::keyed`] instead.
+pub(crate) fn rng_plugin(app: &mut App) {
+    app.init_resource::<GameRng>()
+        .add_systems(OnEnter(GameState::Playing), reseed_game_rng)
+        .add_systems(
+            PreUpdate,
+            reseed_game_rng.run_if(resource_changed::<SessionSeed>()),
+        );
+}
+
+/// The independent sequences of random numbers of the [`GameRng`].
+/// New consumers, like loot drops once the game has any, get a stream of their own instead of sharing one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum RngStream {
+    /// Blinking and other idle motion of faces
+    Faces,
+    /// Variation of particle effects
+    Particles,
+    /// Placement of props in scatter regions
+    Scatter,
+    /// Where NPCs stroll to around the places of their schedules
+    Wandering,
+    /// Layouts of generated levels. Last, so that the streams before it do not depend on the feature.
+    #[cfg(feature = "procgen")]
+    Levels,
+}
+
+impl RngStream {
+    /// Spreads the streams far apart in the seed space
+    fn salt(self) -> u64 {
+        (self as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+    }
+}
+
+#[derive(Debug, Resource, Default)]
+pub(crate) struct GameRng {
+    seed: u64,
+    streams: HashMap<RngStream, StdRng>,
+}
+
+impl GameRng {
+    /// The generator of `stream`, which continues where it was left off the last time it was used
+    pub(crate) fn stream(&mut self, stream: RngStream) -> &mut StdRng {
+        let seed = self.seed;
+        self.streams
+            .entry(stream)
+            .or_insert_with(|| StdRng::seed_from_u64(seed ^ stream.salt()))
+    }
+
+    /// A fresh generator that only depends on `stream` and `key`, not on the session,
+    /// for content that should come out the same in every run
+    pub(crate) fn keyed(stream: RngStream, key: u64) -> StdRng {
+        StdRng::seed_from_u64(key ^ stream.salt())
+    }
+
+    /// Restarts every stream from `seed`
+    pub(crate) fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.streams.clear();
+    }
+}
+
+fn reseed_game_rng(seed: Res<SessionSeed>, mut rng: ResMut<GameRng>) {
+    debug!("Seeding gameplay randomness with {}", seed.0);
+    rng.reseed(seed.0);
+}
@@ -0,0 +1,243 @@
+use crate::{
+    game_events::{DialogEnded, DialogStarted},
+    menu::Settings,
+    GameState,
+};
+use bevy::{prelude::*, utils::HashMap};
+use bevy_egui::{egui, EguiContexts};
+
+/// Height of the letterbox bars at the top and bottom of the screen, as a fraction of the screen height
+pub(crate) const LETTERBOX_HEIGHT: f32 = 0.1;
+const DIALOG_LETTERBOX_DURATION: f32 = 0.3;
+
+/// Covers and reveals the screen with full-screen effects, see [`TransitionEffect`].
+/// A transition is started with a [`ScreenTransition`] event and animates the effect from how much it currently covers
+/// the screen to fully covered or clear. Effects stay as they were left, so a screen faded to black stays black until revealed.
+/// Once a transition is done, a [`TransitionFinished`] is sent, which gameplay can wait for, e.g. to only
+/// leave a level once the screen is black.
+/// Used by level transitions, cutscenes, respawning and dialogs, which are letterboxed while they run.
+/// All effects are cleared when a level starts loading or the menu is opened.
+pub(crate) fn screen_transition_plugin(app: &mut App) {
+    app.add_event::<ScreenTransition>()
+        .add_event::<TransitionFinished>()
+        .init_resource::<ScreenTransitions>()
+        .add_systems(
+            Update,
+            letterbox_dialogs
+                .before(start_transitions)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            Update,
+            (start_transitions, advance_transitions, draw_transitions).chain(),
+        )
+        .add_systems(OnEnter(GameState::Loading), clear_transitions)
+        .add_systems(OnEnter(GameState::Menu), clear_transitions);
+}
+
+/// A way of covering the screen. Each effect covers the screen independently of the others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum TransitionEffect {
+    /// Blends the whole screen into black
+    FadeToBlack,
+    /// Blends the whole screen into white, e.g. for flashes
+    FadeToWhite,
+    /// Black bars sliding in from the top and bottom
+    Letterbox,
+    /// A black circle closing in on the center of the screen. Fades instead if reduced motion is enabled.
+    Iris,
+}
+
+/// Animates `effect` to fully cover the screen or clear it within `duration` seconds.
+#[derive(Debug, Clone, PartialEq, Event)]
+pub(crate) struct ScreenTransition {
+    pub(crate) effect: TransitionEffect,
+    /// 1 for fully covered, 0 for clear
+    pub(crate) target: f32,
+    pub(crate) duration: f32,
+    /// Coverage to start from instead of the current one
+    pub(crate) start: Option<f32>,
+}
+
+impl ScreenTransition {
+    pub(crate) fn cover(effect: TransitionEffect, duration: f32) -> Self {
+        Self {
+            effect,
+            target: 1.,
+            duration,
+            start: None,
+        }
+    }
+
+    pub(crate) fn reveal(effect: TransitionEffect, duration: f32) -> Self {
+        Self {
+            effect,
+            target: 0.,
+            duration,
+            start: None,
+        }
+    }
+
+    /// Starts from a fully covered screen, e.g. when revealing a level that was just loaded behind an opaque loading screen.
+    pub(crate) fn from_covered(self) -> Self {
+        Self {
+            start: Some(1.),
+            ..self
+        }
+    }
+}
+
+/// Sent once `effect` has reached the target of the last [`ScreenTransition`] started for it.
+#[derive(Debug, Clone, PartialEq, Event)]
+pub(crate) struct TransitionFinished {
+    pub(crate) effect: TransitionEffect,
+    pub(crate) covered: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Resource, Default)]
+struct ScreenTransitions(HashMap<TransitionEffect, EffectState>);
+
+#[derive(Debug, Clone, PartialEq, Default)]
+struct EffectState {
+    /// How much of the screen is covered, from 0 to 1
+    coverage: f32,
+    target: f32,
+    /// Coverage per second
+    speed: f32,
+}
+
+impl EffectState {
+    fn is_done(&self) -> bool {
+        self.coverage == self.target
+    }
+}
+
+fn letterbox_dialogs(
+    mut started: EventReader<DialogStarted>,
+    mut ended: EventReader<DialogEnded>,
+    mut transitions: EventWriter<ScreenTransition>,
+) {
+    if started.read().last().is_some() {
+        transitions.send(ScreenTransition::cover(
+            TransitionEffect::Letterbox,
+            DIALOG_LETTERBOX_DURATION,
+        ));
+    }
+    if ended.read().last().is_some() {
+        transitions.send(ScreenTransition::reveal(
+            TransitionEffect::Letterbox,
+            DIALOG_LETTERBOX_DURATION,
+        ));
+    }
+}
+
+fn start_transitions(
+    mut events: EventReader<ScreenTransition>,
+    mut transitions: ResMut<ScreenTransitions>,
+) {
+    for event in events.read() {
+        let state = transitions.0.entry(event.effect).or_default();
+        if let Some(start) = event.start {
+            state.coverage = start.clamp(0., 1.);
+        }
+        state.target = event.target.clamp(0., 1.);
+        // Transitions that start partway take proportionally less time
+        state.speed = if event.duration > 0. {
+            1. / event.duration
+        } else {
+            f32::INFINITY
+        };
+    }
+}
+
+fn advance_transitions(
+    time: Res<Time<Real>>,
+    mut transitions: ResMut<ScreenTransitions>,
+    mut finished_events: EventWriter<TransitionFinished>,
+) {
+    let delta = time.delta_seconds();
+    for (effect, state) in transitions.0.iter_mut() {
+        if state.is_done() {
+            continue;
+        }
+        let step = state.speed * delta;
+        state.coverage = if state.coverage < state.target {
+            (state.coverage + step).min(state.target)
+        } else {
+            (state.coverage - step).max(state.target)
+        };
+        if state.is_done() {
+            finished_events.send(TransitionFinished {
+                effect: *effect,
+                covered: state.target >= 1.,
+            });
+        }
+    }
+    transitions
+        .0
+        .retain(|_, state| !(state.is_done() && state.coverage <= 0.));
+}
+
+fn draw_transitions(
+    transitions: Res<ScreenTransitions>,
+    settings: Res<Settings>,
+    mut egui_contexts: EguiContexts,
+) {
+    if transitions.0.is_empty() {
+        return;
+    }
+    let ctx = egui_contexts.ctx_mut();
+    let screen = ctx.screen_rect();
+    for (effect, state) in transitions.0.iter() {
+        let coverage = state.coverage;
+        // Letterboxing sits below the UI, e.g. subtitles, while everything else hides it
+        let order = if *effect == TransitionEffect::Letterbox {
+            egui::Order::Background
+        } else {
+            egui::Order::Foreground
+        };
+        let painter = ctx.layer_painter(egui::LayerId::new(
+            order,
+            egui::Id::new(("screen_transition", format!("{effect:?}"))),
+        ));
+        let alpha = (coverage * 255.) as u8;
+        match effect {
+            TransitionEffect::FadeToBlack => {
+                painter.rect_filled(screen, 0., egui::Color32::from_black_alpha(alpha));
+            }
+            TransitionEffect::FadeToWhite => {
+                painter.rect_filled(screen, 0., egui::Color32::from_white_alpha(alpha));
+            }
+            TransitionEffect::Letterbox => {
+                let bar_height = screen.height() * LETTERBOX_HEIGHT * coverage;
+                for bar in [
+                    egui::Rect::from_min_size(screen.min, egui::vec2(screen.width(), bar_height)),
+                    egui::Rect::from_min_size(
+                        egui::pos2(screen.min.x, screen.max.y - bar_height),
+                        egui::vec2(screen.width(), bar_height),
+                    ),
+                ] {
+                    painter.rect_filled(bar, 0., egui::Color32::BLACK);
+                }
+            }
+            TransitionEffect::Iris if settings.accessibility.reduce_motion => {
+                painter.rect_filled(screen, 0., egui::Color32::from_black_alpha(alpha));
+            }
+            TransitionEffect::Iris => {
+                // A ring as thick as the screen is wide, whose hole shrinks to nothing
+                let max_radius = screen.size().length() / 2.;
+                let hole = max_radius * (1. - coverage);
+                let thickness = max_radius * 2.;
+                painter.circle_stroke(
+                    screen.center(),
+                    hole + thickness / 2.,
+                    egui::Stroke::new(thickness, egui::Color32::BLACK),
+                );
+            }
+        }
+    }
+}
+
+fn clear_transitions(mut transitions: ResMut<ScreenTransitions>) {
+    transitions.0.clear();
+}
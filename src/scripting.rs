@@ -0,0 +1,319 @@
+use crate::{
+    game_events::{
+        CollectibleCollected, CollectibleSetCompleted, DialogEnded, DialogStarted, ItemCrafted,
+        ItemPickedUp, LevelLoaded, PlayerDamaged, PlayerDied, PlayerLeveledUp, QuestCompleted,
+        QuestStarted,
+    },
+    level_instantiation::spawning::GltfExtrasAppExt,
+    scripting::api::{create_engine, ScriptWorld},
+    GameState,
+};
+use anyhow::{Context, Result};
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    ecs::{event::ManualEventReader, system::SystemParam},
+    prelude::*,
+    utils::{BoxedFuture, HashMap},
+};
+use rhai::{CallFnOptions, Dynamic, Engine, FuncArgs, Map, Scope, AST};
+use serde::{Deserialize, Serialize};
+
+mod api;
+
+/// Lets designers add behavior in [Rhai](https://rhai.rs) scripts instead of Rust, with the `scripting` feature.
+/// An entity runs a script when it has a [`Scripted`] component, e.g. through the custom property
+/// `"script": "scripts/door.rhai"` in Blender. Scripts are assets, so saving one while the game runs reloads it.
+/// A script can define any of these functions, which are called with the entity's own state as `this`:
+/// - `on_start(entity)`: once the script has been loaded
+/// - `on_update(entity, delta)`: every frame while playing, with the seconds since the last frame
+/// - `on_event(entity, name, payload)`: for every [`ScriptEvent`]
+///
+/// See [`create_engine`] for what scripts can do in turn.
+/// Dialogs send script events with `<<script_event name>>`. The [game events](crate::game_events) are forwarded as
+/// `dialog_started` (with the node as payload), `dialog_complete`, `level_loaded` (level), `item_picked_up` (item),
+/// `quest_started` (quest), `quest_completed` (quest), `player_damaged` (amount), `player_died`, `collectible_collected` (`<set>:<id>`)
+/// `collectible_set_completed` (set), `item_crafted` (item) and `player_leveled_up` (level).
+pub(crate) fn scripting_plugin(app: &mut App) {
+    app.register_type::<Scripted>()
+        .register_gltf_extra("script", |entity, value| {
+            let path = value.as_str().context("Expected a path to a script")?;
+            entity.insert(Scripted {
+                path: path.to_string(),
+            });
+            Ok(())
+        })
+        .init_asset::<Script>()
+        .init_asset_loader::<ScriptLoader>()
+        .add_event::<ScriptEvent>()
+        .insert_non_send_resource(ScriptRuntime::default())
+        .add_systems(
+            Update,
+            (load_scripts, forward_game_events, run_scripts)
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+/// Runs the script at `path`, relative to the assets directory.
+#[derive(Debug, Clone, PartialEq, Eq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Scripted {
+    pub(crate) path: String,
+}
+
+/// Sent by scripts and dialogs, and received by every script's `on_event`.
+/// Rust code can send and read these as well to talk to scripts.
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub(crate) struct ScriptEvent {
+    pub(crate) name: String,
+    pub(crate) payload: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Asset, TypePath)]
+pub(crate) struct Script {
+    source: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Component)]
+struct ScriptHandle(Handle<Script>);
+
+#[derive(Debug, Clone, Copy, Default)]
+struct ScriptLoader;
+
+impl AssetLoader for ScriptLoader {
+    type Asset = Script;
+    type Settings = ();
+    type Error = anyhow::Error;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut source = String::new();
+            reader.read_to_string(&mut source).await?;
+            Ok(Script { source })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["rhai"]
+    }
+}
+
+/// Not `Send`, since the functions registered on the engine share the [`ScriptWorld`].
+struct ScriptRuntime {
+    engine: Engine,
+    world: ScriptWorld,
+    /// Recompiled whenever their asset changes
+    compiled: HashMap<AssetId<Script>, CompiledScript>,
+    /// The `this` of every scripted entity, in which scripts keep their state
+    states: HashMap<Entity, Dynamic>,
+    script_events: ManualEventReader<ScriptEvent>,
+    asset_events: ManualEventReader<AssetEvent<Script>>,
+}
+
+struct CompiledScript {
+    path: String,
+    ast: AST,
+}
+
+impl Default for ScriptRuntime {
+    fn default() -> Self {
+        let world = ScriptWorld::default();
+        Self {
+            engine: create_engine(&world),
+            world,
+            compiled: default(),
+            states: default(),
+            script_events: default(),
+            asset_events: default(),
+        }
+    }
+}
+
+impl ScriptRuntime {
+    fn compile_changed_scripts(&mut self, world: &World) {
+        let scripts = world.resource::<Assets<Script>>();
+        let asset_events = world.resource::<Events<AssetEvent<Script>>>();
+        for event in self.asset_events.read(asset_events) {
+            let id = match *event {
+                AssetEvent::Added { id } | AssetEvent::Modified { id } => id,
+                AssetEvent::Removed { id } => {
+                    self.compiled.remove(&id);
+                    continue;
+                }
+                _ => continue,
+            };
+            let Some(script) = scripts.get(id) else {
+                continue;
+            };
+            let path = world
+                .resource::<AssetServer>()
+                .get_path(id)
+                .map_or_else(|| format!("{id:?}"), |path| path.to_string());
+            match self.engine.compile(&script.source) {
+                Ok(ast) => {
+                    info!("Compiled script {path}");
+                    self.compiled.insert(id, CompiledScript { path, ast });
+                }
+                Err(error) => error!("Failed to compile script {path}: {error}"),
+            }
+        }
+    }
+}
+
+fn load_scripts(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    scripted: Query<(Entity, &Scripted), Changed<Scripted>>,
+) {
+    for (entity, scripted) in scripted.iter() {
+        let handle = asset_server.load(scripted.path.clone());
+        commands.entity(entity).insert(ScriptHandle(handle));
+    }
+}
+
+#[derive(SystemParam)]
+struct GameEvents<'w, 's> {
+    player_damaged: EventReader<'w, 's, PlayerDamaged>,
+    player_died: EventReader<'w, 's, PlayerDied>,
+    items_picked_up: EventReader<'w, 's, ItemPickedUp>,
+    dialogs_started: EventReader<'w, 's, DialogStarted>,
+    dialogs_ended: EventReader<'w, 's, DialogEnded>,
+    levels_loaded: EventReader<'w, 's, LevelLoaded>,
+    quests_started: EventReader<'w, 's, QuestStarted>,
+    quests_completed: EventReader<'w, 's, QuestCompleted>,
+    collectibles_collected: EventReader<'w, 's, CollectibleCollected>,
+    collectible_sets_completed: EventReader<'w, 's, CollectibleSetCompleted>,
+    items_crafted: EventReader<'w, 's, ItemCrafted>,
+    players_leveled_up: EventReader<'w, 's, PlayerLeveledUp>,
+}
+
+fn forward_game_events(mut game_events: GameEvents, mut script_events: EventWriter<ScriptEvent>) {
+    let mut forward = |name: &str, payload: String| {
+        script_events.send(ScriptEvent {
+            name: name.to_string(),
+            payload,
+        })
+    };
+    for event in game_events.player_damaged.read() {
+        forward("player_damaged", event.amount.to_string());
+    }
+    for _ in game_events.player_died.read() {
+        forward("player_died", String::new());
+    }
+    for event in game_events.items_picked_up.read() {
+        forward("item_picked_up", event.item.clone());
+    }
+    for event in game_events.dialogs_started.read() {
+        forward("dialog_started", event.node.clone());
+    }
+    for _ in game_events.dialogs_ended.read() {
+        forward("dialog_complete", String::new());
+    }
+    for event in game_events.levels_loaded.read() {
+        forward("level_loaded", event.level.clone());
+    }
+    for event in game_events.quests_started.read() {
+        forward("quest_started", event.id.clone());
+    }
+    for event in game_events.quests_completed.read() {
+        forward("quest_completed", event.id.clone());
+    }
+    for event in game_events.collectibles_collected.read() {
+        forward(
+            "collectible_collected",
+            format!("{}:{}", event.set, event.id),
+        );
+    }
+    for event in game_events.collectible_sets_completed.read() {
+        forward("collectible_set_completed", event.set.clone());
+    }
+    for event in game_events.items_crafted.read() {
+        forward("item_crafted", event.item.clone());
+    }
+    for event in game_events.players_leveled_up.read() {
+        forward("player_leveled_up", event.level.to_string());
+    }
+}
+
+fn run_scripts(world: &mut World) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("run_scripts").entered();
+    let Some(mut runtime) = world.remove_non_send_resource::<ScriptRuntime>() else {
+        return;
+    };
+    runtime.compile_changed_scripts(world);
+    let events: Vec<_> = runtime
+        .script_events
+        .read(world.resource::<Events<ScriptEvent>>())
+        .cloned()
+        .collect();
+    let delta = world.resource::<Time>().delta_seconds_f64();
+    let scripted: Vec<_> = world
+        .query::<(Entity, &ScriptHandle)>()
+        .iter(world)
+        .map(|(entity, handle)| (entity, handle.0.id()))
+        .collect();
+    runtime
+        .states
+        .retain(|entity, _| scripted.iter().any(|(scripted, _)| scripted == entity));
+
+    let ScriptRuntime {
+        engine,
+        world: script_world,
+        compiled,
+        states,
+        ..
+    } = &mut runtime;
+    script_world.lend(world, || {
+        for (entity, id) in scripted {
+            // Not loaded yet or failed to compile
+            let Some(script) = compiled.get(&id) else {
+                continue;
+            };
+            let entity_id = entity.to_bits() as i64;
+            let this = states.entry(entity).or_insert_with(|| {
+                let mut this = Dynamic::from_map(Map::new());
+                call(engine, script, &mut this, "on_start", (entity_id,));
+                this
+            });
+            call(engine, script, this, "on_update", (entity_id, delta));
+            for event in &events {
+                let args = (entity_id, event.name.clone(), event.payload.clone());
+                call(engine, script, this, "on_event", args);
+            }
+        }
+    });
+    world.insert_non_send_resource(runtime);
+}
+
+/// Calls `function` if the script defines it and logs any error it throws.
+fn call(
+    engine: &Engine,
+    script: &CompiledScript,
+    this: &mut Dynamic,
+    function: &str,
+    args: impl FuncArgs,
+) {
+    if !script
+        .ast
+        .iter_functions()
+        .any(|definition| definition.name == function)
+    {
+        return;
+    }
+    let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(this);
+    if let Err(error) = engine.call_fn_with_options::<Dynamic>(
+        options,
+        &mut Scope::new(),
+        &script.ast,
+        function,
+        args,
+    ) {
+        error!("{function} in script {} failed: {error}", script.path);
+    }
+}
@@ -0,0 +1,281 @@
+use crate::{
+    despawn::DespawnOnExit, game_events::DialogStarted, level_instantiation::prefabs::SpawnPrefab,
+    player_control::actions::ActionsFrozen, scripting::ScriptEvent, GameState,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use bevy::{prelude::*, reflect::GetPath};
+use bevy_yarnspinner::prelude::DialogueRunner;
+use rhai::{Dynamic, Engine, EvalAltResult};
+use std::{cell::RefCell, rc::Rc};
+
+/// The world as seen by scripts. Only holds the game's world while [`ScriptWorld::lend`] runs.
+#[derive(Clone, Default)]
+pub(super) struct ScriptWorld(Rc<RefCell<World>>);
+
+impl ScriptWorld {
+    /// Moves `world` in for the duration of `f`, so that the functions registered by [`create_engine`] can access it.
+    pub(super) fn lend<T>(&self, world: &mut World, f: impl FnOnce() -> T) -> T {
+        std::mem::swap(world, &mut self.0.borrow_mut());
+        let result = f();
+        std::mem::swap(world, &mut self.0.borrow_mut());
+        result
+    }
+
+    fn with<T>(&self, f: impl FnOnce(&mut World) -> Result<T>) -> Result<T, Box<EvalAltResult>> {
+        f(&mut self.0.borrow_mut()).map_err(|error| format!("{error:#}").into())
+    }
+}
+
+/// Creates the engine all scripts run in. Entities are passed to scripts as integers.
+/// Scripts can call:
+/// - `spawn(name, position)`: spawns an empty entity at the position and returns it
+/// - `spawn_prefab(name, position)`: spawns the prefab `prefabs/<name>.prefab.ron` at the position and returns it
+/// - `despawn(entity)`
+/// - `find(name)`: returns the first entity called `name`, or `()` if there is none
+/// - `get(entity, component, path)`: reads a field of a reflected component, e.g. `get(door, "Transform", "translation.y")`
+/// - `set(entity, component, path, value)`: writes a field of a reflected component
+/// - `send_event(name, payload)`: sends a [`ScriptEvent`] to all scripts and to Rust code listening for it
+/// - `start_dialog(node)`: starts the dialog at the given yarn node
+///
+/// Numbers, booleans, strings and vectors can be read and written. Vectors, including positions, are arrays of three numbers.
+/// Leaving `path` empty reads or writes the whole component.
+pub(super) fn create_engine(world: &ScriptWorld) -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .on_print(|text| info!("{text}"))
+        .on_debug(|text, source, position| debug!("{} {position}: {text}", source.unwrap_or("")));
+
+    let script_world = world.clone();
+    engine.register_fn("spawn", move |name: &str, position: Dynamic| {
+        script_world.with(|world| {
+            let entity = world.spawn((
+                Name::new(name.to_string()),
+                SpatialBundle::from_transform(Transform::from_translation(vector(position)?)),
+                DespawnOnExit(GameState::Playing),
+            ));
+            Ok(entity.id().to_bits() as i64)
+        })
+    });
+    let script_world = world.clone();
+    engine.register_fn("spawn_prefab", move |name: &str, position: Dynamic| {
+        script_world.with(|world| {
+            let prefab = SpawnPrefab::new(world.resource::<AssetServer>(), name);
+            let entity = world.spawn((
+                Name::new(name.to_string()),
+                prefab,
+                SpatialBundle::from_transform(Transform::from_translation(vector(position)?)),
+                DespawnOnExit(GameState::Playing),
+            ));
+            Ok(entity.id().to_bits() as i64)
+        })
+    });
+    let script_world = world.clone();
+    engine.register_fn("despawn", move |entity: i64| {
+        script_world.with(|world| {
+            let entity = existing_entity(world, entity)?;
+            world.entity_mut(entity).despawn_recursive();
+            Ok(())
+        })
+    });
+    let script_world = world.clone();
+    engine.register_fn("find", move |name: &str| {
+        script_world.with(|world| {
+            let entity = world
+                .query::<(Entity, &Name)>()
+                .iter(world)
+                .find(|(_, entity_name)| entity_name.as_str() == name)
+                .map_or(Dynamic::UNIT, |(entity, _)| {
+                    Dynamic::from_int(entity.to_bits() as i64)
+                });
+            Ok(entity)
+        })
+    });
+    let script_world = world.clone();
+    engine.register_fn("get", move |entity: i64, component: &str, path: &str| {
+        script_world.with(|world| get_field(world, entity, component, path))
+    });
+    let script_world = world.clone();
+    engine.register_fn(
+        "set",
+        move |entity: i64, component: &str, path: &str, value: Dynamic| {
+            script_world.with(|world| set_field(world, entity, component, path, value))
+        },
+    );
+    let script_world = world.clone();
+    engine.register_fn("send_event", move |name: &str, payload: &str| {
+        script_world.with(|world| {
+            world.send_event(ScriptEvent {
+                name: name.to_string(),
+                payload: payload.to_string(),
+            });
+            Ok(())
+        })
+    });
+    let script_world = world.clone();
+    engine.register_fn("start_dialog", move |node: &str| {
+        script_world.with(|world| start_dialog(world, node))
+    });
+    engine
+}
+
+fn existing_entity(world: &World, bits: i64) -> Result<Entity> {
+    let entity = Entity::from_bits(bits as u64);
+    if world.get_entity(entity).is_none() {
+        bail!("The entity {bits} does not exist");
+    }
+    Ok(entity)
+}
+
+fn component_registration(world: &World, component: &str) -> Result<ReflectComponent> {
+    let registry = world.resource::<AppTypeRegistry>().read();
+    let registration = registry
+        .get_with_short_type_path(component)
+        .with_context(|| format!("There is no type called {component}"))?;
+    registration
+        .data::<ReflectComponent>()
+        .cloned()
+        .with_context(|| format!("{component} is not a reflected component"))
+}
+
+fn get_field(world: &mut World, entity: i64, component: &str, path: &str) -> Result<Dynamic> {
+    let entity = existing_entity(world, entity)?;
+    let reflect_component = component_registration(world, component)?;
+    let reflected = reflect_component
+        .reflect(world.entity(entity))
+        .with_context(|| format!("The entity has no {component}"))?;
+    let field = if path.is_empty() {
+        reflected
+    } else {
+        reflected
+            .reflect_path(path)
+            .map_err(|error| anyhow!("Failed to read {component}.{path}: {error}"))?
+    };
+    to_dynamic(field)
+}
+
+fn set_field(
+    world: &mut World,
+    entity: i64,
+    component: &str,
+    path: &str,
+    value: Dynamic,
+) -> Result<()> {
+    let entity = existing_entity(world, entity)?;
+    let reflect_component = component_registration(world, component)?;
+    let mut entity = world.entity_mut(entity);
+    let mut reflected = reflect_component
+        .reflect_mut(&mut entity)
+        .with_context(|| format!("The entity has no {component}"))?;
+    let field = if path.is_empty() {
+        &mut *reflected
+    } else {
+        reflected
+            .reflect_path_mut(path)
+            .map_err(|error| anyhow!("Failed to write {component}.{path}: {error}"))?
+    };
+    apply_dynamic(field, value)
+}
+
+fn start_dialog(world: &mut World, node: &str) -> Result<()> {
+    let mut dialogue_runner = world
+        .query::<&mut DialogueRunner>()
+        .get_single_mut(world)
+        .context("The dialogs have not been loaded yet")?;
+    if dialogue_runner.is_running() {
+        bail!("Cannot start {node} while another dialog is running");
+    }
+    dialogue_runner.start_node(node);
+    world.resource_mut::<ActionsFrozen>().freeze();
+    world.send_event(DialogStarted {
+        node: node.to_string(),
+    });
+    Ok(())
+}
+
+fn to_dynamic(value: &dyn Reflect) -> Result<Dynamic> {
+    let value = if let Some(value) = value.downcast_ref::<f32>() {
+        Dynamic::from_float(*value as f64)
+    } else if let Some(value) = value.downcast_ref::<f64>() {
+        Dynamic::from_float(*value)
+    } else if let Some(value) = value.downcast_ref::<i32>() {
+        Dynamic::from_int(*value as i64)
+    } else if let Some(value) = value.downcast_ref::<u32>() {
+        Dynamic::from_int(*value as i64)
+    } else if let Some(value) = value.downcast_ref::<usize>() {
+        Dynamic::from_int(*value as i64)
+    } else if let Some(value) = value.downcast_ref::<bool>() {
+        Dynamic::from_bool(*value)
+    } else if let Some(value) = value.downcast_ref::<String>() {
+        Dynamic::from(value.clone())
+    } else if let Some(value) = value.downcast_ref::<Vec3>() {
+        Dynamic::from_array(
+            value
+                .to_array()
+                .into_iter()
+                .map(|component| Dynamic::from_float(component as f64))
+                .collect(),
+        )
+    } else {
+        bail!(
+            "Scripts cannot read values of type {}",
+            value.reflect_type_path()
+        );
+    };
+    Ok(value)
+}
+
+fn apply_dynamic(target: &mut dyn Reflect, value: Dynamic) -> Result<()> {
+    let type_path = target.reflect_type_path().to_string();
+    if let Some(target) = target.downcast_mut::<f32>() {
+        *target = number(&value)? as f32;
+    } else if let Some(target) = target.downcast_mut::<f64>() {
+        *target = number(&value)?;
+    } else if let Some(target) = target.downcast_mut::<i32>() {
+        *target = integer(&value)?.try_into()?;
+    } else if let Some(target) = target.downcast_mut::<u32>() {
+        *target = integer(&value)?.try_into()?;
+    } else if let Some(target) = target.downcast_mut::<usize>() {
+        *target = integer(&value)?.try_into()?;
+    } else if let Some(target) = target.downcast_mut::<bool>() {
+        *target = value
+            .as_bool()
+            .map_err(|actual| anyhow!("Expected a bool, got {actual}"))?;
+    } else if let Some(target) = target.downcast_mut::<String>() {
+        *target = value
+            .into_string()
+            .map_err(|actual| anyhow!("Expected a string, got {actual}"))?;
+    } else if let Some(target) = target.downcast_mut::<Vec3>() {
+        *target = vector(value)?;
+    } else {
+        bail!("Scripts cannot write values of type {type_path}");
+    }
+    Ok(())
+}
+
+/// Accepts integers as well, so that scripts can write `1` instead of `1.0`
+fn number(value: &Dynamic) -> Result<f64> {
+    value
+        .as_float()
+        .or_else(|_| value.as_int().map(|value| value as f64))
+        .map_err(|actual| anyhow!("Expected a number, got {actual}"))
+}
+
+fn vector(value: Dynamic) -> Result<Vec3> {
+    let array = value
+        .into_array()
+        .map_err(|actual| anyhow!("Expected an array, got {actual}"))?;
+    let [x, y, z] = array.as_slice() else {
+        bail!("Expected three numbers, got {}", array.len());
+    };
+    Ok(Vec3::new(
+        number(x)? as f32,
+        number(y)? as f32,
+        number(z)? as f32,
+    ))
+}
+
+fn integer(value: &Dynamic) -> Result<i64> {
+    value
+        .as_int()
+        .map_err(|actual| anyhow!("Expected an integer, got {actual}"))
+}
@@ -2,16 +2,21 @@
 use crate::{file_system_interaction::asset_loading::TextureAssets, GameState};
 use anyhow::Result;
 
-use bevy::prelude::*;
-
-use bevy::render::render_resource::{AsBindGroup, ShaderRef};
+use bevy::{
+    pbr::{ExtendedMaterial, MaterialExtension},
+    prelude::*,
+    render::render_resource::{AsBindGroup, ShaderRef},
+};
 
 /// Handles instantiation of shaders. The shaders can be found in the [`shaders`](https://github.com/janhohenheim/foxtrot/tree/main/assets/shaders) directory.
 /// Shaders are stored in [`Material`]s which can be used on objects by attaching a `Handle<Material>` to an entity.
 /// The handles can be stored and retrieved in the [`ShaderMaterials`] resource.
 pub(crate) fn shader_plugin(app: &mut App) {
     app.add_plugins(MaterialPlugin::<GlowyMaterial>::default())
-        .add_systems(OnExit(GameState::Loading), setup_shader);
+        .add_plugins(MaterialPlugin::<HighlightMaterial>::default())
+        .add_plugins(MaterialPlugin::<TriplanarMaterial>::default())
+        .add_plugins(MaterialPlugin::<SplatMaterial>::default())
+        .add_systems(OnExit(GameState::InitialLoading), setup_shader);
 }
 
 #[derive(Resource, Debug, Clone)]
@@ -44,3 +49,99 @@ impl Material for GlowyMaterial {
         "shaders/glowy.wgsl".into()
     }
 }
+
+/// A [`StandardMaterial`] with a rim glow on top, see [`RimHighlight`].
+pub(crate) type HighlightMaterial = ExtendedMaterial<StandardMaterial, RimHighlight>;
+
+#[derive(AsBindGroup, Debug, Clone, Asset, TypePath)]
+/// Material extension for [`rim_highlight.wgsl`](https://github.com/janhohenheim/foxtrot/blob/main/assets/shaders/rim_highlight.wgsl).
+pub(crate) struct RimHighlight {
+    #[uniform(100)]
+    pub(crate) color: Color,
+    /// The higher, the thinner the rim
+    #[uniform(100)]
+    pub(crate) power: f32,
+}
+
+impl Default for RimHighlight {
+    fn default() -> Self {
+        Self {
+            color: Color::rgb(1.0, 0.8, 0.4),
+            power: 3.0,
+        }
+    }
+}
+
+impl MaterialExtension for RimHighlight {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/rim_highlight.wgsl".into()
+    }
+
+    fn deferred_fragment_shader() -> ShaderRef {
+        "shaders/rim_highlight.wgsl".into()
+    }
+}
+
+/// A [`StandardMaterial`] textured by projecting along the world axes instead of by UVs, see [`Triplanar`].
+pub(crate) type TriplanarMaterial = ExtendedMaterial<StandardMaterial, Triplanar>;
+
+#[derive(AsBindGroup, Debug, Clone, Asset, TypePath)]
+/// Material extension for [`triplanar.wgsl`](https://github.com/janhohenheim/foxtrot/blob/main/assets/shaders/triplanar.wgsl).
+pub(crate) struct Triplanar {
+    /// Repetitions of the texture per meter
+    #[uniform(100)]
+    pub(crate) scale: f32,
+    /// The higher, the harder the transition between the projections on rounded surfaces
+    #[uniform(100)]
+    pub(crate) sharpness: f32,
+    /// Should be sampled with [`ImageAddressMode::Repeat`](bevy::render::texture::ImageAddressMode::Repeat)
+    #[texture(101)]
+    #[sampler(102)]
+    pub(crate) texture: Handle<Image>,
+}
+
+impl MaterialExtension for Triplanar {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/triplanar.wgsl".into()
+    }
+
+    fn deferred_fragment_shader() -> ShaderRef {
+        "shaders/triplanar.wgsl".into()
+    }
+}
+
+/// A [`StandardMaterial`] blending textures by a splat map, see [`Splat`].
+pub(crate) type SplatMaterial = ExtendedMaterial<StandardMaterial, Splat>;
+
+#[derive(AsBindGroup, Debug, Clone, Asset, TypePath)]
+/// Material extension for [`splat.wgsl`](https://github.com/janhohenheim/foxtrot/blob/main/assets/shaders/splat.wgsl).
+/// The layer textures are projected from above and should be sampled with
+/// [`ImageAddressMode::Repeat`](bevy::render::texture::ImageAddressMode::Repeat).
+pub(crate) struct Splat {
+    /// Repetitions of the layer textures per meter
+    #[uniform(100)]
+    pub(crate) tiling: f32,
+    /// Mapped by UVs. Its red, green and blue channels are the weights of the layers.
+    #[texture(101)]
+    #[sampler(102)]
+    pub(crate) splat_map: Handle<Image>,
+    #[texture(103)]
+    #[sampler(104)]
+    pub(crate) red: Handle<Image>,
+    #[texture(105)]
+    #[sampler(106)]
+    pub(crate) green: Handle<Image>,
+    #[texture(107)]
+    #[sampler(108)]
+    pub(crate) blue: Handle<Image>,
+}
+
+impl MaterialExtension for Splat {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/splat.wgsl".into()
+    }
+
+    fn deferred_fragment_shader() -> ShaderRef {
+        "shaders/splat.wgsl".into()
+    }
+}
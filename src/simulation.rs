@@ -0,0 +1,190 @@
+use crate::{
+    bevy_config::Headless,
+    level_instantiation::levels::CurrentLevel,
+    movement::navigation::Follower,
+    player_control::{
+        actions::{ActionSnapshot, InputOverrideSystemSet, PlayerAction},
+        player_embodiment::Player,
+    },
+    GamePlugin, GameState,
+};
+use anyhow::{bail, Result};
+use bevy::{
+    app::PluginsState, prelude::*, tasks::tick_global_task_pools_on_main_thread,
+    time::TimeUpdateStrategy,
+};
+use leafwing_input_manager::prelude::ActionState;
+use std::time::Duration;
+
+/// Duration of every simulated tick
+const TICK: Duration = Duration::from_nanos(1_000_000_000 / 60);
+/// Ticks to wait for the assets and the level to load before giving up
+const MAX_LOADING_TICKS: usize = 60 * 60;
+
+/// Runs the whole game without a window or rendering and advances it tick by tick, for integration tests in CI.
+/// Every tick advances the game by exactly 1/60 of a second, so a simulation behaves the same on every machine.
+/// See `tests/simulation.rs` for the tests run in CI.
+///
+/// ```no_run
+/// # use foxtrot::simulation::{Simulation, SimulatedInput};
+/// # use bevy::prelude::*;
+/// let mut simulation = Simulation::start("World", None).unwrap();
+/// let start = simulation.player_position().unwrap();
+/// simulation.hold(&[SimulatedInput::Move(Vec2::Y)], 120);
+/// assert!(simulation.player_position().unwrap().distance(start) > 3.);
+/// ```
+pub struct Simulation {
+    app: App,
+}
+
+/// Input held by the simulated player, standing in for the keyboard and mouse
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SimulatedInput {
+    /// Walk in a direction relative to the camera, with `y` pointing forward
+    Move(Vec2),
+    Sprint,
+    Crouch,
+    Jump,
+    Interact,
+    Throw,
+}
+
+/// The scripted actions applied to the player every tick
+#[derive(Debug, Clone, PartialEq, Resource, Default)]
+struct SimulatedActions(ActionSnapshot<PlayerAction>);
+
+impl Simulation {
+    /// Loads the game headlessly and enters `level`, placing the player at `spawn_point` if given.
+    pub fn start(level: &str, spawn_point: Option<&str>) -> Result<Self> {
+        let mut app = App::new();
+        app.insert_resource(Headless)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(TICK))
+            .init_resource::<SimulatedActions>()
+            .add_plugins(GamePlugin)
+            .add_systems(
+                PreUpdate,
+                apply_simulated_actions
+                    .in_set(InputOverrideSystemSet)
+                    .run_if(in_state(GameState::Playing)),
+            );
+        // Bevy's runners do the same before the first update
+        while app.plugins_state() == PluginsState::Adding {
+            tick_global_task_pools_on_main_thread();
+        }
+        app.finish();
+        app.cleanup();
+        let mut simulation = Self { app };
+        simulation.run_until(MAX_LOADING_TICKS, |simulation| {
+            simulation.state() == GameState::Menu
+        })?;
+
+        let mut current_level = simulation.app.world.resource_mut::<CurrentLevel>();
+        current_level.name = level.to_string();
+        current_level.spawn_point = spawn_point.map(str::to_string);
+        simulation
+            .app
+            .world
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Loading);
+        simulation.run_until(MAX_LOADING_TICKS, |simulation| {
+            simulation.player_position().is_some()
+        })?;
+        Ok(simulation)
+    }
+
+    /// Advances the game by `ticks` ticks.
+    pub fn tick(&mut self, ticks: usize) {
+        for _ in 0..ticks {
+            self.app.update();
+        }
+    }
+
+    /// Holds `inputs` for `ticks` ticks and releases them afterwards.
+    pub fn hold(&mut self, inputs: &[SimulatedInput], ticks: usize) {
+        let mut actions = ActionSnapshot::default();
+        let mut movement = Vec2::ZERO;
+        for input in inputs {
+            let action = match *input {
+                SimulatedInput::Move(direction) => {
+                    movement = direction;
+                    continue;
+                }
+                SimulatedInput::Sprint => PlayerAction::Sprint,
+                SimulatedInput::Crouch => PlayerAction::Crouch,
+                SimulatedInput::Jump => PlayerAction::Jump,
+                SimulatedInput::Interact => PlayerAction::Interact,
+                SimulatedInput::Throw => PlayerAction::Throw,
+            };
+            actions.pressed.push(action);
+        }
+        actions.axis_pairs.push((PlayerAction::Move, movement));
+        self.app.world.resource_mut::<SimulatedActions>().0 = actions;
+        self.tick(ticks);
+        self.app.world.resource_mut::<SimulatedActions>().0 = default();
+    }
+
+    /// Ticks until `condition` holds and returns how many ticks that took, or fails after `max_ticks`.
+    pub fn run_until(
+        &mut self,
+        max_ticks: usize,
+        mut condition: impl FnMut(&mut Self) -> bool,
+    ) -> Result<usize> {
+        for ticks in 0..max_ticks {
+            if condition(self) {
+                return Ok(ticks);
+            }
+            self.app.update();
+        }
+        if condition(self) {
+            return Ok(max_ticks);
+        }
+        bail!("Condition was not met within {max_ticks} ticks")
+    }
+
+    pub fn player_position(&mut self) -> Option<Vec3> {
+        self.app
+            .world
+            .query_filtered::<&GlobalTransform, With<Player>>()
+            .get_single(&self.app.world)
+            .ok()
+            .map(GlobalTransform::translation)
+    }
+
+    /// Position of the player's companion, which paths after the player over the navmesh
+    pub fn follower_position(&mut self) -> Option<Vec3> {
+        self.app
+            .world
+            .query_filtered::<&GlobalTransform, With<Follower>>()
+            .get_single(&self.app.world)
+            .ok()
+            .map(GlobalTransform::translation)
+    }
+
+    /// Position of the first entity called `name`, e.g. an NPC placed in Blender
+    pub fn position_of(&mut self, name: &str) -> Option<Vec3> {
+        self.app
+            .world
+            .query::<(&Name, &GlobalTransform)>()
+            .iter(&self.app.world)
+            .find(|(entity_name, _)| entity_name.as_str() == name)
+            .map(|(_, transform)| transform.translation())
+    }
+
+    /// Direct access to the simulated world for anything the helpers above don't cover.
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.app.world
+    }
+
+    fn state(&self) -> GameState {
+        self.app.world.resource::<State<GameState>>().get().clone()
+    }
+}
+
+fn apply_simulated_actions(
+    simulated: Res<SimulatedActions>,
+    mut players: Query<&mut ActionState<PlayerAction>, With<Player>>,
+) {
+    for mut actions in &mut players {
+        simulated.0.apply(&mut actions);
+    }
+}
@@ -0,0 +1,456 @@
+use crate::menu::{AccessibilitySettings, Settings};
+use bevy::prelude::*;
+use bevy_egui::{
+    egui,
+    egui::{
+        FontFamily::Proportional,
+        FontId,
+        TextStyle::{Body, Button, Heading, Monospace, Small},
+    },
+    EguiContexts, EguiSettings,
+};
+use serde::{Deserialize, Serialize};
+
+/// Handles the look of all UI. Menus and HUD widgets take their fonts, colors, spacing and panels
+/// from the [`UiTheme`] resource. It is derived from the [`BaseUiTheme`] by applying the player's
+/// [`AccessibilitySettings`], so reskinning the game only means changing the base theme.
+/// On top of the window's DPI scaling, the whole UI is scaled by [`Settings::ui_scale`].
+pub(crate) fn theme_plugin(app: &mut App) {
+    app.init_resource::<BaseUiTheme>()
+        .init_resource::<UiTheme>()
+        .add_systems(
+            Update,
+            (
+                (
+                    update_theme,
+                    apply_theme.run_if(resource_changed::<UiTheme>()),
+                )
+                    .chain(),
+                apply_ui_scale,
+            ),
+        );
+}
+
+/// The theme as designed, before accessibility options are applied.
+#[derive(Debug, Clone, PartialEq, Resource, Default, Deref, DerefMut)]
+pub(crate) struct BaseUiTheme(pub(crate) UiTheme);
+
+#[derive(Debug, Clone, PartialEq, Resource)]
+pub(crate) struct UiTheme {
+    /// Replaces egui's default fonts if set
+    pub(crate) fonts: Option<egui::FontDefinitions>,
+    /// Text sizes used by HUD and windows
+    pub(crate) text: TextSizes,
+    /// Larger text sizes used by full-screen menus
+    pub(crate) menu_text: TextSizes,
+    pub(crate) colors: ThemeColors,
+    pub(crate) spacing: Spacing,
+    pub(crate) panel: PanelStyle,
+}
+
+impl Default for UiTheme {
+    fn default() -> Self {
+        Self {
+            fonts: None,
+            text: TextSizes {
+                heading: 18.,
+                body: 14.,
+                button: 14.,
+                small: 10.,
+            },
+            menu_text: TextSizes {
+                heading: 30.,
+                body: 20.,
+                button: 20.,
+                small: 14.,
+            },
+            colors: default(),
+            spacing: default(),
+            panel: default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct TextSizes {
+    pub(crate) heading: f32,
+    pub(crate) body: f32,
+    pub(crate) button: f32,
+    pub(crate) small: f32,
+}
+
+impl TextSizes {
+    fn scaled(self, factor: f32) -> Self {
+        Self {
+            heading: self.heading * factor,
+            body: self.body * factor,
+            button: self.button * factor,
+            small: self.small * factor,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ThemeColors {
+    pub(crate) text: egui::Color32,
+    pub(crate) weak_text: egui::Color32,
+    pub(crate) accent: egui::Color32,
+    /// Background of windows, popups and HUD elements
+    pub(crate) panel: egui::Color32,
+    /// Background of screens drawn on top of the running game, like the pause menu
+    pub(crate) overlay: egui::Color32,
+    /// Background of screens that hide the game completely, like the main menu
+    pub(crate) backdrop: egui::Color32,
+    pub(crate) health: egui::Color32,
+    pub(crate) stamina: egui::Color32,
+    pub(crate) exhausted: egui::Color32,
+    pub(crate) damage: egui::Color32,
+    pub(crate) heal: egui::Color32,
+    pub(crate) marker: egui::Color32,
+    pub(crate) player_marker: egui::Color32,
+}
+
+impl Default for ThemeColors {
+    fn default() -> Self {
+        Self {
+            text: egui::Color32::from_gray(240),
+            weak_text: egui::Color32::from_gray(180),
+            accent: egui::Color32::from_rgb(255, 170, 60),
+            panel: egui::Color32::from_black_alpha(160),
+            overlay: egui::Color32::from_black_alpha(240),
+            backdrop: egui::Color32::from_gray(27),
+            health: egui::Color32::from_rgb(200, 40, 40),
+            stamina: egui::Color32::from_rgb(60, 180, 60),
+            exhausted: egui::Color32::from_rgb(120, 120, 60),
+            damage: egui::Color32::from_rgb(230, 60, 60),
+            heal: egui::Color32::from_rgb(80, 220, 80),
+            marker: egui::Color32::GOLD,
+            player_marker: egui::Color32::RED,
+        }
+    }
+}
+
+impl ThemeColors {
+    /// Opaque black backgrounds with white text and a saturated accent.
+    pub(crate) fn high_contrast(self) -> Self {
+        Self {
+            text: egui::Color32::WHITE,
+            weak_text: egui::Color32::from_gray(220),
+            accent: egui::Color32::YELLOW,
+            panel: egui::Color32::BLACK,
+            overlay: egui::Color32::BLACK,
+            backdrop: egui::Color32::BLACK,
+            ..self
+        }
+    }
+}
+
+/// Palettes for the common kinds of color vision deficiency.
+/// Only colors that carry meaning are substituted, i.e. bars, damage numbers, markers and the accent.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default, Serialize, Deserialize)]
+pub(crate) enum ColorblindMode {
+    #[default]
+    None,
+    /// Red-green, reduced sensitivity to green
+    Deuteranopia,
+    /// Red-green, reduced sensitivity to red
+    Protanopia,
+    /// Blue-yellow
+    Tritanopia,
+}
+
+impl ColorblindMode {
+    pub(crate) const ALL: [Self; 4] = [
+        Self::None,
+        Self::Deuteranopia,
+        Self::Protanopia,
+        Self::Tritanopia,
+    ];
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::None => "Default",
+            Self::Deuteranopia => "Deuteranopia",
+            Self::Protanopia => "Protanopia",
+            Self::Tritanopia => "Tritanopia",
+        }
+    }
+}
+
+impl ThemeColors {
+    /// Replaces red-green or blue-yellow contrasts with ones from the Okabe-Ito palette.
+    pub(crate) fn for_colorblind_mode(self, mode: ColorblindMode) -> Self {
+        let rgb = egui::Color32::from_rgb;
+        let orange = rgb(230, 159, 0);
+        let sky_blue = rgb(86, 180, 233);
+        let blue = rgb(0, 114, 178);
+        let vermillion = rgb(213, 94, 0);
+        let yellow = rgb(240, 228, 66);
+        let bluish_green = rgb(0, 158, 115);
+        let reddish_purple = rgb(204, 121, 167);
+        match mode {
+            ColorblindMode::None => self,
+            ColorblindMode::Deuteranopia => Self {
+                accent: orange,
+                health: vermillion,
+                stamina: sky_blue,
+                exhausted: egui::Color32::from_gray(120),
+                damage: vermillion,
+                heal: sky_blue,
+                marker: yellow,
+                player_marker: blue,
+                ..self
+            },
+            // Reds look darker with protanopia, so brighter tones are used for them
+            ColorblindMode::Protanopia => Self {
+                accent: orange,
+                health: orange,
+                stamina: sky_blue,
+                exhausted: egui::Color32::from_gray(120),
+                damage: orange,
+                heal: sky_blue,
+                marker: yellow,
+                player_marker: blue,
+                ..self
+            },
+            ColorblindMode::Tritanopia => Self {
+                accent: reddish_purple,
+                health: vermillion,
+                stamina: bluish_green,
+                exhausted: egui::Color32::from_gray(120),
+                damage: vermillion,
+                heal: bluish_green,
+                marker: reddish_purple,
+                player_marker: egui::Color32::WHITE,
+                ..self
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Spacing {
+    pub(crate) small: f32,
+    pub(crate) medium: f32,
+    pub(crate) large: f32,
+    /// Distance of HUD elements from the edges of the screen
+    pub(crate) screen_margin: f32,
+}
+
+impl Default for Spacing {
+    fn default() -> Self {
+        Self {
+            small: 10.,
+            medium: 30.,
+            large: 50.,
+            screen_margin: 20.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct PanelStyle {
+    pub(crate) rounding: f32,
+    pub(crate) inner_margin: f32,
+    /// Draws panels with an image instead of a flat color if set
+    pub(crate) nine_slice: Option<NineSlice>,
+}
+
+impl Default for PanelStyle {
+    fn default() -> Self {
+        Self {
+            rounding: 3.,
+            inner_margin: 6.,
+            nine_slice: None,
+        }
+    }
+}
+
+/// An image whose corners are drawn unscaled while its edges and center stretch to fit the panel.
+/// Register the image with [`EguiContexts::add_image`] to get a texture id.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct NineSlice {
+    pub(crate) texture: egui::TextureId,
+    /// Size of the image in pixels
+    pub(crate) size: egui::Vec2,
+    /// Width of the unscaled border in pixels
+    pub(crate) border: f32,
+}
+
+impl NineSlice {
+    fn shape(&self, rect: egui::Rect) -> egui::Shape {
+        let border = self.border.min(rect.width() / 2.).min(rect.height() / 2.);
+        let xs = [
+            rect.min.x,
+            rect.min.x + border,
+            rect.max.x - border,
+            rect.max.x,
+        ];
+        let ys = [
+            rect.min.y,
+            rect.min.y + border,
+            rect.max.y - border,
+            rect.max.y,
+        ];
+        let us = [
+            0.,
+            self.border / self.size.x,
+            1. - self.border / self.size.x,
+            1.,
+        ];
+        let vs = [
+            0.,
+            self.border / self.size.y,
+            1. - self.border / self.size.y,
+            1.,
+        ];
+        let mut mesh = egui::Mesh::with_texture(self.texture);
+        for row in 0..3 {
+            for column in 0..3 {
+                mesh.add_rect_with_uv(
+                    egui::Rect::from_min_max(
+                        egui::pos2(xs[column], ys[row]),
+                        egui::pos2(xs[column + 1], ys[row + 1]),
+                    ),
+                    egui::Rect::from_min_max(
+                        egui::pos2(us[column], vs[row]),
+                        egui::pos2(us[column + 1], vs[row + 1]),
+                    ),
+                    egui::Color32::WHITE,
+                );
+            }
+        }
+        egui::Shape::mesh(mesh)
+    }
+}
+
+impl UiTheme {
+    fn with_accessibility(&self, accessibility: &AccessibilitySettings) -> Self {
+        let mut theme = self.clone();
+        theme.text = theme.text.scaled(accessibility.text_scale);
+        theme.menu_text = theme.menu_text.scaled(accessibility.text_scale);
+        theme.colors = theme
+            .colors
+            .for_colorblind_mode(accessibility.colorblind_mode);
+        if accessibility.high_contrast {
+            theme.colors = theme.colors.high_contrast();
+            // Decorated panels rarely have enough contrast to put text on
+            theme.panel.nine_slice = None;
+        }
+        theme
+    }
+
+    /// The base style for all UI.
+    pub(crate) fn style(&self) -> egui::Style {
+        let mut style = egui::Style::default();
+        set_text_sizes(&mut style, self.text);
+        style.visuals.override_text_color = Some(self.colors.text);
+        style.visuals.selection.bg_fill = self.colors.accent.linear_multiply(0.6);
+        style.visuals.widgets.noninteractive.fg_stroke.color = self.colors.text;
+        style.visuals.window_fill = self.colors.panel;
+        style.visuals.panel_fill = self.colors.backdrop;
+        style.visuals.window_rounding = egui::Rounding::same(self.panel.rounding);
+        style
+    }
+
+    /// Enlarges the text of full-screen menus.
+    pub(crate) fn apply_menu_style(&self, style: &mut egui::Style) {
+        set_text_sizes(style, self.menu_text);
+    }
+
+    /// Frame for screens that hide the game completely.
+    pub(crate) fn backdrop_frame(&self) -> egui::Frame {
+        egui::Frame {
+            inner_margin: egui::style::Margin::same(self.spacing.large),
+            fill: self.colors.backdrop,
+            ..default()
+        }
+    }
+
+    /// Frame for screens drawn on top of the running game.
+    pub(crate) fn overlay_frame(&self) -> egui::Frame {
+        egui::Frame {
+            inner_margin: egui::style::Margin::same(self.spacing.small),
+            fill: self.colors.overlay,
+            ..default()
+        }
+    }
+
+    /// Frame for windows and popups. Transparent if the panel is drawn with a [`NineSlice`].
+    pub(crate) fn panel_frame(&self) -> egui::Frame {
+        let fill = if self.panel.nine_slice.is_some() {
+            egui::Color32::TRANSPARENT
+        } else {
+            self.colors.panel
+        };
+        egui::Frame {
+            inner_margin: egui::style::Margin::same(self.panel.inner_margin),
+            rounding: egui::Rounding::same(self.panel.rounding),
+            fill,
+            ..default()
+        }
+    }
+
+    /// Lays out `add_contents` in a themed panel.
+    pub(crate) fn panel<R>(
+        &self,
+        ui: &mut egui::Ui,
+        add_contents: impl FnOnce(&mut egui::Ui) -> R,
+    ) -> R {
+        // Reserve a spot below the contents for the background, since its size is only known afterwards
+        let background = ui.painter().add(egui::Shape::Noop);
+        let response = self.panel_frame().show(ui, add_contents);
+        if let Some(nine_slice) = self.panel.nine_slice {
+            ui.painter()
+                .set(background, nine_slice.shape(response.response.rect));
+        }
+        response.inner
+    }
+
+    /// Fills `rect` like a panel, for widgets that paint themselves.
+    pub(crate) fn paint_panel(&self, painter: &egui::Painter, rect: egui::Rect) {
+        match self.panel.nine_slice {
+            Some(nine_slice) => {
+                painter.add(nine_slice.shape(rect));
+            }
+            None => {
+                painter.rect_filled(rect, self.panel.rounding, self.colors.panel);
+            }
+        }
+    }
+}
+
+fn set_text_sizes(style: &mut egui::Style, sizes: TextSizes) {
+    style.text_styles = [
+        (Heading, FontId::new(sizes.heading, Proportional)),
+        (Body, FontId::new(sizes.body, Proportional)),
+        (Button, FontId::new(sizes.button, Proportional)),
+        (Small, FontId::new(sizes.small, Proportional)),
+        (
+            Monospace,
+            FontId::new(sizes.body, egui::FontFamily::Monospace),
+        ),
+    ]
+    .into();
+}
+
+fn update_theme(base_theme: Res<BaseUiTheme>, settings: Res<Settings>, mut theme: ResMut<UiTheme>) {
+    if base_theme.is_changed() || settings.is_changed() {
+        // Settings are mutably borrowed every frame while a settings page is open
+        theme.set_if_neq(base_theme.with_accessibility(&settings.accessibility));
+    }
+}
+
+fn apply_theme(theme: Res<UiTheme>, mut egui_contexts: EguiContexts) {
+    let ctx = egui_contexts.ctx_mut();
+    if let Some(fonts) = &theme.fonts {
+        ctx.set_fonts(fonts.clone());
+    }
+    ctx.set_style(theme.style());
+}
+
+fn apply_ui_scale(settings: Res<Settings>, mut egui_settings: ResMut<EguiSettings>) {
+    if egui_settings.scale_factor != settings.ui_scale {
+        egui_settings.scale_factor = settings.ui_scale;
+    }
+}
@@ -0,0 +1,53 @@
+#[cfg(feature = "dev")]
+use crate::dev::console::ConsoleAppExt;
+use crate::GameState;
+#[cfg(feature = "dev")]
+use anyhow::{Context, Result};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// In-game hours that pass per second of game time, i.e. a day lasts 24 minutes
+const HOURS_PER_SECOND: f32 = 1. / 60.;
+
+/// Keeps the in-game clock running while playing. The clock follows [`Time<Virtual>`], so it stands still while paused
+/// and slows down with the rest of the game. It is saved with the game.
+/// NPC schedules and dialogs depend on it, see [`schedules_plugin`](crate::movement::schedules::schedules_plugin).
+pub(crate) fn time_of_day_plugin(app: &mut App) {
+    app.init_resource::<TimeOfDay>().add_systems(
+        Update,
+        advance_time_of_day.run_if(in_state(GameState::Playing)),
+    );
+    #[cfg(feature = "dev")]
+    app.register_console_command("time", "time <hour>", set_time_command);
+}
+
+/// Hours since midnight, from 0 to 24
+#[derive(Debug, Clone, Copy, PartialEq, Resource, Serialize, Deserialize)]
+pub(crate) struct TimeOfDay(pub(crate) f32);
+
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        Self(8.)
+    }
+}
+
+impl TimeOfDay {
+    pub(crate) fn hour(self) -> u32 {
+        self.0 as u32
+    }
+}
+
+fn advance_time_of_day(time: Res<Time<Virtual>>, mut time_of_day: ResMut<TimeOfDay>) {
+    time_of_day.0 = (time_of_day.0 + time.delta_seconds() * HOURS_PER_SECOND).rem_euclid(24.);
+}
+
+#[cfg(feature = "dev")]
+fn set_time_command(world: &mut World, arguments: &[&str]) -> Result<String> {
+    let hour: f32 = arguments
+        .first()
+        .context("Missing argument <hour>")?
+        .parse()
+        .context("Expected an hour like 8 or 20.5")?;
+    world.insert_resource(TimeOfDay(hour.rem_euclid(24.)));
+    Ok(format!("Set the time to {hour}h"))
+}
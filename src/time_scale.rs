@@ -0,0 +1,102 @@
+use crate::GameState;
+use bevy::prelude::*;
+
+/// Scales how fast the game runs by setting the relative speed of [`Time<Virtual>`].
+/// Gameplay, animations and physics all advance with virtual time, so they slow down together,
+/// while the UI and audio run on [`Time<Real>`] and are unaffected.
+/// The overall speed is the [`TimeScale::base`] multiplied by the slowest active [`SlowMotionEvent`] effect.
+pub(crate) fn time_scale_plugin(app: &mut App) {
+    app.init_resource::<TimeScale>()
+        .add_event::<SlowMotionEvent>()
+        .add_systems(
+            PreUpdate,
+            (start_slow_motion, apply_time_scale)
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(OnExit(GameState::Playing), reset_time_scale);
+}
+
+/// Smallest speed the game can be set to, since a relative speed of zero is a pause and handled by the pause menu.
+const MIN_SCALE: f32 = 0.01;
+const MAX_SCALE: f32 = 10.;
+
+#[derive(Debug, Clone, PartialEq, Resource)]
+pub(crate) struct TimeScale {
+    /// Speed of the game without any slow motion effects, e.g. as set through the dev tools
+    pub(crate) base: f32,
+    effects: Vec<SlowMotion>,
+}
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        Self {
+            base: 1.,
+            effects: default(),
+        }
+    }
+}
+
+impl TimeScale {
+    /// The speed the game currently runs at.
+    pub(crate) fn current(&self) -> f32 {
+        let slow_motion = self
+            .effects
+            .iter()
+            .map(|effect| effect.scale)
+            .fold(1., f32::min);
+        (self.base * slow_motion).clamp(MIN_SCALE, MAX_SCALE)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct SlowMotion {
+    scale: f32,
+    /// Remaining real time in seconds
+    remaining: f32,
+}
+
+/// Temporarily slows the game down, e.g. to emphasize a heavy hit.
+#[derive(Debug, Clone, PartialEq, Event)]
+pub(crate) struct SlowMotionEvent {
+    /// Speed of the game while the effect lasts
+    pub(crate) scale: f32,
+    /// How long the effect lasts in real time seconds, so that it is not stretched by itself
+    pub(crate) duration: f32,
+}
+
+fn start_slow_motion(
+    mut slow_motion_events: EventReader<SlowMotionEvent>,
+    mut time_scale: ResMut<TimeScale>,
+) {
+    for event in slow_motion_events.read() {
+        time_scale.effects.push(SlowMotion {
+            scale: event.scale,
+            remaining: event.duration,
+        });
+    }
+}
+
+fn apply_time_scale(
+    real_time: Res<Time<Real>>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut time_scale: ResMut<TimeScale>,
+) {
+    // Effects should not run out while the pause menu is open
+    if !virtual_time.is_paused() {
+        let dt = real_time.delta_seconds();
+        for effect in time_scale.effects.iter_mut() {
+            effect.remaining -= dt;
+        }
+        time_scale.effects.retain(|effect| effect.remaining > 0.);
+    }
+    let scale = time_scale.current();
+    if virtual_time.relative_speed() != scale {
+        virtual_time.set_relative_speed(scale);
+    }
+}
+
+fn reset_time_scale(mut virtual_time: ResMut<Time<Virtual>>, mut time_scale: ResMut<TimeScale>) {
+    time_scale.effects.clear();
+    virtual_time.set_relative_speed(1.);
+}
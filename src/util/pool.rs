@@ -0,0 +1,79 @@
+use crate::GameState;
+use bevy::{ecs::system::EntityCommands, prelude::*};
+use std::marker::PhantomData;
+
+/// Keeps entities with a [`Poolable`] component around after use so that they can be reused
+/// instead of spawning and despawning entities during gameplay spikes.
+/// Added once per pooled component, e.g. `app.fn_plugin(pool_plugin::<Projectile>)`.
+/// All pooled entities, free or in use, are despawned when leaving [`GameState::Playing`].
+/// Used for projectiles, floating numbers and impact particles. Debris is not pooled since the game has none yet.
+pub(crate) fn pool_plugin<T: Poolable>(app: &mut App) {
+    app.init_resource::<Pool<T>>()
+        .add_systems(OnExit(GameState::Playing), clear_pool::<T>);
+}
+
+/// A component that marks an entity as in use by its [`Pool`].
+pub(crate) trait Poolable: Component {
+    /// Called when an entity is returned to the pool to clean up anything that was added to it while in use.
+    /// The entity is already hidden and the component itself removed.
+    fn reset(_entity: &mut EntityCommands) {}
+}
+
+/// Entities that are currently not in use, waiting to be handed out again by [`Pool::acquire`].
+#[derive(Debug, Resource)]
+pub(crate) struct Pool<T: Poolable> {
+    free: Vec<Entity>,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Poolable> Default for Pool<T> {
+    fn default() -> Self {
+        Self {
+            free: default(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Poolable> Pool<T> {
+    /// Inserts `component` into a free entity and makes it visible again.
+    /// If no entity is free, `spawn` is called instead to spawn a new one containing `component`.
+    pub(crate) fn acquire(
+        &mut self,
+        commands: &mut Commands,
+        component: T,
+        spawn: impl FnOnce(&mut Commands, T) -> Entity,
+    ) -> Entity {
+        match self.free.pop() {
+            Some(entity) => {
+                commands
+                    .entity(entity)
+                    .insert((component, Visibility::Inherited));
+                entity
+            }
+            None => spawn(commands, component),
+        }
+    }
+
+    /// Hides the entity, removes its component and keeps it for the next [`Pool::acquire`].
+    /// Releasing an entity that is already free does nothing, so that it is never handed out twice.
+    pub(crate) fn release(&mut self, commands: &mut Commands, entity: Entity) {
+        if self.free.contains(&entity) {
+            return;
+        }
+        let mut entity_commands = commands.entity(entity);
+        entity_commands.insert(Visibility::Hidden).remove::<T>();
+        T::reset(&mut entity_commands);
+        self.free.push(entity);
+    }
+}
+
+fn clear_pool<T: Poolable>(
+    mut commands: Commands,
+    mut pool: ResMut<Pool<T>>,
+    in_use: Query<Entity, With<T>>,
+) {
+    for entity in pool.free.drain(..).chain(in_use.iter()) {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -1,14 +1,43 @@
-use crate::world_interaction::{dialog::dialog_plugin, interactions_ui::interactions_ui_plugin};
+use crate::world_interaction::{
+    collectibles::collectibles_plugin, crafting::crafting_plugin, dialog::dialog_plugin,
+    elevators::elevators_plugin, highlight::highlight_plugin,
+    interactions_ui::interactions_ui_plugin, readables::readables_plugin, shop::shop_plugin,
+    switches::switches_plugin, triggers::triggers_plugin,
+};
 use bevy::prelude::*;
 use seldom_fn_plugin::FnPluginExt;
 
+pub(crate) mod collectibles;
+pub(crate) mod crafting;
 pub(crate) mod dialog;
+pub(crate) mod elevators;
+pub(crate) mod highlight;
 pub(crate) mod interactions_ui;
+pub(crate) mod readables;
+pub(crate) mod shop;
+pub(crate) mod switches;
+pub(crate) mod triggers;
 
 /// Handles player to world interactions. Split in to the following sub-plugins:
 /// - [`dialog_plugin`] handles dialog trees
-/// - [`interactions_ui_plugin`] handles the UI for interacting with an object in front of the player.
+/// - [`interactions_ui_plugin`] handles interacting with an object in front of the player. The prompt itself is drawn by the HUD.
+/// - [`highlight_plugin`] highlights the object the player can interact with.
+/// - [`triggers_plugin`] sends events when something enters or leaves a trigger sensor.
+/// - [`readables_plugin`] opens notes and signs the player interacts with.
+/// - [`shop_plugin`] lets the player trade items with vendors and merchants.
+/// - [`crafting_plugin`] lets the player craft items from recipes, anywhere or at crafting stations.
+/// - [`collectibles_plugin`] lets the player pick up collectibles and tracks which sets they completed.
+/// - [`elevators_plugin`] moves elevator cars between their floors when called with their buttons.
+/// - [`switches_plugin`] turns switches on with pressure plates and opens the doors they drive.
 pub(crate) fn world_interaction_plugin(app: &mut App) {
     app.fn_plugin(dialog_plugin)
-        .fn_plugin(interactions_ui_plugin);
+        .fn_plugin(interactions_ui_plugin)
+        .fn_plugin(highlight_plugin)
+        .fn_plugin(triggers_plugin)
+        .fn_plugin(readables_plugin)
+        .fn_plugin(shop_plugin)
+        .fn_plugin(crafting_plugin)
+        .fn_plugin(collectibles_plugin)
+        .fn_plugin(elevators_plugin)
+        .fn_plugin(switches_plugin);
 }
@@ -0,0 +1,235 @@
+use crate::{
+    file_system_interaction::config::GameConfig,
+    game_events::{CollectibleCollected, CollectibleSetCompleted},
+    level_instantiation::markers::{Marker, MarkersAppExt},
+    movement::physics::CollisionLayer,
+    platform::AchievementUnlocked,
+    player_control::player_embodiment::Player,
+    theme::UiTheme,
+    util::criteria::is_frozen,
+    world_interaction::triggers::{Trigger, TriggerEnter, TriggerKind, TriggerSystemSet},
+    GameState,
+};
+use anyhow::{Context, Result};
+use bevy::{
+    ecs::world::EntityWorldMut,
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_egui::{egui, EguiContexts};
+use bevy_xpbd_3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Seconds the counter stays on screen after picking something up
+const COUNTER_DURATION: f32 = 3.;
+
+/// Handles collectibles, the things scattered around the levels for the player to find.
+/// In Blender, objects named with a `[collectible:<set>:<id>]` suffix become collectibles that are picked up by walking into them.
+/// The `id` only needs to be unique within its set. Sets are configured in the `collectibles` section of the [`GameConfig`]
+/// with their name, how many collectibles they have in total and optionally the achievement unlocked by completing them.
+/// What the player has found is kept in the saved [`Collection`], so collectibles that were picked up do not spawn again.
+/// Picking one up sends a [`CollectibleCollected`] and finding the last one of a set a [`CollectibleSetCompleted`].
+pub(crate) fn collectibles_plugin(app: &mut App) {
+    app.register_type::<Collectible>()
+        .register_type::<Collection>()
+        .init_resource::<Collection>()
+        .init_resource::<CollectionCounter>()
+        .register_marker("collectible", insert_collectible)
+        .add_systems(
+            Update,
+            (
+                despawn_collected,
+                collect.after(TriggerSystemSet),
+                complete_sets,
+                show_collection_counter.run_if(not(is_frozen)),
+            )
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(OnExit(GameState::Playing), reset_collection);
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Collectible {
+    pub(crate) set: String,
+    pub(crate) id: String,
+}
+
+/// The IDs of the collectibles the player has found, by set.
+#[derive(Debug, Clone, PartialEq, Eq, Resource, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Resource, Serialize, Deserialize)]
+pub(crate) struct Collection(HashMap<String, HashSet<String>>);
+
+impl Collection {
+    pub(crate) fn contains(&self, collectible: &Collectible) -> bool {
+        self.0
+            .get(&collectible.set)
+            .is_some_and(|ids| ids.contains(&collectible.id))
+    }
+
+    /// How many collectibles of `set` have been found
+    pub(crate) fn count(&self, set: &str) -> usize {
+        self.0.get(set).map_or(0, |ids| ids.len())
+    }
+}
+
+/// The set whose counter is shown and the seconds it has left
+#[derive(Debug, Clone, PartialEq, Resource, Default)]
+struct CollectionCounter(Option<(String, f32)>);
+
+fn insert_collectible(entity: &mut EntityWorldMut, marker: &Marker) -> Result<()> {
+    let set = marker
+        .argument(0)
+        .context("Expected [collectible:<set>:<id>]")?;
+    let id = marker
+        .argument(1)
+        .context("Expected [collectible:<set>:<id>]")?;
+    entity.insert((
+        Collectible {
+            set: set.to_string(),
+            id: id.to_string(),
+        },
+        // Scaled by the object's transform
+        Collider::cuboid(1., 1., 1.),
+        Sensor,
+        CollisionLayer::trigger(),
+        Trigger::new(TriggerKind::Area),
+    ));
+    Ok(())
+}
+
+fn despawn_collected(
+    mut commands: Commands,
+    collectibles: Query<(Entity, &Collectible), Added<Collectible>>,
+    collection: Res<Collection>,
+) {
+    for (entity, collectible) in collectibles.iter() {
+        if collection.contains(collectible) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn collect(
+    mut commands: Commands,
+    mut trigger_events: EventReader<TriggerEnter>,
+    collectibles: Query<&Collectible>,
+    players: Query<(), With<Player>>,
+    mut collection: ResMut<Collection>,
+    mut counter: ResMut<CollectionCounter>,
+    config: Res<GameConfig>,
+    mut collected_events: EventWriter<CollectibleCollected>,
+) {
+    for event in trigger_events.read() {
+        let Ok(collectible) = collectibles.get(event.trigger) else {
+            continue;
+        };
+        if !players.contains(event.other) || collection.contains(collectible) {
+            continue;
+        }
+        commands.entity(event.trigger).despawn_recursive();
+        collection
+            .0
+            .entry(collectible.set.clone())
+            .or_default()
+            .insert(collectible.id.clone());
+        if !config.collectibles.contains_key(&collectible.set) {
+            warn!(
+                "Collectible {} belongs to the unconfigured set {}",
+                collectible.id, collectible.set
+            );
+        }
+        counter.0 = Some((collectible.set.clone(), COUNTER_DURATION));
+        collected_events.send(CollectibleCollected {
+            set: collectible.set.clone(),
+            id: collectible.id.clone(),
+            found: collection.count(&collectible.set),
+        });
+    }
+}
+
+fn complete_sets(
+    mut collected_events: EventReader<CollectibleCollected>,
+    config: Res<GameConfig>,
+    mut completed_events: EventWriter<CollectibleSetCompleted>,
+    mut achievements: EventWriter<AchievementUnlocked>,
+) {
+    for event in collected_events.read() {
+        let Some(set) = config.collectibles.get(&event.set) else {
+            continue;
+        };
+        // Only the collectible completing the set has exactly the total count
+        if event.found != set.total {
+            continue;
+        }
+        completed_events.send(CollectibleSetCompleted {
+            set: event.set.clone(),
+        });
+        if let Some(id) = &set.achievement {
+            achievements.send(AchievementUnlocked { id: id.clone() });
+        }
+    }
+}
+
+fn show_collection_counter(
+    time: Res<Time<Virtual>>,
+    mut counter: ResMut<CollectionCounter>,
+    collection: Res<Collection>,
+    config: Res<GameConfig>,
+    theme: Res<UiTheme>,
+    mut egui_contexts: EguiContexts,
+) {
+    let Some((set, remaining)) = counter.0.as_mut() else {
+        return;
+    };
+    *remaining -= time.delta_seconds();
+    if *remaining <= 0. {
+        counter.0 = None;
+        return;
+    }
+    let found = collection.count(set);
+    let text = match config.collectibles.get(set.as_str()) {
+        Some(config) => format!("{} {found}/{}", config.name, config.total),
+        None => format!("{set} {found}"),
+    };
+    let margin = theme.spacing.screen_margin;
+    egui::Area::new("Collection counter")
+        .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-margin, 4. * margin))
+        .interactable(false)
+        .show(egui_contexts.ctx_mut(), |ui| {
+            theme.panel(ui, |ui| {
+                ui.label(
+                    egui::RichText::new(text)
+                        .size(theme.text.body)
+                        .color(theme.colors.text),
+                );
+            });
+        });
+}
+
+fn reset_collection(mut collection: ResMut<Collection>, mut counter: ResMut<CollectionCounter>) {
+    *collection = default();
+    *counter = default();
+}
+
+/// Draws how much of each collectible set has been found, e.g. for the pause menu.
+pub(crate) fn completion_ui(ui: &mut egui::Ui, collection: &Collection, config: &GameConfig) {
+    if config.collectibles.is_empty() {
+        return;
+    }
+    let mut sets: Vec<_> = config.collectibles.iter().collect();
+    sets.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let mut found_total = 0;
+    let mut total = 0;
+    for (id, set) in sets {
+        let found = collection.count(id).min(set.total);
+        found_total += found;
+        total += set.total;
+        ui.label(format!("{}: {found}/{}", set.name, set.total));
+    }
+    if total > 0 {
+        let percent = 100 * found_total / total;
+        ui.label(format!("Completion: {percent}%"));
+    }
+}
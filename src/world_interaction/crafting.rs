@@ -0,0 +1,262 @@
+use crate::{
+    file_system_interaction::config::GameConfig,
+    game_events::ItemCrafted,
+    hud::Hotbar,
+    level_instantiation::markers::{Marker, MarkersAppExt},
+    movement::physics::CollisionLayer,
+    player_control::{
+        actions::{ActionsFrozen, PlayerAction, UiAction},
+        player_embodiment::Player,
+    },
+    theme::UiTheme,
+    util::criteria::is_frozen,
+    world_interaction::{
+        interactions_ui::InteractionOpportunity,
+        triggers::{Trigger, TriggerKind},
+    },
+    GameState,
+};
+use anyhow::Result;
+use bevy::{ecs::world::EntityWorldMut, prelude::*, utils::HashMap};
+use bevy_common_assets::ron::RonAssetPlugin;
+use bevy_egui::{egui, EguiContexts};
+use bevy_xpbd_3d::prelude::*;
+use leafwing_input_manager::prelude::ActionState;
+use serde::{Deserialize, Serialize};
+
+const RECIPES_PATH: &str = "recipes/default.recipes.ron";
+const INTERACTION_RADIUS: f32 = 2.;
+
+/// Lets the player craft items in their [`Hotbar`] into new ones.
+/// Recipes are loaded from `recipes/default.recipes.ron`, which maps recipe IDs to the item keys they use up
+/// and the item they make. Item names are taken from the `items` section of the [`GameConfig`].
+/// The crafting window is toggled from anywhere with [`UiAction::ToggleCrafting`], but recipes marked with
+/// `station: true` can only be crafted at a crafting station. In Blender, objects named with a `[crafting_station]`
+/// suffix open the crafting window when interacted with. Every successful craft sends an [`ItemCrafted`].
+pub(crate) fn crafting_plugin(app: &mut App) {
+    app.register_type::<CraftingStation>()
+        .add_plugins(RonAssetPlugin::<RecipeBook>::new(&["recipes.ron"]))
+        .init_resource::<OpenCrafting>()
+        .register_marker("crafting_station", insert_crafting_station)
+        .add_systems(Startup, load_recipes)
+        .add_systems(
+            Update,
+            (
+                toggle_crafting,
+                open_crafting_station.run_if(not(is_frozen)),
+                show_crafting,
+            )
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(OnExit(GameState::Playing), close_crafting);
+}
+
+/// Opens the crafting window with access to all recipes when interacted with.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct CraftingStation;
+
+/// The contents of a `.recipes.ron`, mapping recipe IDs to recipes.
+#[derive(Debug, Clone, PartialEq, Asset, TypePath, Serialize, Deserialize, Default)]
+#[serde(transparent)]
+pub(crate) struct RecipeBook(HashMap<String, Recipe>);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub(crate) struct Recipe {
+    /// Keys of the items used up, listed once per item needed
+    pub(crate) inputs: Vec<String>,
+    /// Key of the item made
+    pub(crate) output: String,
+    /// Whether the recipe can only be crafted at a [`CraftingStation`]
+    #[serde(default)]
+    pub(crate) station: bool,
+}
+
+impl Recipe {
+    /// Whether the hotbar holds all of the inputs
+    fn is_craftable_with(&self, hotbar: &Hotbar) -> bool {
+        let mut available: Vec<_> = hotbar.slots.iter().flatten().collect();
+        self.inputs.iter().all(|input| {
+            available
+                .iter()
+                .position(|item| *item == input)
+                .map(|index| available.swap_remove(index))
+                .is_some()
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Resource, Default)]
+struct RecipeBookHandle(Handle<RecipeBook>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CraftingSource {
+    Inventory,
+    Station,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Resource, Default)]
+struct OpenCrafting(Option<CraftingSource>);
+
+fn insert_crafting_station(entity: &mut EntityWorldMut, _marker: &Marker) -> Result<()> {
+    entity.insert(CraftingStation).with_children(|parent| {
+        parent.spawn((
+            Name::new("Crafting Station Interaction Collider"),
+            SpatialBundle::default(),
+            Collider::ball(INTERACTION_RADIUS),
+            CollisionLayer::trigger(),
+            Sensor,
+            Trigger::new(TriggerKind::Interaction),
+        ));
+    });
+    Ok(())
+}
+
+fn load_recipes(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(RecipeBookHandle(asset_server.load(RECIPES_PATH)));
+}
+
+fn toggle_crafting(
+    actions: Query<&ActionState<UiAction>>,
+    mut open: ResMut<OpenCrafting>,
+    mut freeze: ResMut<ActionsFrozen>,
+) {
+    if !actions
+        .iter()
+        .any(|actions| actions.just_pressed(UiAction::ToggleCrafting))
+    {
+        return;
+    }
+    if open.0.take().is_some() {
+        freeze.unfreeze();
+    } else if !freeze.is_frozen() {
+        open.0 = Some(CraftingSource::Inventory);
+        freeze.freeze();
+    }
+}
+
+fn open_crafting_station(
+    interaction_opportunity: Res<InteractionOpportunity>,
+    stations: Query<(), With<CraftingStation>>,
+    players: Query<&ActionState<PlayerAction>, With<Player>>,
+    mut open: ResMut<OpenCrafting>,
+    mut freeze: ResMut<ActionsFrozen>,
+) {
+    if !interaction_opportunity
+        .0
+        .is_some_and(|target| stations.contains(target))
+    {
+        return;
+    }
+    if players
+        .iter()
+        .any(|actions| actions.just_pressed(PlayerAction::Interact))
+    {
+        open.0 = Some(CraftingSource::Station);
+        freeze.freeze();
+    }
+}
+
+fn show_crafting(
+    mut open: ResMut<OpenCrafting>,
+    actions: Query<&ActionState<UiAction>>,
+    mut players: Query<&mut Hotbar, With<Player>>,
+    recipe_book: Option<Res<RecipeBookHandle>>,
+    recipe_books: Res<Assets<RecipeBook>>,
+    config: Res<GameConfig>,
+    mut crafted_events: EventWriter<ItemCrafted>,
+    mut freeze: ResMut<ActionsFrozen>,
+    theme: Res<UiTheme>,
+    mut egui_contexts: EguiContexts,
+) {
+    let Some(source) = open.0 else {
+        return;
+    };
+    let back_pressed = actions
+        .iter()
+        .any(|actions| actions.just_pressed(UiAction::Back));
+    let Some(mut hotbar) = players.get_single_mut().ok().filter(|_| !back_pressed) else {
+        open.0 = None;
+        freeze.unfreeze();
+        return;
+    };
+    let mut recipes: Vec<_> = recipe_book
+        .and_then(|handle| recipe_books.get(&handle.0))
+        .map(|book| book.0.iter().collect())
+        .unwrap_or_default();
+    recipes.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let item_name = |key: &str| {
+        config
+            .items
+            .get(key)
+            .map_or_else(|| key.to_string(), |item| item.name.clone())
+    };
+    let at_station = source == CraftingSource::Station;
+    let mut close = false;
+    egui::CentralPanel::default()
+        .frame(theme.overlay_frame())
+        .show(egui_contexts.ctx_mut(), |ui| {
+            ui.vertical_centered(|ui| {
+                ui.set_max_width(40. * theme.text.body);
+                ui.add_space(2. * theme.spacing.large);
+                ui.heading(if at_station {
+                    "Crafting Station"
+                } else {
+                    "Crafting"
+                });
+                ui.separator();
+                if recipes.is_empty() {
+                    ui.label("You don't know any recipes yet.");
+                }
+                for (id, recipe) in recipes {
+                    let inputs: Vec<_> = recipe.inputs.iter().map(|key| item_name(key)).collect();
+                    let mut text = format!("{} ({})", item_name(&recipe.output), inputs.join(", "));
+                    if recipe.station && !at_station {
+                        text.push_str(" - needs a crafting station");
+                    }
+                    let craftable =
+                        (at_station || !recipe.station) && recipe.is_craftable_with(&hotbar);
+                    if ui.add_enabled(craftable, egui::Button::new(text)).clicked() {
+                        craft(&mut hotbar, recipe);
+                        info!("Crafted {}", recipe.output);
+                        crafted_events.send(ItemCrafted {
+                            recipe: id.clone(),
+                            item: recipe.output.clone(),
+                        });
+                    }
+                }
+                ui.separator();
+                close = ui.button("Close").clicked();
+            });
+        });
+    if close {
+        open.0 = None;
+        freeze.unfreeze();
+    }
+}
+
+/// Swaps the inputs in the hotbar for the output
+fn craft(hotbar: &mut Hotbar, recipe: &Recipe) {
+    for input in &recipe.inputs {
+        if let Some(slot) = hotbar
+            .slots
+            .iter_mut()
+            .find(|slot| slot.as_ref() == Some(input))
+        {
+            *slot = None;
+        }
+    }
+    if hotbar.add(recipe.output.clone()).is_none() {
+        warn!(
+            "No room for {}, which was crafted from nothing",
+            recipe.output
+        );
+    }
+}
+
+fn close_crafting(mut open: ResMut<OpenCrafting>, mut freeze: ResMut<ActionsFrozen>) {
+    if open.0.take().is_some() {
+        freeze.unfreeze();
+    }
+}
@@ -1,6 +1,21 @@
-use crate::player_control::actions::ActionsFrozen;
+#[cfg(feature = "scripting")]
+use crate::scripting::ScriptEvent;
+use crate::{
+    despawn::DespawnOnExit,
+    game_events::{DialogEnded, ItemPickedUp, QuestCompleted, QuestStarted},
+    level_instantiation::{environment::set_skybox_command, prefabs::SpawnPrefab},
+    platform::AchievementUnlocked,
+    player_control::{
+        actions::ActionsFrozen, player_embodiment::Player, progression::give_experience_command,
+    },
+    time_of_day::TimeOfDay,
+    world_interaction::shop::{give_coins_command, open_shop_command},
+    GameState,
+};
+use anyhow::Result;
 use bevy::prelude::*;
 use bevy_egui::EguiPlugin;
+use bevy_mod_sysfail::*;
 use bevy_yarnspinner::{events::DialogueCompleteEvent, prelude::*};
 use bevy_yarnspinner_example_dialogue_view::prelude::*;
 use serde::{Deserialize, Serialize};
@@ -15,7 +30,10 @@ pub(crate) fn dialog_plugin(app: &mut App) {
         Update,
         (
             spawn_dialogue_runner.run_if(resource_added::<YarnProject>()),
-            unfreeze_after_dialog.after(ExampleYarnSpinnerDialogueViewSystemSet),
+            sync_hour_variable,
+            (publish_dialog_end, unfreeze_after_dialog)
+                .chain()
+                .after(ExampleYarnSpinnerDialogueViewSystemSet),
         ),
     );
 }
@@ -28,16 +46,107 @@ pub(crate) struct DialogTarget {
 
 fn spawn_dialogue_runner(mut commands: Commands, project: Res<YarnProject>) {
     // Create a dialogue runner from the project.
-    let dialogue_runner = project.create_dialogue_runner();
+    let mut dialogue_runner = project.create_dialogue_runner();
+    dialogue_runner
+        .commands_mut()
+        .add_command("spawn_prefab", spawn_prefab_command)
+        .add_command("unlock_achievement", unlock_achievement_command)
+        .add_command("give_item", give_item_command)
+        .add_command("start_quest", start_quest_command)
+        .add_command("complete_quest", complete_quest_command)
+        .add_command("open_shop", open_shop_command)
+        .add_command("give_coins", give_coins_command)
+        .add_command("give_experience", give_experience_command)
+        .add_command("set_skybox", set_skybox_command);
+    #[cfg(feature = "scripting")]
+    dialogue_runner
+        .commands_mut()
+        .add_command("script_event", script_event_command);
     // Immediately start showing the dialogue to the player
     commands.spawn(dialogue_runner);
 }
 
+/// Keeps the `$hour` variable at the hour of the [`TimeOfDay`], so that dialogs can depend on the time.
+#[sysfail(log(level = "error"))]
+fn sync_hour_variable(
+    time_of_day: Res<TimeOfDay>,
+    mut dialogue_runners: Query<&mut DialogueRunner>,
+) -> Result<()> {
+    let hour = YarnValue::Number(time_of_day.hour() as f32);
+    for mut dialogue_runner in dialogue_runners.iter_mut() {
+        let variables = dialogue_runner.variable_storage_mut();
+        if variables.get("$hour").ok().as_ref() != Some(&hour) {
+            variables.set("$hour".to_string(), hour.clone())?;
+        }
+    }
+    Ok(())
+}
+
+/// `<<spawn_prefab barrel>>` spawns the prefab in front of the player.
+fn spawn_prefab_command(
+    In(name): In<String>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    players: Query<&Transform, With<Player>>,
+) {
+    let Some(transform) = players.iter().next() else {
+        return;
+    };
+    let position = transform.translation + transform.forward() * 2.;
+    commands.spawn((
+        Name::new(name.clone()),
+        SpawnPrefab::new(&asset_server, &name),
+        SpatialBundle::from_transform(Transform::from_translation(position)),
+        DespawnOnExit(GameState::Playing),
+    ));
+}
+
+/// `<<unlock_achievement FIRST_DIALOG>>` unlocks an achievement, so that story progress can award them.
+fn unlock_achievement_command(
+    In(id): In<String>,
+    mut achievements: EventWriter<AchievementUnlocked>,
+) {
+    achievements.send(AchievementUnlocked { id });
+}
+
+/// `<<give_item key_red>>` hands the player an item, published as [`ItemPickedUp`].
+fn give_item_command(In(item): In<String>, mut items_picked_up: EventWriter<ItemPickedUp>) {
+    items_picked_up.send(ItemPickedUp { item });
+}
+
+/// `<<start_quest find_the_fox>>` publishes a [`QuestStarted`].
+fn start_quest_command(In(id): In<String>, mut quests_started: EventWriter<QuestStarted>) {
+    quests_started.send(QuestStarted { id });
+}
+
+/// `<<complete_quest find_the_fox>>` publishes a [`QuestCompleted`].
+fn complete_quest_command(In(id): In<String>, mut quests_completed: EventWriter<QuestCompleted>) {
+    quests_completed.send(QuestCompleted { id });
+}
+
+/// `<<script_event door_opened>>` sends a [`ScriptEvent`] to all scripts.
+#[cfg(feature = "scripting")]
+fn script_event_command(In(name): In<String>, mut script_events: EventWriter<ScriptEvent>) {
+    script_events.send(ScriptEvent {
+        name,
+        payload: String::new(),
+    });
+}
+
+fn publish_dialog_end(
+    mut dialogue_complete_events: EventReader<DialogueCompleteEvent>,
+    mut dialogs_ended: EventWriter<DialogEnded>,
+) {
+    for _event in dialogue_complete_events.read() {
+        dialogs_ended.send(DialogEnded);
+    }
+}
+
 fn unfreeze_after_dialog(
-    mut dialogue_complete_event: EventReader<DialogueCompleteEvent>,
+    mut dialogs_ended: EventReader<DialogEnded>,
     mut freeze: ResMut<ActionsFrozen>,
 ) {
-    for _event in dialogue_complete_event.read() {
+    for _event in dialogs_ended.read() {
         freeze.unfreeze();
     }
 }
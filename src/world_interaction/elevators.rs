@@ -0,0 +1,407 @@
+use crate::{
+    file_system_interaction::sound_effects::{SoundCategory, SoundEffect},
+    hud::captions::CaptionEvent,
+    level_instantiation::markers::{Marker, MarkersAppExt},
+    movement::physics::CollisionLayer,
+    player_control::{actions::PlayerAction, player_embodiment::Player},
+    util::criteria::is_frozen,
+    world_interaction::{
+        interactions_ui::InteractionOpportunity,
+        triggers::{Trigger, TriggerKind},
+    },
+    GameState,
+};
+use anyhow::{Context, Result};
+use bevy::{ecs::world::EntityWorldMut, prelude::*};
+use bevy_xpbd_3d::prelude::*;
+use leafwing_input_manager::prelude::ActionState;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+const INTERACTION_RADIUS: f32 = 1.;
+/// Seconds the doors take to fully open or close
+const DOOR_DURATION: f32 = 1.;
+/// How far the doors slide along their own X axis when open
+const DOOR_SLIDE: f32 = 1.;
+/// Closer than this to a stop, the car counts as having arrived
+const ARRIVAL_DISTANCE: f32 = 0.01;
+
+/// Handles elevators and other lifts that move between fixed stops.
+/// In Blender, the car is named with an `[elevator:<name>]` suffix, usually together with a `[collider]`,
+/// and the stops are empty objects named `[elevator_stop:<name>]`. The car moves so that its origin reaches
+/// the origin of a stop, and the stops are numbered as floors from 1 at the lowest one.
+/// Objects named `[elevator_button:<name>:<floor>]` are interactable buttons calling the car to that floor,
+/// both next to the landing doors and inside the car.
+/// Doors named `[elevator_door:<name>:<floor>]` open when the car stops at that floor, ones named
+/// `[elevator_door:<name>]` belong to the car and open at every stop. Doors slide along their own X axis.
+/// The car is a kinematic body moved through its velocity, so the characters standing on it are carried along.
+/// It eases in and out of every stop, waits with open doors for a while and then serves the next call.
+pub(crate) fn elevators_plugin(app: &mut App) {
+    app.register_type::<Elevator>()
+        .register_type::<ElevatorStop>()
+        .register_type::<ElevatorButton>()
+        .register_type::<ElevatorDoor>()
+        .register_marker("elevator", insert_elevator)
+        .register_marker("elevator_stop", insert_elevator_stop)
+        .register_marker("elevator_button", insert_elevator_button)
+        .register_marker("elevator_door", insert_elevator_door)
+        .add_systems(
+            Update,
+            (
+                init_elevators,
+                press_elevator_button.run_if(not(is_frozen)),
+                move_elevators,
+                move_elevator_doors,
+            )
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Elevator {
+    /// Connects the car to its stops, buttons and doors
+    pub(crate) name: String,
+    /// Top speed in meters per second
+    pub(crate) speed: f32,
+    /// In meters per second squared, used both for starting and for slowing down
+    pub(crate) acceleration: f32,
+    /// Seconds the doors stay open at a stop before the car serves the next call
+    pub(crate) wait_time: f32,
+    /// Path of the sound played when the car starts moving
+    pub(crate) departure_sound: Option<String>,
+    /// Path of the sound played when the car arrives at a stop
+    pub(crate) arrival_sound: Option<String>,
+}
+
+impl Default for Elevator {
+    fn default() -> Self {
+        Self {
+            name: default(),
+            speed: 2.,
+            acceleration: 1.,
+            wait_time: 3.,
+            departure_sound: None,
+            arrival_sound: None,
+        }
+    }
+}
+
+/// Where the car of the elevator with the given name can stop.
+#[derive(Debug, Clone, PartialEq, Eq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct ElevatorStop {
+    pub(crate) elevator: String,
+}
+
+/// Calls the car of an elevator to a floor when interacted with.
+#[derive(Debug, Clone, PartialEq, Eq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct ElevatorButton {
+    pub(crate) elevator: String,
+    /// Counting from 1 at the lowest stop
+    pub(crate) floor: usize,
+}
+
+/// A door that opens while the car of an elevator stands at its floor.
+#[derive(Debug, Clone, PartialEq, Eq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct ElevatorDoor {
+    pub(crate) elevator: String,
+    /// Counting from 1 at the lowest stop. Doors of the car itself have none and open at every floor.
+    pub(crate) floor: Option<usize>,
+}
+
+/// What an [`Elevator`] is doing, inserted automatically once its stops are known.
+#[derive(Debug, Clone, PartialEq, Component)]
+pub(crate) struct ElevatorState {
+    /// Positions of the stops, lowest first
+    stops: Vec<Vec3>,
+    /// Index of the stop the car is at or was last at
+    floor: usize,
+    /// Indices of the stops the car was called to, in order
+    calls: VecDeque<usize>,
+    phase: ElevatorPhase,
+    /// How far the doors are open, from 0 to 1
+    doors: f32,
+    /// Current speed of the car along its way
+    speed: f32,
+}
+
+impl ElevatorState {
+    /// The floor the car stands at with its doors not yet shut, counting from 1
+    fn open_floor(&self) -> Option<usize> {
+        let is_standing = match self.phase {
+            ElevatorPhase::Waiting { .. } => true,
+            ElevatorPhase::Departing { .. } => self.doors > 0.,
+            ElevatorPhase::Moving { .. } => false,
+        };
+        is_standing.then_some(self.floor + 1)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ElevatorPhase {
+    /// Standing at a stop for the given seconds
+    Waiting {
+        since: f32,
+    },
+    /// Closing the doors before leaving for the stop with the given index
+    Departing {
+        to: usize,
+    },
+    Moving {
+        to: usize,
+    },
+}
+
+/// The door's position when closed, captured when it is first moved
+#[derive(Debug, Clone, Copy, PartialEq, Component)]
+struct ClosedDoor(Vec3);
+
+fn insert_elevator(entity: &mut EntityWorldMut, marker: &Marker) -> Result<()> {
+    let name = marker.argument(0).context("Expected [elevator:<name>]")?;
+    entity.insert((
+        Elevator {
+            name: name.to_string(),
+            ..default()
+        },
+        RigidBody::Kinematic,
+    ));
+    Ok(())
+}
+
+fn insert_elevator_stop(entity: &mut EntityWorldMut, marker: &Marker) -> Result<()> {
+    let elevator = marker
+        .argument(0)
+        .context("Expected [elevator_stop:<name>]")?;
+    entity.insert(ElevatorStop {
+        elevator: elevator.to_string(),
+    });
+    Ok(())
+}
+
+fn insert_elevator_button(entity: &mut EntityWorldMut, marker: &Marker) -> Result<()> {
+    let usage = "Expected [elevator_button:<name>:<floor>]";
+    let elevator = marker.argument(0).context(usage)?;
+    let floor = marker
+        .argument(1)
+        .context(usage)?
+        .parse()
+        .context("The floor of an elevator button must be a number")?;
+    entity
+        .insert(ElevatorButton {
+            elevator: elevator.to_string(),
+            floor,
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                Name::new("Elevator Button Interaction Collider"),
+                SpatialBundle::default(),
+                Collider::ball(INTERACTION_RADIUS),
+                CollisionLayer::trigger(),
+                Sensor,
+                Trigger::new(TriggerKind::Interaction),
+            ));
+        });
+    Ok(())
+}
+
+fn insert_elevator_door(entity: &mut EntityWorldMut, marker: &Marker) -> Result<()> {
+    let elevator = marker
+        .argument(0)
+        .context("Expected [elevator_door:<name>] or [elevator_door:<name>:<floor>]")?;
+    let floor = marker
+        .argument(1)
+        .map(str::parse)
+        .transpose()
+        .context("The floor of an elevator door must be a number")?;
+    entity.insert(ElevatorDoor {
+        elevator: elevator.to_string(),
+        floor,
+    });
+    Ok(())
+}
+
+/// Collects the stops of elevators once the level has spawned them.
+fn init_elevators(
+    mut commands: Commands,
+    elevators: Query<(Entity, &Elevator, &GlobalTransform), Without<ElevatorState>>,
+    stops: Query<(&ElevatorStop, &GlobalTransform)>,
+) {
+    for (entity, elevator, transform) in elevators.iter() {
+        let mut positions: Vec<_> = stops
+            .iter()
+            .filter(|(stop, _)| stop.elevator == elevator.name)
+            .map(|(_, stop_transform)| stop_transform.translation())
+            .collect();
+        if positions.is_empty() {
+            continue;
+        }
+        positions.sort_by(|a, b| a.y.total_cmp(&b.y));
+        let position = transform.translation();
+        let floor = (0..positions.len())
+            .min_by(|&a, &b| {
+                positions[a]
+                    .distance_squared(position)
+                    .total_cmp(&positions[b].distance_squared(position))
+            })
+            .unwrap_or_default();
+        commands.entity(entity).insert(ElevatorState {
+            stops: positions,
+            floor,
+            calls: VecDeque::new(),
+            phase: ElevatorPhase::Waiting { since: 0. },
+            doors: 0.,
+            speed: 0.,
+        });
+    }
+}
+
+fn press_elevator_button(
+    interaction_opportunity: Res<InteractionOpportunity>,
+    players: Query<&ActionState<PlayerAction>, With<Player>>,
+    buttons: Query<&ElevatorButton>,
+    mut elevators: Query<(&Elevator, &mut ElevatorState)>,
+    mut caption_events: EventWriter<CaptionEvent>,
+) {
+    let Some(button) = interaction_opportunity
+        .0
+        .and_then(|target| buttons.get(target).ok())
+    else {
+        return;
+    };
+    if !players
+        .iter()
+        .any(|actions| actions.just_pressed(PlayerAction::Interact))
+    {
+        return;
+    }
+    caption_events.send(CaptionEvent::sound("[button clicks]"));
+    for (elevator, mut state) in elevators.iter_mut() {
+        if elevator.name != button.elevator {
+            continue;
+        }
+        let Some(index) = button
+            .floor
+            .checked_sub(1)
+            .filter(|&index| index < state.stops.len())
+        else {
+            warn!(
+                "Elevator \"{}\" has no floor {}",
+                elevator.name, button.floor
+            );
+            continue;
+        };
+        if state.open_floor() == Some(button.floor) {
+            // Open the doors again or keep them open a while longer
+            state.phase = ElevatorPhase::Waiting { since: 0. };
+        } else if !state.calls.contains(&index) {
+            state.calls.push_back(index);
+        }
+    }
+}
+
+fn move_elevators(
+    time: Res<Time<Virtual>>,
+    mut elevators: Query<(
+        &Elevator,
+        &mut ElevatorState,
+        &GlobalTransform,
+        &mut LinearVelocity,
+    )>,
+    mut sound_effects: EventWriter<SoundEffect>,
+    mut caption_events: EventWriter<CaptionEvent>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("move_elevators").entered();
+    let dt = time.delta_seconds();
+    let mut play = |path: &Option<String>| {
+        if let Some(path) = path {
+            sound_effects.send(SoundEffect::new(path, SoundCategory::World).with_priority(1));
+        }
+    };
+    for (elevator, mut state, transform, mut velocity) in elevators.iter_mut() {
+        let mut new_velocity = Vec3::ZERO;
+        match state.phase {
+            ElevatorPhase::Waiting { since } => {
+                state.doors = (state.doors + dt / DOOR_DURATION).min(1.);
+                let floor = state.floor;
+                state.calls.retain(|&call| call != floor);
+                if since + dt < elevator.wait_time {
+                    state.phase = ElevatorPhase::Waiting { since: since + dt };
+                } else if let Some(&to) = state.calls.front() {
+                    state.phase = ElevatorPhase::Departing { to };
+                }
+            }
+            ElevatorPhase::Departing { to } => {
+                state.doors = (state.doors - dt / DOOR_DURATION).max(0.);
+                if state.doors <= 0. {
+                    state.phase = ElevatorPhase::Moving { to };
+                    play(&elevator.departure_sound);
+                    caption_events.send(CaptionEvent::sound("[elevator hums]"));
+                }
+            }
+            ElevatorPhase::Moving { to } => {
+                let offset = state.stops[to] - transform.translation();
+                let distance = offset.length();
+                if distance <= ARRIVAL_DISTANCE {
+                    state.floor = to;
+                    state.speed = 0.;
+                    state.calls.retain(|&call| call != to);
+                    state.phase = ElevatorPhase::Waiting { since: 0. };
+                    play(&elevator.arrival_sound);
+                    caption_events.send(CaptionEvent::sound("[elevator dings]"));
+                } else {
+                    // Speed up, but slow down early enough to stop right at the stop
+                    let braking_speed = (2. * elevator.acceleration * distance).sqrt();
+                    let speed = (state.speed + elevator.acceleration * dt)
+                        .min(elevator.speed)
+                        .min(braking_speed)
+                        .min(distance / dt.max(f32::EPSILON));
+                    state.speed = speed;
+                    new_velocity = offset / distance * speed;
+                }
+            }
+        }
+        if velocity.0 != new_velocity {
+            velocity.0 = new_velocity;
+        }
+    }
+}
+
+fn move_elevator_doors(
+    mut commands: Commands,
+    mut doors: Query<(Entity, &ElevatorDoor, &mut Transform, Option<&ClosedDoor>)>,
+    elevators: Query<(&Elevator, &ElevatorState)>,
+) {
+    for (entity, door, mut transform, closed) in doors.iter_mut() {
+        let closed = match closed {
+            Some(closed) => closed.0,
+            None => {
+                commands
+                    .entity(entity)
+                    .insert(ClosedDoor(transform.translation));
+                transform.translation
+            }
+        };
+        let Some((_, state)) = elevators
+            .iter()
+            .find(|(elevator, _)| elevator.name == door.elevator)
+        else {
+            continue;
+        };
+        let is_at_floor = match door.floor {
+            Some(floor) => state.floor + 1 == floor,
+            None => true,
+        };
+        let openness = if is_at_floor { state.doors } else { 0. };
+        // Ease in and out
+        let slide = openness * openness * (3. - 2. * openness) * DOOR_SLIDE;
+        let translation = closed + transform.rotation * Vec3::X * slide;
+        if transform.translation != translation {
+            transform.translation = translation;
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{
+    player_control::actions::ActionsFrozen,
+    shader::{HighlightMaterial, RimHighlight},
+    world_interaction::interactions_ui::InteractionOpportunity,
+    GameState,
+};
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+/// Highlights the object the player can currently interact with by giving its meshes a rim glow.
+/// The highlighted copies of materials are reused until their original changes or the level is left.
+pub(crate) fn highlight_plugin(app: &mut App) {
+    app.register_type::<Highlighted>()
+        .init_resource::<HighlightMaterialCache>()
+        .add_systems(
+            Update,
+            (
+                highlight_interaction_opportunity,
+                evict_highlight_materials,
+                apply_highlight,
+                remove_highlight,
+            )
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(OnExit(GameState::Playing), clear_highlight_materials);
+}
+
+/// Marks an entity whose meshes should be rendered with a [`HighlightMaterial`].
+#[derive(Debug, Clone, Eq, PartialEq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Highlighted;
+
+/// The material a mesh had before it was highlighted.
+#[derive(Debug, Clone, Component)]
+struct UnhighlightedMaterial(Handle<StandardMaterial>);
+
+/// The highlighted copy of each material that has been highlighted so far
+#[derive(Debug, Resource, Default)]
+struct HighlightMaterialCache(HashMap<AssetId<StandardMaterial>, Handle<HighlightMaterial>>);
+
+fn highlight_interaction_opportunity(
+    mut commands: Commands,
+    interaction_opportunity: Res<InteractionOpportunity>,
+    actions_frozen: Res<ActionsFrozen>,
+    mut highlighted: Local<Option<Entity>>,
+) {
+    // Don't keep highlighting while e.g. talking to the target
+    let target = if actions_frozen.is_frozen() {
+        None
+    } else {
+        interaction_opportunity.0
+    };
+    if *highlighted == target {
+        return;
+    }
+    if let Some(previous) = highlighted.take()
+        && let Some(mut entity_commands) = commands.get_entity(previous)
+    {
+        entity_commands.remove::<Highlighted>();
+    }
+    if let Some(target) = target {
+        commands.entity(target).insert(Highlighted);
+    }
+    *highlighted = target;
+}
+
+/// Copies of materials that were changed, e.g. by hot reloading, or unloaded are stale
+fn evict_highlight_materials(
+    mut events: EventReader<AssetEvent<StandardMaterial>>,
+    mut cache: ResMut<HighlightMaterialCache>,
+) {
+    for event in events.read() {
+        if let AssetEvent::Modified { id } | AssetEvent::Removed { id } = event {
+            cache.0.remove(id);
+        }
+    }
+}
+
+fn clear_highlight_materials(mut cache: ResMut<HighlightMaterialCache>) {
+    cache.0.clear();
+}
+
+fn apply_highlight(
+    mut commands: Commands,
+    highlighted: Query<Entity, Added<Highlighted>>,
+    children: Query<&Children>,
+    material_handles: Query<&Handle<StandardMaterial>>,
+    standard_materials: Res<Assets<StandardMaterial>>,
+    mut highlight_materials: ResMut<Assets<HighlightMaterial>>,
+    mut cache: ResMut<HighlightMaterialCache>,
+) {
+    for entity in highlighted.iter() {
+        for mesh in std::iter::once(entity).chain(children.iter_descendants(entity)) {
+            let Ok(original) = material_handles.get(mesh) else {
+                continue;
+            };
+            let Some(base) = standard_materials.get(original) else {
+                continue;
+            };
+            let highlight = cache
+                .0
+                .entry(original.id())
+                .or_insert_with(|| {
+                    highlight_materials.add(HighlightMaterial {
+                        base: base.clone(),
+                        extension: RimHighlight::default(),
+                    })
+                })
+                .clone();
+            commands
+                .entity(mesh)
+                .remove::<Handle<StandardMaterial>>()
+                .insert((highlight, UnhighlightedMaterial(original.clone())));
+        }
+    }
+}
+
+fn remove_highlight(
+    mut commands: Commands,
+    mut removed: RemovedComponents<Highlighted>,
+    children: Query<&Children>,
+    unhighlighted: Query<&UnhighlightedMaterial>,
+) {
+    for entity in removed.read() {
+        for mesh in std::iter::once(entity).chain(children.iter_descendants(entity)) {
+            let Ok(original) = unhighlighted.get(mesh) else {
+                continue;
+            };
+            commands
+                .entity(mesh)
+                .remove::<(Handle<HighlightMaterial>, UnhighlightedMaterial)>()
+                .insert(original.0.clone());
+        }
+    }
+}
@@ -1,31 +1,58 @@
 use crate::{
+    game_events::DialogStarted,
+    level_instantiation::spawning::objects::mount::Mount,
+    movement::vehicle::Vehicle,
     player_control::{
-        actions::{ActionsFrozen, PlayerAction},
+        actions::{ActionsFrozen, CameraAction, PlayerAction},
         camera::{IngameCamera, IngameCameraKind},
         player_embodiment::Player,
     },
     util::criteria::is_frozen,
 };
 
-use crate::{world_interaction::dialog::DialogTarget, GameState};
-use anyhow::{Context, Result};
-use bevy::{prelude::*, window::PrimaryWindow};
-use bevy_egui::{egui, EguiContexts};
+use crate::{
+    world_interaction::{
+        crafting::CraftingStation,
+        dialog::DialogTarget,
+        elevators::ElevatorButton,
+        readables::Readable,
+        shop::Vendor,
+        triggers::{Trigger, TriggerKind, TriggerSystemSet},
+    },
+    GameState,
+};
+use anyhow::Result;
+use bevy::prelude::*;
 use bevy_mod_sysfail::*;
-use bevy_xpbd_3d::prelude::*;
 use bevy_yarnspinner::prelude::DialogueRunner;
 use leafwing_input_manager::prelude::ActionState;
 use serde::{Deserialize, Serialize};
 use std::f32::consts::TAU;
 
+/// Score an interactable gains per point of [`InteractionPriority`]
+const PRIORITY_WEIGHT: f32 = 10.;
+/// Score an interactable loses per meter away from the player
+const DISTANCE_WEIGHT: f32 = 1.;
+/// Score an interactable loses per radian away from where the camera looks
+const ANGLE_WEIGHT: f32 = 4.;
+
+/// Finds what the player can interact with. Everything whose interaction trigger the player stands in and that they
+/// are facing is a candidate. Candidates are ranked by their [`InteractionPriority`], how close they are and how
+/// directly the camera looks at them, and the best one becomes the [`InteractionOpportunity`].
+/// When there are several, the player can cycle through them with [`PlayerAction::NextInteraction`] and
+/// [`PlayerAction::PreviousInteraction`], which are bound to the mouse wheel, so the camera does not zoom meanwhile.
+/// The choice sticks for as long as the chosen candidate stays available.
 pub(crate) fn interactions_ui_plugin(app: &mut App) {
     app.register_type::<InteractionOpportunity>()
+        .register_type::<InteractionPriority>()
         .init_resource::<InteractionOpportunity>()
+        .init_resource::<InteractionCandidates>()
         .add_systems(
             Update,
             (
-                update_interaction_opportunities.after(PhysicsSet::Sync),
-                display_interaction_prompt,
+                update_interaction_opportunities.after(TriggerSystemSet),
+                cycle_interaction_candidates,
+                handle_interaction,
             )
                 .chain()
                 .run_if(
@@ -40,121 +67,165 @@ pub(crate) fn interactions_ui_plugin(app: &mut App) {
 #[reflect(Resource, Serialize, Deserialize)]
 pub(crate) struct InteractionOpportunity(pub(crate) Option<Entity>);
 
-#[sysfail(log(level = "error"))]
+/// Everything the player could interact with right now, best first.
+#[derive(Debug, Clone, Eq, PartialEq, Resource, Default)]
+pub(crate) struct InteractionCandidates(pub(crate) Vec<Entity>);
+
+/// Makes an interactable win over others that are just as close, or lose with a negative value.
+#[derive(
+    Debug, Clone, Copy, Eq, PartialEq, Component, Reflect, Serialize, Deserialize, Default,
+)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct InteractionPriority(pub(crate) i32);
+
 fn update_interaction_opportunities(
-    mut collisions: EventReader<Collision>,
-    player_query: Query<&Transform, With<Player>>,
-    parents: Query<&Parent>,
+    player_query: Query<(Entity, &Transform), With<Player>>,
+    triggers: Query<(Entity, &Trigger, Option<&Parent>)>,
     target_query: Query<
-        (Entity, &Transform),
-        (With<DialogTarget>, Without<Player>, Without<IngameCamera>),
+        (Entity, &Transform, Option<&InteractionPriority>),
+        (
+            Or<(
+                With<DialogTarget>,
+                With<Vehicle>,
+                With<Mount>,
+                With<Readable>,
+                With<Vendor>,
+                With<CraftingStation>,
+                With<ElevatorButton>,
+            )>,
+            Without<Player>,
+            Without<IngameCamera>,
+        ),
     >,
     camera_query: Query<(&IngameCamera, &Transform), Without<Player>>,
     mut interaction_opportunity: ResMut<InteractionOpportunity>,
-) -> Result<()> {
-    interaction_opportunity.0 = None;
-
-    for Collision(ref contacts) in collisions.read() {
-        // Check if the player is colliding with anything
-        let Some((player, sensor)) =
-            get_player_and_target(&player_query, contacts.entity1, contacts.entity2)
-        else {
-            continue;
-        };
-
-        // We might collide with the sensor or the dialog target itself.
-        // If we collide with the sensor, we need to take its parent to get the dialog target
-        let parent = parents.get(sensor).map(Parent::get).unwrap_or(sensor);
+    mut candidates: ResMut<InteractionCandidates>,
+) {
+    let previous = interaction_opportunity.0.take();
+    candidates.0.clear();
+    let Some((camera, camera_transform)) = camera_query.iter().next() else {
+        return;
+    };
 
-        // Check if what we are colliding with is a dialog target
-        let Ok((target, target_transform)) = target_query
-            .get(sensor)
-            .or_else(|_| target_query.get(parent))
-        else {
-            continue;
-        };
+    let mut scored = Vec::new();
+    for (player, player_transform) in player_query.iter() {
+        for (trigger_entity, trigger, parent) in triggers.iter() {
+            if trigger.kind != TriggerKind::Interaction || !trigger.occupants.contains(&player) {
+                continue;
+            }
+            // The trigger is usually a sensor attached to the actual target
+            let Ok((target, target_transform, priority)) = target_query
+                .get(trigger_entity)
+                .or_else(|_| target_query.get(parent.map(Parent::get).unwrap_or(trigger_entity)))
+            else {
+                continue;
+            };
 
-        if !contacts.during_current_frame {
-            continue;
-        }
-
-        // Check if we are facing the right way
-        let player_translation = player_query.get(player).unwrap().translation;
-        let Some((camera, camera_transform)) = camera_query.iter().next() else {
-            continue;
-        };
-        let is_facing_target = is_facing_target(
-            player_translation,
-            target_transform.translation,
-            *camera_transform,
-            camera,
-        );
-        if is_facing_target {
-            interaction_opportunity.0.replace(target);
+            // Check if we are facing the right way
+            let Some(angle) = facing_angle(
+                player_transform.translation,
+                target_transform.translation,
+                *camera_transform,
+                camera,
+            ) else {
+                continue;
+            };
+            let distance = player_transform
+                .translation
+                .distance(target_transform.translation);
+            let priority = priority.map_or(0, |priority| priority.0);
+            let score = priority as f32 * PRIORITY_WEIGHT
+                - distance * DISTANCE_WEIGHT
+                - angle * ANGLE_WEIGHT;
+            scored.push((target, score));
         }
     }
-    Ok(())
-}
 
-fn get_player_and_target(
-    player_query: &Query<&Transform, With<Player>>,
-    entity_a: Entity,
-    entity_b: Entity,
-) -> Option<(Entity, Entity)> {
-    if player_query.contains(entity_a) {
-        Some((entity_a, entity_b))
-    } else if player_query.contains(entity_b) {
-        Some((entity_b, entity_a))
-    } else {
-        None
+    scored.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    for (target, _) in scored {
+        // Targets with several triggers only count once
+        if !candidates.0.contains(&target) {
+            candidates.0.push(target);
+        }
     }
+    interaction_opportunity.0 = previous
+        .filter(|previous| candidates.0.contains(previous))
+        .or_else(|| candidates.0.first().copied());
 }
 
-fn is_facing_target(
+/// The angle between where the camera looks and the target if the player is facing it.
+/// The fixed angle camera does not tell where the player is looking, so there, everything counts as straight ahead.
+fn facing_angle(
     player: Vec3,
     target: Vec3,
     camera_transform: Transform,
     camera: &IngameCamera,
-) -> bool {
+) -> Option<f32> {
     if camera.kind == IngameCameraKind::FixedAngle {
-        return true;
+        return Some(0.);
     }
     let camera_to_player = camera_transform.forward();
     let player_to_target = target - player;
     let angle = camera_to_player.angle_between(player_to_target);
-    angle < TAU / 8.
+    (angle < TAU / 8.).then_some(angle)
+}
+
+fn cycle_interaction_candidates(
+    candidates: Res<InteractionCandidates>,
+    mut interaction_opportunity: ResMut<InteractionOpportunity>,
+    actions: Query<&ActionState<PlayerAction>>,
+    mut camera_actions: Query<&mut ActionState<CameraAction>>,
+) {
+    if candidates.0.len() < 2 {
+        return;
+    }
+    // The mouse wheel picks candidates now instead of zooming
+    for mut camera_actions in camera_actions.iter_mut() {
+        camera_actions.action_data_mut(CameraAction::Zoom).value = default();
+    }
+    let Some(current) = interaction_opportunity
+        .0
+        .and_then(|current| candidates.0.iter().position(|&entity| entity == current))
+    else {
+        return;
+    };
+    let count = candidates.0.len();
+    for actions in actions.iter() {
+        let next = if actions.just_pressed(PlayerAction::NextInteraction) {
+            (current + 1) % count
+        } else if actions.just_pressed(PlayerAction::PreviousInteraction) {
+            (current + count - 1) % count
+        } else {
+            continue;
+        };
+        interaction_opportunity.0 = Some(candidates.0[next]);
+    }
 }
 
 #[sysfail(log(level = "error"))]
-fn display_interaction_prompt(
+fn handle_interaction(
     interaction_opportunity: Res<InteractionOpportunity>,
     mut dialogue_runner: Query<&mut DialogueRunner>,
-    mut egui_contexts: EguiContexts,
     actions: Query<&ActionState<PlayerAction>>,
-    primary_windows: Query<&Window, With<PrimaryWindow>>,
     dialog_target_query: Query<&DialogTarget>,
     mut freeze: ResMut<ActionsFrozen>,
+    mut dialogs_started: EventWriter<DialogStarted>,
 ) -> Result<()> {
     let Some(opportunity) = interaction_opportunity.0 else {
         return Ok(());
     };
-    let dialog_target = dialog_target_query.get(opportunity)?;
-    let window = primary_windows
-        .get_single()
-        .context("Failed to get primary window")?;
-    egui::Window::new("Interaction")
-        .collapsible(false)
-        .title_bar(false)
-        .auto_sized()
-        .fixed_pos(egui::Pos2::new(window.width() / 2., window.height() / 2.))
-        .show(egui_contexts.ctx_mut(), |ui| {
-            ui.label("E: Talk");
-        });
+    // Other kinds of targets, like vehicles, handle being interacted with themselves
+    let Ok(dialog_target) = dialog_target_query.get(opportunity) else {
+        return Ok(());
+    };
     for actions in actions.iter() {
         if actions.just_pressed(PlayerAction::Interact) {
             let mut dialogue_runner = dialogue_runner.single_mut();
             dialogue_runner.start_node(&dialog_target.node);
             freeze.freeze();
+            dialogs_started.send(DialogStarted {
+                node: dialog_target.node.clone(),
+            });
         }
     }
     Ok(())
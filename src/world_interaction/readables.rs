@@ -0,0 +1,267 @@
+use crate::{
+    level_instantiation::markers::{Marker, MarkersAppExt},
+    menu::Settings,
+    movement::physics::CollisionLayer,
+    player_control::{
+        actions::{ActionsFrozen, PlayerAction, UiAction},
+        camera::IngameCamera,
+        player_embodiment::Player,
+    },
+    theme::UiTheme,
+    util::criteria::is_frozen,
+    world_interaction::{
+        interactions_ui::InteractionOpportunity,
+        triggers::{Trigger, TriggerKind},
+    },
+    GameState,
+};
+use anyhow::{bail, Context, Result};
+use bevy::{ecs::world::EntityWorldMut, prelude::*, utils::HashMap};
+use bevy_common_assets::ron::RonAssetPlugin;
+use bevy_egui::{egui, EguiContexts, EguiSettings};
+use bevy_xpbd_3d::prelude::*;
+use leafwing_input_manager::prelude::ActionState;
+use serde::{Deserialize, Serialize};
+
+/// Texts are looked up in this language when the selected one does not have them
+const FALLBACK_LANGUAGE: &str = "en";
+const INTERACTION_RADIUS: f32 = 1.5;
+/// How far above its origin a sign's panel is drawn
+const SIGN_PANEL_OFFSET: f32 = 1.;
+
+/// Lets the player read notes, signs and other texts placed in the level.
+/// In Blender, objects named with a `[readable:<key>]` suffix open a full-screen note when interacted with,
+/// and ones named `[readable:<key>:sign]` open a panel next to the object instead.
+/// The title and body are looked up by `key` in `texts/<language>.readables.ron` for the language in the [`Settings`],
+/// falling back to English. Movement is frozen while a text is open, which is closed with confirm or back.
+pub(crate) fn readables_plugin(app: &mut App) {
+    app.register_type::<Readable>()
+        .register_type::<ReadableStyle>()
+        .add_plugins(RonAssetPlugin::<ReadableTexts>::new(&["readables.ron"]))
+        .init_resource::<OpenReadable>()
+        .register_marker("readable", insert_readable)
+        .add_systems(
+            Update,
+            load_readable_texts.run_if(resource_changed::<Settings>()),
+        )
+        .add_systems(
+            Update,
+            (open_readable.run_if(not(is_frozen)), show_readable)
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(OnExit(GameState::Playing), close_readable);
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Readable {
+    /// Key of the text in the readables files
+    pub(crate) key: String,
+    pub(crate) style: ReadableStyle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Serialize, Deserialize)]
+pub(crate) enum ReadableStyle {
+    /// Covers the whole screen, like a letter held up to the camera
+    #[default]
+    Note,
+    /// A panel shown at the object in the world
+    Sign,
+}
+
+/// The contents of a `texts/<language>.readables.ron`, mapping keys to texts.
+#[derive(Debug, Clone, PartialEq, Asset, TypePath, Serialize, Deserialize, Default)]
+#[serde(transparent)]
+pub(crate) struct ReadableTexts(HashMap<String, ReadableText>);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct ReadableText {
+    pub(crate) title: String,
+    pub(crate) body: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Resource, Default)]
+struct ReadableTextHandles {
+    selected: Handle<ReadableTexts>,
+    fallback: Handle<ReadableTexts>,
+}
+
+/// The readable entity whose text is on screen
+#[derive(Debug, Clone, PartialEq, Eq, Resource, Default)]
+struct OpenReadable(Option<Entity>);
+
+fn insert_readable(entity: &mut EntityWorldMut, marker: &Marker) -> Result<()> {
+    let key = marker
+        .argument(0)
+        .context("Expected [readable:<key>] or [readable:<key>:sign]")?;
+    let style = match marker.argument(1) {
+        None => ReadableStyle::Note,
+        Some("sign") => ReadableStyle::Sign,
+        Some(style) => bail!("Unknown readable style {style}, expected sign"),
+    };
+    entity
+        .insert(Readable {
+            key: key.to_string(),
+            style,
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                Name::new("Readable Interaction Collider"),
+                SpatialBundle::default(),
+                Collider::ball(INTERACTION_RADIUS),
+                CollisionLayer::trigger(),
+                Sensor,
+                Trigger::new(TriggerKind::Interaction),
+            ));
+        });
+    Ok(())
+}
+
+fn load_readable_texts(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    asset_server: Res<AssetServer>,
+) {
+    let path = |language: &str| format!("texts/{language}.readables.ron");
+    commands.insert_resource(ReadableTextHandles {
+        selected: asset_server.load(path(&settings.language)),
+        fallback: asset_server.load(path(FALLBACK_LANGUAGE)),
+    });
+}
+
+fn open_readable(
+    interaction_opportunity: Res<InteractionOpportunity>,
+    readables: Query<(), With<Readable>>,
+    players: Query<&ActionState<PlayerAction>, With<Player>>,
+    mut open: ResMut<OpenReadable>,
+    mut freeze: ResMut<ActionsFrozen>,
+) {
+    let Some(target) = interaction_opportunity
+        .0
+        .filter(|target| readables.contains(*target))
+    else {
+        return;
+    };
+    if players
+        .iter()
+        .any(|actions| actions.just_pressed(PlayerAction::Interact))
+    {
+        open.0 = Some(target);
+        freeze.freeze();
+    }
+}
+
+fn show_readable(
+    mut open: ResMut<OpenReadable>,
+    readables: Query<(&Readable, &GlobalTransform)>,
+    actions: Query<&ActionState<UiAction>>,
+    handles: Option<Res<ReadableTextHandles>>,
+    texts: Res<Assets<ReadableTexts>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<IngameCamera>>,
+    egui_settings: Res<EguiSettings>,
+    mut freeze: ResMut<ActionsFrozen>,
+    theme: Res<UiTheme>,
+    mut egui_contexts: EguiContexts,
+) {
+    let Some(entity) = open.0 else {
+        return;
+    };
+    let closed = actions.iter().any(|actions| {
+        actions.just_pressed(UiAction::Confirm) || actions.just_pressed(UiAction::Back)
+    });
+    // The readable may have been despawned, e.g. by chunk streaming
+    let readable = readables.get(entity).ok().filter(|_| !closed);
+    let Some((readable, transform)) = readable else {
+        open.0 = None;
+        freeze.unfreeze();
+        return;
+    };
+    let text = handles
+        .as_deref()
+        .and_then(|handles| {
+            [&handles.selected, &handles.fallback]
+                .into_iter()
+                .find_map(|handle| texts.get(handle)?.0.get(&readable.key))
+        })
+        .cloned()
+        .unwrap_or_else(|| ReadableText {
+            title: readable.key.clone(),
+            body: "This text is missing.".to_string(),
+        });
+    let ctx = egui_contexts.ctx_mut();
+    let mut close_clicked = false;
+    let sign_position = cameras
+        .iter()
+        .next()
+        .and_then(|(camera, camera_transform)| {
+            let position = transform.translation() + Vec3::Y * SIGN_PANEL_OFFSET;
+            let screen_position = camera.world_to_viewport(camera_transform, position)?;
+            let scale_factor = egui_settings.scale_factor as f32;
+            Some(egui::pos2(
+                screen_position.x / scale_factor,
+                screen_position.y / scale_factor,
+            ))
+        });
+    match (readable.style, sign_position) {
+        (ReadableStyle::Sign, Some(position)) => {
+            egui::Area::new("Readable sign")
+                .fixed_pos(position)
+                .pivot(egui::Align2::CENTER_BOTTOM)
+                .order(egui::Order::Foreground)
+                .show(ctx, |ui| {
+                    theme.panel(ui, |ui| {
+                        ui.set_max_width(20. * theme.text.body);
+                        close_clicked = readable_contents(ui, &theme, &text);
+                    });
+                });
+        }
+        // A sign behind the camera is read like a note
+        _ => {
+            egui::CentralPanel::default()
+                .frame(theme.overlay_frame())
+                .show(ctx, |ui| {
+                    ui.vertical_centered(|ui| {
+                        ui.set_max_width(40. * theme.text.body);
+                        ui.add_space(2. * theme.spacing.large);
+                        close_clicked = readable_contents(ui, &theme, &text);
+                    });
+                });
+        }
+    }
+    if close_clicked {
+        open.0 = None;
+        freeze.unfreeze();
+    }
+}
+
+/// Draws the title, body and a close button. Returns whether the button was clicked.
+fn readable_contents(ui: &mut egui::Ui, theme: &UiTheme, text: &ReadableText) -> bool {
+    if !text.title.is_empty() {
+        ui.label(
+            egui::RichText::new(&text.title)
+                .size(theme.text.heading)
+                .color(theme.colors.text),
+        );
+        ui.add_space(theme.spacing.small);
+    }
+    egui::ScrollArea::vertical()
+        .max_height(ui.available_height() - 4. * theme.spacing.large)
+        .show(ui, |ui| {
+            ui.label(
+                egui::RichText::new(&text.body)
+                    .size(theme.text.body)
+                    .color(theme.colors.text),
+            );
+        });
+    ui.add_space(theme.spacing.small);
+    ui.button("Close").clicked()
+}
+
+fn close_readable(mut open: ResMut<OpenReadable>, mut freeze: ResMut<ActionsFrozen>) {
+    if open.0.take().is_some() {
+        freeze.unfreeze();
+    }
+}
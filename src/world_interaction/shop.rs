@@ -0,0 +1,228 @@
+use crate::{
+    file_system_interaction::config::{GameConfig, ItemDefinition},
+    game_events::ItemPickedUp,
+    hud::Hotbar,
+    level_instantiation::markers::{Marker, MarkersAppExt},
+    movement::physics::CollisionLayer,
+    player_control::{
+        actions::{ActionsFrozen, PlayerAction, UiAction},
+        player_embodiment::Player,
+    },
+    theme::UiTheme,
+    util::criteria::is_frozen,
+    world_interaction::{
+        interactions_ui::InteractionOpportunity,
+        triggers::{Trigger, TriggerKind},
+    },
+    GameState,
+};
+use anyhow::{Context, Result};
+use bevy::{ecs::world::EntityWorldMut, prelude::*};
+use bevy_egui::{egui, EguiContexts};
+use bevy_xpbd_3d::prelude::*;
+use bevy_yarnspinner::prelude::DialogueRunner;
+use leafwing_input_manager::prelude::ActionState;
+use serde::{Deserialize, Serialize};
+
+/// Fraction of an item's price the player gets when selling it
+const SELL_PRICE_FRACTION: f32 = 0.5;
+const INTERACTION_RADIUS: f32 = 2.;
+
+/// Lets the player buy and sell items for [`Coins`].
+/// Items are defined in the `items` section of the [`GameConfig`] with their name and price,
+/// and shops in the `shops` section with their name and the items they sell.
+/// Bought items go into the player's [`Hotbar`] and are published as [`ItemPickedUp`]. Anything in the hotbar
+/// with a price can be sold for half of it.
+/// In Blender, objects named with a `[vendor:<shop>]` suffix open the shop when interacted with.
+/// Merchants can also open their shop from a dialog with `<<open_shop <shop>>>`, ideally at the end of a node.
+/// Dialogs can pay the player with `<<give_coins 10>>`.
+pub(crate) fn shop_plugin(app: &mut App) {
+    app.register_type::<Vendor>()
+        .register_type::<Coins>()
+        .init_resource::<Coins>()
+        .init_resource::<OpenShop>()
+        .register_marker("vendor", insert_vendor)
+        .add_systems(
+            Update,
+            (open_vendor_shop.run_if(not(is_frozen)), show_shop)
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(OnExit(GameState::Playing), (close_shop, reset_coins));
+}
+
+/// Opens the shop with the given ID from the `shops` section of the [`GameConfig`] when interacted with.
+#[derive(Debug, Clone, PartialEq, Eq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Vendor {
+    pub(crate) shop: String,
+}
+
+/// The player's money.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Resource, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Resource, Serialize, Deserialize)]
+pub(crate) struct Coins(pub(crate) u32);
+
+/// The ID of the shop on screen
+#[derive(Debug, Clone, PartialEq, Eq, Resource, Default)]
+struct OpenShop(Option<String>);
+
+fn insert_vendor(entity: &mut EntityWorldMut, marker: &Marker) -> Result<()> {
+    let shop = marker.argument(0).context("Expected [vendor:<shop>]")?;
+    entity
+        .insert(Vendor {
+            shop: shop.to_string(),
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                Name::new("Vendor Interaction Collider"),
+                SpatialBundle::default(),
+                Collider::ball(INTERACTION_RADIUS),
+                CollisionLayer::trigger(),
+                Sensor,
+                Trigger::new(TriggerKind::Interaction),
+            ));
+        });
+    Ok(())
+}
+
+/// `<<open_shop general_store>>` opens the shop once the dialog has ended.
+pub(crate) fn open_shop_command(
+    In(shop): In<String>,
+    mut open: ResMut<OpenShop>,
+    mut freeze: ResMut<ActionsFrozen>,
+) {
+    if open.0.replace(shop).is_none() {
+        freeze.freeze();
+    }
+}
+
+/// `<<give_coins 10>>` pays the player.
+pub(crate) fn give_coins_command(In(amount): In<f32>, mut coins: ResMut<Coins>) {
+    coins.0 = coins.0.saturating_add(amount.max(0.) as u32);
+}
+
+fn open_vendor_shop(
+    interaction_opportunity: Res<InteractionOpportunity>,
+    vendors: Query<&Vendor>,
+    players: Query<&ActionState<PlayerAction>, With<Player>>,
+    mut open: ResMut<OpenShop>,
+    mut freeze: ResMut<ActionsFrozen>,
+) {
+    let Some(vendor) = interaction_opportunity
+        .0
+        .and_then(|target| vendors.get(target).ok())
+    else {
+        return;
+    };
+    if players
+        .iter()
+        .any(|actions| actions.just_pressed(PlayerAction::Interact))
+    {
+        open.0 = Some(vendor.shop.clone());
+        freeze.freeze();
+    }
+}
+
+fn show_shop(
+    mut open: ResMut<OpenShop>,
+    actions: Query<&ActionState<UiAction>>,
+    mut players: Query<&mut Hotbar, With<Player>>,
+    mut coins: ResMut<Coins>,
+    config: Res<GameConfig>,
+    mut items_picked_up: EventWriter<ItemPickedUp>,
+    mut freeze: ResMut<ActionsFrozen>,
+    dialogue_runners: Query<&DialogueRunner>,
+    theme: Res<UiTheme>,
+    mut egui_contexts: EguiContexts,
+) {
+    let Some(shop_id) = open.0.clone() else {
+        return;
+    };
+    // Shops opened from a dialog wait for it to end
+    if dialogue_runners.iter().any(DialogueRunner::is_running) {
+        return;
+    }
+    let back_pressed = actions
+        .iter()
+        .any(|actions| actions.just_pressed(UiAction::Back));
+    let shop = config.shops.get(&shop_id);
+    let Some((shop, mut hotbar)) = shop
+        .zip(players.get_single_mut().ok())
+        .filter(|_| !back_pressed)
+    else {
+        if !back_pressed {
+            error!("There is no shop called {shop_id}");
+        }
+        open.0 = None;
+        freeze.unfreeze();
+        return;
+    };
+    let mut close = false;
+    egui::CentralPanel::default()
+        .frame(theme.overlay_frame())
+        .show(egui_contexts.ctx_mut(), |ui| {
+            ui.vertical_centered(|ui| {
+                ui.set_max_width(40. * theme.text.body);
+                ui.add_space(2. * theme.spacing.large);
+                ui.heading(&shop.name);
+                ui.label(format!("Coins: {}", coins.0));
+                ui.separator();
+                ui.columns(2, |columns| {
+                    columns[0].label("Buy");
+                    for key in &shop.stock {
+                        let Some(item) = config.items.get(key) else {
+                            continue;
+                        };
+                        let has_room = hotbar.slots.iter().any(Option::is_none);
+                        let affordable = coins.0 >= item.price;
+                        let button = egui::Button::new(format!("{} ({})", item.name, item.price));
+                        if columns[0]
+                            .add_enabled(has_room && affordable, button)
+                            .clicked()
+                            && hotbar.add(key.clone()).is_some()
+                        {
+                            coins.0 -= item.price;
+                            items_picked_up.send(ItemPickedUp { item: key.clone() });
+                        }
+                    }
+                    columns[1].label("Sell");
+                    for index in 0..hotbar.slots.len() {
+                        let Some((key, item)) = hotbar.slots[index]
+                            .as_ref()
+                            .and_then(|key| Some((key, config.items.get(key)?)))
+                        else {
+                            continue;
+                        };
+                        let price = sell_price(item);
+                        let button = egui::Button::new(format!("{} ({price})", item.name));
+                        if columns[1].add(button).clicked() {
+                            info!("Sold {key} for {price}");
+                            hotbar.slots[index] = None;
+                            coins.0 = coins.0.saturating_add(price);
+                        }
+                    }
+                });
+                ui.separator();
+                close = ui.button("Close").clicked();
+            });
+        });
+    if close {
+        open.0 = None;
+        freeze.unfreeze();
+    }
+}
+
+fn sell_price(item: &ItemDefinition) -> u32 {
+    (item.price as f32 * SELL_PRICE_FRACTION).floor() as u32
+}
+
+fn close_shop(mut open: ResMut<OpenShop>, mut freeze: ResMut<ActionsFrozen>) {
+    if open.0.take().is_some() {
+        freeze.unfreeze();
+    }
+}
+
+fn reset_coins(mut coins: ResMut<Coins>) {
+    *coins = default();
+}
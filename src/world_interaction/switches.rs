@@ -0,0 +1,236 @@
+use crate::{
+    game_events::SwitchToggled,
+    hud::captions::CaptionEvent,
+    level_instantiation::markers::{Marker, MarkersAppExt},
+    movement::physics::CollisionLayer,
+    world_interaction::triggers::{Trigger, TriggerKind, TriggerSystemSet},
+    GameState,
+};
+use anyhow::{Context, Result};
+use bevy::{ecs::world::EntityWorldMut, prelude::*, utils::HashSet};
+use bevy_xpbd_3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Seconds a switch door takes to fully open or close
+const DOOR_DURATION: f32 = 1.5;
+
+/// Handles switches and the mechanisms they drive, for building physics puzzles.
+/// In Blender, objects named with a `[pressure_plate:<switch>]` or `[pressure_plate:<switch>:<mass>]` suffix become
+/// pressure plates that turn their switch on while the total mass on them reaches the given value.
+/// Masses are those of the physics simulation, in which a cubic meter with the default [`ColliderDensity`] weighs 1,
+/// so that the player weighs about 0.2.
+/// Everything with a dynamic body counts, i.e. the player, NPCs and props, including props stacked on top of them.
+/// Objects named `[switch_door:<switch>]` slide open while their switch is on and close again when it turns off.
+/// Every change sends a [`SwitchToggled`], so that other mechanisms can listen to switches as well.
+pub(crate) fn switches_plugin(app: &mut App) {
+    app.register_type::<PressurePlate>()
+        .register_type::<SwitchDoor>()
+        .register_marker("pressure_plate", insert_pressure_plate)
+        .register_marker("switch_door", |entity, marker| {
+            let switch = marker
+                .argument(0)
+                .context("Expected [switch_door:<switch>]")?;
+            entity.insert(SwitchDoor {
+                switch: switch.to_string(),
+                ..default()
+            });
+            Ok(())
+        })
+        .add_systems(
+            Update,
+            (weigh_pressure_plates, move_switch_doors)
+                .chain()
+                .after(TriggerSystemSet)
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct PressurePlate {
+    /// Name of the switch the plate turns on
+    pub(crate) switch: String,
+    /// Mass needed to press the plate down
+    pub(crate) threshold: f32,
+    /// Size of the box resting on the plate's origin in which things are weighed
+    pub(crate) size: Vec3,
+    /// Mass currently on the plate
+    #[serde(skip)]
+    pub(crate) load: f32,
+    #[serde(skip)]
+    pub(crate) pressed: bool,
+}
+
+impl Default for PressurePlate {
+    fn default() -> Self {
+        Self {
+            switch: default(),
+            threshold: 0.2,
+            size: Vec3::new(1., 0.5, 1.),
+            load: 0.,
+            pressed: false,
+        }
+    }
+}
+
+/// A door or gate that opens while its switch is on.
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct SwitchDoor {
+    pub(crate) switch: String,
+    /// How far the door moves when open, in its own space
+    pub(crate) offset: Vec3,
+    /// How far the door is open, from 0 to 1
+    #[serde(skip)]
+    pub(crate) openness: f32,
+    #[serde(skip)]
+    pub(crate) open: bool,
+}
+
+impl Default for SwitchDoor {
+    fn default() -> Self {
+        Self {
+            switch: default(),
+            offset: Vec3::Y * 2.5,
+            openness: 0.,
+            open: false,
+        }
+    }
+}
+
+/// The door's position when closed, captured when it is first moved
+#[derive(Debug, Clone, Copy, PartialEq, Component)]
+struct ClosedDoor(Vec3);
+
+fn insert_pressure_plate(entity: &mut EntityWorldMut, marker: &Marker) -> Result<()> {
+    let switch = marker
+        .argument(0)
+        .context("Expected [pressure_plate:<switch>] or [pressure_plate:<switch>:<mass>]")?;
+    let mut plate = PressurePlate {
+        switch: switch.to_string(),
+        ..default()
+    };
+    if let Some(threshold) = marker.argument(1) {
+        plate.threshold = threshold
+            .parse()
+            .context("The mass of a pressure plate must be a number")?;
+    }
+    let size = plate.size;
+    entity.insert(plate).with_children(|parent| {
+        parent.spawn((
+            Name::new("Pressure Plate Sensor"),
+            SpatialBundle::from_transform(Transform::from_translation(Vec3::Y * size.y / 2.)),
+            Collider::cuboid(size.x, size.y, size.z),
+            CollisionLayer::weight_trigger(),
+            Sensor,
+            Trigger::new(TriggerKind::Area),
+        ));
+    });
+    Ok(())
+}
+
+fn weigh_pressure_plates(
+    mut plates: Query<(&mut PressurePlate, &Children)>,
+    triggers: Query<&Trigger>,
+    colliders: Query<&ColliderParent>,
+    bodies: Query<(&RigidBody, &Mass, &GlobalTransform)>,
+    collisions: Res<Collisions>,
+    mut switch_events: EventWriter<SwitchToggled>,
+    mut caption_events: EventWriter<CaptionEvent>,
+) {
+    #[cfg(feature = "tracing")]
+    let _span = info_span!("weigh_pressure_plates").entered();
+    let body_of = |collider: Entity| {
+        colliders
+            .get(collider)
+            .map_or(collider, |parent| parent.get())
+    };
+    for (mut plate, children) in plates.iter_mut() {
+        let mut stack: Vec<_> = children
+            .iter()
+            .filter_map(|child| triggers.get(*child).ok())
+            .flat_map(|trigger| trigger.occupants.iter().copied())
+            .collect();
+        let mut weighed = HashSet::new();
+        let mut load = 0.;
+        // Whatever rests on something on the plate presses it down as well
+        while let Some(collider) = stack.pop() {
+            let body = body_of(collider);
+            let Ok((rigid_body, mass, transform)) = bodies.get(body) else {
+                continue;
+            };
+            if !rigid_body.is_dynamic() || !weighed.insert(body) {
+                continue;
+            }
+            load += mass.0;
+            let height = transform.translation().y;
+            for contacts in collisions.collisions_with_entity(collider) {
+                let other = if contacts.entity1 == collider {
+                    contacts.entity2
+                } else {
+                    contacts.entity1
+                };
+                let is_above = bodies
+                    .get(body_of(other))
+                    .is_ok_and(|(_, _, other_transform)| other_transform.translation().y > height);
+                if is_above {
+                    stack.push(other);
+                }
+            }
+        }
+        if plate.load != load {
+            plate.load = load;
+        }
+        let pressed = load >= plate.threshold;
+        if plate.pressed != pressed {
+            plate.pressed = pressed;
+            caption_events.send(CaptionEvent::sound("[plate clicks]"));
+            switch_events.send(SwitchToggled {
+                switch: plate.switch.clone(),
+                on: pressed,
+            });
+        }
+    }
+}
+
+fn move_switch_doors(
+    mut commands: Commands,
+    time: Res<Time<Virtual>>,
+    mut switch_events: EventReader<SwitchToggled>,
+    mut doors: Query<(Entity, &mut SwitchDoor, &mut Transform, Option<&ClosedDoor>)>,
+) {
+    let events: Vec<_> = switch_events.read().collect();
+    let dt = time.delta_seconds();
+    for (entity, mut door, mut transform, closed) in doors.iter_mut() {
+        if let Some(event) = events
+            .iter()
+            .rev()
+            .find(|event| event.switch == door.switch)
+        {
+            door.open = event.on;
+        }
+        let target = if door.open { 1. } else { 0. };
+        if door.openness == target {
+            continue;
+        }
+        let closed = match closed {
+            Some(closed) => closed.0,
+            None => {
+                commands
+                    .entity(entity)
+                    .insert(ClosedDoor(transform.translation));
+                transform.translation
+            }
+        };
+        let step = dt / DOOR_DURATION;
+        door.openness = if door.open {
+            (door.openness + step).min(1.)
+        } else {
+            (door.openness - step).max(0.)
+        };
+        // Ease in and out
+        let openness = door.openness;
+        let eased = openness * openness * (3. - 2. * openness);
+        transform.translation = closed + transform.rotation * door.offset * eased;
+    }
+}
@@ -0,0 +1,145 @@
+use crate::GameState;
+use bevy::prelude::*;
+use bevy_xpbd_3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Turns the raw collisions of [`Trigger`] sensors into [`TriggerEnter`] and [`TriggerExit`] events,
+/// and keeps track of what is currently inside each trigger.
+/// A trigger is usually a child [`Sensor`] collider of whatever it belongs to, like the dialog range of an NPC.
+pub(crate) fn triggers_plugin(app: &mut App) {
+    app.register_type::<Trigger>()
+        .register_type::<TriggerKind>()
+        .add_event::<TriggerEnter>()
+        .add_event::<TriggerExit>()
+        .add_systems(
+            Update,
+            send_trigger_events
+                .in_set(TriggerSystemSet)
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
+pub(crate) struct TriggerSystemSet;
+
+#[derive(Debug, Clone, PartialEq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct Trigger {
+    pub(crate) kind: TriggerKind,
+    /// Colliders currently inside the trigger
+    #[serde(skip)]
+    pub(crate) occupants: Vec<Entity>,
+}
+
+impl Trigger {
+    pub(crate) fn new(kind: TriggerKind) -> Self {
+        Self {
+            kind,
+            occupants: default(),
+        }
+    }
+}
+
+/// What a trigger is for, so that gameplay modules only need to listen to the events they care about.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Serialize, Deserialize)]
+pub(crate) enum TriggerKind {
+    /// The range in which the player can interact with the trigger's parent
+    Interaction,
+    /// A region of the level, e.g. for starting a cutscene when the player walks in
+    #[default]
+    Area,
+}
+
+#[derive(Debug, Clone, PartialEq, Event)]
+pub(crate) struct TriggerEnter {
+    pub(crate) trigger: Entity,
+    /// The collider that entered
+    pub(crate) other: Entity,
+    pub(crate) kind: TriggerKind,
+    pub(crate) name: Option<String>,
+    /// Where the two first touched, in world space
+    pub(crate) point: Vec3,
+}
+
+#[derive(Debug, Clone, PartialEq, Event)]
+pub(crate) struct TriggerExit {
+    pub(crate) trigger: Entity,
+    /// The collider that left
+    pub(crate) other: Entity,
+    pub(crate) kind: TriggerKind,
+    pub(crate) name: Option<String>,
+}
+
+fn send_trigger_events(
+    mut started: EventReader<CollisionStarted>,
+    mut ended: EventReader<CollisionEnded>,
+    mut triggers: Query<(&mut Trigger, Option<&Name>)>,
+    collisions: Res<Collisions>,
+    transforms: Query<&GlobalTransform>,
+    mut enter_events: EventWriter<TriggerEnter>,
+    mut exit_events: EventWriter<TriggerExit>,
+) {
+    for CollisionStarted(entity1, entity2) in started.read() {
+        for (trigger_entity, other) in [(*entity1, *entity2), (*entity2, *entity1)] {
+            let Ok((mut trigger, name)) = triggers.get_mut(trigger_entity) else {
+                continue;
+            };
+            if triggers.contains(other) {
+                // Triggers do not trigger each other
+                continue;
+            }
+            if !trigger.occupants.contains(&other) {
+                trigger.occupants.push(other);
+            }
+            let point = contact_point(&collisions, &transforms, trigger_entity, other);
+            enter_events.send(TriggerEnter {
+                trigger: trigger_entity,
+                other,
+                kind: trigger.kind.clone(),
+                name: name.map(|name| name.to_string()),
+                point,
+            });
+        }
+    }
+    for CollisionEnded(entity1, entity2) in ended.read() {
+        for (trigger_entity, other) in [(*entity1, *entity2), (*entity2, *entity1)] {
+            let Ok((mut trigger, name)) = triggers.get_mut(trigger_entity) else {
+                continue;
+            };
+            if triggers.contains(other) {
+                continue;
+            }
+            trigger.occupants.retain(|occupant| *occupant != other);
+            exit_events.send(TriggerExit {
+                trigger: trigger_entity,
+                other,
+                kind: trigger.kind.clone(),
+                name: name.map(|name| name.to_string()),
+            });
+        }
+    }
+}
+
+/// Sensors only produce rough contacts, so this falls back to the middle between both colliders.
+fn contact_point(
+    collisions: &Collisions,
+    transforms: &Query<&GlobalTransform>,
+    trigger: Entity,
+    other: Entity,
+) -> Vec3 {
+    let position = |entity| {
+        transforms
+            .get(entity)
+            .map(GlobalTransform::translation)
+            .unwrap_or_default()
+    };
+    collisions
+        .get(trigger, other)
+        .and_then(|contacts| {
+            let contact = contacts.manifolds.first()?.contacts.first()?;
+            let transform = transforms.get(contacts.entity1).ok()?;
+            Some(transform.transform_point(contact.point1))
+        })
+        .unwrap_or_else(|| (position(trigger) + position(other)) / 2.)
+}
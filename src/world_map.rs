@@ -0,0 +1,216 @@
+use crate::{
+    despawn::DespawnOnExit, file_system_interaction::config::GameConfig,
+    movement::physics::TransformInterpolationSet, player_control::player_embodiment::Player,
+    util::trait_extension::Vec3Ext, world_map::ui::map_ui_plugin, GameState,
+};
+use bevy::{
+    prelude::*,
+    render::{
+        camera::{RenderTarget, ScalingMode},
+        render_resource::{
+            Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+        },
+    },
+    transform::TransformSystem,
+    utils::HashSet,
+};
+use seldom_fn_plugin::FnPluginExt;
+use serde::{Deserialize, Serialize};
+
+mod ui;
+
+/// Resolution of the image the map camera renders to
+const MAP_IMAGE_SIZE: u32 = 512;
+/// How high above the map center the map camera floats
+const MAP_CAMERA_HEIGHT: f32 = 100.;
+
+/// Handles the minimap and the full-screen world map. Both are rendered by a top-down orthographic camera
+/// into an image that is then shown through egui.
+/// Split into the following sub-plugins:
+/// - [`map_ui_plugin`]: Draws the minimap overlay and the world map screen.
+pub(crate) fn world_map_plugin(app: &mut App) {
+    app.register_type::<MapMarker>()
+        .register_type::<MapCamera>()
+        .init_resource::<MapDiscovery>()
+        .init_resource::<WorldMapScreen>()
+        .add_systems(OnEnter(GameState::Playing), spawn_map_camera)
+        .add_systems(
+            Update,
+            discover_surroundings.run_if(in_state(GameState::Playing)),
+        )
+        // Follows the interpolated player, like the ingame camera
+        .add_systems(
+            PostUpdate,
+            update_map_camera
+                .after(TransformInterpolationSet)
+                .before(TransformSystem::TransformPropagate)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .fn_plugin(map_ui_plugin);
+}
+
+/// Shows a marker with the given label on the minimap and world map, e.g. for quest objectives.
+#[derive(Debug, Clone, Eq, PartialEq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct MapMarker {
+    pub(crate) label: String,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Component, Reflect, Serialize, Deserialize, Default)]
+#[reflect(Component, Serialize, Deserialize)]
+pub(crate) struct MapCamera;
+
+#[derive(Debug, Clone, Resource)]
+pub(crate) struct MapImage(pub(crate) Handle<Image>);
+
+/// The cells of the world the player has already been close to. Everything else is covered by fog on the world map.
+#[derive(Debug, Clone, Eq, PartialEq, Resource, Serialize, Deserialize, Default)]
+pub(crate) struct MapDiscovery {
+    pub(crate) cells: HashSet<IVec2>,
+}
+
+impl MapDiscovery {
+    pub(crate) fn cell(position: Vec3, cell_size: f32) -> IVec2 {
+        (Vec2::new(position.x, position.z) / cell_size)
+            .floor()
+            .as_ivec2()
+    }
+
+    pub(crate) fn is_discovered(&self, cell: IVec2) -> bool {
+        self.cells.contains(&cell)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Resource, Default)]
+pub(crate) struct WorldMapScreen {
+    pub(crate) open: bool,
+}
+
+/// The area of the world currently shown in the [`MapImage`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct MapView {
+    pub(crate) center: Vec3,
+    pub(crate) size: f32,
+}
+
+impl MapView {
+    pub(crate) fn new(screen: WorldMapScreen, player: Vec3, config: &GameConfig) -> Self {
+        if screen.open {
+            Self {
+                center: Vec3::ZERO,
+                size: config.map.world_size,
+            }
+        } else {
+            Self {
+                center: player.horizontal(),
+                size: config.map.minimap_size,
+            }
+        }
+    }
+
+    /// Maps a world position to coordinates on the map, where (0, 0) is the top left corner and (1, 1) the bottom right one.
+    /// North, i.e. negative Z, is up.
+    pub(crate) fn to_map(self, position: Vec3) -> Vec2 {
+        let offset = position - self.center;
+        Vec2::new(offset.x, offset.z) / self.size + Vec2::splat(0.5)
+    }
+}
+
+fn spawn_map_camera(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let size = Extent3d {
+        width: MAP_IMAGE_SIZE,
+        height: MAP_IMAGE_SIZE,
+        ..default()
+    };
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: Some("map"),
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Bgra8UnormSrgb,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_DST
+                | TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        },
+        ..default()
+    };
+    // Fills the image with zeroes
+    image.resize(size);
+    let image = images.add(image);
+
+    commands.spawn((
+        Name::new("Map Camera"),
+        Camera3dBundle {
+            camera: Camera {
+                target: RenderTarget::Image(image.clone()),
+                // Render before the main camera
+                order: -1,
+                ..default()
+            },
+            projection: OrthographicProjection {
+                scaling_mode: ScalingMode::Fixed {
+                    width: 1.,
+                    height: 1.,
+                },
+                far: MAP_CAMERA_HEIGHT * 2.,
+                ..default()
+            }
+            .into(),
+            ..default()
+        },
+        UiCameraConfig { show_ui: false },
+        MapCamera,
+        DespawnOnExit(GameState::Playing),
+    ));
+    commands.insert_resource(MapImage(image));
+}
+
+fn discover_surroundings(
+    players: Query<&Transform, With<Player>>,
+    mut discovery: ResMut<MapDiscovery>,
+    config: Res<GameConfig>,
+) {
+    let cell_size = config.map.fog_cell_size;
+    let radius = (config.map.discovery_radius / cell_size).ceil() as i32;
+    for transform in players.iter() {
+        let center = MapDiscovery::cell(transform.translation, cell_size);
+        for x in -radius..=radius {
+            for y in -radius..=radius {
+                let offset = IVec2::new(x, y);
+                if offset.length_squared() > radius * radius {
+                    continue;
+                }
+                let cell = center + offset;
+                // Avoid triggering change detection every frame
+                if !discovery.is_discovered(cell) {
+                    discovery.cells.insert(cell);
+                }
+            }
+        }
+    }
+}
+
+fn update_map_camera(
+    mut cameras: Query<(&mut Transform, &mut Projection), With<MapCamera>>,
+    players: Query<&Transform, (With<Player>, Without<MapCamera>)>,
+    screen: Res<WorldMapScreen>,
+    config: Res<GameConfig>,
+) {
+    let Some(player) = players.iter().next() else {
+        return;
+    };
+    let view = MapView::new(*screen, player.translation, &config);
+    for (mut transform, mut projection) in cameras.iter_mut() {
+        *transform = Transform::from_translation(view.center + Vec3::Y * MAP_CAMERA_HEIGHT)
+            .looking_at(view.center, Vec3::NEG_Z);
+        if let Projection::Orthographic(orthographic) = projection.as_mut() {
+            orthographic.scaling_mode = ScalingMode::Fixed {
+                width: view.size,
+                height: view.size,
+            };
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use crate::{
+    file_system_interaction::config::GameConfig,
+    ingame_menu::PauseState,
+    player_control::{
+        actions::{ActionsFrozen, UiAction},
+        player_embodiment::Player,
+    },
+    theme::UiTheme,
+    world_map::{MapDiscovery, MapImage, MapMarker, MapView, WorldMapScreen},
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use leafwing_input_manager::prelude::ActionState;
+
+const MINIMAP_SIZE: f32 = 200.;
+
+pub(crate) fn map_ui_plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (toggle_world_map, show_map).chain().run_if(
+            in_state(GameState::Playing)
+                .and_then(in_state(PauseState::Running))
+                .and_then(resource_exists::<MapImage>()),
+        ),
+    );
+}
+
+fn toggle_world_map(
+    actions: Query<&ActionState<UiAction>>,
+    mut screen: ResMut<WorldMapScreen>,
+    mut actions_frozen: ResMut<ActionsFrozen>,
+) {
+    for action in actions.iter() {
+        if action.just_pressed(UiAction::ToggleMap) {
+            screen.open = !screen.open;
+            if screen.open {
+                actions_frozen.freeze();
+            } else {
+                actions_frozen.unfreeze();
+            }
+        }
+    }
+}
+
+fn show_map(
+    mut egui_contexts: EguiContexts,
+    map_image: Res<MapImage>,
+    screen: Res<WorldMapScreen>,
+    discovery: Res<MapDiscovery>,
+    players: Query<&Transform, With<Player>>,
+    markers: Query<(&GlobalTransform, &MapMarker)>,
+    config: Res<GameConfig>,
+    theme: Res<UiTheme>,
+) {
+    let Some(player) = players.iter().next() else {
+        return;
+    };
+    let view = MapView::new(*screen, player.translation, &config);
+    let texture = egui_contexts.add_image(map_image.0.clone_weak());
+    let draw_map = |ui: &mut egui::Ui, size: f32, fog: bool| {
+        let response = ui.image((texture, egui::Vec2::splat(size)));
+        let rect = response.rect;
+        let painter = ui.painter_at(rect);
+        let to_screen = |position: Vec3| {
+            let uv = view.to_map(position);
+            rect.min + egui::vec2(uv.x, uv.y) * size
+        };
+
+        if fog {
+            draw_fog(
+                &painter,
+                &discovery,
+                view,
+                config.map.fog_cell_size,
+                to_screen,
+            );
+        }
+        for (transform, marker) in markers.iter() {
+            let position = to_screen(transform.translation());
+            painter.circle_filled(position, 5., theme.colors.marker);
+            if fog && !marker.label.is_empty() {
+                painter.text(
+                    position + egui::vec2(8., 0.),
+                    egui::Align2::LEFT_CENTER,
+                    &marker.label,
+                    egui::FontId::proportional(theme.text.body),
+                    theme.colors.text,
+                );
+            }
+        }
+        let forward = player.forward();
+        draw_player_arrow(
+            &painter,
+            &theme,
+            to_screen(player.translation),
+            egui::vec2(forward.x, forward.z),
+        );
+    };
+
+    if screen.open {
+        egui::CentralPanel::default()
+            .frame(theme.overlay_frame())
+            .show(egui_contexts.ctx_mut(), |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.heading("Map");
+                    let size = ui.available_width().min(ui.available_height())
+                        - 2. * theme.spacing.screen_margin;
+                    draw_map(ui, size, true);
+                    ui.label("Press M to close");
+                });
+            });
+    } else {
+        egui::Area::new("Minimap")
+            .anchor(
+                egui::Align2::RIGHT_TOP,
+                egui::vec2(-theme.spacing.small, theme.spacing.small),
+            )
+            .show(egui_contexts.ctx_mut(), |ui| {
+                draw_map(ui, MINIMAP_SIZE, false);
+            });
+    }
+}
+
+fn draw_fog(
+    painter: &egui::Painter,
+    discovery: &MapDiscovery,
+    view: MapView,
+    cell_size: f32,
+    to_screen: impl Fn(Vec3) -> egui::Pos2,
+) {
+    let half_size = view.size / 2.;
+    let min = MapDiscovery::cell(view.center - Vec3::new(half_size, 0., half_size), cell_size);
+    let max = MapDiscovery::cell(view.center + Vec3::new(half_size, 0., half_size), cell_size);
+    for x in min.x..=max.x {
+        for y in min.y..=max.y {
+            let cell = IVec2::new(x, y);
+            if discovery.is_discovered(cell) {
+                continue;
+            }
+            let corner = Vec3::new(x as f32, 0., y as f32) * cell_size;
+            let rect = egui::Rect::from_two_pos(
+                to_screen(corner),
+                to_screen(corner + Vec3::new(cell_size, 0., cell_size)),
+            );
+            painter.rect_filled(rect, 0., egui::Color32::from_black_alpha(230));
+        }
+    }
+}
+
+fn draw_player_arrow(
+    painter: &egui::Painter,
+    theme: &UiTheme,
+    center: egui::Pos2,
+    direction: egui::Vec2,
+) {
+    let direction = direction.normalized() * 10.;
+    let side = egui::vec2(-direction.y, direction.x) * 0.5;
+    let points = vec![
+        center + direction,
+        center - direction * 0.6 + side,
+        center - direction * 0.6 - side,
+    ];
+    painter.add(egui::Shape::convex_polygon(
+        points,
+        theme.colors.player_marker,
+        egui::Stroke::new(1., theme.colors.text),
+    ));
+}
@@ -0,0 +1,57 @@
+use bevy::prelude::*;
+use foxtrot::simulation::{SimulatedInput, Simulation};
+
+#[test]
+fn player_moves_forward_under_held_input() {
+    let mut simulation = Simulation::start("World", None).unwrap();
+    // Let the player settle on the ground first
+    simulation.tick(30);
+    let start = simulation.player_position().unwrap();
+
+    simulation.hold(&[SimulatedInput::Move(Vec2::Y)], 120);
+
+    let end = simulation.player_position().unwrap();
+    let distance = (end - start).xz().length();
+    assert!(
+        distance > 3.,
+        "Player only moved {distance} m in two seconds of walking"
+    );
+}
+
+#[test]
+fn follower_paths_after_the_player() {
+    let mut simulation = Simulation::start("World", None).unwrap();
+    // Let the navmesh bake around the level's colliders
+    simulation.tick(120);
+    let start = simulation.follower_position().unwrap();
+
+    let mut previous = start;
+    let mut largest_step: f32 = 0.;
+    let mut caught_up = false;
+    for tick in 0..600 {
+        let walking = tick < 180;
+        let inputs: &[SimulatedInput] = if walking {
+            &[SimulatedInput::Move(Vec2::Y)]
+        } else {
+            &[]
+        };
+        simulation.hold(inputs, 1);
+        let follower = simulation.follower_position().unwrap();
+        largest_step = largest_step.max(follower.distance(previous));
+        previous = follower;
+        let player = simulation.player_position().unwrap();
+        if !walking && (player - follower).xz().length() < 4. {
+            caught_up = true;
+            break;
+        }
+    }
+
+    assert!(caught_up, "The follower did not catch up with the player");
+    let moved = (previous - start).xz().length();
+    assert!(moved > 3., "The follower only moved {moved} m");
+    // Companions teleport behind the player when they get stuck, which does not count as pathing
+    assert!(
+        largest_step < 1.,
+        "The follower jumped {largest_step} m in one tick instead of walking"
+    );
+}